@@ -3,10 +3,9 @@ use crate::command::def::func::CrtFunc;
 use crate::command::hook::Hook;
 use crate::command::watchpoint::Watchpts;
 use crate::{command, handle_cmd};
-use once_cell::sync::Lazy;
 use structopt::StructOpt;
-use crate::command::breakpoint::Brkpts;
-use crate::dbg::BASE_ADDR;
+use crate::command::breakpoint::{Brkpts, IatBrkpt};
+use crate::session::Session;
 
 #[derive(Debug, Default, Copy, Clone)]
 pub struct AfterB {
@@ -31,18 +30,19 @@ pub struct All {
     pub after_b: Vec<AfterB>,
     pub var_def: Vec<def::variable::Var>,
     pub break_ret_va: Vec<Brkpts>,
-    pub print_ot: u8,
     pub pdb_path: Option<String>,
+    pub record: Option<String>,
+    pub break_iat: Vec<IatBrkpt>,
+    pub break_once: Vec<Brkpts>,
 }
 
 impl All {
-    pub fn break_contain(&self, addr: u64) -> bool {
-        unsafe {
-            self.break_rva.iter().any(|brkpt| brkpt.addr == addr || brkpt.addr == addr)
-                | self.break_va.iter().any(|brkpt| brkpt.addr == addr || brkpt.addr == addr + BASE_ADDR)
-                | self.break_ret.iter().any(|brkpt| brkpt.addr == addr)
-                | self.break_ret_va.iter().any(|brkpt| brkpt.addr == addr || brkpt.addr == addr + BASE_ADDR)
-        }
+    pub fn break_contain(&self, addr: u64, base_addr: u64) -> bool {
+        self.break_rva.iter().any(|brkpt| brkpt.addr == addr)
+            | self.break_va.iter().any(|brkpt| brkpt.addr == addr || brkpt.addr == addr + base_addr)
+            | self.break_ret.iter().any(|brkpt| brkpt.addr == addr)
+            | self.break_ret_va.iter().any(|brkpt| brkpt.addr == addr || brkpt.addr == addr + base_addr)
+            | self.break_once.iter().any(|brkpt| brkpt.addr == addr)
     }
     
     pub fn find_b_rva_with_addr(&self, addr: u64) -> Option<&Brkpts> {
@@ -57,20 +57,24 @@ impl All {
 }
 
 
-pub static mut ALL_ELM: Lazy<All> = Lazy::new(|| All::default());
-
-
-
 #[derive(Debug, StructOpt, Default)]
 #[structopt(name = "LisaDbg", version = "1.0")]
 pub struct Dbgoption {
     pub file: Option<String>,
+    #[structopt(long = "file", help = "the file to debug, same as the positional argument")]
+    pub file_opt: Option<String>,
     #[structopt(short = "b", long = "breakpoint", help = "to place a breakpoint at an address (RVA)")]
     breakpoint_addr: Vec<u64>,
     #[structopt(long = "b-ret-va", help = "to place a breakpoint at ret addr of the function which contain the va")]
     b_ret_va: Vec<u64>,
     #[structopt(long = "b-ret", help = "to place a breakpoint at ret addr of the function which contain the rva")]
     b_ret: Vec<u64>,
+    #[structopt(long = "break-entry", help = "to stop once at the entry point of the file")]
+    break_entry: bool,
+    #[structopt(long = "break-main", help = "to stop once at main/wmain/WinMain, found with the symbols or the crt startup code")]
+    break_main: bool,
+    #[structopt(long = "break-tls", help = "to place a breakpoint on every tls callback of the file")]
+    break_tls: bool,
     #[structopt(long = "b-va", help = "to place a breakpoint at an address (VA) you must know in advance the address going and")]
     b_va: Vec<u64>,
     #[structopt(short = "a", long = "arg", help = "set arguments for script to debug")]
@@ -81,26 +85,61 @@ pub struct Dbgoption {
     watchpts: Vec<Watchpts>,
     #[structopt(long = "attach", help = "attach the dbg of a existing process with here pid or here name")]
     attach: Option<String>,
+    #[structopt(long = "dump", help = "open a minidump file (.dmp) for post-mortem analysis")]
+    dump: Option<String>,
+    #[structopt(long = "core", help = "open an elf core file for post-mortem analysis, use it with --file to get the symbols")]
+    core: Option<String>,
+    #[structopt(long = "gdbserver", help = "start the file and serve it to a gdb remote client on the address (:1234, 127.0.0.1:1234...)")]
+    gdbserver: Option<String>,
+    #[structopt(long = "replay", help = "replay a trace recorded with the record command, no process is started")]
+    replay: Option<String>,
 }
 
 impl Dbgoption {
-    pub fn exec_cmd(&self) {
+    pub fn exec_cmd(&self, session: &mut Session) {
+        if self.break_tls {
+            command::breakpoint::handle_break_tls(session);
+        }
+        if self.break_entry {
+            command::start::break_entry(session);
+        }
+        if self.break_main {
+            command::start::break_main(session);
+        }
         for cmd in &self.exec_cmd {
             let linev: Vec<&str> = cmd.split_whitespace().collect();
-            handle_cmd(&linev, &cmd);
+            handle_cmd(session, &linev, cmd);
         }
         if let Some(at_str) = &self.attach {
             let line = format!("attach {at_str}");
-            command::attach::handle_attach(&line.split_whitespace().collect::<Vec<&str>>())
+            command::attach::handle_attach(session, &line.split_whitespace().collect::<Vec<&str>>())
+        }
+        if let Some(dump) = &self.dump {
+            let line = format!("load-dump {dump}");
+            command::dump::handle_load_dump(session, &line.split_whitespace().collect::<Vec<&str>>(), &line)
+        }
+        if let Some(core) = &self.core {
+            let line = format!("load-core {core}");
+            command::dump::handle_load_core(session, &line.split_whitespace().collect::<Vec<&str>>(), &line)
+        }
+        if let Some(trace) = &self.replay {
+            let line = format!("replay {trace}");
+            command::trace::handle_replay(session, &line.split_whitespace().collect::<Vec<&str>>(), &line)
+        }
+        if let Some(addr) = &self.gdbserver {
+            let line = format!("gdbserver {addr}");
+            command::remote::handle_gdbserver(session, &line.split_whitespace().collect::<Vec<&str>>())
         }
     }
 
     pub fn to_all_elm(&self) -> All {
-        let mut result = All::default();
-        result.file = self.file.clone();
+        let mut result = All {
+            file: self.file.clone().or(self.file_opt.clone()),
+            arg: self.arg.clone(),
+            watchpts: self.watchpts.clone(),
+            ..Default::default()
+        };
         set_brkpts(&mut result.break_rva, &self.breakpoint_addr);
-        result.arg = self.arg.clone();
-        result.watchpts = self.watchpts.clone();
         set_brkpts(&mut result.break_va, &self.b_va);
         set_brkpts(&mut result.break_ret, &self.b_ret);
         set_brkpts(&mut result.break_ret_va, &self.b_ret_va);
@@ -1,15 +1,20 @@
-use crate::{dbg, process, usage, ALL_ELM};
+#[cfg(windows)]
+use crate::{dbg, process};
+use crate::usage;
+use crate::session::Session;
+#[cfg(windows)]
 use crate::ut::cast::str_to;
 use crate::ut::fmt::{print_lg, LevelPrint};
 
-pub fn handle_attach(linev: &[&str]) {
+#[cfg(windows)]
+pub fn handle_attach(session: &mut Session, linev: &[&str]) {
     if linev.len() < 2 {
         println!("{}", usage::USAGE_ATTACH);
         return;
     }
-    unsafe { ALL_ELM.attach = Some("".to_string()); }
+    session.all.attach = Some("".to_string());
 
-    let pid = match str_to::<u32>(&linev[1]) {
+    let pid = match str_to::<u32>(linev[1]) {
         Ok(pid) => pid,
         Err(_) => {
             if linev[1].contains("\"") {
@@ -26,5 +31,15 @@ pub fn handle_attach(linev: &[&str]) {
             }
         }
     };
-    unsafe { dbg::attach::attach_dbg(pid) }
+    unsafe { dbg::attach::attach_dbg(session, pid) }
+}
+
+
+#[cfg(not(windows))]
+pub fn handle_attach(_session: &mut Session, linev: &[&str]) {
+    if linev.len() < 2 {
+        println!("{}", usage::USAGE_ATTACH);
+        return;
+    }
+    print_lg(LevelPrint::ErrorO, "attaching to a process is only supported on windows");
 }
@@ -1,25 +1,25 @@
-use std::ptr;
-use std::str::FromStr;
+use crate::dbg::target::DebugTarget;
 use crate::dbg::memory::breakpoint::set_breakpoint;
-use crate::dbg::{memory, BASE_ADDR};
+use crate::dbg::memory;
+use crate::pefile::import;
 use crate::usage;
-use winapi::shared::ntdef::HANDLE;
-use winapi::um::winnt::CONTEXT;
-use crate::cli::ALL_ELM;
+use crate::dbg::CONTEXT;
+use crate::session::Session;
+use crate::symbol::Symbols;
 use crate::ut::{fmt::*};
 use crate::ut::*;
 
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Copy, Clone)]
+#[derive(Default)]
 pub enum BMOD {
+    #[default]
     Normally,
     Pro
 }
 
 
-impl Default for BMOD {
-    fn default() -> Self { BMOD::Normally }
-}
 
 #[derive(Debug, Default, Copy, Clone)]
 pub struct Brkpts {
@@ -34,18 +34,16 @@ pub struct Brkpts {
 
 impl Brkpts {
     pub fn from_addr_no_start(addr: u64) -> Self {
-        let mut res = Brkpts::default();
-        res.addr = addr;
-        res
+        Brkpts { addr, ..Default::default() }
     }
     
-    pub fn from_str_ctx(s: &str, ctx: *const CONTEXT) -> Result<Self, StrErr> {
+    pub fn from_str_ctx(session: &Session, s: &str, ctx: *const CONTEXT) -> Result<Self, StrErr> {
         let linev = s.split_whitespace().collect::<Vec<&str>>();
         if linev.len() < 2 {
             return Err(StrErr::ShortArg);
         }
         let mut result = Brkpts::default();
-        match get_addr_va(linev[1], ctx) {
+        match get_addr_va(session, linev[1], ctx) {
             Ok(addr) => result.addr = addr,
             Err(e) => return Err(StrErr::InvalidAddr(e.to_string()))
         }
@@ -60,6 +58,14 @@ impl Brkpts {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct IatBrkpt {
+    pub iat_rva: u32,
+    pub thunk_rva: u32,
+    pub label: String,
+    pub addr: u64,
+}
+
 pub enum StrErr {
     ShortArg,
     InvalidAddr(String),
@@ -79,15 +85,14 @@ impl std::fmt::Display for StrErr {
 
 
 
-impl FromStr for Brkpts {
-    type Err = StrErr;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+impl Brkpts {
+    pub fn from_str_sym(symbols: &Symbols, s: &str) -> Result<Self, StrErr> {
         let linev = s.split_whitespace().collect::<Vec<&str>>();
         if linev.len() < 2 {
             return Err(StrErr::ShortArg);
         }
         let mut result = Brkpts::default();
-        match get_addr_br(linev[1]) {
+        match get_addr_br(symbols, linev[1]) {
             Ok(addr) => result.addr = addr,
             Err(e) => return Err(StrErr::InvalidAddr(e.to_string()))
         }
@@ -102,19 +107,18 @@ impl FromStr for Brkpts {
     }
 }
 
-pub fn handle_breakpts(linev: &[&str]) {
+pub fn handle_breakpts(session: &mut Session, linev: &[&str]) {
     if linev.len() == 1 {
         eprintln!("{}", usage::USAGE_BRPT);
         return;
     }
-    match Brkpts::from_str(&linev.join(" ")) {
-        Ok(b) => unsafe {
-            let p_allm = ptr::addr_of_mut!(ALL_ELM);
-            if (*p_allm).break_contain(b.addr) {
+    match Brkpts::from_str_sym(&session.symbols, &linev.join(" ")) {
+        Ok(b) => {
+            if session.all.break_contain(b.addr, session.base_addr) {
                 print_lg(LevelPrint::Error, format!("you have already placed a breakpoint here {:#x}", b.addr));
                 return;
             }
-            (*p_allm).break_rva.push(b);
+            session.all.break_rva.push(b);
         }
         Err(e) => print_lg(LevelPrint::ErrorO, format!("failed to set breakpoint : {}", e)),
     } 
@@ -122,19 +126,71 @@ pub fn handle_breakpts(linev: &[&str]) {
 
 
 
-pub fn handle_break_va(linev: &[&str]) {
+pub fn handle_break_import(session: &mut Session, linev: &[&str]) {
+    if linev.len() != 2 {
+        println!("{}", usage::USAGE_B_IMPORT);
+        return;
+    }
+    let import = match session.imports.iter().find(|i| i.matches(linev[1])) {
+        Some(import) => import.clone(),
+        None => {
+            print_lg(LevelPrint::ErrorO, format!("no import named '{}', type \"info imports\" to list them", linev[1]));
+            return;
+        }
+    };
+    if let Some(thunk_rva) = import.delay_thunk {
+        if !session.all.break_iat.iter().any(|b| b.iat_rva == import.iat_rva) {
+            session.all.break_iat.push(IatBrkpt { iat_rva: import.iat_rva, thunk_rva, label: import.label(), addr: 0 });
+            print_lg(LevelPrint::DebugO, format!("{} is delay-loaded, a breakpoint will be set on it once the IAT is patched", import.label()));
+        }
+    }
+    let calls = import::find_iat_calls(&session.sections, session.get_bitness(), session.image_base, import.iat_rva);
+    if calls.is_empty() {
+        print_lg(LevelPrint::WarningO, format!("no call goes directly through the IAT slot of {} ({:#x})", import.label(), import.iat_rva));
+        return;
+    }
+    for rva in calls {
+        if !session.all.break_contain(rva, session.base_addr) {
+            session.all.break_rva.push(Brkpts::from_addr_no_start(rva));
+            print_lg(LevelPrint::DebugO, format!("breakpoint set at {:#x} (call {})", rva, import.label()));
+        }
+    }
+}
+
+
+
+pub fn handle_break_tls(session: &mut Session) {
+    let Some(tls) = &session.tls else {
+        print_lg(LevelPrint::ErrorO, "the file context has no tls directory");
+        return;
+    };
+    if tls.callbacks.is_empty() {
+        print_lg(LevelPrint::WarningO, "the tls directory has no callback");
+        return;
+    }
+    for callback in tls.callbacks.clone() {
+        if !session.all.break_contain(callback as u64, session.base_addr) {
+            session.all.break_rva.push(Brkpts::from_addr_no_start(callback as u64));
+            print_lg(LevelPrint::DebugO, format!("breakpoint set on the tls callback {:#x}", callback));
+        }
+    }
+}
+
+
+
+pub fn handle_break_va(session: &mut Session, linev: &[&str]) {
     if linev.len() != 2 {
         println!("b-va <va>");
         return;
     }
     
-    match Brkpts::from_str(&linev.join(" ")) {
-        Ok(b) => unsafe {
-            if (*ptr::addr_of!(ALL_ELM)).break_contain(b.addr) {
+    match Brkpts::from_str_sym(&session.symbols, &linev.join(" ")) {
+        Ok(b) => {
+            if session.all.break_contain(b.addr, session.base_addr) {
                 print_lg(LevelPrint::ErrorO, format!("you have already placed a breakpoint here {:#x}", b.addr));
                 return;
             }
-            (*ptr::addr_of_mut!(ALL_ELM)).break_va.push(b);
+            session.all.break_va.push(b);
             print_lg(LevelPrint::DebugO, format!("breakpoints are set at address {:#x}", b.addr));
         }
         Err(e) => print_lg(LevelPrint::ErrorO, format!("failed to set breakpoint : {}", e)),
@@ -145,45 +201,43 @@ pub fn handle_break_va(linev: &[&str]) {
 
 
 
-pub fn handle_b_va_proc(linev: &[&str], h_proc: HANDLE, ctx: *const CONTEXT) {
+pub fn handle_b_va_proc(session: &mut Session, linev: &[&str], target: &dyn DebugTarget, ctx: *const CONTEXT) {
     if linev.len() != 2 {
         println!("b-va <address>");
         return;
     }
     
-    unsafe {
-        match Brkpts::from_str_ctx(&linev.join(" "), ctx) {
-            Ok(mut b) => {
-                if (*ptr::addr_of!(ALL_ELM)).break_contain(b.addr) {
-                    print_lg(LevelPrint::ErrorO, format!("you have already placed a breakpoint here : {:#x}", b.addr));
-                    return;
-                }
-                if let Err(e) = set_breakpoint(h_proc, b.addr, &mut b.origin_b) {
-                    print_lg(LevelPrint::ErrorO, e);
-                }else {
-                    (*ptr::addr_of_mut!(ALL_ELM)).break_va.push(b);
-                }
+    match Brkpts::from_str_ctx(session, &linev.join(" "), ctx) {
+        Ok(mut b) => {
+            if session.all.break_contain(b.addr, session.base_addr) {
+                print_lg(LevelPrint::ErrorO, format!("you have already placed a breakpoint here : {:#x}", b.addr));
+                return;
+            }
+            if let Err(e) = set_breakpoint(target, b.addr, &mut b.origin_b) {
+                print_lg(LevelPrint::ErrorO, e);
+            }else {
+                session.all.break_va.push(b);
             }
-            Err(e) => print_lg(LevelPrint::ErrorO, format!("failed to set breakpoint : {}", e)),
         }
+        Err(e) => print_lg(LevelPrint::ErrorO, format!("failed to set breakpoint : {}", e)),
     }
 }
 
 
-pub fn handle_breakpoint_proc(linev: &[&str], h_proc: HANDLE) {
+pub fn handle_breakpoint_proc(session: &mut Session, linev: &[&str], target: &dyn DebugTarget) {
     if linev.len() != 2 {
         eprintln!("{}", usage::USAGE_BRPT);
     } else {
-        match Brkpts::from_str(&linev.join(" ")) {
-            Ok(mut b) => unsafe {
-                if (*ptr::addr_of!(ALL_ELM)).break_contain(b.addr) {
+        match Brkpts::from_str_sym(&session.symbols, &linev.join(" ")) {
+            Ok(mut b) => {
+                if session.all.break_contain(b.addr, session.base_addr) {
                     print_lg(LevelPrint::ErrorO, format!("you have already placed a breakpoint here : {:#x}", b.addr));
                     return;
                 }
-                if let Err(e) = set_breakpoint(h_proc, b.addr + BASE_ADDR, &mut b.origin_b) {
+                if let Err(e) = set_breakpoint(target, b.addr + session.base_addr, &mut b.origin_b) {
                     print_lg(LevelPrint::ErrorO, e);
                 }else {
-                    (*ptr::addr_of_mut!(ALL_ELM)).break_rva.push(b);
+                    session.all.break_rva.push(b);
                 }
             }
             Err(e) => print_lg(LevelPrint::ErrorO, format!("failed to set breakpoint : {}", e)),
@@ -192,10 +246,10 @@ pub fn handle_breakpoint_proc(linev: &[&str], h_proc: HANDLE) {
 }
 
 
-pub fn handle_restore_breakpoint_proc(linev: &[&str], h_proc: HANDLE) {
+pub fn handle_restore_breakpoint_proc(session: &Session, linev: &[&str], target: &dyn DebugTarget) {
     if linev.len() == 2 {
         let addr_str = linev[1];
-        let addr = match get_addr_br(addr_str) {
+        let addr = match get_addr_br(&session.symbols, addr_str) {
             Ok(value) => value,
             Err(e) => {
                 print_lg(LevelPrint::Error, e);
@@ -203,8 +257,29 @@ pub fn handle_restore_breakpoint_proc(linev: &[&str], h_proc: HANDLE) {
             }
         };
         
-        if let Some(b) = unsafe {(*ptr::addr_of!(ALL_ELM)).find_b_rva_with_addr(addr)} {
-            unsafe { memory::breakpoint::restore_byte_of_brkpt(h_proc, b.addr + BASE_ADDR, b.origin_b) }
+        if let Some(b) = session.all.find_b_rva_with_addr(addr) {
+            memory::breakpoint::restore_byte_of_brkpt(target, b.addr + session.base_addr, b.origin_b)
         }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pefile::tls::Tls;
+
+    #[test]
+    fn break_on_tls_callbacks() {
+        let mut session = Session::default();
+        handle_break_tls(&mut session);
+        assert!(session.all.break_rva.is_empty());
+
+        session.tls = Some(Tls { callbacks: vec![0x1000, 0x1020], ..Default::default() });
+        session.all.break_rva.push(Brkpts::from_addr_no_start(0x1000));
+        handle_break_tls(&mut session);
+        handle_break_tls(&mut session);
+        let addrs: Vec<u64> = session.all.break_rva.iter().map(|b| b.addr).collect();
+        assert_eq!(addrs, [0x1000, 0x1020]);
+    }
+}
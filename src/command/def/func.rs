@@ -1,13 +1,18 @@
-use crate::pefile::{NtHeaders, NT_HEADER};
-use crate::symbol::SYMBOLS_V;
+use crate::pefile::NtHeaders;
+use crate::session::Session;
+use crate::symbol::Symbols;
 use crate::usage::USAGE_DEF_FUNC;
-use crate::ALL_ELM;
-use keystone_engine::{Arch, Keystone, KeystoneOutput, Mode, OptionType, OptionValue};
+use keystone_engine::{Arch, Keystone, Mode, OptionType, OptionValue};
+#[cfg(windows)]
+use keystone_engine::KeystoneOutput;
 use std::io::{BufRead, BufReader, Write};
 use std::{io, ptr};
 use std::fs::File;
+#[cfg(windows)]
 use winapi::shared::minwindef::LPVOID;
+#[cfg(windows)]
 use winapi::um::memoryapi::{VirtualAllocEx, WriteProcessMemory};
+#[cfg(windows)]
 use winapi::um::winnt::{HANDLE, MEM_COMMIT, MEM_RESERVE, PAGE_EXECUTE_READWRITE};
 use crate::ut::fmt::{print_lg, LevelPrint};
 
@@ -19,6 +24,7 @@ pub struct CrtFunc {
 }
 
 
+#[cfg(windows)]
 fn get_keystone_out(engine: *const Keystone, line: &str, addr: u64) -> Result<KeystoneOutput, String> {
     unsafe {
         match (*engine).asm(line.to_string(), addr) {
@@ -34,8 +40,9 @@ impl CrtFunc {
         self.code_str.join("; ")
     }
 
-    pub unsafe fn write_cr_func(&mut self, h_proc: HANDLE) -> Result<(), String>{
-        let mod_asm = match NT_HEADER.unwrap() {
+    #[cfg(windows)]
+    pub unsafe fn write_cr_func(&mut self, h_proc: HANDLE, nt_header: NtHeaders) -> Result<(), String>{
+        let mod_asm = match nt_header {
             NtHeaders::Headers32(_) => Mode::MODE_32,
             NtHeaders::Headers64(_) => Mode::MODE_64,
         };
@@ -61,9 +68,9 @@ impl CrtFunc {
     }
 }
 
-fn verify_insn(engine: *const Keystone, asm_line: &str, linev: &Vec<String>) -> Result<(), String> {
+fn verify_insn(engine: *const Keystone, symbols: &Symbols, asm_line: &str, linev: &[String]) -> Result<(), String> {
     let mut asm_line = asm_line.to_string();
-    if let Some(sym) = unsafe { (*ptr::addr_of!(SYMBOLS_V)).symbol_file.iter().find(|s| asm_line.contains(&s.name) && s.name.len() > 3) } {
+    if let Some(sym) = symbols.symbol_file.iter().find(|s| asm_line.contains(&s.name) && s.name.len() > 3) {
         asm_line = asm_line.replace(&sym.name, "0x124");
     }
     let asm_line = format!("{}; {asm_line}", linev.join("; "));
@@ -75,7 +82,7 @@ fn verify_insn(engine: *const Keystone, asm_line: &str, linev: &Vec<String>) ->
     }
 }
 
-fn user_input(engine: *const Keystone, vec_line: &mut Vec<String>) {
+fn user_input(engine: *const Keystone, symbols: &Symbols, vec_line: &mut Vec<String>) {
     loop {
         print!("    ");
         let mut input = String::new();
@@ -90,7 +97,7 @@ fn user_input(engine: *const Keystone, vec_line: &mut Vec<String>) {
         }
 
         for line in input.lines() {
-            if let Err(e) = push_line(engine, line, vec_line) {
+            if let Err(e) = push_line(engine, symbols, line, vec_line) {
                 print_lg(LevelPrint::ErrorO, e);
             }
         }
@@ -98,8 +105,8 @@ fn user_input(engine: *const Keystone, vec_line: &mut Vec<String>) {
 }
 
 
-fn push_line(engine: *const Keystone, line: &str, vec_line: &mut Vec<String>) -> Result<(), String> {
-    match verify_insn(engine, line, vec_line) {
+fn push_line(engine: *const Keystone, symbols: &Symbols, line: &str, vec_line: &mut Vec<String>) -> Result<(), String> {
+    match verify_insn(engine, symbols, line, vec_line) {
         Ok(()) => {
             vec_line.push(line.to_string());
             Ok(())
@@ -112,7 +119,7 @@ fn push_line(engine: *const Keystone, line: &str, vec_line: &mut Vec<String>) ->
     }
 }
 
-fn file_inpt(engine: *const Keystone, file_path: &str, vec_line: &mut Vec<String>) {
+fn file_inpt(engine: *const Keystone, symbols: &Symbols, file_path: &str, vec_line: &mut Vec<String>) {
     match File::open(file_path) {
         Ok(file) => {
             let reader = BufReader::new(file);
@@ -122,7 +129,7 @@ fn file_inpt(engine: *const Keystone, file_path: &str, vec_line: &mut Vec<String
                         if line.to_lowercase().contains("section ") {
                             continue;
                         }
-                        if let Err(e) = push_line(engine, &line, vec_line) {
+                        if let Err(e) = push_line(engine, symbols, &line, vec_line) {
                             print_lg(LevelPrint::ErrorO, e);
                             return;
                         }
@@ -138,14 +145,14 @@ fn file_inpt(engine: *const Keystone, file_path: &str, vec_line: &mut Vec<String
 }
 
 
-pub fn crt_func(arg: &[&str], line: &str) {
+pub fn crt_func(session: &mut Session, arg: &[&str], line: &str) {
     if arg.len() < 2 {
         println!("{USAGE_DEF_FUNC}");
         return;
     }
 
     let mod_asm = {
-        if let Some(nt_h) = unsafe { NT_HEADER } {
+        if let Some(nt_h) = session.nt_header {
             match nt_h {
                 NtHeaders::Headers32(_) => Mode::MODE_32,
                 NtHeaders::Headers64(_) => Mode::MODE_64,
@@ -165,17 +172,15 @@ pub fn crt_func(arg: &[&str], line: &str) {
         name_func = arg[2].trim().to_string();
         let tot_len = arg[0].len() + arg[1].len() + name_func.len() + 2;
         let file_path = line[tot_len..].trim().replace("\"", "");
-        file_inpt(ptr::addr_of!(engine), &file_path, &mut vec_line);
+        file_inpt(ptr::addr_of!(engine), &session.symbols, &file_path, &mut vec_line);
     } else {
         name_func = arg[1].to_string();
         println!("{}:", name_func);
-        user_input(ptr::addr_of!(engine), &mut vec_line);
-    }
-    unsafe {
-        (*ptr::addr_of_mut!(ALL_ELM)).crt_func.push(CrtFunc {
-            name: name_func,
-            code_str: vec_line,
-            addr: 0,
-        });
+        user_input(ptr::addr_of!(engine), &session.symbols, &mut vec_line);
     }
+    session.all.crt_func.push(CrtFunc {
+        name: name_func,
+        code_str: vec_line,
+        addr: 0,
+    });
 }
\ No newline at end of file
@@ -1,3 +1,4 @@
+use crate::session::Session;
 use crate::usage::USAGE_DEF;
 use crate::ut::fmt::{print_lg, LevelPrint};
 
@@ -6,16 +7,16 @@ pub mod structs;
 pub mod variable;
 pub mod types;
 
-pub fn handle_def(linev: &[&str], line: &str) {
+pub fn handle_def(session: &mut Session, linev: &[&str], line: &str) {
     if linev.len() < 2 {
         println!("{USAGE_DEF}");
         return;
     }
     let type_elm = linev[1];
     match type_elm {
-        "func" | "function" => func::crt_func(&linev[1..], line[type_elm.len()..].trim()),
-        "struct" => structs::def_struct(&linev[1..]),
-        "var" | "variable" => variable::variable::handle_var(&linev[2..]),
+        "func" | "function" => func::crt_func(session, &linev[1..], line[type_elm.len()..].trim()),
+        "struct" => structs::def_struct(session, &linev[1..]),
+        "var" | "variable" => variable::variable::handle_var(session, &linev[2..]),
         _ => print_lg(LevelPrint::ErrorO, format!("unknow element '{type_elm}'")),
     }
 }
@@ -1,16 +1,16 @@
 use crate::usage::USAGE_DEF_STRUCT;
-use crate::ALL_ELM;
 use std::io::Write;
 use std::str::FromStr;
-use std::{io, ptr};
+use std::io;
 use crate::command::def::types::{StructP, TypeP};
+use crate::session::Session;
 use crate::ut::fmt::{print_lg, LevelPrint};
 
 
 
 
 
-pub fn def_struct(linev: &[&str]) {
+pub fn def_struct(session: &mut Session, linev: &[&str]) {
     if linev.len() < 2 {
         println!("{USAGE_DEF_STRUCT}");
         return;
@@ -54,5 +54,5 @@ pub fn def_struct(linev: &[&str]) {
         })
     }
 
-    unsafe { (*ptr::addr_of_mut!(ALL_ELM)).struct_def.push(TypeP::Structs(struct_s, linev[1].to_string())); }
+    session.all.struct_def.push(TypeP::Structs(struct_s, linev[1].to_string()));
 }
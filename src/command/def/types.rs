@@ -1,11 +1,12 @@
 use std::fmt;
 use std::str::FromStr;
 use regex::Regex;
-use crate::pefile::{NtHeaders, NT_HEADER};
+use crate::pefile::NtHeaders;
 use crate::ut::cast::str_to;
 use crate::ut::fmt::{print_lg, LevelPrint};
 
 #[derive(Debug, Clone)]
+#[derive(Default)]
 pub enum TypeP {
     U8(usize),
     U16(usize),
@@ -21,14 +22,10 @@ pub enum TypeP {
     Structs(Vec<StructP>, String),
     Bool(usize),
     Ptr(Box<PtrS>, usize),
+    #[default]
     Void,
 }
 
-impl Default for TypeP {
-    fn default() -> Self {
-        Self::Void
-    }
-}
 
 #[derive(Debug, Default, Clone)]
 pub struct PtrS {
@@ -83,14 +80,14 @@ fn write_type(f: &mut fmt::Formatter<'_>, type_name: &str, size: usize) -> fmt::
 }
 
 impl TypeP {
-    pub fn get_size(&self) -> usize {
+    pub fn get_size(&self, nt_header: Option<NtHeaders>) -> usize {
         match self {
-            TypeP::U8(cout) | TypeP::I8(cout) | TypeP::Bool(cout) | TypeP::Char(cout) => cout * 1,
+            TypeP::U8(cout) | TypeP::I8(cout) | TypeP::Bool(cout) | TypeP::Char(cout) => *cout,
             TypeP::U16(cout) | TypeP::I16(cout) => cout * 2,
             TypeP::U32(cout) | TypeP::I32(cout) | TypeP::F32(cout) => cout * 4,
             TypeP::U64(cout) | TypeP::I64(cout) | TypeP::F64(cout) => cout * 8,
             TypeP::Ptr(_, cout) => {
-                if let Some(nt) = unsafe { NT_HEADER } {
+                if let Some(nt) = nt_header {
                     match nt {
                         NtHeaders::Headers32(_) => 4 * cout,
                         NtHeaders::Headers64(_) => 8 * cout,
@@ -103,7 +100,7 @@ impl TypeP {
             TypeP::Structs(vtypes, _) => {
                 let mut result = 0;
                 for types in vtypes {
-                    result += types.type_p.get_size();
+                    result += types.type_p.get_size(nt_header);
                 }
                 result
             }
@@ -131,22 +128,16 @@ impl TypeP {
     
     #[allow(dead_code)]
     pub fn is_ptr_castable(&self) -> bool {
-        match self {
-            TypeP::Ptr(_, _) | TypeP::U64(_) | TypeP::I64(_) => true,
-            _ => false,
-        }
+        matches!(self, TypeP::Ptr(_, _) | TypeP::U64(_) | TypeP::I64(_))
     }
     
     #[allow(dead_code)]
     pub fn is_ptr_absolute(&self) -> bool {
-        match self { 
-            TypeP::Ptr(_,_) => true,
-            _ => false,
-        }
+        matches!(self, TypeP::Ptr(_,_))
     }
     
-    pub fn size_of_type(&self) -> usize {
-        let size = self.get_size();
+    pub fn size_of_type(&self, nt_header: Option<NtHeaders>) -> usize {
+        let size = self.get_size(nt_header);
         let cout = self.cout_elm();
         size / cout
     }
@@ -204,7 +195,7 @@ impl FromStr for TypeP {
             let types = vs[0].to_lowercase();
             let mut cout = 1;
             let re = Regex::new(r"\[(.*?)]").unwrap();
-            for cap in re.captures_iter(&vs[1]) {
+            for cap in re.captures_iter(vs[1]) {
                 if let Some(num) = cap.get(1) {
                     match str_to::<usize>(num.as_str()) {
                         Ok(num) => cout = num,
@@ -1,13 +1,10 @@
-use std::{io, ptr};
-use winapi::shared::minwindef::LPVOID;
-use winapi::um::memoryapi::ReadProcessMemory;
-use winapi::um::winnt::HANDLE;
-use crate::cli::ALL_ELM;
 use crate::command::def::variable::Var;
+use crate::dbg::target::{read_exact, DebugTarget};
+use crate::session::Session;
 use crate::usage::USAGE_PRINTF_VAR;
 use crate::ut::fmt::{print_lg, LevelPrint};
 
-pub fn printf_var(linev: &[&str], line: &str, handle: HANDLE) {
+pub fn printf_var(session: &Session, linev: &[&str], line: &str, target: &dyn DebugTarget) {
     if linev.len() < 2 {
         print_lg(LevelPrint::DebugO, USAGE_PRINTF_VAR);
         return;
@@ -24,7 +21,7 @@ pub fn printf_var(linev: &[&str], line: &str, handle: HANDLE) {
     }
 
     if str_u.is_empty() {
-        if let Err(e) = get_str_from_var_name(ipn, &mut str_u, handle) {
+        if let Err(e) = get_str_from_var_name(session, ipn, &mut str_u, target) {
             print_lg(LevelPrint::ErrorO, format!("the first argument is invalid : {e}"));
             return;
         }
@@ -43,7 +40,7 @@ pub fn printf_var(linev: &[&str], line: &str, handle: HANDLE) {
                     match next_char {
                         'd' | 'i' => {
                             let arg = argv[i_arg];
-                            if let Some(var) = Var::get_var_with_name(unsafe { (*&raw const ALL_ELM).var_def.clone() }, arg) {
+                            if let Some(var) = Var::get_var_with_name(session.all.var_def.clone(), arg) {
                                 match var.to_i32() {
                                     Ok(value) => str_final.push_str(&value.to_string()),
                                     Err(e) => print_lg(LevelPrint::ErrorO, e),
@@ -54,7 +51,7 @@ pub fn printf_var(linev: &[&str], line: &str, handle: HANDLE) {
                         },
                         'u' => {
                             let arg = argv[i_arg];
-                            if let Some(var) = Var::get_var_with_name(unsafe { (*&raw const ALL_ELM).var_def.clone() }, arg) {
+                            if let Some(var) = Var::get_var_with_name(session.all.var_def.clone(), arg) {
                                 match var.to_u32() {
                                     Ok(value) => str_final.push_str(&value.to_string()),
                                     Err(e) => print_lg(LevelPrint::ErrorO, e),
@@ -65,7 +62,7 @@ pub fn printf_var(linev: &[&str], line: &str, handle: HANDLE) {
                         }
                         'x' => {
                             let arg = argv[i_arg];
-                            if let Some(var) = Var::get_var_with_name(unsafe { (*&raw const ALL_ELM).var_def.clone() }, arg) {
+                            if let Some(var) = Var::get_var_with_name(session.all.var_def.clone(), arg) {
                                 match var.to_u32() {
                                     Ok(value) => str_final.push_str(&format!("{:x}", value)),
                                     Err(e) => print_lg(LevelPrint::ErrorO, e),
@@ -76,7 +73,7 @@ pub fn printf_var(linev: &[&str], line: &str, handle: HANDLE) {
                         }
                         'X' => {
                             let arg = argv[i_arg];
-                            if let Some(var) = Var::get_var_with_name(unsafe { (*&raw const ALL_ELM).var_def.clone() }, arg) {
+                            if let Some(var) = Var::get_var_with_name(session.all.var_def.clone(), arg) {
                                 match var.to_u32() {
                                     Ok(value) => str_final.push_str(&format!("{:X}", value)),
                                     Err(e) => print_lg(LevelPrint::ErrorO, e),
@@ -87,7 +84,7 @@ pub fn printf_var(linev: &[&str], line: &str, handle: HANDLE) {
                         }
                         'f' => {
                             let arg = argv[i_arg];
-                            if let Some(var) = Var::get_var_with_name(unsafe { (*&raw const ALL_ELM).var_def.clone() }, arg) {
+                            if let Some(var) = Var::get_var_with_name(session.all.var_def.clone(), arg) {
                                 match var.to_f32() {
                                     Ok(value) => str_final.push_str(&value.to_string()),
                                     Err(e) => print_lg(LevelPrint::ErrorO, e),
@@ -107,7 +104,7 @@ pub fn printf_var(linev: &[&str], line: &str, handle: HANDLE) {
                                 }
                             }else {
                                 let mut str_a = String::new();
-                                if let Err(e) = get_str_from_var_name(arg.to_string(), &mut str_a, handle) {
+                                if let Err(e) = get_str_from_var_name(session, arg.to_string(), &mut str_a, target) {
                                     print_lg(LevelPrint::ErrorO, e);
                                 }else {
                                     str_final.push_str(&str_a);
@@ -130,19 +127,13 @@ pub fn printf_var(linev: &[&str], line: &str, handle: HANDLE) {
 
 
 
-fn get_str_from_var_name(var_name: String, out: &mut String, handle: HANDLE) -> Result<(), anyhow::Error> {
-    if let Some(var) = Var::get_var_with_name(unsafe { (*&raw const ALL_ELM).var_def.clone() }, &var_name) {
+fn get_str_from_var_name(session: &Session, var_name: String, out: &mut String, target: &dyn DebugTarget) -> Result<(), anyhow::Error> {
+    if let Some(var) = Var::get_var_with_name(session.all.var_def.clone(), &var_name) {
         *out = if var.type_p.cout_elm() > 1 {
             String::from_utf8_lossy(&var.value).to_string()
         } else {
-            let addr_ptr = match var.to_u64() {
-                Ok(v) => v as LPVOID,
-                Err(e) => return Err(e),
-            };
-            match read_str_from_lpvoid(handle, addr_ptr) {
-                Ok(v) => v,
-                Err(e) =>  return Err(e),
-            }
+            let addr_ptr = var.to_u64()?;
+            read_str_from_lpvoid(target, addr_ptr)?
         };
         Ok(())
     }else {
@@ -151,10 +142,10 @@ fn get_str_from_var_name(var_name: String, out: &mut String, handle: HANDLE) ->
 }
 
 fn get_str(n_line: &str, ipn: &mut String, str_u: &mut String) -> Result<(), String>{
-    for (i, c) in n_line.chars().enumerate() {
+    for (i, c) in n_line.char_indices() {
         if c == '"' {
             if let Some(pos) = n_line[i+1..].find("\"") {
-                str_u.push_str(&n_line[i+1..pos+1]);
+                str_u.push_str(&n_line[i+1..i+1+pos]);
                 break;
             } else {
                 let mut mt = n_line[i..].to_string();
@@ -172,19 +163,19 @@ fn get_str(n_line: &str, ipn: &mut String, str_u: &mut String) -> Result<(), Str
 
 
 
-pub fn read_str_from_lpvoid(handle: HANDLE, addr: LPVOID) -> Result<String, anyhow::Error>{
+pub fn read_str_from_lpvoid(target: &dyn DebugTarget, addr: u64) -> Result<String, anyhow::Error>{
     let mut addr = addr;
     let mut str_i = String::new();
     loop {
-        let mut c = 0u8;
-        if unsafe {ReadProcessMemory(handle, addr, ptr::addr_of_mut!(c) as LPVOID, 1, &mut 0)} == 0 {
-            return Err(anyhow::Error::msg(format!("failed to read memory at address {:#x} : {}", addr as u64, io::Error::last_os_error())))
+        let mut c = [0u8];
+        if let Err(e) = read_exact(target, addr, &mut c) {
+            return Err(anyhow::Error::msg(format!("failed to read memory at address {:#x} : {e}", addr)))
         }
-        if c == 0 {
+        if c[0] == 0 {
             return Ok(str_i)
         }else {
-            str_i.push(c as char)
+            str_i.push(c[0] as char)
         }
-        addr = (addr as u64 + 1) as LPVOID;
+        addr += 1;
     }
 }
\ No newline at end of file
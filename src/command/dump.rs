@@ -0,0 +1,47 @@
+use crate::dump::{self, elfcore, minidump};
+use crate::session::Session;
+use crate::usage;
+use crate::ut::fmt::{print_lg, LevelPrint};
+
+pub fn handle_load_dump(session: &mut Session, linev: &[&str], line: &str) {
+    if linev.len() < 2 {
+        println!("{}", usage::USAGE_LOAD_DUMP);
+        return;
+    }
+    let path = line[linev[0].len()..].replace("\"", "");
+    let path = path.trim();
+    match minidump::parse(path) {
+        Ok((pm, sys_info)) => {
+            print_lg(LevelPrint::DebugO, format!(
+                "minidump of a {} process, {} processors, windows {}.{}.{}",
+                if pm.bitness == 64 { "x64" } else { "x86" },
+                sys_info.processors, sys_info.major, sys_info.minor, sys_info.build
+            ));
+            dump::run_session(session, pm);
+        }
+        Err(e) => print_lg(LevelPrint::ErrorO, format!("failed to load the dump : {e}")),
+    }
+}
+
+
+pub fn handle_load_core(session: &mut Session, linev: &[&str], line: &str) {
+    if linev.len() < 2 {
+        println!("{}", usage::USAGE_LOAD_CORE);
+        return;
+    }
+    let path = line[linev[0].len()..].replace("\"", "");
+    let path = path.trim();
+    match elfcore::parse(path, session.all.file.as_deref()) {
+        Ok((pm, info)) => {
+            print_lg(LevelPrint::DebugO, format!(
+                "core of the {} process {} '{}' ({})",
+                if pm.bitness == 64 { "x64" } else { "x86" }, info.pid, info.fname, info.args
+            ));
+            for path in &info.missing {
+                print_lg(LevelPrint::WarningO, format!("'{path}' could not be read, the memory it maps is not in the core"));
+            }
+            dump::run_session(session, pm);
+        }
+        Err(e) => print_lg(LevelPrint::ErrorO, format!("failed to load the core file : {e}")),
+    }
+}
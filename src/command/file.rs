@@ -1,31 +1,52 @@
-use crate::{pefile, symbol, ALL_ELM};
+use crate::{elffile, pefile, symbol};
+use crate::session::Session;
 use std::fs::File;
 use std::io::Read;
-use std::ptr;
-use crate::symbol::{Symbols, SYMBOLS_V};
+use crate::symbol::Symbols;
 use crate::ut::fmt::{print_lg, LevelPrint};
 
-pub fn handle_change_file(linev: &[&str], line: &str) {
+pub fn handle_change_file(session: &mut Session, linev: &[&str], line: &str) {
     if linev.len() > 1 {
         let file_str = line[4..].replace("\"", "");
         let file_str = file_str.trim();
         match File::open(file_str) {
             Ok(mut file) => {
-                unsafe {
-                    let mut mz_head = [0u8; 2];
-                    file.read_exact(&mut mz_head).unwrap();
-                    if mz_head == *b"MZ" {
-                        ALL_ELM.file = Some(file_str.to_string());
-                        **(ptr::addr_of_mut!(SYMBOLS_V)) = Symbols::default();
-                        if let Err(e) = pefile::parse_header() {
-                            print_lg(LevelPrint::ErrorO, e);
-                            return;
-                        }
-                        print_lg(LevelPrint::DebugO, format!("Now the file context is '{file_str}'"));
-                        symbol::load_symbol(linev, line);
-                    } else {
-                        print_lg(LevelPrint::ErrorO, "please specify a valid pe file");
+                let mut magic = [0u8; 4];
+                if file.read_exact(&mut magic).is_err() {
+                    print_lg(LevelPrint::ErrorO, "please specify a valid pe or elf file");
+                    return;
+                }
+                if magic == *b"\x7fELF" {
+                    session.all.file = Some(file_str.to_string());
+                    session.symbols = Symbols::default();
+                    session.imports.clear();
+                    session.relocs.clear();
+                    session.tls = None;
+                    session.load_config = None;
+                    session.resources.clear();
+                    if let Err(e) = elffile::parse_header(session) {
+                        print_lg(LevelPrint::ErrorO, e);
+                        return;
+                    }
+                    print_lg(LevelPrint::DebugO, format!("Now the file context is '{file_str}'"));
+                    symbol::load_symbol(session, linev, line);
+                    match elffile::load_symtab(session) {
+                        Ok(count) => print_lg(LevelPrint::DebugO, format!("{count} symbols loaded from the symbol table")),
+                        Err(e) => print_lg(LevelPrint::ErrorO, format!("failed to load the symbol table : {e}")),
+                    }
+                } else if magic[..2] == *b"MZ" {
+                    session.all.file = Some(file_str.to_string());
+                    session.symbols = Symbols::default();
+                    session.elf_header = None;
+                    session.sections.clear();
+                    if let Err(e) = unsafe { pefile::parse_header(session) } {
+                        print_lg(LevelPrint::ErrorO, e);
+                        return;
                     }
+                    print_lg(LevelPrint::DebugO, format!("Now the file context is '{file_str}'"));
+                    symbol::load_symbol(session, linev, line);
+                } else {
+                    print_lg(LevelPrint::ErrorO, "please specify a valid pe or elf file");
                 }
             }
             Err(e) => print_lg(LevelPrint::ErrorO, format!("failed to open file: {e}")),
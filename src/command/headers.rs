@@ -0,0 +1,220 @@
+use std::fs;
+use crate::pefile::winnt::IMAGE_DOS_HEADER;
+use crate::pefile::debug::{self, DebugEntry, DebugInfo};
+use crate::pefile::header::{self, DIRECTORY_NAMES};
+use crate::pefile::rich::{self, RichHeader};
+use crate::pefile::{read_struct, NtHeaders};
+use crate::session::Session;
+use crate::ut::fmt::*;
+
+const EX_DLL_CET_COMPAT: u32 = 0x1;
+
+
+macro_rules! optional_fields {
+    ($o:expr) => {
+        vec![
+            ("Magic", format!("{:#x}", $o.Magic)),
+            ("Linker version", format!("{}.{}", $o.MajorLinkerVersion, $o.MinorLinkerVersion)),
+            ("Size of code", format!("{:#x}", $o.SizeOfCode)),
+            ("Initialized data", format!("{:#x}", $o.SizeOfInitializedData)),
+            ("Uninitialized data", format!("{:#x}", $o.SizeOfUninitializedData)),
+            ("Entry point", format!("{:#x}", $o.AddressOfEntryPoint)),
+            ("Base of code", format!("{:#x}", $o.BaseOfCode)),
+            ("Image base", format!("{:#x}", $o.ImageBase)),
+            ("Section alignment", format!("{:#x}", $o.SectionAlignment)),
+            ("File alignment", format!("{:#x}", $o.FileAlignment)),
+            ("OS version", format!("{}.{}", $o.MajorOperatingSystemVersion, $o.MinorOperatingSystemVersion)),
+            ("Image version", format!("{}.{}", $o.MajorImageVersion, $o.MinorImageVersion)),
+            ("Subsystem version", format!("{}.{}", $o.MajorSubsystemVersion, $o.MinorSubsystemVersion)),
+            ("Win32 version", format!("{:#x}", $o.Win32VersionValue)),
+            ("Size of image", format!("{:#x}", $o.SizeOfImage)),
+            ("Size of headers", format!("{:#x}", $o.SizeOfHeaders)),
+            ("Checksum", format!("{:#x}", $o.CheckSum)),
+            ("Subsystem", format!("{} ({})", $o.Subsystem, header::subsystem_str($o.Subsystem))),
+            ("Dll characteristics", format!("{:#x} {}", $o.DllCharacteristics, header::dll_characteristics_str($o.DllCharacteristics))),
+            ("Stack reserve", format!("{:#x}", $o.SizeOfStackReserve)),
+            ("Stack commit", format!("{:#x}", $o.SizeOfStackCommit)),
+            ("Heap reserve", format!("{:#x}", $o.SizeOfHeapReserve)),
+            ("Heap commit", format!("{:#x}", $o.SizeOfHeapCommit)),
+            ("Loader flags", format!("{:#x}", $o.LoaderFlags)),
+            ("Rva and sizes", format!("{}", $o.NumberOfRvaAndSizes)),
+        ]
+    };
+}
+
+
+fn print_fields(title: &str, fields: &[(&str, String)]) {
+    println!("{VALID_COLOR}{title}:{RESET_COLOR}");
+    for (name, value) in fields {
+        println!("     {VALID_COLOR}{:<20}: {VALUE_COLOR}{value}{RESET_COLOR}", name);
+    }
+}
+
+
+fn print_dos(dos: &IMAGE_DOS_HEADER) {
+    print_fields("DOS header", &[
+        ("Magic", format!("{:#x}", dos.e_magic)),
+        ("Bytes on last page", format!("{:#x}", dos.e_cblp)),
+        ("Pages", format!("{:#x}", dos.e_cp)),
+        ("Relocations", format!("{:#x} at {:#x}", dos.e_crlc, dos.e_lfarlc)),
+        ("Header paragraphs", format!("{:#x}", dos.e_cparhdr)),
+        ("Extra paragraphs", format!("{:#x} - {:#x}", dos.e_minalloc, dos.e_maxalloc)),
+        ("Initial ss:sp", format!("{:04x}:{:04x}", dos.e_ss, dos.e_sp)),
+        ("Initial cs:ip", format!("{:04x}:{:04x}", dos.e_cs, dos.e_ip)),
+        ("Checksum", format!("{:#x}", dos.e_csum)),
+        ("Nt headers", format!("{:#x}", dos.e_lfanew)),
+    ]);
+}
+
+
+fn print_file_header(nt_header: NtHeaders) {
+    let fh = nt_header.file_header();
+    print_fields("File header", &[
+        ("Machine", format!("{:#x} ({})", fh.Machine, header::machine_str(fh.Machine))),
+        ("Sections", format!("{}", fh.NumberOfSections)),
+        ("Time stamp", format!("{:#x}", fh.TimeDateStamp)),
+        ("Symbol table", format!("{:#x} ({} symbols)", fh.PointerToSymbolTable, fh.NumberOfSymbols)),
+        ("Optional header size", format!("{:#x}", fh.SizeOfOptionalHeader)),
+        ("Characteristics", format!("{:#x} {}", fh.Characteristics, header::characteristics_str(fh.Characteristics))),
+    ]);
+}
+
+
+fn print_optional_header(nt_header: NtHeaders) {
+    let fields = match nt_header {
+        NtHeaders::Headers32(nt) => {
+            let mut fields = optional_fields!(nt.OptionalHeader);
+            fields.insert(7, ("Base of data", format!("{:#x}", nt.OptionalHeader.BaseOfData)));
+            fields
+        }
+        NtHeaders::Headers64(nt) => optional_fields!(nt.OptionalHeader),
+    };
+    print_fields("Optional header", &fields);
+}
+
+
+fn print_directories(session: &Session, nt_header: NtHeaders) {
+    println!("{VALID_COLOR}Data directories:{RESET_COLOR}");
+    for (i, name) in DIRECTORY_NAMES.iter().enumerate() {
+        let dir = nt_header.data_directory(i);
+        if dir.VirtualAddress == 0 && dir.Size == 0 {
+            continue;
+        }
+        let section = match header::directory_section(&session.sections, nt_header, i) {
+            Some(section) => section.to_string(),
+            None if i == 4 => "file offset".to_string(),
+            None => "outside of the sections".to_string(),
+        };
+        println!("     {CYAN_COLOR}{i:<3}{RESET_COLOR}{GREEN_COL}{:<16}{RESET_COLOR}{ADDR_COLOR}{:#010x}{RESET_COLOR} {VALUE_COLOR}{:<#10x}{RESET_COLOR} {BLUE_COLOR}{section}{RESET_COLOR}",
+            name, dir.VirtualAddress, dir.Size);
+    }
+}
+
+
+fn print_rich(rich: Option<&RichHeader>) {
+    let Some(rich) = rich else {
+        print_lg(LevelPrint::WarningO, "the file has no rich header");
+        return;
+    };
+    let checksum = if rich.is_valid() { "valid".to_string() } else { format!("invalid, computed {:#x}", rich.checksum) };
+    println!("{VALID_COLOR}Rich header at {:#x}, key {:#x} ({checksum}):{RESET_COLOR}", rich.offset, rich.key);
+    for entry in &rich.entries {
+        println!("     {CYAN_COLOR}{:<8}{RESET_COLOR}{GREEN_COL}{:<28}{RESET_COLOR}{VALUE_COLOR}build {:<7}{RESET_COLOR}{BLUE_COLOR}{:<16}{RESET_COLOR}{VALUE_COLOR}{} objects{RESET_COLOR}",
+            format!("{:#x}", entry.product_id), entry.product_str(), entry.build, entry.vs_version(), entry.count);
+    }
+}
+
+
+fn print_debug_entry(i: usize, entry: &DebugEntry) {
+    println!("{CYAN_COLOR}{i}{RESET_COLOR}: {GREEN_COL}{}{RESET_COLOR} {VALUE_COLOR}version {}.{}, time stamp {:#x}, {:#x} bytes at rva {ADDR_COLOR}{:#x}{VALUE_COLOR} (file offset {:#x}){RESET_COLOR}",
+        entry.type_str(), entry.major_version, entry.minor_version, entry.time_date_stamp, entry.size, entry.rva, entry.raw_ptr);
+    match &entry.info {
+        DebugInfo::CodeView { guid, age, path } => {
+            println!("     {VALID_COLOR}PDB  : {VALUE_COLOR}{path}{RESET_COLOR}");
+            println!("     {VALID_COLOR}GUID : {VALUE_COLOR}{} age {age}{RESET_COLOR}", debug::guid_str(guid));
+            // the directory of the pdb on a symbol server
+            println!("     {VALID_COLOR}Key  : {VALUE_COLOR}{}{:X}{RESET_COLOR}", debug::guid_str(guid).replace('-', ""), age);
+        }
+        DebugInfo::CodeView20 { signature, age, path } => {
+            println!("     {VALID_COLOR}PDB  : {VALUE_COLOR}{path}{RESET_COLOR}");
+            println!("     {VALID_COLOR}NB10 : {VALUE_COLOR}signature {:#x} age {age}{RESET_COLOR}", signature);
+        }
+        DebugInfo::Pogo { signature, entries } => {
+            println!("     {VALID_COLOR}{signature}, {} entries{RESET_COLOR}", entries.len());
+            for pogo in entries {
+                println!("     {ADDR_COLOR}{:#010x}{RESET_COLOR} {VALUE_COLOR}{:<#10x}{RESET_COLOR} {GREEN_COL}{}{RESET_COLOR}", pogo.rva, pogo.size, pogo.name);
+            }
+        }
+        DebugInfo::Repro(hash) if hash.is_empty() => println!("     {VALID_COLOR}deterministic build without hash{RESET_COLOR}"),
+        DebugInfo::Repro(hash) => {
+            println!("     {VALID_COLOR}Hash : {VALUE_COLOR}{}{RESET_COLOR}", hash.iter().map(|b| format!("{:02x}", b)).collect::<String>());
+        }
+        DebugInfo::VcFeature { pre_vc11, c_cpp, gs, sdl, guard_n } => {
+            println!("     {VALID_COLOR}Pre-VC++ 11.00 : {VALUE_COLOR}{pre_vc11}{VALID_COLOR}, C/C++ : {VALUE_COLOR}{c_cpp}{VALID_COLOR}, /GS : {VALUE_COLOR}{gs}{VALID_COLOR}, /sdl : {VALUE_COLOR}{sdl}{VALID_COLOR}, guardN : {VALUE_COLOR}{guard_n}{RESET_COLOR}");
+        }
+        DebugInfo::ExDllCharacteristics(flags) => {
+            let cet = if flags & EX_DLL_CET_COMPAT != 0 { "CET_COMPAT" } else { "" };
+            println!("     {VALID_COLOR}Flags : {VALUE_COLOR}{:#x} {cet}{RESET_COLOR}", flags);
+        }
+        DebugInfo::Raw => {}
+    }
+}
+
+
+fn print_debug(session: &Session, nt_header: NtHeaders) {
+    let entries = match debug::parse_debug_directory(&session.sections, nt_header.data_directory(6)) {
+        Ok(entries) => entries,
+        Err(e) => {
+            print_lg(LevelPrint::ErrorO, format!("failed to parse the debug directory : {e}"));
+            return;
+        }
+    };
+    if entries.is_empty() {
+        print_lg(LevelPrint::WarningO, "the file has no debug directory");
+        return;
+    }
+    println!("{VALID_COLOR}Debug directory:{RESET_COLOR}");
+    for (i, entry) in entries.iter().enumerate() {
+        print_debug_entry(i, entry);
+    }
+}
+
+
+pub fn print_headers(session: &Session, linev: &[&str]) {
+    let (Some(nt_header), Some(file)) = (session.nt_header, &session.all.file) else {
+        print_lg(LevelPrint::WarningO, "the file context is not a PE file");
+        return;
+    };
+    // the dos header and the rich header are not mapped by any section
+    let data = match fs::read(file) {
+        Ok(data) => data,
+        Err(e) => {
+            print_lg(LevelPrint::ErrorO, format!("failed to read {file} : {e}"));
+            return;
+        }
+    };
+    let dos = read_struct::<IMAGE_DOS_HEADER>(&data, 0, "dos header");
+    let rich = rich::parse_rich(&data);
+    match linev.get(1).copied() {
+        None => {
+            if let Ok(dos) = &dos {
+                print_dos(dos);
+            }
+            print_file_header(nt_header);
+            print_optional_header(nt_header);
+            print_directories(session, nt_header);
+            print_rich(rich.as_ref());
+            print_debug(session, nt_header);
+        }
+        Some("dos") => match &dos {
+            Ok(dos) => print_dos(dos),
+            Err(e) => print_lg(LevelPrint::ErrorO, e.to_string()),
+        },
+        Some("file") | Some("coff") => print_file_header(nt_header),
+        Some("optional") | Some("opt") => print_optional_header(nt_header),
+        Some("directories") | Some("dirs") => print_directories(session, nt_header),
+        Some("rich") => print_rich(rich.as_ref()),
+        Some("debug") => print_debug(session, nt_header),
+        Some(part) => print_lg(LevelPrint::ErrorO, format!("unknow header '{part}', expected dos, file, optional, dirs, rich or debug")),
+    }
+}
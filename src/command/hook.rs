@@ -1,8 +1,8 @@
-use std::ptr;
-use winapi::um::winnt::HANDLE;
-use crate::{usage, ALL_ELM};
-use crate::dbg::{memory, BASE_ADDR};
-use crate::symbol::SYMBOLS_V;
+use crate::dbg::target::DebugTarget;
+use crate::usage;
+use crate::dbg::memory;
+use crate::session::Session;
+use crate::symbol::Symbols;
 use crate::ut::cast::str_to;
 use crate::ut::fmt::{print_lg, LevelPrint};
 
@@ -13,11 +13,11 @@ pub struct Hook {
     pub origin_byte: u8,
 }
 
-fn get_addr_or_symbol(linev: &[&str], idx: usize) -> Option<u64> {
+fn get_addr_or_symbol(symbols: &Symbols, linev: &[&str], idx: usize) -> Option<u64> {
     match str_to::<u64>(linev[idx]) {
         Ok(addr) => Some(addr),
         Err(_) => {
-            if let Some(sym) = unsafe { (*ptr::addr_of!(SYMBOLS_V)).symbol_file.iter().find(|s| s.name == linev[idx]) } {
+            if let Some(sym) = symbols.symbol_file.iter().find(|s| s.name == linev[idx]) {
                 if sym.offset > 0 {
                     Some(sym.offset as u64)
                 } else {
@@ -33,56 +33,56 @@ fn get_addr_or_symbol(linev: &[&str], idx: usize) -> Option<u64> {
 }
 
 
-fn set_hook(addr1: u64, addr2: u64, h_proc: Option<HANDLE>) {
+fn set_hook(session: &mut Session, addr1: u64, addr2: u64, target: Option<&dyn DebugTarget>) {
     let mut orig_b = 0;
-    if let Some(h_proc) = h_proc {
-        if let Err(e) = unsafe { memory::breakpoint::set_breakpoint(h_proc, addr1 + BASE_ADDR, &mut orig_b) }  {
+    if let Some(target) = target {
+        if let Err(e) = memory::breakpoint::set_breakpoint(target, addr1 + session.base_addr, &mut orig_b) {
             print_lg(LevelPrint::ErrorO, format!("Failed to set hoot : {}", e));
             return;
         }
     }
-    unsafe { (*ptr::addr_of_mut!(ALL_ELM)).hook.push(Hook { target: addr1, replacen: addr2, origin_byte: orig_b }); }
+    session.all.hook.push(Hook { target: addr1, replacen: addr2, origin_byte: orig_b });
     print_lg(LevelPrint::DebugO, format!("Now when the program reaches rva {:#x}, it will be redirected to rva {:#x}", addr1, addr2));
 }
 
 
 
-pub fn hook(linev: &[&str]) {
+pub fn hook(session: &mut Session, linev: &[&str]) {
     if linev.len() < 3 {
         eprintln!("{}", usage::USAGE_HOOK);
         return;
     }
 
-    let addr1 = match get_addr_or_symbol(linev, 1) {
+    let addr1 = match get_addr_or_symbol(&session.symbols, linev, 1) {
         Some(addr) => addr,
         None => return,
     };
 
-    let addr2 = match get_addr_or_symbol(linev, 2) {
+    let addr2 = match get_addr_or_symbol(&session.symbols, linev, 2) {
         Some(addr) => addr,
         None => return,
     };
 
-    set_hook(addr1, addr2, None);
+    set_hook(session, addr1, addr2, None);
 }
 
 
 
-pub fn handle_hook_proc(linev: &[&str], h_proc: HANDLE) {
+pub fn handle_hook_proc(session: &mut Session, linev: &[&str], target: &dyn DebugTarget) {
     if linev.len() < 3 {
         eprintln!("{}", usage::USAGE_HOOK);
         return;
     }
 
-    let addr1 = match get_addr_or_symbol(linev, 1) {
+    let addr1 = match get_addr_or_symbol(&session.symbols, linev, 1) {
         Some(addr) => addr,
         None => return,
     };
 
-    let addr2 = match get_addr_or_symbol(linev, 2) {
+    let addr2 = match get_addr_or_symbol(&session.symbols, linev, 2) {
         Some(addr) => addr,
         None => return,
     };
 
-    set_hook(addr1, addr2, Some(h_proc));
+    set_hook(session, addr1, addr2, Some(target));
 }
@@ -1,53 +1,72 @@
 use crate::ut::fmt::VALID_COLOR;
-use crate::dbg::{memory, RealAddr, BASE_ADDR};
-use crate::pefile::function::FUNC_INFO;
-use crate::pefile::section::SECTION_VS;
-use crate::symbol::SYMBOLS_V;
+use crate::dbg::RealAddr;
+use crate::session::Session;
 use crate::usage;
+#[cfg(windows)]
 use std::ffi::CStr;
-use std::ptr::addr_of;
-use std::{io, mem, ptr};
+#[cfg(windows)]
+use std::{io, mem};
+#[cfg(windows)]
 use winapi::um::processthreadsapi::GetProcessId;
+#[cfg(windows)]
 use winapi::um::tlhelp32::{
     CreateToolhelp32Snapshot, Thread32First, Thread32Next,
     TH32CS_SNAPTHREAD, THREADENTRY32,
 };
-use winapi::um::winnt::{CONTEXT, HANDLE, RUNTIME_FUNCTION};
-use crate::cli::ALL_ELM;
+use crate::dbg::{CONTEXT, HANDLE};
+use crate::pefile::winnt::RUNTIME_FUNCTION;
 use crate::command::breakpoint::Brkpts;
+use crate::command::{headers, loadconfig, resource};
+#[cfg(windows)]
 use crate::process::get_module;
+use crate::pefile::{function, header, reloc, section};
+use crate::ut::cast::str_to;
 use crate::ut::fmt::*;
 
-pub fn handle_info(linev: &[&str], ctx: *const CONTEXT, proc_handle: HANDLE) {
+pub fn handle_info(session: &Session, linev: &[&str], ctx: *const CONTEXT, proc_handle: HANDLE) {
     if linev.len() < 2 {
         println!("{}", usage::USAGE_INFO);
         return;
     }
     let elm = linev[1];
     match elm {
-        "breakpoint" | "brpt" | "b" => print_elements(unsafe { &*addr_of!(ALL_ELM.break_rva) }),
-        "skip" => print_elements(unsafe { &*addr_of!(ALL_ELM.skip_addr) }),
-        "b-ret" => print_elements(unsafe { &*addr_of!(ALL_ELM.break_ret) }),
-        "symbol" | "sym" | "s" => print_sym(&linev[1..], ctx),
-        "hook-func" | "hook" | "h" => print_hook_func(),
-        "watchpoint" | "watch" | "w" => print_watchpt(ctx),
-        "function" | "func" | "f" => print_function(),
-        "section" | "sec" => print_section(),
-        "break-va" | "bva" | "b-va" => print_elements(unsafe { &*addr_of!(ALL_ELM.break_va) }),
-        "break-ret-va" | "b-ret-va" => print_elements(unsafe { &*addr_of!(ALL_ELM.break_ret_va) }),
+        "breakpoint" | "brpt" | "b" => print_elements(&session.all.break_rva),
+        "skip" => print_elements(&session.all.skip_addr),
+        "b-ret" => print_elements(&session.all.break_ret),
+        "symbol" | "sym" | "s" => print_sym(session, &linev[1..], ctx),
+        "hook-func" | "hook" | "h" => print_hook_func(session),
+        "watchpoint" | "watch" | "w" => print_watchpt(session, ctx),
+        "function" | "func" | "f" => print_function(session),
+        "section" | "sec" => print_section(session),
+        "headers" | "header" | "hdr" => headers::print_headers(session, &linev[1..]),
+        "imports" | "import" | "imp" => print_imports(session, &linev[1..]),
+        "relocs" | "reloc" => print_relocs(session, &linev[1..]),
+        "tls" => print_tls(session),
+        "loadconfig" | "load-config" | "lc" => loadconfig::print_load_config(session, &linev[1..]),
+        "unwind" => print_unwind(session, &linev[1..]),
+        "resources" | "resource" | "res" => resource::print_resources(session, &linev[1..]),
+        "segment" | "seg" => print_segment(session),
+        "break-va" | "bva" | "b-va" => print_elements(&session.all.break_va),
+        "break-ret-va" | "b-ret-va" => print_elements(&session.all.break_ret_va),
+        #[cfg(windows)]
         "proc" => view_all_module(proc_handle),
-        "hmodule" | "module" | "m" => handle_module(&linev[1..], proc_handle),
-        "def" => info_def(&linev[1..]),
+        #[cfg(windows)]
+        "hmodule" | "module" | "m" => handle_module(session, &linev[1..], proc_handle),
+        #[cfg(windows)]
         "thread" | "th" => view_thread(proc_handle),
+        #[cfg(not(windows))]
+        "proc" | "hmodule" | "module" | "m" | "thread" | "th" => {
+            let _ = proc_handle;
+            print_start()
+        }
+        "def" => info_def(session, &linev[1..]),
         "register" | "reg" => print_lg(LevelPrint::ErrorO, "to see the value of a register, type \"reg <register>\" or \"value <register>\""),
         "file" => {
             print!("{VALID_COLOR}");
-            unsafe {
-                if let Some(file) = &(*addr_of!(ALL_ELM)).file {
-                    print!("{file}");
-                } else {
-                    print!("not specified");
-                }
+            if let Some(file) = &session.all.file {
+                print!("{file}");
+            } else {
+                print!("not specified");
             }
             println!("{RESET_COLOR}");
         },
@@ -57,7 +76,8 @@ pub fn handle_info(linev: &[&str], ctx: *const CONTEXT, proc_handle: HANDLE) {
 
 
 
-pub fn handle_module(linev: &[&str], h_proc: HANDLE) {
+#[cfg(windows)]
+pub fn handle_module(session: &Session, linev: &[&str], h_proc: HANDLE) {
     if h_proc.is_null() {
         print_start();
         return;
@@ -70,12 +90,7 @@ pub fn handle_module(linev: &[&str], h_proc: HANDLE) {
         match get_module(h_proc) {
             Ok(module) => unsafe {
                 println!(
-                    "{}{:<10}{} {}{:<25}{} {}{:<25}{} {}{:<15}{} {}{:<5}{}",
-                    "\x1b[32m", "PID", "\x1b[0m",
-                    "\x1b[33m", "Start Addr", "\x1b[0m",
-                    "\x1b[33m", "End Addr", "\x1b[0m",
-                    "\x1b[36m", "Size", "\x1b[0m",
-                    "\x1b[35m", "Module", "\x1b[0m"
+                    "\x1b[32m{:<10}\x1b[0m \x1b[33m{:<25}\x1b[0m \x1b[33m{:<25}\x1b[0m \x1b[36m{:<15}\x1b[0m \x1b[35m{:<5}\x1b[0m", "PID", "Start Addr", "End Addr", "Size", "Module"
                 );
                 
                 let print_f = linev.len() > 2 && linev[2].contains("func");
@@ -86,26 +101,18 @@ pub fn handle_module(linev: &[&str], h_proc: HANDLE) {
                         let module_name = CStr::from_ptr(entry32.szModule.as_ptr()).to_string_lossy();
                         let base = entry32.modBaseAddr as u64;
                         println!(
-                            "{}{:<10}{} {}{:#018x}{}       {}{:#018x}{}       {}{:<#10x}{} {}{:<30}{}",
-                            BLUE_COLOR, entry32.th32ProcessID, RESET_COLOR,
-                            "\x1b[33m", base, RESET_COLOR,
-                            "\x1b[33m", end_addr, RESET_COLOR,
-                            "\x1b[36m", entry32.modBaseSize, RESET_COLOR,
-                            "\x1b[35m", module_name, RESET_COLOR
+                            "{}{:<10}{} \x1b[33m{:#018x}{}       \x1b[33m{:#018x}{}       \x1b[36m{:<#10x}{} \x1b[35m{:<30}{}",
+                            BLUE_COLOR, entry32.th32ProcessID, RESET_COLOR, base, RESET_COLOR, end_addr, RESET_COLOR, entry32.modBaseSize, RESET_COLOR, module_name, RESET_COLOR
                         );
                         if print_f {
-                            let s = (*addr_of!(SYMBOLS_V)).symbol_file.iter().filter(|s|s.is_in_this_dll(base)).collect::<Vec<_>>();
+                            let s = session.symbols.symbol_file.iter().filter(|s|s.is_in_this_dll(base)).collect::<Vec<_>>();
                             println!();
                             println!(
-                                "{}{:<25}{} {}{:<25}{}",
-                                "\x1b[36m", "Start Addr", "\x1b[0m",
-                                "\x1b[35m", "Function", "\x1b[0m"
+                                "\x1b[36m{:<25}\x1b[0m \x1b[35m{:<25}\x1b[0m", "Start Addr", "Function"
                             );
                             for s in s {
                                 println!(
-                                    "{}{:#018x}{}   ->  {}{:<30}{}",
-                                    "\x1b[36m", s.offset as u64 + s.src_file.dll_base(), RESET_COLOR,
-                                    "\x1b[35m", s.name, RESET_COLOR
+                                    "\x1b[36m{:#018x}{}   ->  \x1b[35m{:<30}{}", s.offset as u64 + s.src_file.dll_base(), RESET_COLOR, s.name, RESET_COLOR
                                 );
                             }
                         }
@@ -126,6 +133,7 @@ pub fn handle_module(linev: &[&str], h_proc: HANDLE) {
 
 
 
+#[cfg(windows)]
 pub fn view_thread(proc_handle: HANDLE) {
     if proc_handle.is_null() {
         print_start();
@@ -160,6 +168,7 @@ pub fn view_thread(proc_handle: HANDLE) {
     }
 }
 
+#[cfg(windows)]
 fn print_thread(th32: THREADENTRY32, cout: &mut i32) {
     println!("{}Thread{cout}: ", GREEN_COL);
     println!("    {}Thread id : {}", MAGENTA, th32.th32ThreadID);
@@ -169,31 +178,29 @@ fn print_thread(th32: THREADENTRY32, cout: &mut i32) {
     *cout += 1;
 }
 
-fn info_def(arg: &[&str]) {
+fn info_def(session: &Session, arg: &[&str]) {
     if arg.len() < 2 {
-        print_cr_func(&[""]);
-        print_struct_def(&[""]);
+        print_cr_func(session, &[""]);
+        print_struct_def(session, &[""]);
         return;
     }
     let type_elm = arg[1];
     match type_elm {
-        "function" | "func" => print_cr_func(&arg[1..]),
-        "struct" | "structure" => print_struct_def(&arg[1..]),
+        "function" | "func" => print_cr_func(session, &arg[1..]),
+        "struct" | "structure" => print_struct_def(session, &arg[1..]),
         "variable" | "var" => {},
         _ => print_lg(LevelPrint::ErrorO, format!("unknow option : '{type_elm}'"))
     }
 }
 
-fn print_struct_def(arg: &[&str]) {
+fn print_struct_def(session: &Session, arg: &[&str]) {
     if arg.len() < 2 {
-        unsafe {
-            println!("number of structure : {}", &(*addr_of!(ALL_ELM)).struct_def.len());
-            for structs in &(*addr_of!(ALL_ELM)).struct_def {
-                println!("  struct {};", structs.get_name_of_struct());
-            }
+        println!("number of structure : {}", session.all.struct_def.len());
+        for structs in &session.all.struct_def {
+            println!("  struct {};", structs.get_name_of_struct());
         }
     }else {
-        if let Some(structs) = unsafe {&(*addr_of!(ALL_ELM)).struct_def.iter().find(|s|s.get_name_of_struct() == arg[1])}{
+        if let Some(structs) = session.all.struct_def.iter().find(|s|s.get_name_of_struct() == arg[1]) {
             println!("\nstruct {} {{", structs.get_name_of_struct());
             for field in structs.get_field_of_struct() {
                 println!("    {} {};", field.type_p, field.name_field);
@@ -205,16 +212,14 @@ fn print_struct_def(arg: &[&str]) {
 
 
 
-fn print_cr_func(arg: &[&str]) {
+fn print_cr_func(session: &Session, arg: &[&str]) {
     if arg.len() < 2 {
-        unsafe {
-            print_lg(LevelPrint::DebugO, format!("Number of function : {}", (*addr_of!(ALL_ELM)).crt_func.len()));
-            for func in &(*addr_of!(ALL_ELM)).crt_func {
-                println!("\n{:#x}: {}", func.addr, func.name);
-            }
+        print_lg(LevelPrint::DebugO, format!("Number of function : {}", session.all.crt_func.len()));
+        for func in &session.all.crt_func {
+            println!("\n{:#x}: {}", func.addr, func.name);
         }
     } else {
-        if let Some(cr_func) = unsafe { (*addr_of!(ALL_ELM)).crt_func.iter().find(|s| s.name == arg[1]) } {
+        if let Some(cr_func) = session.all.crt_func.iter().find(|s| s.name == arg[1]) {
             println!("{} {}: ", ADDR_COLOR, cr_func.name);
             for line in &cr_func.code_str {
                 println!("     {line}");
@@ -235,16 +240,12 @@ fn print_start() {
 
 
 
+#[cfg(windows)]
 fn view_all_module(h_proc: HANDLE) {
     match get_module(h_proc) {
         Ok(modules) => unsafe {
             println!(
-                "{}{:<10}{} {}{:<25}{} {}{:<25}{} {}{:<15}{} {}{:<5}{}",
-                "\x1b[32m", "PID", "\x1b[0m",
-                "\x1b[33m", "Start Addr", "\x1b[0m",
-                "\x1b[33m", "End Addr", "\x1b[0m",
-                "\x1b[36m", "Size", "\x1b[0m",
-                "\x1b[35m", "Module", "\x1b[0m"
+                "\x1b[32m{:<10}\x1b[0m \x1b[33m{:<25}\x1b[0m \x1b[33m{:<25}\x1b[0m \x1b[36m{:<15}\x1b[0m \x1b[35m{:<5}\x1b[0m", "PID", "Start Addr", "End Addr", "Size", "Module"
             );
 
             for entry32 in modules {
@@ -252,12 +253,8 @@ fn view_all_module(h_proc: HANDLE) {
                 let module_name = CStr::from_ptr(entry32.szModule.as_ptr()).to_string_lossy();
 
                 println!(
-                    "{}{:<10}{} {}{:#018x}{}       {}{:#018x}{}       {}{:<#10x}{} {}{:<30}{}",
-                    BLUE_COLOR, entry32.th32ProcessID, RESET_COLOR,
-                    "\x1b[33m", entry32.modBaseAddr as u64, RESET_COLOR,
-                    "\x1b[33m", end_addr, RESET_COLOR,
-                    "\x1b[36m", entry32.modBaseSize, RESET_COLOR,
-                    "\x1b[35m", module_name, RESET_COLOR
+                    "{}{:<10}{} \x1b[33m{:#018x}{}       \x1b[33m{:#018x}{}       \x1b[36m{:<#10x}{} \x1b[35m{:<30}{}",
+                    BLUE_COLOR, entry32.th32ProcessID, RESET_COLOR, entry32.modBaseAddr as u64, RESET_COLOR, end_addr, RESET_COLOR, entry32.modBaseSize, RESET_COLOR, module_name, RESET_COLOR
                 );
             }
         }
@@ -267,48 +264,239 @@ fn view_all_module(h_proc: HANDLE) {
 
 
 
-fn print_section() {
-    unsafe {
-        for (i, section) in (*addr_of!(SECTION_VS)).iter().enumerate() {
-            println!("\n{VALID_COLOR}#{i}: \
-            \n     {}Name         : {}\
-            \n     {}Address      : {:#x}\
-            \n     {}Size of code : {:#x}{RESET_COLOR}",
-                GREEN_COL, section.name,
-                ADDR_COLOR, section.addr as u64 + BASE_ADDR,
-                VALUE_COLOR, section.content.len()
-            )
+fn print_section(session: &Session) {
+    for (i, section) in session.sections.iter().enumerate() {
+        println!("\n{VALID_COLOR}#{i}: \
+        \n     {}Name            : {}\
+        \n     {}Address         : {:#x}\
+        \n     {}Virtual size    : {:#x}\
+        \n     {}Raw size        : {:#x} at file offset {:#x}\
+        \n     {}Characteristics : {:#x} {} {}\
+        \n     {}Entropy         : {:.2}{RESET_COLOR}",
+            GREEN_COL, section.name,
+            ADDR_COLOR, section.addr as u64 + session.base_addr,
+            VALUE_COLOR, section.virtual_size,
+            VALUE_COLOR, section.raw_size, section.raw_ptr,
+            BLUE_COLOR, section.characteristics, section.permissions(), header::section_characteristics_str(section.characteristics),
+            VALUE_COLOR, section.entropy(),
+        )
+    }
+    let entry_point = match (session.nt_header, session.elf_header) {
+        (Some(nt_header), _) => nt_header.entry_point(),
+        (None, Some(elf)) => elf.entry.saturating_sub(elf.image_base) as u32,
+        (None, None) => 0,
+    };
+    let warnings = section::check_sections(&session.sections, entry_point);
+    if !warnings.is_empty() {
+        println!();
+    }
+    for warning in warnings {
+        print_lg(LevelPrint::WarningO, warning.to_string());
+    }
+}
+
+fn print_imports(session: &Session, linev: &[&str]) {
+    let delay = linev.contains(&"--delay");
+    let dll_filter = linev[1..].iter().find(|a| **a != "--delay");
+    let mut dll = "";
+    for import in &session.imports {
+        if import.is_delay() != delay || dll_filter.is_some_and(|d| !import.is_from(d)) {
+            continue;
+        }
+        if import.dll != dll {
+            dll = &import.dll;
+            println!("\n{VALID_COLOR}{dll}{RESET_COLOR}");
+        }
+        let thunk = match import.delay_thunk {
+            Some(thunk) => format!("  {BLUE_COLOR}thunk {:#x}", thunk as u64 + session.base_addr),
+            None => String::new(),
+        };
+        match &import.name {
+            Some(name) => println!("     {ADDR_COLOR}{:#x}{RESET_COLOR}  {GREEN_COL}{:<40}{VALUE_COLOR}hint {:#x}{thunk}{RESET_COLOR}", import.iat_rva as u64 + session.base_addr, name, import.hint),
+            None => println!("     {ADDR_COLOR}{:#x}{RESET_COLOR}  {MAGENTA}{:<40}{VALUE_COLOR}ordinal {}{thunk}{RESET_COLOR}", import.iat_rva as u64 + session.base_addr, import.func_name(), import.ordinal.unwrap_or(0)),
+        }
+    }
+    if dll.is_empty() {
+        match (dll_filter, delay) {
+            (Some(d), _) => print_lg(LevelPrint::ErrorO, format!("no import from '{d}'")),
+            (None, true) => print_lg(LevelPrint::WarningO, "the file context has no delay-loaded imports"),
+            (None, false) => print_lg(LevelPrint::WarningO, "the file context has no imports"),
         }
     }
 }
 
-fn print_function() {
-    unsafe {
-        for (i, func) in (&*addr_of!(FUNC_INFO)).iter().enumerate() {
-            println!(
-                "\n{VALID_COLOR}func_#{i}:\
-        \n     {}Address     : {:#x} {}\
-        \n     {}end-address : {:#x}\
-        \n     {}size        : {:#x}{RESET_COLOR}",
-                ADDR_COLOR, func.BeginAddress as u64 + BASE_ADDR, get_sym_name(func),
-                VALUE_COLOR, func.EndAddress as u64 + BASE_ADDR,
-                MAGENTA, func.EndAddress - func.BeginAddress,
-            )
+fn print_tls(session: &Session) {
+    let Some(tls) = &session.tls else {
+        print_lg(LevelPrint::WarningO, "the file context has no tls directory");
+        return;
+    };
+    println!("{VALID_COLOR}TLS directory:\
+    \n     {}Raw data       : {:#x} - {:#x}\
+    \n     {}Index address  : {:#x}\
+    \n     {}Callbacks      : {:#x}\
+    \n     {}Zero fill      : {:#x}\
+    \n     {}Characteristics: {:#x}{RESET_COLOR}",
+        ADDR_COLOR, tls.start_raw_data, tls.end_raw_data,
+        ADDR_COLOR, tls.index_addr,
+        ADDR_COLOR, tls.callbacks_addr,
+        VALUE_COLOR, tls.zero_fill,
+        VALUE_COLOR, tls.characteristics,
+    );
+    if tls.callbacks.is_empty() {
+        println!("{VALID_COLOR}no callback{RESET_COLOR}");
+    }
+    for (i, callback) in tls.callbacks.iter().enumerate() {
+        let name = session.symbols.symbol_file.iter().find(|s| s.offset == *callback as i64).map(|s| format!("<{}>", s.name)).unwrap_or_default();
+        println!("{CYAN_COLOR}{i}{RESET_COLOR}: {ADDR_COLOR}{:#x}{RESET_COLOR} {GREEN_COL}{name}{RESET_COLOR}", *callback as u64 + session.base_addr);
+    }
+}
+
+fn print_relocs(session: &Session, linev: &[&str]) {
+    let (start, end) = match linev.get(1) {
+        Some(range) => {
+            let (start, end) = range.split_once('-').unwrap_or((range, ""));
+            let start = match str_to::<u64>(start) {
+                Ok(start) => start,
+                Err(e) => {
+                    print_lg(LevelPrint::ErrorO, format!("invalid start of range : {e}"));
+                    return;
+                }
+            };
+            match str_to::<u64>(end) {
+                Ok(end) => (start, end),
+                Err(_) if end.is_empty() => (start & !0xfff, (start & !0xfff) + 0x1000),
+                Err(e) => {
+                    print_lg(LevelPrint::ErrorO, format!("invalid end of range : {e}"));
+                    return;
+                }
+            }
         }
+        None => (0, u64::MAX),
+    };
+    let relocs = reloc::relocs_in(&session.relocs, start, end);
+    if relocs.is_empty() {
+        print_lg(LevelPrint::WarningO, "no relocation in this range");
+        return;
+    }
+    let base_addr = session.base_addr;
+    for rel in relocs {
+        let target = match rel.target(&session.sections) {
+            Some(target) if rel.is_pointer() => {
+                let rva = target.wrapping_sub(session.image_base);
+                format!("-> {VALUE_COLOR}{:#x}{RESET_COLOR} (rva {:#x})", if base_addr != 0 { rva.wrapping_add(base_addr) } else { target }, rva)
+            }
+            Some(target) => format!("-> {VALUE_COLOR}{:#x}{RESET_COLOR}", target),
+            None => String::new(),
+        };
+        println!("{ADDR_COLOR}{:#x}{RESET_COLOR}  {BLUE_COLOR}{:<8}{RESET_COLOR} {target}", rel.rva as u64 + base_addr, rel.type_name());
     }
+    println!("{VALID_COLOR}{} relocations{RESET_COLOR}", relocs.len());
 }
 
-fn get_sym_name(func: &RUNTIME_FUNCTION) -> String {
-    if let Some(sym) = unsafe {
-        (*addr_of!(SYMBOLS_V)).symbol_file.iter().find(|s| s.offset + BASE_ADDR as i64 == func.BeginAddress as i64 + BASE_ADDR as i64)
-    } {
+fn print_unwind(session: &Session, linev: &[&str]) {
+    let rva = match linev.get(1).map(|a| str_to::<u64>(a)) {
+        Some(Ok(rva)) => rva,
+        Some(Err(e)) => {
+            print_lg(LevelPrint::ErrorO, format!("invalid rva : {e}"));
+            return;
+        }
+        None => {
+            print_lg(LevelPrint::ErrorO, "USAGE: info unwind <rva>");
+            return;
+        }
+    };
+    let Some(func) = function::find_function(&session.func_info, rva) else {
+        print_lg(LevelPrint::ErrorO, format!("no function of the exception directory contains the rva {:#x}", rva));
+        return;
+    };
+    let mut func = *func;
+    for depth in 0.. {
+        let info = match function::read_unwind_info(&session.sections, &func) {
+            Ok(info) => info,
+            Err(e) => {
+                print_lg(LevelPrint::ErrorO, format!("failed to decode the unwind info of {:#x} : {e}", func.BeginAddress));
+                return;
+            }
+        };
+        let flags = [(function::UNW_FLAG_EHANDLER, "EHANDLER"), (function::UNW_FLAG_UHANDLER, "UHANDLER"), (function::UNW_FLAG_CHAININFO, "CHAININFO")]
+            .iter().filter(|(f, _)| info.flags & f != 0).map(|(_, n)| *n).collect::<Vec<_>>();
+        println!("{VALID_COLOR}{}func_{:#x} [{ADDR_COLOR}{:#x} - {:#x}{VALID_COLOR}] unwind info {:#x}{RESET_COLOR}",
+            if depth == 0 { "" } else { "chained to " }, func.BeginAddress, func.BeginAddress as u64 + session.base_addr, func.EndAddress as u64 + session.base_addr, function::unwind_data(&func));
+        println!("     {BLUE_COLOR}version {}, flags [{}], prolog size {:#x}, frame register {}{RESET_COLOR}",
+            info.version, flags.join(" "), info.prolog_size,
+            if info.frame_register == 0 { "none".to_string() } else { format!("{} (rsp+{:#x})", function::reg_name(info.frame_register), info.frame_offset as u32 * 16) });
+        for code in &info.codes {
+            println!("     {CYAN_COLOR}{:#06x}{RESET_COLOR}  {VALUE_COLOR}{}{RESET_COLOR}", code.prolog_offset, code.describe(info.frame_register, info.frame_offset));
+        }
+        if let Some(handler) = info.handler {
+            println!("     {GREEN_COL}handler {:#x}{RESET_COLOR}", handler as u64 + session.base_addr);
+        }
+        match info.chained {
+            Some(chained) if depth < 32 => func = chained,
+            _ => break,
+        }
+    }
+}
+
+fn print_segment(session: &Session) {
+    match session.elf_header {
+        Some(header) => println!("{VALID_COLOR}ELF{} {}, machine {:#x}, entry point : {ADDR_COLOR}{:#x}{RESET_COLOR}",
+            header.bitness,
+            match header.elf_type {
+                2 => "executable",
+                3 => "shared object",
+                _ => "unknown type",
+            },
+            header.machine, header.entry
+        ),
+        None => {
+            print_lg(LevelPrint::ErrorO, "the file context is not an elf file");
+            return;
+        }
+    }
+    for (i, ph) in session.program_headers.iter().enumerate() {
+        println!("\n{VALID_COLOR}#{i}: \
+        \n     {}Type         : {:#x}\
+        \n     {}Flags        : {}{}{}\
+        \n     {}Offset       : {:#x}\
+        \n     {}Address      : {:#x}\
+        \n     {}File size    : {:#x}\
+        \n     {}Memory size  : {:#x}\
+        \n     {}Align        : {:#x}{RESET_COLOR}",
+            GREEN_COL, ph.p_type,
+            BLUE_COLOR, if ph.flags & 4 != 0 { "r" } else { "-" }, if ph.flags & 2 != 0 { "w" } else { "-" }, if ph.flags & 1 != 0 { "x" } else { "-" },
+            VALUE_COLOR, ph.offset,
+            ADDR_COLOR, ph.vaddr.saturating_sub(session.elf_header.map(|h| h.image_base).unwrap_or(0)) + session.base_addr,
+            VALUE_COLOR, ph.file_size,
+            VALUE_COLOR, ph.mem_size,
+            VALUE_COLOR, ph.align,
+        )
+    }
+}
+
+fn print_function(session: &Session) {
+    for (i, func) in session.func_info.iter().enumerate() {
+        println!(
+            "\n{VALID_COLOR}func_#{i}:\
+    \n     {}Address     : {:#x} {}\
+    \n     {}end-address : {:#x}\
+    \n     {}size        : {:#x}{RESET_COLOR}",
+            ADDR_COLOR, func.BeginAddress as u64 + session.base_addr, get_sym_name(session, func),
+            VALUE_COLOR, func.EndAddress as u64 + session.base_addr,
+            MAGENTA, func.EndAddress - func.BeginAddress,
+        )
+    }
+}
+
+fn get_sym_name(session: &Session, func: &RUNTIME_FUNCTION) -> String {
+    if let Some(sym) = session.symbols.symbol_file.iter().find(|s| s.offset == func.BeginAddress as i64) {
         format!("<{}>", sym.name)
     } else {
         "".to_string()
     }
 }
 
-fn print_elements<T: IntoIterator<Item = &'static Brkpts>>(elements: T) {
+fn print_elements<'a, T: IntoIterator<Item = &'a Brkpts>>(elements: T) {
     for (i, e) in elements.into_iter().enumerate() {
         println!("{i} : {:#x}", e.addr);
     }
@@ -316,41 +504,37 @@ fn print_elements<T: IntoIterator<Item = &'static Brkpts>>(elements: T) {
 
 
 
-fn print_watchpt(ctx: *const CONTEXT) {
-    unsafe {
-        for (i, watchpts) in (*addr_of!(ALL_ELM)).watchpts.iter().enumerate() {
-            println!(
-                "{GREEN_COL}{i}: \
-        \n     {}memory zone    : {}\
-        \n     {}check access   : {:?}\
-        \n     {}offset         : {}\
-        \n     {}size           : {:#x}{RESET_COLOR}",
-                CYAN_COLOR, watchpts.flag_type_mem,
-                BYTES_COLOR, watchpts.check_type,
-                ADDR_COLOR, watchpts.format_offset(ctx),
-                VALID_COLOR, watchpts.memory_size
-            );
-        }
+fn print_watchpt(session: &Session, ctx: *const CONTEXT) {
+    for (i, watchpts) in session.all.watchpts.iter().enumerate() {
+        println!(
+            "{GREEN_COL}{i}: \
+    \n     {}memory zone    : {}\
+    \n     {}check access   : {:?}\
+    \n     {}offset         : {}\
+    \n     {}size           : {:#x}{RESET_COLOR}",
+            CYAN_COLOR, watchpts.flag_type_mem,
+            BYTES_COLOR, watchpts.check_type,
+            ADDR_COLOR, watchpts.format_offset(session, ctx),
+            VALID_COLOR, watchpts.memory_size
+        );
     }
 }
 
-pub fn print_hook_func() {
-    unsafe {
-        for (i, hook) in (*addr_of!(ALL_ELM)).hook.iter().enumerate() {
-            println!(
-                "{VALUE_COLOR}{i}{RESET_COLOR}:\
-            \n     {WAR_COLOR}Target   : {GREEN_COL}{:#x}\
-            \n     {WAR_COLOR}Replace  : {MAGENTA}{:#x}{RESET_COLOR}\n",
-                hook.target, hook.replacen
-            );
-        }
+pub fn print_hook_func(session: &Session) {
+    for (i, hook) in session.all.hook.iter().enumerate() {
+        println!(
+            "{VALUE_COLOR}{i}{RESET_COLOR}:\
+        \n     {WAR_COLOR}Target   : {GREEN_COL}{:#x}\
+        \n     {WAR_COLOR}Replace  : {MAGENTA}{:#x}{RESET_COLOR}\n",
+            hook.target, hook.replacen
+        );
     }
 }
 
-pub fn print_sym(linev: &[&str], ctx: *const CONTEXT) {
-    println!("{VALID_COLOR}Symbol type: {VALUE_COLOR}{}{RESET_COLOR}", unsafe { (*ptr::addr_of!(SYMBOLS_V)).symbol_type });
+pub fn print_sym(session: &Session, linev: &[&str], ctx: *const CONTEXT) {
+    println!("{VALID_COLOR}Symbol type: {VALUE_COLOR}{}{RESET_COLOR}", session.symbols.symbol_type);
     if linev.len() > 1 {
-        if let Some(sym) = unsafe { (*addr_of!(SYMBOLS_V)).symbol_file.iter().find(|s|s.name == linev[1])} {
+        if let Some(sym) = session.symbols.symbol_file.iter().find(|s|s.name == linev[1]) {
             println!(
                 "{CYAN_COLOR}1{RESET_COLOR}:\
             \n     {}Name     : {}\
@@ -360,10 +544,10 @@ pub fn print_sym(linev: &[&str], ctx: *const CONTEXT) {
             \n     {}file     : {}:{}\
             {RESET_COLOR}\n",
                 GREEN_COL, sym.name,
-                if unsafe { BASE_ADDR == 0 } {
+                if session.base_addr == 0 {
                     format!("{}offset   : {:#x}", ADDR_COLOR, sym.offset)
                 } else {
-                    format!("{}address  : {:#x}", ADDR_COLOR, sym.real_addr(ctx))
+                    format!("{}address  : {:#x}", ADDR_COLOR, sym.real_addr(session, ctx))
                 },
                 BLUE_COLOR, sym.types_e,
                 MAGENTA, sym.size,
@@ -373,7 +557,7 @@ pub fn print_sym(linev: &[&str], ctx: *const CONTEXT) {
             print_lg(LevelPrint::ErrorO, "unknown symbol");
         }
     }else {
-        for (i, sym) in unsafe { (*addr_of!(SYMBOLS_V)).symbol_file.iter().enumerate() } {
+        for (i, sym) in session.symbols.symbol_file.iter().enumerate() {
             println!(
                 "{CYAN_COLOR}{i}{RESET_COLOR}:\
             \n     {}Name     : {}\
@@ -383,10 +567,10 @@ pub fn print_sym(linev: &[&str], ctx: *const CONTEXT) {
             \n     {}file     : {}:{}\
             {RESET_COLOR}\n",
                 GREEN_COL, sym.name,
-                if unsafe { BASE_ADDR == 0 } {
+                if session.base_addr == 0 {
                     format!("{}offset   : {:#x}", ADDR_COLOR, sym.offset)
                 } else {
-                    format!("{}address  : {:#x}", ADDR_COLOR, sym.real_addr(ctx))
+                    format!("{}address  : {:#x}", ADDR_COLOR, sym.real_addr(session, ctx))
                 },
                 BLUE_COLOR, sym.types_e,
                 MAGENTA, sym.size,
@@ -398,35 +582,35 @@ pub fn print_sym(linev: &[&str], ctx: *const CONTEXT) {
 
 
 
-pub fn print_frame(count: usize, ctx: *const CONTEXT) {
-    unsafe {
-        for i in 0..count {
-            if let Some(frame) = (*addr_of!(memory::stack::ST_FRAME)).get(i) {
-                let get_function_and_symbol = |offset| {
-                    if let Some(sym) = (*&raw const SYMBOLS_V).symbol_file.iter().find(|s|s.is_in_sym(offset, ctx)) {
-                        let offset = offset - sym.real_addr(ctx);
-                        return format!("<{}+{}>", sym.name, offset);
-                    }
-                            else if let Some(f) = (*&raw const FUNC_INFO).iter().find(|f|{
-                        f.BeginAddress as u64 + BASE_ADDR <= offset && f.EndAddress as u64 + BASE_ADDR >= offset
-                    }) {
-                        let func_addr = f.BeginAddress as u64 + BASE_ADDR;
-                        let offset = offset - func_addr;
-                        return format!("<func_{:#x}+{}>", func_addr - BASE_ADDR, offset);
-                    }
-                    return String::from("")
-                };
-                println!("\n{}#{}:", BLUE_COLOR, i);
-                println!("{}   rip               = {}{:#18x} {}", ADDR_COLOR, VALUE_COLOR, frame.AddrPC.Offset, get_function_and_symbol(frame.AddrPC.Offset));
-                println!("{}   Return Address    = {}{:#18x} {}", ADDR_COLOR, BYTES_COLOR, frame.AddrReturn.Offset, get_function_and_symbol(frame.AddrReturn.Offset));
-                println!("{}   Frame Ptr         = {}{:#18x}", ADDR_COLOR, SYM_COLOR, frame.AddrFrame.Offset);
-                println!("{}   Stack Ptr         = {}{:#18x}", ADDR_COLOR, GREEN_COL, frame.AddrStack.Offset);
-            } else {
-                if count != usize::MAX {
-                    print_lg(LevelPrint::WarningO, format!("the count is greater than the total number of frames, frame: {} count: {}", (*&raw const memory::stack::ST_FRAME).len(), count));
+#[cfg(windows)]
+pub fn print_frame(session: &Session, count: usize, ctx: *const CONTEXT) {
+    let base_addr = session.base_addr;
+    for i in 0..count {
+        if let Some(frame) = session.st_frame.get(i) {
+            let get_function_and_symbol = |offset| {
+                if let Some(sym) = session.symbols.symbol_file.iter().find(|s|s.is_in_sym(session, offset, ctx)) {
+                    let offset = offset - sym.real_addr(session, ctx);
+                    return format!("<{}+{}>", sym.name, offset);
                 }
-                return;
+                        else if let Some(f) = session.func_info.iter().find(|f|{
+                    f.BeginAddress as u64 + base_addr <= offset && f.EndAddress as u64 + base_addr >= offset
+                }) {
+                    let func_addr = f.BeginAddress as u64 + base_addr;
+                    let offset = offset - func_addr;
+                    return format!("<func_{:#x}+{}>", func_addr - base_addr, offset);
+                }
+                String::from("")
+            };
+            println!("\n{}#{}:", BLUE_COLOR, i);
+            println!("{}   rip               = {}{:#18x} {}", ADDR_COLOR, VALUE_COLOR, frame.pc, get_function_and_symbol(frame.pc));
+            println!("{}   Return Address    = {}{:#18x} {}", ADDR_COLOR, BYTES_COLOR, frame.ret, get_function_and_symbol(frame.ret));
+            println!("{}   Frame Ptr         = {}{:#18x}", ADDR_COLOR, SYM_COLOR, frame.frame);
+            println!("{}   Stack Ptr         = {}{:#18x}", ADDR_COLOR, GREEN_COL, frame.stack);
+        } else {
+            if count != usize::MAX {
+                print_lg(LevelPrint::WarningO, format!("the count is greater than the total number of frames, frame: {} count: {}", session.st_frame.len(), count));
             }
+            return;
         }
     }
 }
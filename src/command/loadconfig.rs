@@ -0,0 +1,95 @@
+use crate::pefile::loadconfig::{GuardEntry, LoadConfig};
+use crate::session::Session;
+use crate::ut::fmt::*;
+
+
+// the directory holds VAs at the preferred base, they are moved to the real base once the process is started
+fn va(session: &Session, va: u64) -> String {
+    match va {
+        0 => "none".to_string(),
+        va if session.base_addr != 0 => format!("{:#x}", va.wrapping_sub(session.image_base).wrapping_add(session.base_addr)),
+        va => format!("{:#x}", va),
+    }
+}
+
+
+fn sym_name(session: &Session, rva: u32) -> String {
+    session.symbols.symbol_file.iter()
+        .find(|s| !s.src_file.is_dll() && s.offset == rva as i64)
+        .map(|s| format!("<{}>", s.name))
+        .unwrap_or_default()
+}
+
+
+fn print_table(session: &Session, name: &str, entries: &[GuardEntry]) {
+    if entries.is_empty() {
+        print_lg(LevelPrint::WarningO, format!("the {name} table is empty"));
+        return;
+    }
+    for (i, entry) in entries.iter().enumerate() {
+        println!("{CYAN_COLOR}{i:<6}{RESET_COLOR}{ADDR_COLOR}{:#x}{RESET_COLOR} {GREEN_COL}{:<40}{RESET_COLOR}{BLUE_COLOR}{}{RESET_COLOR}",
+            entry.rva as u64 + session.base_addr, sym_name(session, entry.rva), entry.flags_str());
+    }
+    println!("{VALID_COLOR}{} entries{RESET_COLOR}", entries.len());
+}
+
+
+fn print_directory(session: &Session, lc: &LoadConfig) {
+    println!("{VALID_COLOR}Load config directory ({:#x} bytes):\
+    \n     {}Time stamp         : {:#x}\
+    \n     {}Version            : {}.{}\
+    \n     {}Global flags       : clear {:#x} set {:#x}\
+    \n     {}Dependent load     : {:#x}\
+    \n     {}Security cookie    : {}\
+    \n     {}SEH handler table  : {} ({} handlers)\
+    \n     {}CF check pointer   : {}\
+    \n     {}CF dispatch pointer: {}\
+    \n     {}CF function table  : {} ({} functions, {} bytes per entry)\
+    \n     {}Guard flags        : {:#x} {}\
+    \n     {}Address taken IAT  : {} ({} entries)\
+    \n     {}Long jump targets  : {} ({} entries)\
+    \n     {}EH continuations   : {} ({} entries)\
+    \n     {}XFG check pointer  : {}\
+    \n     {}XFG dispatch       : {}\
+    \n     {}Dynamic relocations: {} (offset {:#x} in section {})\
+    \n     {}CHPE metadata      : {}{RESET_COLOR}",
+        lc.size,
+        VALUE_COLOR, lc.time_date_stamp,
+        VALUE_COLOR, lc.major_version, lc.minor_version,
+        VALUE_COLOR, lc.global_flags_clear, lc.global_flags_set,
+        VALUE_COLOR, lc.dependent_load_flags,
+        ADDR_COLOR, va(session, lc.security_cookie),
+        ADDR_COLOR, va(session, lc.se_handler_table), lc.se_handler_count,
+        ADDR_COLOR, va(session, lc.guard_cf_check_function_pointer),
+        ADDR_COLOR, va(session, lc.guard_cf_dispatch_function_pointer),
+        ADDR_COLOR, va(session, lc.guard_cf_function_table), lc.guard_cf_function_count, lc.guard_entry_size(),
+        BLUE_COLOR, lc.guard_flags, lc.guard_flags_str(),
+        ADDR_COLOR, va(session, lc.guard_address_taken_iat_table), lc.guard_address_taken_iat_count,
+        ADDR_COLOR, va(session, lc.guard_long_jump_table), lc.guard_long_jump_count,
+        ADDR_COLOR, va(session, lc.guard_eh_continuation_table), lc.guard_eh_continuation_count,
+        ADDR_COLOR, va(session, lc.guard_xfg_check_function_pointer),
+        ADDR_COLOR, va(session, lc.guard_xfg_dispatch_function_pointer),
+        ADDR_COLOR, va(session, lc.dynamic_value_reloc_table), lc.dynamic_value_reloc_table_offset, lc.dynamic_value_reloc_table_section,
+        ADDR_COLOR, va(session, lc.chpe_metadata_pointer),
+    );
+}
+
+
+pub fn print_load_config(session: &Session, linev: &[&str]) {
+    let Some(lc) = &session.load_config else {
+        print_lg(LevelPrint::WarningO, "the file context has no load config directory");
+        return;
+    };
+    match linev.get(1).copied() {
+        None => print_directory(session, lc),
+        Some("seh") => {
+            let entries = lc.se_handlers.iter().map(|&rva| GuardEntry { rva, flags: 0 }).collect::<Vec<_>>();
+            print_table(session, "safe seh", &entries);
+        }
+        Some("cf") | Some("cfg") => print_table(session, "guard cf function", &lc.guard_cf_functions),
+        Some("iat") => print_table(session, "address taken iat", &lc.guard_address_taken_iat),
+        Some("longjmp") => print_table(session, "long jump target", &lc.guard_long_jumps),
+        Some("ehcont") => print_table(session, "eh continuation", &lc.guard_eh_continuations),
+        Some(table) => print_lg(LevelPrint::ErrorO, format!("unknow table '{table}', expected seh, cf, iat, longjmp or ehcont")),
+    }
+}
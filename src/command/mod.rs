@@ -1,45 +1,55 @@
 pub mod attach;
 pub mod breakpoint;
 pub mod def;
+pub mod dump;
 pub mod file;
+pub mod headers;
 pub mod hook;
+pub mod loadconfig;
 pub mod proc_addr;
+pub mod remote;
 pub mod remover;
 pub mod reset;
+pub mod resource;
+#[cfg(windows)]
 pub mod set;
 pub mod skip;
+pub mod start;
 pub mod stret;
 pub mod sym;
 pub mod info;
+pub mod trace;
 pub mod watchpoint;
 
 pub mod arg {
-    use crate::{usage, ALL_ELM};
+    use crate::session::Session;
+    use crate::usage;
     use crate::ut::fmt::{print_lg, LevelPrint};
 
-    pub fn set_argument(linev: &[&str]) {
+    pub fn set_argument(session: &mut Session, linev: &[&str]) {
         if linev.len() < 2 {
             print_lg(LevelPrint::ErrorO, usage::USAGE_SET_ARG.to_string());
             return;
         }
         let arg = linev[1..].join(" ");
-        unsafe { ALL_ELM.arg = Some(arg.clone()); }
+        session.all.arg = Some(arg.clone());
         print_lg(LevelPrint::DebugO, format!("the arguments have been recorded\narg expression : {arg}"));
     }
 }
 
 pub mod with_va {
+    use crate::session::Session;
     use crate::ut::cast::str_to;
     use crate::ut::fmt::{print_lg, LevelPrint};
 
-    pub fn handle_calcule_va(linev: &[&str]) {
+    pub fn handle_calcule_va(session: &Session, linev: &[&str]) {
         if linev.len() != 2 {
             print_lg(LevelPrint::ErrorO, "USAGE: cva <rva>".to_string());
             return;
         }
         match str_to::<u64>(linev[1]) {
             Ok(value) => {
-                print_lg(LevelPrint::DebugO, format!("VA is : {:#x}", unsafe { crate::dbg::BASE_ADDR } + value));
+                print_lg(LevelPrint::DebugO, format!("VA is : {:#x}", session.base_addr + value));
             },
             Err(e) => {
                 print_lg(LevelPrint::ErrorO, format!("when transforming rva str '{e}' into u64 : {e}"));
@@ -47,20 +57,20 @@ pub mod with_va {
         }
     }
 
-    pub fn handle_calcule_rva(linev: &[&str]) {
+    pub fn handle_calcule_rva(session: &Session, linev: &[&str]) {
         if linev.len() != 2 {
             return;
         }
         match str_to::<u64>(linev[1]) {
-            Ok(addr_va) => unsafe {
-                if addr_va < crate::dbg::BASE_ADDR {
+            Ok(addr_va) => {
+                if addr_va < session.base_addr {
                     print_lg(LevelPrint::ErrorO, format!(
                         "the specified address cannot be larger than the base address - {:#x} - {:#x}",
-                        addr_va, *&raw const crate::dbg::BASE_ADDR
+                        addr_va, session.base_addr
                     ));
                     return;
                 }
-                print_lg(LevelPrint::DebugO, format!("RVA is : {:#x}", addr_va - crate::dbg::BASE_ADDR));
+                print_lg(LevelPrint::DebugO, format!("RVA is : {:#x}", addr_va - session.base_addr));
             },
             Err(e) => {
                 print_lg(LevelPrint::ErrorO, format!("{e}"));
@@ -73,7 +83,7 @@ pub mod clear_cmd {
     use std::process::Command;
 
     pub fn clear_cmd() {
-        Command::new("cmd").args(&["/C", "cls"]).status().unwrap();
+        Command::new("cmd").args(["/C", "cls"]).status().unwrap();
     }
 }
 
@@ -1,52 +1,85 @@
+#[cfg(windows)]
 use std::ffi::CStr;
+use std::path::Path;
+use std::rc::Rc;
+use anyhow::anyhow;
 use crate::usage::USAGE_PROC_ADDR;
-use std::io;
-use winapi::um::libloaderapi::{FreeLibrary, GetProcAddress, LoadLibraryA};
-use winapi::um::winnt::HANDLE;
+use crate::dbg::HANDLE;
+#[cfg(windows)]
+use crate::dbg::target::WinTarget;
+use crate::pefile::{self, export};
+use crate::pefile::export::{ExportTable, ResolvedExport};
+#[cfg(windows)]
 use crate::process::get_module;
+use crate::session::Session;
 use crate::ut::fmt::{print_lg, LevelPrint};
 
-pub fn handle_get_proc_addr(h_proc: HANDLE, args: &[&str]) {
+
+#[cfg(windows)]
+fn open_loaded(h_proc: HANDLE, dll: &str) -> Result<(u64, Rc<ExportTable>), anyhow::Error> {
+    let module = get_module(h_proc)?.into_iter()
+        .find(|m| export::dll_stem(&unsafe { CStr::from_ptr(m.szModule.as_ptr()) }.to_string_lossy()) == export::dll_stem(dll))
+        .ok_or_else(|| anyhow!("{dll} is not loaded in the process"))?;
+    let base = module.modBaseAddr as u64;
+    Ok((base, Rc::new(export::read_module_exports(&WinTarget::new(h_proc), base)?)))
+}
+
+
+#[cfg(not(windows))]
+fn open_loaded(_h_proc: HANDLE, dll: &str) -> Result<(u64, Rc<ExportTable>), anyhow::Error> {
+    Err(anyhow!("{dll} is not loaded in the process"))
+}
+
+
+fn open_file(session: &Session, dll: &str) -> Result<(u64, Rc<ExportTable>), anyhow::Error> {
+    let app_dir = session.all.file.as_deref().and_then(|f| Path::new(f).parent());
+    let bitness = session.nt_header.map(|nt| nt.get_bitness()).unwrap_or(64);
+    let path = export::find_dll(dll, app_dir, bitness).ok_or_else(|| {
+        let stem = export::dll_stem(dll);
+        if stem.starts_with("api-ms-") || stem.starts_with("ext-ms-") {
+            anyhow!("{dll} is an api set, it is only resolved by the loader once the process is started")
+        } else {
+            anyhow!("cannot find {dll}")
+        }
+    })?;
+    let (nt_header, sections) = pefile::load_pe(&path.to_string_lossy()).map_err(|e| anyhow!("failed to load {} : {e}", path.display()))?;
+    let table = export::parse_export_table(&sections, nt_header.data_directory(0))
+        .map_err(|e| anyhow!("invalid export directory in {} : {e}", path.display()))?;
+    Ok((nt_header.image_base(), Rc::new(table)))
+}
+
+
+fn print_resolved(func: &str, resolved: &ResolvedExport, started: bool) {
+    for forwarder in &resolved.chain {
+        print_lg(LevelPrint::DebugO, format!("{func} is forwarded to {forwarder}"));
+    }
+    let module = export::dll_stem(&resolved.dll);
+    if started {
+        print_lg(LevelPrint::DebugO, format!("address of function : {:#x} ({module}!#{})", resolved.base + resolved.export.rva as u64, resolved.export.ordinal));
+    } else {
+        print_lg(LevelPrint::DebugO, format!(
+            "rva of function : {:#x} in {module} (ordinal {}), {:#x} at the preferred base {:#x}",
+            resolved.export.rva, resolved.export.ordinal, resolved.base + resolved.export.rva as u64, resolved.base,
+        ));
+    }
+}
+
+
+pub fn handle_get_proc_addr(session: &Session, h_proc: HANDLE, args: &[&str]) {
     if args.len() < 3 {
         eprintln!("{}", USAGE_PROC_ADDR);
         return;
     }
-    unsafe {
-        let new_dll = format!("{}\0", args[1].replace("\"", ""));
-        let mut hdll = LoadLibraryA(new_dll.as_ptr() as *const i8);
-        if hdll.is_null() {
-            let last_err = io::Error::last_os_error();
-            if (last_err.raw_os_error() == Some(193) || last_err.raw_os_error() == Some(126)) && !h_proc.is_null() {
-                let mut n_dll = new_dll;
-                n_dll.pop();
-                match get_module(h_proc) {
-                    Ok(modules) => {
-                        for module in modules {
-                            if CStr::from_ptr(module.szModule.as_ptr()).to_string_lossy() == n_dll {
-                                hdll = module.hModule;
-                                break;
-                            }
-                        }
-                        if hdll.is_null() {
-                            print_lg(LevelPrint::ErrorO, format!("unknow dll : {}", n_dll));
-                        }
-                    },
-                    Err(e) => {
-                        print_lg(LevelPrint::ErrorO, e);
-                        return;
-                    }
-                }
-            }
-            print_lg(LevelPrint::ErrorO, format!("failed to get module handle : {}", io::Error::last_os_error()));
-            return;
-        }
-        let new_func = format!("{}\0", args[2]);
-        let addr_func = GetProcAddress(hdll, new_func.as_ptr() as *const i8);
-        if addr_func.is_null() {
-            print_lg(LevelPrint::ErrorO, format!("failed to get addr of func: {}", io::Error::last_os_error()));
-            return;
-        }
-        print_lg(LevelPrint::DebugO, format!("address of function : {:#x}", addr_func as u64));
-        FreeLibrary(hdll);
+    let dll = args[1].replace("\"", "");
+    let func = args[2];
+    let started = !h_proc.is_null();
+    let resolved = if started {
+        export::resolve_export(|dll| open_loaded(h_proc, dll), &dll, func)
+    } else {
+        export::resolve_export(|dll| open_file(session, dll), &dll, func)
+    };
+    match resolved {
+        Ok(resolved) => print_resolved(func, &resolved, started),
+        Err(e) => print_lg(LevelPrint::ErrorO, format!("failed to get addr of func: {e}")),
     }
-}
\ No newline at end of file
+}
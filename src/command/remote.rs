@@ -0,0 +1,16 @@
+use crate::session::Session;
+use crate::{dbg, usage};
+
+pub fn handle_target(session: &mut Session, linev: &[&str]) {
+    match (linev.get(1), linev.get(2)) {
+        (Some(&"remote"), Some(addr)) => dbg::remote::start_remote(session, addr),
+        _ => println!("{}", usage::USAGE_TARGET),
+    }
+}
+
+pub fn handle_gdbserver(session: &mut Session, linev: &[&str]) {
+    match linev.get(1) {
+        Some(addr) => dbg::gdbserver::run(session, addr),
+        None => println!("{}", usage::USAGE_GDBSERVER),
+    }
+}
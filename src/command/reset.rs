@@ -1,26 +1,22 @@
-use std::ptr;
-use winapi::um::winnt::HANDLE;
+use crate::dbg::target::DebugTarget;
 use crate::cli::All;
-use crate::pefile::function;
-use crate::symbol::{SymbolType, Symbols, SYMBOLS_V};
-use crate::{pefile, symbol, usage, ALL_ELM};
+use crate::session::Session;
+use crate::symbol::{SymbolType, Symbols};
+use crate::usage;
 use crate::command::breakpoint::Brkpts;
-use crate::dbg::{memory, BASE_ADDR};
+use crate::dbg::memory;
 use crate::ut::fmt::{print_lg, LevelPrint};
 
-fn clear_symbols() {
-    unsafe {
-        *SYMBOLS_V = Symbols::default();
-        symbol::IMAGE_BASE = 0;
-        pefile::NT_HEADER = None;
-    }
+fn clear_symbols(session: &mut Session) {
+    session.symbols = Symbols::default();
+    session.image_base = 0;
+    session.nt_header = None;
+    session.elf_header = None;
 }
 
-fn restore_breakpoints(h_proc: HANDLE, breakpoints: &Vec<Brkpts>, offset: u64) {
+fn restore_breakpoints(target: &dyn DebugTarget, breakpoints: &Vec<Brkpts>, offset: u64) {
     for b in breakpoints {
-        unsafe {
-            memory::breakpoint::restore_byte_of_brkpt(h_proc, b.addr + offset, b.origin_b);
-        }
+        memory::breakpoint::restore_byte_of_brkpt(target, b.addr + offset, b.origin_b);
     }
 }
 
@@ -28,70 +24,67 @@ fn print_reset_message(item: &str) {
     print_lg(LevelPrint::DebugO, format!("all {item} have been cleared"));
 }
 
-pub fn handle_reset(linev: &[&str]) {
+pub fn handle_reset(session: &mut Session, linev: &[&str]) {
     if linev.len() != 2 {
         eprintln!("{}", usage::USAGE_RESET);
         return;
     }
     let binding = linev[1].to_lowercase();
     let opt = binding.trim();
-    unsafe {
-        let p_allm = ptr::addr_of_mut!(ALL_ELM);
-        match opt {
-            "file" => {
-                clear_symbols();
-                (*p_allm).file = None;
-                print_reset_message("file context");
-            }
-            "breakpoint" | "b" => {
-                (*p_allm).break_rva.clear();
-                print_reset_message("rva breakpoints");
-            }
-            "symbol" | "s" => {
-                clear_symbols();
-                print_reset_message("symbols");
-            }
-            "hook" | "ho" => {
-                (*p_allm).hook.clear();
-                print_reset_message("hooks");
-            }
-            "break-va" | "b-va" => {
-                (*p_allm).break_va.clear();
-                print_reset_message("va breakpoints");
-            }
-            "break-ret" | "b-ret" => {
-                (*p_allm).break_ret.clear();
-                print_reset_message("function returns");
-            }
-            "skip" => {
-                (*p_allm).skip_addr.clear();
-                print_reset_message("skipped functions");
-            }
-            "args" | "arg" | "argv" => {
-                (*p_allm).arg = None;
-                print_reset_message("arguments");
-            }
-            "watchpoint" | "watchpts" | "w" => {
-                (*p_allm).watchpts.clear();
-                print_reset_message("watchpoints");
-            }
-            "all" => {
-                clear_symbols();
-                (*p_allm).skip_addr.clear();
-                (*p_allm).break_ret.clear();
-                (*ptr::addr_of_mut!(function::FUNC_INFO)).clear();
-                (*p_allm).hook.clear();
-                *(*p_allm) = All::default();
-                SYMBOLS_V.symbol_type = SymbolType::Un;
-                (*&raw mut SYMBOLS_V).symbol_file.clear();
-                print_reset_message("elements");
-            }
-            _ => eprintln!("{}", usage::USAGE_RESET),
+    match opt {
+        "file" => {
+            clear_symbols(session);
+            session.all.file = None;
+            print_reset_message("file context");
+        }
+        "breakpoint" | "b" => {
+            session.all.break_rva.clear();
+            print_reset_message("rva breakpoints");
+        }
+        "symbol" | "s" => {
+            clear_symbols(session);
+            print_reset_message("symbols");
+        }
+        "hook" | "ho" => {
+            session.all.hook.clear();
+            print_reset_message("hooks");
+        }
+        "break-va" | "b-va" => {
+            session.all.break_va.clear();
+            print_reset_message("va breakpoints");
+        }
+        "break-ret" | "b-ret" => {
+            session.all.break_ret.clear();
+            print_reset_message("function returns");
+        }
+        "skip" => {
+            session.all.skip_addr.clear();
+            print_reset_message("skipped functions");
+        }
+        "args" | "arg" | "argv" => {
+            session.all.arg = None;
+            print_reset_message("arguments");
+        }
+        "watchpoint" | "watchpts" | "w" => {
+            session.all.watchpts.clear();
+            print_reset_message("watchpoints");
         }
+        "all" => {
+            clear_symbols(session);
+            session.all.skip_addr.clear();
+            session.all.break_ret.clear();
+            session.func_info.clear();
+            session.all.hook.clear();
+            session.all = All::default();
+            session.symbols.symbol_type = SymbolType::Un;
+            session.symbols.symbol_file.clear();
+            print_reset_message("elements");
+        }
+        _ => eprintln!("{}", usage::USAGE_RESET),
     }
 }
 
-pub fn reset_proc(linev: &[&str], h_proc: HANDLE) {
+pub fn reset_proc(session: &mut Session, linev: &[&str], target: &dyn DebugTarget) {
     if linev.len() != 2 {
         eprintln!("{}", usage::USAGE_RESET);
         return;
@@ -99,71 +92,63 @@ pub fn reset_proc(linev: &[&str], h_proc: HANDLE) {
     let binding = linev[1].to_lowercase();
     let opt = binding.trim();
 
-    unsafe {
-        let p_allm = ptr::addr_of_mut!(ALL_ELM);
-        match opt {
-            "file" => {
-                clear_symbols();
-                (*p_allm).file = None;
-                print_reset_message("file context");
-            }
-            "breakpoint" | "b" | "break" => {
-                restore_breakpoints(h_proc, &(*p_allm).break_rva, BASE_ADDR);
-                (*p_allm).break_rva.clear();
-                print_reset_message("breakpoints");
-            }
-            "break-va" | "b-va" => {
-                restore_breakpoints(h_proc, &(*p_allm).break_va, 0);
-                (*p_allm).break_va.clear();
-                print_reset_message("va breakpoints");
-            }
-            "symbol" | "s" => {
-                clear_symbols();
-                print_reset_message("symbols");
-            }
-            "hook" | "ho" => {
-                restore_breakpoints(h_proc, &(*p_allm).hook.iter().map(|h| {
-                    let mut b = Brkpts::default();
-                    b.addr = h.target;
-                    b.origin_b = h.origin_byte;
-                    b
-                }).collect::<Vec<Brkpts>>(), BASE_ADDR);
-                (*p_allm).hook.clear();
-                print_reset_message("hooks");
-            }
-            "break-ret" | "b-ret" => {
-                (*p_allm).break_ret.clear();
-                print_reset_message("function returns");
-            }
-            "skip" => {
-                restore_breakpoints(h_proc, &(*p_allm).skip_addr, BASE_ADDR);
-                (*p_allm).skip_addr.clear();
-                print_reset_message("skipped functions");
-            }
-            "args" | "arg" | "argv" => {
-                (*p_allm).arg = None;
-                print_reset_message("arguments");
-            }
-            "watchpoint" | "watchpts" | "w" => {
-                (*p_allm).watchpts.clear();
-                print_reset_message("watchpoints");
-            }
-            "all" => {
-                clear_symbols();
-                restore_breakpoints(h_proc, &(*p_allm).skip_addr, BASE_ADDR);
-                restore_breakpoints(h_proc, &(*p_allm).break_ret, BASE_ADDR);
-                restore_breakpoints(h_proc, &(*p_allm).hook.iter().map(|h| {
-                    let mut b = Brkpts::default();
-                    b.addr = h.target;
-                    b.origin_b = h.origin_byte;
-                    b
-                }).collect::<Vec<Brkpts>>(), BASE_ADDR);
-                restore_breakpoints(h_proc, &(*p_allm).break_va, 0);
-                restore_breakpoints(h_proc, &(*p_allm).break_rva, BASE_ADDR);
-                *ALL_ELM = All::default();
-                print_reset_message("elements");
-            }
-            _ => eprintln!("{}", usage::USAGE_RESET),
+    let base_addr = session.base_addr;
+    match opt {
+        "file" => {
+            clear_symbols(session);
+            session.all.file = None;
+            print_reset_message("file context");
+        }
+        "breakpoint" | "b" | "break" => {
+            restore_breakpoints(target, &session.all.break_rva, base_addr);
+            session.all.break_rva.clear();
+            print_reset_message("breakpoints");
+        }
+        "break-va" | "b-va" => {
+            restore_breakpoints(target, &session.all.break_va, 0);
+            session.all.break_va.clear();
+            print_reset_message("va breakpoints");
+        }
+        "symbol" | "s" => {
+            clear_symbols(session);
+            print_reset_message("symbols");
+        }
+        "hook" | "ho" => {
+            restore_breakpoints(target, &session.all.hook.iter().map(|h| {
+                Brkpts { addr: h.target, origin_b: h.origin_byte, ..Default::default() }
+            }).collect::<Vec<Brkpts>>(), base_addr);
+            session.all.hook.clear();
+            print_reset_message("hooks");
+        }
+        "break-ret" | "b-ret" => {
+            session.all.break_ret.clear();
+            print_reset_message("function returns");
+        }
+        "skip" => {
+            restore_breakpoints(target, &session.all.skip_addr, base_addr);
+            session.all.skip_addr.clear();
+            print_reset_message("skipped functions");
+        }
+        "args" | "arg" | "argv" => {
+            session.all.arg = None;
+            print_reset_message("arguments");
+        }
+        "watchpoint" | "watchpts" | "w" => {
+            session.all.watchpts.clear();
+            print_reset_message("watchpoints");
+        }
+        "all" => {
+            clear_symbols(session);
+            restore_breakpoints(target, &session.all.skip_addr, base_addr);
+            restore_breakpoints(target, &session.all.break_ret, base_addr);
+            restore_breakpoints(target, &session.all.hook.iter().map(|h| {
+                Brkpts { addr: h.target, origin_b: h.origin_byte, ..Default::default() }
+            }).collect::<Vec<Brkpts>>(), base_addr);
+            restore_breakpoints(target, &session.all.break_va, 0);
+            restore_breakpoints(target, &session.all.break_rva, base_addr);
+            session.all = All::default();
+            print_reset_message("elements");
         }
+        _ => eprintln!("{}", usage::USAGE_RESET),
     }
 }
@@ -0,0 +1,108 @@
+use crate::pefile::resource::{self, Resource, RT_MANIFEST, RT_STRING, RT_VERSION};
+use crate::pefile::resource::ResId;
+use crate::session::Session;
+use crate::usage;
+use crate::ut::fmt::*;
+
+
+// <type>[/<name>[/<lang>]] or #<index> as shown by `info resources`
+fn select<'a>(resources: &'a [Resource], selector: &str) -> Vec<(usize, &'a Resource)> {
+    if let Some(index) = selector.strip_prefix('#') {
+        return index.parse::<usize>().ok()
+            .and_then(|i| resources.get(i).map(|r| (i, r)))
+            .into_iter()
+            .collect();
+    }
+    let mut parts = selector.split('/');
+    let kind = parts.next().unwrap_or_default();
+    let name = parts.next();
+    let lang = parts.next();
+    resources.iter().enumerate()
+        .filter(|(_, r)| r.kind.matches(kind))
+        .filter(|(_, r)| name.is_none_or(|n| r.name.matches(n)))
+        .filter(|(_, r)| lang.is_none_or(|l| ResId::Id(r.lang).matches(l)))
+        .collect()
+}
+
+
+fn print_decoded(session: &Session, res: &Resource) {
+    let Some(data) = res.data(&session.sections) else {
+        print_lg(LevelPrint::WarningO, format!("the data of {}/{} is outside of the sections", res.kind_str(), res.name));
+        return;
+    };
+    if res.is(RT_VERSION) {
+        match resource::decode_version_info(data) {
+            Ok(pairs) => for (key, value) in pairs {
+                println!("     {GREEN_COL}{:<40}{RESET_COLOR}{VALUE_COLOR}{value}{RESET_COLOR}", key);
+            },
+            Err(e) => print_lg(LevelPrint::WarningO, format!("failed to decode the version info : {e}")),
+        }
+    } else if res.is(RT_MANIFEST) {
+        println!("{}", String::from_utf8_lossy(data).trim_start_matches('\u{feff}').trim_end());
+    } else if res.is(RT_STRING) {
+        let ResId::Id(block) = res.name else {
+            return;
+        };
+        for (id, s) in resource::decode_string_table(block, data) {
+            println!("     {CYAN_COLOR}{:<8}{RESET_COLOR}{VALUE_COLOR}{:?}{RESET_COLOR}", id, s);
+        }
+    }
+}
+
+
+pub fn print_resources(session: &Session, linev: &[&str]) {
+    if session.resources.is_empty() {
+        print_lg(LevelPrint::WarningO, "the file context has no resources");
+        return;
+    }
+    let selected = match linev.get(1) {
+        Some(selector) => select(&session.resources, selector),
+        None => session.resources.iter().enumerate().collect(),
+    };
+    if selected.is_empty() {
+        print_lg(LevelPrint::ErrorO, format!("no resource matches '{}'", linev[1]));
+        return;
+    }
+    println!("{VALID_COLOR}#     Type            Name                    Lang    Address             Size      Code page{RESET_COLOR}");
+    for (i, res) in &selected {
+        println!("{CYAN_COLOR}{:<6}{RESET_COLOR}{GREEN_COL}{:<16}{:<24}{RESET_COLOR}{VALUE_COLOR}{:<#8x}{RESET_COLOR}{ADDR_COLOR}{:<#20x}{RESET_COLOR}{VALUE_COLOR}{:<#10x}{}{RESET_COLOR}",
+            format!("#{i}"), res.kind_str(), res.name.to_string(), res.lang, res.rva as u64 + session.base_addr, res.size, res.code_page);
+    }
+    if linev.len() > 1 {
+        for (i, res) in selected {
+            if res.is(RT_VERSION) || res.is(RT_MANIFEST) || res.is(RT_STRING) {
+                println!("\n{VALID_COLOR}#{i} {}/{}:{RESET_COLOR}", res.kind_str(), res.name);
+                print_decoded(session, res);
+            }
+        }
+    }
+}
+
+
+pub fn handle_dump_resource(session: &Session, linev: &[&str]) {
+    if linev.len() < 3 {
+        println!("{}", usage::USAGE_DUMP_RESOURCE);
+        return;
+    }
+    let selected = select(&session.resources, linev[1]);
+    let res = match selected.as_slice() {
+        [(_, res)] => res,
+        [] => {
+            print_lg(LevelPrint::ErrorO, format!("no resource matches '{}'", linev[1]));
+            return;
+        }
+        _ => {
+            print_lg(LevelPrint::ErrorO, format!("{} resources match '{}', specify the name and the language or use #<index>", selected.len(), linev[1]));
+            return;
+        }
+    };
+    let Some(data) = res.data(&session.sections) else {
+        print_lg(LevelPrint::ErrorO, format!("the data of {}/{} is outside of the sections", res.kind_str(), res.name));
+        return;
+    };
+    let path = linev[2..].join(" ").replace("\"", "");
+    match std::fs::write(&path, data) {
+        Ok(()) => print_lg(LevelPrint::DebugO, format!("{:#x} bytes of {}/{}/{:#x} written to '{path}'", data.len(), res.kind_str(), res.name, res.lang)),
+        Err(e) => print_lg(LevelPrint::ErrorO, format!("failed to write '{path}' : {e}")),
+    }
+}
@@ -1,12 +1,13 @@
+use crate::session::Session;
 use crate::usage;
 use crate::ut::fmt::{print_lg, LevelPrint};
 
-pub fn skip(linev: &[&str]) {
+pub fn skip(session: &Session, linev: &[&str]) {
     if linev.len() < 2 {
         eprintln!("{}", usage::USAGE_SKIP);
         return;
     }
-    let addr_func = match crate::ste::get_address(linev) {
+    let addr_func = match crate::ste::get_address(session, linev) {
         Ok(addr) => addr,
         Err(e) => {
             print_lg(LevelPrint::ErrorO, e);
@@ -14,7 +15,7 @@ pub fn skip(linev: &[&str]) {
         }
     };
 
-    match crate::ste::find_func_by_addr(addr_func) {
+    match crate::ste::find_func_by_addr(session, addr_func) {
         Some(_) => {
             print_lg(LevelPrint::DebugO, format!("the function {:#x} will now not be executed", addr_func));
         }
@@ -0,0 +1,89 @@
+use anyhow::anyhow;
+use crate::command::breakpoint::Brkpts;
+use crate::dbg;
+use crate::pefile::entry;
+use crate::session::Session;
+use crate::ut::fmt::{print_lg, LevelPrint};
+
+
+fn entry_rva(session: &Session) -> Result<u64, anyhow::Error> {
+    match (session.nt_header, session.elf_header) {
+        (Some(nt_header), _) => Ok(nt_header.entry_point() as u64),
+        (None, Some(elf_header)) => elf_header.entry.checked_sub(elf_header.image_base)
+            .ok_or_else(|| anyhow!("the entry point {:#x} is below the image base {:#x}", elf_header.entry, elf_header.image_base)),
+        (None, None) => Err(anyhow!("you must first specify a file")),
+    }
+}
+
+
+fn main_rva(session: &Session) -> Option<u64> {
+    if let Some(sym) = session.symbols.symbol_file.iter().find(|s| !s.src_file.is_dll() && s.offset > 0 && entry::is_main_name(&s.name)) {
+        print_lg(LevelPrint::DebugO, format!("'{}' found in the symbols", sym.name));
+        return Some(sym.offset as u64);
+    }
+    let entry = entry_rva(session).map_err(|e| print_lg(LevelPrint::ErrorO, e)).ok()?;
+    let main = entry::find_main_crt(&session.sections, &session.imports, session.get_bitness(), session.image_base, entry)?;
+    print_lg(LevelPrint::DebugO, "main found from the crt startup code");
+    Some(main)
+}
+
+
+fn break_once(session: &mut Session, rva: u64, name: &str) {
+    if session.all.break_contain(rva, session.base_addr) {
+        print_lg(LevelPrint::WarningO, format!("there is already a breakpoint on the {name} ({:#x})", rva));
+        return;
+    }
+    session.all.break_once.push(Brkpts::from_addr_no_start(rva));
+    print_lg(LevelPrint::DebugO, format!("one-shot breakpoint set on the {name} ({:#x})", rva));
+}
+
+
+pub fn break_entry(session: &mut Session) {
+    match entry_rva(session) {
+        Ok(rva) => break_once(session, rva, "entry point"),
+        Err(e) => print_lg(LevelPrint::ErrorO, e),
+    }
+}
+
+
+pub fn break_main(session: &mut Session) {
+    if session.all.file.is_none() {
+        print_lg(LevelPrint::ErrorO, "you must first specify a file");
+        return;
+    }
+    match main_rva(session) {
+        Some(rva) => break_once(session, rva, "main function"),
+        None => print_lg(LevelPrint::ErrorO, "failed to find main in the symbols and in the crt startup code"),
+    }
+}
+
+
+pub fn handle_start(session: &mut Session, linev: &[&str]) {
+    match linev.get(1) {
+        Some(&"main") => break_main(session),
+        Some(&"entry") | None => break_entry(session),
+        Some(other) => {
+            print_lg(LevelPrint::ErrorO, format!("unknow start point '{other}', expected 'entry' or 'main'"));
+            return;
+        }
+    }
+    dbg::run(session);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elffile::ElfHeader;
+
+    #[test]
+    fn entry_below_image_base() {
+        let mut session = Session::default();
+        assert!(entry_rva(&session).is_err());
+        let header = ElfHeader { bitness: 64, elf_type: 2, machine: 0x3e, entry: 0x401000, image_base: 0x400000 };
+        session.elf_header = Some(header);
+        assert_eq!(entry_rva(&session).unwrap(), 0x1000);
+        session.elf_header = Some(ElfHeader { entry: 0x1000, ..header });
+        assert!(entry_rva(&session).is_err());
+    }
+}
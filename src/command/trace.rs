@@ -0,0 +1,52 @@
+use crate::command;
+use crate::dbg::trace::{self, Trace};
+use crate::session::Session;
+use crate::usage;
+use crate::ut::fmt::{print_lg, LevelPrint};
+use std::path::Path;
+
+pub fn handle_record(session: &mut Session, linev: &[&str], line: &str) {
+    if linev.len() < 2 {
+        println!("{}", usage::USAGE_RECORD);
+        return;
+    }
+    let path = line.trim()[linev[0].len()..].replace("\"", "");
+    let path = path.trim();
+    if path == "off" {
+        session.all.record = None;
+        print_lg(LevelPrint::DebugO, "the next run will not be recorded");
+        return;
+    }
+    let dir = Path::new(path).parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    if !dir.is_dir() || Path::new(path).is_dir() {
+        print_lg(LevelPrint::ErrorO, format!("cannot record in '{path}' : the directory does not exist or the path is a directory"));
+        return;
+    }
+    session.all.record = Some(path.to_string());
+    print_lg(LevelPrint::DebugO, format!("the next run will be recorded in '{path}'"));
+}
+
+
+pub fn handle_replay(session: &mut Session, linev: &[&str], line: &str) {
+    if linev.len() < 2 {
+        println!("{}", usage::USAGE_REPLAY);
+        return;
+    }
+    let path = line.trim()[linev[0].len()..].replace("\"", "");
+    match Trace::load(path.trim()) {
+        Ok(trace) => {
+            if session.all.file.is_none() {
+                match &trace.file {
+                    Some(file) if Path::new(file).exists() => {
+                        let line = format!("file {file}");
+                        command::file::handle_change_file(session, &line.split_whitespace().collect::<Vec<&str>>(), &line);
+                    }
+                    Some(file) => print_lg(LevelPrint::WarningO, format!("'{file}' was not found, load it with \"file <path>\" to get its symbols")),
+                    None => {}
+                }
+            }
+            trace::replay(session, trace);
+        }
+        Err(e) => print_lg(LevelPrint::ErrorO, format!("failed to load the trace : {e}")),
+    }
+}
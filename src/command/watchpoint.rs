@@ -1,12 +1,14 @@
-use crate::dbg::dbg_cmd::x32::info_reg::ToValue32;
-use crate::dbg::dbg_cmd::x64::info_reg::{ToValue, Value};
-use crate::dbg::{memory, RealAddr, BASE_ADDR};
-use crate::{symbol, usage, ALL_ELM};
+use crate::dbg::regs::{RegisterSet, Value};
+use crate::dbg::{memory, RealAddr};
+use crate::session::Session;
+use crate::symbol::Symbols;
+use crate::usage;
 use std::fmt;
 use std::fmt::Formatter;
 use std::str::FromStr;
-use winapi::um::winnt::{CONTEXT, WOW64_CONTEXT};
-use crate::pefile::{NtHeaders, NT_HEADER};
+use crate::dbg::CONTEXT;
+#[cfg(windows)]
+use crate::dbg::WOW64_CONTEXT;
 use crate::ut::cast::str_to;
 use crate::ut::fmt::*;
 
@@ -55,82 +57,67 @@ pub struct Watchpts {
     pub register: String,
 }
 
-impl RealAddr for Watchpts {
-    fn real_addr64(&self, ctx: CONTEXT) -> u64 {
-        unsafe {
-            if self.register != "" {
-                let value = ctx.str_to_value_ctx(&self.register);
-                print_lg(LevelPrint::Error, "cannot take the value of a register 128 as a basis");
-                match value {
-                    Value::U64(value_reg) => return (value_reg as i64 + self.offset) as u64,
-                    Value::U128(_) => print_lg(LevelPrint::Error, "cannot take the value of a register 128 as a basis"),
-                    _ => print_lg(LevelPrint::Error, format!("unknow register : {}", self.register)),
+impl Watchpts {
+    pub fn real_addr_regs(&self, session: &Session, regs: &RegisterSet) -> u64 {
+        if !self.register.is_empty() {
+            return match regs.value(&self.register) {
+                Value::U128(_) => {
+                    print_lg(LevelPrint::Error, "cannot take the value of a register 128 as a basis");
+                    0
                 }
-                return 0;
-            }
-            match self.flag_type_mem {
-                FlagTypeMem::Stack => {
-                    if let Some(frame) = memory::stack::get_frame_before_func(ctx.Rip) {
-                        ((frame.AddrStack.Offset as u64) as i64 + self.offset) as u64
-                    } else {
-                        0
-                    }
+                Value::Un => {
+                    print_lg(LevelPrint::Error, format!("unknow register : {}", self.register));
+                    0
                 }
-                FlagTypeMem::MemoryStatic => self.offset as u64 + BASE_ADDR,
-                FlagTypeMem::VirtualAddr => self.offset as u64,
-                _ => 0,
-            }
+                value => (value.as_u64().unwrap_or_default() as i64 + self.offset) as u64,
+            };
         }
-    }
-
-    fn real_addr32(&self, ctx: WOW64_CONTEXT) -> u32 {
-        unsafe {
-            if self.register != "" {
-                return ctx.str_to_ctx(&self.register);
-            }
-            
-            match self.flag_type_mem {
-                FlagTypeMem::Stack => {
-                    if let Some(frame) = memory::stack::get_frame_before_func(ctx.Eip as u64) {
-                        (frame.AddrStack.Offset as i64 + self.offset) as u32
-                    } else {
-                        0
-                    }
+        match self.flag_type_mem {
+            FlagTypeMem::Stack => {
+                if let Some(frame) = memory::stack::get_frame_before_func(session, regs.ip()) {
+                    (frame.stack as i64 + self.offset) as u64
+                } else {
+                    0
                 }
-                FlagTypeMem::MemoryStatic => self.offset as u32 + BASE_ADDR as u32,
-                FlagTypeMem::VirtualAddr => self.offset as u32,
-                _ => 0,
             }
+            FlagTypeMem::MemoryStatic => self.offset as u64 + session.base_addr,
+            FlagTypeMem::VirtualAddr => self.offset as u64,
+            _ => 0,
         }
     }
+}
 
-    fn real_addr(&self, ctx: *const CONTEXT) -> u64 {
-        unsafe {
-            match NT_HEADER {
-                Some(NtHeaders::Headers32(_)) => self.real_addr32(*(ctx as *const WOW64_CONTEXT)) as u64,
-                Some(NtHeaders::Headers64(_)) => self.real_addr64(*ctx),
-                None => 0,
-            }
+impl RealAddr for Watchpts {
+    #[cfg(windows)]
+    fn real_addr64(&self, session: &Session, ctx: CONTEXT) -> u64 {
+        self.real_addr_regs(session, &RegisterSet::from(&ctx))
+    }
+
+    #[cfg(windows)]
+    fn real_addr32(&self, session: &Session, ctx: WOW64_CONTEXT) -> u32 {
+        self.real_addr_regs(session, &RegisterSet::from(&ctx)) as u32
+    }
+
+    fn real_addr(&self, session: &Session, ctx: *const CONTEXT) -> u64 {
+        if session.nt_header.is_none() {
+            return 0;
         }
+        self.real_addr_regs(session, &unsafe { RegisterSet::from_ctx_ptr(session.get_bitness() == 64, ctx) })
     }
 }
 
 impl Watchpts {
     pub fn acces_type_to_bits(&self) -> u32 {
-        let mut result = 0;
-        for ac_type in &self.check_type {
-            match ac_type {
-                CheckType::R => result |= 1 << 0,
-                CheckType::W => result |= 1 << 1,
-                CheckType::X => result |= 1 << 2,
-            }
+        match (self.check_type.contains(&CheckType::R), self.check_type.contains(&CheckType::W)) {
+            (true, _) => 0b11,
+            (false, true) => 0b01,
+            (false, false) => 0b00,
         }
-        result
     }
 
-    pub fn format_offset(&self, ctx: *const CONTEXT) -> String {
-        if unsafe { BASE_ADDR != 0 } || self.flag_type_mem == FlagTypeMem::VirtualAddr {
-            format!("{:#x}", self.real_addr(ctx))
+    pub fn format_offset(&self, session: &Session, ctx: *const CONTEXT) -> String {
+        if session.base_addr != 0 || self.flag_type_mem == FlagTypeMem::VirtualAddr {
+            format!("{:#x}", self.real_addr(session, ctx))
         } else if self.flag_type_mem == FlagTypeMem::Stack {
             format!(".fp{:+}", self.offset)
         } else {
@@ -139,10 +126,8 @@ impl Watchpts {
     }
 }
 
-impl FromStr for Watchpts {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+impl Watchpts {
+    pub fn from_str_sym(symbols: &Symbols, s: &str) -> Result<Self, String> {
         let parts: Vec<&str> = s.split_whitespace().collect();
         let mut offset: Option<i64> = None;
         let mut flag_type_mem = FlagTypeMem::NotDef;
@@ -192,9 +177,7 @@ impl FromStr for Watchpts {
                 match str_to::<i64>(part) {
                     Ok(parsed_offset) => offset = Some(parsed_offset),
                     Err(_) => {
-                        if let Some(sym) = unsafe {
-                            (*&raw const symbol::SYMBOLS_V).symbol_file.iter().find(|s| s.name == part.trim_start().trim_end())
-                        } {
+                        if let Some(sym) = symbols.symbol_file.iter().find(|s| s.name == part.trim_start().trim_end()) {
                             if flag_type_mem == FlagTypeMem::NotDef && sym.offset < 0 {
                                 flag_type_mem = FlagTypeMem::Stack;
                             }
@@ -220,41 +203,51 @@ impl FromStr for Watchpts {
     }
 }
 
-pub fn watchpoint(linev: &[&str]) {
+impl FromStr for Watchpts {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Watchpts::from_str_sym(&Symbols::default(), s)
+    }
+}
+
+pub fn watchpoint(session: &mut Session, linev: &[&str]) {
     if linev.len() == 1 {
         println!("{}", usage::USAGE_WATCHPTS);
         return;
     }
-    unsafe {
-        match Watchpts::from_str(&linev[1..].join(" ")) {
-            Ok(wt) => {
-                if (*&raw const ALL_ELM).watchpts.len() < 4 {
-                    (*&raw mut ALL_ELM).watchpts.push(wt.clone());
-                    print_lg(LevelPrint::DebugO, format!("watchpoint was set successfully at offset {}: {}", wt.format_offset(std::mem::zeroed()), (*&raw const ALL_ELM).watchpts.len()));
-                } else {
-                    print_lg(LevelPrint::ErrorO, "you can only place 4 watchpoints");
-                }
+    match Watchpts::from_str_sym(&session.symbols, &linev[1..].join(" ")) {
+        Ok(wt) => {
+            if session.all.watchpts.len() < 4 {
+                session.all.watchpts.push(wt.clone());
+                let offset = wt.format_offset(session, unsafe { std::mem::zeroed() });
+                print_lg(LevelPrint::DebugO, format!("watchpoint was set successfully at offset {}: {}", offset, session.all.watchpts.len()));
+            } else {
+                print_lg(LevelPrint::ErrorO, "you can only place 4 watchpoints");
             }
-            Err(e) => print_lg(LevelPrint::ErrorO, e),
         }
+        Err(e) => print_lg(LevelPrint::ErrorO, e),
     }
 }
 
-pub fn watchpoint_proc(linev: &[&str], ctx: &mut CONTEXT) {
+#[cfg(windows)]
+pub fn watchpoint_proc(session: &mut Session, linev: &[&str], ctx: *mut CONTEXT) {
     if linev.len() == 1 {
         println!("{}", usage::USAGE_WATCHPTS);
         return;
     }
-    match Watchpts::from_str(&linev[1..].join(" ")) {
-        Ok(wt) => unsafe {
-            if (*&raw mut ALL_ELM).watchpts.len() < 4 {
-                (*&raw mut ALL_ELM).watchpts.push(wt.clone());
-                memory::watchpoint::set_dreg(ctx, &wt, (*&raw const ALL_ELM).watchpts.len() - 1);
-                print_lg(LevelPrint::ErrorO, format!("watchpoint {} was set at successfully for watch address {:#x} with access {:?}", (*&raw const ALL_ELM).watchpts.len(), wt.real_addr64(*ctx), wt.check_type));
+    match Watchpts::from_str_sym(&session.symbols, &linev[1..].join(" ")) {
+        Ok(wt) => {
+            if session.all.watchpts.len() < 4 {
+                session.all.watchpts.push(wt.clone());
+                let mut regs = unsafe { RegisterSet::from_ctx_ptr(session.get_bitness() == 64, ctx) };
+                memory::watchpoint::set_dreg(session, &mut regs, &wt, session.all.watchpts.len() - 1);
+                unsafe { regs.write_ctx_ptr(ctx) };
+                print_lg(LevelPrint::ErrorO, format!("watchpoint {} was set at successfully for watch address {:#x} with access {:?}", session.all.watchpts.len(), wt.real_addr_regs(session, &regs), wt.check_type));
             } else {
                 print_lg(LevelPrint::ErrorO, "you can only place 4 watchpoints");
             }
-        },
+        }
         Err(e) => print_lg(LevelPrint::ErrorO, e),
     }
 }
@@ -1,5 +1,5 @@
 use crate::dbg::exec;
-use crate::ALL_ELM;
+use crate::session::Session;
 use std::ffi::CStr;
 use std::{io, ptr};
 use winapi::um::debugapi::DebugActiveProcess;
@@ -9,7 +9,7 @@ use winapi::um::winbase::QueryFullProcessImageNameA;
 use winapi::um::winnt::{LPSTR, PROCESS_ALL_ACCESS};
 use crate::ut::fmt::{print_lg, LevelPrint};
 
-pub unsafe fn attach_dbg(pid: u32) {
+pub unsafe fn attach_dbg(session: &mut Session, pid: u32) {
     let h_proc = OpenProcess(PROCESS_ALL_ACCESS, 0, pid);
     if h_proc.is_null() || h_proc == INVALID_HANDLE_VALUE {
         print_lg(LevelPrint::ErrorO, format!("Failed to open pid {pid} : {}", io::Error::last_os_error()));
@@ -27,9 +27,9 @@ pub unsafe fn attach_dbg(pid: u32) {
     let path_str = CStr::from_ptr(path_buf.as_ptr() as *const i8).to_string_lossy();
     print_lg(LevelPrint::DebugO, format!("Process path: {}", path_str));
 
-    ALL_ELM.file = Some(path_str.to_string());
+    session.all.file = Some(path_str.to_string());
 
-    if let Err(e) = crate::pefile::parse_header() {
+    if let Err(e) = crate::pefile::parse_header(session) {
         print_lg(LevelPrint::Error, format!("Error when parsing PE headers: {e}"));
         CloseHandle(h_proc);
         return;
@@ -41,6 +41,7 @@ pub unsafe fn attach_dbg(pid: u32) {
         return;
     }
 
-    exec::debug_loop(h_proc);
+    exec::debug_loop(session, h_proc);
+    session.end_process();
     CloseHandle(h_proc);
 }
\ No newline at end of file
@@ -1,15 +1,13 @@
 use crate::dbg::dbg_cmd::usages;
-use crate::dbg::{BASE_ADDR};
-use crate::pefile::function::FUNC_INFO;
-use crate::pefile::{get_section_of_rva, NtHeaders, NT_HEADER};
-use crate::symbol::{SymbolFile, SYMBOLS_V};
+use crate::pefile::NtHeaders;
+use crate::pefile::reloc;
+use crate::session::Session;
 use iced_x86::{Decoder, DecoderOptions, Formatter, Instruction, IntelFormatter, Mnemonic, SymbolResolver, SymbolResult};
-use std::{io, mem, ptr};
+use std::ptr;
 use anyhow::anyhow;
-use winapi::shared::minwindef::LPVOID;
-use winapi::shared::ntdef::HANDLE;
-use winapi::um::memoryapi::VirtualQueryEx;
-use winapi::um::winnt::{CONTEXT, MEMORY_BASIC_INFORMATION, MEM_COMMIT, RUNTIME_FUNCTION};
+use crate::dbg::CONTEXT;
+use crate::pefile::winnt::RUNTIME_FUNCTION;
+use crate::dbg::target::DebugTarget;
 use crate::dbg::memory::deref_mem;
 use crate::ut::{get_addr_br, get_addr_va};
 use crate::ut::cast::str_to;
@@ -24,34 +22,74 @@ pub struct JAddr {
 
 #[derive(Clone)]
 struct Sym {
-    pub sym_file: Vec<SymbolFile>,
+    pub sym_file: Vec<(u64, String)>,
     pub func: Vec<RUNTIME_FUNCTION>,
     pub j_jump: Vec<JAddr>,
-    pub ctx: *const CONTEXT,
+    pub iat: Vec<(u64, String)>,
+    pub base_addr: u64,
+    pub color: bool,
 }
 
 impl Sym {
-    pub fn from_va_sym(sym_file: &[SymbolFile], func: &[RUNTIME_FUNCTION], j_jump: Vec<JAddr>, ctx: *const CONTEXT) -> Sym {
-        Sym {sym_file: sym_file.to_vec(), func: func.to_vec(), ctx, j_jump}
+    pub fn from_session(session: &Session, j_jump: Vec<JAddr>, ctx: *const CONTEXT) -> Sym {
+        let mut sym_file: Vec<(u64, String)> = session.symbols.symbol_file.iter().map(|s| (s.addr_ot(session, ctx), s.name.clone())).collect();
+        sym_file.extend(session.imports.iter().filter_map(|i| Some((i.delay_thunk? as u64 + session.base_addr, i.thunk_name()))));
+        let iat_base = if session.base_addr == 0 && session.get_bitness() == 32 { session.image_base } else { session.base_addr };
+        let mut iat: Vec<(u64, String)> = session.imports.iter().map(|i| (i.iat_rva as u64 + iat_base, i.label())).collect();
+        if let Some(lc) = &session.load_config {
+            iat.extend(lc.guard_pointers(session.image_base).into_iter().map(|(rva, name)| (rva + iat_base, name.to_string())));
+        }
+        Sym {sym_file, func: session.func_info.clone(), j_jump, iat, base_addr: session.base_addr, color: true}
+    }
+
+    fn label(&self, addr: u64) -> Option<String> {
+        if let Some((_, name)) = self.sym_file.iter().find(|s|s.0 == addr) {
+            Some(name.clone())
+        } else if let Some(f) = self.func.iter().find(|s| s.BeginAddress as u64 + self.base_addr == addr) {
+            Some(format!("func_{:x}", f.BeginAddress))
+        } else if self.j_jump.iter().any(|j|j.addr == addr) {
+            Some(format!("label_{:x}", addr))
+        } else {
+            None
+        }
     }
 }
 
 
 impl SymbolResolver for Sym {
     fn symbol(&mut self, _insn: &Instruction, _op: u32, _insn_op: Option<u32>, rel_addr: u64, _addr_size: u32) -> Option<SymbolResult<'_>> {
-        if let Some(sym) = self.sym_file.iter().find(|s|s.addr_ot(self.ctx) == rel_addr) {
-            Some(SymbolResult::with_string(rel_addr, format!("{BYTES_COLOR}{}{RESET_COLOR}", sym.name)))
-        }else if let Some(f) = self.func.iter().find(|s| s.BeginAddress as u64 + unsafe {BASE_ADDR} == rel_addr) {
-            return Some(SymbolResult::with_string(rel_addr, format!("{BYTES_COLOR}func_{:x}{RESET_COLOR}", f.BeginAddress)))
-        }else if let Some(_) = self.j_jump.iter().find(|j|j.addr == rel_addr) {
-            return Some(SymbolResult::with_string(rel_addr, format!("{BYTES_COLOR}label_{:x}{RESET_COLOR}", rel_addr)))
+        let label = self.label(rel_addr).or_else(|| self.iat.iter().find(|i| i.0 == rel_addr).map(|i| i.1.clone()))?;
+        if self.color {
+            Some(SymbolResult::with_string(rel_addr, format!("{BYTES_COLOR}{label}{RESET_COLOR}")))
         } else {
-            None
+            Some(SymbolResult::with_string(rel_addr, label))
         }
     }
 }
 
 
+#[derive(Debug, Clone)]
+pub struct DisasmLine {
+    pub addr: u64,
+    pub bytes: Vec<u8>,
+    pub text: String,
+    pub label: Option<String>,
+}
+
+
+pub fn disasm_code(sym_file: Vec<(u64, String)>, iat: Vec<(u64, String)>, func: Vec<RUNTIME_FUNCTION>, base_addr: u64, bitness: u32, code: &[u8], ip: u64) -> Vec<DisasmLine> {
+    let (insnv, j_jump) = first_it(Decoder::with_ip(bitness, code, ip, DecoderOptions::NONE), usize::MAX);
+    let sym = Sym { sym_file, func, j_jump, iat, base_addr, color: false };
+    let mut fmter = IntelFormatter::with_options(Some(Box::new(sym.clone())), None);
+    insnv.iter().map(|insn| {
+        let mut text = String::new();
+        fmter.format(insn, &mut text);
+        let start = (insn.ip() - ip) as usize;
+        DisasmLine { addr: insn.ip(), bytes: code[start..start + insn.len()].to_vec(), text, label: sym.label(insn.ip()) }
+    }).collect()
+}
+
+
 /*fn finder(sym: &SymbolFile, target: u64) -> bool {
     if sym.offset < 0 {
         false
@@ -61,37 +99,28 @@ impl SymbolResolver for Sym {
 }*/
 
 
-pub fn is_valid_addr(h_proc: HANDLE, addr: u64) -> bool {
-    if h_proc.is_null() {
-        get_section_of_rva(addr).is_some()
-    }
-    else {
-        unsafe {
-            let mut mem_info = mem::zeroed();
-            if VirtualQueryEx(h_proc, addr as LPVOID, &mut mem_info, size_of::<MEMORY_BASIC_INFORMATION>()) == 0 {
-                false
-            } else {
-                mem_info.State == MEM_COMMIT
-            }
-        }
+pub fn is_valid_addr(target: &dyn DebugTarget, addr: u64) -> bool {
+    match target.query_region(addr) {
+        Ok(region) => region.is_commit(),
+        Err(_) => false,
     }
 }
 
 
 
 
-pub fn get_addr_va1(addr_str: &str, ctx: *const CONTEXT) -> Result<u64, anyhow::Error> {
+pub fn get_addr_va1(session: &Session, addr_str: &str, ctx: *const CONTEXT) -> Result<u64, anyhow::Error> {
     if ctx.is_null() {
-        return get_addr_br(addr_str);
+        return get_addr_br(&session.symbols, addr_str);
     }
-    match unsafe { NT_HEADER } {
-        Some(NtHeaders::Headers32(_)) | Some(NtHeaders::Headers64(_)) => get_addr_va(addr_str, ctx),
+    match session.nt_header {
+        Some(NtHeaders::Headers32(_)) | Some(NtHeaders::Headers64(_)) => get_addr_va(session, addr_str, ctx),
         None => Err(anyhow!("you must load a file for disasm")),
     }
 }
 
 
-pub fn handle_disasm(linev: &[&str], h_proc: HANDLE, ctx: *const CONTEXT) {
+pub fn handle_disasm(session: &Session, linev: &[&str], target: &dyn DebugTarget, ctx: *const CONTEXT) {
     if linev.len() < 2 {
         println!("{}", usages::USAGE_DISASM);
         return;
@@ -99,43 +128,18 @@ pub fn handle_disasm(linev: &[&str], h_proc: HANDLE, ctx: *const CONTEXT) {
 
     let addr_str = linev[1];
     let count_str = linev.get(2);
-    match get_addr_va1(addr_str, ctx) {
-        Ok(addr) => disasm(h_proc, addr, count_str, ctx),
+    match get_addr_va1(session, addr_str, ctx) {
+        Ok(addr) => disasm(session, target, addr, count_str, ctx),
         Err(e) => {
             print_lg(LevelPrint::ErrorO, e);
-            return;
         }
     }
 }
 
 
-fn get_memory_info(h_proc: HANDLE, addr: u64) -> Result<MEMORY_BASIC_INFORMATION, anyhow::Error> {
-    unsafe {
-        if h_proc.is_null() {
-            return if let Some(sec) = get_section_of_rva(addr) {
-                let mut mem_info: MEMORY_BASIC_INFORMATION = mem::zeroed();
-                mem_info.RegionSize = sec.content.len();
-                mem_info.BaseAddress = sec.addr as LPVOID;
-                Ok(mem_info)
-            } else {
-                Err(anyhow!("invalid address"))
-            }
-        }
-
-        let mut mem_info = mem::zeroed();
-        if VirtualQueryEx(h_proc, addr as LPVOID, &mut mem_info, size_of::<MEMORY_BASIC_INFORMATION>()) == 0 {
-            Err(anyhow!("Failed to get the size of region of {:#x} : {}", addr, io::Error::last_os_error()))
-        } else {
-            Ok(mem_info)
-        }
-    }
-}
-
-
-
-fn disasm(h_proc: HANDLE, addr: u64, count_str: Option<&&str>, ctx: *const CONTEXT) {
-    let count = if count_str.is_some() {
-        match str_to::<usize>(count_str.unwrap()) {
+fn disasm(session: &Session, target: &dyn DebugTarget, addr: u64, count_str: Option<&&str>, ctx: *const CONTEXT) {
+    let count = if let Some(count_str) = count_str {
+        match str_to::<usize>(count_str) {
             Ok(count) => count,
             Err(e) => {
                 print_lg(LevelPrint::ErrorO, format!("Invalid count : {e}"));
@@ -146,42 +150,41 @@ fn disasm(h_proc: HANDLE, addr: u64, count_str: Option<&&str>, ctx: *const CONTE
         usize::MAX
     };
     let size;
+    let base_addr = session.base_addr;
 
-    unsafe {
-        if (*&raw const FUNC_INFO).len() != 0 {
-            if let Some(func) = (*&raw const FUNC_INFO).iter().find(|f| {
-                f.BeginAddress as u64 + BASE_ADDR <= addr && f.EndAddress as u64 + BASE_ADDR > addr
-            }) {
-                size = (func.EndAddress as u64 + BASE_ADDR - addr) as usize;
-            } else {
-                match get_memory_info(h_proc, addr) {
-                    Ok(mem_info) => size = mem_info.BaseAddress as usize +  mem_info.RegionSize - addr as usize,
-                    Err(e) => {
-                        print_lg(LevelPrint::ErrorO, e);
-                        return;
-                    },
-                    
-                }
-            }
+    if !session.func_info.is_empty() {
+        if let Some(func) = session.func_info.iter().find(|f| {
+            f.BeginAddress as u64 + base_addr <= addr && f.EndAddress as u64 + base_addr > addr
+        }) {
+            size = (func.EndAddress as u64 + base_addr - addr) as usize;
         } else {
-            match get_memory_info(h_proc, addr) {
-                Ok(mem_info) => size = mem_info.BaseAddress as usize + mem_info.RegionSize - addr as usize,
+            match target.query_region(addr) {
+                Ok(region) => size = (region.end() - addr) as usize,
                 Err(e) => {
                     print_lg(LevelPrint::ErrorO, e);
                     return;
                 },
+                
             }
         }
+    } else {
+        match target.query_region(addr) {
+            Ok(region) => size = (region.end() - addr) as usize,
+            Err(e) => {
+                print_lg(LevelPrint::ErrorO, e);
+                return;
+            },
+        }
     }
 
     let mut buffer = vec![0u8; size];
-    if let Err(e) = read_mem(h_proc, addr, &mut buffer) {
+    if let Err(e) = read_mem(target, addr, &mut buffer) {
         print_lg(LevelPrint::ErrorO, e);
         return;
     }
-    let (insnv, j_jump) = first_it(Decoder::with_ip(unsafe { NT_HEADER }.unwrap().get_bitness() as u32, &buffer, addr, DecoderOptions::NONE), count);
+    let (insnv, j_jump) = first_it(Decoder::with_ip(session.get_bitness() as u32, &buffer, addr, DecoderOptions::NONE), count);
 
-    let sym = unsafe { Sym::from_va_sym(&*ptr::addr_of!(SYMBOLS_V.symbol_file) , &*ptr::addr_of_mut!(FUNC_INFO), j_jump, ctx)};
+    let sym = Sym::from_session(session, j_jump, ctx);
 
     let mut fmter = IntelFormatter::with_options(Some(Box::new(sym.clone())), None);
     let mut out = String::new();
@@ -191,37 +194,27 @@ fn disasm(h_proc: HANDLE, addr: u64, count_str: Option<&&str>, ctx: *const CONTE
     for insn in insnv {
         out.clear();
         fmter.format(&insn, &mut out);
-        if let Some(sym) = sym.sym_file.iter().find(|s|s.addr_ot(ctx) == insn.ip()) {
-            println!("{ADDR_COLOR}{:#x}: ", insn.ip());
-            println!("{ADDR_COLOR}{:#x}: ", insn.ip());
-            println!("{ADDR_COLOR}{:#x}: {:<40}{}:{RESET_COLOR}", insn.ip(), "", sym.name);
-        }
-        else if let Some(f) = sym.func.iter().find(|f|f.BeginAddress as u64 + unsafe {BASE_ADDR} == insn.ip()) {
-            println!("{ADDR_COLOR}{:#x}: ", insn.ip());
-            println!("{ADDR_COLOR}{:#x}: ", insn.ip());
-            println!("{ADDR_COLOR}{:#x}: {:<40}{}{RESET_COLOR}", insn.ip(), "", format!("func_{:x}:", f.BeginAddress));
-        }
-        else if let Some(_) = sym.j_jump.iter().find(|j|j.addr == insn.ip()) {
+        if let Some(label) = sym.label(insn.ip()) {
             println!("{ADDR_COLOR}{:#x}: ", insn.ip());
             println!("{ADDR_COLOR}{:#x}: ", insn.ip());
-            println!("{ADDR_COLOR}{:#x}: {:<40}{}{RESET_COLOR}", insn.ip(), "", format!("label_{:x}:", insn.ip()));
+            println!("{ADDR_COLOR}{:#x}: {:<40}{}:{RESET_COLOR}", insn.ip(), "", label);
         }
         if insn.is_ip_rel_memory_operand() {
             let mut r1 = insn.ip_rel_memory_address();
             let mem_size = insn.memory_displ_size() as usize;
             out.push_str(&format!("{CYAN_COLOR} ;"));
             unsafe {
-                if mem_size == NT_HEADER.unwrap().get_size_of_arch() {
+                if mem_size == session.get_size_of_arch() {
                     let mut addrpass = Vec::new();
                     let mut new_v = 0u64;
-                    out.push_str(&format!(" {r1:#x}"));
+                    out.push_str(&format!(" {r1:#x}{}{CYAN_COLOR}", reloc::reloc_note(session, r1)));
                     loop {
-                        if let Err(e) = read_mem(h_proc, r1, std::slice::from_raw_parts_mut(ptr::addr_of_mut!(new_v) as *mut u8, mem_size)) {
+                        if let Err(e) = read_mem(target, r1, std::slice::from_raw_parts_mut(ptr::addr_of_mut!(new_v) as *mut u8, mem_size)) {
                             print_lg(LevelPrint::ErrorO, format!("<{insn}> - Failed to read process memory at address {:#x} : {e}", r1));
                             break;
                         }
 
-                        if is_valid_addr(h_proc, new_v) {
+                        if is_valid_addr(target, new_v) {
                             if addrpass.contains(&new_v) {
                                 out.push_str(&format!(" <-> {:#x}", new_v));
                                 break
@@ -230,11 +223,11 @@ fn disasm(h_proc: HANDLE, addr: u64, count_str: Option<&&str>, ctx: *const CONTE
                             out.push_str(&format!(" -> {:#x}", new_v));
                             r1 = new_v;
                         }else {
-                            if let Some(s) = sym.sym_file.iter().find(|s|s.addr_ot(ctx) == r1) {
-                                out.push_str(&format!(" -> {ADDR_COLOR}{}{RESET_COLOR}", s.name));
+                            if let Some((_, name)) = sym.sym_file.iter().find(|s|s.0 == r1) {
+                                out.push_str(&format!(" -> {ADDR_COLOR}{}{RESET_COLOR}", name));
                             }
 
-                            else if let Some(f) = sym.func.iter().find(|f|f.BeginAddress as u64 + BASE_ADDR == r1) {
+                            else if let Some(f) = sym.func.iter().find(|f|f.BeginAddress as u64 + base_addr == r1) {
                                 out.push_str(&format!(" -> {BYTES_COLOR}func_{:x}{RESET_COLOR}", f.BeginAddress as u64))
                             }
 
@@ -242,7 +235,7 @@ fn disasm(h_proc: HANDLE, addr: u64, count_str: Option<&&str>, ctx: *const CONTE
                                 out.push_str(&format!(" -> {BYTES_COLOR}label_{:x}{RESET_COLOR}", j.addr));
                             }
                             else {
-                                match read_str(h_proc, r1, insn) {
+                                match read_str(target, r1, insn) {
                                     Ok(str1) => out.push_str(&format!(" -> {str1}")),
                                     Err(e) => print_lg(LevelPrint::ErrorO, e),
                                 }
@@ -253,13 +246,13 @@ fn disasm(h_proc: HANDLE, addr: u64, count_str: Option<&&str>, ctx: *const CONTE
                 }
                 else if mem_size != 0 {
                     let mut u128v = 0u128;
-                    if let Err(e) = read_mem(h_proc, r1, std::slice::from_raw_parts_mut(ptr::addr_of_mut!(u128v) as *mut u8, mem_size)) {
+                    if let Err(e) = read_mem(target, r1, std::slice::from_raw_parts_mut(ptr::addr_of_mut!(u128v) as *mut u8, mem_size)) {
                         print_lg(LevelPrint::ErrorO, format!("<{insn}> - Failed to read process memory at address {:#x} : {e}", r1));
                         continue;
                     }
                     out.push_str(&format!("{:#x}", u128v));
                 }else {
-                    match read_str(h_proc, r1, insn) {
+                    match read_str(target, r1, insn) {
                         Ok(str1) => out.push_str(&format!(" {str1}")),
                         Err(e) => {
                             print_lg(LevelPrint::ErrorO, e);
@@ -269,21 +262,24 @@ fn disasm(h_proc: HANDLE, addr: u64, count_str: Option<&&str>, ctx: *const CONTE
                 }
             }
         }
+        if let Some(rel) = insn.ip().checked_sub(base_addr).and_then(|rva| reloc::relocs_in(&session.relocs, rva, rva + insn.len() as u64).first()) {
+            out.push_str(&format!("{WAR_COLOR} ; {} reloc at +{:#x}", rel.type_name(), rel.rva as u64 + base_addr - insn.ip()));
+        }
         let start_index = (insn.ip() - addr) as usize;
         let instr_bytes = &buffer[start_index..start_index + insn.len()];
         let byte_str = instr_bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<String>>().join(" ");
-        println!("{ADDR_COLOR}{:#x}: {}{:<48} {VALUE_COLOR}{out}{RESET_COLOR}", insn.ip(), "\x1b[38;5;166m", byte_str);
+        println!("{ADDR_COLOR}{:#x}: \x1b[38;5;166m{:<48} {VALUE_COLOR}{out}{RESET_COLOR}", insn.ip(), byte_str);
     }
 }
 
 
 
-pub fn read_str(h_proc: HANDLE, addr: u64, insn: Instruction) -> Result<String, anyhow::Error> {
+pub fn read_str(target: &dyn DebugTarget, addr: u64, insn: Instruction) -> Result<String, anyhow::Error> {
     let mut str_buf = [0u8;260];
-    if let Err(e) = read_mem(h_proc, addr, &mut str_buf) {
+    if let Err(e) = read_mem(target, addr, &mut str_buf) {
         return Err(anyhow!("<{insn}> - Failed to read process memory at address {:#x} : {}", addr, e));
     }
-    let f: Vec<u8> = str_buf.iter().take_while(|&&c| c != 0).map(|&c| c).collect();
+    let f: Vec<u8> = str_buf.iter().take_while(|&&c| c != 0).copied().collect();
     Ok(format!("\"{}\"", deref_mem::espc(&f)))
 }
 
@@ -318,3 +314,46 @@ pub fn first_it(decoder: Decoder, count: usize) -> (Vec<Instruction>, Vec<JAddr>
     }
     (res_insn, result)
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: u64 = 0x140000000;
+
+    #[test]
+    fn disasm_with_labels() {
+        let code = [
+            0x55,
+            0x48, 0x89, 0xe5,
+            0xff, 0x15, 0xf6, 0x1f, 0x00, 0x00,
+            0xe8, 0x11, 0x00, 0x00, 0x00,
+            0x74, 0x01,
+            0x90,
+            0xc3,
+        ];
+        let sym_file = vec![(BASE + 0x1000, "main".to_string())];
+        let iat = vec![(BASE + 0x3000, "KERNEL32.dll!ExitProcess".to_string())];
+        let func = vec![RUNTIME_FUNCTION { BeginAddress: 0x1020, EndAddress: 0x1040, UnwindData: 0 }];
+        let lines = disasm_code(sym_file, iat, func, BASE, 64, &code, BASE + 0x1000);
+        let addrs: Vec<u64> = lines.iter().map(|l| l.addr - BASE).collect();
+        assert_eq!(addrs, [0x1000, 0x1001, 0x1004, 0x100a, 0x100f, 0x1011, 0x1012]);
+        assert_eq!(lines[1].bytes, [0x48, 0x89, 0xe5]);
+        assert_eq!(lines[0].label.as_deref(), Some("main"));
+        assert_eq!(lines[0].text, "push rbp");
+        assert_eq!(lines[2].text, "call qword ptr [KERNEL32.dll!ExitProcess]");
+        assert_eq!(lines[3].text, "call func_1020");
+        assert_eq!(lines[4].text, "je short label_140001012");
+        assert_eq!(lines[6].label.as_deref(), Some("label_140001012"));
+        assert_eq!(lines[5].label, None);
+    }
+
+    #[test]
+    fn disasm_32bit_and_truncated() {
+        let lines = disasm_code(Vec::new(), Vec::new(), Vec::new(), 0x400000, 32, &[0x8b, 0x45, 0x08, 0xe8, 0x00], 0x401000);
+        assert_eq!(lines[0].text, "mov eax,[ebp+8]");
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].bytes, [0xe8, 0x00]);
+    }
+}
@@ -0,0 +1,286 @@
+use crate::command::{self, sym};
+use crate::dbg::dbg_cmd::{disasm, register, usages};
+use crate::dbg::memory::set::set_memory;
+use crate::dbg::memory::unwind::{Frame, Unwinder};
+use crate::dbg::memory::{deref_mem, finder, mem_info};
+use crate::dbg::target::{self, DebugTarget, Registers};
+use crate::dbg::regs::RegisterSet;
+use crate::dbg::DbgState;
+use crate::session::Session;
+use crate::usage;
+use crate::ut::cast::str_to;
+use crate::ut::fmt::*;
+use std::io::{self, Write};
+use std::ptr;
+
+const MAX_FRAMES: usize = 256;
+
+
+pub fn cmd_wait(session: &mut Session, target: &dyn DebugTarget, regs: &mut Registers, c_dbg: &mut DbgState, extra: &mut dyn FnMut(&mut Session, &[&str], &mut Registers) -> bool) {
+    let mut input = String::new();
+    loop {
+        input.clear();
+        print!("\x1b[38;5;129m>> ");
+        io::stdout().flush().unwrap();
+        io::stdin().read_line(&mut input).unwrap();
+        print!("{RESET_COLOR}");
+        io::stdout().flush().unwrap();
+        let linev: Vec<&str> = input.split_whitespace().collect();
+        if extra(session, &linev, regs) {
+            continue;
+        }
+        let cmd = linev.first();
+        match cmd {
+            Some(&"c") | Some(&"continue") | Some(&"run") => break,
+            Some(&"q") | Some(&"quit") | Some(&"break") | Some(&"exit") => {
+                *c_dbg = DbgState::Stopped;
+                break;
+            }
+            Some(&"v") | Some(&"value") | Some(&"registers") | Some(&"register") | Some(&"reg") | Some(&"r") => register::handle_reg(&linev, &reg_set(session, regs)),
+            Some(&"deref") => handle_deref(session, &linev, regs, target),
+            Some(&"set") => handle_set(session, &linev, regs, target),
+            Some(&"disasm") => handle_disasm(session, &linev, regs, target),
+            Some(&"backtrace") | Some(&"frame") => backtrace(session, &linev, regs, target),
+            Some(&"ret") => handle_ret(session, regs, target),
+            Some(&"b") | Some(&"breakpoint") => command::breakpoint::handle_breakpoint_proc(session, &linev, target),
+            Some(&"rb") | Some(&"delete-breakpoint") | Some(&"remove-b") | Some(&"remove-breakpoint") => command::breakpoint::handle_restore_breakpoint_proc(session, &linev, target),
+            Some(&"b-va") | Some(&"break-va") => command::breakpoint::handle_b_va_proc(session, &linev, target, ptr::null()),
+            Some(&"break-ret") | Some(&"b-ret") => command::stret::handle_stret(session, &linev, target),
+            Some(&"b-ret-va") | Some(&"b-retva") => command::stret::handle_proc_b_ret_va(session, &linev, target),
+            Some(&"hook") => command::hook::handle_hook_proc(session, &linev, target),
+            Some(&"reset") => command::reset::reset_proc(session, &linev, target),
+            Some(&"remove") => command::remover::remove_element_proc(session, &linev, target, ptr::null_mut()),
+            Some(&"cva") => command::with_va::handle_calcule_va(session, &linev),
+            Some(&"crva") => command::with_va::handle_calcule_rva(session, &linev),
+            Some(&"symbol-address") | Some(&"sym-address") | Some(&"sym-addr") => sym::handle_sym_addr(session, &linev, ptr::null()),
+            Some(&"base-addr") | Some(&"ba") => println!("base address : {VALUE_COLOR}{:#x}{RESET_COLOR}", session.base_addr),
+            Some(&"memory-info") | Some(&"mem-info") => match linev.get(1).and_then(|a| to_addr(session, regs, a)) {
+                Some(addr) => mem_info::get_mem_info(addr, target),
+                None => println!("{}", usage::USAGE_MEM_INFO),
+            },
+            Some(&"find") => finder::handle_find(&linev, target),
+            Some(&"help") => usages::help(&linev),
+            None => print_lg(LevelPrint::ErrorO, "Please enter a command"),
+            _ => print_lg(LevelPrint::ErrorO, format!("Unknown command: {}", cmd.unwrap())),
+        }
+    }
+}
+
+
+pub fn reg_set(session: &Session, regs: &Registers) -> RegisterSet {
+    RegisterSet::from_registers(regs, session.get_bitness() == 64)
+}
+
+pub fn to_addr(session: &Session, regs: &Registers, addr_str: &str) -> Option<u64> {
+    reg_set(session, regs).value(addr_str).as_u64().or_else(|| str_to::<u64>(addr_str).ok())
+}
+
+
+fn handle_deref(session: &Session, linev: &[&str], regs: &Registers, target: &dyn DebugTarget) {
+    if linev.len() < 3 {
+        eprintln!("{}", usages::USAGE_DEREF);
+        return;
+    }
+    match to_addr(session, regs, linev[2]) {
+        Some(addr) => {
+            if let Err(e) = deref_mem::deref_memory(session, target, linev[1], addr as usize) {
+                print_lg(LevelPrint::ErrorO, e);
+            }
+        }
+        None => print_lg(LevelPrint::ErrorO, format!("invalid target : '{}'", linev[2])),
+    }
+}
+
+
+fn handle_set(session: &Session, linev: &[&str], regs: &mut Registers, target: &dyn DebugTarget) {
+    if linev.len() < 4 {
+        println!("{}", usage::USAGE_SET);
+        return;
+    }
+    match linev[1].to_lowercase().as_str() {
+        "register" | "reg" => {
+            let mut set = reg_set(session, regs);
+            register::set_register(&linev[2..], &mut set);
+            set.write_registers(regs);
+        }
+        "memory" | "mem" => {
+            if linev.len() < 5 {
+                eprintln!("{}", usages::USAGE_SET_MEM);
+                return;
+            }
+            match to_addr(session, regs, linev[3]) {
+                Some(addr) => {
+                    let types_r = linev[2].split('[').next().unwrap_or_default().to_lowercase();
+                    set_memory::target_mem(session, target, &linev[4..].join(" "), addr, 1, &types_r);
+                }
+                None => print_lg(LevelPrint::ErrorO, format!("invalid target : {}", linev[3])),
+            }
+        }
+        _ => print_lg(LevelPrint::ErrorO, format!("unknow element {}", linev[1])),
+    }
+}
+
+
+fn handle_disasm(session: &Session, linev: &[&str], regs: &Registers, target: &dyn DebugTarget) {
+    if linev.len() < 2 {
+        println!("{}", usages::USAGE_DISASM);
+        return;
+    }
+    let addr_str = match reg_set(session, regs).value(linev[1]).as_u64() {
+        Some(value) => format!("{:#x}", value),
+        None => linev[1].to_string(),
+    };
+    let mut args = vec![linev[0], &addr_str];
+    args.extend_from_slice(&linev[2..]);
+    disasm::handle_disasm(session, &args, target, ptr::null());
+}
+
+
+fn symbol_at(session: &Session, addr: u64) -> Option<String> {
+    let rva = addr.checked_sub(session.base_addr)?;
+    session.sections.iter().find(|s| s.addr as u64 <= rva && rva < s.addr as u64 + s.content.len() as u64)?;
+    session.symbols.symbol_file.iter()
+        .filter(|s| s.offset >= 0 && s.offset as u64 <= rva)
+        .max_by_key(|s| s.offset)
+        .map(|s| format!("{}+{:#x}", s.name, rva - s.offset as u64))
+}
+
+
+fn image_size(session: &Session) -> u64 {
+    match session.nt_header {
+        Some(nt_header) => nt_header.size_of_image() as u64,
+        None => session.sections.iter().map(|s| s.addr as u64 + s.virtual_size as u64).max().unwrap_or(0),
+    }
+}
+
+// the .pdata of the file context unwinds the functions built without a frame pointer,
+// without it the frames are found by following the rbp chain
+pub fn frames(session: &Session, regs: &Registers, target: &dyn DebugTarget) -> Vec<Frame> {
+    if session.get_bitness() == 64 && !session.func_info.is_empty() {
+        let mut unwinder = Unwinder::new(target);
+        unwinder.add_module(session.base_addr, image_size(session), session.func_info.clone());
+        let frames = unwinder.backtrace(*regs);
+        if !frames.is_empty() {
+            return frames;
+        }
+    }
+    let ptr_size = session.get_size_of_arch();
+    let mut frames = Vec::new();
+    let mut rip = regs.rip;
+    let mut rbp = regs.rbp;
+    let mut rsp = regs.rsp;
+    while frames.len() < MAX_FRAMES && rip != 0 {
+        frames.push(Frame { pc: rip, frame: rbp, stack: rsp, ..Default::default() });
+        let Some(ret_slot) = rbp.checked_add(ptr_size as u64).filter(|_| rbp != 0) else {
+            break;
+        };
+        rip = match target::read_ptr(target, ret_slot, ptr_size) {
+            Ok(ret) => ret,
+            Err(_) => break,
+        };
+        rsp = ret_slot.saturating_add(ptr_size as u64);
+        rbp = match target::read_ptr(target, rbp, ptr_size) {
+            Ok(next) if next > rbp => next,
+            _ => 0,
+        };
+    }
+    frames
+}
+
+fn handle_ret(session: &Session, regs: &mut Registers, target: &dyn DebugTarget) {
+    match frames(session, regs, target).get(1) {
+        Some(caller) => {
+            regs.rip = caller.pc;
+            regs.rsp = caller.stack;
+            println!(
+                "{VALID_COLOR}now rip points to the address : {VALUE_COLOR}{:#x}{RESET_COLOR}\n\
+                {VALID_COLOR}and rsp to the stack of the caller : {VALUE_COLOR}{:#x}{RESET_COLOR}",
+                regs.rip,
+                regs.rsp
+            );
+        }
+        None => print_lg(LevelPrint::ErrorO, format!("an error occurred while getting return address of the current stack frame: rip: {:#x}", regs.rip)),
+    }
+}
+
+
+fn backtrace(session: &Session, linev: &[&str], regs: &Registers, target: &dyn DebugTarget) {
+    let count = match linev.get(1) {
+        None | Some(&"full") => usize::MAX,
+        Some(count_str) => match str_to::<usize>(count_str) {
+            Ok(count) => count,
+            Err(e) => {
+                print_lg(LevelPrint::ErrorO, format!("invalid count: {e}"));
+                return;
+            }
+        },
+    };
+    for (i, frame) in frames(session, regs, target).iter().take(count).enumerate() {
+        match symbol_at(session, frame.pc) {
+            Some(sym) => println!("#{i} {ADDR_COLOR}{:#x}{RESET_COLOR} in {SYM_COLOR}{sym}{RESET_COLOR} frame {VALUE_COLOR}{:#x}{RESET_COLOR}", frame.pc, frame.frame),
+            None => println!("#{i} {ADDR_COLOR}{:#x}{RESET_COLOR} frame {VALUE_COLOR}{:#x}{RESET_COLOR}", frame.pc, frame.frame),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbg::target::{MemTarget, PROT_EXECUTE_READWRITE, PROT_READWRITE};
+    use crate::pefile::winnt::RUNTIME_FUNCTION;
+    use crate::pefile::Section;
+
+    const BASE: u64 = 0x140000000;
+    const STACK: u64 = 0x7ff000;
+
+    // sub rsp, 0x28 at +0x1000 without a frame pointer, described by its unwind info at +0x2000
+    fn target() -> MemTarget {
+        let mut image = vec![0x90u8; 0x3000];
+        image[0x1000..0x1004].copy_from_slice(&[0x48, 0x83, 0xec, 0x28]);
+        image[0x2000..0x2008].copy_from_slice(&[1, 4, 1, 0, 4, 0x42, 0, 0]);
+        let target = MemTarget::new();
+        target.map(BASE, image, PROT_EXECUTE_READWRITE);
+        target.map(STACK, vec![0; 0x100], PROT_READWRITE);
+        target.write_memory(STACK + 0x28, &(BASE + 0x1800).to_le_bytes()).unwrap();
+        target.write_memory(STACK + 0x80, &[0; 8]).unwrap();
+        target.write_memory(STACK + 0x88, &(BASE + 0x1900).to_le_bytes()).unwrap();
+        target
+    }
+
+    fn session(func_info: Vec<RUNTIME_FUNCTION>) -> Session {
+        let text = Section { name: ".text".to_string(), content: Vec::new(), addr: 0x1000, characteristics: 0, virtual_size: 0x2000, raw_size: 0, raw_ptr: 0 };
+        Session { base_addr: BASE, sections: vec![text], func_info, ..Default::default() }
+    }
+
+    fn pcs(session: &Session, regs: &Registers) -> Vec<u64> {
+        frames(session, regs, &target()).iter().map(|f| f.pc).collect()
+    }
+
+    #[test]
+    fn backtrace_unwinds_with_pdata() {
+        let session = session(vec![RUNTIME_FUNCTION { BeginAddress: 0x1000, EndAddress: 0x1040, UnwindData: 0x2000 }]);
+        let regs = Registers { rip: BASE + 0x1008, rsp: STACK, rbp: STACK + 0x80, ..Default::default() };
+        assert_eq!(pcs(&session, &regs), [BASE + 0x1008, BASE + 0x1800]);
+    }
+
+    #[test]
+    fn backtrace_follows_rbp_without_pdata() {
+        let session = session(Vec::new());
+        let regs = Registers { rip: BASE + 0x1008, rsp: STACK, rbp: STACK + 0x80, ..Default::default() };
+        assert_eq!(pcs(&session, &regs), [BASE + 0x1008, BASE + 0x1900]);
+        let regs = Registers { rip: BASE + 0x1008, rbp: u64::MAX - 4, ..Default::default() };
+        assert_eq!(pcs(&session, &regs), [BASE + 0x1008]);
+    }
+
+    #[test]
+    fn ret_moves_to_the_caller() {
+        let mut regs = Registers { rip: BASE + 0x1008, rsp: STACK, rbp: STACK + 0x80, ..Default::default() };
+        handle_ret(&session(vec![RUNTIME_FUNCTION { BeginAddress: 0x1000, EndAddress: 0x1040, UnwindData: 0x2000 }]), &mut regs, &target());
+        assert_eq!((regs.rip, regs.rsp), (BASE + 0x1800, STACK + 0x30));
+
+        let mut regs = Registers { rip: BASE + 0x1008, rsp: STACK, rbp: STACK + 0x80, ..Default::default() };
+        handle_ret(&session(Vec::new()), &mut regs, &target());
+        assert_eq!((regs.rip, regs.rsp), (BASE + 0x1900, STACK + 0x90));
+    }
+}
@@ -1,41 +1,82 @@
+#[cfg(windows)]
+use crate::dbg::target::WinTarget;
+#[cfg(windows)]
 use crate::ctx_ptr;
-use crate::dbg::memory::stack::ST_FRAME;
-use crate::dbg::{memory, DbgState, RealAddr, BASE_ADDR};
-use crate::pefile::function::FUNC_INFO;
-use crate::pefile::NT_HEADER;
-use crate::symbol::{SymbolType, SYMBOLS_V};
-use crate::{command, usage, ALL_ELM};
+#[cfg(windows)]
+use crate::dbg::{memory, DbgState, RealAddr};
+#[cfg(windows)]
+use crate::session::Session;
+#[cfg(windows)]
+use crate::symbol::{sym_init, SymbolType};
+#[cfg(windows)]
+use crate::{command, usage};
+#[cfg(windows)]
 use std::io;
+#[cfg(windows)]
 use std::io::Write;
-use std::str::FromStr;
+#[cfg(windows)]
 use winapi::shared::ntdef::HANDLE;
-use winapi::um::dbghelp::SymCleanup;
+#[cfg(windows)]
 use winapi::um::winbase::DebugSetProcessKillOnExit;
-use winapi::um::winnt::CONTEXT;
+#[cfg(windows)]
+use winapi::um::winnt::{CONTEXT, WOW64_CONTEXT};
+#[cfg(windows)]
+use winapi::um::dbghelp::SymCleanup;
+#[cfg(windows)]
+use crate::dbg::regs::RegisterSet;
+#[cfg(windows)]
 use crate::command::breakpoint::Brkpts;
-use crate::ut::cast::{str_to, NumConvert};
+#[cfg(windows)]
+use crate::ut::cast::str_to;
+#[cfg(windows)]
 use crate::ut::fmt::*;
 
-pub(crate) mod disasm;
+pub mod disasm;
+pub mod generic;
+pub mod register;
 pub mod usages;
-pub mod x32;
-pub mod x64;
+#[cfg(windows)]
+pub mod win;
 
-pub(crate) fn init_cm(ctx: CONTEXT, h_proc: HANDLE, h_thread: HANDLE, addr_func: &mut u64) {
+#[cfg(windows)]
+pub(crate) fn init_cm(session: &mut Session, ctx: CONTEXT, h_proc: HANDLE, addr_func: &mut u64) {
     unsafe {
-        memory::stack::LEN = 0;
-        (*&raw mut ST_FRAME).clear();
-        memory::stack::get_frame_st(h_proc, h_thread, ctx);
-        *addr_func = if let Some(func) = (*&raw const FUNC_INFO).iter().find(|f| {
-            f.BeginAddress as u64 + BASE_ADDR <= ctx.Rip
-                && f.EndAddress as u64 + BASE_ADDR >= ctx.Rip
+        let base_addr = session.base_addr;
+        session.st_len = 0;
+        session.st_frame.clear();
+        memory::stack::get_frame_st(session, h_proc, &ctx);
+        *addr_func = if let Some(func) = session.func_info.iter().find(|f| {
+            f.BeginAddress as u64 + base_addr <= ctx.Rip
+                && f.EndAddress as u64 + base_addr >= ctx.Rip
         }) {
-            func.BeginAddress as u64 + BASE_ADDR
+            func.BeginAddress as u64 + base_addr
         } else {
             ctx.Rip
         };
-        if SYMBOLS_V.symbol_type == SymbolType::PDB {
-            memory::stack::get_local_sym(h_proc, *addr_func, ctx_ptr!(ctx));
+        if session.symbols.symbol_type == SymbolType::PDB {
+            match sym_init(session, h_proc) {
+                Ok(()) => memory::stack::get_local_sym(session, h_proc, *addr_func, ctx_ptr!(ctx)),
+                Err(e) => print_lg(LevelPrint::Error, e),
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+pub(crate) fn init_cm32(session: &mut Session, ctx: WOW64_CONTEXT, h_proc: HANDLE, h_thread: HANDLE, addr_func: &mut u64) {
+    unsafe {
+        let base_addr = session.base_addr as u32;
+        session.st_frame.clear();
+        memory::stack::get_frame_st32(session, h_proc, h_thread, ctx);
+        *addr_func = if let Some(func) = session.func_info.iter().find(|f| { f.BeginAddress + base_addr <= ctx.Eip
+                && f.EndAddress + base_addr >= ctx.Eip
+        }) {
+            (func.BeginAddress + base_addr) as u64
+        } else {
+            ctx.Eip as u64
+        };
+        if session.symbols.symbol_type == SymbolType::PDB {
+            memory::stack::get_local_sym(session, h_proc, *addr_func, ctx_ptr!(ctx));
         } else {
             SymCleanup(h_proc);
         }
@@ -49,56 +90,56 @@ macro_rules! ctx_ptr {
     };
 }
 
-fn unint_cm() {
-    unsafe {
-        for _ in 0..memory::stack::LEN {
-            (*&raw mut SYMBOLS_V).symbol_file.pop();
-        }
+#[cfg(windows)]
+fn unint_cm(session: &mut Session) {
+    for _ in 0..session.st_len {
+        session.symbols.symbol_file.pop();
     }
 }
 
-fn handle_backtrace(linev: &[&str], ctx: *const CONTEXT) {
+#[cfg(windows)]
+fn handle_backtrace(session: &Session, linev: &[&str], ctx: *const CONTEXT) {
     let count;
     let arg1 = linev.get(1);
-    if arg1 == Some(&"full") || arg1.is_none() {
-        count = usize::MAX;
-    } else {
-        match str_to::<usize>(arg1.unwrap()) {
+    if let Some(arg1) = arg1.filter(|a| **a != "full") {
+        match str_to::<usize>(arg1) {
             Ok(counts) => count = counts,
             Err(e) => {
                 print_lg(LevelPrint::ErrorO, format!("invalid count: {e}"));
                 return;
             }
         }
+    } else {
+        count = usize::MAX;
     }
-    command::info::print_frame(count, ctx);
+    command::info::print_frame(session, count, ctx);
 }
 
-fn print_curr_func(addr_func: u64, ctx: *const CONTEXT) {
-    unsafe {
-        println!("{}Function    : {:#x} {}{RESET_COLOR}",
-            ADDR_COLOR, addr_func,
-            if let Some(sym) = (*&raw const SYMBOLS_V).symbol_file.iter().find(|s| s.real_addr(ctx) == addr_func)
-            {
-                format!("<{}>", sym.name)
-            } else {
-                "".to_string()
-            }
-        );
-        if let Some(func) = (*&raw const FUNC_INFO).iter().find(|f| f.BeginAddress as u64 + BASE_ADDR == addr_func) {
-            println!("{}End Address : {:#x}", VALUE_COLOR, func.EndAddress as u64 + BASE_ADDR);
-            println!("{}Size        : {:#x}{RESET_COLOR}", MAGENTA, func.EndAddress - func.BeginAddress);
+#[cfg(windows)]
+fn print_curr_func(session: &Session, addr_func: u64, ctx: *const CONTEXT) {
+    println!("{}Function    : {:#x} {}{RESET_COLOR}",
+        ADDR_COLOR, addr_func,
+        if let Some(sym) = session.symbols.symbol_file.iter().find(|s| s.real_addr(session, ctx) == addr_func)
+        {
+            format!("<{}>", sym.name)
+        } else {
+            "".to_string()
         }
+    );
+    if let Some(func) = session.func_info.iter().find(|f| f.BeginAddress as u64 + session.base_addr == addr_func) {
+        println!("{}End Address : {:#x}", VALUE_COLOR, func.EndAddress as u64 + session.base_addr);
+        println!("{}Size        : {:#x}{RESET_COLOR}", MAGENTA, func.EndAddress - func.BeginAddress);
     }
 }
 
-fn handle_quit(input: &mut String, continue_debugging: &mut DbgState, stop_process: &mut bool) {
+#[cfg(windows)]
+fn handle_quit(session: &Session, input: &mut String, continue_debugging: &mut DbgState, stop_process: &mut bool) {
     input.clear();
     print!("Are you sure to stop this process? [y/n] : ");
     io::stdout().flush().unwrap();
     io::stdin().read_line(input).unwrap();
     if input.trim() == "y" || input.trim() == "yes" {
-        if unsafe {(*&raw mut ALL_ELM).attach.is_some()}{
+        if session.all.attach.is_some() {
             loop {
                 print!("kill attach process ? [y/n] : ");
                 input.clear();
@@ -126,32 +167,39 @@ fn handle_quit(input: &mut String, continue_debugging: &mut DbgState, stop_proce
     }
 }
 
-fn handle_ret<T: NumConvert + num::Num + std::ops::SubAssign + std::fmt::LowerHex + Copy>(rip: &mut T, rsp: &mut T) {
-    unsafe {
-        if let Some(frame_ret) = memory::stack::get_real_frame(rip.to_u64()) {
-            *rip = T::from_u64(frame_ret.AddrReturn.Offset);
-            *rsp -= T::from_u64(NT_HEADER.unwrap().get_size_of_arch() as u64);
-            println!(
-                "{VALID_COLOR}now rip points to the address : {VALUE_COLOR}{:#x}{RESET_COLOR}\n\
-                {VALID_COLOR}and rsp was decremented by {} : {VALUE_COLOR}{:#x}{RESET_COLOR}",
-                *rip,
-                NT_HEADER.unwrap().get_size_of_arch(),
-                *rsp
-            );
-        } else {
-            print_lg(LevelPrint::ErrorO, format!("an error occurred while getting return address of the current stack frame: rip: {:#x}", *rip));
-        }
+#[cfg(windows)]
+fn handle_ret(session: &Session, ctx: *mut CONTEXT) {
+    let mut regs = unsafe { RegisterSet::from_ctx_ptr(session.get_bitness() == 64, ctx) };
+    let (ip, sp) = if regs.is64 { ("rip", "rsp") } else { ("eip", "esp") };
+    let Some(frame_ret) = memory::stack::get_real_frame(session, regs.ip()) else {
+        print_lg(LevelPrint::ErrorO, format!("an error occurred while getting return address of the current stack frame: rip: {:#x}", regs.ip()));
+        return;
+    };
+    let size = session.get_size_of_arch() as u64;
+    let new_sp = regs.sp().wrapping_sub(size) & if regs.is64 { u64::MAX } else { u32::MAX as u64 };
+    if let Err(e) = regs.set(ip, frame_ret.ret as u128).and_then(|_| regs.set(sp, new_sp as u128)) {
+        print_lg(LevelPrint::ErrorO, e);
+        return;
     }
+    unsafe { regs.write_ctx_ptr(ctx) };
+    println!(
+        "{VALID_COLOR}now rip points to the address : {VALUE_COLOR}{:#x}{RESET_COLOR}\n\
+        {VALID_COLOR}and rsp was decremented by {} : {VALUE_COLOR}{:#x}{RESET_COLOR}",
+        regs.ip(),
+        size,
+        regs.sp()
+    );
 }
 
-fn handle_skip(linev: &[&str], h_proc: HANDLE) {
+#[cfg(windows)]
+fn handle_skip(session: &mut Session, linev: &[&str], h_proc: HANDLE) {
     if linev.len() == 2 {
-        match Brkpts::from_str(&linev.join(" ")) {
-            Ok(mut b) => unsafe {
-                if let Err(e) = memory::set_addr_over(h_proc, b.addr + BASE_ADDR, &mut b.origin_b) {
+        match Brkpts::from_str_sym(&session.symbols, &linev.join(" ")) {
+            Ok(mut b) => {
+                if let Err(e) = memory::set_addr_over(&WinTarget::new(h_proc), &session.symbols, session.base_addr, b.addr + session.base_addr, &mut b.origin_b) {
                     print_lg(LevelPrint::ErrorO, e);
                 }else {
-                    (*&raw mut ALL_ELM).skip_addr.push(b);
+                    session.all.skip_addr.push(b);
                 }
             }
             Err(e) => print_lg(LevelPrint::ErrorO, format!("error while trying to skip memory: {}", e)),
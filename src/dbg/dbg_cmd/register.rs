@@ -0,0 +1,100 @@
+use crate::dbg::memory::deref_mem;
+use crate::dbg::regs::{RegisterSet, Value, DEBUG, SEGMENTS};
+use crate::usage;
+use crate::ut::cast::str_to;
+use crate::ut::fmt::*;
+
+
+pub fn handle_reg(linev: &[&str], regs: &RegisterSet) {
+    match linev.get(1) {
+        Some(&"all-reg") | Some(&"all-register") => {
+            for reg_name in regs.gpr_names() {
+                print_value(reg_name, regs.value(reg_name));
+            }
+        }
+
+        Some(&"all-seg") | Some(&"all-segment") => {
+            for reg_name in SEGMENTS {
+                print_value(reg_name, regs.value(reg_name));
+            }
+        }
+
+        Some(&"all-vec") | Some(&"all-vector") => {
+            for reg_name in regs.xmm_names() {
+                print_value(reg_name, regs.value(reg_name));
+            }
+            print_value("mxcsr", regs.value("mxcsr"));
+        }
+
+        Some(&"all-dbg") | Some(&"all-debug") => {
+            for reg_name in DEBUG {
+                print_value(reg_name, regs.value(reg_name));
+            }
+        }
+
+        Some(&"all") => {
+            for reg_name in regs.gpr_names().iter().chain(SEGMENTS.iter()).chain(["eflags"].iter()) {
+                print_value(reg_name, regs.value(reg_name));
+            }
+            for reg_name in regs.xmm_names() {
+                print_value(reg_name, regs.value(reg_name));
+            }
+            print_value("mxcsr", regs.value("mxcsr"));
+        }
+
+        Some(register) => print_value(register, regs.value(register)),
+        None => println!("{}", usage::USAGE_REG),
+    }
+}
+
+
+fn print_value(register: &str, value: Value) {
+    match value {
+        Value::U128(v) => {
+            let (low, high) = (v as u64, (v >> 64) as i64);
+            let str1 = deref_mem::espc(&low.to_le_bytes());
+            let str2 = deref_mem::espc(&high.to_le_bytes());
+            println!("{:<6} = {{\"{str1}{str2}\"}}", register);
+            println!("{:<6} = {{{}{:#x}, {:#x}{}}}", "_m128i", VALUE_COLOR, low, high, RESET_COLOR);
+            let float: [f32; 4] = unsafe { std::mem::transmute(v) };
+            println!("{:<6} = {{{}{}, {}, {}, {}{}}}", "_m128", VALUE_COLOR, float[0], float[1], float[2], float[3], RESET_COLOR);
+            let double: [f64;2] = unsafe { std::mem::transmute(v) };
+            println!("{:<6} = {{{VALUE_COLOR}{}, {}{RESET_COLOR}}}", "_m128d", double[0], double[1]);
+            println!();
+        }
+        Value::U64(v) => {
+            let str1 = deref_mem::espc(&v.to_le_bytes());
+            println!("{:<6} = {VALUE_COLOR}{:>#18x}{RESET_COLOR} | {VALUE_COLOR}{:>20}{RESET_COLOR} | \"{str1}\"", register, v, v as i64);
+        }
+        Value::U32(v) => {
+            let str1 = deref_mem::espc(&v.to_le_bytes());
+            println!("{:<6} = {VALUE_COLOR}{:>#18x}{RESET_COLOR} | {VALUE_COLOR}{:>20}{RESET_COLOR} | \"{str1}\"", register, v, v as i32);
+        }
+        Value::U16(v) => {
+            let str1 = deref_mem::espc(&v.to_le_bytes());
+            println!("{:<6} = {VALUE_COLOR}{:>#18x}{RESET_COLOR} | {VALUE_COLOR}{:>20}{RESET_COLOR} | \"{str1}\"", register, v, v as i16);
+        }
+        Value::U8(v) => {
+            println!("{:<6} = {VALUE_COLOR}{:>#18x}{RESET_COLOR} | {VALUE_COLOR}{:>20}{RESET_COLOR} | '{VALID_COLOR}{}{RESET_COLOR}'", register, v, v as i8, v as char);
+        }
+        Value::Un => print_lg(LevelPrint::ErrorO, format!("unknow register : '{register}'")),
+    }
+}
+
+
+pub fn set_register(linev: &[&str], regs: &mut RegisterSet) {
+    if linev.len() < 2 {
+        eprintln!("{}", usage::USAGE_SET_REG);
+        return;
+    }
+    let value = match str_to::<u128>(linev[1]) {
+        Ok(value) => value,
+        Err(e) => {
+            print_lg(LevelPrint::ErrorO, format!("error to parse '{}' : {e}", linev[1]));
+            return;
+        }
+    };
+    if let Err(e) = regs.set(linev[0], value) {
+        print_lg(LevelPrint::ErrorO, e);
+    }
+}
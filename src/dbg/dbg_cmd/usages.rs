@@ -229,7 +229,7 @@ for more information (if available) just type <command> without its arguments{RE
             "proc-addr" => println!("{}", usage::USAGE_PROC_ADDR),
             "reset" => println!("{}", usage::USAGE_RESET),
             "s" | "symbol" => println!("{}", usage::USAGE_SYM),
-            "set" => help_set(&linev),
+            "set" => help_set(linev),
             "skip" => println!("{}", usage::USAGE_SKIP),
             "sym-addr" | "sym-address" => println!("for view the symbol address with here name (va)"),
             "sym-info" => println!("{}", usage::USAGE_SYM_INFO),
@@ -0,0 +1,86 @@
+use crate::ut::fmt::*;
+use crate::dbg::target::WinTarget;
+use crate::command::{hook, sym};
+use crate::dbg::dbg_cmd::*;
+use crate::dbg::memory::deref_mem;
+use crate::dbg::memory::set::thread::get_thread_now;
+use crate::dbg::regs::RegisterSet;
+use crate::dbg::{memory, DbgState};
+use crate::session::Session;
+use crate::{command, symbol};
+use std::io::Write;
+use std::io;
+use winapi::shared::ntdef::HANDLE;
+use winapi::um::winnt::{CONTEXT, WOW64_CONTEXT};
+use crate::command::def::variable;
+
+
+// ctx points to a CONTEXT for a 64-bit process and to a WOW64_CONTEXT for a 32-bit one
+pub fn cmd_wait(session: &mut Session, ctx: *mut CONTEXT, h_proc: HANDLE, h_thread: &mut HANDLE, c_dbg: &mut DbgState) {
+    let target = WinTarget::new(h_proc);
+    let is64 = session.get_bitness() == 64;
+    let mut input = String::new();
+    let mut stop_intp = false;
+    let mut addr_func = 0;
+    unsafe {
+        if is64 {
+            init_cm(session, *ctx, h_proc, &mut addr_func);
+        } else {
+            init_cm32(session, *(ctx as *const WOW64_CONTEXT), h_proc, *h_thread, &mut addr_func);
+        }
+    }
+
+    while !stop_intp {
+        input.clear();
+        print!("\x1b[38;5;129m>> ");
+        io::stdout().flush().unwrap();
+        io::stdin().read_line(&mut input).unwrap();
+        print!("{RESET_COLOR}");
+        io::stdout().flush().unwrap();
+        let linev: Vec<&str> = input.split_whitespace().collect();
+        let cmd = linev.first();
+        match cmd {
+            Some(&"c") | Some(&"continue") | Some(&"run") => break,
+            Some(&"v") | Some(&"value") | Some(&"registers") | Some(&"register") | Some(&"reg") | Some(&"r") => register::handle_reg(&linev, &unsafe { RegisterSet::from_ctx_ptr(is64, ctx) }),
+            Some(&"deref") => deref_mem::handle_deref(session, &linev, ctx, &target),
+            Some(&"set") => command::set::set_element(session, h_proc, ctx, &linev),
+            Some(&"q") | Some(&"quit") | Some(&"break") | Some(&"exit") => handle_quit(session, &mut input, c_dbg, &mut stop_intp),
+            Some(&"base-addr") | Some(&"ba") => println!("base address : {VALUE_COLOR}{:#x}{RESET_COLOR}", session.base_addr),
+            Some(&"b") | Some(&"breakpoint") => command::breakpoint::handle_breakpoint_proc(session, &linev, &target),
+            Some(&"rb") | Some(&"delete-breakpoint") | Some(&"remove-b") | Some(&"remove-breakpoint") => command::breakpoint::handle_restore_breakpoint_proc(session, &linev, &target),
+            Some(&"reset") => command::reset::reset_proc(session, &linev, &target),
+            Some(&"remove") => command::remover::remove_element_proc(session, &linev, &target, ctx),
+            Some(&"cva") => command::with_va::handle_calcule_va(session, &linev),
+            Some(&"crva") => command::with_va::handle_calcule_rva(session, &linev),
+            Some(&"ret") => handle_ret(session, ctx),
+            Some(&"break-ret") | Some(&"b-ret") => command::stret::handle_stret(session, &linev, &target),
+            Some(&"skip") => handle_skip(session, &linev, h_proc),
+            Some(&"info") => command::info::handle_info(session, &linev, ctx, h_proc),
+            Some(&"disasm") => disasm::handle_disasm(session, &linev, &target, ctx),
+            Some(&"s") => symbol::load_symbol(session, &linev, &input),
+            Some(&"symbol-address") | Some(&"sym-address") | Some(&"sym-addr") => sym::handle_sym_addr(session, &linev, ctx),
+            Some(&"backtrace") | Some(&"frame") => handle_backtrace(session, &linev, ctx),
+            Some(&"sym-info") => sym::handle_sym_info(session, &linev, ctx),
+            Some(&"add") => command::little_secret::add_op(&linev),
+            Some(&"sub") => command::little_secret::sub_op(&linev),
+            Some(&"watchpoint") | Some(&"watch") | Some(&"w") => command::watchpoint::watchpoint_proc(session, &linev, ctx),
+            Some(&"address-function") | Some(&"address-func") | Some(&"addr-func") => print_curr_func(session, addr_func, ctx),
+            Some(&"symbol-local") | Some(&"sym-local") => sym::print_local_sym(session, ctx),
+            Some(&"memory-info") | Some(&"mem-info") => memory::mem_info::handle_mem_info(session, &linev, &target, ctx),
+            Some(&"b-va") | Some(&"break-va") => command::breakpoint::handle_b_va_proc(session, &linev, &target, ctx),
+            Some(&"def") => command::def::handle_def(session, &linev, &input),
+            Some(&"find") => memory::finder::handle_find(&linev, &target),
+            Some(&"debug-thread") | Some(&"dbg-thread") | Some(&"dbg-th") => memory::set::thread::change_dbg_thread(session, &linev, ctx, h_proc, h_thread, &mut addr_func),
+            Some(&"thread-info") | Some(&"th-info") => get_thread_now(*h_thread),
+            Some(&"help") => usages::help(&linev),
+            Some(&"hook") => hook::handle_hook_proc(session, &linev, &target),
+            Some(&"printf") => variable::printf::printf_var(session, &linev, &input, &target),
+            Some(&"b-ret-va") | Some(&"b-retva") => command::stret::handle_proc_b_ret_va(session, &linev, &target),
+            Some(&"clear") | Some(&"cls") => command::clear_cmd::clear_cmd(),
+            Some(&"proc-addr") => command::proc_addr::handle_get_proc_addr(session, h_proc, &linev),
+            None => print_lg(LevelPrint::ErrorO, "Please enter a command"),
+            _ => print_lg(LevelPrint::ErrorO, format!("Unknown command: {}", cmd.unwrap())),
+        }
+    }
+    unint_cm(session);
+}
@@ -0,0 +1,477 @@
+use crate::dbg::memory::{breakpoint, watchpoint};
+use crate::dbg::regs::RegisterSet;
+use crate::dbg::target::DebugTarget;
+use crate::dbg::{handle_point, memory, DbgState};
+use crate::pefile::{export, reloc};
+use crate::session::Session;
+use crate::symbol::SymbolFile;
+use crate::ut::fmt::{print_lg, LevelPrint};
+
+pub const EXCEPTION_DATATYPE_MISALIGNMENT: u32 = 0x80000002;
+pub const EXCEPTION_BREAKPOINT: u32 = 0x80000003;
+pub const EXCEPTION_SINGLE_STEP: u32 = 0x80000004;
+pub const EXCEPTION_ACCESS_VIOLATION: u32 = 0xc0000005;
+pub const EXCEPTION_ILLEGAL_INSTRUCTION: u32 = 0xc000001d;
+pub const EXCEPTION_ARRAY_BOUNDS_EXCEEDED: u32 = 0xc000008c;
+pub const EXCEPTION_FLT_DENORMAL_OPERAND: u32 = 0xc000008d;
+pub const EXCEPTION_FLT_DIVIDE_BY_ZERO: u32 = 0xc000008e;
+pub const EXCEPTION_FLT_INEXACT_RESULT: u32 = 0xc000008f;
+pub const EXCEPTION_FLT_INVALID_OPERATION: u32 = 0xc0000090;
+pub const EXCEPTION_FLT_OVERFLOW: u32 = 0xc0000091;
+pub const EXCEPTION_STACK_OVERFLOW: u32 = 0xc00000fd;
+pub const CONTROL_C_EXIT: u32 = 0xc000013a;
+pub const STATUS_WX86_SINGLE_STEP: u32 = 0x4000001e;
+pub const STATUS_WX86_BREAKPOINT: u32 = 0x4000001f;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DebugEvent {
+    ProcessCreated { image_base: u64 },
+    ModuleLoaded { base: u64, path: Option<String> },
+    ModuleUnloaded { base: u64 },
+    BreakpointHit { address: u64 },
+    SingleStep { address: u64 },
+    Exception { code: u32, first_chance: bool, address: u64, info: Vec<u64> },
+    ThreadCreated { start_address: u64 },
+    ThreadExited { exit_code: u32 },
+    Output(String),
+    Exited { exit_code: u32 },
+}
+
+impl DebugEvent {
+    pub fn from_exception(code: u32, first_chance: bool, address: u64, info: Vec<u64>) -> DebugEvent {
+        match code {
+            EXCEPTION_BREAKPOINT | STATUS_WX86_BREAKPOINT => DebugEvent::BreakpointHit { address },
+            EXCEPTION_SINGLE_STEP | STATUS_WX86_SINGLE_STEP => DebugEvent::SingleStep { address },
+            _ => DebugEvent::Exception { code, first_chance, address, info },
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventRecord {
+    pub pid: u32,
+    pub tid: u32,
+    pub event: DebugEvent,
+}
+
+impl EventRecord {
+    pub fn new(pid: u32, tid: u32, event: DebugEvent) -> EventRecord {
+        EventRecord { pid, tid, event }
+    }
+}
+
+
+pub trait EventHost {
+    fn target(&self) -> &dyn DebugTarget;
+
+    fn event(&mut self, _ev: &EventRecord) {}
+
+    fn stop(&mut self, session: &mut Session, ev: &EventRecord, c_dbg: &mut DbgState);
+
+    fn process_created(&mut self, _session: &mut Session, _ev: &EventRecord) {}
+
+    fn module_symbols(&mut self, _base: u64) -> Result<Vec<SymbolFile>, anyhow::Error> {
+        Ok(Vec::new())
+    }
+
+    fn module_unloaded(&mut self, _base: u64) {}
+
+    fn return_address(&mut self, _session: &mut Session, _ev: &EventRecord) -> Option<u64> {
+        None
+    }
+
+    fn detach(&mut self, _pid: u32) {}
+}
+
+
+struct DllLoad {
+    base: u64,
+    name: Option<String>,
+}
+
+
+pub struct EventLoop<H: EventHost> {
+    pub host: H,
+    pub c_dbg: DbgState,
+    dll_load: Vec<DllLoad>,
+}
+
+impl<H: EventHost> EventLoop<H> {
+    pub fn new(host: H) -> EventLoop<H> {
+        EventLoop { host, c_dbg: DbgState::Continue, dll_load: Vec::new() }
+    }
+
+    #[cfg(test)]
+    pub fn replay<I: IntoIterator<Item = EventRecord>>(&mut self, session: &mut Session, events: I) -> DbgState {
+        for ev in events {
+            if self.c_dbg != DbgState::Continue {
+                break;
+            }
+            self.dispatch(session, &ev);
+        }
+        self.c_dbg
+    }
+
+    pub fn dispatch(&mut self, session: &mut Session, ev: &EventRecord) {
+        self.host.event(ev);
+        match &ev.event {
+            DebugEvent::BreakpointHit { address } => self.breakpoint_hit(session, ev, *address),
+            DebugEvent::SingleStep { .. } => self.single_step(session, ev),
+            DebugEvent::Exception { code, address, info, .. } => self.exception(ev, *code, *address, info),
+            DebugEvent::ProcessCreated { image_base } => {
+                print_lg(LevelPrint::Debug, format!("Process created at address: {:#x}", image_base));
+                session.base_addr = *image_base;
+                reloc::check_rebase(session);
+                breakpoint::set_breakpoints(session, self.host.target());
+                self.host.process_created(session, ev);
+            }
+            DebugEvent::Exited { exit_code } => {
+                print_lg(LevelPrint::Debug, format!("Process exited with exit code : {}", exit_code));
+                self.c_dbg = DbgState::NeedStop;
+            }
+            DebugEvent::ThreadCreated { start_address } => print_lg(LevelPrint::Debug, format!("Thread created : {:#x}", start_address)),
+            DebugEvent::ThreadExited { exit_code } => print_lg(LevelPrint::Debug, format!("Thread exited with exit code : {}", exit_code)),
+            DebugEvent::ModuleLoaded { base, path } => {
+                match path {
+                    Some(path) => print_lg(LevelPrint::Debug, format!("Dll at address : {:#x} has been loaded ;{}", base, path)),
+                    None => print_lg(LevelPrint::Debug, format!("Dll at address : {:#x} has been loaded", base)),
+                }
+                self.dll_load.push(DllLoad { base: *base, name: path.clone() });
+                match self.host.module_symbols(*base) {
+                    Ok(export_func) => session.symbols.symbol_file.extend_from_slice(&export_func),
+                    Err(e) => print_lg(LevelPrint::Error, format!("failed to get export function of dll : {e}")),
+                }
+                if session.all.break_iat.iter().any(|b| b.addr == 0) {
+                    let modules: Vec<(u64, String)> = self.dll_load.iter()
+                        .filter_map(|d| d.name.as_deref().map(|name| (d.base, export::dll_stem(name))))
+                        .collect();
+                    breakpoint::set_delay_breakpoints(session, self.host.target(), &modules, *base);
+                }
+                breakpoint::set_iat_breakpoints(session, self.host.target());
+            }
+            DebugEvent::ModuleUnloaded { base } => {
+                match self.dll_load.iter().position(|d| d.base == *base).map(|pos| self.dll_load.remove(pos)) {
+                    Some(DllLoad { name: Some(name), .. }) => print_lg(LevelPrint::Debug, format!("Dll at address : {:#x} has been unloaded ;{}", base, name)),
+                    _ => print_lg(LevelPrint::Debug, format!("Dll at address : {:#x} has been unloaded", base)),
+                }
+                self.host.module_unloaded(*base);
+                session.symbols.symbol_file.retain(|s| !s.src_file.is_dll() || s.src_file.dll_base() != *base);
+            }
+            DebugEvent::Output(dbg_str) => print_lg(LevelPrint::Debug, format!("Debug string output : \"{dbg_str}\"")),
+        }
+    }
+
+    fn breakpoint_hit(&mut self, session: &mut Session, ev: &EventRecord, except_addr: u64) {
+        let base_addr = session.base_addr;
+        if let Some(after_b) = session.all.after_b.iter().find(|a| a.after_b == except_addr) {
+            handle_point::handle_after_b(self.host.target(), *after_b, &mut self.c_dbg, ev);
+        }
+        if let Some(hook_func) = session.all.hook.iter().find(|a| a.target + base_addr == except_addr) {
+            handle_point::handle_hook_func(self.host.target(), base_addr, *hook_func, ev, &mut self.c_dbg);
+            return;
+        }
+        if let Some(pos) = session.all.break_once.iter().position(|s| s.addr + base_addr == except_addr) {
+            let b = session.all.break_once.remove(pos);
+            breakpoint::handle_br_once(session, &mut self.host, ev, except_addr, b.origin_b, &mut self.c_dbg);
+        }
+        if let Some(b) = session.all.break_rva.iter().find(|s| s.addr + base_addr == except_addr).copied() {
+            breakpoint::handle_br(session, &mut self.host, ev, b.addr + base_addr, b.origin_b, &mut self.c_dbg);
+        }
+        if let Some(b) = session.all.break_va.iter().find(|s| s.addr == except_addr).copied() {
+            breakpoint::handle_br(session, &mut self.host, ev, b.addr, b.origin_b, &mut self.c_dbg);
+        }
+        if let Some(b) = session.all.break_ret.iter().find(|v| v.addr + base_addr == except_addr).copied() {
+            breakpoint::set_breakpoint_in_ret_func(session, &mut self.host, ev, except_addr, b);
+        }
+        if let Some(b) = session.all.break_ret_va.iter().find(|v| v.addr == except_addr).copied() {
+            breakpoint::set_breakpoint_in_ret_func(session, &mut self.host, ev, except_addr, b);
+        }
+        breakpoint::set_iat_breakpoints(session, self.host.target());
+    }
+
+    fn single_step(&mut self, session: &mut Session, ev: &EventRecord) {
+        let target = self.host.target();
+        let mut regs = match target.get_registers(ev.tid) {
+            Ok(regs) => regs,
+            Err(e) => {
+                print_lg(LevelPrint::Error, format!("failed to get thread context of thread {} : {e}", ev.tid));
+                return;
+            }
+        };
+        if let Some(name) = watchpoint::watch_hit(session, &RegisterSet::from_registers(&regs, session.get_bitness() == 64)) {
+            print_lg(LevelPrint::Debug, format!("except address {:#x}, there was access to the address {name}", regs.rip));
+            regs.dr6 = 0;
+            if let Err(e) = target.set_registers(ev.tid, &regs) {
+                print_lg(LevelPrint::Error, format!("failed to set thread context of thread {} : {e}", ev.tid));
+            }
+            self.host.stop(session, ev, &mut self.c_dbg);
+        }
+    }
+
+    fn exception(&mut self, ev: &EventRecord, code: u32, except_addr: u64, info: &[u64]) {
+        let c_dbg = &mut self.c_dbg;
+        match code {
+            EXCEPTION_ARRAY_BOUNDS_EXCEEDED =>
+                print_lg(LevelPrint::Error, format!("The code tries to access an invalid index in the table : {:#x}", except_addr)),
+
+            EXCEPTION_DATATYPE_MISALIGNMENT =>
+                print_lg(LevelPrint::Critical1(ev.pid, c_dbg), format!("An alignment problem occurred at address {:#x} and the system does not provide alignment", except_addr)),
+
+            EXCEPTION_FLT_DENORMAL_OPERAND =>
+                print_lg(LevelPrint::Critical1(ev.pid, c_dbg), format!("One of the operands of a floating point operation is too small to be considered a floating point at address {:#x}", except_addr)),
+
+            EXCEPTION_FLT_DIVIDE_BY_ZERO =>
+                print_lg(LevelPrint::Critical1(ev.pid, c_dbg), format!("The thread attempted to divide a floating point value by a floating point divisor of zero at address {:#x}", except_addr)),
+
+            EXCEPTION_FLT_INEXACT_RESULT =>
+                print_lg(LevelPrint::Critical1(ev.pid, c_dbg), format!("The result of a floating point operation cannot be represented exactly as a decimal fraction at address {:#x}", except_addr)),
+
+            EXCEPTION_FLT_INVALID_OPERATION =>
+                print_lg(LevelPrint::Critical1(ev.pid, c_dbg), format!("An error with floating point numbers occurred at address {:#x}", except_addr)),
+
+            EXCEPTION_FLT_OVERFLOW =>
+                print_lg(LevelPrint::Critical1(ev.pid, c_dbg), format!("A floating point operation resulted in a value too large to represent at address {:#x}", except_addr)),
+
+            EXCEPTION_ILLEGAL_INSTRUCTION =>
+                print_lg(LevelPrint::Critical1(ev.pid, c_dbg), format!("bad instruction at address {:#x}", except_addr)),
+
+            EXCEPTION_STACK_OVERFLOW =>
+                print_lg(LevelPrint::Critical1(ev.pid, c_dbg), format!("stack overflow at address {:#x}", except_addr)),
+
+            EXCEPTION_ACCESS_VIOLATION => {
+                let access_type = info.first().copied().unwrap_or_default();
+                let drs = info.get(1).copied().unwrap_or_default();
+                let access_str = match access_type {
+                    0 => "read",
+                    1 => "write",
+                    8 => "execute",
+                    _ => "unknown",
+                };
+                match self.host.target().query_region(drs) {
+                    Ok(region) => {
+                        print_lg(LevelPrint::Critical1(ev.pid, c_dbg), format!("memory access violation for '{access_str}' at address {:#x} caused by instruction at address {:#x}", drs, except_addr));
+                        memory::mem_info::print_mem_info(region);
+                    }
+                    Err(e) => print_lg(LevelPrint::Critical1(ev.pid, c_dbg), format!("Failed to query memory information : {e}")),
+                }
+            }
+            CONTROL_C_EXIT => {
+                print_lg(LevelPrint::Debug, "control C exit");
+                self.host.detach(ev.pid);
+            }
+            _ => {}
+        }
+    }
+}
+
+
+#[cfg(test)]
+pub struct ScriptedHost<T: DebugTarget> {
+    pub target: T,
+    pub stops: Vec<(u32, u64)>,
+}
+
+#[cfg(test)]
+impl<T: DebugTarget> ScriptedHost<T> {
+    pub fn new(target: T) -> ScriptedHost<T> {
+        ScriptedHost { target, stops: Vec::new() }
+    }
+}
+
+#[cfg(test)]
+impl<T: DebugTarget> EventHost for ScriptedHost<T> {
+    fn target(&self) -> &dyn DebugTarget {
+        &self.target
+    }
+
+    fn stop(&mut self, _session: &mut Session, ev: &EventRecord, _c_dbg: &mut DbgState) {
+        let rip = self.target.get_registers(ev.tid).map(|r| r.rip).unwrap_or_default();
+        self.stops.push((ev.tid, rip));
+    }
+
+    // without a stack walker, the breakpoint is expected on the first instruction of the function
+    fn return_address(&mut self, session: &mut Session, ev: &EventRecord) -> Option<u64> {
+        let rsp = self.target.get_registers(ev.tid).ok()?.rsp;
+        crate::dbg::target::read_ptr(&self.target, rsp, session.get_size_of_arch()).ok()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::breakpoint::{Brkpts, IatBrkpt};
+    use crate::command::hook::Hook;
+    use crate::dbg::target::{self, MemTarget, Registers, PROT_EXECUTE_READWRITE, PROT_READWRITE};
+
+    const PID: u32 = 4;
+    const TID: u32 = 8;
+    const DLL_BASE: u64 = 0x7ff800000000;
+    const IMAGE_BASE: u64 = 0x140000000;
+    const STACK: u64 = 0x7ff000;
+
+    fn put(image: &mut [u8], off: usize, bytes: &[u8]) {
+        image[off..off + bytes.len()].copy_from_slice(bytes);
+    }
+
+    // x64 dll exporting Sleep (ordinal 1) at +0x2000 and an ordinal only function (2) at +0x2010
+    fn dll_image() -> Vec<u8> {
+        let mut image = vec![0x90u8; 0x3000];
+        image[..0x2000].fill(0);
+        put(&mut image, 0x3c, &0x80u32.to_le_bytes());
+        put(&mut image, 0x80, b"PE\0\0");
+        put(&mut image, 0x98, &0x20bu16.to_le_bytes());
+        put(&mut image, 0x98 + 108, &1u32.to_le_bytes());
+        put(&mut image, 0x98 + 112, &[0x1000u32.to_le_bytes(), 0x100u32.to_le_bytes()].concat());
+        let dir = [0, 0, 0, 0x1080, 1, 2, 1, 0x1040, 0x1050, 0x1060];
+        put(&mut image, 0x1000, &dir.iter().flat_map(|v: &u32| v.to_le_bytes()).collect::<Vec<u8>>());
+        put(&mut image, 0x1040, &[0x2000u32.to_le_bytes(), 0x2010u32.to_le_bytes()].concat());
+        put(&mut image, 0x1050, &0x1070u32.to_le_bytes());
+        put(&mut image, 0x1060, &0u16.to_le_bytes());
+        put(&mut image, 0x1070, b"Sleep\0");
+        put(&mut image, 0x1080, b"KERNEL32.dll\0");
+        image
+    }
+
+    // mov rax, rcx ; ret at +0x1000, nops up to +0x2000
+    fn main_image() -> Vec<u8> {
+        let mut image = vec![0x90u8; 0x2000];
+        put(&mut image, 0x1000, &[0x48, 0x89, 0xc8, 0xc3]);
+        image
+    }
+
+    fn event_loop() -> EventLoop<ScriptedHost<MemTarget>> {
+        let target = MemTarget::new();
+        target.map_image(IMAGE_BASE, main_image(), PROT_EXECUTE_READWRITE);
+        target.map_image(DLL_BASE, dll_image(), PROT_EXECUTE_READWRITE);
+        target.map(STACK, vec![0; 0x100], PROT_READWRITE);
+        target.add_thread(TID, Registers { rsp: STACK + 0x80, ..Default::default() });
+        EventLoop::new(ScriptedHost::new(target))
+    }
+
+    fn started(session: &mut Session) -> EventLoop<ScriptedHost<MemTarget>> {
+        let mut ev_loop = event_loop();
+        dispatch(&mut ev_loop, session, DebugEvent::ProcessCreated { image_base: IMAGE_BASE });
+        ev_loop
+    }
+
+    fn dispatch(ev_loop: &mut EventLoop<ScriptedHost<MemTarget>>, session: &mut Session, event: DebugEvent) {
+        ev_loop.dispatch(session, &EventRecord::new(PID, TID, event));
+    }
+
+    fn hit(ev_loop: &mut EventLoop<ScriptedHost<MemTarget>>, session: &mut Session, address: u64) {
+        let target = &ev_loop.host.target;
+        let regs = Registers { rip: address + 1, ..target.get_registers(TID).unwrap() };
+        target.set_registers(TID, &regs).unwrap();
+        dispatch(ev_loop, session, DebugEvent::BreakpointHit { address });
+    }
+
+    fn byte(ev_loop: &EventLoop<ScriptedHost<MemTarget>>, addr: u64) -> u8 {
+        target::read_ptr(&ev_loop.host.target, addr, 1).unwrap() as u8
+    }
+
+    fn regs(ev_loop: &EventLoop<ScriptedHost<MemTarget>>) -> Registers {
+        ev_loop.host.target.get_registers(TID).unwrap()
+    }
+
+    #[test]
+    fn breakpoint_rearmed_after_step() {
+        let mut session = Session::default();
+        session.all.break_rva.push(Brkpts::from_addr_no_start(0x1000));
+        let mut ev_loop = started(&mut session);
+        let b_addr = IMAGE_BASE + 0x1000;
+        assert_eq!(byte(&ev_loop, b_addr), 0xcc);
+        assert_eq!(session.all.break_rva[0].origin_b, 0x48);
+
+        hit(&mut ev_loop, &mut session, b_addr);
+        assert_eq!(ev_loop.host.stops, [(TID, b_addr)]);
+        assert_eq!(regs(&ev_loop).rip, b_addr);
+        assert_eq!(byte(&ev_loop, b_addr), 0x48);
+        // the breakpoint moves to the next instruction until the hit one has run
+        assert_eq!(byte(&ev_loop, b_addr + 3), 0xcc);
+
+        hit(&mut ev_loop, &mut session, b_addr + 3);
+        assert_eq!(ev_loop.host.stops.len(), 1);
+        assert_eq!(regs(&ev_loop).rip, b_addr + 3);
+        assert_eq!(byte(&ev_loop, b_addr + 3), 0xc3);
+        assert_eq!(byte(&ev_loop, b_addr), 0xcc);
+
+        // a single step without a debug register hit is not a stop
+        dispatch(&mut ev_loop, &mut session, DebugEvent::SingleStep { address: b_addr + 3 });
+        assert_eq!(ev_loop.host.stops.len(), 1);
+        assert_eq!(ev_loop.c_dbg, DbgState::Continue);
+    }
+
+    #[test]
+    fn ret_breakpoint_stops_at_caller() {
+        let mut session = Session::default();
+        session.all.break_ret.push(Brkpts::from_addr_no_start(0x1000));
+        let mut ev_loop = started(&mut session);
+        let ret_addr = IMAGE_BASE + 0x1800;
+        ev_loop.host.target.write_memory(STACK + 0x80, &ret_addr.to_le_bytes()).unwrap();
+
+        hit(&mut ev_loop, &mut session, IMAGE_BASE + 0x1000);
+        assert!(ev_loop.host.stops.is_empty());
+        assert_eq!(regs(&ev_loop).rip, IMAGE_BASE + 0x1000);
+        assert_eq!(byte(&ev_loop, IMAGE_BASE + 0x1000), 0x48);
+        assert_eq!(byte(&ev_loop, ret_addr), 0xcc);
+        assert_eq!(session.all.break_va.iter().map(|b| (b.addr, b.origin_b)).collect::<Vec<_>>(), [(ret_addr, 0x90)]);
+
+        hit(&mut ev_loop, &mut session, ret_addr);
+        assert_eq!(ev_loop.host.stops, [(TID, ret_addr)]);
+        assert_eq!(byte(&ev_loop, ret_addr), 0x90);
+    }
+
+    #[test]
+    fn hook_redirects_without_stopping() {
+        let mut session = Session::default();
+        session.all.hook.push(Hook { target: 0x1000, replacen: 0x1100, origin_byte: 0 });
+        let mut ev_loop = started(&mut session);
+        assert_eq!(byte(&ev_loop, IMAGE_BASE + 0x1000), 0xcc);
+
+        hit(&mut ev_loop, &mut session, IMAGE_BASE + 0x1000);
+        assert_eq!(regs(&ev_loop).rip, IMAGE_BASE + 0x1100);
+        assert!(ev_loop.host.stops.is_empty());
+        // the hook stays armed for the next call
+        assert_eq!(byte(&ev_loop, IMAGE_BASE + 0x1000), 0xcc);
+    }
+
+    #[test]
+    fn watchpoint_hit_from_dr6() {
+        let mut session = Session::default();
+        let mut ev_loop = started(&mut session);
+        let target = &ev_loop.host.target;
+        let watched = IMAGE_BASE + 0x1f00;
+        target.set_registers(TID, &Registers { rip: IMAGE_BASE + 0x1004, dr1: watched, dr6: 0b10, ..regs(&ev_loop) }).unwrap();
+
+        dispatch(&mut ev_loop, &mut session, DebugEvent::SingleStep { address: IMAGE_BASE + 0x1004 });
+        assert_eq!(ev_loop.host.stops, [(TID, IMAGE_BASE + 0x1004)]);
+        assert_eq!(regs(&ev_loop).dr6, 0);
+        assert_eq!(regs(&ev_loop).dr1, watched);
+
+        dispatch(&mut ev_loop, &mut session, DebugEvent::SingleStep { address: IMAGE_BASE + 0x1005 });
+        assert_eq!(ev_loop.host.stops.len(), 1);
+    }
+
+    #[test]
+    fn delay_import_armed_on_load() {
+        let mut session = Session::default();
+        for label in ["kernel32!Sleep", "kernel32!#2", "user32!MessageBoxA"] {
+            session.all.break_iat.push(IatBrkpt { iat_rva: 0x3000, thunk_rva: 0x1234, label: label.to_string(), addr: 0 });
+        }
+        let mut ev_loop = event_loop();
+        dispatch(&mut ev_loop, &mut session, DebugEvent::ModuleLoaded { base: DLL_BASE, path: Some("C:\\Windows\\System32\\KERNEL32.DLL".to_string()) });
+
+        let armed: Vec<u64> = session.all.break_iat.iter().map(|b| b.addr).collect();
+        assert_eq!(armed, [DLL_BASE + 0x2000, DLL_BASE + 0x2010, 0]);
+        assert_eq!(byte(&ev_loop, DLL_BASE + 0x2000), 0xcc);
+        assert_eq!(byte(&ev_loop, DLL_BASE + 0x2010), 0xcc);
+
+        // the first call, made by the helper before the IAT slot is read again, stops
+        hit(&mut ev_loop, &mut session, DLL_BASE + 0x2000);
+        assert_eq!(ev_loop.host.stops, [(TID, DLL_BASE + 0x2000)]);
+        assert_eq!(byte(&ev_loop, DLL_BASE + 0x2000), 0x90);
+    }
+}
@@ -0,0 +1,76 @@
+use crate::command::watchpoint::Watchpts;
+use crate::dbg::gdbserver::{set_thread_watchpoints, Inferior};
+use crate::dbg::target::gdb::GdbStop;
+use crate::dbg::target::ptrace::{PtraceStop, PtraceTarget};
+use crate::dbg::target::DebugTarget;
+use crate::ut::fmt::{print_lg, LevelPrint};
+
+
+pub struct PtraceInferior {
+    target: PtraceTarget,
+    base_addr: u64,
+}
+
+
+impl PtraceInferior {
+    pub fn spawn(file: &str, arg: &str) -> Result<(Box<dyn Inferior>, GdbStop), anyhow::Error> {
+        let args: Vec<&str> = arg.split_whitespace().collect();
+        let target = PtraceTarget::spawn(file, &args)?;
+        let base_addr = target.image_base(file).unwrap_or_else(|e| {
+            print_lg(LevelPrint::Error, format!("failed to find the base address : {e}"));
+            0
+        });
+        let stop = GdbStop::Signal { sig: libc::SIGTRAP as u8, tid: Some(target.pid), watch: None };
+        Ok((Box::new(PtraceInferior { target, base_addr }), stop))
+    }
+}
+
+
+impl Inferior for PtraceInferior {
+    fn target(&self) -> &dyn DebugTarget {
+        &self.target
+    }
+
+    fn pid(&self) -> u32 {
+        self.target.pid
+    }
+
+    fn base_addr(&self) -> u64 {
+        self.base_addr
+    }
+
+    fn resume(&self, tid: u32, step: bool, sig: u8, watchpts: &[Watchpts]) -> Result<GdbStop, anyhow::Error> {
+        match step {
+            true => self.target.step(tid, sig as i32)?,
+            false => self.target.cont(tid, sig as i32)?,
+        }
+        loop {
+            match self.target.wait()? {
+                PtraceStop::Exited(code) => return Ok(GdbStop::Exited(code as u8)),
+                PtraceStop::Killed(sig) => return Ok(GdbStop::Killed(sig as u8)),
+                PtraceStop::ThreadExited(t) => print_lg(LevelPrint::Debug, format!("thread {t} exited")),
+                PtraceStop::Clone(t, new_tid) => {
+                    print_lg(LevelPrint::Debug, format!("new thread {new_tid}"));
+                    self.target.cont(t, 0)?;
+                }
+                PtraceStop::Signal(t, libc::SIGSTOP) if t != self.target.pid => {
+                    set_thread_watchpoints(&self.target, t, self.base_addr, watchpts);
+                    self.target.cont(t, 0)?;
+                }
+                PtraceStop::Signal(t, sig) => return Ok(GdbStop::Signal { sig: sig as u8, tid: Some(t), watch: None }),
+                PtraceStop::Trap(t) => return Ok(GdbStop::Signal { sig: libc::SIGTRAP as u8, tid: Some(t), watch: None }),
+            }
+        }
+    }
+
+    fn kill(&self) {
+        self.target.kill();
+        let _ = self.target.wait();
+    }
+
+    fn detach(&self) {
+        for tid in self.target.threads().unwrap_or_default() {
+            unsafe { libc::ptrace(libc::PTRACE_DETACH, tid as libc::pid_t, 0, 0) };
+        }
+    }
+}
@@ -0,0 +1,694 @@
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(windows)]
+mod win;
+
+use crate::command::breakpoint::Brkpts;
+use crate::command::watchpoint::{CheckType, FlagTypeMem, Watchpts};
+use crate::dbg::memory::{breakpoint, watchpoint};
+use crate::dbg::regs::{RegisterSet, Value};
+use crate::dbg::target::gdb::{decode_hex, encode_hex, gpr_layout, values_to_regs, GdbPoint, GdbStop, RspConnection};
+use crate::dbg::target::{self, DebugTarget, Registers};
+use crate::session::Session;
+use crate::ut::fmt::{print_lg, LevelPrint};
+use anyhow::anyhow;
+use std::net::TcpListener;
+
+
+const GDB_REGS64: [&str; 24] = [
+    "rax", "rbx", "rcx", "rdx", "rsi", "rdi", "rbp", "rsp", "r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15",
+    "rip", "eflags", "cs", "ss", "ds", "es", "fs", "gs",
+];
+
+const GDB_REGS32: [&str; 16] = [
+    "eax", "ecx", "edx", "ebx", "esp", "ebp", "esi", "edi", "eip", "eflags", "cs", "ss", "ds", "es", "fs", "gs",
+];
+
+const SIGTRAP: u8 = 5;
+const PACKET_SIZE: usize = 0x4000;
+
+
+pub trait Inferior {
+    fn target(&self) -> &dyn DebugTarget;
+    fn pid(&self) -> u32;
+    fn base_addr(&self) -> u64;
+    fn resume(&self, tid: u32, step: bool, sig: u8, watchpts: &[Watchpts]) -> Result<GdbStop, anyhow::Error>;
+    fn kill(&self);
+    fn detach(&self);
+}
+
+
+struct GdbServer<'a> {
+    session: &'a mut Session,
+    conn: RspConnection,
+    inferior: Box<dyn Inferior>,
+    is64: bool,
+    g_tid: u32,
+    c_tid: u32,
+    last_stop: String,
+    done: bool,
+}
+
+
+pub fn run(session: &mut Session, addr: &str) {
+    let file = match session.all.file.clone() {
+        Some(file) => file,
+        None => {
+            print_lg(LevelPrint::ErrorO, "Please enter a file path");
+            return;
+        }
+    };
+    let arg = session.all.arg.clone().unwrap_or_default();
+    #[cfg(windows)]
+    let spawned = win::WinInferior::spawn(&file, &arg);
+    #[cfg(target_os = "linux")]
+    let spawned = linux::PtraceInferior::spawn(&file, &arg);
+    let (inferior, first_stop) = match spawned {
+        Ok(spawned) => spawned,
+        Err(e) => {
+            print_lg(LevelPrint::ErrorO, format!("failed to start '{file}' : {e}"));
+            return;
+        }
+    };
+
+    let addr = listen_addr(addr);
+    let listener = match TcpListener::bind(&addr) {
+        Ok(listener) => listener,
+        Err(e) => {
+            print_lg(LevelPrint::ErrorO, format!("failed to listen on {addr} : {e}"));
+            inferior.kill();
+            return;
+        }
+    };
+    print_lg(LevelPrint::DebugO, format!("process {} created, listening on {addr}", inferior.pid()));
+    let conn = match listener.accept().map_err(anyhow::Error::from).and_then(|(stream, peer)| {
+        print_lg(LevelPrint::DebugO, format!("remote debugging from {peer}"));
+        RspConnection::new(stream)
+    }) {
+        Ok(conn) => conn,
+        Err(e) => {
+            print_lg(LevelPrint::ErrorO, format!("failed to accept a connection : {e}"));
+            inferior.kill();
+            return;
+        }
+    };
+
+    session.base_addr = inferior.base_addr();
+    let mut server = GdbServer {
+        is64: session.get_bitness() == 64,
+        session,
+        conn,
+        g_tid: 0,
+        c_tid: 0,
+        last_stop: String::new(),
+        done: false,
+        inferior,
+    };
+    init(server.session, server.inferior.target());
+    server.last_stop = server.stop_reply(first_stop, false);
+    server.serve();
+    server.session.base_addr = 0;
+}
+
+
+fn listen_addr(addr: &str) -> String {
+    if addr.starts_with(':') { format!("127.0.0.1{addr}") } else { addr.to_string() }
+}
+
+
+fn init(session: &mut Session, target: &dyn DebugTarget) {
+    for b in &mut session.all.break_rva {
+        if let Err(e) = breakpoint::set_breakpoint(target, b.addr + session.base_addr, &mut b.origin_b) {
+            print_lg(LevelPrint::Error, e);
+        }
+    }
+    for b in &mut session.all.break_va {
+        if let Err(e) = breakpoint::set_breakpoint(target, b.addr, &mut b.origin_b) {
+            print_lg(LevelPrint::Error, e);
+        }
+    }
+    apply_watchpoints(target, session.base_addr, &session.all.watchpts);
+}
+
+fn apply_watchpoints(target: &dyn DebugTarget, base_addr: u64, watchpts: &[Watchpts]) {
+    for tid in target.threads().unwrap_or_default() {
+        set_thread_watchpoints(target, tid, base_addr, watchpts);
+    }
+}
+
+pub(super) fn set_thread_watchpoints(target: &dyn DebugTarget, tid: u32, base_addr: u64, watchpts: &[Watchpts]) {
+    let mut regs = match target.get_registers(tid) {
+        Ok(regs) => regs,
+        Err(e) => {
+            print_lg(LevelPrint::Error, format!("failed to get registers for set watchpoint : {e}"));
+            return;
+        }
+    };
+    regs.dr7 = 0;
+    for (i, watch) in watchpts.iter().enumerate().take(4) {
+        let addr = match watch.flag_type_mem {
+            FlagTypeMem::MemoryStatic if watch.register.is_empty() => watch.offset as u64 + base_addr,
+            FlagTypeMem::VirtualAddr if watch.register.is_empty() => watch.offset as u64,
+            _ => continue,
+        };
+        match i {
+            0 => regs.dr0 = addr,
+            1 => regs.dr1 = addr,
+            2 => regs.dr2 = addr,
+            _ => regs.dr3 = addr,
+        }
+        watchpoint::set_dr7::<u64>(&mut regs.dr7, i, watch);
+    }
+    if let Err(e) = target.set_registers(tid, &regs) {
+        print_lg(LevelPrint::Error, format!("failed to set registers for set watchpoint : {e}"));
+    }
+}
+
+
+fn find_brkpt(session: &mut Session, addr: u64) -> Option<&mut Brkpts> {
+    let base_addr = session.base_addr;
+    let all = &mut session.all;
+    match all.break_rva.iter().position(|b| b.addr + base_addr == addr) {
+        Some(i) => Some(&mut all.break_rva[i]),
+        None => all.break_va.iter_mut().find(|b| b.addr == addr),
+    }
+}
+
+fn brkpts_in(session: &Session, addr: u64, len: usize) -> Vec<(u64, u8)> {
+    session.all.break_rva.iter().map(|b| (b.addr + session.base_addr, b.origin_b))
+        .chain(session.all.break_va.iter().map(|b| (b.addr, b.origin_b)))
+        .filter(|(b_addr, _)| b_addr.checked_sub(addr).is_some_and(|off| off < len as u64))
+        .collect()
+}
+
+fn watch_kind(point: GdbPoint) -> Vec<CheckType> {
+    match point {
+        GdbPoint::Write => vec![CheckType::W],
+        GdbPoint::Read | GdbPoint::Access => vec![CheckType::R, CheckType::W],
+        _ => vec![CheckType::X],
+    }
+}
+
+fn value_bytes(value: Value, size: usize) -> Vec<u8> {
+    let mut bytes = match value {
+        Value::U8(v) => v.to_le_bytes().to_vec(),
+        Value::U16(v) => v.to_le_bytes().to_vec(),
+        Value::U32(v) => v.to_le_bytes().to_vec(),
+        Value::U64(v) => v.to_le_bytes().to_vec(),
+        Value::U128(_) | Value::Un => Vec::new(),
+    };
+    bytes.resize(size, 0);
+    bytes
+}
+
+fn parse_hex<T: TryFrom<u64>>(s: &str) -> Result<T, anyhow::Error> {
+    let value = u64::from_str_radix(s, 16).map_err(|_| anyhow!("invalid number : '{s}'"))?;
+    T::try_from(value).map_err(|_| anyhow!("number out of range : '{s}'"))
+}
+
+fn parse_tid(s: &str) -> Result<u32, anyhow::Error> {
+    match s {
+        "-1" | "0" => Ok(0),
+        _ => parse_hex(s.rsplit('.').next().unwrap_or(s)),
+    }
+}
+
+
+impl GdbServer<'_> {
+    fn target(&self) -> &dyn DebugTarget {
+        self.inferior.target()
+    }
+
+    fn serve(&mut self) {
+        while !self.done {
+            let packet = match self.conn.recv_packet() {
+                Ok(packet) => packet,
+                Err(e) => {
+                    print_lg(LevelPrint::Error, format!("the connection was closed : {e}"));
+                    self.inferior.kill();
+                    return;
+                }
+            };
+            let reply = match self.handle_packet(&packet) {
+                Ok(reply) => reply,
+                Err(e) => {
+                    print_lg(LevelPrint::Error, format!("'{packet}' : {e}"));
+                    "E01".to_string()
+                }
+            };
+            if packet == "k" {
+                return;
+            }
+            if let Err(e) = self.conn.send_packet(&reply) {
+                print_lg(LevelPrint::Error, format!("the connection was closed : {e}"));
+                self.inferior.kill();
+                return;
+            }
+        }
+    }
+
+    fn handle_packet(&mut self, packet: &str) -> Result<String, anyhow::Error> {
+        let (cmd, args) = packet.split_at_checked(1).unwrap_or(("", ""));
+        Ok(match cmd {
+            "?" => self.last_stop.clone(),
+            "q" => self.handle_query(args)?,
+            "v" => self.handle_v(args)?,
+            "H" => {
+                let tid = parse_tid(args.get(1..).unwrap_or_default())?;
+                match args.chars().next() {
+                    Some('g') => self.g_tid = tid,
+                    Some('c') => self.c_tid = tid,
+                    _ => return Ok(String::new()),
+                }
+                "OK".to_string()
+            }
+            "T" => match self.target().threads()?.contains(&parse_tid(args)?) {
+                true => "OK".to_string(),
+                false => "E01".to_string(),
+            },
+            "g" => {
+                let regs = RegisterSet::from_registers(&self.target().get_registers(self.reg_tid()?)?, self.is64);
+                let mut bytes = Vec::new();
+                for (i, (_, size)) in gpr_layout(self.is64).iter().enumerate() {
+                    bytes.extend(value_bytes(regs.value(self.reg_name(i)), *size));
+                }
+                encode_hex(&bytes)
+            }
+            "G" => {
+                let tid = self.reg_tid()?;
+                let bytes = decode_hex(args)?;
+                let mut values = Vec::new();
+                let mut off = 0;
+                for (_, size) in gpr_layout(self.is64) {
+                    let mut buf = [0u8; 8];
+                    let raw = bytes.get(off..off + size).ok_or_else(|| anyhow!("the register packet is too short"))?;
+                    buf[..*size].copy_from_slice(raw);
+                    values.push(u64::from_le_bytes(buf));
+                    off += size;
+                }
+                self.write_values(tid, &values)?;
+                "OK".to_string()
+            }
+            "p" => {
+                let index = parse_hex::<usize>(args)?;
+                match gpr_layout(self.is64).get(index) {
+                    Some((_, size)) => {
+                        let regs = RegisterSet::from_registers(&self.target().get_registers(self.reg_tid()?)?, self.is64);
+                        encode_hex(&value_bytes(regs.value(self.reg_name(index)), *size))
+                    }
+                    None => "E00".to_string(),
+                }
+            }
+            "P" => {
+                let (index, value) = args.split_once('=').ok_or_else(|| anyhow!("invalid register packet"))?;
+                let index = parse_hex::<usize>(index)?;
+                if index >= gpr_layout(self.is64).len() {
+                    return Ok("E00".to_string());
+                }
+                let tid = self.reg_tid()?;
+                let regs = RegisterSet::from_registers(&self.target().get_registers(tid)?, self.is64);
+                let mut values: Vec<u64> = (0..gpr_layout(self.is64).len())
+                    .map(|i| u64::from_le_bytes(value_bytes(regs.value(self.reg_name(i)), 8).try_into().unwrap()))
+                    .collect();
+                let mut buf = decode_hex(value)?;
+                buf.resize(8, 0);
+                values[index] = u64::from_le_bytes(buf.try_into().unwrap());
+                self.write_values(tid, &values)?;
+                "OK".to_string()
+            }
+            "m" => {
+                let (addr, len) = args.split_once(',').ok_or_else(|| anyhow!("invalid memory packet"))?;
+                let (addr, len) = (parse_hex::<u64>(addr)?, parse_hex::<usize>(len)?);
+                // the reply is hex encoded, two characters per byte
+                let mut buf = vec![0u8; len.min(PACKET_SIZE / 2)];
+                match self.target().read_memory(addr, &mut buf) {
+                    Ok(read) if read > 0 => {
+                        buf.truncate(read);
+                        for (b_addr, origin_b) in brkpts_in(self.session, addr, read) {
+                            buf[(b_addr - addr) as usize] = origin_b;
+                        }
+                        encode_hex(&buf)
+                    }
+                    _ => "E14".to_string(),
+                }
+            }
+            "M" => {
+                let (location, data) = args.split_once(':').ok_or_else(|| anyhow!("invalid memory packet"))?;
+                let addr = parse_hex::<u64>(location.split(',').next().unwrap_or_default())?;
+                let mut data = decode_hex(data)?;
+                for (b_addr, _) in brkpts_in(self.session, addr, data.len()) {
+                    let off = (b_addr - addr) as usize;
+                    if let Some(b) = find_brkpt(self.session, b_addr) {
+                        b.origin_b = data[off];
+                    }
+                    data[off] = 0xcc;
+                }
+                target::write_unprotected(self.target(), addr, &data)?;
+                "OK".to_string()
+            }
+            "Z" | "z" => self.handle_point(cmd == "Z", args)?,
+            "c" | "s" | "C" | "S" => {
+                let sig = match cmd {
+                    "C" | "S" => parse_hex::<u8>(args.split(';').next().unwrap_or_default())?,
+                    _ => 0,
+                };
+                self.resume(self.c_tid, cmd == "s" || cmd == "S", sig)?
+            }
+            "D" => {
+                self.uninit();
+                self.inferior.detach();
+                self.done = true;
+                "OK".to_string()
+            }
+            "k" => {
+                self.inferior.kill();
+                self.done = true;
+                "OK".to_string()
+            }
+            _ => String::new(),
+        })
+    }
+
+    fn handle_query(&mut self, args: &str) -> Result<String, anyhow::Error> {
+        Ok(match args {
+            _ if args.starts_with("Supported") => format!("PacketSize={:x};swbreak+;hwbreak+;qXfer:features:read+", PACKET_SIZE),
+            _ if args.starts_with("Xfer:features:read:target.xml:") => {
+                let (off, len) = args.rsplit(':').next().unwrap_or_default().split_once(',').ok_or_else(|| anyhow!("invalid qXfer packet"))?;
+                let xml = format!(
+                    "<?xml version=\"1.0\"?><!DOCTYPE target SYSTEM \"gdb-target.dtd\"><target><architecture>{}</architecture></target>",
+                    if self.is64 { "i386:x86-64" } else { "i386" }
+                );
+                let off = parse_hex::<usize>(off)?.min(xml.len());
+                let end = (off + parse_hex::<usize>(len)?).min(xml.len());
+                format!("{}{}", if end == xml.len() { 'l' } else { 'm' }, &xml[off..end])
+            }
+            _ if args.starts_with("Xfer:") => String::new(),
+            "fThreadInfo" => {
+                let threads: Vec<String> = self.target().threads()?.iter().map(|t| format!("{:x}", t)).collect();
+                format!("m{}", threads.join(","))
+            }
+            "sThreadInfo" => "l".to_string(),
+            "C" => format!("QC{:x}", self.current_tid()),
+            "Attached" => "0".to_string(),
+            "Symbol::" => "OK".to_string(),
+            _ => String::new(),
+        })
+    }
+
+    fn handle_v(&mut self, args: &str) -> Result<String, anyhow::Error> {
+        if args == "Cont?" {
+            return Ok("vCont;c;C;s;S".to_string());
+        }
+        if let Some(actions) = args.strip_prefix("Cont;") {
+            let mut resume = None;
+            for action in actions.split(';') {
+                let (action, tid) = match action.split_once(':') {
+                    Some((action, tid)) => (action, parse_tid(tid)?),
+                    None => (action, 0),
+                };
+                let (kind, sig) = action.split_at_checked(1).unwrap_or((action, ""));
+                let sig = if sig.is_empty() { 0 } else { parse_hex::<u8>(sig)? };
+                match kind {
+                    "s" | "S" => {
+                        resume = Some((tid, true, sig));
+                        break;
+                    }
+                    "c" | "C" if resume.is_none() => resume = Some((tid, false, sig)),
+                    _ => {}
+                }
+            }
+            return match resume {
+                Some((tid, step, sig)) => self.resume(tid, step, sig),
+                None => Ok("E01".to_string()),
+            };
+        }
+        if args.starts_with("Kill") {
+            self.inferior.kill();
+            self.done = true;
+            return Ok("OK".to_string());
+        }
+        Ok(String::new())
+    }
+
+    fn handle_point(&mut self, insert: bool, args: &str) -> Result<String, anyhow::Error> {
+        let mut fields = args.split([',', ';']);
+        let kind = match fields.next() {
+            Some("0") => GdbPoint::Software,
+            Some("1") => GdbPoint::Hardware,
+            Some("2") => GdbPoint::Write,
+            Some("3") => GdbPoint::Read,
+            Some("4") => GdbPoint::Access,
+            _ => return Ok(String::new()),
+        };
+        let addr = parse_hex::<u64>(fields.next().unwrap_or_default())?;
+        let len = parse_hex::<usize>(fields.next().unwrap_or_default())?;
+
+        if kind == GdbPoint::Software {
+            let base_addr = self.session.base_addr;
+            match (insert, find_brkpt(self.session, addr).map(|b| b.origin_b)) {
+                (true, Some(_)) => {}
+                (true, None) => {
+                    let mut b = Brkpts::from_addr_no_start(addr);
+                    breakpoint::set_breakpoint(self.target(), addr, &mut b.origin_b).map_err(|e| anyhow!(e))?;
+                    self.session.all.break_va.push(b);
+                }
+                (false, Some(origin_b)) => {
+                    breakpoint::restore_byte_of_brkpt(self.target(), addr, origin_b);
+                    self.session.all.break_va.retain(|b| b.addr != addr);
+                    self.session.all.break_rva.retain(|b| b.addr + base_addr != addr);
+                }
+                (false, None) => {}
+            }
+            return Ok("OK".to_string());
+        }
+
+        let all = &mut self.session.all;
+        let check_type = watch_kind(kind);
+        let position = all.watchpts.iter().position(|w| {
+            w.flag_type_mem == FlagTypeMem::VirtualAddr && w.register.is_empty() && w.offset as u64 == addr && w.check_type == check_type
+        });
+        match (insert, position) {
+            (true, None) => {
+                if all.watchpts.len() >= 4 {
+                    return Ok("E0e".to_string());
+                }
+                all.watchpts.push(Watchpts {
+                    offset: addr as i64,
+                    flag_type_mem: FlagTypeMem::VirtualAddr,
+                    check_type,
+                    memory_size: len.max(1),
+                    register: String::new(),
+                });
+            }
+            (false, Some(i)) => {
+                all.watchpts.remove(i);
+            }
+            _ => return Ok("OK".to_string()),
+        }
+        apply_watchpoints(self.inferior.target(), self.session.base_addr, &self.session.all.watchpts);
+        Ok("OK".to_string())
+    }
+
+    fn uninit(&self) {
+        for (b_addr, origin_b) in brkpts_in(self.session, 0, usize::MAX) {
+            breakpoint::restore_byte_of_brkpt(self.target(), b_addr, origin_b);
+        }
+        apply_watchpoints(self.target(), self.session.base_addr, &[]);
+    }
+
+
+    fn current_tid(&self) -> u32 {
+        self.last_stop.split(';')
+            .find_map(|f| f.strip_prefix("thread:"))
+            .and_then(|t| u32::from_str_radix(t, 16).ok())
+            .unwrap_or_else(|| self.inferior.pid())
+    }
+
+    fn reg_tid(&self) -> Result<u32, anyhow::Error> {
+        Ok(if self.g_tid == 0 { self.current_tid() } else { self.g_tid })
+    }
+
+    fn reg_name(&self, index: usize) -> &'static str {
+        if self.is64 { GDB_REGS64[index] } else { GDB_REGS32[index] }
+    }
+
+    fn write_values(&self, tid: u32, values: &[u64]) -> Result<(), anyhow::Error> {
+        let old = self.target().get_registers(tid)?;
+        let regs = Registers {
+            dr0: old.dr0, dr1: old.dr1, dr2: old.dr2, dr3: old.dr3, dr6: old.dr6, dr7: old.dr7,
+            ..values_to_regs(self.is64, values)
+        };
+        self.target().set_registers(tid, &regs)
+    }
+
+
+    fn resume(&mut self, tid: u32, step: bool, sig: u8) -> Result<String, anyhow::Error> {
+        let tid = if tid == 0 { self.current_tid() } else { tid };
+        let rip = self.target().get_registers(tid)?.rip;
+        let (stop, stepped) = match find_brkpt(self.session, rip).map(|b| b.origin_b) {
+            Some(origin_b) => {
+                breakpoint::restore_byte_of_brkpt(self.target(), rip, origin_b);
+                let stop = self.inferior.resume(tid, true, sig, &self.session.all.watchpts);
+                if let Some(b) = find_brkpt(self.session, rip) {
+                    if let Err(e) = breakpoint::set_breakpoint(self.inferior.target(), rip, &mut b.origin_b) {
+                        print_lg(LevelPrint::Error, e);
+                    }
+                }
+                match stop? {
+                    GdbStop::Signal { sig: SIGTRAP, tid: Some(t), .. } if t == tid && !step && !self.is_watch_hit(tid) => (self.inferior.resume(tid, false, 0, &self.session.all.watchpts)?, false),
+                    stop => (stop, true),
+                }
+            }
+            None => (self.inferior.resume(tid, step, sig, &self.session.all.watchpts)?, step),
+        };
+        self.g_tid = 0;
+        self.last_stop = self.stop_reply(stop, stepped);
+        if self.last_stop.starts_with('W') || self.last_stop.starts_with('X') {
+            self.done = true;
+        }
+        Ok(self.last_stop.clone())
+    }
+
+    fn is_watch_hit(&self, tid: u32) -> bool {
+        self.target().get_registers(tid).is_ok_and(|r| r.dr6 & 0xf != 0)
+    }
+
+    fn stop_reply(&mut self, stop: GdbStop, stepped: bool) -> String {
+        let (sig, tid) = match stop {
+            GdbStop::Exited(code) => return format!("W{:02x}", code),
+            GdbStop::Killed(sig) => return format!("X{:02x}", sig),
+            GdbStop::Signal { sig, tid, .. } => (sig, tid.unwrap_or_else(|| self.inferior.pid())),
+        };
+        let mut reply = format!("T{:02x}thread:{:x};", sig, tid);
+        if sig != SIGTRAP {
+            return reply;
+        }
+        let mut regs = match self.target().get_registers(tid) {
+            Ok(regs) => regs,
+            Err(e) => {
+                print_lg(LevelPrint::Error, e);
+                return reply;
+            }
+        };
+        if regs.dr6 & 0xf != 0 {
+            let i = (regs.dr6 & 0xf).trailing_zeros() as usize;
+            let addr = [regs.dr0, regs.dr1, regs.dr2, regs.dr3][i];
+            let kind = self.session.all.watchpts.get(i).map(|w| w.check_type.clone()).unwrap_or_default();
+            regs.dr6 = 0;
+            if let Err(e) = self.target().set_registers(tid, &regs) {
+                print_lg(LevelPrint::Error, e);
+            }
+            match (kind.contains(&CheckType::X), kind.contains(&CheckType::R)) {
+                (true, _) => reply.push_str("hwbreak:;"),
+                (false, true) => reply.push_str(&format!("awatch:{:x};", addr)),
+                (false, false) => reply.push_str(&format!("watch:{:x};", addr)),
+            }
+        } else if !stepped && find_brkpt(self.session, regs.rip.wrapping_sub(1)).is_some() {
+            regs.rip -= 1;
+            if let Err(e) = self.target().set_registers(tid, &regs) {
+                print_lg(LevelPrint::Error, e);
+            }
+            reply.push_str("swbreak:;");
+        }
+        reply
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbg::target::{MemTarget, PROT_EXECUTE_READWRITE};
+    use std::net::TcpStream;
+    use std::thread;
+
+    struct MemInferior(MemTarget);
+
+    impl Inferior for MemInferior {
+        fn target(&self) -> &dyn DebugTarget {
+            &self.0
+        }
+
+        fn pid(&self) -> u32 {
+            1
+        }
+
+        fn base_addr(&self) -> u64 {
+            0
+        }
+
+        fn resume(&self, tid: u32, _step: bool, _sig: u8, _watchpts: &[Watchpts]) -> Result<GdbStop, anyhow::Error> {
+            Ok(GdbStop::Signal { sig: SIGTRAP, tid: Some(tid), watch: None })
+        }
+
+        fn kill(&self) {}
+
+        fn detach(&self) {}
+    }
+
+    fn serve(packets: &'static [&'static str]) -> Vec<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let conn = RspConnection::new(TcpStream::connect(addr).unwrap()).unwrap();
+            let mut replies = Vec::new();
+            for packet in packets {
+                conn.send_packet(packet).unwrap();
+                if *packet != "k" {
+                    replies.push(conn.recv_packet().unwrap());
+                }
+            }
+            replies
+        });
+
+        let target = MemTarget::new();
+        let mut code = vec![0x90u8; 0x10000];
+        code[2] = 0xcc;
+        target.map(0x1000, code, PROT_EXECUTE_READWRITE);
+        target.add_thread(1, Registers { rip: 0x1000, ..Default::default() });
+        let mut session = Session::default();
+        session.all.break_va.push(Brkpts { origin_b: 0x55, ..Brkpts::from_addr_no_start(0x1002) });
+
+        let (stream, _) = listener.accept().unwrap();
+        let mut server = GdbServer {
+            session: &mut session,
+            conn: RspConnection::new(stream).unwrap(),
+            inferior: Box::new(MemInferior(target)),
+            is64: true,
+            g_tid: 0,
+            c_tid: 0,
+            last_stop: "T05thread:1;".to_string(),
+            done: false,
+        };
+        server.serve();
+        client.join().unwrap()
+    }
+
+    #[test]
+    fn listens_on_loopback_by_default() {
+        assert_eq!(listen_addr(":1234"), "127.0.0.1:1234");
+        assert_eq!(listen_addr("0.0.0.0:1234"), "0.0.0.0:1234");
+    }
+
+    #[test]
+    fn memory_packets() {
+        let replies = serve(&["qSupported:swbreak+", "?", "m1000,4", "m1000,ffffffffffff", "mffffffffffffff00,200", "M1002,01:77", "m1002,1", "k"]);
+        assert!(replies[0].starts_with("PacketSize=4000;"));
+        assert_eq!(replies[1], "T05thread:1;");
+        assert_eq!(replies[2], "90905590");
+        assert_eq!(replies[3].len(), PACKET_SIZE);
+        assert_eq!(replies[4], "E14");
+        assert_eq!(replies[5], "OK");
+        assert_eq!(replies[6], "77");
+    }
+
+    #[test]
+    fn breakpoint_range_does_not_overflow() {
+        let mut session = Session::default();
+        session.all.break_va.push(Brkpts::from_addr_no_start(0x10));
+        session.all.break_va.push(Brkpts::from_addr_no_start(u64::MAX - 1));
+        assert_eq!(brkpts_in(&session, u64::MAX - 2, 0x10).len(), 1);
+        assert_eq!(brkpts_in(&session, 0, usize::MAX).len(), 2);
+        assert!(brkpts_in(&session, 0x11, 0x10).is_empty());
+    }
+}
@@ -0,0 +1,147 @@
+use crate::command::watchpoint::Watchpts;
+use crate::dbg::gdbserver::{set_thread_watchpoints, Inferior};
+use crate::dbg::target::gdb::GdbStop;
+use crate::dbg::target::{DebugTarget, WinTarget};
+use crate::dbg::event::{STATUS_WX86_BREAKPOINT, STATUS_WX86_SINGLE_STEP};
+use crate::ut::fmt::{print_lg, LevelPrint};
+use anyhow::anyhow;
+use std::cell::Cell;
+use std::{io, mem, ptr};
+use winapi::um::debugapi::{ContinueDebugEvent, DebugActiveProcessStop, WaitForDebugEventEx};
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::minwinbase::*;
+use winapi::um::processthreadsapi::{CreateProcessA, TerminateProcess, PROCESS_INFORMATION, STARTUPINFOA};
+use winapi::um::winbase::{DEBUG_ONLY_THIS_PROCESS, INFINITE};
+use winapi::um::winnt::{DBG_CONTINUE, DBG_EXCEPTION_NOT_HANDLED};
+
+
+pub struct WinInferior {
+    target: WinTarget,
+    pid: u32,
+    event_tid: Cell<u32>,
+    base_addr: Cell<u64>,
+}
+
+
+fn exception_to_signal(code: u32) -> u8 {
+    match code {
+        EXCEPTION_BREAKPOINT | STATUS_WX86_BREAKPOINT | EXCEPTION_SINGLE_STEP | STATUS_WX86_SINGLE_STEP => 5,
+        EXCEPTION_ILLEGAL_INSTRUCTION | EXCEPTION_PRIV_INSTRUCTION => 4,
+        EXCEPTION_INT_DIVIDE_BY_ZERO | EXCEPTION_INT_OVERFLOW | EXCEPTION_FLT_DIVIDE_BY_ZERO | EXCEPTION_FLT_OVERFLOW
+        | EXCEPTION_FLT_UNDERFLOW | EXCEPTION_FLT_INVALID_OPERATION | EXCEPTION_FLT_INEXACT_RESULT => 8,
+        EXCEPTION_ACCESS_VIOLATION | EXCEPTION_STACK_OVERFLOW | EXCEPTION_IN_PAGE_ERROR | EXCEPTION_ARRAY_BOUNDS_EXCEEDED => 11,
+        _ => 6,
+    }
+}
+
+
+impl WinInferior {
+    pub fn spawn(file: &str, arg: &str) -> Result<(Box<dyn Inferior>, GdbStop), anyhow::Error> {
+        let mut cli = format!("{file} {arg}\0").into_bytes();
+        unsafe {
+            let mut si = mem::zeroed::<STARTUPINFOA>();
+            let mut pi = mem::zeroed::<PROCESS_INFORMATION>();
+            si.cb = size_of::<STARTUPINFOA>() as u32;
+            if CreateProcessA(ptr::null_mut(), cli.as_mut_ptr() as *mut i8, ptr::null_mut(), ptr::null_mut(), 0, DEBUG_ONLY_THIS_PROCESS, ptr::null_mut(), ptr::null_mut(), &mut si, &mut pi) == 0 {
+                return Err(anyhow!("CreateProcess failed : {}", io::Error::last_os_error()));
+            }
+            CloseHandle(pi.hThread);
+            let inferior = WinInferior { target: WinTarget::new(pi.hProcess), pid: pi.dwProcessId, event_tid: Cell::new(0), base_addr: Cell::new(0) };
+            let stop = inferior.wait(&[])?;
+            Ok((Box::new(inferior), stop))
+        }
+    }
+
+    fn continue_event(&self, status: u32) -> Result<(), anyhow::Error> {
+        if unsafe { ContinueDebugEvent(self.pid, self.event_tid.get(), status) } == 0 {
+            return Err(anyhow!("failed to ContinueDebugEvent : {}", io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    fn wait(&self, watchpts: &[Watchpts]) -> Result<GdbStop, anyhow::Error> {
+        unsafe {
+            let mut debug_event = mem::zeroed::<DEBUG_EVENT>();
+            loop {
+                if WaitForDebugEventEx(&mut debug_event, INFINITE) == 0 {
+                    return Err(anyhow!("failed to WaitForDebugEventEx : {}", io::Error::last_os_error()));
+                }
+                self.event_tid.set(debug_event.dwThreadId);
+                match debug_event.dwDebugEventCode {
+                    EXCEPTION_DEBUG_EVENT => {
+                        let code = debug_event.u.Exception().ExceptionRecord.ExceptionCode;
+                        return Ok(GdbStop::Signal { sig: exception_to_signal(code), tid: Some(debug_event.dwThreadId), watch: None });
+                    }
+                    CREATE_PROCESS_DEBUG_EVENT => {
+                        self.base_addr.set(debug_event.u.CreateProcessInfo().lpBaseOfImage as u64);
+                        if !debug_event.u.CreateProcessInfo().hFile.is_null() {
+                            CloseHandle(debug_event.u.CreateProcessInfo().hFile);
+                        }
+                    }
+                    CREATE_THREAD_DEBUG_EVENT => set_thread_watchpoints(&self.target, debug_event.dwThreadId, self.base_addr.get(), watchpts),
+                    LOAD_DLL_DEBUG_EVENT if !debug_event.u.LoadDll().hFile.is_null() => {
+                        CloseHandle(debug_event.u.LoadDll().hFile);
+                    }
+                    EXIT_PROCESS_DEBUG_EVENT => {
+                        let code = debug_event.u.ExitProcess().dwExitCode;
+                        self.continue_event(DBG_CONTINUE)?;
+                        return Ok(GdbStop::Exited(code as u8));
+                    }
+                    _ => {}
+                }
+                self.continue_event(DBG_CONTINUE)?;
+            }
+        }
+    }
+}
+
+
+impl Inferior for WinInferior {
+    fn target(&self) -> &dyn DebugTarget {
+        &self.target
+    }
+
+    fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    fn base_addr(&self) -> u64 {
+        self.base_addr.get()
+    }
+
+    fn resume(&self, tid: u32, step: bool, sig: u8, watchpts: &[Watchpts]) -> Result<GdbStop, anyhow::Error> {
+        if step {
+            let mut regs = self.target.get_registers(tid)?;
+            regs.eflags |= 0x100;
+            self.target.set_registers(tid, &regs)?;
+        }
+        self.continue_event(if sig != 0 { DBG_EXCEPTION_NOT_HANDLED } else { DBG_CONTINUE })?;
+        self.wait(watchpts)
+    }
+
+    fn kill(&self) {
+        unsafe {
+            if TerminateProcess(self.target.h_proc, 1) == 0 {
+                print_lg(LevelPrint::Error, format!("failed to TerminateProcess : {}", io::Error::last_os_error()));
+            }
+        }
+        let _ = self.continue_event(DBG_CONTINUE);
+        while let Ok(GdbStop::Signal { .. }) = self.wait(&[]) {
+            let _ = self.continue_event(DBG_CONTINUE);
+        }
+    }
+
+    fn detach(&self) {
+        let _ = self.continue_event(DBG_CONTINUE);
+        if unsafe { DebugActiveProcessStop(self.pid) } == 0 {
+            print_lg(LevelPrint::Error, format!("failed to DebugActiveProcessStop : {}", io::Error::last_os_error()));
+        }
+    }
+}
+
+
+impl Drop for WinInferior {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.target.h_proc) };
+    }
+}
@@ -0,0 +1,308 @@
+use crate::command::breakpoint::Brkpts;
+use crate::command::watchpoint::FlagTypeMem;
+use crate::dbg::dbg_cmd::generic;
+use crate::dbg::event::*;
+use crate::dbg::memory::{breakpoint, watchpoint};
+use crate::dbg::target::ptrace::{PtraceStop, PtraceTarget};
+use crate::dbg::target::DebugTarget;
+use crate::dbg::trace::{self, RecordHost, Recorder};
+use crate::dbg::{handle_point, DbgState};
+use crate::session::Session;
+use crate::ut::fmt::*;
+use std::collections::VecDeque;
+
+
+pub fn start_debugging(session: &mut Session, file: &str, arg: &str) {
+    let args: Vec<&str> = arg.split_whitespace().collect();
+    let target = match PtraceTarget::spawn(file, &args) {
+        Ok(target) => target,
+        Err(e) => {
+            print_lg(LevelPrint::Error, format!("failed to start '{file}' : {e}"));
+            return;
+        }
+    };
+    match target.image_base(file) {
+        Ok(base) => session.base_addr = base,
+        Err(e) => print_lg(LevelPrint::Error, format!("failed to find the base address : {e}")),
+    }
+    let mut host = PtraceHost(&target);
+    match session.all.record.clone() {
+        Some(path) => match Recorder::create(&target, &path, session) {
+            Ok(rec) => {
+                print_lg(LevelPrint::DebugO, format!("the session is recorded in '{path}'"));
+                debug_loop(session, &target, &mut RecordHost { inner: host, target: rec });
+            }
+            Err(e) => {
+                print_lg(LevelPrint::ErrorO, format!("{e}, the session is not recorded"));
+                debug_loop(session, &target, &mut host);
+            }
+        },
+        None => debug_loop(session, &target, &mut host),
+    }
+    session.base_addr = 0;
+}
+
+
+struct PtraceHost<'a>(&'a PtraceTarget);
+
+impl EventHost for PtraceHost<'_> {
+    fn target(&self) -> &dyn DebugTarget {
+        self.0
+    }
+
+    fn stop(&mut self, session: &mut Session, ev: &EventRecord, c_dbg: &mut DbgState) {
+        trace::prompt(session, self.0, ev, c_dbg);
+    }
+
+    fn return_address(&mut self, session: &mut Session, ev: &EventRecord) -> Option<u64> {
+        let regs = self.0.get_registers(ev.tid).ok()?;
+        generic::frames(session, &regs, self.0).get(1).map(|f| f.pc)
+    }
+}
+
+fn signal_event(sig: i32, address: u64, fault_addr: u64) -> DebugEvent {
+    let (code, info) = match sig {
+        libc::SIGSEGV | libc::SIGBUS => (EXCEPTION_ACCESS_VIOLATION, vec![0, fault_addr]),
+        libc::SIGILL => (EXCEPTION_ILLEGAL_INSTRUCTION, Vec::new()),
+        libc::SIGINT => (CONTROL_C_EXIT, Vec::new()),
+        libc::SIGTRAP => (EXCEPTION_BREAKPOINT, Vec::new()),
+        _ => (sig as u32, Vec::new()),
+    };
+    DebugEvent::from_exception(code, true, address, info)
+}
+
+
+fn init(session: &mut Session, target: &dyn DebugTarget, pid: u32) {
+    breakpoint::set_breakpoints(session, target);
+    for addr in &mut session.all.break_va {
+        if let Err(e) = breakpoint::set_breakpoint(target, addr.addr, &mut addr.origin_b) {
+            print_lg(LevelPrint::Error, e);
+        }
+    }
+    set_watchpoints(session, target, pid);
+}
+
+
+fn set_watchpoints(session: &Session, target: &dyn DebugTarget, tid: u32) {
+    let watchpts = &session.all.watchpts;
+    if watchpts.is_empty() {
+        return;
+    }
+    let mut regs = match target.get_registers(tid) {
+        Ok(regs) => regs,
+        Err(e) => {
+            print_lg(LevelPrint::Error, format!("failed to get registers for set watchpoint, all watchpoints are useless: {e}"));
+            return;
+        }
+    };
+    let mut set = generic::reg_set(session, &regs);
+    for (i, watch) in watchpts.iter().enumerate().take(4) {
+        match watch.flag_type_mem {
+            FlagTypeMem::MemoryStatic | FlagTypeMem::VirtualAddr if watch.register.is_empty() => {}
+            _ => {
+                print_lg(LevelPrint::WarningO, format!("watchpoint {i} is relative to a frame or a register and is not supported on this target"));
+                continue;
+            }
+        }
+        watchpoint::set_dreg(session, &mut set, watch, i);
+        print_lg(LevelPrint::Debug, format!("activation of watchpoint {} monitoring on register dr{} : {:#x}", i, i, watch.real_addr_regs(session, &set)));
+    }
+    set.write_registers(&mut regs);
+    if let Err(e) = target.set_registers(tid, &regs) {
+        print_lg(LevelPrint::Error, format!("failed to set registers for set watchpoint, all watchpoints are useless: {e}"));
+    }
+}
+
+
+fn find_brkpt(session: &mut Session, addr: u64) -> Option<&mut Brkpts> {
+    let base_addr = session.base_addr;
+    let all = &mut session.all;
+    match all.break_rva.iter().position(|b| b.addr + base_addr == addr) {
+        Some(i) => Some(&mut all.break_rva[i]),
+        None => all.break_va.iter_mut().find(|b| b.addr == addr),
+    }
+}
+
+
+fn debug_loop(session: &mut Session, target: &PtraceTarget, host: &mut dyn EventHost) {
+    let pid = target.pid;
+    host.event(&EventRecord::new(pid, pid, DebugEvent::ProcessCreated { image_base: session.base_addr }));
+    init(session, host.target(), pid);
+    let mut c_dbg = DbgState::Continue;
+    let mut tid = pid;
+    let mut sig = 0;
+    // stops of other threads seen while stepping over a breakpoint, handled before waiting again
+    let mut pending = VecDeque::new();
+    while c_dbg == DbgState::Continue {
+        if let Err(e) = target.cont(tid, sig) {
+            print_lg(LevelPrint::Error, e);
+            break;
+        }
+        sig = 0;
+        let next = match pending.pop_front() {
+            Some(stop) => Ok(stop),
+            None => target.wait(),
+        };
+        match next {
+            Ok(PtraceStop::Exited(code)) => {
+                host.event(&EventRecord::new(pid, tid, DebugEvent::Exited { exit_code: code as u32 }));
+                print_lg(LevelPrint::DebugO, format!("the process exited with code {code}"));
+                return;
+            }
+            Ok(PtraceStop::Killed(s)) => {
+                host.event(&EventRecord::new(pid, tid, DebugEvent::Exited { exit_code: 128 + s as u32 }));
+                print_lg(LevelPrint::DebugO, format!("the process was killed by signal {s}"));
+                return;
+            }
+            Ok(PtraceStop::ThreadExited(t)) => {
+                host.event(&EventRecord::new(pid, t, DebugEvent::ThreadExited { exit_code: 0 }));
+                print_lg(LevelPrint::Debug, format!("thread {t} exited"));
+                tid = pid;
+            }
+            Ok(PtraceStop::Clone(t, new_tid)) => {
+                host.event(&EventRecord::new(pid, new_tid, DebugEvent::ThreadCreated { start_address: 0 }));
+                print_lg(LevelPrint::Debug, format!("new thread {new_tid}"));
+                tid = t;
+            }
+            Ok(PtraceStop::Signal(t, libc::SIGSTOP)) if t != pid => {
+                set_watchpoints(session, host.target(), t);
+                tid = t;
+            }
+            Ok(PtraceStop::Signal(t, s)) => {
+                tid = t;
+                report_signal(session, target, host, t, s, &mut c_dbg);
+                sig = s;
+            }
+            Ok(PtraceStop::Trap(t)) => {
+                tid = t;
+                handle_trap(session, target, host, t, &mut c_dbg, &mut pending);
+            }
+            Err(e) => {
+                print_lg(LevelPrint::Error, e);
+                break;
+            }
+        }
+    }
+    target.kill();
+    let _ = target.wait();
+}
+
+
+// the registers are first read past the recorder to build the event, a trace holds the event before what is read to handle it
+fn handle_trap(session: &mut Session, target: &PtraceTarget, host: &mut dyn EventHost, tid: u32, c_dbg: &mut DbgState, pending: &mut VecDeque<PtraceStop>) {
+    let rip = match target.get_registers(tid) {
+        Ok(regs) if regs.dr6 & 0xf != 0 => {
+            let ev = EventRecord::new(target.pid, tid, DebugEvent::from_exception(EXCEPTION_SINGLE_STEP, true, regs.rip, Vec::new()));
+            host.event(&ev);
+            watch_hit(session, host, &ev, c_dbg);
+            return;
+        }
+        Ok(regs) => regs.rip,
+        Err(e) => {
+            print_lg(LevelPrint::Error, e);
+            return;
+        }
+    };
+
+    let b_addr = rip - 1;
+    let base_addr = session.base_addr;
+    let all = &session.all;
+    let hook = all.hook.iter().find(|h| h.target + base_addr == b_addr).copied();
+    let ret = all.break_ret.iter().find(|b| b.addr + base_addr == b_addr).or_else(|| all.break_ret_va.iter().find(|b| b.addr == b_addr)).copied();
+    let once = all.break_once.iter().position(|b| b.addr + base_addr == b_addr);
+    if hook.is_none() && ret.is_none() && once.is_none() && find_brkpt(session, b_addr).is_none() {
+        return;
+    }
+    let ev = EventRecord::new(target.pid, tid, DebugEvent::from_exception(EXCEPTION_BREAKPOINT, true, b_addr, Vec::new()));
+    host.event(&ev);
+    if let Some(hook) = hook {
+        handle_point::handle_hook_func(host.target(), base_addr, hook, &ev, c_dbg);
+        return;
+    }
+    if let Some(b) = ret {
+        breakpoint::set_breakpoint_in_ret_func(session, host, &ev, b_addr, b);
+        return;
+    }
+    let (origin_b, once) = match once {
+        Some(pos) => (session.all.break_once.remove(pos).origin_b, true),
+        None => (find_brkpt(session, b_addr).map(|b| b.origin_b).unwrap_or_default(), false),
+    };
+    match once {
+        true => print_lg(LevelPrint::Debug, format!("One-shot breakpoint hit at address: {:#x}", b_addr)),
+        false => print_lg(LevelPrint::Debug, format!("Breakpoint hit at address: {:#x}", b_addr)),
+    }
+    breakpoint::restore_byte_of_brkpt(host.target(), b_addr, origin_b);
+    if let Err(e) = breakpoint::rewind_ip(host.target(), tid, b_addr) {
+        print_lg(LevelPrint::Error, e);
+        return;
+    }
+    host.stop(session, &ev, c_dbg);
+    if !once && *c_dbg == DbgState::Continue {
+        step_over_brkpt(session, target, host, tid, b_addr, c_dbg, pending);
+    }
+}
+
+
+fn watch_hit(session: &mut Session, host: &mut dyn EventHost, ev: &EventRecord, c_dbg: &mut DbgState) {
+    let mut regs = match host.target().get_registers(ev.tid) {
+        Ok(regs) => regs,
+        Err(e) => {
+            print_lg(LevelPrint::Error, e);
+            return;
+        }
+    };
+    let access_addr = [regs.dr0, regs.dr1, regs.dr2, regs.dr3][(regs.dr6 & 0xf).trailing_zeros() as usize];
+    print_lg(LevelPrint::Debug, format!("except address {:#x}, there was access to the address {:#x}", regs.rip, access_addr));
+    regs.dr6 = 0;
+    if let Err(e) = host.target().set_registers(ev.tid, &regs) {
+        print_lg(LevelPrint::Error, e);
+    }
+    host.stop(session, ev, c_dbg);
+}
+
+
+fn report_signal(session: &mut Session, target: &PtraceTarget, host: &mut dyn EventHost, tid: u32, sig: i32, c_dbg: &mut DbgState) {
+    let rip = target.get_registers(tid).map(|r| r.rip).unwrap_or_default();
+    let ev = EventRecord::new(target.pid, tid, signal_event(sig, rip, target.fault_addr(tid)));
+    host.event(&ev);
+    print_lg(LevelPrint::WarningO, format!("thread {tid} received signal {sig}"));
+    host.stop(session, &ev, c_dbg);
+}
+
+
+// a signal stopping the thread before the step is reported, then delivered by the retried step
+fn step_over_brkpt(session: &mut Session, target: &PtraceTarget, host: &mut dyn EventHost, tid: u32, b_addr: u64, c_dbg: &mut DbgState, pending: &mut VecDeque<PtraceStop>) {
+    let mut sig = 0;
+    loop {
+        if let Err(e) = target.step(tid, sig) {
+            print_lg(LevelPrint::Error, e);
+            return;
+        }
+        let s = loop {
+            match target.wait() {
+                Ok(PtraceStop::Trap(t)) if t == tid => break 0,
+                Ok(PtraceStop::Exited(_)) | Ok(PtraceStop::Killed(_)) => return,
+                Ok(PtraceStop::Signal(t, s)) if t == tid => break s,
+                Ok(stop) => pending.push_back(stop),
+                Err(e) => {
+                    print_lg(LevelPrint::Error, e);
+                    return;
+                }
+            }
+        };
+        if s == 0 {
+            break;
+        }
+        report_signal(session, target, host, tid, s, c_dbg);
+        if *c_dbg != DbgState::Continue {
+            return;
+        }
+        sig = s;
+    }
+    if let Some(b) = find_brkpt(session, b_addr) {
+        if let Err(e) = breakpoint::set_breakpoint(host.target(), b_addr, &mut b.origin_b) {
+            print_lg(LevelPrint::Error, e);
+        }
+    }
+}
+
@@ -1,168 +1,248 @@
 use crate::cli::AfterB;
-use crate::dbg::memory::stack::{get_frame_st, get_frame_st32, get_real_frame, ST_FRAME};
-use crate::dbg::{dbg_cmd, DbgState, BASE_ADDR};
-use crate::pefile::{NtHeaders, NT_HEADER};
-use crate::{ut, ALL_ELM};
+#[cfg(windows)]
+use crate::dbg::memory::stack::{get_frame_st, get_frame_st32, get_real_frame};
+use crate::dbg::event::{EventHost, EventRecord};
+use crate::dbg::{memory, DbgState};
+#[cfg(windows)]
+use crate::pefile::NtHeaders;
+use crate::session::Session;
+#[cfg(windows)]
+use crate::ut;
 use iced_x86::{Decoder, DecoderOptions, Instruction};
-use std::{io, ptr};
-use winapi::shared::minwindef::{FALSE, LPVOID};
+use crate::dbg::target::{self, DebugTarget};
+#[cfg(windows)]
+use std::{io, mem};
+#[cfg(windows)]
+use winapi::shared::minwindef::FALSE;
+#[cfg(windows)]
 use winapi::shared::ntdef::HANDLE;
+#[cfg(windows)]
 use winapi::um::dbghelp::SymCleanup;
+#[cfg(windows)]
 use winapi::um::handleapi::CloseHandle;
-use winapi::um::memoryapi::*;
-use winapi::um::minwinbase::DEBUG_EVENT;
-use winapi::um::processthreadsapi::{GetThreadContext, OpenThread, SetThreadContext};
-use winapi::um::winbase::{Wow64GetThreadContext, Wow64SetThreadContext};
+#[cfg(windows)]
+use winapi::um::processthreadsapi::{GetThreadContext, OpenThread};
+#[cfg(windows)]
+use winapi::um::winbase::Wow64GetThreadContext;
+#[cfg(windows)]
 use winapi::um::winnt::{
-    CONTEXT, CONTEXT_ALL, PAGE_EXECUTE_READWRITE, THREAD_ALL_ACCESS, WOW64_CONTEXT,
+    CONTEXT, CONTEXT_ALL, THREAD_ALL_ACCESS, WOW64_CONTEXT,
     WOW64_CONTEXT_ALL,
 };
 use crate::command::breakpoint::Brkpts;
+use crate::pefile::export::{self, ExportTable};
 use crate::ut::fmt::{print_lg, LevelPrint};
+use anyhow::anyhow;
+use std::rc::Rc;
 
-pub unsafe fn restore_byte_of_brkpt(h_proc: HANDLE, b_addr: u64, last_oc: u8) {
-    let mut old_protect = 0;
-    let mut written = 0;
-    if VirtualProtectEx(h_proc, b_addr as LPVOID, 1, PAGE_EXECUTE_READWRITE, &mut old_protect) == 0 {
-        print_lg(LevelPrint::Error, format!("error when changing memory protection at address : {:#x}", b_addr));
-        return;
-    }
-    if WriteProcessMemory(h_proc, b_addr as LPVOID, ptr::addr_of!(last_oc) as LPVOID, 1, &mut written) == 0 {
-        print_lg(LevelPrint::Error, format!("error when writing to memory at address : {:#x} : {}", b_addr, io::Error::last_os_error()));
-    }
-    if VirtualProtectEx(h_proc, b_addr as LPVOID, 1, old_protect, &mut old_protect) == 0 {
-        print_lg(LevelPrint::Error, format!("error while restoring memory protection at address: {:#x}", b_addr));
+pub fn restore_byte_of_brkpt(target: &dyn DebugTarget, b_addr: u64, last_oc: u8) {
+    if let Err(e) = target::write_unprotected(target, b_addr, &[last_oc]) {
+        print_lg(LevelPrint::Error, format!("error when restoring the breakpoint at address {:#x} : {e}", b_addr));
     }
 }
 
 
 
-pub unsafe fn handle_br(h_proc: HANDLE, debug_event: DEBUG_EVENT, b_addr: u64, origin_b: u8, c_dbg: &mut DbgState) {
+pub fn handle_br(session: &mut Session, host: &mut dyn EventHost, ev: &EventRecord, b_addr: u64, origin_b: u8, c_dbg: &mut DbgState) {
     print_lg(LevelPrint::Debug, format!("Breakpoint hit at address: {:#x}", b_addr));
-    restore_byte_of_brkpt(h_proc, b_addr, origin_b);
-
-    let mut h_thread = OpenThread(THREAD_ALL_ACCESS, FALSE, debug_event.dwThreadId);
-    if h_thread.is_null() {
-        print_lg(LevelPrint::Error, format!("Failed to open thread: {}", io::Error::last_os_error()));
+    restore_byte_of_brkpt(host.target(), b_addr, origin_b);
+    if let Err(e) = rewind_ip(host.target(), ev.tid, b_addr) {
+        print_lg(LevelPrint::Critical1(ev.pid, c_dbg), format!("failed to get thread context: {e}"));
         return;
     }
-
-    match NT_HEADER {
-        Some(NtHeaders::Headers64(_)) => {
-            match ut::mem::alloc_size_align::<CONTEXT>() {
-                Ok(pctx) => {
-                    (*pctx).ContextFlags = CONTEXT_ALL;
-                    if GetThreadContext(h_thread, pctx) != 0 {
-                        (*pctx).Rip -= 1;
-                        dbg_cmd::x64::cmd_wait(&mut *pctx, h_proc, &mut h_thread, c_dbg);
-                        if SetThreadContext(h_thread, pctx) == 0 {
-                            print_lg(LevelPrint::Error, format!("error when setting thread context: {}", io::Error::last_os_error()));
-                        }
-                    } else {
-                        print_lg(LevelPrint::Critical1(debug_event.dwProcessId, c_dbg), format!("failed to get thread context: {}", io::Error::last_os_error()));
-                    }
-                }
-                Err(e) => print_lg(LevelPrint::Critical1(debug_event.dwProcessId, c_dbg), e),
-            }
-        }
-        Some(NtHeaders::Headers32(_)) => {
-            match ut::mem::alloc_size_align::<WOW64_CONTEXT>() {
-                Ok(pctx) => {
-                    (*pctx).ContextFlags = WOW64_CONTEXT_ALL;
-                    if Wow64GetThreadContext(h_thread, pctx) != 0 {
-                        (*pctx).Eip -= 1;
-                        dbg_cmd::x32::cmd_wait32(&mut *pctx, h_proc, &mut h_thread, c_dbg);
-                        if Wow64SetThreadContext(h_thread, pctx) == 0 {
-                            print_lg(LevelPrint::Error, format!("error when setting thread context: {}", io::Error::last_os_error()));
-                        }
-                    } else {
-                        print_lg(LevelPrint::Critical1(debug_event.dwProcessId, c_dbg), format!("failed to get thread context: {}", io::Error::last_os_error()));
-                    }
-                }
-                Err(e) => print_lg(LevelPrint::Critical1(debug_event.dwProcessId, c_dbg), e)
-            }
-        }
-        None => print_lg(LevelPrint::Critical1(debug_event.dwProcessId, c_dbg), "Unexpected state: NT_HEADER is None"),
-    }
+    host.stop(session, ev, c_dbg);
 
     if *c_dbg == DbgState::Continue {
+        let target = host.target();
         let mut b_insn = [0u8; 15];
-        if ReadProcessMemory(h_proc, b_addr as LPVOID, b_insn.as_mut_ptr() as LPVOID, 15, &mut 0) == 0 {
-            print_lg(LevelPrint::Error, format!("Failed to get insn at address {:#x} : {}", b_addr, io::Error::last_os_error()));
+        if let Err(e) = target.read_memory(b_addr, &mut b_insn) {
+            print_lg(LevelPrint::Error, format!("Failed to get insn at address {:#x} : {e}", b_addr));
             return;
         }
-        let mut decoder = Decoder::with_ip(NT_HEADER.unwrap().get_bitness() as u32, &b_insn, b_addr, DecoderOptions::NONE);
+        let mut decoder = Decoder::with_ip(session.get_bitness() as u32, &b_insn, b_addr, DecoderOptions::NONE);
         let mut insn = Instruction::new();
         decoder.decode_out(&mut insn);
         let next_addr = b_addr + insn.len() as u64;
-        let mut last_oc = 0u8;
-        if ReadProcessMemory(h_proc, next_addr as LPVOID, ptr::addr_of_mut!(last_oc) as LPVOID, 1, &mut 0) == 0 {
-            print_lg(LevelPrint::Error, format!("Failed to read memory at address {:#x} : {}", next_addr, io::Error::last_os_error()));
-            return;
-        }
-        (*&raw mut ALL_ELM).after_b.push(AfterB {
-            last_addr_b: b_addr,
-            after_b: next_addr,
-            last_oc,
-        });
-        if WriteProcessMemory(h_proc, next_addr as LPVOID, &0xccu8 as *const u8 as LPVOID, 1, &mut 0) == 0 {
-            print_lg(LevelPrint::Error, format!("Failed to write memory at address {:#x} : {}", next_addr, io::Error::last_os_error()));
-            return;
+        match target::patch_byte(target, next_addr, 0xcc) {
+            Ok(last_oc) => session.all.after_b.push(AfterB {
+                last_addr_b: b_addr,
+                after_b: next_addr,
+                last_oc,
+            }),
+            Err(e) => print_lg(LevelPrint::Error, format!("Failed to set the step breakpoint at address {:#x} : {e}", next_addr)),
         }
     }
-    CloseHandle(h_thread);
 }
 
-pub unsafe fn set_breakpoint(h_proc: HANDLE, b_addr: u64, last_oc: &mut u8) -> Result<(), String>{
-    let mut old_protect = 0;
-    if VirtualProtectEx(h_proc, b_addr as LPVOID, 1, PAGE_EXECUTE_READWRITE, &mut old_protect) == 0 {
-        return Err(format!("Failed to change memory protection at address: 0x{:x} : {}", b_addr, io::Error::last_os_error()));
+pub fn handle_br_once(session: &mut Session, host: &mut dyn EventHost, ev: &EventRecord, b_addr: u64, origin_b: u8, c_dbg: &mut DbgState) {
+    print_lg(LevelPrint::Debug, format!("One-shot breakpoint hit at address: {:#x}", b_addr));
+    restore_byte_of_brkpt(host.target(), b_addr, origin_b);
+    if let Err(e) = rewind_ip(host.target(), ev.tid, b_addr) {
+        print_lg(LevelPrint::Critical1(ev.pid, c_dbg), format!("failed to get thread context: {e}"));
+        return;
     }
-    if ReadProcessMemory(h_proc, b_addr as LPVOID, ptr::addr_of_mut!(*last_oc) as LPVOID, 1, ptr::null_mut()) == 0 {
-        return Err(format!("Failed to read memory at address: 0x{:x} : {}", b_addr, io::Error::last_os_error()));
+    host.stop(session, ev, c_dbg);
+}
+
+pub fn rewind_ip(target: &dyn DebugTarget, tid: u32, b_addr: u64) -> Result<(), anyhow::Error> {
+    let mut regs = target.get_registers(tid)?;
+    regs.rip = b_addr;
+    target.set_registers(tid, &regs)
+}
+
+pub fn set_breakpoint(target: &dyn DebugTarget, b_addr: u64, last_oc: &mut u8) -> Result<(), String>{
+    *last_oc = target::patch_byte(target, b_addr, 0xcc).map_err(|e| format!("Failed to write breakpoint at address: {:#x} : {e}", b_addr))?;
+    print_lg(LevelPrint::Debug, format!("Breakpoint set at address: {:#x}", b_addr));
+    Ok(())
+}
+
+
+
+pub fn set_breakpoints(session: &mut Session, target: &dyn DebugTarget) {
+    let base_addr = session.base_addr;
+    for hook in &mut session.all.hook {
+        if let Err(e) = set_breakpoint(target, hook.target + base_addr, &mut hook.origin_byte) {
+            print_lg(LevelPrint::Error, e);
+        }
     }
-    if WriteProcessMemory(h_proc, b_addr as LPVOID, &0xccu8 as *const u8 as LPVOID, 1, &mut 0) == 0 {
-        return Err(format!("Failed to write breakpoint at address: 0x{:x} : {}", b_addr, io::Error::last_os_error()));
+
+    for addr in &mut session.all.break_rva {
+        if let Err(e) = set_breakpoint(target, addr.addr + base_addr, &mut addr.origin_b) {
+            print_lg(LevelPrint::Error, e);
+        }
     }
-    if VirtualProtectEx(h_proc, b_addr as LPVOID, 1, old_protect, &mut old_protect) == 0 {
-        return Err(format!("Failed to restore memory protection at address: 0x{:x} : {}", b_addr, io::Error::last_os_error()))
+
+    for addr in &mut session.all.break_once {
+        if let Err(e) = set_breakpoint(target, addr.addr + base_addr, &mut addr.origin_b) {
+            print_lg(LevelPrint::Error, e);
+        }
+    }
+
+    for addr in &mut session.all.break_ret {
+        if let Err(e) = set_breakpoint(target, addr.addr + base_addr, &mut addr.origin_b) {
+            print_lg(LevelPrint::Error, e);
+        }
+    }
+
+    for addr in &mut session.all.break_ret_va {
+        if let Err(e) = set_breakpoint(target, addr.addr, &mut addr.origin_b) {
+            print_lg(LevelPrint::Error, e);
+        }
+    }
+
+    for addr_over in &mut session.all.skip_addr {
+        if let Err(e) = memory::set_addr_over(target, &session.symbols, base_addr, addr_over.addr + base_addr, &mut addr_over.origin_b) {
+            print_lg(LevelPrint::Error, e);
+        }
     }
-    print_lg(LevelPrint::Debug, format!("Breakpoint set at address: {:#x}", b_addr));
-    Ok(())
 }
 
 
 
-pub unsafe fn set_breakpoint_in_ret_func(h_proc: HANDLE, debug_event: DEBUG_EVENT, b: &mut Brkpts) {
-    restore_byte_of_brkpt(h_proc, b.addr, b.origin_b);
-    let h_thread = OpenThread(THREAD_ALL_ACCESS, FALSE, debug_event.dwThreadId);
+// the delay-load helper calls the function right after patching the IAT slot, so it is armed from the exports of its dll as soon as it is loaded
+pub fn set_delay_breakpoints(session: &mut Session, target: &dyn DebugTarget, modules: &[(u64, String)], base: u64) {
+    let Some((_, loaded)) = modules.iter().find(|(b, _)| *b == base) else {
+        return;
+    };
+    let mut tables: Vec<(u64, Rc<ExportTable>)> = Vec::new();
+    let mut open = |dll: &str| -> Result<(u64, Rc<ExportTable>), anyhow::Error> {
+        let stem = export::dll_stem(dll);
+        let (b, _) = modules.iter().find(|(_, s)| *s == stem).ok_or_else(|| anyhow!("{stem} is not loaded"))?;
+        if let Some((_, table)) = tables.iter().find(|(t, _)| t == b) {
+            return Ok((*b, table.clone()));
+        }
+        let table = Rc::new(export::read_module_exports(target, *b)?);
+        tables.push((*b, table.clone()));
+        Ok((*b, table))
+    };
+    let all = &mut session.all;
+    for iat_b in all.break_iat.iter_mut().filter(|b| b.addr == 0) {
+        let Some((dll, func)) = iat_b.label.split_once('!').filter(|(dll, _)| dll == loaded) else {
+            continue;
+        };
+        // a forwarder to a dll that is not loaded yet is left to set_iat_breakpoints
+        let func_addr = match export::resolve_export(&mut open, dll, func) {
+            Ok(resolved) => resolved.base + resolved.export.rva as u64,
+            Err(_) => continue,
+        };
+        let mut b = Brkpts::from_addr_no_start(func_addr);
+        if let Err(e) = set_breakpoint(target, func_addr, &mut b.origin_b) {
+            print_lg(LevelPrint::Error, e);
+            continue;
+        }
+        print_lg(LevelPrint::Debug, format!("{} has been loaded, breakpoint set at {:#x}", iat_b.label, func_addr));
+        iat_b.addr = func_addr;
+        all.break_va.push(b);
+    }
+}
+
+
+pub fn set_iat_breakpoints(session: &mut Session, target: &dyn DebugTarget) {
+    let base_addr = session.base_addr;
+    let size = session.get_size_of_arch();
+    let all = &mut session.all;
+    for iat_b in all.break_iat.iter_mut().filter(|b| b.addr == 0) {
+        let func = match target::read_ptr(target, iat_b.iat_rva as u64 + base_addr, size) {
+            Ok(func) => func,
+            Err(_) => continue,
+        };
+        if func == 0 || func == iat_b.thunk_rva as u64 + base_addr {
+            continue;
+        }
+        let mut b = Brkpts::from_addr_no_start(func);
+        if let Err(e) = set_breakpoint(target, func, &mut b.origin_b) {
+            print_lg(LevelPrint::Error, e);
+            continue;
+        }
+        print_lg(LevelPrint::Debug, format!("{} has been resolved by the delay-load helper at {:#x}", iat_b.label, func));
+        iat_b.addr = func;
+        all.break_va.push(b);
+    }
+}
+
+
+
+pub fn set_breakpoint_in_ret_func(session: &mut Session, host: &mut dyn EventHost, ev: &EventRecord, b_addr: u64, b: Brkpts) {
+    restore_byte_of_brkpt(host.target(), b_addr, b.origin_b);
+    if let Err(e) = rewind_ip(host.target(), ev.tid, b_addr) {
+        print_lg(LevelPrint::Error, format!("Failed to adjust the instruction pointer : {e}"));
+        return;
+    }
+    session.st_frame.clear();
+    match host.return_address(session, ev) {
+        Some(ret_addr) => {
+            let mut bh = Brkpts::from_addr_no_start(ret_addr);
+            if let Err(e) = set_breakpoint(host.target(), bh.addr, &mut bh.origin_b) {
+                print_lg(LevelPrint::Error, e);
+                return;
+            }
+            print_lg(LevelPrint::Debug, format!("Address of function return: {:#x}", ret_addr));
+            bh.b_mod = b.b_mod;
+            session.all.break_va.push(bh);
+        }
+        None => print_lg(LevelPrint::Error, format!("Failed to retrieve function frame for instruction at {:#x}", b_addr - session.base_addr)),
+    }
+}
+
+
+#[cfg(windows)]
+pub unsafe fn ret_addr_of_frame(session: &mut Session, h_proc: HANDLE, tid: u32) -> Option<u64> {
+    let h_thread = OpenThread(THREAD_ALL_ACCESS, FALSE, tid);
     if h_thread.is_null() {
         print_lg(LevelPrint::Error, format!("Failed to open thread : {}", io::Error::last_os_error()));
-        return;
+        return None;
     }
-    (*&raw mut ST_FRAME).clear();
-    let rip = match NT_HEADER.unwrap() {
+    let rip = match session.nt_header.unwrap() {
         NtHeaders::Headers32(_) => {
-            match ut::mem::alloc_size_align::<WOW64_CONTEXT>() {
-                Ok(pctx) => {
-                    (*pctx).ContextFlags = WOW64_CONTEXT_ALL;
-                    if Wow64GetThreadContext(h_thread, pctx) == 0 {
-                        print_lg(LevelPrint::Error, format!("Failed to get thread context: {}", io::Error::last_os_error()));
-                        return;
-                    }
-                    (*pctx).Eip -= 1;
-                    get_frame_st32(h_proc, h_thread, *pctx);
-                    if Wow64SetThreadContext(h_thread, pctx) == 0 {
-                        print_lg(LevelPrint::Error, format!("Failed to adjust EIP : {}", io::Error::last_os_error()));
-                        return;
-                    }
-                    (*pctx).Eip as u64
-                }
-                Err(e) => {
-                    print_lg(LevelPrint::Error, e);
-                    return;
-                },
+            let mut ctx = mem::zeroed::<WOW64_CONTEXT>();
+            ctx.ContextFlags = WOW64_CONTEXT_ALL;
+            if Wow64GetThreadContext(h_thread, &mut ctx) == 0 {
+                print_lg(LevelPrint::Error, format!("Failed to get thread context: {}", io::Error::last_os_error()));
+                CloseHandle(h_thread);
+                return None;
             }
+            get_frame_st32(session, h_proc, h_thread, ctx);
+            ctx.Eip as u64
         }
         NtHeaders::Headers64(_) => {
             match ut::mem::alloc_size_align::<CONTEXT>() {
@@ -170,36 +250,104 @@ pub unsafe fn set_breakpoint_in_ret_func(h_proc: HANDLE, debug_event: DEBUG_EVEN
                     (*pctx).ContextFlags = CONTEXT_ALL;
                     if GetThreadContext(h_thread, pctx) == 0 {
                         print_lg(LevelPrint::Error, format!("Failed to get thread context: {}", io::Error::last_os_error()));
-                        return;
-                    }
-                    (*pctx).Rip -= 1;
-                    get_frame_st(h_proc, h_thread, *pctx);
-                    if SetThreadContext(h_thread, pctx) == 0 {
-                        print_lg(LevelPrint::Error, format!("Failed to adjust RIP : {}", io::Error::last_os_error()));
-                        return;
+                        CloseHandle(h_thread);
+                        return None;
                     }
+                    get_frame_st(session, h_proc, &*pctx);
                     (*pctx).Rip
                 }
                 Err(e) => {
                     print_lg(LevelPrint::Error, e);
-                    return;
+                    CloseHandle(h_thread);
+                    return None;
                 }
             }
         }
     };
-
-    if let Some(frame) = get_real_frame(rip) {
-        let mut bh = Brkpts::from_addr_no_start(frame.AddrReturn.Offset);
-        if let Err(e) = set_breakpoint(h_proc, bh.addr, &mut bh.origin_b) {
-            print_lg(LevelPrint::Error, e);
-            return;
-        }
-        print_lg(LevelPrint::Debug, format!("Address of function return: {:#x}", frame.AddrReturn.Offset));
-        bh.b_mod = b.b_mod;
-        (*ptr::addr_of_mut!(ALL_ELM)).break_va.push(bh);
-    } else {
-        print_lg(LevelPrint::Error, format!("Failed to retrieve function frame for instruction at {:#x}", b.addr - BASE_ADDR));
-    }
+    let ret_addr = get_real_frame(session, rip).map(|frame| frame.ret);
     SymCleanup(h_proc);
     CloseHandle(h_thread);
+    ret_addr
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::breakpoint::IatBrkpt;
+    use crate::dbg::target::{MemTarget, Registers, PROT_EXECUTE_READ, PROT_READWRITE};
+
+    const BASE: u64 = 0x140000000;
+
+    fn target() -> MemTarget {
+        let target = MemTarget::new();
+        target.map(BASE, vec![0x90, 0x55, 0x48, 0x89, 0xe5, 0xc3, 0x31, 0xc0], PROT_EXECUTE_READ);
+        target
+    }
+
+    fn code(target: &MemTarget) -> [u8; 8] {
+        let mut buf = [0u8; 8];
+        target::read_exact(target, BASE, &mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn set_and_restore_breakpoint() {
+        let target = target();
+        let mut origin = 0;
+        set_breakpoint(&target, BASE + 1, &mut origin).unwrap();
+        assert_eq!(origin, 0x55);
+        assert_eq!(code(&target)[..3], [0x90, 0xcc, 0x48]);
+        assert_eq!(target.query_region(BASE).unwrap().protect, PROT_EXECUTE_READ);
+        restore_byte_of_brkpt(&target, BASE + 1, origin);
+        assert_eq!(code(&target)[..3], [0x90, 0x55, 0x48]);
+        assert!(set_breakpoint(&target, BASE + 0x1000, &mut origin).is_err());
+        assert_eq!(origin, 0x55);
+    }
+
+    #[test]
+    fn set_breakpoints_of_the_session() {
+        let target = target();
+        let mut session = Session { base_addr: BASE, ..Default::default() };
+        session.all.break_rva.push(Brkpts::from_addr_no_start(1));
+        session.all.break_once.push(Brkpts::from_addr_no_start(5));
+        session.all.break_ret_va.push(Brkpts::from_addr_no_start(BASE + 6));
+        session.all.break_rva.push(Brkpts::from_addr_no_start(0x1000));
+        set_breakpoints(&mut session, &target);
+        assert_eq!(code(&target), [0x90, 0xcc, 0x48, 0x89, 0xe5, 0xcc, 0xcc, 0xc0]);
+        assert_eq!(session.all.break_rva[0].origin_b, 0x55);
+        assert_eq!(session.all.break_once[0].origin_b, 0xc3);
+        assert_eq!(session.all.break_ret_va[0].origin_b, 0x31);
+        assert_eq!(session.all.break_rva[1].origin_b, 0);
+    }
+
+    #[test]
+    fn rewind_to_the_breakpoint() {
+        let target = target();
+        target.add_thread(7, Registers { rip: BASE + 2, rax: 1, ..Default::default() });
+        rewind_ip(&target, 7, BASE + 1).unwrap();
+        let regs = target.get_registers(7).unwrap();
+        assert_eq!((regs.rip, regs.rax), (BASE + 1, 1));
+        assert!(rewind_ip(&target, 8, BASE + 1).is_err());
+    }
+
+    #[test]
+    fn iat_breakpoint_once_the_slot_is_patched() {
+        let target = target();
+        target.map(BASE + 0x2000, (BASE + 0x1080).to_le_bytes().to_vec(), PROT_READWRITE);
+        let mut session = Session { base_addr: BASE, ..Default::default() };
+        session.all.break_iat.push(IatBrkpt { iat_rva: 0x2000, thunk_rva: 0x1080, label: "delay!DelayedFunc".to_string(), addr: 0 });
+        set_iat_breakpoints(&mut session, &target);
+        assert!(session.all.break_va.is_empty());
+        assert_eq!(session.all.break_iat[0].addr, 0);
+
+        // the delay-load helper has resolved the import
+        target.write_memory(BASE + 0x2000, &(BASE + 1).to_le_bytes()).unwrap();
+        set_iat_breakpoints(&mut session, &target);
+        set_iat_breakpoints(&mut session, &target);
+        assert_eq!(session.all.break_iat[0].addr, BASE + 1);
+        let set: Vec<(u64, u8)> = session.all.break_va.iter().map(|b| (b.addr, b.origin_b)).collect();
+        assert_eq!(set, [(BASE + 1, 0x55)]);
+        assert_eq!(code(&target)[..2], [0x90, 0xcc]);
+    }
 }
@@ -1,35 +1,37 @@
 use crate::ut::fmt::BYTES_COLOR;
+#[cfg(windows)]
 use crate::dbg::dbg_cmd::usages;
-use crate::dbg::dbg_cmd::x64::info_reg::{ToValue, Value};
-use crate::dbg::RealAddr;
-use crate::pefile::NT_HEADER;
-use crate::symbol::SYMBOLS_V;
-use crate::ALL_ELM;
+use crate::pefile::reloc;
+use crate::session::Session;
 use regex::Regex;
 use std::io::Write;
-use std::{io, ptr};
-use winapi::shared::minwindef::LPVOID;
-use winapi::shared::ntdef::HANDLE;
-use winapi::um::memoryapi::ReadProcessMemory;
-use winapi::um::winnt::CONTEXT;
+use std::{io, slice};
+use crate::dbg::target::{read_ptr, DebugTarget};
+#[cfg(windows)]
+use crate::dbg::CONTEXT;
 use crate::command::def::types::{StructP, TypeP};
 use crate::ut::cast::str_to;
+#[cfg(windows)]
+use crate::ut::get_addr_va;
 use crate::ut::fmt::*;
 
-unsafe fn read_memory<T: Print + Default + Clone>(h_proc: HANDLE, address: usize, count_ptr: usize, array_cout: usize, field_name: &str, bytes_read: &mut usize) {
+unsafe fn read_memory<T: Print + Default + Clone>(session: &Session, target: &dyn DebugTarget, address: usize, count_ptr: usize, array_cout: usize, field_name: &str, bytes_read: &mut usize) {
     let r_size = array_cout * size_of::<T>();
     let mut result = vec![T::default(); array_cout];
-    let ptr_size = NT_HEADER.unwrap().get_size_of_arch();
+    let ptr_size = session.get_size_of_arch();
     let mut addr_v = address;
 
     for i in 0..count_ptr {
-        print!("{ADDR_COLOR}{:#x}{RESET_COLOR} -> ", addr_v);
+        print!("{ADDR_COLOR}{:#x}{RESET_COLOR}{} -> ", addr_v, if i == count_ptr - 1 { String::new() } else { reloc::reloc_note(session, addr_v as u64) });
 
         if i == count_ptr - 1 {
-            if ReadProcessMemory(h_proc, addr_v as LPVOID, result.as_mut_ptr() as LPVOID, r_size, bytes_read) == 0 {
-                io::stdout().flush().unwrap();
-                print_lg(LevelPrint::ErrorO, format!("Bad ptr : {}", io::Error::last_os_error()));
-                return;
+            match target.read_memory(addr_v as u64, slice::from_raw_parts_mut(result.as_mut_ptr() as *mut u8, r_size)) {
+                Ok(n) => *bytes_read = n,
+                Err(e) => {
+                    io::stdout().flush().unwrap();
+                    print_lg(LevelPrint::ErrorO, format!("Bad ptr : {e}"));
+                    return;
+                }
             }
 
             print!("{field_name}: {}{VALUE_COLOR}", if array_cout > 1 { "\n[\n" } else { "" });
@@ -42,105 +44,97 @@ unsafe fn read_memory<T: Print + Default + Clone>(h_proc: HANDLE, address: usize
                 }
 
                 if (i + 1) % 4 == 0 && i != array_cout - 1 {
-                    print!("\n");
+                    println!();
                 }
             }
             println!("{RESET_COLOR}{}", if array_cout > 1 { "\n]\n" } else { "" });
             io::stdout().flush().unwrap();
         } else {
-            if ReadProcessMemory(h_proc, addr_v as LPVOID, ptr::addr_of_mut!(addr_v) as LPVOID, ptr_size, &mut 0) == 0 {
-                io::stdout().flush().unwrap();
-                print_lg(LevelPrint::Error, format!("bad ptr : {}", io::Error::last_os_error()));
-                return;
+            match read_ptr(target, addr_v as u64, ptr_size) {
+                Ok(v) => addr_v = v as usize,
+                Err(e) => {
+                    io::stdout().flush().unwrap();
+                    print_lg(LevelPrint::Error, format!("bad ptr : {e}"));
+                    return;
+                }
             }
         }
     }
 }
 
-unsafe fn match_fi(type_p: TypeP, h_proc: HANDLE, addr_n: usize, cout_ptr: usize, field_name: &str) {
+unsafe fn match_fi(session: &Session, type_p: TypeP, target: &dyn DebugTarget, addr_n: usize, cout_ptr: usize, field_name: &str) {
     match type_p {
-        TypeP::U8(cout) => read_memory::<u8>(h_proc, addr_n, cout_ptr, cout, field_name, &mut 0),
-        TypeP::I8(cout) => read_memory::<i8>(h_proc, addr_n, cout_ptr, cout, field_name, &mut 0),
-        TypeP::U16(cout) => read_memory::<u16>(h_proc, addr_n, cout_ptr, cout, field_name, &mut 0),
-        TypeP::I16(cout) => read_memory::<i16>(h_proc, addr_n, cout_ptr, cout, field_name, &mut 0),
-        TypeP::U32(cout) => read_memory::<u32>(h_proc, addr_n, cout_ptr, cout, field_name, &mut 0),
-        TypeP::I32(cout) => read_memory::<i32>(h_proc, addr_n, cout_ptr, cout, field_name, &mut 0),
-        TypeP::U64(cout) => read_memory::<u64>(h_proc, addr_n, cout_ptr, cout, field_name, &mut 0),
-        TypeP::I64(cout) => read_memory::<i64>(h_proc, addr_n, cout_ptr, cout, field_name, &mut 0),
-        TypeP::F32(cout) => read_memory::<f32>(h_proc, addr_n, cout_ptr, cout, field_name, &mut 0),
-        TypeP::F64(cout) => read_memory::<f64>(h_proc, addr_n, cout_ptr, cout, field_name, &mut 0),
+        TypeP::U8(cout) => read_memory::<u8>(session, target, addr_n, cout_ptr, cout, field_name, &mut 0),
+        TypeP::I8(cout) => read_memory::<i8>(session, target, addr_n, cout_ptr, cout, field_name, &mut 0),
+        TypeP::U16(cout) => read_memory::<u16>(session, target, addr_n, cout_ptr, cout, field_name, &mut 0),
+        TypeP::I16(cout) => read_memory::<i16>(session, target, addr_n, cout_ptr, cout, field_name, &mut 0),
+        TypeP::U32(cout) => read_memory::<u32>(session, target, addr_n, cout_ptr, cout, field_name, &mut 0),
+        TypeP::I32(cout) => read_memory::<i32>(session, target, addr_n, cout_ptr, cout, field_name, &mut 0),
+        TypeP::U64(cout) => read_memory::<u64>(session, target, addr_n, cout_ptr, cout, field_name, &mut 0),
+        TypeP::I64(cout) => read_memory::<i64>(session, target, addr_n, cout_ptr, cout, field_name, &mut 0),
+        TypeP::F32(cout) => read_memory::<f32>(session, target, addr_n, cout_ptr, cout, field_name, &mut 0),
+        TypeP::F64(cout) => read_memory::<f64>(session, target, addr_n, cout_ptr, cout, field_name, &mut 0),
         TypeP::Ptr(ptrs, cout) => {
-            let size_ptr = NT_HEADER.unwrap().get_size_of_arch();
+            let size_ptr = session.get_size_of_arch();
             for i in 0..cout {
-                match_fi(*ptrs.type_deref.clone(), h_proc, addr_n + (i * size_ptr), ptrs.cout_ptr + 1, field_name)
+                match_fi(session, *ptrs.type_deref.clone(), target, addr_n + (i * size_ptr), ptrs.cout_ptr + 1, field_name)
             }
         }
-        TypeP::Bool(cout) => read_memory::<bool>(h_proc, addr_n, cout_ptr, cout, field_name, &mut 0),
-        TypeP::Char(cout) => read_string(h_proc, addr_n, cout_ptr, cout, &mut 0, false),
-        TypeP::Structs(field_s, name) => read_memory_for_struct(h_proc, addr_n, 1, field_s, &name),
+        TypeP::Bool(cout) => read_memory::<bool>(session, target, addr_n, cout_ptr, cout, field_name, &mut 0),
+        TypeP::Char(cout) => read_string(session, target, addr_n, cout_ptr, cout, &mut 0, false),
+        TypeP::Structs(field_s, name) => read_memory_for_struct(session, target, addr_n, 1, field_s, &name),
         _ => {}
     }
 }
 
-unsafe fn read_memory_for_struct(h_proc: HANDLE, address: usize, cout_ptr: usize, struct_def: Vec<StructP>, name_struct: &str) {
+unsafe fn read_memory_for_struct(session: &Session, target: &dyn DebugTarget, address: usize, cout_ptr: usize, struct_def: Vec<StructP>, name_struct: &str) {
     let mut addr_n = address;
-    let size_ptr = NT_HEADER.unwrap().get_size_of_arch();
+    let size_ptr = session.get_size_of_arch();
     for i in 0..cout_ptr {
         if i == cout_ptr - 1 {
             println!("struct {name_struct} {{");
             for field in &struct_def {
-                match_fi(field.type_p.clone(), h_proc, addr_n, 1, &field.name_field);
-                addr_n += field.type_p.get_size();
+                match_fi(session, field.type_p.clone(), target, addr_n, 1, &field.name_field);
+                addr_n += field.type_p.get_size(session.nt_header);
             }
             println!("}}");
         } else {
-            if ReadProcessMemory(h_proc, addr_n as LPVOID, ptr::addr_of_mut!(addr_n) as LPVOID, size_ptr, &mut 0) == 0 {
-                print_lg(LevelPrint::ErrorO, format!("Bad ptr : {}", io::Error::last_os_error()));
-                return;
+            match read_ptr(target, addr_n as u64, size_ptr) {
+                Ok(v) => addr_n = v as usize,
+                Err(e) => {
+                    print_lg(LevelPrint::ErrorO, format!("Bad ptr : {e}"));
+                    return;
+                }
             }
         }
     }
 }
 
-pub fn handle_deref(linev: &[&str], ctx: CONTEXT, h_proc: HANDLE) {
+#[cfg(windows)]
+pub fn handle_deref(session: &Session, linev: &[&str], ctx: *const CONTEXT, target: &dyn DebugTarget) {
     if linev.len() < 3 {
         eprintln!("{}", usages::USAGE_DEREF);
         return;
     }
     let dtype = linev[1];
-    let target = linev[2];
-    if target == "" {
+    let addr_str = linev[2];
+    if addr_str.is_empty() {
         print_lg(LevelPrint::ErrorO, "empty target");
         return;
     }
-    let address = if let Ok(addr) = str_to::<u64>(target) {
-        addr
-    } else {
-        let value = ctx.str_to_value_ctx(target);
-        let addr = match value {
-            Value::U64(value) => value,
-            Value::U128(xmm) => xmm.Low,
-            _ => 0,
-        };
-        if addr != 0 {
-            addr
-        } else {
-            unsafe {
-                if let Some(sym) = (*ptr::addr_of!(SYMBOLS_V)).symbol_file.iter().find(|s| s.name == target) {
-                    sym.real_addr64(ctx)
-                } else {
-                    print_lg(LevelPrint::ErrorO, format!("invalid target : '{target}'"));
-                    return;
-                }
-            }
+    let address = match get_addr_va(session, addr_str, ctx) {
+        Ok(addr) => addr,
+        Err(e) => {
+            print_lg(LevelPrint::ErrorO, e);
+            return;
         }
     };
-    if let Err(err) = deref_memory(h_proc, dtype, address as usize) {
+    if let Err(err) = deref_memory(session, target, dtype, address as usize) {
         print_lg(LevelPrint::ErrorO, err);
     }
 }
 
-pub fn deref_memory(h_proc: HANDLE, dtype: &str, address: usize) -> Result<(), String> {
+pub fn deref_memory(session: &Session, target: &dyn DebugTarget, dtype: &str, address: usize) -> Result<usize, String> {
     let mut bytes_read = 0;
     let re = Regex::new(r"\[(.*?)]").unwrap();
     let mut size = 1;
@@ -162,25 +156,24 @@ pub fn deref_memory(h_proc: HANDLE, dtype: &str, address: usize) -> Result<(), S
     let count_ptr = dtype.matches("*").count() + 1;
     unsafe {
         match types_r {
-            "bool" => read_memory::<bool>(h_proc, address, size, bytes_read, "", &mut bytes_read),
-            "uint8_t" | "byte" | "u8" => read_memory::<u8>(h_proc, address, count_ptr, size, "", &mut bytes_read),
-            "int8_t" | "i8" => read_memory::<i8>(h_proc, address, count_ptr, size, "", &mut bytes_read),
-            "uint16_t" | "word" | "u16" => read_memory::<u16>(h_proc, address, count_ptr, size, "", &mut bytes_read),
-            "int16_t" | "short" | "i16" => read_memory::<i16>(h_proc, address, count_ptr, size, "", &mut bytes_read),
-            "uint32_t" | "u32" | "dword" => read_memory::<u32>(h_proc, address, count_ptr, size, "", &mut bytes_read),
-            "int32_t" | "int" | "long" | "i32" => read_memory::<i32>(h_proc, address, count_ptr, size, "", &mut bytes_read),
-            "uint64_t" | "qword" | "u64" => read_memory::<u64>(h_proc, address, count_ptr, size, "", &mut bytes_read),
-            "int64_t" | "i64" => read_memory::<i64>(h_proc, address, count_ptr, size, "", &mut bytes_read),
-            "float" | "f32" => read_memory::<f32>(h_proc, address, count_ptr, size, "", &mut bytes_read),
-            "double" | "f64" => read_memory::<f64>(h_proc, address, count_ptr, size, "", &mut bytes_read),
-            "size_t" => read_memory::<usize>(h_proc, address, count_ptr, size, "", &mut bytes_read),
-            "char" | "str" | "string" => read_string(h_proc, address, count_ptr, size, &mut bytes_read, false),
-            "wchar" | "wstr" | "wstring" => read_string(h_proc, address, count_ptr, size, &mut bytes_read, true),
+            "bool" => read_memory::<bool>(session, target, address, count_ptr, size, "", &mut bytes_read),
+            "uint8_t" | "byte" | "u8" => read_memory::<u8>(session, target, address, count_ptr, size, "", &mut bytes_read),
+            "int8_t" | "i8" => read_memory::<i8>(session, target, address, count_ptr, size, "", &mut bytes_read),
+            "uint16_t" | "word" | "u16" => read_memory::<u16>(session, target, address, count_ptr, size, "", &mut bytes_read),
+            "int16_t" | "short" | "i16" => read_memory::<i16>(session, target, address, count_ptr, size, "", &mut bytes_read),
+            "uint32_t" | "u32" | "dword" => read_memory::<u32>(session, target, address, count_ptr, size, "", &mut bytes_read),
+            "int32_t" | "int" | "long" | "i32" => read_memory::<i32>(session, target, address, count_ptr, size, "", &mut bytes_read),
+            "uint64_t" | "qword" | "u64" => read_memory::<u64>(session, target, address, count_ptr, size, "", &mut bytes_read),
+            "int64_t" | "i64" => read_memory::<i64>(session, target, address, count_ptr, size, "", &mut bytes_read),
+            "float" | "f32" => read_memory::<f32>(session, target, address, count_ptr, size, "", &mut bytes_read),
+            "double" | "f64" => read_memory::<f64>(session, target, address, count_ptr, size, "", &mut bytes_read),
+            "size_t" => read_memory::<usize>(session, target, address, count_ptr, size, "", &mut bytes_read),
+            "char" | "str" | "string" => read_string(session, target, address, count_ptr, size, &mut bytes_read, false),
+            "wchar" | "wstr" | "wstring" => read_string(session, target, address, count_ptr, size, &mut bytes_read, true),
             _ => {
-                let pallm = ptr::addr_of!(ALL_ELM);
-                if (*pallm).struct_def.iter().any(|s| s.get_name_of_struct() == types_r) {
-                    for structs in &(*pallm).struct_def {
-                        read_memory_for_struct(h_proc, address, count_ptr, structs.get_field_of_struct(), &structs.get_name_of_struct())
+                if session.all.struct_def.iter().any(|s| s.get_name_of_struct() == types_r) {
+                    for structs in &session.all.struct_def {
+                        read_memory_for_struct(session, target, address, count_ptr, structs.get_field_of_struct(), &structs.get_name_of_struct())
                     }
                 } else {
                     return Err(format!("Unknown type: {}", dtype));
@@ -188,7 +181,7 @@ pub fn deref_memory(h_proc: HANDLE, dtype: &str, address: usize) -> Result<(), S
             }
         }
     }
-    Ok(())
+    Ok(bytes_read)
 }
 
 
@@ -213,10 +206,10 @@ pub fn espc(input: &[u8]) -> String {
 
 
 
-pub unsafe fn read_string(h_proc: HANDLE, address: usize, count_ptr: usize, size: usize, bytes_read: &mut usize, wstr: bool) {
+pub unsafe fn read_string(session: &Session, target: &dyn DebugTarget, address: usize, count_ptr: usize, size: usize, bytes_read: &mut usize, wstr: bool) {
     let mut b_str = Vec::new();
     let mut addr_s = address;
-    let ptr_size = NT_HEADER.unwrap().get_size_of_arch();
+    let ptr_size = session.get_size_of_arch();
     let mut b = 0u8;
     let add_value = if wstr { 2 } else { 1 };
     for i in 0..count_ptr {
@@ -224,9 +217,9 @@ pub unsafe fn read_string(h_proc: HANDLE, address: usize, count_ptr: usize, size
         if i == count_ptr - 1 {
             let mut j = 0;
             loop {
-                if ReadProcessMemory(h_proc, (addr_s + j * add_value) as LPVOID, ptr::addr_of_mut!(b) as LPVOID, 1, &mut 0) == 0 {
+                if let Err(e) = target.read_memory((addr_s + j * add_value) as u64, slice::from_mut(&mut b)) {
                     io::stdout().flush().unwrap();
-                    print_lg(LevelPrint::ErrorO, format!("Bad ptr : {}", io::Error::last_os_error()));
+                    print_lg(LevelPrint::ErrorO, format!("Bad ptr : {e}"));
                     return;
                 }
                 if size != 0 && j == size || size == 0 && b == 0 {
@@ -241,11 +234,59 @@ pub unsafe fn read_string(h_proc: HANDLE, address: usize, count_ptr: usize, size
             println!("{}\"{}\"", if wstr { "L" } else { "" }, str_r);
             return;
         } else {
-            if ReadProcessMemory(h_proc, addr_s as LPVOID, ptr::addr_of_mut!(addr_s) as LPVOID, ptr_size, &mut 0) == 0 {
-                io::stdout().flush().unwrap();
-                print_lg(LevelPrint::ErrorO, format!("bad ptr : {}", io::Error::last_os_error()));
-                return;
+            match read_ptr(target, addr_s as u64, ptr_size) {
+                Ok(v) => addr_s = v as usize,
+                Err(e) => {
+                    io::stdout().flush().unwrap();
+                    print_lg(LevelPrint::ErrorO, format!("bad ptr : {e}"));
+                    return;
+                }
             }
         }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbg::target::{MemTarget, PROT_READWRITE};
+
+    const BASE: u64 = 0x7000;
+
+    fn target() -> MemTarget {
+        let target = MemTarget::new();
+        let mut data = vec![0u8; 0x40];
+        data[..8].copy_from_slice(&(BASE + 0x10).to_le_bytes());
+        data[8..16].copy_from_slice(&BASE.to_le_bytes());
+        data[0x10..0x16].copy_from_slice(b"hello\0");
+        data[0x20..0x30].copy_from_slice(&[1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0]);
+        data[0x30..0x36].copy_from_slice(&[b'h', 0, b'i', 0, 0, 0]);
+        target.map(BASE, data, PROT_READWRITE);
+        target
+    }
+
+    #[test]
+    fn deref_values_and_arrays() {
+        let target = target();
+        let session = Session::default();
+        assert_eq!(deref_memory(&session, &target, "u32", BASE as usize + 0x20), Ok(4));
+        assert_eq!(deref_memory(&session, &target, "u32[4]", BASE as usize + 0x20), Ok(16));
+        assert_eq!(deref_memory(&session, &target, "qword[2]", BASE as usize), Ok(16));
+        assert_eq!(deref_memory(&session, &target, "bool[3]", BASE as usize + 0x20), Ok(3));
+        assert!(deref_memory(&session, &target, "u32[x]", BASE as usize).is_err());
+        assert!(deref_memory(&session, &target, "unknown", BASE as usize).is_err());
+    }
+
+    #[test]
+    fn deref_pointers_and_strings() {
+        let target = target();
+        let session = Session::default();
+        assert_eq!(deref_memory(&session, &target, "str", BASE as usize + 0x10), Ok(5));
+        assert_eq!(deref_memory(&session, &target, "str*", BASE as usize), Ok(5));
+        assert_eq!(deref_memory(&session, &target, "u8**[2]", BASE as usize + 8), Ok(2));
+        assert_eq!(deref_memory(&session, &target, "wstr", BASE as usize + 0x30), Ok(2));
+        assert_eq!(deref_memory(&session, &target, "u32*", BASE as usize + 0x20), Ok(0));
+        assert_eq!(deref_memory(&session, &target, "u32", 0x100000), Ok(0));
+    }
+}
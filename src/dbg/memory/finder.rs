@@ -1,13 +1,10 @@
 use crate::dbg::dbg_cmd::usages::USAGE_FIND;
-use std::{io, mem};
-use winapi::shared::minwindef::LPVOID;
-use winapi::shared::ntdef::HANDLE;
-use winapi::um::memoryapi::{ReadProcessMemory, VirtualQueryEx};
-use winapi::um::winnt::MEMORY_BASIC_INFORMATION;
+use crate::dbg::target::{DebugTarget, REGION_COMMIT};
+use std::slice;
 use crate::ut::cast::{str_to, Char, ToType};
 use crate::ut::fmt::{print_lg, LevelPrint};
 
-pub fn handle_find(linev: &[&str], h_proc: HANDLE) {
+pub fn handle_find(linev: &[&str], target: &dyn DebugTarget) {
     if linev.len() < 5 {
         print_lg(LevelPrint::DebugO, USAGE_FIND);
         return;
@@ -29,30 +26,45 @@ pub fn handle_find(linev: &[&str], h_proc: HANDLE) {
     };
 
     match linev[1] {
-        "uint8_t" | "u8" | "byte" => find_seq_byte::<u8>(h_proc, begin_addr, end_addr, linev[4..].join(" ")),
-        "i8" | "int8_t" => find_seq_byte::<i8>(h_proc, begin_addr, end_addr, linev[4..].join(" ")),
-        "char" => find_seq_byte::<Char>(h_proc, begin_addr, end_addr, linev[4..].join(" ")),
-        "uint16_t" | "u16" | "word" => find_seq_byte::<u16>(h_proc, begin_addr, end_addr, linev[4..].join(" ")),
-        "int16_t" | "i16" | "short" => find_seq_byte::<i16>(h_proc, begin_addr, end_addr, linev[4..].join(" ")),
-        "uint32_t" | "u32" | "dword" => find_seq_byte::<u32>(h_proc, begin_addr, end_addr, linev[4..].join(" ")),
-        "int32_t" | "i32" | "int" | "long" => find_seq_byte::<i32>(h_proc, begin_addr, end_addr, linev[4..].join(" ")),
-        "uint64_t" | "u64" | "qword" => find_seq_byte::<u64>(h_proc, begin_addr, end_addr, linev[4..].join(" ")),
-        "int64_t" | "i64" | "long long" => find_seq_byte::<i64>(h_proc, begin_addr, end_addr, linev[4..].join(" ")),
+        "uint8_t" | "u8" | "byte" => find_seq_byte::<u8>(target, begin_addr, end_addr, linev[4..].join(" ")),
+        "i8" | "int8_t" => find_seq_byte::<i8>(target, begin_addr, end_addr, linev[4..].join(" ")),
+        "char" => find_seq_byte::<Char>(target, begin_addr, end_addr, linev[4..].join(" ")),
+        "uint16_t" | "u16" | "word" => find_seq_byte::<u16>(target, begin_addr, end_addr, linev[4..].join(" ")),
+        "int16_t" | "i16" | "short" => find_seq_byte::<i16>(target, begin_addr, end_addr, linev[4..].join(" ")),
+        "uint32_t" | "u32" | "dword" => find_seq_byte::<u32>(target, begin_addr, end_addr, linev[4..].join(" ")),
+        "int32_t" | "i32" | "int" | "long" => find_seq_byte::<i32>(target, begin_addr, end_addr, linev[4..].join(" ")),
+        "uint64_t" | "u64" | "qword" => find_seq_byte::<u64>(target, begin_addr, end_addr, linev[4..].join(" ")),
+        "int64_t" | "i64" | "long long" => find_seq_byte::<i64>(target, begin_addr, end_addr, linev[4..].join(" ")),
         _ => print_lg(LevelPrint::ErrorO, format!("unknown type: {}", linev[1])),
     }
 }
 
 fn find_seq_byte<T: ToType + Default + Clone + std::fmt::Debug + PartialEq + std::fmt::Display>(
-    h_proc: HANDLE,
+    target: &dyn DebugTarget,
     beg_addr: u64,
     end_addr: u64,
     args: String,
 ) {
+    match find_seq::<T>(target, beg_addr, end_addr, &args) {
+        Ok((result, found_addr)) if found_addr.is_empty() => print_lg(LevelPrint::ErrorO, format!("element {:?} not found", result)),
+        Ok((_, found_addr)) => for addr in found_addr {
+            print_lg(LevelPrint::DebugO, format!("element found at address : {:#x}", addr));
+        },
+        Err(e) => print_lg(LevelPrint::ErrorO, e),
+    }
+}
+
+fn find_seq<T: ToType + Default + Clone + std::fmt::Debug + PartialEq + std::fmt::Display>(
+    target: &dyn DebugTarget,
+    beg_addr: u64,
+    end_addr: u64,
+    args: &str,
+) -> Result<(Vec<T>, Vec<u64>), String> {
     let wordv = args.split(",").map(|s| s.trim()).collect::<Vec<&str>>();
     let mut result = Vec::new();
 
     for word in wordv {
-        if word.starts_with("\"") || word.starts_with("'") {
+        if word.len() >= 2 && (word.starts_with("\"") || word.starts_with("'")) {
             let new_word = word[1..word.len() - 1].trim();
             for c in new_word.chars() {
                 result.push(T::from_char(c));
@@ -64,63 +76,92 @@ fn find_seq_byte<T: ToType + Default + Clone + std::fmt::Debug + PartialEq + std
             }
         }
     }
+    if result.is_empty() {
+        return Err("no element to search for".to_string());
+    }
 
     let size_mem = if end_addr != 0 {
         if beg_addr < end_addr {
             (end_addr - beg_addr) as usize
         } else {
-            print_lg(LevelPrint::ErrorO, "you cannot specify a start address greater than the end address".to_string());
-            return;
+            return Err("you cannot specify a start address greater than the end address".to_string());
         }
     } else {
-        unsafe {
-            let mut mem_info = mem::zeroed();
-            if VirtualQueryEx(h_proc, beg_addr as LPVOID, &mut mem_info, size_of::<MEMORY_BASIC_INFORMATION>()) == 0 {
-                print_lg(LevelPrint::ErrorO, format!("failed to query memory info for addr {:#x}: {}", beg_addr, io::Error::last_os_error()));
-                return;
-            }
-            mem_info.RegionSize
+        match target.query_region(beg_addr) {
+            Ok(region) if region.state != REGION_COMMIT => return Err(format!("the memory at address {:#x} is not committed", beg_addr)),
+            Ok(region) => (region.end() - beg_addr) as usize,
+            Err(e) => return Err(format!("failed to query memory info for addr {:#x}: {e}", beg_addr)),
         }
     };
 
     if size_of::<T>() > size_mem {
-        print_lg(LevelPrint::ErrorO, format!("the size of the memory to analyze is smaller than a single {}", std::any::type_name::<T>()));
-        return;
+        return Err(format!("the size of the memory to analyze is smaller than a single {}", std::any::type_name::<T>()));
     }
 
     let mut plage_mem = vec![T::default(); size_mem / size_of::<T>()];
-    unsafe {
-        let mut bytes_read = 0;
-        if ReadProcessMemory(h_proc, beg_addr as LPVOID, plage_mem.as_mut_ptr() as LPVOID, size_mem, &mut bytes_read) == 0 {
-            print_lg(LevelPrint::ErrorO, format!("failed to read memory {size_mem} bytes at address {:#x}: {}", beg_addr, io::Error::last_os_error()));
-            return;
-        }
-
-        if bytes_read == 0 {
-            print_lg(LevelPrint::ErrorO, "No bytes read from memory".to_string());
-            return;
-        }
+    let plage_bytes = unsafe { slice::from_raw_parts_mut(plage_mem.as_mut_ptr() as *mut u8, plage_mem.len() * size_of::<T>()) };
+    match target.read_memory(beg_addr, plage_bytes) {
+        Ok(0) => return Err("No bytes read from memory".to_string()),
+        Ok(_) => {}
+        Err(e) => return Err(format!("failed to read memory {size_mem} bytes at address {:#x}: {e}", beg_addr)),
     }
 
     if result.len() > plage_mem.len() {
-        print_lg(LevelPrint::ErrorO, "you specified too many elements for the elements".to_string());
-        return;
+        return Err("you specified too many elements for the elements".to_string());
     }
 
-    let mut found_addr = Vec::new();
-    for i in 0..plage_mem.len() {
-        if plage_mem[i] == result[0] && plage_mem[i..].len() >= result.len() {
-            if plage_mem[i..i + result.len()] == *result {
-                found_addr.push(beg_addr + (i * size_of::<T>()) as u64);
-            }
-        }
+    let found_addr = plage_mem.windows(result.len())
+        .enumerate()
+        .filter(|(_, w)| *w == result.as_slice())
+        .map(|(i, _)| beg_addr + (i * size_of::<T>()) as u64)
+        .collect();
+    Ok((result, found_addr))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbg::target::{MemTarget, PROT_READWRITE};
+
+    const BASE: u64 = 0x7000;
+
+    fn target() -> MemTarget {
+        let target = MemTarget::new();
+        let mut data = b"..ab..ab.a".to_vec();
+        data.extend_from_slice(&0xdeadbeefu32.to_le_bytes());
+        data.extend_from_slice(&[0; 2]);
+        data.extend_from_slice(&0xdeadbeefu32.to_le_bytes());
+        target.map(BASE, data, PROT_READWRITE);
+        target
     }
 
-    if found_addr.is_empty() {
-        print_lg(LevelPrint::ErrorO, format!("element {:?} not found", result));
-    } else {
-        for addr in found_addr {
-            print_lg(LevelPrint::DebugO, format!("element found at address : {:#x}", addr));
-        }
+    #[test]
+    fn find_bytes_and_chars() {
+        let target = target();
+        assert_eq!(find_seq::<u8>(&target, BASE, 0, "'ab'").unwrap().1, [BASE + 2, BASE + 6]);
+        assert_eq!(find_seq::<Char>(&target, BASE, 0, "\"ab\"").unwrap().1, [BASE + 2, BASE + 6]);
+        assert_eq!(find_seq::<u8>(&target, BASE, 0, "0x2e, 'a'").unwrap().1, [BASE + 1, BASE + 5, BASE + 8]);
+        assert_eq!(find_seq::<u8>(&target, BASE, BASE + 6, "'ab'").unwrap().1, [BASE + 2]);
+        assert!(find_seq::<u8>(&target, BASE, 0, "'zz'").unwrap().1.is_empty());
+    }
+
+    #[test]
+    fn find_words_at_their_alignment() {
+        let target = target();
+        assert_eq!(find_seq::<u16>(&target, BASE, 0, "0xbeef").unwrap().1, [BASE + 10, BASE + 16]);
+        assert_eq!(find_seq::<u32>(&target, BASE, 0, "0xdeadbeef").unwrap().1, [BASE + 16]);
+        assert_eq!(find_seq::<u32>(&target, BASE + 2, 0, "0xdeadbeef").unwrap().1, [BASE + 10]);
+        assert!(find_seq::<u32>(&target, BASE + 1, 0, "0xdeadbeef").unwrap().1.is_empty());
+    }
+
+    #[test]
+    fn invalid_ranges() {
+        let target = target();
+        assert!(find_seq::<u8>(&target, BASE + 4, BASE, "1").is_err());
+        assert!(find_seq::<u64>(&target, BASE, BASE + 4, "1").is_err());
+        assert!(find_seq::<u8>(&target, BASE, BASE + 2, "1, 2, 3").is_err());
+        assert!(find_seq::<u8>(&target, 0x100000, 0, "1").is_err());
+        assert!(find_seq::<u8>(&target, BASE, 0, "").is_err());
     }
 }
@@ -1,74 +1,70 @@
+#[cfg(windows)]
 use crate::usage::USAGE_MEM_INFO;
-use std::{io, mem};
-use winapi::shared::minwindef::LPVOID;
-use winapi::um::memoryapi::VirtualQueryEx;
-use winapi::um::winnt::*;
-use crate::ut::{get_addr_va};
+#[cfg(windows)]
+use crate::dbg::CONTEXT;
+use crate::dbg::target::*;
+#[cfg(windows)]
+use crate::session::Session;
+#[cfg(windows)]
+use crate::ut::get_addr_va;
 use crate::ut::fmt::{print_lg, LevelPrint};
 
 
 
 
-pub fn handle_mem_info(linev: &[&str], h_proc: HANDLE, ctx: *const CONTEXT) {
+#[cfg(windows)]
+pub fn handle_mem_info(session: &Session, linev: &[&str], target: &dyn DebugTarget, ctx: *const CONTEXT) {
     if linev.len() != 2 {
         println!("{USAGE_MEM_INFO}");
         return;
     }
-    let target = linev[1];
-    let addr = match get_addr_va(target, ctx) {
+    let addr = match get_addr_va(session, linev[1], ctx) {
         Ok(addr) => addr,
         Err(e) => {
             eprintln!("{e}");
             return;
         }
     };
-    get_mem_info(addr, h_proc);
+    get_mem_info(addr, target);
 }
 
 
 
-fn get_mem_info(addr: u64, h_proc: HANDLE) {
-    unsafe {
-        let mut mem_info: MEMORY_BASIC_INFORMATION = mem::zeroed();
-        if VirtualQueryEx(h_proc, addr as LPVOID, &mut mem_info, size_of::<MEMORY_BASIC_INFORMATION>()) == 0 {
-            print_lg(LevelPrint::ErrorO, format!("failed to get memory information for address {:#x} : {}", addr, io::Error::last_os_error()));
-            return;
-        }
-        print_mem_info(mem_info);
+pub fn get_mem_info(addr: u64, target: &dyn DebugTarget) {
+    match target.query_region(addr) {
+        Ok(region) => print_mem_info(region),
+        Err(e) => print_lg(LevelPrint::ErrorO, format!("failed to get memory information for address {:#x} : {e}", addr)),
     }
 }
 
-pub fn print_mem_info(mem_info: MEMORY_BASIC_INFORMATION) {
+pub fn print_mem_info(mem_info: MemRegion) {
     println!("  Memory information :");
-    println!("       Base Address    : {:#x}", mem_info.BaseAddress as usize);
-    println!("       Allocation Base : {:#x}", mem_info.AllocationBase as usize);
-    println!("       Region Size     : {:#x}", mem_info.RegionSize);
+    println!("       Base Address    : {:#x}", mem_info.base);
+    println!("       Allocation Base : {:#x}", mem_info.alloc_base);
+    println!("       Region Size     : {:#x}", mem_info.size);
     println!("       State           : {}",
-        if mem_info.State == MEM_COMMIT {
-            "Committed"
-        } else if mem_info.State == MEM_RESERVE {
-            "Reserved"
-        } else if mem_info.State == MEM_FREE {
-            "free"
-        } else {
-            "unknow"
+        match mem_info.state {
+            REGION_COMMIT => "Committed",
+            REGION_RESERVE => "Reserved",
+            REGION_FREE => "free",
+            _ => "unknow",
         }
     );
     println!("       Protect         : {}",
-        match mem_info.Protect {
-            PAGE_READONLY => "Read Only",
-            PAGE_READWRITE => "Read/Write",
-            PAGE_EXECUTE => "Execute",
-            PAGE_EXECUTE_READ => "Execute/Read",
-            PAGE_EXECUTE_READWRITE => "Execute/Read/Write",
+        match mem_info.protect {
+            PROT_READONLY => "Read Only",
+            PROT_READWRITE => "Read/Write",
+            PROT_EXECUTE => "Execute",
+            PROT_EXECUTE_READ => "Execute/Read",
+            PROT_EXECUTE_READWRITE => "Execute/Read/Write",
             _ => "Unknown",
         }
     );
     println!("       Type            : {}",
-        match mem_info.Type {
-            MEM_IMAGE => "Image",
-            MEM_MAPPED => "Mapped",
-            MEM_PRIVATE => "Private",
+        match mem_info.mem_type {
+            REGION_IMAGE => "Image",
+            REGION_MAPPED => "Mapped",
+            REGION_PRIVATE => "Private",
             _ => "Unknown",
         }
     );
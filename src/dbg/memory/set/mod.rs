@@ -1,3 +1,5 @@
 pub mod set_memory;
+#[cfg(windows)]
 pub mod set_protect;
+#[cfg(windows)]
 pub mod thread;
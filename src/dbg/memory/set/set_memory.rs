@@ -1,17 +1,16 @@
+#[cfg(windows)]
 use crate::dbg::dbg_cmd::usages;
-use crate::dbg::dbg_cmd::x32::info_reg::ToValue32;
-use crate::dbg::dbg_cmd::x64::info_reg::{ToValue, Value};
-use crate::pefile::{NtHeaders, NT_HEADER};
+use crate::session::Session;
 use regex::Regex;
-use std::{io, ptr};
-use winapi::shared::minwindef::LPVOID;
-use winapi::shared::ntdef::HANDLE;
-use winapi::um::memoryapi::{ReadProcessMemory, VirtualProtectEx, WriteProcessMemory};
-use winapi::um::winnt::{CONTEXT, PAGE_EXECUTE_READWRITE, WOW64_CONTEXT};
-use crate::cli::ALL_ELM;
+use std::slice;
+#[cfg(windows)]
+use crate::dbg::CONTEXT;
+use crate::dbg::target::{read_exact, read_ptr, write_unprotected, DebugTarget};
 use crate::command::def::types::{StructP, TypeP};
 use crate::ut::cast::{str_to, ToType};
 use crate::ut::fmt::{print_lg, LevelPrint};
+#[cfg(windows)]
+use crate::ut::get_addr_va;
 
 fn str_t_to_all_str<T>() -> Vec<String> {
     match std::any::type_name::<T>() {
@@ -28,69 +27,51 @@ fn str_t_to_all_str<T>() -> Vec<String> {
 }
 
 
-pub fn set_memory(h_proc: HANDLE, ctx: *const CONTEXT, arg: &[&str]) {
+#[cfg(windows)]
+pub fn set_memory(session: &Session, target: &dyn DebugTarget, ctx: *const CONTEXT, arg: &[&str]) {
     if arg.len() < 3 {
         eprintln!("{}", usages::USAGE_SET_MEM);
         return;
     }
 
     let types = arg[0];
-    let target = arg[1];
+    let addr_str = arg[1];
     let new_value_str = arg[2..].join(" ");
     let mut size = 1;
     get_size(&mut size, types);
 
-    let target_addr = match unsafe { NT_HEADER }.unwrap() {
-        NtHeaders::Headers32(_) => unsafe {
-            let value = (*(ctx as *const WOW64_CONTEXT)).str_to_ctx(target);
-            if value != 0 {
-                value as u64
-            }else {
-                match str_to::<u64>(&target) {
-                    Ok(v) => v,
-                    Err(_) => {
-                        print_lg(LevelPrint::ErrorO, format!("invalid target : {target}"));
-                        return;
-                    }
-                }
-            }
-        }
-        NtHeaders::Headers64(_) => unsafe {
-            match (*ctx).str_to_value_ctx(target) {
-                Value::U64(u) => u,
-                Value::U128(m) => m.Low,
-                _ => {
-                    print_lg(LevelPrint::ErrorO, format!("invalid value : {target}"));
-                    return;
-                }
-            }
+    let target_addr = match get_addr_va(session, addr_str, ctx) {
+        Ok(addr) => addr,
+        Err(e) => {
+            print_lg(LevelPrint::ErrorO, e);
+            return;
         }
     };
 
     let types_r = types.split('[').next().unwrap_or_default().to_lowercase();
-    target_mem(h_proc, &new_value_str, target_addr, size, &types_r);
+    target_mem(session, target, &new_value_str, target_addr, size, &types_r);
 }
 
 
-pub fn target_mem(h_proc: HANDLE, value_str: &str, target_addr: u64, cout: usize, types_r: &str) {
+pub fn target_mem(session: &Session, target: &dyn DebugTarget, value_str: &str, target_addr: u64, cout: usize, types_r: &str) {
     match types_r {
-        "uint8_t" | "u8" | "byte" => target_in_memory::<u8>(h_proc, value_str, target_addr, cout),
-        "int8_t" | "i8" | "char" => target_in_memory::<i8>(h_proc, value_str, target_addr, cout),
-        "uint16_t" | "word" | "u16" => target_in_memory::<u16>(h_proc, value_str, target_addr, cout),
-        "int16_t" | "i16" => target_in_memory::<i16>(h_proc, value_str, target_addr, cout),
-        "uint32_t" | "dword" | "u32" => target_in_memory::<u32>(h_proc, value_str, target_addr, cout),
-        "int32_t" | "int" | "i32" => target_in_memory::<i32>(h_proc, value_str, target_addr, cout),
-        "uint64_t" | "qword" | "u64" => target_in_memory::<u64>(h_proc, value_str, target_addr, cout),
-        "int64_t" | "i64" => target_in_memory::<i64>(h_proc, value_str, target_addr, cout),
-        "float" | "f32" => target_in_memory::<f32>(h_proc, value_str, target_addr, cout),
-        "double" | "f64" => target_in_memory::<f64>(h_proc, value_str, target_addr, cout),
-        _ => unsafe {
-            if let Some(structs) = (*ptr::addr_of!(ALL_ELM)).struct_def.iter().find(|s|s.get_name_of_struct() == types_r) {
-                handle_other_type(h_proc, target_addr, structs, value_str);
+        "uint8_t" | "u8" | "byte" => target_in_memory::<u8>(session, target, value_str, target_addr, cout),
+        "int8_t" | "i8" | "char" => target_in_memory::<i8>(session, target, value_str, target_addr, cout),
+        "uint16_t" | "word" | "u16" => target_in_memory::<u16>(session, target, value_str, target_addr, cout),
+        "int16_t" | "i16" => target_in_memory::<i16>(session, target, value_str, target_addr, cout),
+        "uint32_t" | "dword" | "u32" => target_in_memory::<u32>(session, target, value_str, target_addr, cout),
+        "int32_t" | "int" | "i32" => target_in_memory::<i32>(session, target, value_str, target_addr, cout),
+        "uint64_t" | "qword" | "u64" => target_in_memory::<u64>(session, target, value_str, target_addr, cout),
+        "int64_t" | "i64" => target_in_memory::<i64>(session, target, value_str, target_addr, cout),
+        "float" | "f32" => target_in_memory::<f32>(session, target, value_str, target_addr, cout),
+        "double" | "f64" => target_in_memory::<f64>(session, target, value_str, target_addr, cout),
+        _ => {
+            if let Some(structs) = session.all.struct_def.iter().find(|s|s.get_name_of_struct() == types_r) {
+                handle_other_type(session, target, target_addr, structs, value_str);
             }else {
                 print_lg(LevelPrint::ErrorO, "unsupported type");
             }
-        },
+        }
     }
 }
 
@@ -104,22 +85,22 @@ fn get_value_str(value_str: &str, field_name: &str) -> Result<String, String> {
             } else {
                 Err("you did not close the array with \"]\"".to_string())
             }
-        } else if value.starts_with('\"') {
-            return if let Some(end_pos) = value[1..].find('\"') {
+        } else if let Some(rest) = value.strip_prefix('"') {
+            if let Some(end_pos) = rest.find('"') {
                 Ok(value[1..=end_pos].to_string())
             } else {
                 Err("you did not close the string with \"".to_string())
             }
-        } else if value.starts_with('\'') {
-            return if let Some(end_pos) = value[1..].find('\'') {
+        } else if let Some(rest) = value.strip_prefix('\'') {
+            if let Some(end_pos) = rest.find('\'') {
                 Ok(value[1..=end_pos].to_string())
             } else {
                 Err("you did not close the string with ' ".to_string())
             }
         } else if let Some(end_pos) = value.find(',') {
-            return Ok(value[..end_pos].to_string());
+            Ok(value[..end_pos].to_string())
         } else {
-            return Ok(value.to_string());
+            Ok(value.to_string())
         }
     } else {
         Err(format!("the entry does not contain the {field_name} field"))
@@ -128,43 +109,43 @@ fn get_value_str(value_str: &str, field_name: &str) -> Result<String, String> {
 
 
 
-fn match_type(h_proc: HANDLE, field: StructP, value_str: String, addr: &mut u64) {
+fn match_type(session: &Session, target: &dyn DebugTarget, field: StructP, value_str: String, addr: &mut u64) {
     match field.type_p {
-        TypeP::U8(cout) => target_in_memory::<u8>(h_proc, &value_str, *addr, cout),
-        TypeP::I8(cout) => target_in_memory::<i8>(h_proc, &value_str, *addr, cout),
-        TypeP::I16(cout) => target_in_memory::<i16>(h_proc, &value_str, *addr, cout),
-        TypeP::U16(cout) => target_in_memory::<u16>(h_proc, &value_str, *addr, cout),
-        TypeP::I32(cout) => target_in_memory::<i32>(h_proc, &value_str, *addr, cout),
-        TypeP::U32(cout) => target_in_memory::<u32>(h_proc, &value_str, *addr, cout),
-        TypeP::I64(cout) => target_in_memory::<i64>(h_proc, &value_str, *addr, cout),
-        TypeP::U64(cout) => target_in_memory::<u64>(h_proc, &value_str, *addr, cout),
-        TypeP::F32(cout) => target_in_memory::<f32>(h_proc, &value_str, *addr, cout),
-        TypeP::F64(cout) => target_in_memory::<f64>(h_proc, &value_str, *addr, cout),
-        TypeP::Bool(cout) => target_in_memory::<bool>(h_proc, &value_str, *addr, cout),
-        TypeP::Char(cout) => target_in_memory::<char>(h_proc, &value_str, *addr, cout),
+        TypeP::U8(cout) => target_in_memory::<u8>(session, target, &value_str, *addr, cout),
+        TypeP::I8(cout) => target_in_memory::<i8>(session, target, &value_str, *addr, cout),
+        TypeP::I16(cout) => target_in_memory::<i16>(session, target, &value_str, *addr, cout),
+        TypeP::U16(cout) => target_in_memory::<u16>(session, target, &value_str, *addr, cout),
+        TypeP::I32(cout) => target_in_memory::<i32>(session, target, &value_str, *addr, cout),
+        TypeP::U32(cout) => target_in_memory::<u32>(session, target, &value_str, *addr, cout),
+        TypeP::I64(cout) => target_in_memory::<i64>(session, target, &value_str, *addr, cout),
+        TypeP::U64(cout) => target_in_memory::<u64>(session, target, &value_str, *addr, cout),
+        TypeP::F32(cout) => target_in_memory::<f32>(session, target, &value_str, *addr, cout),
+        TypeP::F64(cout) => target_in_memory::<f64>(session, target, &value_str, *addr, cout),
+        TypeP::Bool(cout) => target_in_memory::<bool>(session, target, &value_str, *addr, cout),
+        TypeP::Char(cout) => target_in_memory::<char>(session, target, &value_str, *addr, cout),
         TypeP::Structs(ref fields, _) => {
             for field in fields {
-                match_type(h_proc, field.clone(), value_str.clone(), addr);
+                match_type(session, target, field.clone(), value_str.clone(), addr);
             }
         }
-        TypeP::Ptr(_, cout) => unsafe {
-            match NT_HEADER.unwrap() {
-                NtHeaders::Headers32(_) => target_in_memory::<u32>(h_proc, &value_str, *addr, cout),
-                NtHeaders::Headers64(_) => target_in_memory::<u64>(h_proc, &value_str, *addr, cout),
+        TypeP::Ptr(_, cout) => {
+            match session.get_bitness() {
+                64 => target_in_memory::<u64>(session, target, &value_str, *addr, cout),
+                _ => target_in_memory::<u32>(session, target, &value_str, *addr, cout),
             }
         }
         TypeP::Void => {},
     }
-    *addr += field.type_p.get_size() as u64;
+    *addr += field.type_p.get_size(session.nt_header) as u64;
 }
 
 
 
-fn handle_other_type(h_proc: HANDLE, addr: u64, struct_p: &TypeP, value_str: &str) {
+fn handle_other_type(session: &Session, target: &dyn DebugTarget, addr: u64, struct_p: &TypeP, value_str: &str) {
     let mut addr = addr;
     for field in struct_p.get_field_of_struct() {
         match get_value_str(value_str, &field.name_field) {
-            Ok(value_str) => match_type(h_proc, field, value_str, &mut addr),
+            Ok(value_str) => match_type(session, target, field, value_str, &mut addr),
             Err(err) => eprintln!("{err}"),
         }
     }
@@ -173,6 +154,7 @@ fn handle_other_type(h_proc: HANDLE, addr: u64, struct_p: &TypeP, value_str: &st
 
 
 
+#[cfg(windows)]
 fn get_size(size: &mut usize, type_t: &str) {
     let re = Regex::new(r"\[(.*?)]").unwrap();
     for cap in re.captures_iter(type_t) {
@@ -194,7 +176,7 @@ fn get_size(size: &mut usize, type_t: &str) {
 
 
 
-pub fn target_in_memory<T: ToType + Default + Clone>(h_proc: HANDLE, value_str: &str, target_addr: u64, size: usize) {
+pub fn target_in_memory<T: ToType + Default + Clone>(session: &Session, target: &dyn DebugTarget, value_str: &str, target_addr: u64, size: usize) {
     let mut result: Vec<T> = Vec::new();
     let deref_p = Regex::new(r"(\*+)\(([^*\[\]]+)(?:\[(\d*)])?(\*+)\)(0x[0-9a-fA-F]+)").unwrap();
     let v_part: Vec<&str> = value_str.split(',').map(|s| s.trim()).collect();
@@ -219,14 +201,17 @@ pub fn target_in_memory<T: ToType + Default + Clone>(h_proc: HANDLE, value_str:
                 match str_to::<u64>(addr_str) {
                     Ok(mut addr) => unsafe {
                         for _ in 0..first_ast.len() - 1 {
-                            if ReadProcessMemory(h_proc, addr as LPVOID, ptr::addr_of_mut!(addr) as LPVOID, NT_HEADER.unwrap().get_size_of_arch(), &mut 0) == 0 {
-                                print_lg(LevelPrint::ErrorO, format!("Bad ptr: {:#x} : {}", addr, io::Error::last_os_error()));
-                                continue;
+                            match read_ptr(target, addr, session.get_size_of_arch()) {
+                                Ok(v) => addr = v,
+                                Err(e) => {
+                                    print_lg(LevelPrint::ErrorO, format!("Bad ptr: {:#x} : {e}", addr));
+                                    continue;
+                                }
                             }
                         }
                         let mut value_t = vec![T::default(); count];
-                        if ReadProcessMemory(h_proc, addr as LPVOID, value_t.as_mut_ptr() as LPVOID, size_of::<T>() * count, &mut 0) == 0 {
-                            print_lg(LevelPrint::ErrorO, format!("Error dereferencing memory at address: {:#x} : {}", addr, io::Error::last_os_error()));
+                        if let Err(e) = read_exact(target, addr, slice::from_raw_parts_mut(value_t.as_mut_ptr() as *mut u8, size_of::<T>() * count)) {
+                            print_lg(LevelPrint::ErrorO, format!("Error dereferencing memory at address: {:#x} : {e}", addr));
                             return;
                         }
                         result.extend(value_t);
@@ -246,22 +231,58 @@ pub fn target_in_memory<T: ToType + Default + Clone>(h_proc: HANDLE, value_str:
     }
 
     let ef_size = size.min(result.len()) * size_of::<T>();
-    let mut old_protect = 0;
+    let bytes = unsafe { slice::from_raw_parts(result.as_ptr() as *const u8, ef_size) };
+    if let Err(e) = write_unprotected(target, target_addr, bytes) {
+        print_lg(LevelPrint::ErrorO, format!("Error writing to memory at address: {:#x}: {e}", target_addr));
+    }
+}
 
-    unsafe {
-        if VirtualProtectEx(h_proc, target_addr as LPVOID, ef_size, PAGE_EXECUTE_READWRITE, &mut old_protect) == 0 {
-            print_lg(LevelPrint::ErrorO, format!("Error removing memory protection at address {:#x} : {}", target_addr, io::Error::last_os_error()));
-            return;
-        }
 
-        if WriteProcessMemory(h_proc, target_addr as LPVOID, result.as_ptr() as LPVOID, ef_size, &mut 0) == 0 {
-            print_lg(LevelPrint::ErrorO, format!("Error writing to memory at address: {:#x}: {}", target_addr, io::Error::last_os_error()));
-            return;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbg::target::{MemTarget, PROT_READONLY};
 
-        if VirtualProtectEx(h_proc, target_addr as LPVOID, ef_size, old_protect, &mut old_protect, ) == 0 {
-            print_lg(LevelPrint::ErrorO, format!("Error restoring memory protection at address {:#x}: {}", target_addr, io::Error::last_os_error()));
-            return;
-        }
+    const BASE: u64 = 0x7000;
+
+    fn target() -> MemTarget {
+        let target = MemTarget::new();
+        let mut data = vec![0u8; 0x30];
+        data[..8].copy_from_slice(&(BASE + 0x10).to_le_bytes());
+        data[0x10..0x18].copy_from_slice(&0x1122334455667788u64.to_le_bytes());
+        target.map(BASE, data, PROT_READONLY);
+        target
+    }
+
+    fn read(target: &MemTarget, addr: u64, len: usize) -> Vec<u8> {
+        let mut buf = vec![0u8; len];
+        read_exact(target, addr, &mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn write_values() {
+        let target = target();
+        let session = Session::default();
+        target_mem(&session, &target, "1, 0x302", BASE + 0x20, 2, "u16");
+        assert_eq!(read(&target, BASE + 0x20, 4), [1, 0, 2, 3]);
+        target_mem(&session, &target, "-1, -2, -3", BASE + 0x20, 2, "i8");
+        assert_eq!(read(&target, BASE + 0x20, 4), [0xff, 0xfe, 2, 3]);
+        target_mem(&session, &target, "'AB', 0x43", BASE + 0x28, usize::MAX, "byte");
+        assert_eq!(read(&target, BASE + 0x28, 4), *b"ABC\0");
+        target_mem(&session, &target, "7", BASE + 0x20, 1, "unknown");
+        assert_eq!(read(&target, BASE + 0x20, 1), [0xff]);
+    }
+
+    #[test]
+    fn write_dereferenced_values() {
+        let target = target();
+        let session = Session::default();
+        target_mem(&session, &target, "*(u32*)0x7010", BASE + 0x20, 1, "u32");
+        assert_eq!(read(&target, BASE + 0x20, 4), 0x55667788u32.to_le_bytes());
+        target_mem(&session, &target, "**(u64**)0x7000", BASE + 0x28, 1, "u64");
+        assert_eq!(read(&target, BASE + 0x28, 8), 0x1122334455667788u64.to_le_bytes());
+        target_mem(&session, &target, "*(u16*)0x100000", BASE + 0x20, 1, "u16");
+        assert_eq!(read(&target, BASE + 0x20, 2), [0x88, 0x77]);
     }
 }
@@ -1,20 +1,16 @@
-use crate::dbg::dbg_cmd::x32::info_reg::ToValue32;
-use crate::dbg::dbg_cmd::x64::info_reg::{ToValue, Value};
-use crate::dbg::RealAddr;
-use crate::symbol::SYMBOLS_V;
+use crate::session::Session;
 use crate::usage;
-use std::{io, ptr};
+use std::io;
 use winapi::shared::minwindef::LPVOID;
 use winapi::shared::ntdef::HANDLE;
 use winapi::um::memoryapi::{VirtualProtectEx, VirtualQueryEx};
 use winapi::um::winnt::{
     CONTEXT, MEMORY_BASIC_INFORMATION, PAGE_EXECUTE, PAGE_EXECUTE_READ, PAGE_EXECUTE_READWRITE,
     PAGE_EXECUTE_WRITECOPY, PAGE_NOACCESS, PAGE_READONLY, PAGE_READWRITE, PAGE_WRITECOPY,
-    WOW64_CONTEXT,
 };
-use crate::pefile::{NtHeaders, NT_HEADER};
 use crate::ut::cast::str_to;
 use crate::ut::fmt::*;
+use crate::ut::get_addr_va;
 
 fn protect_to_str(protect: u32) -> &'static str {
     match protect {
@@ -74,44 +70,18 @@ fn change_protect_mem(h_proc: HANDLE, addr: u64, size_mem: usize, new_protect: u
 
 
 
-pub fn change_protect(h_proc: HANDLE, ctx: *const CONTEXT, arg: &[&str]) {
+pub fn change_protect(session: &Session, h_proc: HANDLE, ctx: *const CONTEXT, arg: &[&str]) {
     if arg.len() < 2 {
         print_lg(LevelPrint::WarningO, usage::USAGE_SET_PROTECT);
         return;
     }
 
     let target = arg[0];
-    let addr = match str_to::<u64>(target) {
+    let addr = match get_addr_va(session, target, ctx) {
         Ok(addr) => addr,
-        Err(_) => unsafe {
-            if let Some(sym) = (*ptr::addr_of!(SYMBOLS_V)).symbol_file.iter().find(|s| s.name == target) {
-                sym.real_addr(ctx)
-            } else {
-                match NT_HEADER.unwrap() {
-                    NtHeaders::Headers64(_) => {
-                        let v = (*ctx).str_to_value_ctx(target);
-                        match v {
-                            Value::U64(addr) => addr,
-                            Value::U128(_) => {
-                                print_lg(LevelPrint::Error, "you can't specify a SIMD register".to_string());
-                                return;
-                            }
-                            _ => {
-                                print_lg(LevelPrint::Error, format!("Unknown target: {target}"));
-                                return;
-                            }
-                        }
-                    }
-                    NtHeaders::Headers32(_) => {
-                        let v = (*(ctx as *const WOW64_CONTEXT)).str_to_ctx(target);
-                        if v == 0 {
-                            print_lg(LevelPrint::Error, format!("Unknown target: {target}"));
-                            return;
-                        }
-                        v as u64
-                    }
-                }
-            }
+        Err(e) => {
+            print_lg(LevelPrint::Error, e);
+            return;
         }
     };
 
@@ -1,6 +1,7 @@
 use crate::{dbg, ut};
 use crate::dbg::dbg_cmd::usages::USAGE_DBG_T;
-use crate::pefile::{NtHeaders, NT_HEADER};
+use crate::pefile::NtHeaders;
+use crate::session::Session;
 use ntapi::ntpsapi::{NtQueryInformationThread, THREAD_BASIC_INFORMATION};
 use std::{io, ptr};
 use winapi::shared::minwindef::LPVOID;
@@ -11,10 +12,11 @@ use winapi::um::winnt::{
     CONTEXT, CONTEXT_ALL, HANDLE, THREAD_ALL_ACCESS, WOW64_CONTEXT, WOW64_CONTEXT_ALL,
 };
 use crate::ut::cast::str_to;
+use crate::dbg::target::{DebugTarget, WinTarget};
 use crate::ut::fmt::*;
 
 
-pub fn change_dbg_thread(linev: &[&str], ctx: *mut CONTEXT, h_proc: HANDLE, h_thread1: &mut HANDLE, addr_func: &mut u64) {
+pub fn change_dbg_thread(session: &mut Session, linev: &[&str], ctx: *mut CONTEXT, h_proc: HANDLE, h_thread1: &mut HANDLE, addr_func: &mut u64) {
     if linev.len() < 2 {
         print_lg(LevelPrint::WarningO, USAGE_DBG_T);
         return;
@@ -28,6 +30,15 @@ pub fn change_dbg_thread(linev: &[&str], ctx: *mut CONTEXT, h_proc: HANDLE, h_th
         }
     };
 
+    match WinTarget::new(h_proc).threads() {
+        Ok(threads) if !threads.contains(&tip) => {
+            print_lg(LevelPrint::ErrorO, format!("the thread {tip} does not belong to the debugged process"));
+            return;
+        }
+        Ok(_) => {}
+        Err(e) => print_lg(LevelPrint::Error, format!("failed to enumerate the threads of the process: {e}")),
+    }
+
     unsafe {
         let h_thread = OpenThread(THREAD_ALL_ACCESS, 0, tip);
         if h_thread.is_null() {
@@ -35,7 +46,7 @@ pub fn change_dbg_thread(linev: &[&str], ctx: *mut CONTEXT, h_proc: HANDLE, h_th
             return;
         }
 
-        match NT_HEADER {
+        match session.nt_header {
             Some(NtHeaders::Headers64(_)) => {
                 if SetThreadContext(*h_thread1, ctx) == 0 {
                     print_lg(LevelPrint::Error, format!("failed to set thread context: {}", io::Error::last_os_error()));
@@ -51,7 +62,7 @@ pub fn change_dbg_thread(linev: &[&str], ctx: *mut CONTEXT, h_proc: HANDLE, h_th
                             return;
                         }
                         *ctx = *pctx;
-                        dbg::dbg_cmd::init_cm(*ctx, h_proc, h_thread, addr_func);
+                        dbg::dbg_cmd::init_cm(session, *ctx, h_proc, addr_func);
                     }
                     Err(e) => {
                         print_lg(LevelPrint::Error, e);
@@ -77,9 +88,7 @@ pub fn change_dbg_thread(linev: &[&str], ctx: *mut CONTEXT, h_proc: HANDLE, h_th
                         let w64_ctx = ctx as *mut WOW64_CONTEXT;
                         *w64_ctx = *pctx;
 
-                        let mut addr2 = *addr_func as u32;
-                        dbg::dbg_cmd::x32::init_cm(*w64_ctx, h_proc, h_thread, &mut addr2);
-                        *addr_func = addr2 as u64;
+                        dbg::dbg_cmd::init_cm32(session, *w64_ctx, h_proc, h_thread, addr_func);
                     }
                     Err(e) => print_lg(LevelPrint::Error, e)
                 }
@@ -1,18 +1,31 @@
-use crate::symbol::{sym_init, SrcFile, SymType, SymbolFile, SYMBOLS_V};
+use crate::dbg::memory::unwind::Frame;
+use crate::session::Session;
+#[cfg(windows)]
+use crate::symbol::{sym_init, SrcFile, SymType, SymbolFile};
+#[cfg(windows)]
 use std::ffi::{c_char, CStr};
+#[cfg(windows)]
 use std::{mem, ptr};
+#[cfg(windows)]
 use winapi::shared::minwindef::LPVOID;
-use winapi::shared::ntdef::HANDLE;
+#[cfg(windows)]
 use winapi::um::dbghelp::{AddrModeFlat, StackWalk64, STACKFRAME64};
-use winapi::um::winnt::{CONTEXT, WOW64_CONTEXT};
+#[cfg(windows)]
 use crate::ctx_ptr;
+#[cfg(windows)]
+use crate::dbg::{CONTEXT, HANDLE, WOW64_CONTEXT};
+#[cfg(windows)]
+use crate::dbg::memory::unwind::Unwinder;
+#[cfg(windows)]
+use crate::dbg::target::{Registers, WinTarget};
+#[cfg(windows)]
 use crate::dllib::Dll;
-use crate::pefile::{NtHeaders, NT_HEADER};
+#[cfg(windows)]
+use crate::pefile::NtHeaders;
+#[cfg(windows)]
 use crate::ut::fmt::{print_lg, LevelPrint};
 
-pub static mut ST_FRAME: Vec<STACKFRAME64> = Vec::new();
-pub static mut LEN: usize = 0;
-
+#[cfg(windows)]
 #[repr(C)]
 #[derive(Debug)]
 pub struct LocalSym {
@@ -26,33 +39,43 @@ pub struct LocalSym {
     pub register: u32,
 }
 
-pub unsafe fn get_real_frame(rip: u64) -> Option<STACKFRAME64> {
-    for frame in &*ST_FRAME {
-        if frame.AddrPC.Offset == rip {
+#[cfg(windows)]
+pub fn get_real_frame(session: &Session, rip: u64) -> Option<Frame> {
+    for frame in &session.st_frame {
+        if frame.pc == rip {
             return Some(*frame);
         }
     }
     None
 }
 
-pub unsafe fn get_frame_before_func(rip: u64) -> Option<STACKFRAME64> {
-    for (i, frame) in (*ptr::addr_of!(ST_FRAME)).iter().enumerate() {
-        if frame.AddrPC.Offset == rip {
-            return (*ptr::addr_of!(ST_FRAME)).get(i + 1).cloned();
+pub fn get_frame_before_func(session: &Session, rip: u64) -> Option<Frame> {
+    for (i, frame) in session.st_frame.iter().enumerate() {
+        if frame.pc == rip {
+            return session.st_frame.get(i + 1).cloned();
         }
     }
     None
 }
 
-pub unsafe fn get_frame_st(h_proc: HANDLE, h_thread: HANDLE, ctx: CONTEXT) {
-    let mut ctx = ctx;
-    stack_walk(0x8664, h_proc, h_thread, ptr::addr_of_mut!(ctx) as LPVOID, ctx.Rip, ctx.Rsp, ctx.Rbp);
+#[cfg(windows)]
+pub fn get_frame_st(session: &mut Session, h_proc: HANDLE, ctx: &CONTEXT) {
+    let target = WinTarget::new(h_proc);
+    let mut unwinder = Unwinder::new(&target);
+    if let Some(nt_header) = session.nt_header {
+        if session.base_addr != 0 {
+            unwinder.add_module(session.base_addr, nt_header.size_of_image() as u64, session.func_info.clone());
+        }
+    }
+    session.st_frame = unwinder.backtrace(Registers::from(ctx));
 }
 
 
 
 
-fn stack_walk(machine: u32, h_proc: HANDLE, h_thread: HANDLE, pctx: LPVOID, rip: u64, rsp: u64, rbp: u64) {
+#[cfg(windows)]
+#[allow(clippy::too_many_arguments)]
+fn stack_walk(session: &mut Session, machine: u32, h_proc: HANDLE, h_thread: HANDLE, pctx: LPVOID, rip: u64, rsp: u64, rbp: u64) {
     let mut stack_frame: STACKFRAME64 = unsafe { mem::zeroed() };
     stack_frame.AddrPC.Offset = rip;
     stack_frame.AddrPC.Mode = AddrModeFlat;
@@ -61,49 +84,55 @@ fn stack_walk(machine: u32, h_proc: HANDLE, h_thread: HANDLE, pctx: LPVOID, rip:
     stack_frame.AddrFrame.Offset = rbp;
     stack_frame.AddrFrame.Mode = AddrModeFlat;
     unsafe {
-        if let Err(e) = sym_init(h_proc){
+        if let Err(e) = sym_init(session, h_proc){
             print_lg(LevelPrint::Error, e);
             return;
         }
         while StackWalk64(machine, h_proc, h_thread, &mut stack_frame, pctx, None,
                           Some(winapi::um::dbghelp::SymFunctionTableAccess64), Some(winapi::um::dbghelp::SymGetModuleBase64), None) != 0 {
-            (*ptr::addr_of_mut!(ST_FRAME)).push(stack_frame);
+            session.st_frame.push(Frame {
+                pc: stack_frame.AddrPC.Offset,
+                ret: stack_frame.AddrReturn.Offset,
+                frame: stack_frame.AddrFrame.Offset,
+                stack: stack_frame.AddrStack.Offset,
+            });
         }
     }
 }
 
-pub unsafe fn get_frame_st32(h_proc: HANDLE, h_thread: HANDLE, ctx: WOW64_CONTEXT) {
+#[cfg(windows)]
+pub unsafe fn get_frame_st32(session: &mut Session, h_proc: HANDLE, h_thread: HANDLE, ctx: WOW64_CONTEXT) {
     let mut ctx = ctx;
-    stack_walk(0x14c, h_proc, h_thread, ptr::addr_of_mut!(ctx) as LPVOID, ctx.Eip as u64, ctx.Esp as u64, ctx.Ebp as u64);
+    stack_walk(session, 0x14c, h_proc, h_thread, ptr::addr_of_mut!(ctx) as LPVOID, ctx.Eip as u64, ctx.Esp as u64, ctx.Ebp as u64);
 }
 
-pub unsafe fn get_local_sym(h_proc: HANDLE, addr_sym: u64, ctx: *const CONTEXT) {
+#[cfg(windows)]
+pub unsafe fn get_local_sym(session: &mut Session, h_proc: HANDLE, addr_sym: u64, ctx: *const CONTEXT) {
     match Dll::new("symbol_pe.dll") {
         Ok(dll) => {
             let get_local_var: unsafe extern "C" fn(HANDLE, u64, *const CONTEXT, &mut usize) -> *mut LocalSym = mem::transmute(dll.get_func("GetLocalVar").unwrap());
             let mut len = 0;
-            let pst_frame = (*ptr::addr_of!(ST_FRAME)).clone();
-            let ac_frame = if pst_frame.len() > 0 {pst_frame[0]} else {mem::zeroed()};
+            let ac_frame = session.st_frame.first().copied().unwrap_or_default();
 
             let ctx = {
-                match NT_HEADER {
+                match session.nt_header {
                     Some(NtHeaders::Headers32(_)) => {
                         let mut ctx = *(ctx as *const WOW64_CONTEXT);
                         if ctx.Ebp == 0 {
-                            ctx.Ebp = ac_frame.AddrFrame.Offset as u32;
+                            ctx.Ebp = ac_frame.frame as u32;
                         }
                         if ctx.Esp == 0 {
-                            ctx.Esp = ac_frame.AddrStack.Offset as u32;
+                            ctx.Esp = ac_frame.stack as u32;
                         }
                         ctx_ptr!(ctx)
                     }
                     Some(NtHeaders::Headers64(_)) => {
                         let mut ctx = *ctx;
                         if ctx.Rbp == 0 {
-                            ctx.Rbp = ac_frame.AddrFrame.Offset;
+                            ctx.Rbp = ac_frame.frame;
                         }
                         if ctx.Rsp == 0 {
-                            ctx.Rsp = ac_frame.AddrStack.Offset;
+                            ctx.Rsp = ac_frame.stack;
                         }
                         ptr::addr_of!(ctx)
                     }
@@ -114,7 +143,7 @@ pub unsafe fn get_local_sym(h_proc: HANDLE, addr_sym: u64, ctx: *const CONTEXT)
             if sym.is_null() {
                 return;
             }
-            LEN = len;
+            session.st_len = len;
             let get_tag_str: unsafe extern "C" fn(u32) -> *const c_char = mem::transmute(dll.get_func("GetTagString").unwrap());
             let sym_ar = std::slice::from_raw_parts(sym, len);
             for sym in sym_ar {
@@ -129,7 +158,7 @@ pub unsafe fn get_local_sym(h_proc: HANDLE, addr_sym: u64, ctx: *const CONTEXT)
                     src_file: SrcFile::Ex,
                     symbol_type: SymType::Local,
                 };
-                (*ptr::addr_of_mut!(SYMBOLS_V)).symbol_file.push(sym_file);
+                session.symbols.symbol_file.push(sym_file);
             }
             let free_sym: unsafe extern "C" fn(*mut LocalSym, usize) = mem::transmute(dll.get_func("freeLocalSym").unwrap());
             free_sym(sym, len);
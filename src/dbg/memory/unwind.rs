@@ -0,0 +1,440 @@
+use anyhow::anyhow;
+use iced_x86::{Decoder, DecoderOptions, FlowControl, Instruction, Mnemonic, OpKind, Register};
+use crate::pefile::winnt::RUNTIME_FUNCTION;
+use crate::dbg::target::{read_exact, read_ptr, DebugTarget, Registers, REGION_IMAGE};
+use crate::pefile::function::{self, UnwindInfo, UnwindOp};
+use crate::ut::fmt::{print_lg, LevelPrint};
+
+const MAX_FRAMES: usize = 256;
+const MAX_CHAIN: usize = 32;
+const MAX_EPILOG: usize = 64;
+const RUNTIME_FUNCTION_INDIRECT: u32 = 0x1;
+
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Frame {
+    pub pc: u64,
+    pub ret: u64,
+    pub frame: u64,
+    pub stack: u64,
+}
+
+
+struct Module {
+    base: u64,
+    end: u64,
+    functions: Vec<RUNTIME_FUNCTION>,
+}
+
+
+pub struct Unwinder<'a> {
+    target: &'a dyn DebugTarget,
+    modules: Vec<Module>,
+}
+
+
+fn reg_mut(regs: &mut Registers, reg: u8) -> Option<&mut u64> {
+    Some(match reg {
+        0 => &mut regs.rax,
+        1 => &mut regs.rcx,
+        2 => &mut regs.rdx,
+        3 => &mut regs.rbx,
+        4 => &mut regs.rsp,
+        5 => &mut regs.rbp,
+        6 => &mut regs.rsi,
+        7 => &mut regs.rdi,
+        8 => &mut regs.r8,
+        9 => &mut regs.r9,
+        10 => &mut regs.r10,
+        11 => &mut regs.r11,
+        12 => &mut regs.r12,
+        13 => &mut regs.r13,
+        14 => &mut regs.r14,
+        15 => &mut regs.r15,
+        _ => return None,
+    })
+}
+
+fn reg_number(reg: Register) -> Option<u8> {
+    let n = (reg as u32).wrapping_sub(Register::RAX as u32);
+    (n < 16).then_some(n as u8)
+}
+
+// the stack of the debuggee can hold anything, an address past the end of the address space stops the unwinding
+fn stack_add(addr: u64, n: u64) -> Result<u64, anyhow::Error> {
+    addr.checked_add(n).ok_or_else(|| anyhow!("the stack address {:#x} + {:#x} overflows", addr, n))
+}
+
+
+impl<'a> Unwinder<'a> {
+    pub fn new(target: &'a dyn DebugTarget) -> Self {
+        Unwinder { target, modules: Vec::new() }
+    }
+
+    pub fn add_module(&mut self, base: u64, size: u64, mut functions: Vec<RUNTIME_FUNCTION>) {
+        functions.sort_by_key(|f| f.BeginAddress);
+        self.modules.retain(|m| m.base != base);
+        self.modules.push(Module { base, end: base.saturating_add(size), functions });
+    }
+
+    fn read_u32(&self, addr: u64) -> Result<u32, anyhow::Error> {
+        let mut buf = [0u8; 4];
+        read_exact(self.target, addr, &mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    // the exception directory of a module that is not the file context is read from the process memory
+    fn load_module(&mut self, base: u64) -> Result<(), anyhow::Error> {
+        let mut mz = [0u8; 2];
+        read_exact(self.target, base, &mut mz)?;
+        if mz != *b"MZ" {
+            return Err(anyhow!("no pe image at {:#x}", base));
+        }
+        let nt = base.wrapping_add(self.read_u32(base.wrapping_add(0x3c))? as u64);
+        if self.read_u32(nt)? != 0x4550 {
+            return Err(anyhow!("invalid nt headers at {:#x}", nt));
+        }
+        let opt = nt.wrapping_add(24);
+        let mut magic = [0u8; 2];
+        read_exact(self.target, opt, &mut magic)?;
+        if u16::from_le_bytes(magic) != 0x20b {
+            return Err(anyhow!("the module at {:#x} is not a pe32+ image", base));
+        }
+        let size_of_image = self.read_u32(opt.wrapping_add(56))? as u64;
+        let pdata_rva = self.read_u32(opt.wrapping_add(112 + 3 * 8))? as u64;
+        let pdata_size = self.read_u32(opt.wrapping_add(112 + 3 * 8 + 4))?.min(size_of_image as u32) as usize;
+        let mut pdata = vec![0u8; pdata_size - pdata_size % function::RUNTIME_FUNCTION_SIZE];
+        read_exact(self.target, base.wrapping_add(pdata_rva), &mut pdata)?;
+        let functions = pdata.chunks_exact(function::RUNTIME_FUNCTION_SIZE).map(function::runtime_function).filter(|f| f.BeginAddress != 0).collect();
+        self.add_module(base, size_of_image, functions);
+        Ok(())
+    }
+
+    fn lookup(&mut self, rip: u64) -> Option<(u64, RUNTIME_FUNCTION)> {
+        if !self.modules.iter().any(|m| m.base <= rip && rip < m.end) {
+            let region = self.target.query_region(rip).ok()?;
+            if region.mem_type != REGION_IMAGE || region.alloc_base == 0 {
+                return None;
+            }
+            if let Err(e) = self.load_module(region.alloc_base) {
+                print_lg(LevelPrint::Debug, format!("no unwind data for {:#x} : {e}", rip));
+                self.add_module(region.alloc_base, region.end().saturating_sub(region.alloc_base), Vec::new());
+                return None;
+            }
+        }
+        let module = self.modules.iter().find(|m| m.base <= rip && rip < m.end)?;
+        let mut func = *function::find_function(&module.functions, rip - module.base)?;
+        for _ in 0..MAX_CHAIN {
+            let data = function::unwind_data(&func);
+            if data & RUNTIME_FUNCTION_INDIRECT == 0 {
+                return Some((module.base, func));
+            }
+            let mut bytes = [0u8; function::RUNTIME_FUNCTION_SIZE];
+            read_exact(self.target, module.base.wrapping_add((data & !RUNTIME_FUNCTION_INDIRECT) as u64), &mut bytes).ok()?;
+            func = function::runtime_function(&bytes);
+        }
+        None
+    }
+
+    fn unwind_info(&self, base: u64, func: &RUNTIME_FUNCTION) -> Result<UnwindInfo, anyhow::Error> {
+        let addr = base.wrapping_add(function::unwind_data(func) as u64);
+        let mut header = [0u8; 4];
+        read_exact(self.target, addr, &mut header)?;
+        let mut data = vec![0u8; UnwindInfo::size(&header)];
+        read_exact(self.target, addr, &mut data)?;
+        UnwindInfo::parse(&data)
+    }
+
+    // an epilog is `add rsp, n` or `lea rsp, [fp+n]`, then pops, then a ret or a tail jmp,
+    // its effects are not described by the unwind codes so it is emulated forward
+    fn epilog(&self, regs: &Registers, begin: u64, end: u64) -> Option<Vec<Instruction>> {
+        let mut code = [0u8; MAX_EPILOG];
+        let len = self.target.read_memory(regs.rip, &mut code).ok()?;
+        let mut decoder = Decoder::with_ip(64, &code[..len], regs.rip, DecoderOptions::NONE);
+        let mut insnv = Vec::new();
+        while decoder.can_decode() {
+            let insn = decoder.decode();
+            let first = insnv.is_empty();
+            insnv.push(insn);
+            match insn.mnemonic() {
+                Mnemonic::Add if first && insn.op0_register() == Register::RSP && matches!(insn.op1_kind(), OpKind::Immediate8to64 | OpKind::Immediate32to64) => {}
+                Mnemonic::Lea if first && insn.op0_register() == Register::RSP => {}
+                Mnemonic::Pop if insn.op0_kind() == OpKind::Register && reg_number(insn.op0_register()).is_some() => {}
+                Mnemonic::Ret => return Some(insnv),
+                Mnemonic::Jmp if insn.flow_control() == FlowControl::IndirectBranch => return Some(insnv),
+                Mnemonic::Jmp if !(begin..end).contains(&insn.near_branch_target()) => return Some(insnv),
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    fn unwind_epilog(&self, regs: &mut Registers, insnv: &[Instruction]) -> Result<u64, anyhow::Error> {
+        for insn in insnv {
+            match insn.mnemonic() {
+                Mnemonic::Add => regs.rsp = regs.rsp.wrapping_add(insn.immediate(1)),
+                Mnemonic::Lea => {
+                    let base = reg_number(insn.memory_base()).and_then(|r| reg_mut(regs, r).map(|v| *v)).unwrap_or_default();
+                    regs.rsp = base.wrapping_add(insn.memory_displacement64());
+                }
+                Mnemonic::Pop => {
+                    let value = read_ptr(self.target, regs.rsp, 8)?;
+                    if let Some(reg) = reg_number(insn.op0_register()).and_then(|r| reg_mut(regs, r)) {
+                        *reg = value;
+                    }
+                    regs.rsp = stack_add(regs.rsp, 8)?;
+                }
+                _ => {}
+            }
+        }
+        let frame = regs.rsp;
+        regs.rip = read_ptr(self.target, regs.rsp, 8)?;
+        regs.rsp = stack_add(regs.rsp, 8)?;
+        Ok(frame)
+    }
+
+    // turns `regs` into the registers of the caller and returns the establisher frame of the function
+    pub fn unwind_frame(&mut self, regs: &mut Registers) -> Result<u64, anyhow::Error> {
+        let Some((base, mut func)) = self.lookup(regs.rip) else {
+            let frame = regs.rsp;
+            regs.rip = read_ptr(self.target, regs.rsp, 8)?;
+            regs.rsp = stack_add(regs.rsp, 8)?;
+            return Ok(frame);
+        };
+        let begin = base.wrapping_add(func.BeginAddress as u64);
+        let offset = regs.rip.wrapping_sub(begin);
+        let first = self.unwind_info(base, &func)?;
+        if offset >= first.prolog_size as u64 {
+            if let Some(insnv) = self.epilog(regs, begin, base.wrapping_add(func.EndAddress as u64)) {
+                return self.unwind_epilog(regs, &insnv);
+            }
+        }
+
+        let mut prolog_offset = Some(offset);
+        let mut frame = regs.rsp;
+        let mut mach_frame = false;
+        let mut info = first;
+        for _ in 0..MAX_CHAIN {
+            if info.frame_register != 0 {
+                let fp_set = match prolog_offset {
+                    Some(off) if off < info.prolog_size as u64 => info.codes.iter().any(|c| c.op == UnwindOp::SetFpReg && off >= c.prolog_offset as u64),
+                    _ => true,
+                };
+                if fp_set {
+                    let fp = reg_mut(regs, info.frame_register).map(|v| *v).unwrap_or_default();
+                    frame = fp.wrapping_sub(info.frame_offset as u64 * 16);
+                }
+            }
+            for code in &info.codes {
+                if prolog_offset.is_some_and(|off| off < code.prolog_offset as u64) {
+                    continue;
+                }
+                match code.op {
+                    UnwindOp::PushNonVol(reg) => {
+                        let value = read_ptr(self.target, regs.rsp, 8)?;
+                        if let Some(r) = reg_mut(regs, reg) {
+                            *r = value;
+                        }
+                        regs.rsp = stack_add(regs.rsp, 8)?;
+                    }
+                    UnwindOp::AllocLarge(size) | UnwindOp::AllocSmall(size) => regs.rsp = stack_add(regs.rsp, size as u64)?,
+                    UnwindOp::SetFpReg => regs.rsp = frame,
+                    UnwindOp::SaveNonVol(reg, off) => {
+                        let value = read_ptr(self.target, stack_add(frame, off as u64)?, 8)?;
+                        if let Some(r) = reg_mut(regs, reg) {
+                            *r = value;
+                        }
+                    }
+                    UnwindOp::PushMachFrame(error_code) => {
+                        if error_code {
+                            regs.rsp = stack_add(regs.rsp, 8)?;
+                        }
+                        regs.rip = read_ptr(self.target, regs.rsp, 8)?;
+                        regs.rsp = read_ptr(self.target, stack_add(regs.rsp, 0x18)?, 8)?;
+                        mach_frame = true;
+                    }
+                    UnwindOp::SaveXmm128(..) | UnwindOp::Epilog | UnwindOp::Unknown(_) => {}
+                }
+            }
+            let Some(chained) = info.chained else {
+                break;
+            };
+            func = chained;
+            prolog_offset = None;
+            info = self.unwind_info(base, &func)?;
+        }
+        if !mach_frame {
+            regs.rip = read_ptr(self.target, regs.rsp, 8)?;
+            regs.rsp = stack_add(regs.rsp, 8)?;
+        }
+        Ok(frame)
+    }
+
+    pub fn backtrace(&mut self, regs: Registers) -> Vec<Frame> {
+        let mut frames = Vec::new();
+        let mut regs = regs;
+        while frames.len() < MAX_FRAMES && regs.rip != 0 {
+            let mut caller = regs;
+            let frame = match self.unwind_frame(&mut caller) {
+                Ok(frame) => frame,
+                Err(e) => {
+                    print_lg(LevelPrint::Debug, format!("the stack unwinding stopped at {:#x} : {e}", regs.rip));
+                    break;
+                }
+            };
+            frames.push(Frame { pc: regs.rip, ret: caller.rip, frame, stack: regs.rsp });
+            if caller.rsp <= regs.rsp {
+                break;
+            }
+            regs = caller;
+        }
+        frames
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbg::target::{MemTarget, PROT_EXECUTE_READWRITE, PROT_READWRITE};
+    use crate::pefile::function::UNW_FLAG_CHAININFO;
+
+    const BASE: u64 = 0x140000000;
+    const STACK: u64 = 0x7ff000;
+    const RET: u64 = BASE + 0x1800;
+
+    fn code(prolog_offset: u8, op: u8, info: u8) -> u16 {
+        u16::from_le_bytes([prolog_offset, op | info << 4])
+    }
+
+    fn unwind_info(version: u8, flags: u8, prolog_size: u8, slots: &[u16], trailer: &[u8]) -> Vec<u8> {
+        let mut data = vec![version | flags << 3, prolog_size, slots.len() as u8, 0];
+        data.extend(slots.iter().flat_map(|s| s.to_le_bytes()));
+        if !slots.len().is_multiple_of(2) {
+            data.extend([0, 0]);
+        }
+        data.extend(trailer);
+        data
+    }
+
+    fn ops(data: &[u8]) -> Vec<UnwindOp> {
+        UnwindInfo::parse(data).unwrap().codes.iter().map(|c| c.op).collect()
+    }
+
+    #[test]
+    fn alloc_large() {
+        assert_eq!(ops(&unwind_info(1, 0, 7, &[code(7, 1, 0), 0x30], &[])), [UnwindOp::AllocLarge(0x180)]);
+        assert_eq!(ops(&unwind_info(1, 0, 11, &[code(11, 1, 1), 0x2340, 0x1], &[])), [UnwindOp::AllocLarge(0x12340)]);
+        assert!(UnwindInfo::parse(&[1, 11, 2, 0, 11, 0x11, 0x40, 0x23]).is_err());
+    }
+
+    #[test]
+    fn save_nonvol() {
+        let data = unwind_info(1, 0, 16, &[code(16, 5, 6), 0x8, 0x1, code(8, 4, 3), 0x2], &[]);
+        assert_eq!(ops(&data), [UnwindOp::SaveNonVol(6, 0x10008), UnwindOp::SaveNonVol(3, 0x10)]);
+    }
+
+    #[test]
+    fn chained_info() {
+        let chain = [0x1000u32, 0x1040, 0x2000].iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>();
+        let info = UnwindInfo::parse(&unwind_info(1, UNW_FLAG_CHAININFO, 0, &[code(0, 0, 3)], &chain)).unwrap();
+        let chained = info.chained.unwrap();
+        assert_eq!((chained.BeginAddress, chained.EndAddress, chained.UnwindData), (0x1000, 0x1040, 0x2000));
+        assert_eq!(info.handler, None);
+        assert!(UnwindInfo::parse(&unwind_info(1, UNW_FLAG_CHAININFO, 0, &[], &chain[..8])).is_err());
+    }
+
+    #[test]
+    fn epilog_codes() {
+        // version 2 puts one slot epilog descriptors before the prolog codes
+        let slots = [code(3, 6, 1), code(0x10, 6, 0), code(4, 2, 4)];
+        assert_eq!(ops(&unwind_info(2, 0, 4, &slots, &[])), [UnwindOp::Epilog, UnwindOp::Epilog, UnwindOp::AllocSmall(0x28)]);
+        // in version 1 the op is an unknown two slots code
+        assert_eq!(ops(&unwind_info(1, 0, 4, &slots, &[])), [UnwindOp::Unknown(6), UnwindOp::AllocSmall(0x28)]);
+    }
+
+    // push rbx ; push rbp ; sub rsp, 0x28 ; nops ; add rsp, 0x28 ; pop rbp ; pop rbx ; ret at +0x1000,
+    // sub rsp, 0x28 ; nops in an interrupt handler at +0x1100
+    fn target() -> MemTarget {
+        let mut image = vec![0x90u8; 0x3000];
+        image[0x1000..0x1006].copy_from_slice(&[0x53, 0x55, 0x48, 0x83, 0xec, 0x28]);
+        image[0x1010..0x1017].copy_from_slice(&[0x48, 0x83, 0xc4, 0x28, 0x5d, 0x5b, 0xc3]);
+        image[0x1100..0x1104].copy_from_slice(&[0x48, 0x83, 0xec, 0x28]);
+        let info = unwind_info(1, 0, 6, &[code(6, 2, 4), code(2, 0, 5), code(1, 0, 3)], &[0; 4]);
+        image[0x2000..0x2000 + info.len()].copy_from_slice(&info);
+        let target = MemTarget::new();
+        target.map_image(BASE, image, PROT_EXECUTE_READWRITE);
+        target.map(STACK, vec![0; 0x100], PROT_READWRITE);
+        target
+    }
+
+    fn unwinder(target: &MemTarget, mach_frame_info: u8) -> Unwinder<'_> {
+        let info = unwind_info(1, 0, 4, &[code(4, 2, 4), code(0, 10, mach_frame_info)], &[0; 4]);
+        target.write_memory(BASE + 0x2040, &info).unwrap();
+        let mut unwinder = Unwinder::new(target);
+        unwinder.add_module(BASE, 0x3000, vec![
+            RUNTIME_FUNCTION { BeginAddress: 0x1000, EndAddress: 0x1040, UnwindData: 0x2000 },
+            RUNTIME_FUNCTION { BeginAddress: 0x1100, EndAddress: 0x1140, UnwindData: 0x2040 },
+        ]);
+        unwinder
+    }
+
+    fn write_stack(target: &MemTarget, values: &[(u64, u64)]) {
+        for (addr, value) in values {
+            target.write_memory(*addr, &value.to_le_bytes()).unwrap();
+        }
+    }
+
+    // the caller entered at STACK + 0x80 with rbx = 0x1111 and rbp = 0x2222
+    fn unwind_at(rip: u64, rsp: u64) -> (Registers, u64) {
+        let target = target();
+        write_stack(&target, &[(STACK + 0x80, RET), (STACK + 0x78, 0x1111), (STACK + 0x70, 0x2222)]);
+        let mut regs = Registers { rip, rsp, rbx: 0xdead, rbp: 0xbeef, ..Default::default() };
+        let frame = unwinder(&target, 0).unwind_frame(&mut regs).unwrap();
+        (regs, frame)
+    }
+
+    #[test]
+    fn unwind_body() {
+        let (regs, frame) = unwind_at(BASE + 0x1008, STACK + 0x80 - 0x38);
+        assert_eq!((regs.rip, regs.rsp, regs.rbx, regs.rbp), (RET, STACK + 0x88, 0x1111, 0x2222));
+        assert_eq!(frame, STACK + 0x80 - 0x38);
+    }
+
+    #[test]
+    fn unwind_mid_prolog() {
+        let (regs, _) = unwind_at(BASE + 0x1001, STACK + 0x78);
+        assert_eq!((regs.rip, regs.rsp, regs.rbx, regs.rbp), (RET, STACK + 0x88, 0x1111, 0xbeef));
+        let (regs, _) = unwind_at(BASE + 0x1002, STACK + 0x70);
+        assert_eq!((regs.rip, regs.rsp, regs.rbx, regs.rbp), (RET, STACK + 0x88, 0x1111, 0x2222));
+    }
+
+    #[test]
+    fn unwind_epilog() {
+        let (regs, _) = unwind_at(BASE + 0x1010, STACK + 0x80 - 0x38);
+        assert_eq!((regs.rip, regs.rsp, regs.rbx, regs.rbp), (RET, STACK + 0x88, 0x1111, 0x2222));
+        let (regs, _) = unwind_at(BASE + 0x1015, STACK + 0x78);
+        assert_eq!((regs.rip, regs.rsp, regs.rbx, regs.rbp), (RET, STACK + 0x88, 0x1111, 0xbeef));
+        let (regs, _) = unwind_at(BASE + 0x1016, STACK + 0x80);
+        assert_eq!((regs.rip, regs.rsp), (RET, STACK + 0x88));
+    }
+
+    #[test]
+    fn unwind_overflowing_stack() {
+        let target = target();
+        let mut regs = Registers { rip: BASE + 0x1008, rsp: u64::MAX - 0x10, ..Default::default() };
+        assert!(unwinder(&target, 0).unwind_frame(&mut regs).is_err());
+    }
+
+    #[test]
+    fn unwind_machine_frame() {
+        for error_code in [0, 1] {
+            let target = target();
+            let frame = STACK + 0x40 + error_code as u64 * 8;
+            write_stack(&target, &[(frame, BASE + 0x1234), (frame + 0x18, STACK + 0xe0)]);
+            let mut regs = Registers { rip: BASE + 0x1104, rsp: STACK + 0x40 - 0x28, ..Default::default() };
+            unwinder(&target, error_code).unwind_frame(&mut regs).unwrap();
+            assert_eq!((regs.rip, regs.rsp), (BASE + 0x1234, STACK + 0xe0));
+        }
+    }
+}
@@ -1,46 +1,47 @@
 use crate::command::watchpoint::Watchpts;
-use crate::dbg::{dbg_cmd, DbgState, RealAddr, BASE_ADDR};
-use crate::pefile::NT_HEADER;
-use crate::{pefile, ut, ALL_ELM};
-use std::ops::{BitAnd, BitOrAssign};
-use std::ptr::addr_of;
+use crate::dbg::regs::{RegisterSet, DEBUG_ADDR_REGS};
+use crate::session::Session;
+#[cfg(windows)]
+use crate::{pefile, ut};
+use std::ops::BitOrAssign;
+use iced_x86::Register;
+#[cfg(windows)]
 use std::io;
+#[cfg(windows)]
 use winapi::shared::minwindef::FALSE;
+#[cfg(windows)]
 use winapi::um::handleapi::CloseHandle;
-use winapi::um::minwinbase::DEBUG_EVENT;
+#[cfg(windows)]
 use winapi::um::processthreadsapi::{GetThreadContext, OpenThread, SetThreadContext};
+#[cfg(windows)]
 use winapi::um::winbase::{Wow64GetThreadContext, Wow64SetThreadContext};
+#[cfg(windows)]
 use winapi::um::winnt::*;
-use crate::symbol::SYMBOLS_V;
 use crate::ut::cast::NumConvert;
 use crate::ut::fmt::*;
 
-pub fn clear_dreg(ctx: &mut CONTEXT, reg_index: usize) {
-    match reg_index {
-        0 => ctx.Dr0 = 0,
-        1 => ctx.Dr1 = 0,
-        2 => ctx.Dr2 = 0,
-        3 => ctx.Dr3 = 0,
-        _ => {}
+pub fn clear_dreg(regs: &mut RegisterSet, reg_index: usize) {
+    if let Some(&dreg) = DEBUG_ADDR_REGS.get(reg_index) {
+        let _ = regs.write(dreg, 0);
     }
-    let mask = !(1 << (reg_index * 2));
-    ctx.Dr7 &= mask;
-    ctx.Dr7 &= !(0b1111 << (16 + reg_index * 4));
-    ctx.Dr7 &= !(0b11 << (18 + reg_index * 4));
+    let mut dr7 = regs.read(Register::DR7).unwrap_or_default() as u64;
+    dr7 &= !(1 << (reg_index * 2));
+    dr7 &= !(0b1111 << (16 + reg_index * 4));
+    dr7 &= !(0b11 << (18 + reg_index * 4));
+    let _ = regs.write(Register::DR7, dr7 as u128);
 }
 
-pub fn set_dreg(ctx: &mut CONTEXT, watch: &Watchpts, reg_index: usize) {
-    match reg_index {
-        0 => ctx.Dr0 = watch.real_addr64(*ctx),
-        1 => ctx.Dr1 = watch.real_addr64(*ctx),
-        2 => ctx.Dr2 = watch.real_addr64(*ctx),
-        3 => ctx.Dr3 = watch.real_addr64(*ctx),
-        _ => {}
+pub fn set_dreg(session: &Session, regs: &mut RegisterSet, watch: &Watchpts, reg_index: usize) {
+    if let Some(&dreg) = DEBUG_ADDR_REGS.get(reg_index) {
+        let addr = watch.real_addr_regs(session, regs);
+        let _ = regs.write(dreg, addr as u128);
     }
-    set_dr7::<u64>(&mut ctx.Dr7, reg_index, watch);
+    let mut dr7 = regs.read(Register::DR7).unwrap_or_default() as u64;
+    set_dr7::<u64>(&mut dr7, reg_index, watch);
+    let _ = regs.write(Register::DR7, dr7 as u128);
 }
 
-fn set_dr7<T: NumConvert + std::ops::BitAndAssign + BitOrAssign>(dr7: &mut T, reg_index: usize, watch: &Watchpts) {
+pub(crate) fn set_dr7<T: NumConvert + std::ops::BitAndAssign + BitOrAssign>(dr7: &mut T, reg_index: usize, watch: &Watchpts) {
     *dr7 |= T::from_u64(1 << (reg_index * 2));
     let access_bits = watch.acces_type_to_bits();
     *dr7 &= T::from_u64(!(0b11 << (16 + reg_index * 4)));
@@ -60,114 +61,83 @@ fn set_dr7<T: NumConvert + std::ops::BitAndAssign + BitOrAssign>(dr7: &mut T, re
     *dr7 |= T::from_u64(size_bits << (18 + reg_index * 4));
 }
 
-fn set_watchpoint32(ctx: &mut WOW64_CONTEXT, watch: &Watchpts, reg_index: usize) {
-    match reg_index {
-        0 => ctx.Dr0 = watch.real_addr32(*ctx),
-        1 => ctx.Dr1 = watch.real_addr32(*ctx),
-        2 => ctx.Dr2 = watch.real_addr32(*ctx),
-        3 => ctx.Dr3 = watch.real_addr32(*ctx),
-        _ => {}
-    }
-    set_dr7::<u32>(&mut ctx.Dr7, reg_index, watch);
-}
-
-
-pub unsafe fn set_watchpoint(debug_event: DEBUG_EVENT, _h_proc: HANDLE) {
-    let h_thread = OpenThread(THREAD_ALL_ACCESS, FALSE, debug_event.dwThreadId);
+#[cfg(windows)]
+pub unsafe fn set_watchpoint(session: &Session, tid: u32) {
+    let h_thread = OpenThread(THREAD_ALL_ACCESS, FALSE, tid);
     if !h_thread.is_null() {
-        match &*addr_of!(NT_HEADER) {
-            Some(nt_head) => match nt_head {
-                pefile::NtHeaders::Headers32(_) => {
-                    match ut::mem::alloc_size_align::<WOW64_CONTEXT>() {
-                        Ok(pctx) => {
-                            let ctx = &mut *pctx;
-                            ctx.ContextFlags = WOW64_CONTEXT_ALL;
-                            if Wow64GetThreadContext(h_thread, ctx) == 0 {
-                                print_lg(LevelPrint::Error, format!("failed to get context for set watchpoint, all watchpoint is useless: {}", io::Error::last_os_error()));
-                                return;
-                            }
-                            for (i, watchpts) in (*addr_of!(ALL_ELM)).watchpts.iter().enumerate() {
-                                set_watchpoint32(ctx, watchpts, i);
-                                print_lg(LevelPrint::Debug, format!("activation of watchpoint {} monitoring on register dr{} : {:#x}", i, i, *&raw const BASE_ADDR));
-                            }
-                            if Wow64SetThreadContext(h_thread, ctx) == 0 {
-                                print_lg(LevelPrint::Error, format!("failed to set context for set watchpoint, all watchpoints are useless: {}", io::Error::last_os_error()));
-                                return;
-                            }
+        if let Some(nt_head) = &session.nt_header { match nt_head {
+            pefile::NtHeaders::Headers32(_) => {
+                match ut::mem::alloc_size_align::<WOW64_CONTEXT>() {
+                    Ok(pctx) => {
+                        let ctx = &mut *pctx;
+                        ctx.ContextFlags = WOW64_CONTEXT_ALL;
+                        if Wow64GetThreadContext(h_thread, ctx) == 0 {
+                            print_lg(LevelPrint::Error, format!("failed to get context for set watchpoint, all watchpoint is useless: {}", io::Error::last_os_error()));
+                            return;
+                        }
+                        let mut regs = RegisterSet::from(&*ctx);
+                        for (i, watchpts) in session.all.watchpts.iter().enumerate() {
+                            set_dreg(session, &mut regs, watchpts, i);
+                            print_lg(LevelPrint::Debug, format!("activation of watchpoint {} monitoring on register dr{} : {:#x}", i, i, session.base_addr));
+                        }
+                        regs.write_ctx32(ctx);
+                        if Wow64SetThreadContext(h_thread, ctx) == 0 {
+                            print_lg(LevelPrint::Error, format!("failed to set context for set watchpoint, all watchpoints are useless: {}", io::Error::last_os_error()));
+                            return;
                         }
-                        Err(e) => print_lg(LevelPrint::Error, format!("failed to set context for set watchpoint: {e}")),
                     }
+                    Err(e) => print_lg(LevelPrint::Error, format!("failed to set context for set watchpoint: {e}")),
                 }
-                pefile::NtHeaders::Headers64(_) => {
-                    match ut::mem::alloc_size_align::<CONTEXT>() {
-                        Ok(pctx) => {
-                            let ctx = &mut *pctx;
-                            ctx.ContextFlags = WOW64_CONTEXT_ALL;
-                            if GetThreadContext(h_thread, ctx) == 0 {
-                                print_lg(LevelPrint::Error, format!("failed to get context for set watchpoint, all watchpoint is useless: {}", io::Error::last_os_error()));
-                                return;
-                            }
-                            for (i, watchpts) in (*addr_of!(ALL_ELM)).watchpts.iter().enumerate() {
-                                set_dreg(ctx, watchpts, i);
-                                print_lg(LevelPrint::Debug, format!("activation of watchpoint {} monitoring on register dr{}", i, i));
-                            }
-                            if SetThreadContext(h_thread, ctx) == 0 {
-                                print_lg(LevelPrint::Error, format!("failed to set context for set watchpoint, all watchpoints are useless: {}", io::Error::last_os_error()));
-                                return;
-                            }
+            }
+            pefile::NtHeaders::Headers64(_) => {
+                match ut::mem::alloc_size_align::<CONTEXT>() {
+                    Ok(pctx) => {
+                        let ctx = &mut *pctx;
+                        ctx.ContextFlags = WOW64_CONTEXT_ALL;
+                        if GetThreadContext(h_thread, ctx) == 0 {
+                            print_lg(LevelPrint::Error, format!("failed to get context for set watchpoint, all watchpoint is useless: {}", io::Error::last_os_error()));
+                            return;
+                        }
+                        let mut regs = RegisterSet::from(&*ctx);
+                        for (i, watchpts) in session.all.watchpts.iter().enumerate() {
+                            set_dreg(session, &mut regs, watchpts, i);
+                            print_lg(LevelPrint::Debug, format!("activation of watchpoint {} monitoring on register dr{}", i, i));
+                        }
+                        regs.write_ctx(ctx);
+                        if SetThreadContext(h_thread, ctx) == 0 {
+                            print_lg(LevelPrint::Error, format!("failed to set context for set watchpoint, all watchpoints are useless: {}", io::Error::last_os_error()));
+                            return;
                         }
-                        Err(e) => print_lg(LevelPrint::Error, format!("failed to set context for set watchpoint: {e}")),
                     }
+                    Err(e) => print_lg(LevelPrint::Error, format!("failed to set context for set watchpoint: {e}")),
                 }
-            },
-            None => {}
-        }
+            }
+        } }
         CloseHandle(h_thread);
     } else {
         print_lg(LevelPrint::Error, format!("Failed to open thread: {}", io::Error::last_os_error()));
     }
 }
 
-fn get_acc_addr<T: NumConvert + BitAnd + PartialEq + Copy>(dr6: T, dr0: T, dr1: T, dr2: T, dr3: T) -> T
-where
-    <T as BitAnd>::Output: PartialEq<T>,
-{
-    let zer = T::from_u64(0);
-    if dr6 & T::from_u64(0b0001) != zer {
-        dr0
-    } else if dr6 & T::from_u64(0b0010) != zer {
-        dr1
-    } else if dr6 & T::from_u64(0b0100) != zer {
-        dr2
-    } else if dr6 & T::from_u64(0b1000) != zer {
-        dr3
-    } else {
-        T::from_u64(0)
-    }
+fn get_acc_addr(regs: &RegisterSet) -> u64 {
+    let dr6 = regs.read(Register::DR6).unwrap_or_default();
+    DEBUG_ADDR_REGS.iter().enumerate()
+        .find(|(i, _)| dr6 & (1 << i) != 0)
+        .and_then(|(_, dreg)| regs.read(*dreg))
+        .unwrap_or_default() as u64
 }
 
-unsafe fn get_b(access_addr: u64) -> String{
-    if let Some(sym) = (*addr_of!(SYMBOLS_V)).symbol_file.iter().find(|s| s.offset + BASE_ADDR as i64 == access_addr as i64) {
+fn get_b(session: &Session, access_addr: u64) -> String{
+    if let Some(sym) = session.symbols.symbol_file.iter().find(|s| s.offset + session.base_addr as i64 == access_addr as i64) {
         format!("of {} - {:#x}", sym.name, access_addr)
     } else {
         format!("{:#x}", access_addr)
     }
 }
 
-pub unsafe fn handle_watchpoint64(debug_event: DEBUG_EVENT, h_proc: HANDLE, h_thread: &mut HANDLE, ctx: &mut CONTEXT, continue_dbg: &mut DbgState) {
-    let access_addr = get_acc_addr::<u64>(ctx.Dr6, ctx.Dr0, ctx.Dr1, ctx.Dr2, ctx.Dr3);
-    let name = get_b(access_addr);
-    let except_addr = debug_event.u.Exception().ExceptionRecord.ExceptionAddress;
-    print_lg(LevelPrint::Debug, format!("except address {:#x}, there was access to the address {name}", except_addr as u64));
-    dbg_cmd::x64::cmd_wait(ctx, h_proc, h_thread, continue_dbg);
+pub fn watch_hit(session: &Session, regs: &RegisterSet) -> Option<String> {
+    match regs.read(Register::DR6).unwrap_or_default() & 0xf {
+        0 => None,
+        _ => Some(get_b(session, get_acc_addr(regs))),
+    }
 }
-
-
-
-pub unsafe fn handle_watchpoint32(debug_event: DEBUG_EVENT, h_proc: HANDLE, h_thread: &mut HANDLE, ctx: &mut WOW64_CONTEXT, continue_dbg: &mut DbgState) {
-    let access_addr = get_acc_addr::<u32>(ctx.Dr6, ctx.Dr0, ctx.Dr1, ctx.Dr2, ctx.Dr3);
-    let name = get_b(access_addr as u64);
-    let except_addr = debug_event.u.Exception().ExceptionRecord.ExceptionAddress;
-    print_lg(LevelPrint::Debug, format!("except address {:#x}, there was access to the address {name}", except_addr as u64));
-    dbg_cmd::x32::cmd_wait32(ctx, h_proc, h_thread, continue_dbg);
-}
\ No newline at end of file
@@ -1,21 +1,40 @@
-use std::{io, ptr};
+#[cfg(windows)]
+use std::io;
+#[cfg(windows)]
 use winapi::um::debugapi::DebugActiveProcessStop;
 
-use winapi::um::winnt::{CONTEXT, HANDLE, WOW64_CONTEXT};
-use crate::cli::ALL_ELM;
+use crate::session::Session;
 use crate::ut::fmt::{print_lg, LevelPrint};
 
+#[cfg(windows)]
 pub mod attach;
 pub mod dbg_cmd;
+pub mod event;
+#[cfg(windows)]
 mod exec;
+pub mod gdbserver;
 mod handle_point;
+#[cfg(target_os = "linux")]
+mod linux;
 pub mod memory;
+pub mod regs;
+pub mod remote;
+pub mod target;
+pub mod trace;
 
-const STATUS_WX86_BREAKPOINT: u32 = 0x4000001f;
-const STATUS_WX86_SINGLE_STEP: u32 = 0x4000001e;
+#[cfg(windows)]
+pub use winapi::um::winnt::{CONTEXT, HANDLE, WOW64_CONTEXT};
 
+// outside of windows the registers come from a DebugTarget, the context pointers passed to the commands are always null
+#[cfg(not(windows))]
+#[allow(clippy::upper_case_acronyms)]
+pub enum CONTEXT {}
+#[cfg(not(windows))]
+#[allow(clippy::upper_case_acronyms)]
+pub type HANDLE = *mut std::ffi::c_void;
 
-#[derive(PartialOrd, PartialEq, Copy, Clone)]
+
+#[derive(Debug, PartialOrd, PartialEq, Copy, Clone)]
 pub enum DbgState {
     Continue,
     NeedStop,
@@ -25,74 +44,32 @@ pub enum DbgState {
 
 
 pub trait RealAddr {
-    fn real_addr64(&self, ctx: CONTEXT) -> u64;
-    fn real_addr32(&self, ctx: WOW64_CONTEXT) -> u32;
-    fn real_addr(&self, ctx: *const CONTEXT) -> u64;
+    #[cfg(windows)]
+    fn real_addr64(&self, session: &Session, ctx: CONTEXT) -> u64;
+    #[cfg(windows)]
+    fn real_addr32(&self, session: &Session, ctx: WOW64_CONTEXT) -> u32;
+    fn real_addr(&self, session: &Session, ctx: *const CONTEXT) -> u64;
 }
 
-pub static mut BASE_ADDR: u64 = 0;
-
-pub fn run() {
-    unsafe {
-        if let Some(file) = &(*ptr::addr_of_mut!(ALL_ELM)).file {
-            let arg = (*ptr::addr_of!(ALL_ELM)).arg.clone().unwrap_or_default();
-            let arg_str = format!("{file} {arg}");
-            print_lg(LevelPrint::Debug, format!("cli : {arg_str}"));
-            exec::start_debugging(&arg_str);
-        } else {
-            print_lg(LevelPrint::ErrorO, "Please enter a file path");
-        }
+pub fn run(session: &mut Session) {
+    if let Some(file) = session.all.file.clone() {
+        let arg = session.all.arg.clone().unwrap_or_default();
+        let arg_str = format!("{file} {arg}");
+        print_lg(LevelPrint::Debug, format!("cli : {arg_str}"));
+        #[cfg(windows)]
+        exec::start_debugging(session, &arg_str);
+        #[cfg(target_os = "linux")]
+        linux::start_debugging(session, &file, &arg);
+    } else {
+        print_lg(LevelPrint::ErrorO, "Please enter a file path");
     }
 }
 
 
+#[cfg(windows)]
 pub unsafe fn stop_dbg(pid: u32) {
-    for crt_func in (*ptr::addr_of_mut!(ALL_ELM)).crt_func.iter_mut() {
-        crt_func.addr = 0;
-    }
-    BASE_ADDR = 0;
     if DebugActiveProcessStop(pid) == 0 {
         print_lg(LevelPrint::Error, format!("failed to DebugActiveProcessStop : {}", io::Error::last_os_error()))
     }
 }
 
-
-fn init(h_proc: HANDLE) {
-    unsafe {
-        for hook in &mut (*ptr::addr_of_mut!(ALL_ELM)).hook {
-            if let Err(e) = memory::breakpoint::set_breakpoint(h_proc, hook.target + BASE_ADDR, &mut hook.origin_byte) {
-                print_lg(LevelPrint::Error, e);
-            }
-        }
-
-        for addr in &mut (*ptr::addr_of_mut!(ALL_ELM)).break_rva {
-            if let Err(e) = memory::breakpoint::set_breakpoint(h_proc, addr.addr + BASE_ADDR, &mut addr.origin_b) {
-                print_lg(LevelPrint::Error, e);
-            }
-        }
-
-        for addr in &mut (*ptr::addr_of_mut!(ALL_ELM)).break_ret {
-            if let Err(e) = memory::breakpoint::set_breakpoint(h_proc, addr.addr + BASE_ADDR, &mut addr.origin_b) {
-                print_lg(LevelPrint::Error, e);
-            }
-        }
-
-        for addr in &mut (*ptr::addr_of_mut!(ALL_ELM)).break_ret_va {
-            if let Err(e) = memory::breakpoint::set_breakpoint(h_proc, addr.addr, &mut addr.origin_b) {
-                print_lg(LevelPrint::Error, e);
-            }
-        }
-
-        for addr_over in &mut (*ptr::addr_of_mut!(ALL_ELM)).skip_addr {
-            if let Err(e) = memory::set_addr_over(h_proc, addr_over.addr + BASE_ADDR, &mut addr_over.origin_b) {
-                print_lg(LevelPrint::Error, e);
-            }
-        }
-
-        for crt in (*ptr::addr_of_mut!(ALL_ELM)).crt_func.iter_mut() {
-            if let Err(e) = crt.write_cr_func(h_proc) {
-                print_lg(LevelPrint::Error, e);
-            }
-        }
-    }
-}
\ No newline at end of file
@@ -0,0 +1,396 @@
+use crate::dbg::target::Registers;
+use anyhow::anyhow;
+use iced_x86::Register;
+use std::collections::BTreeMap;
+use crate::dbg::CONTEXT;
+#[cfg(windows)]
+use crate::dbg::WOW64_CONTEXT;
+#[cfg(windows)]
+use winapi::um::winnt::M128A;
+
+pub enum Value {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    Un,
+}
+
+impl Value {
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Value::U8(v) => Some(v as u64),
+            Value::U16(v) => Some(v as u64),
+            Value::U32(v) => Some(v as u64),
+            Value::U64(v) => Some(v),
+            Value::U128(_) | Value::Un => None,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegName {
+    Reg(Register),
+    Flags,
+    Mxcsr,
+}
+
+pub fn parse_reg(name: &str) -> Option<RegName> {
+    let name = name.to_lowercase();
+    match name.as_str() {
+        "eflags" | "eflag" | "flags" | "flag" => return Some(RegName::Flags),
+        "mxcsr" => return Some(RegName::Mxcsr),
+        _ => {}
+    }
+    let name = name.strip_prefix("seg").filter(|n| n.len() == 2).unwrap_or(&name);
+    Register::values().find(|r| format!("{:?}", r).eq_ignore_ascii_case(name)).map(RegName::Reg)
+}
+
+
+pub const GPR64: [&str; 17] = ["rax", "rbx", "rcx", "rdx", "rsi", "rdi", "rbp", "rsp", "rip", "r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15"];
+pub const GPR32: [&str; 9] = ["eax", "ebx", "ecx", "edx", "esi", "edi", "ebp", "esp", "eip"];
+pub const SEGMENTS: [&str; 6] = ["cs", "ds", "es", "fs", "gs", "ss"];
+pub const XMM: [&str; 16] = ["xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm6", "xmm7", "xmm8", "xmm9", "xmm10", "xmm11", "xmm12", "xmm13", "xmm14", "xmm15"];
+pub const DEBUG: [&str; 6] = ["dr0", "dr1", "dr2", "dr3", "dr6", "dr7"];
+
+pub const DEBUG_ADDR_REGS: [Register; 4] = [Register::DR0, Register::DR1, Register::DR2, Register::DR3];
+
+#[cfg(windows)]
+const XMM_REGS: [Register; 16] = [
+    Register::XMM0, Register::XMM1, Register::XMM2, Register::XMM3, Register::XMM4, Register::XMM5, Register::XMM6, Register::XMM7,
+    Register::XMM8, Register::XMM9, Register::XMM10, Register::XMM11, Register::XMM12, Register::XMM13, Register::XMM14, Register::XMM15,
+];
+
+#[cfg(windows)]
+const FXSAVE_MXCSR: usize = 24;
+#[cfg(windows)]
+const FXSAVE_XMM: usize = 160;
+
+
+fn mask(size: usize) -> u128 {
+    if size >= 16 {
+        u128::MAX
+    } else {
+        (1u128 << (size * 8)) - 1
+    }
+}
+
+#[cfg(windows)]
+fn to_m128a(value: u128) -> M128A {
+    M128A { Low: value as u64, High: (value >> 64) as i64 }
+}
+
+#[cfg(windows)]
+fn from_m128a(value: M128A) -> u128 {
+    value.Low as u128 | ((value.High as u64 as u128) << 64)
+}
+
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RegisterSet {
+    pub is64: bool,
+    regs: BTreeMap<Register, u128>,
+    pub eflags: u32,
+    pub mxcsr: u32,
+}
+
+impl RegisterSet {
+    pub fn new(is64: bool) -> RegisterSet {
+        RegisterSet { is64, ..Default::default() }
+    }
+
+    fn slot(&self, reg: Register) -> Option<(Register, usize, usize)> {
+        let slot = if reg == Register::RIP || reg == Register::EIP {
+            (Register::RIP, 0, reg.size())
+        } else if reg.is_gpr() {
+            if !self.is64 && matches!(reg, Register::SPL | Register::BPL | Register::SIL | Register::DIL) {
+                return None;
+            }
+            let shift = if matches!(reg, Register::AH | Register::BH | Register::CH | Register::DH) { 8 } else { 0 };
+            (reg.full_register(), shift, reg.size())
+        } else if reg.is_xmm() {
+            (reg, 0, 16)
+        } else if reg.is_segment_register() || reg.is_dr() {
+            (reg, 0, reg.size())
+        } else {
+            return None;
+        };
+        if !self.is64 && (reg.is_gpr() || reg.is_ip()) && slot.2 == 8 {
+            return None;
+        }
+        self.regs.contains_key(&slot.0).then_some(slot)
+    }
+
+    pub fn read(&self, reg: Register) -> Option<u128> {
+        let (full, shift, size) = self.slot(reg)?;
+        Some((self.regs[&full] >> shift) & mask(size))
+    }
+
+    pub fn write(&mut self, reg: Register, value: u128) -> Result<(), anyhow::Error> {
+        let (full, shift, size) = self.slot(reg).ok_or_else(|| anyhow!("unknown register : {:?}", reg))?;
+        if value > mask(size) {
+            return Err(anyhow!("you cannot put a value above {}bits in {:?}", size * 8, reg));
+        }
+        let old = self.regs.get_mut(&full).unwrap();
+        *old = (*old & !(mask(size) << shift)) | (value << shift);
+        Ok(())
+    }
+
+    pub fn value(&self, name: &str) -> Value {
+        match parse_reg(name) {
+            Some(RegName::Flags) => Value::U32(self.eflags),
+            Some(RegName::Mxcsr) => Value::U32(self.mxcsr),
+            Some(RegName::Reg(reg)) => match self.read(reg) {
+                Some(v) => match self.slot(reg).map(|s| s.2) {
+                    Some(1) => Value::U8(v as u8),
+                    Some(2) => Value::U16(v as u16),
+                    Some(4) => Value::U32(v as u32),
+                    Some(8) => Value::U64(v as u64),
+                    _ => Value::U128(v),
+                },
+                None => Value::Un,
+            },
+            None => Value::Un,
+        }
+    }
+
+    pub fn set(&mut self, name: &str, value: u128) -> Result<(), anyhow::Error> {
+        match parse_reg(name) {
+            Some(RegName::Flags) => {
+                self.eflags = u32::try_from(value).map_err(|_| anyhow!("you cannot put a value above 32bits in this destination"))?;
+                Ok(())
+            }
+            Some(RegName::Mxcsr) => {
+                self.mxcsr = u32::try_from(value).map_err(|_| anyhow!("you cannot put a value above 32bits in this destination"))?;
+                Ok(())
+            }
+            Some(RegName::Reg(reg)) => self.write(reg, value),
+            None => Err(anyhow!("unknown register : {name}")),
+        }
+    }
+
+    pub fn ip(&self) -> u64 {
+        self.regs.get(&Register::RIP).copied().unwrap_or_default() as u64
+    }
+
+    pub fn sp(&self) -> u64 {
+        self.regs.get(&Register::RSP).copied().unwrap_or_default() as u64
+    }
+
+    pub fn gpr_names(&self) -> &'static [&'static str] {
+        if self.is64 { &GPR64 } else { &GPR32 }
+    }
+
+    pub fn xmm_names(&self) -> &'static [&'static str] {
+        if self.is64 { &XMM } else { &XMM[..8] }
+    }
+
+    fn insert(&mut self, reg: Register, value: u128) {
+        self.regs.insert(reg, value);
+    }
+
+    fn get(&self, reg: Register) -> u128 {
+        self.regs.get(&reg).copied().unwrap_or_default()
+    }
+
+    pub fn from_registers(r: &Registers, is64: bool) -> RegisterSet {
+        let mut set = RegisterSet::new(is64);
+        for (reg, value) in [
+            (Register::RAX, r.rax), (Register::RBX, r.rbx), (Register::RCX, r.rcx), (Register::RDX, r.rdx),
+            (Register::RSI, r.rsi), (Register::RDI, r.rdi), (Register::RBP, r.rbp), (Register::RSP, r.rsp),
+            (Register::RIP, r.rip),
+            (Register::DR0, r.dr0), (Register::DR1, r.dr1), (Register::DR2, r.dr2), (Register::DR3, r.dr3), (Register::DR6, r.dr6), (Register::DR7, r.dr7),
+        ] {
+            set.insert(reg, value as u128);
+        }
+        if is64 {
+            for (reg, value) in [
+                (Register::R8, r.r8), (Register::R9, r.r9), (Register::R10, r.r10), (Register::R11, r.r11),
+                (Register::R12, r.r12), (Register::R13, r.r13), (Register::R14, r.r14), (Register::R15, r.r15),
+            ] {
+                set.insert(reg, value as u128);
+            }
+        }
+        for (reg, value) in [(Register::CS, r.cs), (Register::DS, r.ds), (Register::ES, r.es), (Register::FS, r.fs), (Register::GS, r.gs), (Register::SS, r.ss)] {
+            set.insert(reg, value as u128);
+        }
+        set.eflags = r.eflags;
+        set
+    }
+
+    pub fn write_registers(&self, r: &mut Registers) {
+        r.rax = self.get(Register::RAX) as u64; r.rbx = self.get(Register::RBX) as u64; r.rcx = self.get(Register::RCX) as u64; r.rdx = self.get(Register::RDX) as u64;
+        r.rsi = self.get(Register::RSI) as u64; r.rdi = self.get(Register::RDI) as u64; r.rbp = self.get(Register::RBP) as u64; r.rsp = self.get(Register::RSP) as u64;
+        r.rip = self.get(Register::RIP) as u64;
+        if self.is64 {
+            r.r8 = self.get(Register::R8) as u64; r.r9 = self.get(Register::R9) as u64; r.r10 = self.get(Register::R10) as u64; r.r11 = self.get(Register::R11) as u64;
+            r.r12 = self.get(Register::R12) as u64; r.r13 = self.get(Register::R13) as u64; r.r14 = self.get(Register::R14) as u64; r.r15 = self.get(Register::R15) as u64;
+        }
+        r.cs = self.get(Register::CS) as u16; r.ds = self.get(Register::DS) as u16; r.es = self.get(Register::ES) as u16;
+        r.fs = self.get(Register::FS) as u16; r.gs = self.get(Register::GS) as u16; r.ss = self.get(Register::SS) as u16;
+        r.dr0 = self.get(Register::DR0) as u64; r.dr1 = self.get(Register::DR1) as u64; r.dr2 = self.get(Register::DR2) as u64;
+        r.dr3 = self.get(Register::DR3) as u64; r.dr6 = self.get(Register::DR6) as u64; r.dr7 = self.get(Register::DR7) as u64;
+        r.eflags = self.eflags;
+    }
+
+    #[cfg(windows)]
+    pub fn write_ctx(&self, ctx: &mut CONTEXT) {
+        let mut r = Registers::from(&*ctx);
+        self.write_registers(&mut r);
+        r.write_ctx(ctx);
+        ctx.MxCsr = self.mxcsr;
+        let xmm = unsafe { ctx.u.s_mut() };
+        for (i, slot) in [
+            &mut xmm.Xmm0, &mut xmm.Xmm1, &mut xmm.Xmm2, &mut xmm.Xmm3, &mut xmm.Xmm4, &mut xmm.Xmm5, &mut xmm.Xmm6, &mut xmm.Xmm7,
+            &mut xmm.Xmm8, &mut xmm.Xmm9, &mut xmm.Xmm10, &mut xmm.Xmm11, &mut xmm.Xmm12, &mut xmm.Xmm13, &mut xmm.Xmm14, &mut xmm.Xmm15,
+        ].into_iter().enumerate() {
+            *slot = to_m128a(self.get(XMM_REGS[i]));
+        }
+    }
+
+    #[cfg(windows)]
+    pub fn write_ctx32(&self, ctx: &mut WOW64_CONTEXT) {
+        let mut r = Registers::from(&*ctx);
+        self.write_registers(&mut r);
+        r.write_ctx32(ctx);
+        ctx.ExtendedRegisters[FXSAVE_MXCSR..FXSAVE_MXCSR + 4].copy_from_slice(&self.mxcsr.to_le_bytes());
+        for (i, reg) in XMM_REGS[..8].iter().enumerate() {
+            let offset = FXSAVE_XMM + i * 16;
+            ctx.ExtendedRegisters[offset..offset + 16].copy_from_slice(&self.get(*reg).to_le_bytes());
+        }
+    }
+
+    #[cfg(windows)]
+    pub unsafe fn from_ctx_ptr(is64: bool, ctx: *const CONTEXT) -> RegisterSet {
+        if ctx.is_null() {
+            RegisterSet::new(is64)
+        } else if is64 {
+            RegisterSet::from(&*ctx)
+        } else {
+            RegisterSet::from(&*(ctx as *const WOW64_CONTEXT))
+        }
+    }
+
+    #[cfg(windows)]
+    pub unsafe fn write_ctx_ptr(&self, ctx: *mut CONTEXT) {
+        if ctx.is_null() {
+            return;
+        }
+        if self.is64 {
+            self.write_ctx(&mut *ctx);
+        } else {
+            self.write_ctx32(&mut *(ctx as *mut WOW64_CONTEXT));
+        }
+    }
+
+    // there is no thread context outside of windows, the pointer is always null
+    #[cfg(not(windows))]
+    pub unsafe fn from_ctx_ptr(is64: bool, _ctx: *const CONTEXT) -> RegisterSet {
+        RegisterSet::new(is64)
+    }
+
+    #[cfg(not(windows))]
+    pub unsafe fn write_ctx_ptr(&self, _ctx: *mut CONTEXT) {}
+}
+
+
+#[cfg(windows)]
+impl From<&CONTEXT> for RegisterSet {
+    fn from(ctx: &CONTEXT) -> Self {
+        let mut set = RegisterSet::from_registers(&Registers::from(ctx), true);
+        set.mxcsr = ctx.MxCsr;
+        let xmm = unsafe { ctx.u.s() };
+        for (i, value) in [
+            xmm.Xmm0, xmm.Xmm1, xmm.Xmm2, xmm.Xmm3, xmm.Xmm4, xmm.Xmm5, xmm.Xmm6, xmm.Xmm7,
+            xmm.Xmm8, xmm.Xmm9, xmm.Xmm10, xmm.Xmm11, xmm.Xmm12, xmm.Xmm13, xmm.Xmm14, xmm.Xmm15,
+        ].into_iter().enumerate() {
+            set.insert(XMM_REGS[i], from_m128a(value));
+        }
+        set
+    }
+}
+
+#[cfg(windows)]
+impl From<&WOW64_CONTEXT> for RegisterSet {
+    fn from(ctx: &WOW64_CONTEXT) -> Self {
+        let mut set = RegisterSet::from_registers(&Registers::from(ctx), false);
+        set.mxcsr = u32::from_le_bytes(ctx.ExtendedRegisters[FXSAVE_MXCSR..FXSAVE_MXCSR + 4].try_into().unwrap());
+        for (i, reg) in XMM_REGS[..8].iter().enumerate() {
+            let offset = FXSAVE_XMM + i * 16;
+            set.insert(*reg, u128::from_le_bytes(ctx.ExtendedRegisters[offset..offset + 16].try_into().unwrap()));
+        }
+        set
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set32() -> RegisterSet {
+        let regs = Registers { rax: 0x11223344, rsp: 0x19ff70, rip: 0x401000, eflags: 0x246, ..Default::default() };
+        RegisterSet::from_registers(&regs, false)
+    }
+
+    #[test]
+    fn eax_views() {
+        let mut set = set32();
+        assert_eq!(set.value("eax").as_u64(), Some(0x11223344));
+        assert_eq!(set.value("ax").as_u64(), Some(0x3344));
+        assert_eq!(set.value("al").as_u64(), Some(0x44));
+        assert_eq!(set.value("ah").as_u64(), Some(0x33));
+        assert!(matches!(set.value("ax"), Value::U16(_)));
+        assert!(matches!(set.value("ah"), Value::U8(_)));
+
+        set.set("ah", 0xaa).unwrap();
+        assert_eq!(set.value("eax").as_u64(), Some(0x1122aa44));
+        set.set("al", 0xbb).unwrap();
+        set.set("ax", 0x1234).unwrap();
+        assert_eq!(set.value("eax").as_u64(), Some(0x11221234));
+        assert_eq!((set.ip(), set.sp()), (0x401000, 0x19ff70));
+    }
+
+    #[test]
+    fn flags() {
+        let mut set = set32();
+        assert!(matches!(set.value("eflags"), Value::U32(0x246)));
+        set.set("flags", 0x202).unwrap();
+        assert_eq!(set.eflags, 0x202);
+        assert!(set.set("eflags", 1 << 32).is_err());
+        assert_eq!(set.eflags, 0x202);
+    }
+
+    #[test]
+    fn no_64bit_registers_in_32bit_mode() {
+        let mut set = set32();
+        for name in ["rax", "rip", "r8", "r8d", "spl"] {
+            assert!(matches!(set.value(name), Value::Un), "{name}");
+            assert!(set.set(name, 1).is_err(), "{name}");
+        }
+        assert_eq!(set.read(Register::R8), None);
+        assert_eq!(set.gpr_names(), &GPR32);
+        assert_eq!(set.xmm_names().len(), 8);
+
+        let set = RegisterSet::from_registers(&Registers { r8: 0x8888, ..Default::default() }, true);
+        assert_eq!(set.value("r8").as_u64(), Some(0x8888));
+        assert_eq!(set.value("r8l").as_u64(), Some(0x88));
+    }
+
+    #[test]
+    fn write_overflow() {
+        let mut set = set32();
+        assert!(set.set("al", 0x100).is_err());
+        assert!(set.set("ax", 0x1_0000).is_err());
+        assert!(set.set("eax", 1 << 32).is_err());
+        assert_eq!(set.value("eax").as_u64(), Some(0x11223344));
+
+        let mut set = RegisterSet::from_registers(&Registers::default(), true);
+        set.set("rax", u64::MAX as u128).unwrap();
+        assert!(set.set("rax", u64::MAX as u128 + 1).is_err());
+        assert!(set.set("unknown", 0).is_err());
+        assert_eq!(set.value("rax").as_u64(), Some(u64::MAX));
+    }
+}
@@ -0,0 +1,246 @@
+use crate::command::breakpoint::Brkpts;
+use crate::command::watchpoint::{CheckType, FlagTypeMem, Watchpts};
+use crate::dbg::dbg_cmd::generic;
+use crate::dbg::target::gdb::{GdbPoint, GdbStop, GdbTarget};
+use crate::dbg::target::{DebugTarget, Registers};
+use crate::dbg::DbgState;
+use crate::session::Session;
+use crate::ut::cast::str_to;
+use crate::ut::fmt::*;
+
+
+pub fn start_remote(session: &mut Session, addr: &str) {
+    let target = match GdbTarget::connect(addr, session.get_bitness() == 64) {
+        Ok(target) => target,
+        Err(e) => {
+            print_lg(LevelPrint::ErrorO, e);
+            return;
+        }
+    };
+    print_lg(LevelPrint::DebugO, format!("connected to {addr} ({} bits{})", if target.is64 { 64 } else { 32 }, if target.vcont { ", vCont" } else { "" }));
+    session.base_addr = target.offsets().unwrap_or(0);
+
+    let mut tid = match target.stop_reason() {
+        Ok(GdbStop::Signal { tid, .. }) => tid.unwrap_or(0),
+        Ok(_) => {
+            print_lg(LevelPrint::ErrorO, "the remote process is not running");
+            return;
+        }
+        Err(e) => {
+            print_lg(LevelPrint::ErrorO, e);
+            return;
+        }
+    };
+    init(session, &target);
+
+    let mut c_dbg = DbgState::Continue;
+    stop(session, &target, &mut tid, &mut c_dbg);
+    while c_dbg == DbgState::Continue {
+        match target.resume(tid, false) {
+            Ok(GdbStop::Exited(code)) => {
+                print_lg(LevelPrint::DebugO, format!("the process exited with code {code}"));
+                break;
+            }
+            Ok(GdbStop::Killed(sig)) => {
+                print_lg(LevelPrint::DebugO, format!("the process was killed by signal {sig}"));
+                break;
+            }
+            Ok(GdbStop::Signal { sig, tid: new_tid, watch }) => {
+                tid = new_tid.unwrap_or(tid);
+                report_stop(session, &target, tid, sig, watch);
+                stop(session, &target, &mut tid, &mut c_dbg);
+            }
+            Err(e) => {
+                print_lg(LevelPrint::Error, e);
+                break;
+            }
+        }
+    }
+    if c_dbg == DbgState::Stopped {
+        uninit(session, &target);
+        target.detach();
+    }
+    session.base_addr = 0;
+}
+
+
+fn watch_kind(watch: &Watchpts) -> GdbPoint {
+    match (watch.check_type.contains(&CheckType::R), watch.check_type.contains(&CheckType::W)) {
+        (true, true) => GdbPoint::Access,
+        (true, false) => GdbPoint::Read,
+        (false, true) => GdbPoint::Write,
+        (false, false) => GdbPoint::Hardware,
+    }
+}
+
+fn points(session: &Session) -> Vec<(GdbPoint, u64, usize)> {
+    let mut result = Vec::new();
+    for b in &session.all.break_rva {
+        result.push((GdbPoint::Software, b.addr + session.base_addr, 1));
+    }
+    for b in &session.all.break_va {
+        result.push((GdbPoint::Software, b.addr, 1));
+    }
+    for (i, watch) in session.all.watchpts.iter().enumerate() {
+        match watch_addr(session, watch) {
+            Some(addr) => result.push((watch_kind(watch), addr, watch.memory_size.max(1))),
+            None => print_lg(LevelPrint::WarningO, format!("watchpoint {i} is relative to a frame or a register and is not supported on this target")),
+        }
+    }
+    result
+}
+
+fn watch_addr(session: &Session, watch: &Watchpts) -> Option<u64> {
+    match watch.flag_type_mem {
+        FlagTypeMem::MemoryStatic if watch.register.is_empty() => Some(watch.offset as u64 + session.base_addr),
+        FlagTypeMem::VirtualAddr if watch.register.is_empty() => Some(watch.offset as u64),
+        _ => None,
+    }
+}
+
+fn init(session: &Session, target: &GdbTarget) {
+    for (kind, addr, len) in points(session) {
+        if let Err(e) = target.set_point(kind, addr, len) {
+            print_lg(LevelPrint::Error, format!("failed to set the point at {:#x} : {e}", addr));
+        }
+    }
+}
+
+fn uninit(session: &Session, target: &GdbTarget) {
+    for (kind, addr, len) in points(session) {
+        let _ = target.remove_point(kind, addr, len);
+    }
+}
+
+
+fn report_stop(session: &Session, target: &GdbTarget, tid: u32, sig: u8, watch: Option<u64>) {
+    if let Some(addr) = watch {
+        let rip = target.get_registers(tid).map(|r| r.rip).unwrap_or_default();
+        print_lg(LevelPrint::Debug, format!("except address {:#x}, there was access to the address {:#x}", rip, addr));
+        return;
+    }
+    match target.get_registers(tid) {
+        Ok(regs) if sig == 5 && is_brkpt(session, regs.rip) => print_lg(LevelPrint::Debug, format!("Breakpoint hit at address: {:#x}", regs.rip)),
+        Ok(regs) => print_lg(LevelPrint::WarningO, format!("thread {tid} stopped by signal {sig} at {:#x}", regs.rip)),
+        Err(e) => print_lg(LevelPrint::Error, e),
+    }
+}
+
+fn is_brkpt(session: &Session, addr: u64) -> bool {
+    session.all.break_rva.iter().any(|b| b.addr + session.base_addr == addr) || session.all.break_va.iter().any(|b| b.addr == addr)
+}
+
+
+fn handle_remote_cmd(session: &mut Session, target: &GdbTarget, tid: &mut u32, linev: &[&str], regs: &mut Registers) -> bool {
+    match linev.first() {
+        Some(&"si") | Some(&"step") | Some(&"stepi") => match target.set_registers(*tid, regs).and_then(|_| target.resume(*tid, true)) {
+            Ok(GdbStop::Signal { tid: new_tid, .. }) => {
+                *tid = new_tid.unwrap_or(*tid);
+                match target.get_registers(*tid) {
+                    Ok(new_regs) => {
+                        *regs = new_regs;
+                        println!("rip : {ADDR_COLOR}{:#x}{RESET_COLOR}", regs.rip);
+                    }
+                    Err(e) => print_lg(LevelPrint::ErrorO, e),
+                }
+            }
+            Ok(_) => print_lg(LevelPrint::DebugO, "the process is terminated"),
+            Err(e) => print_lg(LevelPrint::ErrorO, e),
+        },
+        Some(&"b") | Some(&"breakpoint") | Some(&"b-va") | Some(&"break-va") => {
+            let is_va = linev[0].contains("va");
+            match linev.get(1).map(|a| str_to::<u64>(a)) {
+                Some(Ok(addr)) => {
+                    let real = if is_va { addr } else { addr + session.base_addr };
+                    match target.set_point(GdbPoint::Software, real, 1) {
+                        Ok(()) => {
+                            let all = &mut session.all;
+                            if is_va { &mut all.break_va } else { &mut all.break_rva }.push(Brkpts::from_addr_no_start(addr));
+                            print_lg(LevelPrint::DebugO, format!("breakpoint set at {:#x}", real));
+                        }
+                        Err(e) => print_lg(LevelPrint::ErrorO, e),
+                    }
+                }
+                Some(Err(e)) => print_lg(LevelPrint::ErrorO, format!("invalid address : {e}")),
+                None => print_lg(LevelPrint::WarningO, "USAGE: b <rva> | b-va <va>"),
+            }
+        }
+        Some(&"rb") | Some(&"delete-breakpoint") | Some(&"remove-b") | Some(&"remove-breakpoint") => match linev.get(1).map(|a| str_to::<u64>(a)) {
+            Some(Ok(addr)) => {
+                let base_addr = session.base_addr;
+                let all = &mut session.all;
+                let found = match all.break_rva.iter().position(|b| b.addr == addr) {
+                    Some(pos) => Some((&mut all.break_rva, pos, addr + base_addr)),
+                    None => all.break_va.iter().position(|b| b.addr == addr).map(|pos| (&mut all.break_va, pos, addr)),
+                };
+                match found {
+                    Some((brkpts, pos, real)) => match target.remove_point(GdbPoint::Software, real, 1) {
+                        Ok(()) => {
+                            brkpts.remove(pos);
+                            print_lg(LevelPrint::DebugO, format!("breakpoint at {:#x} removed", real));
+                        }
+                        Err(e) => print_lg(LevelPrint::ErrorO, e),
+                    },
+                    None => print_lg(LevelPrint::ErrorO, format!("no breakpoint was set at {:#x}", addr)),
+                }
+            }
+            Some(Err(e)) => print_lg(LevelPrint::ErrorO, format!("invalid address : {e}")),
+            None => print_lg(LevelPrint::WarningO, "USAGE: rb <rva | va>"),
+        },
+        Some(&"w") | Some(&"watch") | Some(&"watchpoint") if linev.len() == 1 => println!("{}", crate::usage::USAGE_WATCHPTS),
+        Some(&"w") | Some(&"watch") | Some(&"watchpoint") => match Watchpts::from_str_sym(&session.symbols, &linev[1..].join(" ")) {
+            Ok(_) if session.all.watchpts.len() >= 4 => print_lg(LevelPrint::ErrorO, "you can only place 4 watchpoints"),
+            Ok(watch) => match watch_addr(session, &watch) {
+                Some(addr) => match target.set_point(watch_kind(&watch), addr, watch.memory_size.max(1)) {
+                    Ok(()) => {
+                        print_lg(LevelPrint::DebugO, format!("watchpoint {} was set for watch address {:#x} with access {:?}", session.all.watchpts.len() + 1, addr, watch.check_type));
+                        session.all.watchpts.push(watch);
+                    }
+                    Err(e) => print_lg(LevelPrint::ErrorO, e),
+                },
+                None => print_lg(LevelPrint::ErrorO, "a watchpoint relative to a frame or a register is not supported on a remote target"),
+            },
+            Err(e) => print_lg(LevelPrint::ErrorO, e),
+        },
+        // the stops of a remote target are not matched against hooks and return breakpoints, and its breakpoints are not in its memory
+        Some(&"hook") | Some(&"break-ret") | Some(&"b-ret") | Some(&"b-ret-va") | Some(&"b-retva") | Some(&"remove") | Some(&"reset") =>
+            print_lg(LevelPrint::ErrorO, format!("'{}' is not supported on a remote target", linev[0])),
+        Some(&"thread-info") | Some(&"th-info") => println!("{}Thread id : {}{RESET_COLOR}", MAGENTA, tid),
+        Some(&"debug-thread") | Some(&"dbg-thread") | Some(&"dbg-th") => match linev.get(1).map(|t| str_to::<u32>(t)) {
+            Some(Ok(new_tid)) => match target.get_registers(new_tid) {
+                Ok(new_regs) => {
+                    *tid = new_tid;
+                    *regs = new_regs;
+                    print_lg(LevelPrint::DebugO, format!("now you are on the thread {new_tid}"));
+                }
+                Err(e) => print_lg(LevelPrint::ErrorO, e),
+            },
+            Some(Err(e)) => print_lg(LevelPrint::ErrorO, format!("failed to parse thread id: {e}")),
+            None => print_lg(LevelPrint::WarningO, crate::dbg::dbg_cmd::usages::USAGE_DBG_T),
+        },
+        Some(&"info") if matches!(linev.get(1), Some(&"thread") | Some(&"th")) => match target.threads() {
+            Ok(threads) => for t in threads {
+                println!("{}{:<10}{}", if t == *tid { VALID_COLOR } else { BLUE_COLOR }, t, if t == *tid { " *" } else { "" });
+            },
+            Err(e) => print_lg(LevelPrint::ErrorO, e),
+        },
+        _ => return false,
+    }
+    true
+}
+
+
+fn stop(session: &mut Session, target: &GdbTarget, tid: &mut u32, c_dbg: &mut DbgState) {
+    let mut regs = match target.get_registers(*tid) {
+        Ok(regs) => regs,
+        Err(e) => {
+            print_lg(LevelPrint::Error, e);
+            *c_dbg = DbgState::Stopped;
+            return;
+        }
+    };
+    generic::cmd_wait(session, target, &mut regs, c_dbg, &mut |session, linev, regs| handle_remote_cmd(session, target, tid, linev, regs));
+    if let Err(e) = target.set_registers(*tid, &regs) {
+        print_lg(LevelPrint::Error, format!("error when setting registers: {e}"));
+    }
+}
@@ -0,0 +1,608 @@
+use std::cell::{Cell, RefCell};
+use std::io::{BufReader, Read, Write};
+use std::net::TcpStream;
+use anyhow::anyhow;
+use crate::dbg::target::*;
+
+
+pub enum GdbStop {
+    Signal { sig: u8, tid: Option<u32>, watch: Option<u64> },
+    Exited(u8),
+    Killed(u8),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum GdbPoint {
+    Software = 0,
+    Hardware = 1,
+    Write = 2,
+    Read = 3,
+    Access = 4,
+}
+
+
+pub struct GdbTarget {
+    conn: RspConnection,
+    pub is64: bool,
+    pub vcont: bool,
+    pub xml_features: bool,
+    current_thread: Cell<u32>,
+}
+
+
+pub struct RspConnection {
+    stream: RefCell<BufReader<TcpStream>>,
+}
+
+impl RspConnection {
+    pub fn new(stream: TcpStream) -> Result<Self, anyhow::Error> {
+        stream.set_nodelay(true)?;
+        Ok(RspConnection { stream: RefCell::new(BufReader::new(stream)) })
+    }
+
+    pub fn send_packet(&self, data: &str) -> Result<(), anyhow::Error> {
+        let packet = format!("${data}#{:02x}", checksum(data.as_bytes()));
+        let mut stream = self.stream.borrow_mut();
+        for _ in 0..3 {
+            stream.get_mut().write_all(packet.as_bytes())?;
+            let mut ack = [0u8];
+            stream.read_exact(&mut ack)?;
+            match ack[0] {
+                b'+' => return Ok(()),
+                b'-' => continue,
+                other => return Err(anyhow!("unexpected byte {:#x} instead of an ack", other)),
+            }
+        }
+        Err(anyhow!("the stub rejected the packet '{data}'"))
+    }
+
+    pub fn recv_packet(&self) -> Result<String, anyhow::Error> {
+        let mut stream = self.stream.borrow_mut();
+        let mut byte = [0u8];
+        loop {
+            let mut data = Vec::new();
+            loop {
+                stream.read_exact(&mut byte)?;
+                if byte[0] == b'$' {
+                    break;
+                }
+            }
+            loop {
+                stream.read_exact(&mut byte)?;
+                if byte[0] == b'#' {
+                    break;
+                }
+                data.push(byte[0]);
+            }
+            let mut cs = [0u8; 2];
+            stream.read_exact(&mut cs)?;
+            let expected = u8::from_str_radix(std::str::from_utf8(&cs)?, 16)?;
+            if checksum(&data) != expected {
+                stream.get_mut().write_all(b"-")?;
+                continue;
+            }
+            stream.get_mut().write_all(b"+")?;
+
+            let mut result = Vec::with_capacity(data.len());
+            let mut i = 0;
+            while i < data.len() {
+                match data[i] {
+                    b'}' if i + 1 < data.len() => {
+                        result.push(data[i + 1] ^ 0x20);
+                        i += 1;
+                    }
+                    b'*' if i + 1 < data.len() && !result.is_empty() => {
+                        let last = *result.last().unwrap();
+                        for _ in 0..data[i + 1].saturating_sub(29) {
+                            result.push(last);
+                        }
+                        i += 1;
+                    }
+                    b => result.push(b),
+                }
+                i += 1;
+            }
+            return Ok(String::from_utf8_lossy(&result).to_string());
+        }
+    }
+}
+
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |acc, b| acc.wrapping_add(*b))
+}
+
+pub fn decode_hex(hex: &str) -> Result<Vec<u8>, anyhow::Error> {
+    if !hex.len().is_multiple_of(2) {
+        return Err(anyhow!("invalid hex data : '{hex}'"));
+    }
+    (0..hex.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| anyhow!("invalid hex data : '{hex}'")))
+        .collect()
+}
+
+pub fn encode_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn check_error(reply: &str) -> Result<(), anyhow::Error> {
+    if reply.len() == 3 && reply.starts_with('E') {
+        return Err(anyhow!("the stub returned the error {}", &reply[1..]));
+    }
+    Ok(())
+}
+
+fn parse_stop(reply: &str) -> Result<GdbStop, anyhow::Error> {
+    let code = reply.get(1..3).and_then(|c| u8::from_str_radix(c, 16).ok());
+    match (reply.chars().next(), code) {
+        (Some('W'), Some(code)) => Ok(GdbStop::Exited(code)),
+        (Some('X'), Some(sig)) => Ok(GdbStop::Killed(sig)),
+        (Some('S'), Some(sig)) => Ok(GdbStop::Signal { sig, tid: None, watch: None }),
+        (Some('T'), Some(sig)) => {
+            let mut tid = None;
+            let mut watch = None;
+            for field in reply[3..].split(';') {
+                match field.split_once(':') {
+                    Some(("thread", value)) => tid = u32::from_str_radix(value.rsplit('.').next().unwrap_or(value), 16).ok(),
+                    Some(("watch" | "rwatch" | "awatch", value)) => watch = u64::from_str_radix(value, 16).ok(),
+                    _ => {}
+                }
+            }
+            Ok(GdbStop::Signal { sig, tid, watch })
+        }
+        _ => Err(anyhow!("unexpected stop reply : '{reply}'")),
+    }
+}
+
+
+impl GdbTarget {
+    pub fn connect(addr: &str, is64: bool) -> Result<Self, anyhow::Error> {
+        let stream = TcpStream::connect(addr).map_err(|e| anyhow!("failed to connect to {addr} : {e}"))?;
+        let mut target = GdbTarget {
+            conn: RspConnection::new(stream)?,
+            is64: true,
+            vcont: false,
+            xml_features: false,
+            current_thread: Cell::new(0),
+        };
+        let supported = target.request("qSupported:multiprocess-;swbreak+;hwbreak+;xmlRegisters=i386")?;
+        target.xml_features = supported.split(';').any(|f| f == "qXfer:features:read+");
+        target.vcont = target.request("vCont?")?.split(';').any(|a| a == "s");
+        target.is64 = match target.xml_features {
+            true => target.read_xfer("features", "target.xml").map(|xml| !xml.contains("<architecture>i386</architecture>")).unwrap_or(true),
+            false => is64,
+        };
+        Ok(target)
+    }
+
+    fn recv_packet(&self) -> Result<String, anyhow::Error> {
+        loop {
+            let reply = self.conn.recv_packet()?;
+            if let Some(out) = reply.strip_prefix('O').filter(|o| !o.is_empty() && o.len() % 2 == 0) {
+                if let Ok(text) = decode_hex(out) {
+                    print!("{}", String::from_utf8_lossy(&text));
+                    continue;
+                }
+            }
+            return Ok(reply);
+        }
+    }
+
+    pub fn request(&self, data: &str) -> Result<String, anyhow::Error> {
+        self.conn.send_packet(data)?;
+        self.recv_packet()
+    }
+
+    fn read_xfer(&self, object: &str, annex: &str) -> Result<String, anyhow::Error> {
+        let mut result = String::new();
+        loop {
+            let reply = self.request(&format!("qXfer:{object}:read:{annex}:{:x},{:x}", result.len(), 0xfff))?;
+            check_error(&reply)?;
+            match reply.split_at_checked(1) {
+                Some(("m", part)) => result.push_str(part),
+                Some(("l", part)) => {
+                    result.push_str(part);
+                    return Ok(result);
+                }
+                _ => return Err(anyhow!("unexpected reply to qXfer : '{reply}'")),
+            }
+        }
+    }
+
+    fn select_thread(&self, tid: u32) -> Result<(), anyhow::Error> {
+        if tid == 0 || tid == self.current_thread.get() {
+            return Ok(());
+        }
+        let reply = self.request(&format!("Hg{:x}", tid))?;
+        check_error(&reply)?;
+        self.current_thread.set(tid);
+        Ok(())
+    }
+
+    pub fn stop_reason(&self) -> Result<GdbStop, anyhow::Error> {
+        parse_stop(&self.request("?")?)
+    }
+
+    pub fn offsets(&self) -> Option<u64> {
+        let reply = self.request("qOffsets").ok()?;
+        let text = reply.split(';').find_map(|f| f.strip_prefix("Text="))?;
+        u64::from_str_radix(text, 16).ok()
+    }
+
+    pub fn resume(&self, tid: u32, step: bool) -> Result<GdbStop, anyhow::Error> {
+        let packet = match (step, self.vcont) {
+            (false, true) => "vCont;c".to_string(),
+            (false, false) => "c".to_string(),
+            (true, true) if tid != 0 => format!("vCont;s:{:x}", tid),
+            (true, _) => {
+                if tid != 0 {
+                    check_error(&self.request(&format!("Hc{:x}", tid))?)?;
+                }
+                "s".to_string()
+            }
+        };
+        self.conn.send_packet(&packet)?;
+        let stop = parse_stop(&self.recv_packet()?)?;
+        if let GdbStop::Signal { tid: Some(tid), .. } = stop {
+            self.current_thread.set(0);
+            self.select_thread(tid)?;
+        }
+        Ok(stop)
+    }
+
+    pub fn set_point(&self, kind: GdbPoint, addr: u64, len: usize) -> Result<(), anyhow::Error> {
+        self.point_packet('Z', kind, addr, len)
+    }
+
+    pub fn remove_point(&self, kind: GdbPoint, addr: u64, len: usize) -> Result<(), anyhow::Error> {
+        self.point_packet('z', kind, addr, len)
+    }
+
+    fn point_packet(&self, op: char, kind: GdbPoint, addr: u64, len: usize) -> Result<(), anyhow::Error> {
+        let reply = self.request(&format!("{op}{},{:x},{:x}", kind as u8, addr, len))?;
+        check_error(&reply)?;
+        if reply.is_empty() {
+            return Err(anyhow!("the stub does not support the {op}{} packet", kind as u8));
+        }
+        Ok(())
+    }
+
+    pub fn detach(&self) {
+        let _ = self.request("D");
+    }
+
+    fn read_register(&self, index: usize, size: usize) -> Result<u64, anyhow::Error> {
+        let reply = self.request(&format!("p{:x}", index))?;
+        check_error(&reply)?;
+        let bytes = decode_hex(&reply)?;
+        let mut buf = [0u8; 8];
+        let n = bytes.len().min(size);
+        buf[..n].copy_from_slice(&bytes[..n]);
+        Ok(u64::from_le_bytes(buf))
+    }
+}
+
+
+impl DebugTarget for GdbTarget {
+    fn read_memory(&self, addr: u64, buffer: &mut [u8]) -> Result<usize, anyhow::Error> {
+        let mut done = 0;
+        while done < buffer.len() {
+            let len = (buffer.len() - done).min(0x800);
+            let reply = self.request(&format!("m{:x},{:x}", addr + done as u64, len))?;
+            check_error(&reply).map_err(|e| anyhow!("failed to read memory at address {:#x} : {e}", addr + done as u64))?;
+            let bytes = decode_hex(&reply)?;
+            if bytes.is_empty() {
+                break;
+            }
+            let n = bytes.len().min(buffer.len() - done);
+            buffer[done..done + n].copy_from_slice(&bytes[..n]);
+            done += n;
+        }
+        Ok(done)
+    }
+
+    fn write_memory(&self, addr: u64, buffer: &[u8]) -> Result<usize, anyhow::Error> {
+        for (i, chunk) in buffer.chunks(0x400).enumerate() {
+            let chunk_addr = addr + (i * 0x400) as u64;
+            let reply = self.request(&format!("M{:x},{:x}:{}", chunk_addr, chunk.len(), encode_hex(chunk)))?;
+            check_error(&reply).map_err(|e| anyhow!("failed to write memory at address {:#x} : {e}", chunk_addr))?;
+        }
+        Ok(buffer.len())
+    }
+
+    fn query_region(&self, addr: u64) -> Result<MemRegion, anyhow::Error> {
+        let mut byte = [0u8];
+        match self.read_memory(addr, &mut byte) {
+            Ok(1) => Ok(MemRegion { base: addr & !0xfff, alloc_base: addr & !0xfff, size: 0x1000, state: REGION_COMMIT, protect: PROT_EXECUTE_READWRITE, mem_type: REGION_PRIVATE }),
+            _ => Ok(MemRegion { base: addr & !0xfff, size: 0x1000, state: REGION_FREE, protect: PROT_NOACCESS, ..Default::default() }),
+        }
+    }
+
+    fn protect_region(&self, _addr: u64, _size: usize, _protect: u32) -> Result<u32, anyhow::Error> {
+        Ok(PROT_EXECUTE_READWRITE)
+    }
+
+    fn threads(&self) -> Result<Vec<u32>, anyhow::Error> {
+        let mut result = Vec::new();
+        let mut reply = self.request("qfThreadInfo")?;
+        while let Some(list) = reply.strip_prefix('m') {
+            for tid in list.split(',') {
+                if let Ok(tid) = u32::from_str_radix(tid.rsplit('.').next().unwrap_or(tid), 16) {
+                    result.push(tid);
+                }
+            }
+            reply = self.request("qsThreadInfo")?;
+        }
+        Ok(result)
+    }
+
+    fn get_registers(&self, tid: u32) -> Result<Registers, anyhow::Error> {
+        self.select_thread(tid)?;
+        let reply = self.request("g")?;
+        check_error(&reply).map_err(|e| anyhow!("failed to get registers of thread {tid} : {e}"))?;
+        let bytes = decode_hex(&reply)?;
+        let mut values = Vec::new();
+        let mut off = 0;
+        for (index, size) in gpr_layout(self.is64) {
+            match bytes.get(off..off + size) {
+                Some(raw) => {
+                    let mut buf = [0u8; 8];
+                    buf[..*size].copy_from_slice(raw);
+                    values.push(u64::from_le_bytes(buf));
+                }
+                None => values.push(self.read_register(*index, *size)?),
+            }
+            off += size;
+        }
+        Ok(values_to_regs(self.is64, &values))
+    }
+
+    fn set_registers(&self, tid: u32, r: &Registers) -> Result<(), anyhow::Error> {
+        self.select_thread(tid)?;
+        let reply = self.request("g")?;
+        check_error(&reply)?;
+        let mut bytes = decode_hex(&reply)?;
+        let values = regs_to_values(self.is64, r);
+        let mut off = 0;
+        let mut complete = true;
+        for ((_, size), value) in gpr_layout(self.is64).iter().zip(&values) {
+            match bytes.get_mut(off..off + size) {
+                Some(raw) => raw.copy_from_slice(&value.to_le_bytes()[..*size]),
+                None => complete = false,
+            }
+            off += size;
+        }
+        if complete {
+            let reply = self.request(&format!("G{}", encode_hex(&bytes)))?;
+            if reply == "OK" {
+                return Ok(());
+            }
+        }
+        for ((index, size), value) in gpr_layout(self.is64).iter().zip(&values) {
+            let reply = self.request(&format!("P{:x}={}", index, encode_hex(&value.to_le_bytes()[..*size])))?;
+            check_error(&reply).map_err(|e| anyhow!("failed to set register {index} of thread {tid} : {e}"))?;
+        }
+        Ok(())
+    }
+}
+
+
+pub fn gpr_layout(is64: bool) -> &'static [(usize, usize)] {
+    if is64 {
+        &[(0, 8), (1, 8), (2, 8), (3, 8), (4, 8), (5, 8), (6, 8), (7, 8), (8, 8), (9, 8), (10, 8), (11, 8),
+          (12, 8), (13, 8), (14, 8), (15, 8), (16, 8), (17, 4), (18, 4), (19, 4), (20, 4), (21, 4), (22, 4), (23, 4)]
+    } else {
+        &[(0, 4), (1, 4), (2, 4), (3, 4), (4, 4), (5, 4), (6, 4), (7, 4), (8, 4), (9, 4), (10, 4), (11, 4),
+          (12, 4), (13, 4), (14, 4), (15, 4)]
+    }
+}
+
+pub fn regs_to_values(is64: bool, r: &Registers) -> Vec<u64> {
+    if is64 {
+        vec![r.rax, r.rbx, r.rcx, r.rdx, r.rsi, r.rdi, r.rbp, r.rsp, r.r8, r.r9, r.r10, r.r11, r.r12, r.r13, r.r14, r.r15,
+             r.rip, r.eflags as u64, r.cs as u64, r.ss as u64, r.ds as u64, r.es as u64, r.fs as u64, r.gs as u64]
+    } else {
+        vec![r.rax, r.rcx, r.rdx, r.rbx, r.rsp, r.rbp, r.rsi, r.rdi,
+             r.rip, r.eflags as u64, r.cs as u64, r.ss as u64, r.ds as u64, r.es as u64, r.fs as u64, r.gs as u64]
+    }
+}
+
+pub fn values_to_regs(is64: bool, v: &[u64]) -> Registers {
+    if is64 {
+        Registers {
+            rax: v[0], rbx: v[1], rcx: v[2], rdx: v[3], rsi: v[4], rdi: v[5], rbp: v[6], rsp: v[7],
+            r8: v[8], r9: v[9], r10: v[10], r11: v[11], r12: v[12], r13: v[13], r14: v[14], r15: v[15],
+            rip: v[16], eflags: v[17] as u32,
+            cs: v[18] as u16, ss: v[19] as u16, ds: v[20] as u16, es: v[21] as u16, fs: v[22] as u16, gs: v[23] as u16,
+            ..Default::default()
+        }
+    } else {
+        Registers {
+            rax: v[0], rcx: v[1], rdx: v[2], rbx: v[3], rsp: v[4], rbp: v[5], rsi: v[6], rdi: v[7],
+            rip: v[8], eflags: v[9] as u32,
+            cs: v[10] as u16, ss: v[11] as u16, ds: v[12] as u16, es: v[13] as u16, fs: v[14] as u16, gs: v[15] as u16,
+            ..Default::default()
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    const BASE: u64 = 0x1000;
+
+    fn stub(reply: impl Fn(&str) -> String + Send + 'static) -> GdbTarget {
+        stub_bits(true, reply)
+    }
+
+    fn stub_bits(is64: bool, reply: impl Fn(&str) -> String + Send + 'static) -> GdbTarget {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let conn = RspConnection::new(stream).unwrap();
+            while let Ok(packet) = conn.recv_packet() {
+                let answer = match packet.as_str() {
+                    p if p.starts_with("qSupported") || p == "vCont?" => String::new(),
+                    p => reply(p),
+                };
+                if conn.send_packet(&answer).is_err() {
+                    break;
+                }
+            }
+        });
+        GdbTarget::connect(&addr, is64).unwrap()
+    }
+
+    // answers from a register file of sizes, only the first g_len of them in the g reply, and records every packet
+    fn register_stub(is64: bool, g_len: usize, accept_g: bool) -> (GdbTarget, Arc<Mutex<Vec<String>>>) {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let packets = log.clone();
+        let target = stub_bits(is64, move |p| {
+            packets.lock().unwrap().push(p.to_string());
+            let layout = gpr_layout(is64);
+            match p.chars().next() {
+                Some('g') => encode_hex(&layout[..g_len].iter().enumerate().flat_map(|(i, (_, size))| (i as u64 + 1).to_le_bytes()[..*size].to_vec()).collect::<Vec<u8>>()),
+                Some('p') => {
+                    let index = usize::from_str_radix(&p[1..], 16).unwrap();
+                    encode_hex(&(index as u64 + 1).to_le_bytes()[..layout[index].1])
+                }
+                Some('G') if !accept_g => String::new(),
+                _ => "OK".to_string(),
+            }
+        });
+        (target, log)
+    }
+
+    fn memory(packet: &str, extra: usize, max: usize) -> String {
+        let (addr, len) = packet.strip_prefix('m').and_then(|p| p.split_once(',')).unwrap();
+        let addr = u64::from_str_radix(addr, 16).unwrap() - BASE;
+        let len = (usize::from_str_radix(len, 16).unwrap() + extra).min(max);
+        encode_hex(&(addr as u8..).take(len).collect::<Vec<u8>>())
+    }
+
+    #[test]
+    fn read_longer_reply() {
+        let target = stub(|p| memory(p, 4, usize::MAX));
+        let mut buffer = [0u8; 4];
+        assert_eq!(target.read_memory(BASE + 2, &mut buffer).unwrap(), 4);
+        assert_eq!(buffer, [2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn read_shorter_replies() {
+        let target = stub(|p| memory(p, 0, 3));
+        let mut buffer = [0u8; 8];
+        assert_eq!(target.read_memory(BASE, &mut buffer).unwrap(), 8);
+        assert_eq!(buffer, [0, 1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn read_empty_and_error_replies() {
+        let target = stub(|p| if p.starts_with("m1000,") { String::new() } else { "E14".to_string() });
+        let mut buffer = [0u8; 4];
+        assert_eq!(target.read_memory(BASE, &mut buffer).unwrap(), 0);
+        assert!(target.read_memory(BASE + 0x10, &mut buffer).is_err());
+    }
+
+    #[test]
+    fn stop_replies() {
+        assert!(matches!(parse_stop("W00"), Ok(GdbStop::Exited(0))));
+        assert!(matches!(parse_stop("X09"), Ok(GdbStop::Killed(9))));
+        assert!(matches!(parse_stop("S0b"), Ok(GdbStop::Signal { sig: 11, tid: None, watch: None })));
+        assert!(matches!(parse_stop("T05thread:1f;"), Ok(GdbStop::Signal { sig: 5, tid: Some(0x1f), watch: None })));
+        assert!(matches!(parse_stop("T05thread:p1c.2a;06:0000000000000000;"), Ok(GdbStop::Signal { sig: 5, tid: Some(0x2a), watch: None })));
+        assert!(matches!(parse_stop("T05watch:7ffe0010;thread:3;"), Ok(GdbStop::Signal { sig: 5, tid: Some(3), watch: Some(0x7ffe0010) })));
+        assert!(matches!(parse_stop("T05awatch:404000;"), Ok(GdbStop::Signal { watch: Some(0x404000), .. })));
+        assert!(parse_stop("OK").is_err());
+        assert!(parse_stop("T").is_err());
+    }
+
+    #[test]
+    fn run_length_and_escapes() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let conn = RspConnection::new(TcpStream::connect(listener.local_addr().unwrap()).unwrap()).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+        let body = b"ab* }]}\x03";
+        let mut raw = b"$ab#00".to_vec();
+        raw.push(b'$');
+        raw.extend_from_slice(body);
+        raw.extend_from_slice(format!("#{:02x}", checksum(body)).as_bytes());
+        server.write_all(&raw).unwrap();
+        assert_eq!(conn.recv_packet().unwrap(), "abbbb}#");
+        let mut acks = [0u8; 2];
+        server.read_exact(&mut acks).unwrap();
+        assert_eq!(&acks, b"-+");
+    }
+
+    #[test]
+    fn registers_from_g_reply() {
+        let (target, _) = register_stub(true, 24, true);
+        let regs = target.get_registers(0).unwrap();
+        assert_eq!((regs.rax, regs.rbx, regs.rcx, regs.rsp, regs.r15), (1, 2, 3, 8, 16));
+        assert_eq!((regs.rip, regs.eflags, regs.cs, regs.gs), (17, 18, 19, 24));
+
+        let (target, _) = register_stub(false, 16, true);
+        let regs = target.get_registers(0).unwrap();
+        assert_eq!((regs.rax, regs.rcx, regs.rbx, regs.rsp, regs.rdi), (1, 2, 4, 5, 8));
+        assert_eq!((regs.rip, regs.eflags, regs.gs), (9, 10, 16));
+    }
+
+    #[test]
+    fn registers_missing_from_g_reply() {
+        let (target, log) = register_stub(true, 17, true);
+        let regs = target.get_registers(0).unwrap();
+        assert_eq!((regs.rip, regs.eflags, regs.ss, regs.gs), (17, 18, 20, 24));
+        let log = log.lock().unwrap();
+        assert_eq!(log[log.len() - 7..], ["p11", "p12", "p13", "p14", "p15", "p16", "p17"]);
+    }
+
+    #[test]
+    fn set_registers_with_g_and_p() {
+        let (target, log) = register_stub(true, 24, true);
+        let mut regs = target.get_registers(0).unwrap();
+        regs.rip = 0x401000;
+        regs.eflags = 0x246;
+        target.set_registers(0, &regs).unwrap();
+        let packet = log.lock().unwrap().last().unwrap().clone();
+        let bytes = decode_hex(packet.strip_prefix('G').unwrap()).unwrap();
+        assert_eq!(bytes.len(), 17 * 8 + 7 * 4);
+        assert_eq!(bytes[16 * 8..17 * 8], 0x401000u64.to_le_bytes());
+        assert_eq!(bytes[17 * 8..17 * 8 + 4], 0x246u32.to_le_bytes());
+
+        let (target, log) = register_stub(false, 16, false);
+        let mut regs = target.get_registers(0).unwrap();
+        regs.rip = 0x401000;
+        target.set_registers(0, &regs).unwrap();
+        let log = log.lock().unwrap();
+        assert!(log.iter().any(|p| p.starts_with('G')));
+        assert!(log.contains(&"P8=00104000".to_string()));
+        assert!(log.contains(&"P0=01000000".to_string()));
+        assert_eq!(log.iter().filter(|p| p.starts_with('P')).count(), 16);
+    }
+
+    #[test]
+    fn point_packets() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let packets = log.clone();
+        let target = stub(move |p| {
+            packets.lock().unwrap().push(p.to_string());
+            match p {
+                p if p.starts_with("Z4") => String::new(),
+                p if p.starts_with("Z3") => "E22".to_string(),
+                _ => "OK".to_string(),
+            }
+        });
+        target.set_point(GdbPoint::Software, 0x401000, 1).unwrap();
+        target.set_point(GdbPoint::Write, 0x7000, 4).unwrap();
+        target.remove_point(GdbPoint::Write, 0x7000, 4).unwrap();
+        assert!(target.set_point(GdbPoint::Read, 0x7000, 8).is_err());
+        assert!(target.set_point(GdbPoint::Access, 0x7000, 2).is_err());
+        assert_eq!(*log.lock().unwrap(), ["Z0,401000,1", "Z2,7000,4", "z2,7000,4", "Z3,7000,8", "Z4,7000,2"]);
+    }
+}
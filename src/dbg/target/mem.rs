@@ -0,0 +1,191 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use anyhow::anyhow;
+use crate::dbg::target::*;
+use crate::pefile::Section;
+
+
+// the bytes past data up to size read as zero, they are only allocated when written
+struct MemBlock {
+    data: Vec<u8>,
+    size: u64,
+    protect: u32,
+    mem_type: u32,
+}
+
+
+#[derive(Default)]
+pub struct MemTarget {
+    blocks: RefCell<BTreeMap<u64, MemBlock>>,
+    threads: RefCell<BTreeMap<u32, Registers>>,
+}
+
+impl MemTarget {
+    pub fn new() -> Self {
+        MemTarget::default()
+    }
+
+    pub fn map(&self, base: u64, data: Vec<u8>, protect: u32) {
+        let size = data.len() as u64;
+        self.map_sized(base, data, size, protect, REGION_PRIVATE);
+    }
+
+    #[cfg(test)]
+    pub fn map_image(&self, base: u64, data: Vec<u8>, protect: u32) {
+        let size = data.len() as u64;
+        self.map_sized(base, data, size, protect, REGION_IMAGE);
+    }
+
+    pub fn map_sized(&self, base: u64, mut data: Vec<u8>, size: u64, protect: u32, mem_type: u32) {
+        data.truncate(size as usize);
+        self.blocks.borrow_mut().insert(base, MemBlock { data, size, protect, mem_type });
+    }
+
+    pub fn add_thread(&self, tid: u32, regs: Registers) {
+        self.threads.borrow_mut().insert(tid, regs);
+    }
+}
+
+
+impl DebugTarget for MemTarget {
+    fn read_memory(&self, addr: u64, buffer: &mut [u8]) -> Result<usize, anyhow::Error> {
+        let blocks = self.blocks.borrow();
+        let mut done = 0;
+        while done < buffer.len() {
+            let cur = addr + done as u64;
+            let (base, block) = blocks.range(..=cur).next_back()
+                .filter(|(b, m)| cur - **b < m.size)
+                .ok_or_else(|| anyhow!("address {:#x} is not mapped", cur))?;
+            let off = cur - base;
+            let n = (block.size - off).min((buffer.len() - done) as u64) as usize;
+            let off = off as usize;
+            let stored = block.data.len().saturating_sub(off).min(n);
+            buffer[done..done + stored].copy_from_slice(&block.data[off..off + stored]);
+            buffer[done + stored..done + n].fill(0);
+            done += n;
+        }
+        Ok(done)
+    }
+
+    fn write_memory(&self, addr: u64, buffer: &[u8]) -> Result<usize, anyhow::Error> {
+        let mut blocks = self.blocks.borrow_mut();
+        let mut done = 0;
+        while done < buffer.len() {
+            let cur = addr + done as u64;
+            let (base, block) = blocks.range_mut(..=cur).next_back()
+                .filter(|(b, m)| cur - **b < m.size)
+                .ok_or_else(|| anyhow!("address {:#x} is not mapped", cur))?;
+            if block.protect & (PROT_READWRITE | PROT_EXECUTE_READWRITE) == 0 {
+                return Err(anyhow!("address {:#x} is not writable", cur));
+            }
+            let off = cur - *base;
+            let n = (block.size - off).min((buffer.len() - done) as u64) as usize;
+            let off = off as usize;
+            if block.data.len() < off + n {
+                block.data.resize(off + n, 0);
+            }
+            block.data[off..off + n].copy_from_slice(&buffer[done..done + n]);
+            done += n;
+        }
+        Ok(done)
+    }
+
+    fn query_region(&self, addr: u64) -> Result<MemRegion, anyhow::Error> {
+        let blocks = self.blocks.borrow();
+        match blocks.range(..=addr).next_back() {
+            Some((base, block)) if addr - base < block.size => Ok(MemRegion {
+                base: *base,
+                alloc_base: *base,
+                size: block.size,
+                state: REGION_COMMIT,
+                protect: block.protect,
+                mem_type: block.mem_type,
+            }),
+            _ => {
+                let next = blocks.range(addr..).next().map(|(b, _)| *b).unwrap_or(u64::MAX);
+                Ok(MemRegion { base: addr, alloc_base: 0, size: next - addr, state: REGION_FREE, protect: PROT_NOACCESS, mem_type: 0 })
+            }
+        }
+    }
+
+    fn protect_region(&self, addr: u64, _size: usize, protect: u32) -> Result<u32, anyhow::Error> {
+        let mut blocks = self.blocks.borrow_mut();
+        match blocks.range_mut(..=addr).next_back() {
+            Some((base, block)) if addr - base < block.size => Ok(std::mem::replace(&mut block.protect, protect)),
+            _ => Err(anyhow!("address {:#x} is not mapped", addr)),
+        }
+    }
+
+    fn threads(&self) -> Result<Vec<u32>, anyhow::Error> {
+        Ok(self.threads.borrow().keys().copied().collect())
+    }
+
+    fn get_registers(&self, tid: u32) -> Result<Registers, anyhow::Error> {
+        self.threads.borrow().get(&tid).copied().ok_or_else(|| anyhow!("unknown thread {tid}"))
+    }
+
+    fn set_registers(&self, tid: u32, regs: &Registers) -> Result<(), anyhow::Error> {
+        match self.threads.borrow_mut().get_mut(&tid) {
+            Some(r) => {
+                *r = *regs;
+                Ok(())
+            }
+            None => Err(anyhow!("unknown thread {tid}")),
+        }
+    }
+}
+
+
+
+pub struct ImageTarget<'a>(pub &'a [Section]);
+
+impl DebugTarget for ImageTarget<'_> {
+    fn read_memory(&self, addr: u64, buffer: &mut [u8]) -> Result<usize, anyhow::Error> {
+        match crate::pefile::get_section_of_rva(self.0, addr) {
+            Some(sec) => {
+                let offset = (addr - sec.addr as u64) as usize;
+                let tmp = sec.content.get(offset..).unwrap_or_default();
+                if tmp.len() < buffer.len() {
+                    return Err(anyhow!("address {:#x} + {:#x} is outside the section {}", addr, buffer.len(), sec.name));
+                }
+                buffer.copy_from_slice(&tmp[..buffer.len()]);
+                Ok(buffer.len())
+            }
+            None => Err(anyhow!("invalid address")),
+        }
+    }
+
+    fn write_memory(&self, addr: u64, _buffer: &[u8]) -> Result<usize, anyhow::Error> {
+        Err(anyhow!("cannot write at {:#x} : the process is not started", addr))
+    }
+
+    fn query_region(&self, addr: u64) -> Result<MemRegion, anyhow::Error> {
+        match crate::pefile::get_section_of_rva(self.0, addr) {
+            Some(sec) => Ok(MemRegion {
+                base: sec.addr as u64,
+                alloc_base: 0,
+                size: sec.content.len() as u64,
+                state: REGION_COMMIT,
+                protect: PROT_READONLY,
+                mem_type: REGION_IMAGE,
+            }),
+            None => Err(anyhow!("invalid address")),
+        }
+    }
+
+    fn protect_region(&self, _addr: u64, _size: usize, _protect: u32) -> Result<u32, anyhow::Error> {
+        Ok(PROT_READONLY)
+    }
+
+    fn threads(&self) -> Result<Vec<u32>, anyhow::Error> {
+        Ok(Vec::new())
+    }
+
+    fn get_registers(&self, _tid: u32) -> Result<Registers, anyhow::Error> {
+        Err(anyhow!("the process is not started"))
+    }
+
+    fn set_registers(&self, _tid: u32, _regs: &Registers) -> Result<(), anyhow::Error> {
+        Err(anyhow!("the process is not started"))
+    }
+}
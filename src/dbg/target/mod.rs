@@ -0,0 +1,218 @@
+use anyhow::anyhow;
+
+pub mod gdb;
+pub mod mem;
+#[cfg(target_os = "linux")]
+pub mod ptrace;
+#[cfg(windows)]
+pub mod win;
+
+pub use mem::{ImageTarget, MemTarget};
+#[cfg(windows)]
+pub use win::WinTarget;
+
+pub const REGION_COMMIT: u32 = 0x1000;
+pub const REGION_RESERVE: u32 = 0x2000;
+pub const REGION_FREE: u32 = 0x10000;
+
+pub const REGION_PRIVATE: u32 = 0x20000;
+pub const REGION_MAPPED: u32 = 0x40000;
+pub const REGION_IMAGE: u32 = 0x1000000;
+
+pub const PROT_NOACCESS: u32 = 0x01;
+pub const PROT_READONLY: u32 = 0x02;
+pub const PROT_READWRITE: u32 = 0x04;
+pub const PROT_EXECUTE: u32 = 0x10;
+pub const PROT_EXECUTE_READ: u32 = 0x20;
+pub const PROT_EXECUTE_READWRITE: u32 = 0x40;
+
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MemRegion {
+    pub base: u64,
+    pub alloc_base: u64,
+    pub size: u64,
+    pub state: u32,
+    pub protect: u32,
+    pub mem_type: u32,
+}
+
+impl MemRegion {
+    pub fn end(&self) -> u64 {
+        self.base.saturating_add(self.size)
+    }
+
+    pub fn is_commit(&self) -> bool {
+        self.state == REGION_COMMIT
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Registers {
+    pub rax: u64,
+    pub rbx: u64,
+    pub rcx: u64,
+    pub rdx: u64,
+    pub rsi: u64,
+    pub rdi: u64,
+    pub rbp: u64,
+    pub rsp: u64,
+    pub r8: u64,
+    pub r9: u64,
+    pub r10: u64,
+    pub r11: u64,
+    pub r12: u64,
+    pub r13: u64,
+    pub r14: u64,
+    pub r15: u64,
+    pub rip: u64,
+    pub eflags: u32,
+    pub cs: u16,
+    pub ds: u16,
+    pub es: u16,
+    pub fs: u16,
+    pub gs: u16,
+    pub ss: u16,
+    pub dr0: u64,
+    pub dr1: u64,
+    pub dr2: u64,
+    pub dr3: u64,
+    pub dr6: u64,
+    pub dr7: u64,
+}
+
+pub trait DebugTarget {
+    fn read_memory(&self, addr: u64, buffer: &mut [u8]) -> Result<usize, anyhow::Error>;
+    fn write_memory(&self, addr: u64, buffer: &[u8]) -> Result<usize, anyhow::Error>;
+    fn query_region(&self, addr: u64) -> Result<MemRegion, anyhow::Error>;
+    fn protect_region(&self, addr: u64, size: usize, protect: u32) -> Result<u32, anyhow::Error>;
+    fn threads(&self) -> Result<Vec<u32>, anyhow::Error>;
+    fn get_registers(&self, tid: u32) -> Result<Registers, anyhow::Error>;
+    fn set_registers(&self, tid: u32, regs: &Registers) -> Result<(), anyhow::Error>;
+}
+
+impl<T: DebugTarget + ?Sized> DebugTarget for &T {
+    fn read_memory(&self, addr: u64, buffer: &mut [u8]) -> Result<usize, anyhow::Error> {
+        (**self).read_memory(addr, buffer)
+    }
+
+    fn write_memory(&self, addr: u64, buffer: &[u8]) -> Result<usize, anyhow::Error> {
+        (**self).write_memory(addr, buffer)
+    }
+
+    fn query_region(&self, addr: u64) -> Result<MemRegion, anyhow::Error> {
+        (**self).query_region(addr)
+    }
+
+    fn protect_region(&self, addr: u64, size: usize, protect: u32) -> Result<u32, anyhow::Error> {
+        (**self).protect_region(addr, size, protect)
+    }
+
+    fn threads(&self) -> Result<Vec<u32>, anyhow::Error> {
+        (**self).threads()
+    }
+
+    fn get_registers(&self, tid: u32) -> Result<Registers, anyhow::Error> {
+        (**self).get_registers(tid)
+    }
+
+    fn set_registers(&self, tid: u32, regs: &Registers) -> Result<(), anyhow::Error> {
+        (**self).set_registers(tid, regs)
+    }
+}
+
+
+
+pub fn read_exact(target: &dyn DebugTarget, addr: u64, buffer: &mut [u8]) -> Result<(), anyhow::Error> {
+    let read = target.read_memory(addr, buffer)?;
+    if read != buffer.len() {
+        return Err(anyhow!("only {read} of {} bytes could be read at address {:#x}", buffer.len(), addr));
+    }
+    Ok(())
+}
+
+pub fn read_ptr(target: &dyn DebugTarget, addr: u64, ptr_size: usize) -> Result<u64, anyhow::Error> {
+    let mut buf = [0u8; 8];
+    read_exact(target, addr, &mut buf[..ptr_size])?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+pub fn write_unprotected(target: &dyn DebugTarget, addr: u64, buffer: &[u8]) -> Result<(), anyhow::Error> {
+    let old_protect = target.protect_region(addr, buffer.len(), PROT_EXECUTE_READWRITE)
+        .map_err(|e| anyhow!("failed to remove memory protection at address {:#x} : {e}", addr))?;
+    let written = target.write_memory(addr, buffer);
+    target.protect_region(addr, buffer.len(), old_protect)
+        .map_err(|e| anyhow!("failed to restore memory protection at address {:#x} : {e}", addr))?;
+    match written {
+        Ok(n) if n == buffer.len() => Ok(()),
+        Ok(n) => Err(anyhow!("only {n} of {} bytes were written at address {:#x}", buffer.len(), addr)),
+        Err(e) => Err(anyhow!("failed to write memory at address {:#x} : {e}", addr)),
+    }
+}
+
+pub fn patch_byte(target: &dyn DebugTarget, addr: u64, byte: u8) -> Result<u8, anyhow::Error> {
+    let mut old = [0u8];
+    read_exact(target, addr, &mut old).map_err(|e| anyhow!("failed to read memory at address {:#x} : {e}", addr))?;
+    write_unprotected(target, addr, &[byte])?;
+    Ok(old[0])
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target() -> MemTarget {
+        let target = MemTarget::new();
+        target.map(0x1000, (0..0x10).collect(), PROT_READWRITE);
+        target.map(0x1010, (0x10..0x20).collect(), PROT_READWRITE);
+        target.map(0x2000, vec![0xc3, 0x90, 0x90, 0x90], PROT_EXECUTE_READ);
+        target
+    }
+
+    #[test]
+    fn read_exact_spans_blocks() {
+        let target = target();
+        let mut buf = [0u8; 8];
+        read_exact(&target, 0x100c, &mut buf).unwrap();
+        assert_eq!(buf, [0x0c, 0x0d, 0x0e, 0x0f, 0x10, 0x11, 0x12, 0x13]);
+    }
+
+    #[test]
+    fn read_exact_unmapped() {
+        let target = target();
+        let mut buf = [0u8; 4];
+        assert!(read_exact(&target, 0x101e, &mut buf).is_err());
+        assert!(read_exact(&target, 0x3000, &mut buf).is_err());
+    }
+
+    #[test]
+    fn read_ptr_sizes() {
+        let target = target();
+        assert_eq!(read_ptr(&target, 0x1000, 8).unwrap(), 0x0706050403020100);
+        assert_eq!(read_ptr(&target, 0x1004, 4).unwrap(), 0x07060504);
+        assert!(read_ptr(&target, 0x101c, 8).is_err());
+    }
+
+    #[test]
+    fn patch_byte_restores_protection() {
+        let target = target();
+        assert!(target.write_memory(0x2000, &[0xcc]).is_err());
+        assert_eq!(patch_byte(&target, 0x2000, 0xcc).unwrap(), 0xc3);
+        assert_eq!(read_ptr(&target, 0x2000, 1).unwrap(), 0xcc);
+        assert_eq!(target.query_region(0x2000).unwrap().protect, PROT_EXECUTE_READ);
+        assert_eq!(patch_byte(&target, 0x2000, 0xc3).unwrap(), 0xcc);
+    }
+
+    #[test]
+    fn write_unprotected_readonly() {
+        let target = MemTarget::new();
+        target.map(0x4000, vec![0; 4], PROT_READONLY);
+        write_unprotected(&target, 0x4000, &[1, 2, 3, 4]).unwrap();
+        assert_eq!(read_ptr(&target, 0x4000, 4).unwrap(), 0x04030201);
+        assert_eq!(target.query_region(0x4000).unwrap().protect, PROT_READONLY);
+        assert!(write_unprotected(&target, 0x4002, &[0; 4]).is_err());
+        assert!(write_unprotected(&target, 0x5000, &[0]).is_err());
+    }
+}
@@ -0,0 +1,269 @@
+use std::ffi::CString;
+use std::fs::{self, File, OpenOptions};
+use std::os::unix::fs::FileExt;
+use std::{io, mem, ptr};
+use anyhow::anyhow;
+use crate::dbg::target::*;
+
+
+pub enum PtraceStop {
+    Trap(u32),
+    Signal(u32, i32),
+    Clone(u32, u32),
+    ThreadExited(u32),
+    Exited(i32),
+    Killed(i32),
+}
+
+
+pub struct PtraceTarget {
+    pub pid: u32,
+    mem: File,
+}
+
+
+fn os_err(what: &str) -> anyhow::Error {
+    anyhow!("{what} : {}", io::Error::last_os_error())
+}
+
+fn debugreg_offset(i: usize) -> usize {
+    mem::offset_of!(libc::user, u_debugreg) + i * size_of::<u64>()
+}
+
+
+impl PtraceTarget {
+    pub fn spawn(path: &str, args: &[&str]) -> Result<Self, anyhow::Error> {
+        let c_path = CString::new(path)?;
+        let mut c_args = vec![c_path.clone()];
+        for arg in args {
+            c_args.push(CString::new(*arg)?);
+        }
+        let mut argv: Vec<*const libc::c_char> = c_args.iter().map(|a| a.as_ptr()).collect();
+        argv.push(ptr::null());
+
+        unsafe {
+            let pid = libc::fork();
+            if pid < 0 {
+                return Err(os_err("failed to fork"));
+            }
+            if pid == 0 {
+                libc::ptrace(libc::PTRACE_TRACEME, 0, 0, 0);
+                libc::personality(libc::ADDR_NO_RANDOMIZE as libc::c_ulong);
+                libc::execv(c_path.as_ptr(), argv.as_ptr());
+                libc::_exit(127);
+            }
+
+            let mut status = 0;
+            if libc::waitpid(pid, &mut status, 0) < 0 {
+                return Err(os_err("failed to wait for the debuggee"));
+            }
+            if !libc::WIFSTOPPED(status) {
+                return Err(anyhow!("failed to execute '{path}'"));
+            }
+            let options = libc::PTRACE_O_EXITKILL | libc::PTRACE_O_TRACECLONE;
+            if libc::ptrace(libc::PTRACE_SETOPTIONS, pid, 0, options) < 0 {
+                return Err(os_err("failed to set ptrace options"));
+            }
+            Self::open(pid as u32)
+        }
+    }
+
+    fn open(pid: u32) -> Result<Self, anyhow::Error> {
+        let mem = OpenOptions::new().read(true).write(true).open(format!("/proc/{pid}/mem"))
+            .map_err(|e| anyhow!("failed to open the memory of process {pid} : {e}"))?;
+        Ok(PtraceTarget { pid, mem })
+    }
+
+    pub fn image_base(&self, path: &str) -> Result<u64, anyhow::Error> {
+        let full_path = fs::canonicalize(path)?;
+        let full_path = full_path.to_string_lossy();
+        self.maps()?.iter()
+            .filter(|(_, path)| *path == full_path)
+            .map(|(region, _)| region.base)
+            .min()
+            .ok_or_else(|| anyhow!("'{full_path}' is not mapped in process {}", self.pid))
+    }
+
+    fn maps(&self) -> Result<Vec<(MemRegion, String)>, anyhow::Error> {
+        let maps = fs::read_to_string(format!("/proc/{}/maps", self.pid))?;
+        let mut result = Vec::new();
+        for line in maps.lines() {
+            let mut fields = line.split_whitespace();
+            let (range, perms) = match (fields.next(), fields.next()) {
+                (Some(range), Some(perms)) => (range, perms),
+                _ => continue,
+            };
+            let path = fields.nth(3).unwrap_or_default().to_string();
+            let (start, end) = match range.split_once('-') {
+                Some((s, e)) => (u64::from_str_radix(s, 16)?, u64::from_str_radix(e, 16)?),
+                None => continue,
+            };
+            let mem_type = if path.starts_with('/') {
+                REGION_IMAGE
+            } else if path.is_empty() || perms.ends_with('p') {
+                REGION_PRIVATE
+            } else {
+                REGION_MAPPED
+            };
+            result.push((MemRegion {
+                base: start,
+                alloc_base: start,
+                size: end - start,
+                state: REGION_COMMIT,
+                protect: perms_to_prot(perms),
+                mem_type,
+            }, path));
+        }
+        Ok(result)
+    }
+
+    pub fn cont(&self, tid: u32, sig: i32) -> Result<(), anyhow::Error> {
+        if unsafe { libc::ptrace(libc::PTRACE_CONT, tid as libc::pid_t, 0, sig) } < 0 {
+            return Err(os_err(&format!("failed to continue thread {tid}")));
+        }
+        Ok(())
+    }
+
+    pub fn step(&self, tid: u32, sig: i32) -> Result<(), anyhow::Error> {
+        if unsafe { libc::ptrace(libc::PTRACE_SINGLESTEP, tid as libc::pid_t, 0, sig) } < 0 {
+            return Err(os_err(&format!("failed to single step thread {tid}")));
+        }
+        Ok(())
+    }
+
+    pub fn fault_addr(&self, tid: u32) -> u64 {
+        let mut info: libc::siginfo_t = unsafe { mem::zeroed() };
+        if unsafe { libc::ptrace(libc::PTRACE_GETSIGINFO, tid as libc::pid_t, 0, &mut info) } < 0 {
+            return 0;
+        }
+        unsafe { info.si_addr() as u64 }
+    }
+
+    pub fn kill(&self) {
+        unsafe { libc::kill(self.pid as libc::pid_t, libc::SIGKILL) };
+    }
+
+    pub fn wait(&self) -> Result<PtraceStop, anyhow::Error> {
+        let mut status = 0;
+        let tid = unsafe { libc::waitpid(-1, &mut status, libc::__WALL) };
+        if tid < 0 {
+            return Err(os_err("failed to wait for the debuggee"));
+        }
+        let tid = tid as u32;
+        if libc::WIFEXITED(status) {
+            return Ok(if tid == self.pid { PtraceStop::Exited(libc::WEXITSTATUS(status)) } else { PtraceStop::ThreadExited(tid) });
+        }
+        if libc::WIFSIGNALED(status) {
+            return Ok(if tid == self.pid { PtraceStop::Killed(libc::WTERMSIG(status)) } else { PtraceStop::ThreadExited(tid) });
+        }
+        let sig = libc::WSTOPSIG(status);
+        if sig == libc::SIGTRAP && status >> 16 == libc::PTRACE_EVENT_CLONE {
+            let mut new_tid: libc::c_ulong = 0;
+            unsafe { libc::ptrace(libc::PTRACE_GETEVENTMSG, tid as libc::pid_t, 0, &mut new_tid) };
+            return Ok(PtraceStop::Clone(tid, new_tid as u32));
+        }
+        Ok(match sig {
+            libc::SIGTRAP => PtraceStop::Trap(tid),
+            _ => PtraceStop::Signal(tid, sig),
+        })
+    }
+}
+
+
+fn perms_to_prot(perms: &str) -> u32 {
+    let b = perms.as_bytes();
+    match (b.first() == Some(&b'r'), b.get(1) == Some(&b'w'), b.get(2) == Some(&b'x')) {
+        (_, true, true) => PROT_EXECUTE_READWRITE,
+        (_, false, true) => if b[0] == b'r' { PROT_EXECUTE_READ } else { PROT_EXECUTE },
+        (_, true, false) => PROT_READWRITE,
+        (true, false, false) => PROT_READONLY,
+        (false, false, false) => PROT_NOACCESS,
+    }
+}
+
+
+impl DebugTarget for PtraceTarget {
+    fn read_memory(&self, addr: u64, buffer: &mut [u8]) -> Result<usize, anyhow::Error> {
+        self.mem.read_at(buffer, addr).map_err(|e| anyhow!("failed to read memory at address {:#x} : {e}", addr))
+    }
+
+    fn write_memory(&self, addr: u64, buffer: &[u8]) -> Result<usize, anyhow::Error> {
+        self.mem.write_at(buffer, addr).map_err(|e| anyhow!("failed to write memory at address {:#x} : {e}", addr))
+    }
+
+    fn query_region(&self, addr: u64) -> Result<MemRegion, anyhow::Error> {
+        let maps = self.maps()?;
+        if let Some((region, _)) = maps.iter().find(|(r, _)| r.base <= addr && addr < r.end()) {
+            return Ok(*region);
+        }
+        let base = maps.iter().map(|(r, _)| r.end()).filter(|end| *end <= addr).max().unwrap_or(0);
+        let end = maps.iter().map(|(r, _)| r.base).filter(|b| *b > addr).min().unwrap_or(u64::MAX);
+        Ok(MemRegion { base, size: end - base, state: REGION_FREE, protect: PROT_NOACCESS, ..Default::default() })
+    }
+
+    fn protect_region(&self, addr: u64, _size: usize, _protect: u32) -> Result<u32, anyhow::Error> {
+        Ok(self.query_region(addr)?.protect)
+    }
+
+    fn threads(&self) -> Result<Vec<u32>, anyhow::Error> {
+        let mut result = Vec::new();
+        for entry in fs::read_dir(format!("/proc/{}/task", self.pid))? {
+            if let Ok(tid) = entry?.file_name().to_string_lossy().parse::<u32>() {
+                result.push(tid);
+            }
+        }
+        Ok(result)
+    }
+
+    fn get_registers(&self, tid: u32) -> Result<Registers, anyhow::Error> {
+        unsafe {
+            let mut regs: libc::user_regs_struct = mem::zeroed();
+            if libc::ptrace(libc::PTRACE_GETREGS, tid as libc::pid_t, 0, &mut regs) < 0 {
+                return Err(os_err(&format!("failed to get registers of thread {tid}")));
+            }
+            let mut dr = [0u64; 8];
+            for i in [0, 1, 2, 3, 6, 7] {
+                *libc::__errno_location() = 0;
+                let value = libc::ptrace(libc::PTRACE_PEEKUSER, tid as libc::pid_t, debugreg_offset(i), 0);
+                if value == -1 && *libc::__errno_location() != 0 {
+                    return Err(os_err(&format!("failed to read dr{i} of thread {tid}")));
+                }
+                dr[i] = value as u64;
+            }
+            Ok(Registers {
+                rax: regs.rax, rbx: regs.rbx, rcx: regs.rcx, rdx: regs.rdx,
+                rsi: regs.rsi, rdi: regs.rdi, rbp: regs.rbp, rsp: regs.rsp,
+                r8: regs.r8, r9: regs.r9, r10: regs.r10, r11: regs.r11,
+                r12: regs.r12, r13: regs.r13, r14: regs.r14, r15: regs.r15,
+                rip: regs.rip,
+                eflags: regs.eflags as u32,
+                cs: regs.cs as u16, ds: regs.ds as u16, es: regs.es as u16, fs: regs.fs as u16, gs: regs.gs as u16, ss: regs.ss as u16,
+                dr0: dr[0], dr1: dr[1], dr2: dr[2], dr3: dr[3], dr6: dr[6], dr7: dr[7],
+            })
+        }
+    }
+
+    fn set_registers(&self, tid: u32, r: &Registers) -> Result<(), anyhow::Error> {
+        unsafe {
+            let mut regs: libc::user_regs_struct = mem::zeroed();
+            if libc::ptrace(libc::PTRACE_GETREGS, tid as libc::pid_t, 0, &mut regs) < 0 {
+                return Err(os_err(&format!("failed to get registers of thread {tid}")));
+            }
+            regs.rax = r.rax; regs.rbx = r.rbx; regs.rcx = r.rcx; regs.rdx = r.rdx;
+            regs.rsi = r.rsi; regs.rdi = r.rdi; regs.rbp = r.rbp; regs.rsp = r.rsp;
+            regs.r8 = r.r8; regs.r9 = r.r9; regs.r10 = r.r10; regs.r11 = r.r11;
+            regs.r12 = r.r12; regs.r13 = r.r13; regs.r14 = r.r14; regs.r15 = r.r15;
+            regs.rip = r.rip;
+            regs.eflags = r.eflags as u64;
+            if libc::ptrace(libc::PTRACE_SETREGS, tid as libc::pid_t, 0, &regs) < 0 {
+                return Err(os_err(&format!("failed to set registers of thread {tid}")));
+            }
+            for (i, value) in [(0, r.dr0), (1, r.dr1), (2, r.dr2), (3, r.dr3), (6, r.dr6), (7, r.dr7)] {
+                if libc::ptrace(libc::PTRACE_POKEUSER, tid as libc::pid_t, debugreg_offset(i), value) < 0 {
+                    return Err(os_err(&format!("failed to write dr{i} of thread {tid}")));
+                }
+            }
+            Ok(())
+        }
+    }
+}
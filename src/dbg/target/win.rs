@@ -0,0 +1,244 @@
+use std::{io, mem};
+use anyhow::anyhow;
+use winapi::shared::minwindef::{FALSE, LPVOID};
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::memoryapi::{ReadProcessMemory, VirtualProtectEx, VirtualQueryEx, WriteProcessMemory};
+use winapi::um::processthreadsapi::{GetProcessId, GetThreadContext, OpenThread, SetThreadContext};
+use winapi::um::tlhelp32::{CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32};
+use winapi::um::winbase::{Wow64GetThreadContext, Wow64SetThreadContext};
+use winapi::um::winnt::{CONTEXT, CONTEXT_ALL, HANDLE, MEMORY_BASIC_INFORMATION, THREAD_GET_CONTEXT, THREAD_SET_CONTEXT, WOW64_CONTEXT, WOW64_CONTEXT_ALL};
+use crate::dbg::target::*;
+use winapi::um::wow64apiset::IsWow64Process;
+
+
+// GetThreadContext needs a 16 byte aligned CONTEXT, the winapi struct does not carry the alignment
+#[repr(C, align(16))]
+struct AlignedContext(CONTEXT);
+
+
+pub struct WinTarget {
+    pub h_proc: HANDLE,
+}
+
+impl WinTarget {
+    pub fn new(h_proc: HANDLE) -> Self {
+        WinTarget { h_proc }
+    }
+
+    fn is_wow64(&self) -> Result<bool, anyhow::Error> {
+        let mut wow64 = FALSE;
+        if unsafe { IsWow64Process(self.h_proc, &mut wow64) } == 0 {
+            return Err(anyhow!("failed to IsWow64Process : {}", io::Error::last_os_error()));
+        }
+        Ok(wow64 != FALSE)
+    }
+}
+
+
+
+impl From<&CONTEXT> for Registers {
+    fn from(ctx: &CONTEXT) -> Self {
+        Registers {
+            rax: ctx.Rax, rbx: ctx.Rbx, rcx: ctx.Rcx, rdx: ctx.Rdx,
+            rsi: ctx.Rsi, rdi: ctx.Rdi, rbp: ctx.Rbp, rsp: ctx.Rsp,
+            r8: ctx.R8, r9: ctx.R9, r10: ctx.R10, r11: ctx.R11,
+            r12: ctx.R12, r13: ctx.R13, r14: ctx.R14, r15: ctx.R15,
+            rip: ctx.Rip,
+            eflags: ctx.EFlags,
+            cs: ctx.SegCs, ds: ctx.SegDs, es: ctx.SegEs, fs: ctx.SegFs, gs: ctx.SegGs, ss: ctx.SegSs,
+            dr0: ctx.Dr0, dr1: ctx.Dr1, dr2: ctx.Dr2, dr3: ctx.Dr3, dr6: ctx.Dr6, dr7: ctx.Dr7,
+        }
+    }
+}
+
+impl From<&WOW64_CONTEXT> for Registers {
+    fn from(ctx: &WOW64_CONTEXT) -> Self {
+        Registers {
+            rax: ctx.Eax as u64, rbx: ctx.Ebx as u64, rcx: ctx.Ecx as u64, rdx: ctx.Edx as u64,
+            rsi: ctx.Esi as u64, rdi: ctx.Edi as u64, rbp: ctx.Ebp as u64, rsp: ctx.Esp as u64,
+            rip: ctx.Eip as u64,
+            eflags: ctx.EFlags,
+            cs: ctx.SegCs as u16, ds: ctx.SegDs as u16, es: ctx.SegEs as u16, fs: ctx.SegFs as u16, gs: ctx.SegGs as u16, ss: ctx.SegSs as u16,
+            dr0: ctx.Dr0 as u64, dr1: ctx.Dr1 as u64, dr2: ctx.Dr2 as u64, dr3: ctx.Dr3 as u64, dr6: ctx.Dr6 as u64, dr7: ctx.Dr7 as u64,
+            ..Default::default()
+        }
+    }
+}
+
+impl Registers {
+    pub fn write_ctx(&self, ctx: &mut CONTEXT) {
+        ctx.Rax = self.rax; ctx.Rbx = self.rbx; ctx.Rcx = self.rcx; ctx.Rdx = self.rdx;
+        ctx.Rsi = self.rsi; ctx.Rdi = self.rdi; ctx.Rbp = self.rbp; ctx.Rsp = self.rsp;
+        ctx.R8 = self.r8; ctx.R9 = self.r9; ctx.R10 = self.r10; ctx.R11 = self.r11;
+        ctx.R12 = self.r12; ctx.R13 = self.r13; ctx.R14 = self.r14; ctx.R15 = self.r15;
+        ctx.Rip = self.rip;
+        ctx.EFlags = self.eflags;
+        ctx.SegCs = self.cs; ctx.SegDs = self.ds; ctx.SegEs = self.es; ctx.SegFs = self.fs; ctx.SegGs = self.gs; ctx.SegSs = self.ss;
+        ctx.Dr0 = self.dr0; ctx.Dr1 = self.dr1; ctx.Dr2 = self.dr2; ctx.Dr3 = self.dr3; ctx.Dr6 = self.dr6; ctx.Dr7 = self.dr7;
+    }
+
+    pub fn write_ctx32(&self, ctx: &mut WOW64_CONTEXT) {
+        ctx.Eax = self.rax as u32; ctx.Ebx = self.rbx as u32; ctx.Ecx = self.rcx as u32; ctx.Edx = self.rdx as u32;
+        ctx.Esi = self.rsi as u32; ctx.Edi = self.rdi as u32; ctx.Ebp = self.rbp as u32; ctx.Esp = self.rsp as u32;
+        ctx.Eip = self.rip as u32;
+        ctx.EFlags = self.eflags;
+        ctx.SegCs = self.cs as u32; ctx.SegDs = self.ds as u32; ctx.SegEs = self.es as u32; ctx.SegFs = self.fs as u32; ctx.SegGs = self.gs as u32; ctx.SegSs = self.ss as u32;
+        ctx.Dr0 = self.dr0 as u32; ctx.Dr1 = self.dr1 as u32; ctx.Dr2 = self.dr2 as u32; ctx.Dr3 = self.dr3 as u32; ctx.Dr6 = self.dr6 as u32; ctx.Dr7 = self.dr7 as u32;
+    }
+}
+
+
+
+impl From<MEMORY_BASIC_INFORMATION> for MemRegion {
+    fn from(mem_info: MEMORY_BASIC_INFORMATION) -> Self {
+        MemRegion {
+            base: mem_info.BaseAddress as u64,
+            alloc_base: mem_info.AllocationBase as u64,
+            size: mem_info.RegionSize as u64,
+            state: mem_info.State,
+            protect: mem_info.Protect,
+            mem_type: mem_info.Type,
+        }
+    }
+}
+
+
+struct ThreadHandle(HANDLE);
+
+impl ThreadHandle {
+    fn open(tid: u32) -> Result<Self, anyhow::Error> {
+        let h_thread = unsafe { OpenThread(THREAD_GET_CONTEXT | THREAD_SET_CONTEXT, FALSE, tid) };
+        if h_thread.is_null() {
+            Err(anyhow!("failed to open thread {tid} : {}", io::Error::last_os_error()))
+        } else {
+            Ok(ThreadHandle(h_thread))
+        }
+    }
+}
+
+impl Drop for ThreadHandle {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.0) };
+    }
+}
+
+
+
+impl DebugTarget for WinTarget {
+    fn read_memory(&self, addr: u64, buffer: &mut [u8]) -> Result<usize, anyhow::Error> {
+        let mut read = 0;
+        if unsafe { ReadProcessMemory(self.h_proc, addr as LPVOID, buffer.as_mut_ptr() as LPVOID, buffer.len(), &mut read) } == 0 {
+            return Err(anyhow!("failed to read memory at address {:#x} : {}", addr, io::Error::last_os_error()));
+        }
+        Ok(read)
+    }
+
+    fn write_memory(&self, addr: u64, buffer: &[u8]) -> Result<usize, anyhow::Error> {
+        let mut written = 0;
+        if unsafe { WriteProcessMemory(self.h_proc, addr as LPVOID, buffer.as_ptr() as LPVOID, buffer.len(), &mut written) } == 0 {
+            return Err(anyhow!("failed to write memory at address {:#x} : {}", addr, io::Error::last_os_error()));
+        }
+        Ok(written)
+    }
+
+    fn query_region(&self, addr: u64) -> Result<MemRegion, anyhow::Error> {
+        unsafe {
+            let mut mem_info: MEMORY_BASIC_INFORMATION = mem::zeroed();
+            if VirtualQueryEx(self.h_proc, addr as LPVOID, &mut mem_info, size_of::<MEMORY_BASIC_INFORMATION>()) == 0 {
+                return Err(anyhow!("failed to query memory information for address {:#x} : {}", addr, io::Error::last_os_error()));
+            }
+            Ok(mem_info.into())
+        }
+    }
+
+    fn protect_region(&self, addr: u64, size: usize, protect: u32) -> Result<u32, anyhow::Error> {
+        let mut old_protect = 0;
+        if unsafe { VirtualProtectEx(self.h_proc, addr as LPVOID, size, protect, &mut old_protect) } == 0 {
+            return Err(anyhow!("failed to change memory protection at address {:#x} : {}", addr, io::Error::last_os_error()));
+        }
+        Ok(old_protect)
+    }
+
+    fn threads(&self) -> Result<Vec<u32>, anyhow::Error> {
+        unsafe {
+            let pid = GetProcessId(self.h_proc);
+            let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, pid);
+            if snapshot.is_null() {
+                return Err(anyhow!("failed to create tool snapshot : {}", io::Error::last_os_error()));
+            }
+            let mut th32: THREADENTRY32 = mem::zeroed();
+            th32.dwSize = size_of::<THREADENTRY32>() as u32;
+            let mut result = Vec::new();
+            if Thread32First(snapshot, &mut th32) != 0 {
+                loop {
+                    if th32.th32OwnerProcessID == pid {
+                        result.push(th32.th32ThreadID);
+                    }
+                    if Thread32Next(snapshot, &mut th32) == 0 {
+                        break;
+                    }
+                }
+            }
+            CloseHandle(snapshot);
+            Ok(result)
+        }
+    }
+
+    fn get_registers(&self, tid: u32) -> Result<Registers, anyhow::Error> {
+        let h_thread = ThreadHandle::open(tid)?;
+        unsafe {
+            match self.is_wow64()? {
+                true => {
+                    let mut ctx: WOW64_CONTEXT = mem::zeroed();
+                    ctx.ContextFlags = WOW64_CONTEXT_ALL;
+                    if Wow64GetThreadContext(h_thread.0, &mut ctx) == 0 {
+                        return Err(anyhow!("failed to get thread context of thread {tid} : {}", io::Error::last_os_error()));
+                    }
+                    Ok(Registers::from(&ctx))
+                }
+                false => {
+                    let mut aligned = AlignedContext(mem::zeroed());
+                    let ctx = &mut aligned.0;
+                    ctx.ContextFlags = CONTEXT_ALL;
+                    if GetThreadContext(h_thread.0, ctx) == 0 {
+                        return Err(anyhow!("failed to get thread context of thread {tid} : {}", io::Error::last_os_error()));
+                    }
+                    Ok(Registers::from(&*ctx))
+                }
+            }
+        }
+    }
+
+    fn set_registers(&self, tid: u32, regs: &Registers) -> Result<(), anyhow::Error> {
+        let h_thread = ThreadHandle::open(tid)?;
+        unsafe {
+            match self.is_wow64()? {
+                true => {
+                    let mut ctx: WOW64_CONTEXT = mem::zeroed();
+                    ctx.ContextFlags = WOW64_CONTEXT_ALL;
+                    if Wow64GetThreadContext(h_thread.0, &mut ctx) == 0 {
+                        return Err(anyhow!("failed to get thread context of thread {tid} : {}", io::Error::last_os_error()));
+                    }
+                    regs.write_ctx32(&mut ctx);
+                    if Wow64SetThreadContext(h_thread.0, &ctx) == 0 {
+                        return Err(anyhow!("failed to set thread context of thread {tid} : {}", io::Error::last_os_error()));
+                    }
+                    Ok(())
+                }
+                false => {
+                    let mut aligned = AlignedContext(mem::zeroed());
+                    let ctx = &mut aligned.0;
+                    ctx.ContextFlags = CONTEXT_ALL;
+                    if GetThreadContext(h_thread.0, ctx) == 0 {
+                        return Err(anyhow!("failed to get thread context of thread {tid} : {}", io::Error::last_os_error()));
+                    }
+                    regs.write_ctx(ctx);
+                    if SetThreadContext(h_thread.0, ctx) == 0 {
+                        return Err(anyhow!("failed to set thread context of thread {tid} : {}", io::Error::last_os_error()));
+                    }
+                    Ok(())
+                }
+            }
+        }
+    }
+}
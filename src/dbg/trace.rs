@@ -0,0 +1,603 @@
+use crate::command::breakpoint::{Brkpts, IatBrkpt};
+use crate::command::hook::Hook;
+use crate::dbg::dbg_cmd::generic;
+use crate::dbg::event::{DebugEvent, EventHost, EventLoop, EventRecord};
+use crate::dbg::target::gdb::{decode_hex, encode_hex};
+use crate::dbg::target::{DebugTarget, MemRegion, Registers, PROT_READWRITE};
+use crate::dbg::DbgState;
+use crate::session::Session;
+use crate::symbol::{SrcFile, SymbolFile};
+use crate::ut::fmt::{print_lg, LevelPrint};
+use anyhow::anyhow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+const MAGIC: &str = "lisa-trace 1";
+
+
+fn regs_to_values(r: &Registers) -> [u64; 30] {
+    [
+        r.rax, r.rbx, r.rcx, r.rdx, r.rsi, r.rdi, r.rbp, r.rsp,
+        r.r8, r.r9, r.r10, r.r11, r.r12, r.r13, r.r14, r.r15,
+        r.rip, r.eflags as u64,
+        r.cs as u64, r.ds as u64, r.es as u64, r.fs as u64, r.gs as u64, r.ss as u64,
+        r.dr0, r.dr1, r.dr2, r.dr3, r.dr6, r.dr7,
+    ]
+}
+
+fn values_to_regs(v: &[u64; 30]) -> Registers {
+    Registers {
+        rax: v[0], rbx: v[1], rcx: v[2], rdx: v[3], rsi: v[4], rdi: v[5], rbp: v[6], rsp: v[7],
+        r8: v[8], r9: v[9], r10: v[10], r11: v[11], r12: v[12], r13: v[13], r14: v[14], r15: v[15],
+        rip: v[16], eflags: v[17] as u32,
+        cs: v[18] as u16, ds: v[19] as u16, es: v[20] as u16, fs: v[21] as u16, gs: v[22] as u16, ss: v[23] as u16,
+        dr0: v[24], dr1: v[25], dr2: v[26], dr3: v[27], dr6: v[28], dr7: v[29],
+    }
+}
+
+fn num(s: &str) -> Result<u64, anyhow::Error> {
+    u64::from_str_radix(s.trim_start_matches("0x"), 16).map_err(|_| anyhow!("invalid number : '{s}'"))
+}
+
+fn arg<'a>(args: &[&'a str], i: usize) -> Result<&'a str, anyhow::Error> {
+    args.get(i).copied().ok_or_else(|| anyhow!("missing field {i}"))
+}
+
+
+fn event_line(ev: &EventRecord) -> String {
+    let body = match &ev.event {
+        DebugEvent::ProcessCreated { image_base } => format!("process-created {:#x}", image_base),
+        DebugEvent::ModuleLoaded { base, path: Some(path) } => format!("module-loaded {:#x} {path}", base),
+        DebugEvent::ModuleLoaded { base, path: None } => format!("module-loaded {:#x}", base),
+        DebugEvent::ModuleUnloaded { base } => format!("module-unloaded {:#x}", base),
+        DebugEvent::BreakpointHit { address } => format!("breakpoint {:#x}", address),
+        DebugEvent::SingleStep { address } => format!("single-step {:#x}", address),
+        DebugEvent::Exception { code, first_chance, address, info } => {
+            let info: String = info.iter().map(|i| format!(" {:#x}", i)).collect();
+            format!("exception {:#x} {} {:#x}{info}", code, *first_chance as u8, address)
+        }
+        DebugEvent::ThreadCreated { start_address } => format!("thread-created {:#x}", start_address),
+        DebugEvent::ThreadExited { exit_code } => format!("thread-exited {:#x}", exit_code),
+        DebugEvent::Output(text) => format!("output {}", encode_hex(text.as_bytes())),
+        DebugEvent::Exited { exit_code } => format!("exited {:#x}", exit_code),
+    };
+    format!("event {:#x} {:#x} {body}", ev.pid, ev.tid)
+}
+
+fn parse_event(line: &str) -> Result<EventRecord, anyhow::Error> {
+    let fields: Vec<&str> = line.splitn(6, ' ').collect();
+    let args: Vec<&str> = line.split(' ').collect();
+    let pid = num(arg(&args, 1)?)? as u32;
+    let tid = num(arg(&args, 2)?)? as u32;
+    let value = || num(arg(&args, 4)?);
+    let event = match arg(&args, 3)? {
+        "process-created" => DebugEvent::ProcessCreated { image_base: value()? },
+        "module-loaded" => DebugEvent::ModuleLoaded { base: value()?, path: fields.get(5).map(|p| p.to_string()) },
+        "module-unloaded" => DebugEvent::ModuleUnloaded { base: value()? },
+        "breakpoint" => DebugEvent::BreakpointHit { address: value()? },
+        "single-step" => DebugEvent::SingleStep { address: value()? },
+        "exception" => DebugEvent::Exception {
+            code: value()? as u32,
+            first_chance: arg(&args, 5)? == "1",
+            address: num(arg(&args, 6)?)?,
+            info: args[7..].iter().map(|i| num(i)).collect::<Result<_, _>>()?,
+        },
+        "thread-created" => DebugEvent::ThreadCreated { start_address: value()? },
+        "thread-exited" => DebugEvent::ThreadExited { exit_code: value()? as u32 },
+        "output" => DebugEvent::Output(String::from_utf8_lossy(&decode_hex(args.get(4).copied().unwrap_or_default())?).to_string()),
+        "exited" => DebugEvent::Exited { exit_code: value()? as u32 },
+        kind => return Err(anyhow!("unknown event '{kind}'")),
+    };
+    Ok(EventRecord::new(pid, tid, event))
+}
+
+
+
+pub struct Recorder<T: DebugTarget> {
+    inner: T,
+    out: RefCell<BufWriter<File>>,
+}
+
+impl<T: DebugTarget> Recorder<T> {
+    pub fn create(inner: T, path: &str, session: &Session) -> Result<Recorder<T>, anyhow::Error> {
+        let file = File::create(path).map_err(|e| anyhow!("failed to create '{path}' : {e}"))?;
+        let rec = Recorder { inner, out: RefCell::new(BufWriter::new(file)) };
+        rec.line(MAGIC.to_string());
+        rec.line(format!("bitness {}", session.get_bitness()));
+        if let Some(file) = &session.all.file {
+            rec.line(format!("file {file}"));
+        }
+        let all = &session.all;
+        for (kind, brkpts) in [("b-rva", &all.break_rva), ("b-va", &all.break_va), ("b-ret", &all.break_ret), ("b-ret-va", &all.break_ret_va), ("skip", &all.skip_addr), ("b-once", &all.break_once)] {
+            for b in brkpts {
+                rec.line(format!("{kind} {:#x}", b.addr));
+            }
+        }
+        for hook in &all.hook {
+            rec.line(format!("hook {:#x} {:#x}", hook.target, hook.replacen));
+        }
+        for iat_b in &all.break_iat {
+            rec.line(format!("b-iat {:#x} {:#x} {}", iat_b.iat_rva, iat_b.thunk_rva, iat_b.label));
+        }
+        Ok(rec)
+    }
+
+    fn line(&self, line: String) {
+        let _ = writeln!(self.out.borrow_mut(), "{line}");
+    }
+
+    pub fn event(&self, ev: &EventRecord) {
+        let _ = self.out.borrow_mut().flush();
+        self.line(event_line(ev));
+    }
+}
+
+impl<T: DebugTarget> DebugTarget for Recorder<T> {
+    fn read_memory(&self, addr: u64, buffer: &mut [u8]) -> Result<usize, anyhow::Error> {
+        let n = self.inner.read_memory(addr, buffer)?;
+        self.line(format!("read {:#x} {}", addr, encode_hex(&buffer[..n])));
+        Ok(n)
+    }
+
+    fn write_memory(&self, addr: u64, buffer: &[u8]) -> Result<usize, anyhow::Error> {
+        let n = self.inner.write_memory(addr, buffer)?;
+        self.line(format!("write {:#x} {}", addr, encode_hex(&buffer[..n])));
+        Ok(n)
+    }
+
+    fn query_region(&self, addr: u64) -> Result<MemRegion, anyhow::Error> {
+        let r = self.inner.query_region(addr)?;
+        self.line(format!("region {:#x} {:#x} {:#x} {:#x} {:#x} {:#x} {:#x}", addr, r.base, r.alloc_base, r.size, r.state, r.protect, r.mem_type));
+        Ok(r)
+    }
+
+    fn protect_region(&self, addr: u64, size: usize, protect: u32) -> Result<u32, anyhow::Error> {
+        self.inner.protect_region(addr, size, protect)
+    }
+
+    fn threads(&self) -> Result<Vec<u32>, anyhow::Error> {
+        let threads = self.inner.threads()?;
+        self.line(format!("threads{}", threads.iter().map(|t| format!(" {:#x}", t)).collect::<String>()));
+        Ok(threads)
+    }
+
+    fn get_registers(&self, tid: u32) -> Result<Registers, anyhow::Error> {
+        let regs = self.inner.get_registers(tid)?;
+        self.line(format!("regs {:#x}{}", tid, regs_to_values(&regs).iter().map(|v| format!(" {:#x}", v)).collect::<String>()));
+        Ok(regs)
+    }
+
+    fn set_registers(&self, tid: u32, regs: &Registers) -> Result<(), anyhow::Error> {
+        self.inner.set_registers(tid, regs)?;
+        self.line(format!("set-regs {:#x}", tid));
+        Ok(())
+    }
+}
+
+
+
+pub fn prompt(session: &mut Session, target: &dyn DebugTarget, ev: &EventRecord, c_dbg: &mut DbgState) {
+    let mut regs = match target.get_registers(ev.tid) {
+        Ok(regs) => regs,
+        Err(e) => {
+            print_lg(LevelPrint::Error, e);
+            return;
+        }
+    };
+    generic::cmd_wait(session, target, &mut regs, c_dbg, &mut |_, _, _| false);
+    if let Err(e) = target.set_registers(ev.tid, &regs) {
+        print_lg(LevelPrint::Error, format!("error when setting registers: {e}"));
+    }
+}
+
+
+pub struct RecordHost<H: EventHost, T: DebugTarget> {
+    pub inner: H,
+    pub target: Recorder<T>,
+}
+
+impl<H: EventHost, T: DebugTarget> EventHost for RecordHost<H, T> {
+    fn target(&self) -> &dyn DebugTarget {
+        &self.target
+    }
+
+    fn event(&mut self, ev: &EventRecord) {
+        self.target.event(ev);
+    }
+
+    fn stop(&mut self, session: &mut Session, ev: &EventRecord, c_dbg: &mut DbgState) {
+        prompt(session, &self.target, ev, c_dbg);
+    }
+
+    fn process_created(&mut self, session: &mut Session, ev: &EventRecord) {
+        self.inner.process_created(session, ev);
+    }
+
+    fn module_symbols(&mut self, base: u64) -> Result<Vec<SymbolFile>, anyhow::Error> {
+        let symbols = self.inner.module_symbols(base)?;
+        for sym in &symbols {
+            self.target.line(format!("export {:#x} {:#x} {}", sym.src_file.dll_base(), sym.offset, sym.name));
+        }
+        Ok(symbols)
+    }
+
+    fn module_unloaded(&mut self, base: u64) {
+        self.inner.module_unloaded(base);
+    }
+
+    fn return_address(&mut self, session: &mut Session, ev: &EventRecord) -> Option<u64> {
+        let ret_addr = self.inner.return_address(session, ev);
+        self.target.line(match ret_addr {
+            Some(addr) => format!("ret-addr {:#x}", addr),
+            None => "ret-addr none".to_string(),
+        });
+        ret_addr
+    }
+
+    fn detach(&mut self, pid: u32) {
+        self.inner.detach(pid);
+    }
+}
+
+
+
+enum Entry {
+    Read(u64, Vec<u8>),
+    Write(u64, Vec<u8>),
+    Regs(u32, Registers),
+    SetRegs(u32),
+    Region(u64, MemRegion),
+    Threads(Vec<u32>),
+    Export(u64, i64, String),
+    RetAddr(Option<u64>),
+}
+
+fn parse_entry(line: &str) -> Result<Entry, anyhow::Error> {
+    let args: Vec<&str> = line.split(' ').collect();
+    Ok(match args[0] {
+        "read" => Entry::Read(num(arg(&args, 1)?)?, decode_hex(args.get(2).copied().unwrap_or_default())?),
+        "write" => Entry::Write(num(arg(&args, 1)?)?, decode_hex(args.get(2).copied().unwrap_or_default())?),
+        "regs" => {
+            let mut values = [0u64; 30];
+            for (i, value) in values.iter_mut().enumerate() {
+                *value = num(arg(&args, i + 2)?)?;
+            }
+            Entry::Regs(num(arg(&args, 1)?)? as u32, values_to_regs(&values))
+        }
+        "set-regs" => Entry::SetRegs(num(arg(&args, 1)?)? as u32),
+        "region" => {
+            let v = (1..8).map(|i| num(arg(&args, i)?)).collect::<Result<Vec<u64>, _>>()?;
+            Entry::Region(v[0], MemRegion { base: v[1], alloc_base: v[2], size: v[3], state: v[4] as u32, protect: v[5] as u32, mem_type: v[6] as u32 })
+        }
+        "threads" => Entry::Threads(args[1..].iter().map(|t| num(t).map(|t| t as u32)).collect::<Result<_, _>>()?),
+        "export" => {
+            let fields: Vec<&str> = line.splitn(4, ' ').collect();
+            Entry::Export(num(arg(&fields, 1)?)?, num(arg(&fields, 2)?)? as i64, arg(&fields, 3)?.to_string())
+        }
+        "ret-addr" => Entry::RetAddr(match arg(&args, 1)? {
+            "none" => None,
+            addr => Some(num(addr)?),
+        }),
+        kind => return Err(anyhow!("unknown record '{kind}'")),
+    })
+}
+
+
+pub struct Trace {
+    pub bitness: usize,
+    pub file: Option<String>,
+    brkpts: Vec<(String, u64)>,
+    hooks: Vec<Hook>,
+    iat_brkpts: Vec<IatBrkpt>,
+    events: Vec<(EventRecord, Vec<Entry>)>,
+}
+
+impl Trace {
+    pub fn load(path: &str) -> Result<Trace, anyhow::Error> {
+        let file = File::open(path).map_err(|e| anyhow!("failed to open '{path}' : {e}"))?;
+        let mut lines = BufReader::new(file).lines();
+        if lines.next().transpose()?.as_deref() != Some(MAGIC) {
+            return Err(anyhow!("'{path}' is not a LisaDbg trace"));
+        }
+        let mut trace = Trace { bitness: 64, file: None, brkpts: Vec::new(), hooks: Vec::new(), iat_brkpts: Vec::new(), events: Vec::new() };
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            let err = |e: anyhow::Error| anyhow!("line {} : {e}", i + 2);
+            let (kind, rest) = line.split_once(' ').unwrap_or((&line, ""));
+            match kind {
+                "" => {}
+                "bitness" => trace.bitness = rest.parse().map_err(|_| err(anyhow!("invalid bitness '{rest}'")))?,
+                "file" => trace.file = Some(rest.to_string()),
+                "b-rva" | "b-va" | "b-ret" | "b-ret-va" | "skip" | "b-once" => trace.brkpts.push((kind.to_string(), num(rest).map_err(err)?)),
+                "hook" => {
+                    let (target, replacen) = rest.split_once(' ').ok_or_else(|| err(anyhow!("invalid hook")))?;
+                    trace.hooks.push(Hook { target: num(target).map_err(err)?, replacen: num(replacen).map_err(err)?, origin_byte: 0 });
+                }
+                "b-iat" => {
+                    let mut it = rest.splitn(3, ' ');
+                    let iat_rva = num(it.next().unwrap_or_default()).map_err(err)? as u32;
+                    let thunk_rva = num(it.next().unwrap_or_default()).map_err(err)? as u32;
+                    trace.iat_brkpts.push(IatBrkpt { iat_rva, thunk_rva, label: it.next().unwrap_or_default().to_string(), addr: 0 });
+                }
+                "event" => trace.events.push((parse_event(&line).map_err(err)?, Vec::new())),
+                _ => match trace.events.last_mut() {
+                    Some((_, entries)) => entries.push(parse_entry(&line).map_err(err)?),
+                    None => return Err(err(anyhow!("'{kind}' before the first event"))),
+                },
+            }
+        }
+        Ok(trace)
+    }
+
+    fn configure(&self, session: &mut Session) {
+        let all = &mut session.all;
+        for (kind, addr) in &self.brkpts {
+            let brkpts = match kind.as_str() {
+                "b-rva" => &mut all.break_rva,
+                "b-va" => &mut all.break_va,
+                "b-ret" => &mut all.break_ret,
+                "b-ret-va" => &mut all.break_ret_va,
+                "b-once" => &mut all.break_once,
+                _ => &mut all.skip_addr,
+            };
+            brkpts.push(Brkpts::from_addr_no_start(*addr));
+        }
+        all.hook.extend(self.hooks.iter().copied());
+        all.break_iat.extend(self.iat_brkpts.iter().cloned());
+    }
+}
+
+
+
+#[derive(Default)]
+pub struct ReplayTarget {
+    memory: RefCell<BTreeMap<u64, u8>>,
+    regs: RefCell<BTreeMap<u32, Registers>>,
+    regions: RefCell<Vec<(u64, MemRegion)>>,
+    threads: RefCell<Vec<u32>>,
+}
+
+impl ReplayTarget {
+    fn load(&self, entries: &[Entry]) {
+        let mut memory = self.memory.borrow_mut();
+        let mut regs = self.regs.borrow_mut();
+        let mut written = HashSet::new();
+        let mut seen = HashSet::new();
+        let mut set_tids = HashSet::new();
+        let mut seen_tids = HashSet::new();
+        for entry in entries {
+            match entry {
+                Entry::Read(addr, bytes) => {
+                    for (i, b) in bytes.iter().enumerate() {
+                        let a = addr + i as u64;
+                        if !written.contains(&a) && seen.insert(a) {
+                            memory.insert(a, *b);
+                        }
+                    }
+                }
+                Entry::Write(addr, bytes) => written.extend((0..bytes.len() as u64).map(|i| addr + i)),
+                Entry::Regs(tid, r) => {
+                    if !set_tids.contains(tid) && seen_tids.insert(*tid) {
+                        regs.insert(*tid, *r);
+                    }
+                }
+                Entry::SetRegs(tid) => {
+                    set_tids.insert(*tid);
+                }
+                Entry::Region(addr, region) => {
+                    let mut regions = self.regions.borrow_mut();
+                    regions.retain(|(a, _)| a != addr);
+                    regions.push((*addr, *region));
+                }
+                Entry::Threads(threads) => *self.threads.borrow_mut() = threads.clone(),
+                Entry::Export(..) | Entry::RetAddr(_) => {}
+            }
+        }
+    }
+}
+
+impl DebugTarget for ReplayTarget {
+    fn read_memory(&self, addr: u64, buffer: &mut [u8]) -> Result<usize, anyhow::Error> {
+        let memory = self.memory.borrow();
+        let mut n = 0;
+        while n < buffer.len() {
+            match memory.get(&(addr + n as u64)) {
+                Some(b) => buffer[n] = *b,
+                None => break,
+            }
+            n += 1;
+        }
+        match n {
+            0 => Err(anyhow!("the memory at address {:#x} is not in the trace", addr)),
+            n => Ok(n),
+        }
+    }
+
+    fn write_memory(&self, addr: u64, buffer: &[u8]) -> Result<usize, anyhow::Error> {
+        let mut memory = self.memory.borrow_mut();
+        for (i, b) in buffer.iter().enumerate() {
+            memory.insert(addr + i as u64, *b);
+        }
+        Ok(buffer.len())
+    }
+
+    fn query_region(&self, addr: u64) -> Result<MemRegion, anyhow::Error> {
+        let regions = self.regions.borrow();
+        regions.iter().rev().find(|(a, _)| *a == addr)
+            .or_else(|| regions.iter().rev().find(|(_, r)| r.base <= addr && addr < r.end()))
+            .map(|(_, r)| *r)
+            .ok_or_else(|| anyhow!("the memory information of address {:#x} is not in the trace", addr))
+    }
+
+    fn protect_region(&self, _addr: u64, _size: usize, _protect: u32) -> Result<u32, anyhow::Error> {
+        Ok(PROT_READWRITE)
+    }
+
+    fn threads(&self) -> Result<Vec<u32>, anyhow::Error> {
+        match self.threads.borrow().is_empty() {
+            true => Ok(self.regs.borrow().keys().copied().collect()),
+            false => Ok(self.threads.borrow().clone()),
+        }
+    }
+
+    fn get_registers(&self, tid: u32) -> Result<Registers, anyhow::Error> {
+        self.regs.borrow().get(&tid).copied().ok_or_else(|| anyhow!("the registers of thread {tid} are not in the trace"))
+    }
+
+    fn set_registers(&self, tid: u32, regs: &Registers) -> Result<(), anyhow::Error> {
+        self.regs.borrow_mut().insert(tid, *regs);
+        Ok(())
+    }
+}
+
+
+#[derive(Default)]
+pub struct ReplayHost {
+    pub target: ReplayTarget,
+    exports: Vec<(u64, i64, String)>,
+    ret_addrs: VecDeque<Option<u64>>,
+}
+
+impl ReplayHost {
+    fn load(&mut self, entries: &[Entry]) {
+        self.target.load(entries);
+        for entry in entries {
+            match entry {
+                Entry::Export(base, offset, name) => self.exports.push((*base, *offset, name.clone())),
+                Entry::RetAddr(addr) => self.ret_addrs.push_back(*addr),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl EventHost for ReplayHost {
+    fn target(&self) -> &dyn DebugTarget {
+        &self.target
+    }
+
+    fn stop(&mut self, session: &mut Session, ev: &EventRecord, c_dbg: &mut DbgState) {
+        prompt(session, &self.target, ev, c_dbg);
+    }
+
+    fn module_symbols(&mut self, base: u64) -> Result<Vec<SymbolFile>, anyhow::Error> {
+        let (symbols, rest) = std::mem::take(&mut self.exports).into_iter().partition(|(b, _, _)| *b == base);
+        self.exports = rest;
+        Ok(symbols.into_iter().map(|(base, offset, name)| SymbolFile { name, offset, src_file: SrcFile::Dll(base), ..Default::default() }).collect())
+    }
+
+    fn return_address(&mut self, _session: &mut Session, _ev: &EventRecord) -> Option<u64> {
+        self.ret_addrs.pop_front().flatten()
+    }
+}
+
+
+pub fn replay(session: &mut Session, trace: Trace) {
+    let all = &mut session.all;
+    let saved = (
+        std::mem::take(&mut all.break_rva), std::mem::take(&mut all.break_va), std::mem::take(&mut all.break_ret),
+        std::mem::take(&mut all.break_ret_va), std::mem::take(&mut all.skip_addr), std::mem::take(&mut all.hook),
+        std::mem::take(&mut all.break_iat), std::mem::take(&mut all.break_once),
+    );
+    trace.configure(session);
+    session.dump_bitness = Some(trace.bitness);
+    print_lg(LevelPrint::DebugO, format!("replay of {} events ({} bits)", trace.events.len(), trace.bitness));
+
+    let mut ev_loop = EventLoop::new(ReplayHost::default());
+    for (ev, entries) in &trace.events {
+        if ev_loop.c_dbg != DbgState::Continue {
+            break;
+        }
+        ev_loop.host.load(entries);
+        ev_loop.dispatch(session, ev);
+    }
+    if ev_loop.c_dbg == DbgState::Continue {
+        print_lg(LevelPrint::WarningO, "the trace ends before the process exits");
+    }
+
+    let all = &mut session.all;
+    (all.break_rva, all.break_va, all.break_ret, all.break_ret_va, all.skip_addr, all.hook, all.break_iat, all.break_once) = saved;
+    all.after_b.clear();
+    session.base_addr = 0;
+    session.dump_bitness = None;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbg::target::{read_exact, MemTarget, PROT_EXECUTE_READ};
+
+    struct MemHost(MemTarget);
+
+    impl EventHost for MemHost {
+        fn target(&self) -> &dyn DebugTarget {
+            &self.0
+        }
+
+        fn stop(&mut self, _session: &mut Session, _ev: &EventRecord, _c_dbg: &mut DbgState) {}
+
+        fn module_symbols(&mut self, base: u64) -> Result<Vec<SymbolFile>, anyhow::Error> {
+            Ok(vec![SymbolFile { name: "ExitProcess".to_string(), offset: 0x10, src_file: SrcFile::Dll(base), ..Default::default() }])
+        }
+    }
+
+    fn events() -> Vec<EventRecord> {
+        vec![
+            EventRecord::new(4, 8, DebugEvent::ProcessCreated { image_base: 0x400000 }),
+            EventRecord::new(4, 8, DebugEvent::ModuleLoaded { base: 0x7ff00000, path: Some("C:\\Windows\\kernel32 .dll".to_string()) }),
+            EventRecord::new(4, 12, DebugEvent::ThreadCreated { start_address: 0x401000 }),
+            EventRecord::new(4, 12, DebugEvent::ThreadExited { exit_code: 0 }),
+            EventRecord::new(4, 8, DebugEvent::Output("hello world\n".to_string())),
+            EventRecord::new(4, 8, DebugEvent::Exited { exit_code: 3 }),
+        ]
+    }
+
+    #[test]
+    fn record_then_replay() {
+        let path = std::env::temp_dir().join(format!("lisa_dbg_{}.trace", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let memory = MemTarget::new();
+        memory.map(0x401000, vec![0x55, 0x48, 0x89, 0xe5], PROT_EXECUTE_READ);
+        memory.add_thread(8, Registers { rip: 0x401000, rsp: 0x7ff000, ..Default::default() });
+        let mut session = Session::default();
+        session.all.break_rva.push(Brkpts::from_addr_no_start(0x1001));
+        session.all.hook.push(Hook { target: 0x1002, replacen: 0x1003, origin_byte: 0 });
+
+        let target = Recorder::create(memory, path, &session).unwrap();
+        let mut ev_loop = EventLoop::new(RecordHost { inner: MemHost(MemTarget::new()), target });
+        assert_eq!(ev_loop.replay(&mut session, events()), DbgState::NeedStop);
+        assert_eq!(session.all.break_rva[0].origin_b, 0x48);
+        assert_eq!(ev_loop.host.target.get_registers(8).unwrap().rip, 0x401000);
+        drop(ev_loop);
+
+        let trace = Trace::load(path).unwrap();
+        let _ = std::fs::remove_file(path);
+        assert_eq!(trace.bitness, 64);
+        assert_eq!(trace.brkpts, [("b-rva".to_string(), 0x1001)]);
+        assert_eq!((trace.hooks[0].target, trace.hooks[0].replacen), (0x1002, 0x1003));
+        assert_eq!(trace.events.iter().map(|(ev, _)| ev.clone()).collect::<Vec<_>>(), events());
+
+        // the memory read before the breakpoints were written is served with its original content
+        let mut host = ReplayHost::default();
+        for (_, entries) in &trace.events {
+            host.load(entries);
+        }
+        let mut code = [0u8; 2];
+        read_exact(&host.target, 0x401001, &mut code).unwrap();
+        assert_eq!(code, [0x48, 0x89]);
+        assert!(read_exact(&host.target, 0x401000, &mut code).is_err());
+        assert_eq!(host.target.get_registers(8).unwrap().rsp, 0x7ff000);
+        assert!(host.target.get_registers(12).is_err());
+        assert_eq!(host.module_symbols(0x7ff00000).unwrap()[0].name, "ExitProcess");
+
+        let mut replayed = Session::default();
+        replay(&mut replayed, trace);
+        assert!(replayed.symbols.symbol_file.iter().any(|s| s.name == "ExitProcess" && s.src_file == SrcFile::Dll(0x7ff00000)));
+        assert!(replayed.all.break_rva.is_empty() && replayed.all.hook.is_empty());
+        assert_eq!(replayed.base_addr, 0);
+    }
+}
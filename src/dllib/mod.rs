@@ -1,12 +1,23 @@
-use std::{io};
 use anyhow::anyhow;
-use winapi::shared::minwindef::{FARPROC, HMODULE};
+#[cfg(windows)]
+use std::io;
+#[cfg(windows)]
+pub use winapi::shared::minwindef::FARPROC;
+#[cfg(windows)]
+use winapi::shared::minwindef::HMODULE;
+#[cfg(windows)]
 use winapi::um::libloaderapi::{FreeLibrary, GetProcAddress, LoadLibraryA};
 
+#[cfg(not(windows))]
+#[allow(clippy::upper_case_acronyms)]
+pub type FARPROC = *mut std::ffi::c_void;
+
+#[cfg(windows)]
 pub struct Dll {
     hmod: HMODULE,
 }
 
+#[cfg(windows)]
 impl Dll {
     pub fn new(name: &str) -> Result<Dll, anyhow::Error> {
         unsafe {
@@ -31,10 +42,27 @@ impl Dll {
 
 
 
+#[cfg(windows)]
 impl Drop for Dll {
     fn drop(&mut self) {
         unsafe {
             FreeLibrary(self.hmod);
         }
     }
-}
\ No newline at end of file
+}
+
+
+// the helper dlls are windows binaries, loading one fails elsewhere
+#[cfg(not(windows))]
+pub struct Dll;
+
+#[cfg(not(windows))]
+impl Dll {
+    pub fn new(name: &str) -> Result<Dll, anyhow::Error> {
+        Err(anyhow!("Failed to load {name} : only available on windows"))
+    }
+
+    pub fn get_func(&self, name: &str) -> Result<FARPROC, anyhow::Error>{
+        Err(anyhow!("Failed to get {name} function : only available on windows"))
+    }
+}
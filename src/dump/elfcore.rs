@@ -0,0 +1,429 @@
+use crate::dbg::target::MemTarget;
+use crate::dbg::target::*;
+use crate::dump::{DumpModule, PostMortem};
+use anyhow::anyhow;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::{fs, io};
+
+const ET_CORE: u16 = 4;
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+
+const NT_PRSTATUS: u32 = 1;
+const NT_FPREGSET: u32 = 2;
+const NT_PRPSINFO: u32 = 3;
+const NT_PRXFPREG: u32 = 0x46e62b7f;
+const NT_SIGINFO: u32 = 0x53494749;
+const NT_FILE: u32 = 0x46494c45;
+
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+
+
+pub struct CoreInfo {
+    pub pid: u32,
+    pub fname: String,
+    pub args: String,
+    pub missing: Vec<String>,
+}
+
+struct FileMapping {
+    start: u64,
+    end: u64,
+    offset: u64,
+    path: String,
+}
+
+
+struct Reader<'a> {
+    data: &'a [u8],
+    is64: bool,
+}
+
+impl Reader<'_> {
+    fn bytes(&self, off: u64, len: u64) -> Result<&[u8], anyhow::Error> {
+        let end = off.checked_add(len).ok_or_else(|| anyhow!("invalid offset {:#x}", off))?;
+        self.data.get(off as usize..end as usize).ok_or_else(|| anyhow!("offset {:#x}+{:#x} is outside the core file", off, len))
+    }
+
+    fn u16(&self, off: u64) -> Result<u16, anyhow::Error> {
+        Ok(u16::from_le_bytes(self.bytes(off, 2)?.try_into()?))
+    }
+
+    fn u32(&self, off: u64) -> Result<u32, anyhow::Error> {
+        Ok(u32::from_le_bytes(self.bytes(off, 4)?.try_into()?))
+    }
+
+    fn u64(&self, off: u64) -> Result<u64, anyhow::Error> {
+        Ok(u64::from_le_bytes(self.bytes(off, 8)?.try_into()?))
+    }
+
+    fn word(&self, off: u64) -> Result<u64, anyhow::Error> {
+        if self.is64 { self.u64(off) } else { self.u32(off).map(|v| v as u64) }
+    }
+
+    fn word_size(&self) -> u64 {
+        if self.is64 { 8 } else { 4 }
+    }
+}
+
+
+fn c_str(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).trim().to_string()
+}
+
+fn flags_to_prot(flags: u32) -> u32 {
+    match (flags & PF_R != 0, flags & PF_W != 0, flags & PF_X != 0) {
+        (_, true, true) => PROT_EXECUTE_READWRITE,
+        (true, false, true) => PROT_EXECUTE_READ,
+        (false, false, true) => PROT_EXECUTE,
+        (_, true, false) => PROT_READWRITE,
+        (true, false, false) => PROT_READONLY,
+        (false, false, false) => PROT_NOACCESS,
+    }
+}
+
+pub fn signal_name(sig: u32) -> &'static str {
+    match sig {
+        1 => "SIGHUP", 2 => "SIGINT", 3 => "SIGQUIT", 4 => "SIGILL",
+        5 => "SIGTRAP", 6 => "SIGABRT", 7 => "SIGBUS", 8 => "SIGFPE",
+        9 => "SIGKILL", 10 => "SIGUSR1", 11 => "SIGSEGV", 12 => "SIGUSR2",
+        13 => "SIGPIPE", 14 => "SIGALRM", 15 => "SIGTERM", 24 => "SIGXCPU",
+        25 => "SIGXFSZ", 31 => "SIGSYS",
+        _ => "signal",
+    }
+}
+
+
+fn prstatus64(r: &Reader, desc: u64) -> Result<(u32, Registers), anyhow::Error> {
+    let reg = desc + 0x70;
+    Ok((r.u32(desc + 0x20)?, Registers {
+        r15: r.u64(reg)?, r14: r.u64(reg + 0x08)?, r13: r.u64(reg + 0x10)?, r12: r.u64(reg + 0x18)?,
+        rbp: r.u64(reg + 0x20)?, rbx: r.u64(reg + 0x28)?, r11: r.u64(reg + 0x30)?, r10: r.u64(reg + 0x38)?,
+        r9: r.u64(reg + 0x40)?, r8: r.u64(reg + 0x48)?, rax: r.u64(reg + 0x50)?, rcx: r.u64(reg + 0x58)?,
+        rdx: r.u64(reg + 0x60)?, rsi: r.u64(reg + 0x68)?, rdi: r.u64(reg + 0x70)?,
+        rip: r.u64(reg + 0x80)?, cs: r.u64(reg + 0x88)? as u16, eflags: r.u64(reg + 0x90)? as u32,
+        rsp: r.u64(reg + 0x98)?, ss: r.u64(reg + 0xa0)? as u16,
+        ds: r.u64(reg + 0xb8)? as u16, es: r.u64(reg + 0xc0)? as u16, fs: r.u64(reg + 0xc8)? as u16, gs: r.u64(reg + 0xd0)? as u16,
+        ..Default::default()
+    }))
+}
+
+fn prstatus32(r: &Reader, desc: u64) -> Result<(u32, Registers), anyhow::Error> {
+    let reg = desc + 0x48;
+    Ok((r.u32(desc + 0x18)?, Registers {
+        rbx: r.u32(reg)? as u64, rcx: r.u32(reg + 0x04)? as u64, rdx: r.u32(reg + 0x08)? as u64,
+        rsi: r.u32(reg + 0x0c)? as u64, rdi: r.u32(reg + 0x10)? as u64, rbp: r.u32(reg + 0x14)? as u64,
+        rax: r.u32(reg + 0x18)? as u64, ds: r.u32(reg + 0x1c)? as u16, es: r.u32(reg + 0x20)? as u16,
+        fs: r.u32(reg + 0x24)? as u16, gs: r.u32(reg + 0x28)? as u16,
+        rip: r.u32(reg + 0x30)? as u64, cs: r.u32(reg + 0x34)? as u16, eflags: r.u32(reg + 0x38)?,
+        rsp: r.u32(reg + 0x3c)? as u64, ss: r.u32(reg + 0x40)? as u16,
+        ..Default::default()
+    }))
+}
+
+
+fn parse_file_note(r: &Reader, desc: u64, size: u64) -> Result<Vec<FileMapping>, anyhow::Error> {
+    let ws = r.word_size();
+    let count = r.word(desc)?.min(size / (ws * 3));
+    let page_size = r.word(desc + ws)?;
+    let mut names = r.bytes(desc + ws * 2 + count * ws * 3, size.saturating_sub(ws * 2 + count * ws * 3))?.split(|&b| b == 0);
+    let mut result = Vec::with_capacity(count as usize);
+    for i in 0..count {
+        let entry = desc + ws * 2 + i * ws * 3;
+        let (start, end) = (r.word(entry)?, r.word(entry + ws)?);
+        let path = names.next().map(|n| String::from_utf8_lossy(n).to_string()).unwrap_or_default();
+        if end <= start {
+            continue;
+        }
+        result.push(FileMapping { start, end, offset: r.word(entry + ws * 2)?.saturating_mul(page_size), path });
+    }
+    Ok(result)
+}
+
+
+fn local_path(file: Option<&str>, mapped: &str) -> String {
+    match file {
+        Some(file) if Path::new(file).file_name() == Path::new(mapped).file_name() => file.to_string(),
+        _ => mapped.to_string(),
+    }
+}
+
+fn read_file_at(path: &str, offset: u64, len: u64) -> Result<Vec<u8>, io::Error> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut content = Vec::new();
+    file.take(len).read_to_end(&mut content)?;
+    Ok(content)
+}
+
+// data holds the bytes saved in the core, it only grows by what the mapped files really contain
+fn fill_from_files(file: Option<&str>, data: &mut Vec<u8>, vaddr: u64, size: u64, files: &[FileMapping], missing: &mut Vec<String>) -> bool {
+    let end = vaddr.saturating_add(size);
+    let from = vaddr.saturating_add(data.len() as u64);
+    for map in files.iter().filter(|m| m.start < end && m.end > from) {
+        let start = map.start.max(from);
+        let path = local_path(file, &map.path);
+        match read_file_at(&path, map.offset.saturating_add(start - map.start), map.end.min(end).saturating_sub(start)) {
+            Ok(content) if !content.is_empty() => {
+                let off = (start - vaddr) as usize;
+                if data.len() < off + content.len() {
+                    data.resize(off + content.len(), 0);
+                }
+                data[off..off + content.len()].copy_from_slice(&content);
+            }
+            Ok(_) => {}
+            Err(_) => if !missing.contains(&path) {
+                missing.push(path);
+            },
+        }
+    }
+    files.iter().any(|m| m.start < end && m.end > vaddr)
+}
+
+
+pub fn parse(path: &str, file: Option<&str>) -> Result<(PostMortem, CoreInfo), anyhow::Error> {
+    let data = fs::read(path)?;
+    if data.len() < 0x34 || data[..4] != *b"\x7fELF" {
+        return Err(anyhow!("'{path}' is not an elf file"));
+    }
+    let r = Reader { data: &data, is64: data[4] == 2 };
+    if r.u16(0x10)? != ET_CORE {
+        return Err(anyhow!("'{path}' is not a core file"));
+    }
+    let (phoff, phentsize, phnum) = if r.is64 {
+        (r.u64(0x20)?, r.u16(0x36)? as u64, r.u16(0x38)? as u64)
+    } else {
+        (r.u32(0x1c)? as u64, r.u16(0x2a)? as u64, r.u16(0x2c)? as u64)
+    };
+
+    let mut loads = Vec::new();
+    let mut notes = Vec::new();
+    for i in 0..phnum {
+        let ph = phoff + i * phentsize;
+        let (p_type, flags, offset, vaddr, file_size, mem_size) = if r.is64 {
+            (r.u32(ph)?, r.u32(ph + 4)?, r.u64(ph + 8)?, r.u64(ph + 16)?, r.u64(ph + 32)?, r.u64(ph + 40)?)
+        } else {
+            (r.u32(ph)?, r.u32(ph + 24)?, r.u32(ph + 4)? as u64, r.u32(ph + 8)? as u64, r.u32(ph + 16)? as u64, r.u32(ph + 20)? as u64)
+        };
+        match p_type {
+            PT_LOAD => loads.push((flags, offset, vaddr, file_size, mem_size)),
+            PT_NOTE => notes.push((offset, file_size)),
+            _ => {}
+        }
+    }
+
+    let target = MemTarget::new();
+    let mut info = CoreInfo { pid: 0, fname: String::new(), args: String::new(), missing: Vec::new() };
+    let mut files = Vec::new();
+    let mut fxsave = BTreeMap::new();
+    let mut first_tid = None;
+    let mut cur_tid = None;
+    let mut cursig = 0;
+    let mut reason = None;
+
+    for (offset, size) in notes {
+        let end = offset.saturating_add(size).min(data.len() as u64);
+        let mut off = offset;
+        while off.checked_add(12).is_some_and(|next| next <= end) {
+            let name_size = r.u32(off)? as u64;
+            let desc_size = r.u32(off + 4)? as u64;
+            let n_type = r.u32(off + 8)?;
+            let Some(desc) = (off + 12).checked_add((name_size + 3) & !3) else { break };
+            let Some(next) = desc.checked_add((desc_size + 3) & !3) else { break };
+            off = next;
+            match n_type {
+                NT_PRSTATUS => {
+                    let (tid, regs) = if r.is64 { prstatus64(&r, desc)? } else { prstatus32(&r, desc)? };
+                    if first_tid.is_none() {
+                        cursig = r.u16(desc + 12)? as u32;
+                        first_tid = Some(tid);
+                    }
+                    target.add_thread(tid, regs);
+                    cur_tid = Some(tid);
+                }
+                NT_FPREGSET | NT_PRXFPREG if desc_size >= 512 => if let Some(tid) = cur_tid {
+                    fxsave.insert(tid, r.bytes(desc, 512)?.to_vec());
+                },
+                NT_PRPSINFO => {
+                    let (pid_off, fname_off) = if r.is64 { (24, 40) } else { (16, 32) };
+                    info.pid = r.u32(desc + pid_off)?;
+                    info.fname = c_str(r.bytes(desc + fname_off, 16)?);
+                    info.args = c_str(r.bytes(desc + fname_off + 16, 80)?);
+                }
+                NT_SIGINFO => {
+                    let signo = r.u32(desc)?;
+                    let code = r.u32(desc + 8)? as i32;
+                    let fault = matches!(signo, 4 | 7 | 8 | 11);
+                    let addr = if fault { Some(r.word(desc + if r.is64 { 16 } else { 12 })?) } else { None };
+                    reason = Some(format!(
+                        "{} ({signo}) code {code}{}",
+                        signal_name(signo),
+                        addr.map(|a| format!(" at address {:#x}", a)).unwrap_or_default()
+                    ));
+                }
+                NT_FILE => files = parse_file_note(&r, desc, desc_size)?,
+                _ => {}
+            }
+        }
+    }
+
+    for (flags, offset, vaddr, file_size, mem_size) in loads {
+        let mut seg = r.bytes(offset, file_size.min(mem_size))?.to_vec();
+        let mem_type = if fill_from_files(file, &mut seg, vaddr, mem_size, &files, &mut info.missing) { REGION_IMAGE } else { REGION_PRIVATE };
+        target.map_sized(vaddr, seg, mem_size, flags_to_prot(flags), mem_type);
+    }
+
+    let mut modules: Vec<DumpModule> = Vec::new();
+    for map in &files {
+        match modules.iter_mut().find(|m| m.name == map.path) {
+            Some(m) => {
+                let end = m.base.saturating_add(m.size).max(map.end);
+                m.base = m.base.min(map.start);
+                m.size = end.saturating_sub(m.base);
+            }
+            None => modules.push(DumpModule { name: map.path.clone(), base: map.start, size: map.end.saturating_sub(map.start) }),
+        }
+    }
+
+    let reason = reason.or_else(|| (cursig != 0).then(|| format!("{} ({cursig})", signal_name(cursig))));
+    let tid = first_tid.ok_or_else(|| anyhow!("the core file has no NT_PRSTATUS note"))?;
+    let bitness = if r.is64 { 64 } else { 32 };
+    Ok((PostMortem { target, modules, tid, reason, bitness, fxsave }, info))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(n_type: u32, desc: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&5u32.to_le_bytes());
+        out.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+        out.extend_from_slice(&n_type.to_le_bytes());
+        out.extend_from_slice(b"CORE\0\0\0\0");
+        out.extend_from_slice(desc);
+        out.resize((out.len() + 3) & !3, 0);
+        out
+    }
+
+    // (vaddr, contents, mem_size) for each PT_LOAD
+    fn core(loads: &[(u64, &[u8], u64)], files: &[(u64, u64, &str)]) -> Vec<u8> {
+        let mut prstatus = vec![0u8; 0x150];
+        prstatus[0x20..0x24].copy_from_slice(&42u32.to_le_bytes());
+        prstatus[0x70 + 0x80..0x70 + 0x88].copy_from_slice(&0x401000u64.to_le_bytes());
+        let mut notes = note(NT_PRSTATUS, &prstatus);
+        if !files.is_empty() {
+            let mut desc = Vec::new();
+            desc.extend_from_slice(&(files.len() as u64).to_le_bytes());
+            desc.extend_from_slice(&0x1000u64.to_le_bytes());
+            for (start, end, _) in files {
+                for v in [*start, *end, 0] {
+                    desc.extend_from_slice(&v.to_le_bytes());
+                }
+            }
+            for (_, _, path) in files {
+                desc.extend_from_slice(path.as_bytes());
+                desc.push(0);
+            }
+            notes.extend(note(NT_FILE, &desc));
+        }
+
+        let phnum = loads.len() + 1;
+        let mut offset = 0x40 + phnum as u64 * 56;
+        let mut out = vec![0u8; 0x40];
+        out[..4].copy_from_slice(b"\x7fELF");
+        out[4] = 2;
+        out[0x10..0x12].copy_from_slice(&ET_CORE.to_le_bytes());
+        out[0x20..0x28].copy_from_slice(&0x40u64.to_le_bytes());
+        out[0x36..0x38].copy_from_slice(&56u16.to_le_bytes());
+        out[0x38..0x3a].copy_from_slice(&(phnum as u16).to_le_bytes());
+        let mut ph = |p_type: u32, flags: u32, vaddr: u64, file_size: u64, mem_size: u64| {
+            for v in [p_type, flags] {
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            for v in [offset, vaddr, 0, file_size, mem_size, 0x1000] {
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            offset += file_size;
+        };
+        ph(PT_NOTE, 0, 0, notes.len() as u64, 0);
+        for (vaddr, contents, mem_size) in loads {
+            ph(PT_LOAD, PF_R | PF_W, *vaddr, contents.len() as u64, *mem_size);
+        }
+        out.extend(notes);
+        for (_, contents, _) in loads {
+            out.extend_from_slice(contents);
+        }
+        out
+    }
+
+    fn try_parse_core(name: &str, data: &[u8]) -> Result<(PostMortem, CoreInfo), anyhow::Error> {
+        let path = std::env::temp_dir().join(format!("lisa_dbg_{}_{name}.core", std::process::id()));
+        fs::write(&path, data).unwrap();
+        let result = parse(path.to_str().unwrap(), None);
+        let _ = fs::remove_file(&path);
+        result
+    }
+
+    fn parse_core(name: &str, data: &[u8]) -> (PostMortem, CoreInfo) {
+        try_parse_core(name, data).unwrap()
+    }
+
+    #[test]
+    fn zero_fill_is_lazy() {
+        let data = core(&[(0x7f0000000000, &[0xaa; 0x10], 1 << 40)], &[]);
+        let (pm, _) = parse_core("lazy", &data);
+        assert_eq!(pm.tid, 42);
+        assert_eq!(pm.target.get_registers(42).unwrap().rip, 0x401000);
+        let mut buf = [0xffu8; 0x20];
+        read_exact(&pm.target, 0x7f0000000000, &mut buf).unwrap();
+        assert_eq!(buf[..0x10], [0xaa; 0x10]);
+        assert_eq!(buf[0x10..], [0; 0x10]);
+        assert_eq!(pm.target.query_region(0x7f0000000000 + (1 << 39)).unwrap().size, 1 << 40);
+        pm.target.write_memory(0x7f0000000100, &[1, 2]).unwrap();
+        assert_eq!(read_ptr(&pm.target, 0x7f00000000ff, 4).unwrap(), 0x00020100);
+    }
+
+    #[test]
+    fn file_size_larger_than_mem_size() {
+        let data = core(&[(0x400000, &[0x11; 0x20], 0x10)], &[]);
+        let (pm, _) = parse_core("trunc", &data);
+        assert_eq!(pm.target.query_region(0x400000).unwrap().size, 0x10);
+        assert!(read_ptr(&pm.target, 0x400010, 1).is_err());
+    }
+
+    #[test]
+    fn missing_mapped_file() {
+        let data = core(&[(0x400000, &[], 1 << 40)], &[(0x400000, 0x400000 + (1 << 40), "/nonexistent/lisa_dbg.so")]);
+        let (pm, info) = parse_core("missing", &data);
+        assert_eq!(info.missing, ["/nonexistent/lisa_dbg.so"]);
+        assert_eq!(pm.target.query_region(0x400000).unwrap().mem_type, REGION_IMAGE);
+        assert_eq!(read_ptr(&pm.target, 0x400000, 8).unwrap(), 0);
+    }
+
+    #[test]
+    fn inverted_file_mapping_is_skipped() {
+        let data = core(&[(0x400000, &[0x11; 0x10], 0x2000)], &[(0x401000, 0x400800, "/nonexistent/lisa_dbg.so")]);
+        let (pm, info) = parse_core("inverted", &data);
+        assert!(pm.modules.is_empty());
+        assert!(info.missing.is_empty());
+        assert_eq!(pm.target.query_region(0x400000).unwrap().mem_type, REGION_PRIVATE);
+    }
+
+    #[test]
+    fn note_segment_at_the_end_of_the_address_space() {
+        let mut data = core(&[], &[]);
+        data[0x48..0x50].copy_from_slice(&(u64::MAX - 4).to_le_bytes());
+        assert!(try_parse_core("note_end", &data).is_err());
+        data[0x48..0x50].copy_from_slice(&0x78u64.to_le_bytes());
+        data[0x60..0x68].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(try_parse_core("note_size", &data).is_ok());
+    }
+}
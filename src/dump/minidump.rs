@@ -0,0 +1,347 @@
+use crate::dbg::target::MemTarget;
+use crate::dbg::target::{Registers, PROT_READONLY};
+use crate::dump::{DumpModule, PostMortem};
+use anyhow::anyhow;
+use std::collections::BTreeMap;
+use std::fs;
+
+const MINIDUMP_SIGNATURE: u32 = 0x504d444d;
+
+const THREAD_LIST_STREAM: u32 = 3;
+const MODULE_LIST_STREAM: u32 = 4;
+const MEMORY_LIST_STREAM: u32 = 5;
+const EXCEPTION_STREAM: u32 = 6;
+const SYSTEM_INFO_STREAM: u32 = 7;
+const MEMORY64_LIST_STREAM: u32 = 9;
+
+const PROCESSOR_ARCHITECTURE_INTEL: u16 = 0;
+const PROCESSOR_ARCHITECTURE_AMD64: u16 = 9;
+
+
+pub struct SystemInfo {
+    pub arch: u16,
+    pub processors: u8,
+    pub major: u32,
+    pub minor: u32,
+    pub build: u32,
+}
+
+pub struct DumpException {
+    pub tid: u32,
+    pub code: u32,
+    pub flags: u32,
+    pub address: u64,
+    pub params: Vec<u64>,
+}
+
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn bytes(&self, off: u64, len: u64) -> Result<&[u8], anyhow::Error> {
+        let end = off.checked_add(len).ok_or_else(|| anyhow!("invalid location {:#x}", off))?;
+        self.0.get(off as usize..end as usize).ok_or_else(|| anyhow!("location {:#x}+{:#x} is outside the dump", off, len))
+    }
+
+    fn u8(&self, off: u64) -> Result<u8, anyhow::Error> {
+        Ok(self.bytes(off, 1)?[0])
+    }
+
+    fn u16(&self, off: u64) -> Result<u16, anyhow::Error> {
+        Ok(u16::from_le_bytes(self.bytes(off, 2)?.try_into()?))
+    }
+
+    fn u32(&self, off: u64) -> Result<u32, anyhow::Error> {
+        Ok(u32::from_le_bytes(self.bytes(off, 4)?.try_into()?))
+    }
+
+    fn u64(&self, off: u64) -> Result<u64, anyhow::Error> {
+        Ok(u64::from_le_bytes(self.bytes(off, 8)?.try_into()?))
+    }
+
+    fn string(&self, rva: u64) -> Result<String, anyhow::Error> {
+        let len = self.u32(rva)? as u64;
+        let raw = self.bytes(rva + 4, len)?;
+        let wide: Vec<u16> = raw.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+        Ok(String::from_utf16_lossy(&wide))
+    }
+}
+
+
+fn context64(r: &Reader, rva: u64) -> Result<Registers, anyhow::Error> {
+    Ok(Registers {
+        cs: r.u16(rva + 0x38)?, ds: r.u16(rva + 0x3a)?, es: r.u16(rva + 0x3c)?,
+        fs: r.u16(rva + 0x3e)?, gs: r.u16(rva + 0x40)?, ss: r.u16(rva + 0x42)?,
+        eflags: r.u32(rva + 0x44)?,
+        dr0: r.u64(rva + 0x48)?, dr1: r.u64(rva + 0x50)?, dr2: r.u64(rva + 0x58)?,
+        dr3: r.u64(rva + 0x60)?, dr6: r.u64(rva + 0x68)?, dr7: r.u64(rva + 0x70)?,
+        rax: r.u64(rva + 0x78)?, rcx: r.u64(rva + 0x80)?, rdx: r.u64(rva + 0x88)?, rbx: r.u64(rva + 0x90)?,
+        rsp: r.u64(rva + 0x98)?, rbp: r.u64(rva + 0xa0)?, rsi: r.u64(rva + 0xa8)?, rdi: r.u64(rva + 0xb0)?,
+        r8: r.u64(rva + 0xb8)?, r9: r.u64(rva + 0xc0)?, r10: r.u64(rva + 0xc8)?, r11: r.u64(rva + 0xd0)?,
+        r12: r.u64(rva + 0xd8)?, r13: r.u64(rva + 0xe0)?, r14: r.u64(rva + 0xe8)?, r15: r.u64(rva + 0xf0)?,
+        rip: r.u64(rva + 0xf8)?,
+    })
+}
+
+fn context32(r: &Reader, rva: u64) -> Result<Registers, anyhow::Error> {
+    Ok(Registers {
+        dr0: r.u32(rva + 0x04)? as u64, dr1: r.u32(rva + 0x08)? as u64, dr2: r.u32(rva + 0x0c)? as u64,
+        dr3: r.u32(rva + 0x10)? as u64, dr6: r.u32(rva + 0x14)? as u64, dr7: r.u32(rva + 0x18)? as u64,
+        gs: r.u32(rva + 0x8c)? as u16, fs: r.u32(rva + 0x90)? as u16, es: r.u32(rva + 0x94)? as u16, ds: r.u32(rva + 0x98)? as u16,
+        rdi: r.u32(rva + 0x9c)? as u64, rsi: r.u32(rva + 0xa0)? as u64, rbx: r.u32(rva + 0xa4)? as u64, rdx: r.u32(rva + 0xa8)? as u64,
+        rcx: r.u32(rva + 0xac)? as u64, rax: r.u32(rva + 0xb0)? as u64, rbp: r.u32(rva + 0xb4)? as u64, rip: r.u32(rva + 0xb8)? as u64,
+        cs: r.u32(rva + 0xbc)? as u16, eflags: r.u32(rva + 0xc0)?, rsp: r.u32(rva + 0xc4)? as u64, ss: r.u32(rva + 0xc8)? as u16,
+        ..Default::default()
+    })
+}
+
+
+pub fn parse(path: &str) -> Result<(PostMortem, SystemInfo), anyhow::Error> {
+    let data = fs::read(path)?;
+    let r = Reader(&data);
+    if r.u32(0)? != MINIDUMP_SIGNATURE {
+        return Err(anyhow!("'{path}' is not a minidump file"));
+    }
+    let stream_count = r.u32(8)? as u64;
+    let dir_rva = r.u32(12)? as u64;
+    // a directory entry is 12 bytes, the count cannot exceed what the file holds
+    let mut streams = Vec::with_capacity(stream_count.min(data.len() as u64 / 12) as usize);
+    for i in 0..stream_count {
+        let off = dir_rva + i * 12;
+        streams.push((r.u32(off)?, r.u32(off + 4)? as u64, r.u32(off + 8)? as u64));
+    }
+    let stream = |kind: u32| streams.iter().find(|s| s.0 == kind).map(|s| (s.1, s.2));
+
+    let sys_info = match stream(SYSTEM_INFO_STREAM) {
+        Some((_, rva)) => SystemInfo {
+            arch: r.u16(rva)?,
+            processors: r.u8(rva + 6)?,
+            major: r.u32(rva + 8)?,
+            minor: r.u32(rva + 12)?,
+            build: r.u32(rva + 16)?,
+        },
+        None => return Err(anyhow!("the dump has no system info stream")),
+    };
+    let bitness = match sys_info.arch {
+        PROCESSOR_ARCHITECTURE_AMD64 => 64,
+        PROCESSOR_ARCHITECTURE_INTEL => 32,
+        arch => return Err(anyhow!("unsupported processor architecture {arch}")),
+    };
+
+    let target = MemTarget::new();
+    if let Some((_, rva)) = stream(MEMORY_LIST_STREAM) {
+        for i in 0..r.u32(rva)? as u64 {
+            let desc = rva + 4 + i * 16;
+            let start = r.u64(desc)?;
+            let size = r.u32(desc + 8)? as u64;
+            let data_rva = r.u32(desc + 12)? as u64;
+            target.map(start, r.bytes(data_rva, size)?.to_vec(), PROT_READONLY);
+        }
+    }
+    if let Some((_, rva)) = stream(MEMORY64_LIST_STREAM) {
+        let count = r.u64(rva)?;
+        let mut data_rva = r.u64(rva + 8)?;
+        for i in 0..count {
+            let desc = rva + 16 + i * 16;
+            let start = r.u64(desc)?;
+            let size = r.u64(desc + 8)?;
+            target.map(start, r.bytes(data_rva, size)?.to_vec(), PROT_READONLY);
+            data_rva += size;
+        }
+    }
+
+    let mut modules = Vec::new();
+    if let Some((_, rva)) = stream(MODULE_LIST_STREAM) {
+        for i in 0..r.u32(rva)? as u64 {
+            let m = rva + 4 + i * 108;
+            modules.push(DumpModule {
+                base: r.u64(m)?,
+                size: r.u32(m + 8)? as u64,
+                name: r.string(r.u32(m + 20)? as u64)?,
+            });
+        }
+    }
+
+    let mut first_tid = None;
+    let mut fxsave = BTreeMap::new();
+    if let Some((_, rva)) = stream(THREAD_LIST_STREAM) {
+        for i in 0..r.u32(rva)? as u64 {
+            let t = rva + 4 + i * 48;
+            let tid = r.u32(t)?;
+            let ctx_rva = r.u32(t + 44)? as u64;
+            let regs = if bitness == 64 { context64(&r, ctx_rva)? } else { context32(&r, ctx_rva)? };
+            target.add_thread(tid, regs);
+            if bitness == 64 {
+                fxsave.insert(tid, r.bytes(ctx_rva + 0x100, 512)?.to_vec());
+            }
+            first_tid.get_or_insert(tid);
+        }
+    }
+
+    let mut exception = None;
+    if let Some((_, rva)) = stream(EXCEPTION_STREAM) {
+        let rec = rva + 8;
+        let param_count = (r.u32(rec + 24)? as u64).min(15);
+        let mut params = Vec::new();
+        for i in 0..param_count {
+            params.push(r.u64(rec + 32 + i * 8)?);
+        }
+        let exc = DumpException {
+            tid: r.u32(rva)?,
+            code: r.u32(rec)?,
+            flags: r.u32(rec + 4)?,
+            address: r.u64(rec + 16)?,
+            params,
+        };
+        let ctx_rva = r.u32(rva + 8 + 152 + 4)? as u64;
+        if ctx_rva != 0 {
+            let regs = if bitness == 64 { context64(&r, ctx_rva)? } else { context32(&r, ctx_rva)? };
+            target.add_thread(exc.tid, regs);
+            if bitness == 64 {
+                fxsave.insert(exc.tid, r.bytes(ctx_rva + 0x100, 512)?.to_vec());
+            }
+        }
+        exception = Some(exc);
+    }
+
+    let reason = exception.as_ref().map(|e| format!(
+        "exception {:#x} (flags {:#x}) at address {:#x} in thread {}{}",
+        e.code, e.flags, e.address, e.tid,
+        if e.params.is_empty() { String::new() } else { format!(", parameters {:x?}", e.params) }
+    ));
+    let tid = exception.as_ref().map(|e| e.tid).or(first_tid).ok_or_else(|| anyhow!("the dump has no thread"))?;
+
+    Ok((PostMortem { target, modules, tid, reason, bitness, fxsave }, sys_info))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbg::target::{read_ptr, DebugTarget};
+
+    fn u32s(out: &mut Vec<u8>, values: &[u32]) {
+        for v in values {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+
+    fn u64s(out: &mut Vec<u8>, values: &[u64]) {
+        for v in values {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+
+    // x64 dump of one thread stopped on an access violation, with one module and one memory range
+    fn sample() -> Vec<u8> {
+        let mut streams: Vec<(u32, Vec<u8>)> = Vec::new();
+
+        let mut sys = vec![0u8; 56];
+        sys[..2].copy_from_slice(&PROCESSOR_ARCHITECTURE_AMD64.to_le_bytes());
+        sys[6] = 4;
+        sys[8..20].copy_from_slice(&[10, 0, 0, 0, 0, 0, 0, 0, 0x61, 0x4a, 0, 0]);
+        streams.push((SYSTEM_INFO_STREAM, sys));
+        streams.push((THREAD_LIST_STREAM, Vec::new()));
+        streams.push((MODULE_LIST_STREAM, Vec::new()));
+        streams.push((EXCEPTION_STREAM, Vec::new()));
+        streams.push((MEMORY64_LIST_STREAM, Vec::new()));
+
+        // the variable parts are placed after the directory, their rva is known once the streams are sized
+        let header = 32 + streams.len() as u64 * 12;
+        let sizes = [56, 4 + 48, 4 + 108, 168, 16 + 16];
+        let extra = header + sizes.iter().sum::<u64>();
+        let ctx_rva = extra;
+        let name_rva = ctx_rva + 0x4d0;
+        let mem_rva = name_rva + 4 + 16;
+
+        let thread = &mut streams[1].1;
+        u32s(thread, &[1, 0x1234, 0, 0, 0]);
+        u64s(thread, &[0, 0x7ff000]);
+        u32s(thread, &[0, 0, 0x4d0, ctx_rva as u32]);
+
+        let module = &mut streams[2].1;
+        u32s(module, &[1]);
+        u64s(module, &[0x140000000]);
+        u32s(module, &[0x5000, 0, 0, name_rva as u32]);
+        module.resize(4 + 108, 0);
+
+        let exc = &mut streams[3].1;
+        u32s(exc, &[0x1234, 0, 0xc0000005, 0]);
+        u64s(exc, &[0, 0x140001010]);
+        u32s(exc, &[2, 0]);
+        u64s(exc, &[1, 0xdead]);
+        exc.resize(8 + 152, 0);
+        u32s(exc, &[0x4d0, ctx_rva as u32]);
+
+        let mem = &mut streams[4].1;
+        u64s(mem, &[1, mem_rva, 0x7ff000, 0x10]);
+
+        let mut out = Vec::new();
+        u32s(&mut out, &[MINIDUMP_SIGNATURE, 0xa793, streams.len() as u32, 32, 0, 0, 0, 0]);
+        let mut rva = header;
+        for (kind, body) in &streams {
+            u32s(&mut out, &[*kind, body.len() as u32, rva as u32]);
+            rva += body.len() as u64;
+        }
+        for (_, body) in &streams {
+            out.extend_from_slice(body);
+        }
+        assert_eq!(out.len() as u64, extra);
+
+        let mut ctx = vec![0u8; 0x4d0];
+        ctx[0x98..0xa0].copy_from_slice(&0x7ff008u64.to_le_bytes());
+        ctx[0xf8..0x100].copy_from_slice(&0x140001010u64.to_le_bytes());
+        out.extend(ctx);
+        u32s(&mut out, &[16]);
+        out.extend("a.exe\0\0\0".encode_utf16().flat_map(|c| c.to_le_bytes()));
+        out.extend(0x10u8..0x20);
+        out
+    }
+
+    fn parse_dump(name: &str, data: &[u8]) -> Result<(PostMortem, SystemInfo), anyhow::Error> {
+        let path = std::env::temp_dir().join(format!("lisa_dbg_{}_{name}.dmp", std::process::id()));
+        fs::write(&path, data).unwrap();
+        let result = parse(path.to_str().unwrap());
+        let _ = fs::remove_file(&path);
+        result
+    }
+
+    #[test]
+    fn sample_dump() {
+        let (pm, sys) = parse_dump("sample", &sample()).unwrap();
+        assert_eq!((sys.arch, sys.processors, sys.major, sys.build), (PROCESSOR_ARCHITECTURE_AMD64, 4, 10, 0x4a61));
+        assert_eq!(pm.bitness, 64);
+        assert_eq!(pm.tid, 0x1234);
+        assert_eq!(pm.modules.len(), 1);
+        assert_eq!((pm.modules[0].base, pm.modules[0].size), (0x140000000, 0x5000));
+        assert_eq!(pm.modules[0].name.trim_end_matches('\0'), "a.exe");
+        assert_eq!(pm.reason.as_deref(), Some("exception 0xc0000005 (flags 0x0) at address 0x140001010 in thread 4660, parameters [1, dead]"));
+        let regs = pm.target.get_registers(0x1234).unwrap();
+        assert_eq!((regs.rip, regs.rsp), (0x140001010, 0x7ff008));
+        assert_eq!(read_ptr(&pm.target, 0x7ff008, 8).unwrap(), 0x1f1e1d1c1b1a1918);
+        assert!(read_ptr(&pm.target, 0x7ff010, 1).is_err());
+    }
+
+    #[test]
+    fn huge_stream_count() {
+        let mut data = sample();
+        data[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse_dump("streams", &data).is_err());
+    }
+
+    #[test]
+    fn huge_memory_count() {
+        let mut data = sample();
+        let mem = data.len() - 0x10 - 20 - 0x4d0 - 32;
+        data[mem..mem + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(parse_dump("memory", &data).is_err());
+    }
+
+    #[test]
+    fn truncated_dump() {
+        let data = sample();
+        for len in [0, 4, 16, 40, data.len() / 2, data.len() - 1] {
+            assert!(parse_dump(&format!("truncated{len}"), &data[..len]).is_err());
+        }
+    }
+}
@@ -0,0 +1,158 @@
+pub mod elfcore;
+pub mod minidump;
+
+use crate::dbg::dbg_cmd::generic;
+use crate::dbg::memory::deref_mem;
+use crate::dbg::target::MemTarget;
+use crate::dbg::target::{DebugTarget, Registers};
+use crate::dbg::DbgState;
+use crate::session::Session;
+use crate::ut::cast::str_to;
+use crate::ut::fmt::*;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+
+pub struct DumpModule {
+    pub name: String,
+    pub base: u64,
+    pub size: u64,
+}
+
+pub struct PostMortem {
+    pub target: MemTarget,
+    pub modules: Vec<DumpModule>,
+    pub tid: u32,
+    pub reason: Option<String>,
+    pub bitness: usize,
+    pub fxsave: BTreeMap<u32, Vec<u8>>,
+}
+
+fn main_module_base(file: Option<&str>, modules: &[DumpModule]) -> u64 {
+    let file_name = file.and_then(|f| Path::new(f).file_name().map(|n| n.to_string_lossy().to_lowercase()));
+    let by_name = file_name.and_then(|name| modules.iter().find(|m| {
+        m.name.rsplit(['\\', '/']).next().unwrap_or_default().to_lowercase() == name
+    }));
+    by_name.or(modules.first()).map(|m| m.base).unwrap_or(0)
+}
+
+
+fn print_modules(modules: &[DumpModule]) {
+    println!(
+        "\x1b[33m{:<25}\x1b[0m \x1b[33m{:<25}\x1b[0m \x1b[36m{:<15}\x1b[0m \x1b[35m{:<5}\x1b[0m", "Start Addr", "End Addr", "Size", "Module"
+    );
+    for m in modules {
+        println!(
+            "\x1b[33m{:#018x}{}       \x1b[33m{:#018x}{}       \x1b[36m{:<#10x}{} \x1b[35m{:<30}{}", m.base, RESET_COLOR, m.base + m.size, RESET_COLOR, m.size, RESET_COLOR, m.name, RESET_COLOR
+        );
+    }
+}
+
+fn print_threads(target: &MemTarget, current: u32) {
+    match target.threads() {
+        Ok(threads) => for tid in threads {
+            let rip = target.get_registers(tid).map(|r| r.rip).unwrap_or_default();
+            println!("{}{:<10}{} {ADDR_COLOR}{:#018x}{RESET_COLOR}", if tid == current { VALID_COLOR } else { BLUE_COLOR }, tid, if tid == current { " *" } else { "" }, rip);
+        }
+        Err(e) => print_lg(LevelPrint::ErrorO, e),
+    }
+}
+
+fn print_vector_reg(name: &str, fxsave: &[u8]) {
+    let value = match name {
+        "mxcsr" => {
+            let mxcsr = u32::from_le_bytes(fxsave[24..28].try_into().unwrap());
+            println!("{:<5} = {VALUE_COLOR}{:#x}{RESET_COLOR}", name, mxcsr);
+            return;
+        }
+        _ => match name.strip_prefix("xmm").and_then(|i| i.parse::<usize>().ok()).filter(|i| *i < 16) {
+            Some(i) => &fxsave[160 + i * 16..176 + i * 16],
+            None => {
+                print_lg(LevelPrint::ErrorO, format!("unknow register : '{name}'"));
+                return;
+            }
+        },
+    };
+    let low = u64::from_le_bytes(value[..8].try_into().unwrap());
+    let high = u64::from_le_bytes(value[8..].try_into().unwrap());
+    let float: Vec<f32> = value.chunks_exact(4).map(|c| f32::from_le_bytes(c.try_into().unwrap())).collect();
+    println!("{:<6} = {{\"{}{}\"}}", name, deref_mem::espc(&low.to_le_bytes()), deref_mem::espc(&high.to_le_bytes()));
+    println!("{:<6} = {{{}{:#x}, {:#x}{}}}", "_m128i", VALUE_COLOR, low, high, RESET_COLOR);
+    println!("{:<6} = {{{}{}, {}, {}, {}{}}}", "_m128", VALUE_COLOR, float[0], float[1], float[2], float[3], RESET_COLOR);
+    println!("{:<6} = {{{VALUE_COLOR}{}, {}{RESET_COLOR}}}", "_m128d", f64::from_bits(low), f64::from_bits(high));
+    println!();
+}
+
+fn handle_vector_reg(pm: &PostMortem, tid: u32, name: &str) {
+    let fxsave = match pm.fxsave.get(&tid) {
+        Some(fxsave) => fxsave,
+        None => {
+            print_lg(LevelPrint::ErrorO, format!("the dump does not record the vector registers of thread {tid}"));
+            return;
+        }
+    };
+    match name {
+        "all-vec" | "all-vector" => for i in 0..if pm.bitness == 64 { 16 } else { 8 } {
+            print_vector_reg(&format!("xmm{i}"), fxsave);
+        },
+        _ => print_vector_reg(name, fxsave),
+    }
+}
+
+
+fn handle_dump_cmd(pm: &PostMortem, tid: &mut u32, linev: &[&str], regs: &mut Registers) -> bool {
+    match linev.first() {
+        Some(&"c") | Some(&"continue") | Some(&"run") => print_lg(LevelPrint::ErrorO, "there is no process to continue, type \"q\" to leave the dump"),
+        Some(&"set") => print_lg(LevelPrint::ErrorO, "the dump is read-only"),
+        Some(&"v") | Some(&"value") | Some(&"registers") | Some(&"register") | Some(&"reg") | Some(&"r")
+            if linev.get(1).is_some_and(|r| r.starts_with("xmm") || r.starts_with("all-vec") || *r == "mxcsr") => handle_vector_reg(pm, *tid, linev[1]),
+        Some(&"thread-info") | Some(&"th-info") => println!("{}Thread id : {}{RESET_COLOR}", MAGENTA, tid),
+        Some(&"debug-thread") | Some(&"dbg-thread") | Some(&"dbg-th") => match linev.get(1).map(|t| str_to::<u32>(t)) {
+            Some(Ok(new_tid)) => match pm.target.get_registers(new_tid) {
+                Ok(new_regs) => {
+                    *tid = new_tid;
+                    *regs = new_regs;
+                    print_lg(LevelPrint::DebugO, format!("now you are on the thread {new_tid}"));
+                }
+                Err(e) => print_lg(LevelPrint::ErrorO, e),
+            },
+            Some(Err(e)) => print_lg(LevelPrint::ErrorO, format!("failed to parse thread id: {e}")),
+            None => print_lg(LevelPrint::WarningO, crate::dbg::dbg_cmd::usages::USAGE_DBG_T),
+        },
+        Some(&"info") => match linev.get(1) {
+            Some(&"hmodule") | Some(&"module") | Some(&"m") => print_modules(&pm.modules),
+            Some(&"thread") | Some(&"th") => print_threads(&pm.target, *tid),
+            Some(&"exception") | Some(&"exc") | Some(&"signal") => match &pm.reason {
+                Some(reason) => println!("{WAR_COLOR}{reason}{RESET_COLOR}"),
+                None => println!("{VALID_COLOR}the dump does not record an exception{RESET_COLOR}"),
+            },
+            Some(elm) => print_lg(LevelPrint::ErrorO, format!("unknow option : '{elm}'")),
+            None => println!("{}", crate::usage::USAGE_INFO),
+        },
+        _ => return false,
+    }
+    true
+}
+
+
+pub fn run_session(session: &mut Session, pm: PostMortem) {
+    let mut tid = pm.tid;
+    let mut regs = match pm.target.get_registers(tid) {
+        Ok(regs) => regs,
+        Err(e) => {
+            print_lg(LevelPrint::ErrorO, e);
+            return;
+        }
+    };
+    session.base_addr = main_module_base(session.all.file.as_deref(), &pm.modules);
+    session.dump_bitness = Some(pm.bitness);
+    if let Some(reason) = &pm.reason {
+        print_lg(LevelPrint::WarningO, reason);
+    }
+    print_lg(LevelPrint::DebugO, format!("{} threads, {} modules, current thread {tid}", pm.target.threads().map(|t| t.len()).unwrap_or(0), pm.modules.len()));
+
+    let mut c_dbg = DbgState::Continue;
+    generic::cmd_wait(session, &pm.target, &mut regs, &mut c_dbg, &mut |_, linev, regs| handle_dump_cmd(&pm, &mut tid, linev, regs));
+    session.base_addr = 0;
+    session.dump_bitness = None;
+}
@@ -0,0 +1,335 @@
+use crate::pefile::Section;
+use crate::session::Session;
+use crate::symbol::{SymType, SymbolFile};
+use std::collections::HashSet;
+use std::{fs, io};
+
+const PT_LOAD: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_NOBITS: u32 = 8;
+const SHT_DYNSYM: u32 = 11;
+const SHF_ALLOC: u64 = 2;
+const SHF_EXECINSTR: u64 = 4;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+
+
+#[derive(Clone, Copy)]
+pub struct ElfHeader {
+    pub bitness: usize,
+    pub elf_type: u16,
+    pub machine: u16,
+    pub entry: u64,
+    pub image_base: u64,
+}
+
+#[derive(Clone, Copy)]
+pub struct ProgramHeader {
+    pub p_type: u32,
+    pub flags: u32,
+    pub offset: u64,
+    pub vaddr: u64,
+    pub file_size: u64,
+    pub mem_size: u64,
+    pub align: u64,
+}
+
+struct SectionHeader {
+    name: u32,
+    sh_type: u32,
+    flags: u64,
+    addr: u64,
+    offset: u64,
+    size: u64,
+    link: u32,
+    entsize: u64,
+}
+
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    is64: bool,
+}
+
+impl Reader<'_> {
+    fn bytes(&self, off: u64, len: usize) -> Result<&[u8], io::Error> {
+        let off = usize::try_from(off).map_err(|_| invalid("offset out of file"))?;
+        self.data.get(off..off.checked_add(len).ok_or_else(|| invalid("offset out of file"))?).ok_or_else(|| invalid("truncated elf file"))
+    }
+
+    fn u8(&self, off: u64) -> Result<u8, io::Error> {
+        Ok(self.bytes(off, 1)?[0])
+    }
+
+    fn u16(&self, off: u64) -> Result<u16, io::Error> {
+        Ok(u16::from_le_bytes(self.bytes(off, 2)?.try_into().unwrap()))
+    }
+
+    fn u32(&self, off: u64) -> Result<u32, io::Error> {
+        Ok(u32::from_le_bytes(self.bytes(off, 4)?.try_into().unwrap()))
+    }
+
+    fn u64(&self, off: u64) -> Result<u64, io::Error> {
+        Ok(u64::from_le_bytes(self.bytes(off, 8)?.try_into().unwrap()))
+    }
+
+    fn addr(&self, off: u64) -> Result<u64, io::Error> {
+        if self.is64 { self.u64(off) } else { self.u32(off).map(|v| v as u64) }
+    }
+
+    fn c_str(&self, off: u64) -> String {
+        let start = off as usize;
+        match self.data.get(start..) {
+            Some(rest) => {
+                let end = rest.iter().position(|&b| b == 0).unwrap_or(rest.len());
+                String::from_utf8_lossy(&rest[..end]).to_string()
+            }
+            None => String::new(),
+        }
+    }
+}
+
+
+fn read_program_header(r: &Reader, off: u64) -> Result<ProgramHeader, io::Error> {
+    if r.is64 {
+        Ok(ProgramHeader {
+            p_type: r.u32(off)?,
+            flags: r.u32(off + 4)?,
+            offset: r.u64(off + 8)?,
+            vaddr: r.u64(off + 16)?,
+            file_size: r.u64(off + 32)?,
+            mem_size: r.u64(off + 40)?,
+            align: r.u64(off + 48)?,
+        })
+    } else {
+        Ok(ProgramHeader {
+            p_type: r.u32(off)?,
+            offset: r.u32(off + 4)? as u64,
+            vaddr: r.u32(off + 8)? as u64,
+            file_size: r.u32(off + 16)? as u64,
+            mem_size: r.u32(off + 20)? as u64,
+            flags: r.u32(off + 24)?,
+            align: r.u32(off + 28)? as u64,
+        })
+    }
+}
+
+fn read_section_header(r: &Reader, off: u64) -> Result<SectionHeader, io::Error> {
+    if r.is64 {
+        Ok(SectionHeader {
+            name: r.u32(off)?,
+            sh_type: r.u32(off + 4)?,
+            flags: r.u64(off + 8)?,
+            addr: r.u64(off + 16)?,
+            offset: r.u64(off + 24)?,
+            size: r.u64(off + 32)?,
+            link: r.u32(off + 40)?,
+            entsize: r.u64(off + 56)?,
+        })
+    } else {
+        Ok(SectionHeader {
+            name: r.u32(off)?,
+            sh_type: r.u32(off + 4)?,
+            flags: r.u32(off + 8)? as u64,
+            addr: r.u32(off + 12)? as u64,
+            offset: r.u32(off + 16)? as u64,
+            size: r.u32(off + 20)? as u64,
+            link: r.u32(off + 24)?,
+            entsize: r.u32(off + 36)? as u64,
+        })
+    }
+}
+
+
+pub fn parse_header(session: &mut Session) -> Result<(), io::Error> {
+    let data = fs::read(session.all.file.clone().unwrap())?;
+    if data.len() < 0x34 || data[..4] != *b"\x7fELF" {
+        return Err(invalid("Invalid ELF signature"));
+    }
+    if data[5] != 1 {
+        return Err(invalid("only little endian elf file is supported"));
+    }
+    let r = Reader { data: &data, is64: data[4] == 2 };
+    let (phoff, shoff, phentsize, phnum, shentsize, shnum, shstrndx) = if r.is64 {
+        (r.u64(0x20)?, r.u64(0x28)?, r.u16(0x36)?, r.u16(0x38)?, r.u16(0x3a)?, r.u16(0x3c)?, r.u16(0x3e)?)
+    } else {
+        (r.u32(0x1c)? as u64, r.u32(0x20)? as u64, r.u16(0x2a)?, r.u16(0x2c)?, r.u16(0x2e)?, r.u16(0x30)?, r.u16(0x32)?)
+    };
+    let machine = r.u16(0x12)?;
+    if machine != 0x3e && machine != 0x03 {
+        return Err(invalid("only x64 - x32 file is supported"));
+    }
+
+    let mut program_headers = Vec::with_capacity(phnum as usize);
+    for i in 0..phnum as u64 {
+        program_headers.push(read_program_header(&r, phoff + i * phentsize as u64)?);
+    }
+    let image_base = program_headers.iter()
+        .filter(|p| p.p_type == PT_LOAD)
+        .map(|p| p.vaddr & !0xfff)
+        .min()
+        .unwrap_or(0);
+
+    let mut section_headers = Vec::with_capacity(shnum as usize);
+    for i in 0..shnum as u64 {
+        section_headers.push(read_section_header(&r, shoff + i * shentsize as u64)?);
+    }
+    let shstr_off = section_headers.get(shstrndx as usize).map(|s| s.offset).unwrap_or(0);
+
+    session.sections.clear();
+    for sh in &section_headers {
+        if sh.sh_type == 0 || sh.flags & SHF_ALLOC == 0 {
+            continue;
+        }
+        let content = if sh.sh_type == SHT_NOBITS {
+            Vec::new()
+        } else {
+            r.bytes(sh.offset, sh.size as usize)?.to_vec()
+        };
+        let raw_size = content.len() as u32;
+        session.sections.push(Section {
+            name: r.c_str(shstr_off + sh.name as u64),
+            content,
+            addr: sh.addr.saturating_sub(image_base) as u32,
+            characteristics: if sh.flags & SHF_EXECINSTR != 0 { 0x20000000 } else { 0 },
+            virtual_size: sh.size as u32,
+            raw_size,
+            raw_ptr: sh.offset as u32,
+        });
+    }
+
+    session.elf_header = Some(ElfHeader {
+        bitness: if r.is64 { 64 } else { 32 },
+        elf_type: r.u16(0x10)?,
+        machine,
+        entry: r.addr(0x18)?,
+        image_base,
+    });
+    session.program_headers = program_headers;
+    session.image_base = image_base;
+    session.nt_header = None;
+    session.func_info.clear();
+    Ok(())
+}
+
+
+pub fn load_symtab(session: &mut Session) -> Result<usize, io::Error> {
+    let data = fs::read(session.all.file.clone().unwrap())?;
+    let r = Reader { data: &data, is64: data.get(4) == Some(&2) };
+    let (shoff, shentsize, shnum) = if r.is64 {
+        (r.u64(0x28)?, r.u16(0x3a)?, r.u16(0x3c)?)
+    } else {
+        (r.u32(0x20)? as u64, r.u16(0x2e)?, r.u16(0x30)?)
+    };
+    let mut section_headers = Vec::with_capacity(shnum as usize);
+    for i in 0..shnum as u64 {
+        section_headers.push(read_section_header(&r, shoff + i * shentsize as u64)?);
+    }
+    let image_base = session.image_base;
+    let symbols = &mut session.symbols;
+    // the .symtab repeats most of the .dynsym
+    let mut seen: HashSet<(String, i64)> = symbols.symbol_file.iter().map(|s| (s.name.clone(), s.offset)).collect();
+    let mut count = 0;
+
+    for sh in section_headers.iter().filter(|s| s.sh_type == SHT_SYMTAB || s.sh_type == SHT_DYNSYM) {
+        let str_off = match section_headers.get(sh.link as usize) {
+            Some(strtab) => strtab.offset,
+            None => continue,
+        };
+        let entsize = if sh.entsize != 0 { sh.entsize } else if r.is64 { 24 } else { 16 };
+        for i in 1..sh.size / entsize {
+            let off = sh.offset + i * entsize;
+            let (name, info, value, size) = if r.is64 {
+                (r.u32(off)?, r.u8(off + 4)?, r.u64(off + 8)?, r.u64(off + 16)?)
+            } else {
+                (r.u32(off)?, r.u8(off + 12)?, r.u32(off + 4)? as u64, r.u32(off + 8)? as u64)
+            };
+            let types_e = match info & 0xf {
+                STT_FUNC => "STT_FUNC",
+                STT_OBJECT => "STT_OBJECT",
+                _ => continue,
+            };
+            let name = r.c_str(str_off + name as u64);
+            if value == 0 || name.is_empty() {
+                continue;
+            }
+            let offset = value.saturating_sub(image_base) as i64;
+            if !seen.insert((name.clone(), offset)) {
+                continue;
+            }
+            symbols.symbol_file.push(SymbolFile {
+                name,
+                offset,
+                size: size as usize,
+                types_e: types_e.to_string(),
+                symbol_type: SymType::Global,
+                ..Default::default()
+            });
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(out: &mut Vec<u8>, name: u32, sh_type: u32, flags: u64, addr: u64, offset: u64, size: u64) {
+        for v in [name, sh_type] {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        for v in [flags, addr, offset, size, 0, 1, 0] {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+
+    fn elf() -> Vec<u8> {
+        let mut out = vec![0u8; 0x140];
+        out[..4].copy_from_slice(b"\x7fELF");
+        out[4] = 2;
+        out[5] = 1;
+        out[0x10..0x12].copy_from_slice(&2u16.to_le_bytes());
+        out[0x12..0x14].copy_from_slice(&0x3eu16.to_le_bytes());
+        out[0x18..0x20].copy_from_slice(&0x401000u64.to_le_bytes());
+        out[0x20..0x28].copy_from_slice(&0x40u64.to_le_bytes());
+        out[0x28..0x30].copy_from_slice(&0x140u64.to_le_bytes());
+        for (off, v) in [(0x36, 56u16), (0x38, 1), (0x3a, 64), (0x3c, 4), (0x3e, 3)] {
+            out[off..off + 2].copy_from_slice(&v.to_le_bytes());
+        }
+        out[0x40..0x44].copy_from_slice(&PT_LOAD.to_le_bytes());
+        out[0x44..0x48].copy_from_slice(&5u32.to_le_bytes());
+        for (i, v) in [0u64, 0x400000, 0x400000, 0x140, 0x140, 0x1000].iter().enumerate() {
+            out[0x48 + i * 8..0x50 + i * 8].copy_from_slice(&v.to_le_bytes());
+        }
+        out[0x100..0x104].copy_from_slice(&[0x31, 0xc0, 0xc3, 0x90]);
+        out[0x110..0x114].copy_from_slice(b"GCC\0");
+        out[0x120..0x13a].copy_from_slice(b"\0.text\0.comment\0.shstrtab\0");
+        section(&mut out, 0, 0, 0, 0, 0, 0);
+        section(&mut out, 1, 1, SHF_ALLOC | SHF_EXECINSTR, 0x401000, 0x100, 4);
+        section(&mut out, 7, 1, 0x30, 0, 0x110, 4);
+        section(&mut out, 16, 3, 0, 0, 0x120, 0x1a);
+        out
+    }
+
+    #[test]
+    fn only_alloc_sections_are_mapped() {
+        let path = std::env::temp_dir().join(format!("lisa_dbg_{}.elf", std::process::id()));
+        fs::write(&path, elf()).unwrap();
+        let mut session = Session::default();
+        session.all.file = Some(path.to_str().unwrap().to_string());
+        let result = parse_header(&mut session);
+        let _ = fs::remove_file(&path);
+        result.unwrap();
+
+        assert_eq!(session.image_base, 0x400000);
+        let sections: Vec<_> = session.sections.iter().map(|s| (s.name.as_str(), s.addr, s.characteristics)).collect();
+        assert_eq!(sections, [(".text", 0x1000, 0x20000000)]);
+        assert_eq!(session.sections[0].content, [0x31, 0xc0, 0xc3, 0x90]);
+    }
+}
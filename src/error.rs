@@ -0,0 +1,80 @@
+use std::{fmt, io};
+use crate::pefile::PeError;
+
+
+/// Error returned by the public library API.
+#[derive(Debug)]
+pub enum Error {
+    /// The image could not be read.
+    Io(io::Error),
+    /// The headers or the section table are malformed, or the file is not a supported PE file.
+    Pe(PeError),
+    /// The DWARF sections are present but malformed.
+    Dwarf(String),
+    /// The COFF symbol table or its string table is malformed.
+    Coff(String),
+    /// The export directory is malformed.
+    Export(String),
+    /// The import directory is malformed.
+    Import(String),
+    /// The base relocation directory is malformed.
+    Reloc(String),
+    /// The TLS directory is malformed.
+    Tls(String),
+    /// The load config directory or one of its guard tables is malformed.
+    LoadConfig(String),
+    /// The resource directory is malformed.
+    Resource(String),
+    /// The debug directory is malformed.
+    Debug(String),
+    /// No symbol or function matches the given name.
+    NotFound(String),
+    /// The relative virtual address is not mapped by any section.
+    OutOfImage(u64),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "failed to load image : {e}"),
+            Error::Pe(e) => write!(f, "invalid pe file : {e}"),
+            Error::Dwarf(e) => write!(f, "invalid dwarf info : {e}"),
+            Error::Coff(e) => write!(f, "invalid coff symbol table : {e}"),
+            Error::Export(e) => write!(f, "invalid export directory : {e}"),
+            Error::Import(e) => write!(f, "invalid import directory : {e}"),
+            Error::Reloc(e) => write!(f, "invalid relocation directory : {e}"),
+            Error::Tls(e) => write!(f, "invalid tls directory : {e}"),
+            Error::LoadConfig(e) => write!(f, "invalid load config directory : {e}"),
+            Error::Resource(e) => write!(f, "invalid resource directory : {e}"),
+            Error::Debug(e) => write!(f, "invalid debug directory : {e}"),
+            Error::NotFound(name) => write!(f, "symbol '{name}' not found"),
+            Error::OutOfImage(rva) => write!(f, "rva {rva:#x} is outside of the image"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Pe(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<PeError> for Error {
+    fn from(e: PeError) -> Self {
+        Error::Pe(e)
+    }
+}
+
+
+/// Result type of the public library API.
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,297 @@
+use crate::dbg::dbg_cmd::disasm::{disasm_code, DisasmLine};
+use crate::error::{Error, Result};
+use crate::pefile::{self, debug, export, function, import, rich, section, NtHeaders, Section};
+use crate::pefile::debug::DebugEntry;
+use crate::pefile::export::ExportTable;
+use crate::pefile::import::Import;
+use crate::pefile::loadconfig::{self, LoadConfig};
+use crate::pefile::reloc::{self, Reloc};
+use crate::pefile::resource::{self, Resource};
+use crate::pefile::rich::RichHeader;
+use crate::pefile::section::SectionWarning;
+use crate::pefile::tls::{self, Tls};
+use crate::symbol::{coff, dwarf, SymbolFile};
+use crate::pefile::winnt::RUNTIME_FUNCTION;
+
+
+/// A PE image loaded from disk, with its sections, `.pdata` entries and symbols.
+///
+/// Addresses taken and returned by this type are relative virtual addresses
+/// unless stated otherwise. Disassembly is laid out at the preferred image base.
+pub struct Image {
+    path: String,
+    nt_header: NtHeaders,
+    sections: Vec<Section>,
+    functions: Vec<RUNTIME_FUNCTION>,
+    symbols: Vec<SymbolFile>,
+    imports: Vec<Import>,
+    relocs: Vec<Reloc>,
+    tls: Option<Tls>,
+    load_config: Option<LoadConfig>,
+    resources: Vec<Resource>,
+    rich: Option<RichHeader>,
+    debug: Vec<DebugEntry>,
+    errors: Vec<Error>,
+}
+
+
+impl Image {
+    /// Parses the PE file at `path`, its exception directory, its DWARF info, or its COFF symbol
+    /// table when it has no DWARF info, its exports, its imports, its base relocations, its TLS
+    /// directory, its load config directory, its resources, its Rich header and its debug directory.
+    ///
+    /// Only unreadable headers or section table fail the load. A malformed directory or symbol
+    /// table is left empty and its error is kept, see [`Image::errors`].
+    pub fn load(path: &str) -> Result<Image> {
+        let data = std::fs::read(path)?;
+        let (nt_header, sections) = pefile::parse_pe(&data)?;
+        let bitness = nt_header.get_bitness();
+        let image_base = nt_header.image_base();
+        let mut errors = Vec::new();
+        let functions = function::read_pdata(&sections, nt_header.data_directory(3)).unwrap_or_default();
+        let mut symbols = or_keep(dwarf::parse_dwarf(&sections, image_base), Error::Dwarf, &mut errors);
+        if symbols.is_empty() {
+            symbols = or_keep(coff::parse_coff(&data), Error::Coff, &mut errors);
+        }
+        let exports = or_keep(export::parse_exports(&sections, nt_header.data_directory(0)), Error::Export, &mut errors);
+        for sym in exports {
+            if !symbols.iter().any(|s| s.name == sym.name) {
+                symbols.push(sym);
+            }
+        }
+        let mut imports = or_keep(import::parse_imports(&sections, nt_header.data_directory(1), bitness), Error::Import, &mut errors);
+        imports.extend(or_keep(import::parse_delay_imports(&sections, nt_header.data_directory(13), bitness, image_base), Error::Import, &mut errors));
+        let relocs = or_keep(reloc::parse_relocs(&sections, nt_header.data_directory(5)), Error::Reloc, &mut errors);
+        let tls = or_keep(tls::parse_tls(&sections, nt_header.data_directory(9), bitness, image_base), Error::Tls, &mut errors);
+        let load_config = or_keep(loadconfig::parse_load_config(&sections, nt_header.data_directory(10), bitness, image_base), Error::LoadConfig, &mut errors);
+        let resources = or_keep(resource::parse_resources(&sections, nt_header.data_directory(2)), Error::Resource, &mut errors);
+        let rich = rich::parse_rich(&data);
+        let debug = or_keep(debug::parse_debug_directory(&sections, nt_header.data_directory(6)), Error::Debug, &mut errors);
+        Ok(Image { path: path.to_string(), nt_header, sections, functions, symbols, imports, relocs, tls, load_config, resources, rich, debug, errors })
+    }
+
+    /// Errors of the directories and symbol tables that could not be parsed by [`Image::load`],
+    /// in the order they were read.
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    /// Path the image was loaded from.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Raw NT headers, 32 or 64-bit.
+    pub fn nt_headers(&self) -> &NtHeaders {
+        &self.nt_header
+    }
+
+    /// Rich header left by the Microsoft linker in the DOS stub, with the tools that
+    /// built the objects of the image.
+    pub fn rich_header(&self) -> Option<&RichHeader> {
+        self.rich.as_ref()
+    }
+
+    /// Entries of the debug directory. CodeView, POGO, repro, VC feature and extended
+    /// DLL characteristics entries are decoded when their data is mapped.
+    pub fn debug_directory(&self) -> &[DebugEntry] {
+        &self.debug
+    }
+
+    /// 32 or 64.
+    pub fn bitness(&self) -> usize {
+        self.nt_header.get_bitness()
+    }
+
+    /// Preferred image base from the optional header.
+    pub fn image_base(&self) -> u64 {
+        self.nt_header.image_base()
+    }
+
+    /// Sections with their raw content.
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+
+    /// Section named `name`, e.g. `.text`.
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|s| s.name == name)
+    }
+
+    /// Signs that the image is packed or modifies its own code: writable and executable
+    /// sections, executable sections without raw data, section names of known packers,
+    /// sections of high entropy and an entry point outside of the code.
+    pub fn section_warnings(&self) -> Vec<SectionWarning> {
+        section::check_sections(&self.sections, self.nt_header.entry_point())
+    }
+
+    /// Entries of the exception directory (`.pdata`). Empty for 32-bit images.
+    pub fn functions(&self) -> &[RUNTIME_FUNCTION] {
+        &self.functions
+    }
+
+    /// DWARF symbols, or COFF symbols for an image without DWARF info, followed by
+    /// the exported functions not already described by them.
+    pub fn symbols(&self) -> &[SymbolFile] {
+        &self.symbols
+    }
+
+    /// Exported functions that are code in this image. The ones exported only
+    /// by ordinal are named `dll!#ordinal`, forwarders are left out.
+    pub fn exports(&self) -> Result<Vec<SymbolFile>> {
+        export::parse_exports(&self.sections, self.nt_header.data_directory(0)).map_err(|e| Error::Export(e.to_string()))
+    }
+
+    /// Export directory with one entry per name, or per ordinal when it has no
+    /// name, forwarders included. See [`export::resolve_export`] to follow them.
+    pub fn export_table(&self) -> Result<ExportTable> {
+        export::parse_export_table(&self.sections, self.nt_header.data_directory(0)).map_err(|e| Error::Export(e.to_string()))
+    }
+
+    /// Imported functions, one entry per IAT slot, in directory order. The
+    /// delay-loaded ones come last and carry the RVA of their loading thunk.
+    pub fn imports(&self) -> &[Import] {
+        &self.imports
+    }
+
+    /// Import whose IAT slot is at `rva`.
+    pub fn import_at(&self, rva: u64) -> Option<&Import> {
+        self.imports.iter().find(|i| i.iat_rva as u64 == rva)
+    }
+
+    /// RVAs of the `call`/`jmp` instructions that go through the IAT slot of
+    /// `target`, written `dll!name` or `dll!#ordinal`. Executable sections are
+    /// swept linearly.
+    pub fn import_calls(&self, target: &str) -> Result<Vec<u64>> {
+        let import = self.imports.iter().find(|i| i.matches(target)).ok_or_else(|| Error::NotFound(target.to_string()))?;
+        Ok(import::find_iat_calls(&self.sections, self.bitness(), self.image_base(), import.iat_rva))
+    }
+
+    /// Base relocations sorted by RVA, without the padding entries.
+    pub fn relocations(&self) -> &[Reloc] {
+        &self.relocs
+    }
+
+    /// Relocation of the slot at `rva`, i.e. whether it holds an absolute
+    /// address that the loader rebases.
+    pub fn reloc_at(&self, rva: u64) -> Option<&Reloc> {
+        reloc::find_reloc(&self.relocs, rva)
+    }
+
+    /// TLS directory, if the image has one. The callbacks are RVAs, the other
+    /// addresses are VAs at the preferred image base.
+    pub fn tls(&self) -> Option<&Tls> {
+        self.tls.as_ref()
+    }
+
+    /// Load config directory, if the image has one. Its addresses are VAs at the
+    /// preferred image base, the entries of its tables are RVAs.
+    pub fn load_config(&self) -> Option<&LoadConfig> {
+        self.load_config.as_ref()
+    }
+
+    /// Leaves of the resource tree, in directory order.
+    pub fn resources(&self) -> &[Resource] {
+        &self.resources
+    }
+
+    /// Raw bytes of a resource, `None` if its data is not mapped by any section.
+    pub fn resource_data(&self, res: &Resource) -> Option<&[u8]> {
+        res.data(&self.sections)
+    }
+
+    /// RVA of the symbol named `name`.
+    pub fn resolve(&self, name: &str) -> Result<u64> {
+        self.symbols.iter()
+            .find(|s| s.name == name && s.offset > 0)
+            .map(|s| s.offset as u64)
+            .ok_or_else(|| Error::NotFound(name.to_string()))
+    }
+
+    /// Symbol starting at, or covering, `rva`.
+    pub fn symbol_at(&self, rva: u64) -> Option<&SymbolFile> {
+        self.symbols.iter()
+            .filter(|s| s.offset > 0)
+            .find(|s| s.offset as u64 == rva || (s.offset as u64) < rva && rva < s.offset as u64 + s.size as u64)
+    }
+
+    /// `.pdata` entry of the function containing `rva`.
+    pub fn function_at(&self, rva: u64) -> Option<&RUNTIME_FUNCTION> {
+        self.functions.iter().find(|f| f.BeginAddress as u64 <= rva && rva < f.EndAddress as u64)
+    }
+
+    /// Disassembles the function named `name`. See [`Image::disasm_rva`].
+    pub fn disasm_function(&self, name: &str) -> Result<Vec<DisasmLine>> {
+        self.disasm_rva(self.resolve(name)?)
+    }
+
+    /// Disassembles from `rva` to the end of its `.pdata` function, or to the end of
+    /// its section when the image has no exception directory. Branch targets and
+    /// symbols are named like the `disasm` command does, without colors,
+    /// memory operands that read an IAT slot show the imported function, the ones
+    /// that read a Control Flow Guard pointer show `__guard_check_icall_fptr` and
+    /// the like, and delay-load thunks are named `__imp_load_<function>`.
+    pub fn disasm_rva(&self, rva: u64) -> Result<Vec<DisasmLine>> {
+        let section = pefile::get_section_of_rva(&self.sections, rva).ok_or(Error::OutOfImage(rva))?;
+        let section_end = section.addr as u64 + section.content.len() as u64;
+        let end = match self.function_at(rva) {
+            Some(func) => (func.EndAddress as u64).min(section_end),
+            None => section_end,
+        };
+        let code = pefile::read_rva(&self.sections, rva, (end - rva) as usize).ok_or(Error::OutOfImage(rva))?;
+        let image_base = self.image_base();
+        let sym_file = self.symbols.iter()
+            .filter(|s| s.offset > 0)
+            .map(|s| (s.offset as u64 + image_base, s.name.clone()))
+            .chain(self.imports.iter().filter_map(|i| Some((i.delay_thunk? as u64 + image_base, i.thunk_name()))))
+            .collect();
+        let mut iat: Vec<(u64, String)> = self.imports.iter().map(|i| (i.iat_rva as u64 + image_base, i.label())).collect();
+        if let Some(lc) = &self.load_config {
+            iat.extend(lc.guard_pointers(image_base).into_iter().map(|(rva, name)| (rva + image_base, name.to_string())));
+        }
+        Ok(disasm_code(sym_file, iat, self.functions.clone(), image_base, self.bitness() as u32, code, rva + image_base))
+    }
+}
+
+
+fn or_keep<T: Default, E: ToString>(res: std::result::Result<T, E>, kind: fn(String) -> Error, errors: &mut Vec<Error>) -> T {
+    res.unwrap_or_else(|e| {
+        errors.push(kind(e.to_string()));
+        T::default()
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corpus(name: &str) -> String {
+        format!("{}/fuzz/corpus/pe_parse/{name}", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    #[test]
+    fn valid_image_has_no_errors() {
+        let image = Image::load(&corpus("valid_pe64")).unwrap();
+        assert!(image.errors().is_empty());
+        assert!(!image.sections().is_empty());
+    }
+
+    #[test]
+    fn malformed_directories_are_kept() {
+        let image = Image::load(&corpus("import_unterminated")).unwrap();
+        assert!(matches!(image.errors(), [Error::Import(_)]));
+        assert!(image.imports().is_empty());
+        assert!(image.section(".text").is_some());
+
+        let image = Image::load(&corpus("packer_sections")).unwrap();
+        assert!(matches!(image.errors(), [Error::Import(_), Error::Reloc(_), Error::Resource(_)]));
+        assert!(!image.section_warnings().is_empty());
+    }
+
+    #[test]
+    fn malformed_headers_fail() {
+        assert!(matches!(Image::load(&corpus("magic_mismatch")), Err(Error::Pe(_))));
+        assert!(matches!(Image::load(&corpus("empty")), Err(Error::Pe(_))));
+    }
+}
@@ -0,0 +1,117 @@
+//! LisaDbg as a library.
+//!
+//! [`Image`] loads a PE file and exposes its headers, sections, `.pdata`
+//! entries, exports and DWARF symbols, and disassembles functions with the
+//! same symbol-aware formatter as the `disasm` command. Every call returns a
+//! [`Result`] instead of printing.
+//!
+//! [`pefile`] and [`symbol`] hold the parsers behind it. The debugger itself
+//! is internal to the crate and only reachable through [`run`].
+
+
+extern crate core;
+
+mod error;
+mod image;
+
+pub mod pefile;
+pub mod symbol;
+
+pub(crate) mod cli;
+pub(crate) mod command;
+pub(crate) mod dbg;
+#[cfg(windows)]
+pub(crate) mod process;
+pub(crate) mod ste;
+pub(crate) mod usage;
+pub(crate) mod ut;
+pub(crate) mod dllib;
+pub(crate) mod dump;
+pub(crate) mod elffile;
+pub(crate) mod session;
+
+pub use dbg::dbg_cmd::disasm::DisasmLine;
+pub use error::{Error, Result};
+pub use image::Image;
+pub use pefile::import::Import;
+pub use pefile::PeError;
+
+use crate::command::def;
+use command::def::variable;
+use crate::dbg::{CONTEXT, HANDLE};
+use crate::ut::fmt::{print_lg, LevelPrint};
+use crate::dbg::target::{ImageTarget, MemTarget};
+use crate::session::Session;
+use std::io::{self, Write};
+use structopt::StructOpt;
+
+
+/// Starts the `LisaDbg` prompt with the options of the command line.
+pub fn run() {
+    let option = cli::Dbgoption::from_args();
+    let mut session = Session::new(option.to_all_elm());
+    if let Some(file) = option.file.as_ref().or(option.file_opt.as_ref()) {
+        let intp = format!("file {file}");
+        command::file::handle_change_file(&mut session, &intp.split_whitespace().collect::<Vec<&str>>(), &intp);
+    }
+    option.exec_cmd(&mut session);
+    ctx_before_run(&mut session);
+}
+
+
+fn ctx_before_run(session: &mut Session) {
+    loop {
+        let mut input = String::new();
+        print!(">> ");
+        io::stdout().flush().unwrap();
+        io::stdin().read_line(&mut input).unwrap();
+        let input = input.trim_start().trim_end();
+        let linev: Vec<&str> = input.split_whitespace().collect();
+        handle_cmd(session, &linev, input);
+    }
+}
+
+
+pub(crate) fn handle_cmd(session: &mut Session, linev: &[&str], input: &str) {
+    let cmd = linev.first();
+    match cmd {
+        Some(&"breakpoint") | Some(&"b") => command::breakpoint::handle_breakpts(session, linev),
+        Some(&"file") => command::file::handle_change_file(session, linev, input),
+        Some(&"run") => dbg::run(session),
+        Some(&"start") => command::start::handle_start(session, linev),
+        Some(&"reset") => command::reset::handle_reset(session, linev),
+        Some(&"quit") | Some(&"q") | Some(&"exit") => std::process::exit(0),
+        Some(&"s") | Some(&"sym") | Some(&"symbol") => symbol::load_symbol(session, linev, input),
+        Some(&"break-ret") | Some(&"b-ret") => command::stret::st_return(session, linev),
+        Some(&"skip") => command::skip::skip(session, linev),
+        Some(&"hook") | Some(&"ho") => command::hook::hook(session, linev),
+        Some(&"def") => def::handle_def(session, linev, input),
+        Some(&"arg") | Some(&"args") | Some(&"argv") => command::arg::set_argument(session, linev),
+        Some(&"help") | Some(&"h") => usage::help(linev),
+        Some(&"help-c") => dbg::dbg_cmd::usages::help(linev),
+        Some(&"info") => unsafe { command::info::handle_info(session, linev, std::mem::zeroed(), 0 as HANDLE) },
+        Some(&"w") | Some(&"watch") | Some(&"watchpoint") => command::watchpoint::watchpoint(session, linev),
+        Some(&"clear") => command::clear_cmd::clear_cmd(),
+        Some(&"remove") => command::remover::remove_element(session, linev),
+        Some(&"sym-info") => unsafe { command::sym::handle_sym_info(session, linev, std::mem::zeroed()) },
+        Some(&"attach") => command::attach::handle_attach(session, linev),
+        Some(&"target") => command::remote::handle_target(session, linev),
+        Some(&"gdbserver") => command::remote::handle_gdbserver(session, linev),
+        Some(&"load-dump") => command::dump::handle_load_dump(session, linev, input),
+        Some(&"load-core") => command::dump::handle_load_core(session, linev, input),
+        Some(&"record") => command::trace::handle_record(session, linev, input),
+        Some(&"replay") => command::trace::handle_replay(session, linev, input),
+        Some(&"bva") | Some(&"b-va") | Some(&"break-va") => command::breakpoint::handle_break_va(session, linev),
+        Some(&"b-import") | Some(&"break-import") => command::breakpoint::handle_break_import(session, linev),
+        Some(&"b-tls") | Some(&"break-tls") => command::breakpoint::handle_break_tls(session),
+        Some(&"dump-resource") => command::resource::handle_dump_resource(session, linev),
+        Some(&"proc-addr") => command::proc_addr::handle_get_proc_addr(session, 0 as HANDLE, linev),
+        Some(&"disasm") => dbg::dbg_cmd::disasm::handle_disasm(session, linev, &ImageTarget(&session.sections), std::ptr::null::<CONTEXT>()),
+        Some(&"b-ret-va") | Some(&"b-retva") => command::stret::handle_b_ret_va(session, linev),
+        Some(&"add") => command::little_secret::add_op(linev),
+        Some(&"sub") => command::little_secret::sub_op(linev),
+        Some(&"printf") => variable::printf::printf_var(session, linev, input, &MemTarget::new()),
+        None => print_lg(LevelPrint::ErrorO, "please enter a command"),
+        _ => print_lg(LevelPrint::ErrorO, format!("command '{}' is unknow", cmd.unwrap())),
+    }
+}
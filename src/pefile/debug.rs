@@ -0,0 +1,217 @@
+use anyhow::anyhow;
+use crate::pefile::winnt::IMAGE_DATA_DIRECTORY;
+use crate::pefile::{read_rva, Section, MAX_ENTRIES};
+
+const DEBUG_ENTRY_SIZE: usize = 28;
+
+pub const IMAGE_DEBUG_TYPE_CODEVIEW: u32 = 2;
+pub const IMAGE_DEBUG_TYPE_VC_FEATURE: u32 = 12;
+pub const IMAGE_DEBUG_TYPE_POGO: u32 = 13;
+pub const IMAGE_DEBUG_TYPE_REPRO: u32 = 16;
+pub const IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS: u32 = 20;
+
+
+#[derive(Debug, Clone)]
+pub struct PogoEntry {
+    pub rva: u32,
+    pub size: u32,
+    pub name: String,
+}
+
+
+#[derive(Debug, Clone)]
+pub enum DebugInfo {
+    CodeView { guid: [u8; 16], age: u32, path: String },
+    CodeView20 { signature: u32, age: u32, path: String },
+    Pogo { signature: String, entries: Vec<PogoEntry> },
+    Repro(Vec<u8>),
+    VcFeature { pre_vc11: u32, c_cpp: u32, gs: u32, sdl: u32, guard_n: u32 },
+    ExDllCharacteristics(u32),
+    Raw,
+}
+
+
+#[derive(Debug, Clone)]
+pub struct DebugEntry {
+    pub characteristics: u32,
+    pub time_date_stamp: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub debug_type: u32,
+    pub size: u32,
+    pub rva: u32,
+    pub raw_ptr: u32,
+    pub info: DebugInfo,
+}
+
+impl DebugEntry {
+    pub fn type_str(&self) -> &'static str {
+        match self.debug_type {
+            1 => "coff",
+            IMAGE_DEBUG_TYPE_CODEVIEW => "codeview",
+            3 => "fpo",
+            4 => "misc",
+            5 => "exception",
+            6 => "fixup",
+            7 => "omap to src",
+            8 => "omap from src",
+            9 => "borland",
+            11 => "clsid",
+            IMAGE_DEBUG_TYPE_VC_FEATURE => "vc feature",
+            IMAGE_DEBUG_TYPE_POGO => "pogo",
+            14 => "iltcg",
+            15 => "mpx",
+            IMAGE_DEBUG_TYPE_REPRO => "repro",
+            17 => "embedded portable pdb",
+            19 => "pdb checksum",
+            IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS => "ex dll characteristics",
+            _ => "unknown",
+        }
+    }
+}
+
+
+// the first three fields of a guid are little-endian, as in the pdb and on the symbol servers
+pub fn guid_str(guid: &[u8; 16]) -> String {
+    format!("{:08X}-{:04X}-{:04X}-{}-{}",
+        u32::from_le_bytes([guid[0], guid[1], guid[2], guid[3]]),
+        u16::from_le_bytes([guid[4], guid[5]]),
+        u16::from_le_bytes([guid[6], guid[7]]),
+        guid[8..10].iter().map(|b| format!("{:02X}", b)).collect::<String>(),
+        guid[10..].iter().map(|b| format!("{:02X}", b)).collect::<String>(),
+    )
+}
+
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+
+fn cstr_at(data: &[u8], offset: usize) -> String {
+    let bytes = data.get(offset..).unwrap_or_default();
+    String::from_utf8_lossy(&bytes[..bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len())]).to_string()
+}
+
+
+fn decode_pogo(data: &[u8]) -> Option<DebugInfo> {
+    // the signature is a dword, "LTCG" or "PGU"
+    let signature = String::from_utf8_lossy(data.get(..4)?).chars().rev().collect::<String>().trim_end_matches('\0').to_string();
+    let mut entries = Vec::new();
+    let mut off = 4;
+    while let (Some(rva), Some(size)) = (u32_at(data, off), u32_at(data, off + 4)) {
+        entries.push(PogoEntry { rva, size, name: cstr_at(data, off + 8) });
+        // the name is null terminated and padded to 4 bytes, the lossy string can be longer than its bytes
+        let name = data.get(off + 8..).unwrap_or_default();
+        off += 8 + ((name.iter().position(|&b| b == 0).unwrap_or(name.len()) + 4) & !3);
+    }
+    Some(DebugInfo::Pogo { signature, entries })
+}
+
+
+fn decode(debug_type: u32, data: &[u8]) -> Option<DebugInfo> {
+    Some(match debug_type {
+        IMAGE_DEBUG_TYPE_CODEVIEW => match data.get(..4)? {
+            b"RSDS" => DebugInfo::CodeView { guid: data.get(4..20)?.try_into().ok()?, age: u32_at(data, 20)?, path: cstr_at(data, 24) },
+            b"NB10" => DebugInfo::CodeView20 { signature: u32_at(data, 8)?, age: u32_at(data, 12)?, path: cstr_at(data, 16) },
+            _ => return None,
+        },
+        IMAGE_DEBUG_TYPE_POGO => return decode_pogo(data),
+        IMAGE_DEBUG_TYPE_REPRO => match u32_at(data, 0) {
+            Some(len) => DebugInfo::Repro(data.get(4..4 + len as usize)?.to_vec()),
+            None => DebugInfo::Repro(Vec::new()),
+        },
+        IMAGE_DEBUG_TYPE_VC_FEATURE => DebugInfo::VcFeature {
+            pre_vc11: u32_at(data, 0)?,
+            c_cpp: u32_at(data, 4)?,
+            gs: u32_at(data, 8)?,
+            sdl: u32_at(data, 12)?,
+            guard_n: u32_at(data, 16)?,
+        },
+        IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS => DebugInfo::ExDllCharacteristics(u32_at(data, 0)?),
+        _ => return None,
+    })
+}
+
+
+pub fn parse_debug_directory(sections: &[Section], debug_dir: IMAGE_DATA_DIRECTORY) -> Result<Vec<DebugEntry>, anyhow::Error> {
+    if debug_dir.VirtualAddress == 0 || debug_dir.Size == 0 {
+        return Ok(Vec::new());
+    }
+    let count = debug_dir.Size as usize / DEBUG_ENTRY_SIZE;
+    if count > MAX_ENTRIES {
+        return Err(anyhow!("the debug directory has {count} entries"));
+    }
+    let table = read_rva(sections, debug_dir.VirtualAddress as u64, count * DEBUG_ENTRY_SIZE)
+        .ok_or_else(|| anyhow!("debug directory {:#x} is outside of the sections", debug_dir.VirtualAddress))?;
+
+    let mut result = Vec::new();
+    for raw in table.chunks_exact(DEBUG_ENTRY_SIZE) {
+        let u16_at = |off: usize| u16::from_le_bytes([raw[off], raw[off + 1]]);
+        let u32_at = |off: usize| u32::from_le_bytes([raw[off], raw[off + 1], raw[off + 2], raw[off + 3]]);
+        let mut entry = DebugEntry {
+            characteristics: u32_at(0),
+            time_date_stamp: u32_at(4),
+            major_version: u16_at(8),
+            minor_version: u16_at(10),
+            debug_type: u32_at(12),
+            size: u32_at(16),
+            rva: u32_at(20),
+            raw_ptr: u32_at(24),
+            info: DebugInfo::Raw,
+        };
+        // the data of some entries is only in the file, those are left undecoded
+        if entry.debug_type == IMAGE_DEBUG_TYPE_REPRO && entry.size == 0 {
+            // a deterministic build without a hash
+            entry.info = DebugInfo::Repro(Vec::new());
+        } else if entry.rva != 0 {
+            if let Some(info) = read_rva(sections, entry.rva as u64, entry.size as usize).and_then(|data| decode(entry.debug_type, data)) {
+                entry.info = info;
+            }
+        }
+        result.push(entry);
+    }
+    Ok(result)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pefile::load_pe;
+
+    #[test]
+    fn codeview_pogo_and_repro() {
+        let (nt, sections) = load_pe(&format!("{}/fuzz/corpus/pe_parse/debug_directory", env!("CARGO_MANIFEST_DIR"))).unwrap();
+        let entries = parse_debug_directory(&sections, nt.data_directory(6)).unwrap();
+        let types: Vec<&str> = entries.iter().map(|e| e.type_str()).collect();
+        assert_eq!(types, ["codeview", "pogo", "repro", "repro"]);
+
+        let DebugInfo::CodeView { guid, age, path } = &entries[0].info else { panic!("{:?}", entries[0].info) };
+        assert_eq!(guid_str(guid), "03020100-0504-0706-0809-0A0B0C0D0E0F");
+        assert_eq!((*age, path.as_str()), (1, "C:\\out\\app.pdb"));
+
+        let DebugInfo::Pogo { signature, entries: pogo } = &entries[1].info else { panic!("{:?}", entries[1].info) };
+        assert_eq!(signature, "LTCG");
+        let pogo: Vec<(u32, u32, &str)> = pogo.iter().map(|p| (p.rva, p.size, p.name.as_str())).collect();
+        assert_eq!(pogo, [(0x1000, 0xc, ".text$mn")]);
+
+        assert!(matches!(&entries[2].info, DebugInfo::Repro(hash) if *hash == [0; 32]));
+        assert!(matches!(&entries[3].info, DebugInfo::Repro(hash) if hash.is_empty()));
+    }
+
+    #[test]
+    fn pogo_names_that_are_not_utf8() {
+        let mut data = b"\0GTC".to_vec();
+        for (rva, size, name) in [(0x1000u32, 0x10u32, &b"a\xff"[..]), (0x1010, 0x20, b".text$mn")] {
+            data.extend(rva.to_le_bytes());
+            data.extend(size.to_le_bytes());
+            data.extend(name);
+            data.resize(data.len() + 4 - name.len() % 4, 0);
+        }
+        let Some(DebugInfo::Pogo { signature, entries }) = decode_pogo(&data) else { panic!() };
+        assert_eq!(signature, "CTG");
+        let entries: Vec<(u32, u32, &str)> = entries.iter().map(|p| (p.rva, p.size, p.name.as_str())).collect();
+        assert_eq!(entries, [(0x1000, 0x10, "a\u{fffd}"), (0x1010, 0x20, ".text$mn")]);
+    }
+}
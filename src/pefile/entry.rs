@@ -0,0 +1,230 @@
+use iced_x86::{Decoder, DecoderOptions, FlowControl, Instruction, Mnemonic, OpKind, Register};
+use crate::pefile::import::{slot_of_operand, Import};
+use crate::pefile::{get_section_of_rva, Section};
+
+const MAIN_NAMES: [&str; 8] = ["main", "wmain", "WinMain", "wWinMain", "_main", "_wmain", "_WinMain@16", "_wWinMain@16"];
+const EXIT_IMPORTS: [&str; 5] = ["exit", "_exit", "_cexit", "_c_exit", "ExitProcess"];
+const MAX_INSN: usize = 1000;
+const MAX_DEPTH: usize = 3;
+const MAX_FUNC: usize = 64;
+
+
+pub fn is_main_name(name: &str) -> bool {
+    MAIN_NAMES.contains(&name)
+}
+
+
+struct CrtScan<'a> {
+    sections: &'a [Section],
+    imports: &'a [Import],
+    bitness: u32,
+    image_base: u64,
+}
+
+impl CrtScan<'_> {
+    fn decode(&self, rva: u64, max: usize) -> Vec<Instruction> {
+        let Some(section) = get_section_of_rva(self.sections, rva) else {
+            return Vec::new();
+        };
+        let code = &section.content[(rva - section.addr as u64) as usize..];
+        let mut decoder = Decoder::with_ip(self.bitness, code, self.image_base + rva, DecoderOptions::NONE);
+        let mut result = Vec::new();
+        while decoder.can_decode() && result.len() < max {
+            let insn = decoder.decode();
+            if insn.mnemonic() == Mnemonic::Int3 || insn.is_invalid() {
+                break;
+            }
+            result.push(insn);
+        }
+        result
+    }
+
+    fn import_of_slot(&self, slot: u64) -> Option<&Import> {
+        self.imports.iter().find(|i| i.iat_rva as u64 + self.image_base == slot)
+    }
+
+    // a call reaches an import either through its IAT slot or through a `jmp [slot]` stub
+    fn import_of_call(&self, insn: &Instruction) -> Option<&Import> {
+        if let Some(slot) = slot_of_operand(insn) {
+            return self.import_of_slot(slot);
+        }
+        let target = self.internal_target(insn)?;
+        let stub = self.decode(target - self.image_base, 1);
+        let first = stub.first()?;
+        if first.flow_control() == FlowControl::IndirectBranch {
+            self.import_of_slot(slot_of_operand(first)?)
+        } else {
+            None
+        }
+    }
+
+    fn internal_target(&self, insn: &Instruction) -> Option<u64> {
+        if insn.op_count() == 0 || !matches!(insn.op0_kind(), OpKind::NearBranch32 | OpKind::NearBranch64) {
+            return None;
+        }
+        let target = insn.near_branch_target();
+        get_section_of_rva(self.sections, target.checked_sub(self.image_base)?)?;
+        Some(target)
+    }
+
+    // the value returned by main is saved right after the call and later given to exit
+    fn saves_eax(insn: &Instruction) -> bool {
+        insn.mnemonic() == Mnemonic::Mov && insn.op_count() == 2 && insn.op1_kind() == OpKind::Register && insn.op1_register() == Register::EAX
+    }
+
+    // a cdecl caller pops the arguments of main before saving its result
+    fn pops_args(insn: &Instruction) -> bool {
+        insn.mnemonic() == Mnemonic::Add && insn.op0_kind() == OpKind::Register && insn.op0_register() == Register::ESP && insn.op1_kind() != OpKind::Register
+    }
+
+    fn find(&self, rva: u64, depth: usize, visited: &mut Vec<u64>) -> Option<u64> {
+        if depth > MAX_DEPTH || visited.len() >= MAX_FUNC || visited.contains(&rva) {
+            return None;
+        }
+        visited.push(rva);
+        let insnv = self.decode(rva, MAX_INSN);
+        let mut candidate = None;
+        let mut callees = Vec::new();
+        for (i, insn) in insnv.iter().enumerate() {
+            let flow = insn.flow_control();
+            if !matches!(flow, FlowControl::Call | FlowControl::IndirectCall | FlowControl::UnconditionalBranch) {
+                continue;
+            }
+            if let Some(import) = self.import_of_call(insn) {
+                if candidate.is_some() && import.name.as_deref().is_some_and(|n| EXIT_IMPORTS.contains(&n)) {
+                    return candidate;
+                }
+            } else if let Some(target) = self.internal_target(insn) {
+                let mut next = insnv.get(i + 1);
+                if next.is_some_and(Self::pops_args) {
+                    next = insnv.get(i + 2);
+                }
+                if flow == FlowControl::Call && next.is_some_and(Self::saves_eax) {
+                    candidate = Some(target - self.image_base);
+                }
+                // past the entry stub, a jmp stays in the same function
+                if flow == FlowControl::Call || depth == 0 {
+                    callees.push(target - self.image_base);
+                }
+            }
+        }
+        callees.into_iter().find_map(|callee| self.find(callee, depth + 1, visited))
+    }
+}
+
+
+pub fn find_main_crt(sections: &[Section], imports: &[Import], bitness: usize, image_base: u64, entry_rva: u64) -> Option<u64> {
+    let scan = CrtScan { sections, imports, bitness: bitness as u32, image_base };
+    scan.find(entry_rva, 0, &mut Vec::new())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMAGE_BASE: u64 = 0x140000000;
+    const IMAGE_BASE_32: u64 = 0x400000;
+    const EXIT_SLOT: u32 = 0x2000;
+    const ARGC_SLOT: u32 = 0x2008;
+
+    struct Asm(Vec<u8>);
+
+    impl Asm {
+        fn at(&mut self, rva: u64) -> &mut Self {
+            self.0.resize((rva - 0x1000) as usize, 0xcc);
+            self
+        }
+
+        fn rva(&self) -> u64 {
+            0x1000 + self.0.len() as u64
+        }
+
+        fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+            self.0.extend_from_slice(bytes);
+            self
+        }
+
+        fn rel(&mut self, op: &[u8], target: u64) -> &mut Self {
+            let next = self.rva() + op.len() as u64 + 4;
+            self.bytes(op).bytes(&(target.wrapping_sub(next) as u32).to_le_bytes())
+        }
+
+        fn call(&mut self, target: u64) -> &mut Self {
+            self.rel(&[0xe8], target)
+        }
+
+        fn call_slot(&mut self, slot: u32) -> &mut Self {
+            self.rel(&[0xff, 0x15], slot as u64)
+        }
+
+        fn call_slot32(&mut self, slot: u32) -> &mut Self {
+            self.bytes(&[0xff, 0x15]).bytes(&(IMAGE_BASE_32 as u32 + slot).to_le_bytes())
+        }
+    }
+
+    fn section(name: &str, addr: u32, content: Vec<u8>, characteristics: u32) -> Section {
+        let size = content.len() as u32;
+        Section { name: name.to_string(), content, addr, characteristics, virtual_size: size, raw_size: size, raw_ptr: addr }
+    }
+
+    fn find_in(text: &Asm, dll: &str, bitness: usize, image_base: u64) -> Option<u64> {
+        let sections = [section(".text", 0x1000, text.0.clone(), 0x60000020), section(".rdata", 0x2000, vec![0; 0x100], 0x40000040)];
+        let import = |name: &str, iat_rva| Import { dll: dll.to_string(), name: Some(name.to_string()), ordinal: None, hint: 0, iat_rva, delay_thunk: None };
+        let imports = [import("exit", EXIT_SLOT), import("__p___argc", ARGC_SLOT)];
+        find_main_crt(&sections, &imports, bitness, image_base, 0x1000)
+    }
+
+    fn find(text: &Asm, dll: &str) -> Option<u64> {
+        find_in(text, dll, 64, IMAGE_BASE)
+    }
+
+    // mainCRTStartup -> __scrt_common_main_seh, with invoke_main inlined and exit called through the IAT
+    fn msvc(exit: bool) -> Asm {
+        let mut text = Asm(Vec::new());
+        text.bytes(&[0x48, 0x83, 0xec, 0x28]).call(0x1100).bytes(&[0x48, 0x83, 0xc4, 0x28]).rel(&[0xe9], 0x1200);
+        text.at(0x1100).bytes(&[0xc3]);
+        text.at(0x1200).bytes(&[0x48, 0x83, 0xec, 0x28]).call_slot(ARGC_SLOT).call(0x1100).call(0x1300).bytes(&[0x8b, 0xd8, 0x8b, 0xcb]);
+        if exit {
+            text.call_slot(EXIT_SLOT);
+        }
+        text.bytes(&[0xcc]);
+        text.at(0x1300).bytes(&[0x31, 0xc0, 0xc3]);
+        text
+    }
+
+    #[test]
+    fn msvc_startup() {
+        assert_eq!(find(&msvc(true), "api-ms-win-crt-runtime-l1-1-0.dll"), Some(0x1300));
+    }
+
+    #[test]
+    fn no_exit_no_main() {
+        assert_eq!(find(&msvc(false), "api-ms-win-crt-runtime-l1-1-0.dll"), None);
+    }
+
+    // the 32-bit __scrt_common_main_seh pops the three arguments of main before saving its result
+    #[test]
+    fn msvc_startup_32() {
+        let mut text = Asm(Vec::new());
+        text.call(0x1100).rel(&[0xe9], 0x1200);
+        text.at(0x1100).bytes(&[0xc3]);
+        text.at(0x1200).call_slot32(ARGC_SLOT).bytes(&[0x50, 0x50, 0x50]).call(0x1300)
+            .bytes(&[0x83, 0xc4, 0x0c, 0x8b, 0xf0, 0x56]).call_slot32(EXIT_SLOT).bytes(&[0xcc]);
+        text.at(0x1300).bytes(&[0x33, 0xc0, 0xc3]);
+        assert_eq!(find_in(&text, "api-ms-win-crt-runtime-l1-1-0.dll", 32, IMAGE_BASE_32), Some(0x1300));
+    }
+
+    // mainCRTStartup -> __tmainCRTStartup, main's result stored in a global and exit reached through a jmp stub
+    #[test]
+    fn mingw_startup() {
+        let mut text = Asm(Vec::new());
+        text.bytes(&[0x48, 0x83, 0xec, 0x28]).call(0x1100).bytes(&[0x90, 0x48, 0x83, 0xc4, 0x28, 0xc3]);
+        text.at(0x1100).bytes(&[0x48, 0x83, 0xec, 0x28]).call(0x1300).bytes(&[0x31, 0xc9]).call(0x1400)
+            .bytes(&[0x89, 0x05, 0x00, 0x10, 0x00, 0x00, 0x8b, 0xc8]).call(0x1500).bytes(&[0xcc]);
+        text.at(0x1300).bytes(&[0xc3]);
+        text.at(0x1400).bytes(&[0x31, 0xc0, 0xc3]);
+        text.at(0x1500).rel(&[0xff, 0x25], EXIT_SLOT as u64);
+        assert_eq!(find(&text, "msvcrt.dll"), Some(0x1400));
+    }
+}
@@ -0,0 +1,45 @@
+use std::{fmt, io};
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PeError {
+    Truncated { what: &'static str, offset: u64, size: u64 },
+    DosSignature(u16),
+    NtSignature(u32),
+    Machine(u16),
+    Magic { machine: u16, magic: u16 },
+    OptionalHeaderSize(u16),
+    Alignment { file: u32, section: u32 },
+    SectionCount(u16),
+    HeaderOverlap(String),
+    OverlappingSections(String, String),
+    SectionOutOfImage(String),
+    TooLarge { what: &'static str, size: u64 },
+}
+
+impl fmt::Display for PeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PeError::Truncated { what, offset, size } => write!(f, "the {what} ({size:#x} bytes at offset {offset:#x}) is past the end of the file"),
+            PeError::DosSignature(magic) => write!(f, "invalid dos signature {magic:#x}"),
+            PeError::NtSignature(signature) => write!(f, "invalid nt signature {signature:#x}"),
+            PeError::Machine(machine) => write!(f, "unsupported machine {machine:#x}, only x64 and x86 files are supported"),
+            PeError::Magic { machine, magic } => write!(f, "the optional header magic {magic:#x} does not match the machine {machine:#x}"),
+            PeError::OptionalHeaderSize(size) => write!(f, "the optional header size {size:#x} is smaller than its fixed fields"),
+            PeError::Alignment { file, section } => write!(f, "invalid alignment, file {file:#x} section {section:#x}"),
+            PeError::SectionCount(count) => write!(f, "the section table of {count} entries is past the end of the file"),
+            PeError::HeaderOverlap(name) => write!(f, "the raw data of the section '{name}' overlaps the headers"),
+            PeError::OverlappingSections(a, b) => write!(f, "the sections '{a}' and '{b}' overlap"),
+            PeError::SectionOutOfImage(name) => write!(f, "the section '{name}' is outside of SizeOfImage"),
+            PeError::TooLarge { what, size } => write!(f, "the {what} is too large ({size:#x} bytes)"),
+        }
+    }
+}
+
+impl std::error::Error for PeError {}
+
+impl From<PeError> for io::Error {
+    fn from(e: PeError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
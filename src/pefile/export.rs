@@ -1,77 +1,438 @@
-use std::{io, mem, ptr};
-use std::ffi::CStr;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::{mem, ptr};
 use anyhow::anyhow;
-use winapi::shared::minwindef::LPVOID;
-use winapi::um::memoryapi::ReadProcessMemory;
-use winapi::um::winnt::{HANDLE, IMAGE_DOS_HEADER, IMAGE_EXPORT_DIRECTORY, IMAGE_NT_HEADERS32, IMAGE_NT_HEADERS64};
+use crate::pefile::winnt::{IMAGE_DATA_DIRECTORY, IMAGE_EXPORT_DIRECTORY};
+use crate::dbg::target::{read_exact, DebugTarget, ImageTarget};
+use crate::pefile::{Section, MAX_ENTRIES};
 use crate::symbol::{SrcFile, SymbolFile};
 
-pub fn get_export_func_in_dll(h_proc: HANDLE, base: u64) -> Result<Vec<SymbolFile>, anyhow::Error> {
-    let mut result = Vec::new();
-    let mut addr = base;
-    unsafe {
-        let mut dos_header: IMAGE_DOS_HEADER = mem::zeroed();
-        if ReadProcessMemory(h_proc, addr as LPVOID, ptr::addr_of_mut!(dos_header) as LPVOID, size_of::<IMAGE_DOS_HEADER>(), &mut 0) == 0 {
-            return Err(anyhow!("failed to read dos headers : {}", io::Error::last_os_error()))
+const MAX_NAME_LEN: usize = 0x1000;
+const MAX_FORWARD_DEPTH: usize = 16;
+
+
+#[derive(Debug, Clone)]
+pub struct Export {
+    pub name: Option<String>,
+    pub ordinal: u32,
+    pub rva: u32,
+    pub forwarder: Option<String>,
+}
+
+impl Export {
+    pub fn label(&self, dll: &str) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("{}!#{}", dll_stem(dll), self.ordinal),
+        }
+    }
+
+    pub fn matches(&self, func: &str) -> bool {
+        match func.strip_prefix('#') {
+            Some(ordinal) => ordinal.parse::<u32>().ok() == Some(self.ordinal),
+            None => self.name.as_deref() == Some(func),
         }
-        addr += (dos_header.e_lfanew + 4) as u64;
+    }
 
-        let mut machine = 0u16;
-        if ReadProcessMemory(h_proc, addr as LPVOID, ptr::addr_of_mut!(machine) as LPVOID, 2, &mut 0) == 0 {
-            return Err(anyhow!("failed to read machine header : {}", io::Error::last_os_error()))
+    fn symbol(&self, dll: &str, base: u64, rva: u32) -> SymbolFile {
+        SymbolFile {
+            name: self.label(dll),
+            offset: rva as i64,
+            types_e: if self.forwarder.is_some() { "forwarder" } else { "export" }.to_string(),
+            value_str: self.forwarder.clone().unwrap_or_default(),
+            src_file: if base != 0 { SrcFile::Dll(base) } else { SrcFile::Ex },
+            ..Default::default()
         }
+    }
+}
+
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportTable {
+    pub dll: String,
+    pub exports: Vec<Export>,
+}
 
-        addr -= 4;
-        let export_dir;
-        match machine {
-            0x014c => {
-                let mut nt32: IMAGE_NT_HEADERS32 = mem::zeroed();
-                if ReadProcessMemory(h_proc, addr as LPVOID, ptr::addr_of_mut!(nt32) as LPVOID, mem::size_of::<IMAGE_NT_HEADERS32>(), &mut 0) == 0 {
-                    return Err(anyhow!("failed to read nt headers : {}", io::Error::last_os_error()))
-                };
-                export_dir = nt32.OptionalHeader.DataDirectory[0];
+impl ExportTable {
+    pub fn find(&self, func: &str) -> Option<&Export> {
+        self.exports.iter().find(|e| e.matches(func))
+    }
+
+    pub fn symbols(&self, base: u64) -> Vec<SymbolFile> {
+        self.exports.iter()
+            .filter(|e| e.forwarder.is_none())
+            .map(|e| e.symbol(&self.dll, base, e.rva))
+            .collect()
+    }
+}
+
+
+pub fn dll_stem(dll: &str) -> String {
+    let file = dll.rsplit(['\\', '/']).next().unwrap_or(dll).to_lowercase();
+    file.strip_suffix(".dll").map(str::to_string).unwrap_or(file)
+}
+
+
+// "NTDLL.RtlAllocateHeap" or "NTDLL.#12", the module name itself can contain dots
+pub fn split_forwarder(forwarder: &str) -> Option<(&str, &str)> {
+    forwarder.rsplit_once('.').filter(|(dll, func)| !dll.is_empty() && !func.is_empty())
+}
+
+
+fn read_u32s(target: &dyn DebugTarget, addr: u64, count: usize) -> Result<Vec<u32>, anyhow::Error> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    let mut buf = vec![0u8; count * 4];
+    read_exact(target, addr, &mut buf)?;
+    Ok(buf.chunks_exact(4).map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect())
+}
+
+fn read_u16s(target: &dyn DebugTarget, addr: u64, count: usize) -> Result<Vec<u16>, anyhow::Error> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    let mut buf = vec![0u8; count * 2];
+    read_exact(target, addr, &mut buf)?;
+    Ok(buf.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect())
+}
+
+fn read_cstr(target: &dyn DebugTarget, addr: u64) -> Result<String, anyhow::Error> {
+    let mut result = Vec::new();
+    let mut chunk = [0u8; 64];
+    while result.len() < MAX_NAME_LEN {
+        let cur = addr + result.len() as u64;
+        // a name can end right before an unreadable page or the end of a section
+        let len = match read_exact(target, cur, &mut chunk) {
+            Ok(()) => chunk.len(),
+            Err(_) => {
+                read_exact(target, cur, &mut chunk[..1])?;
+                1
             }
-            0x8664 => {
-                let mut nt64: IMAGE_NT_HEADERS64 = mem::zeroed();
-                if ReadProcessMemory(h_proc, addr as LPVOID, ptr::addr_of_mut!(nt64) as LPVOID, mem::size_of::<IMAGE_NT_HEADERS64>(), &mut 0) == 0 {
-                    return Err(anyhow!("failed to read nt headers : {}", io::Error::last_os_error()))
-                }
-                export_dir = nt64.OptionalHeader.DataDirectory[0];
+        };
+        match chunk[..len].iter().position(|&b| b == 0) {
+            Some(end) => {
+                result.extend_from_slice(&chunk[..end]);
+                return Ok(String::from_utf8_lossy(&result).to_string());
             }
-            _ => return Err(anyhow!("invalid machine header: {:#x}", machine))
+            None => result.extend_from_slice(&chunk[..len]),
         }
+    }
+    Err(anyhow!("the string at {:#x} is longer than {MAX_NAME_LEN} bytes", addr))
+}
+
+
+fn read_export_table(target: &dyn DebugTarget, base: u64, export_dir: IMAGE_DATA_DIRECTORY) -> Result<ExportTable, anyhow::Error> {
+    if export_dir.VirtualAddress == 0 || export_dir.Size == 0 {
+        return Ok(ExportTable::default());
+    }
+    let mut raw = [0u8; mem::size_of::<IMAGE_EXPORT_DIRECTORY>()];
+    read_exact(target, base + export_dir.VirtualAddress as u64, &mut raw)
+        .map_err(|e| anyhow!("failed to read the export directory at {:#x} : {e}", export_dir.VirtualAddress))?;
+    let img_export: IMAGE_EXPORT_DIRECTORY = unsafe { ptr::read_unaligned(raw.as_ptr() as *const IMAGE_EXPORT_DIRECTORY) };
 
-        let mut img_export: IMAGE_EXPORT_DIRECTORY = mem::zeroed();
-        addr = export_dir.VirtualAddress as u64 + base;
+    let (func_count, name_count) = (img_export.NumberOfFunctions as usize, img_export.NumberOfNames as usize);
+    if func_count > MAX_ENTRIES || name_count > MAX_ENTRIES {
+        return Err(anyhow!("the export directory has {func_count} functions and {name_count} names"));
+    }
+    let dll = match img_export.Name {
+        0 => String::new(),
+        rva => read_cstr(target, base + rva as u64).map_err(|e| anyhow!("failed to read the dll name : {e}"))?,
+    };
+    let functions = read_u32s(target, base + img_export.AddressOfFunctions as u64, func_count)
+        .map_err(|e| anyhow!("failed to read the function addresses : {e}"))?;
+    let name_rvas = read_u32s(target, base + img_export.AddressOfNames as u64, name_count)
+        .map_err(|e| anyhow!("failed to read the name addresses : {e}"))?;
+    let name_ordinals = read_u16s(target, base + img_export.AddressOfNameOrdinals as u64, name_count)
+        .map_err(|e| anyhow!("failed to read the name ordinals : {e}"))?;
 
-        if ReadProcessMemory(h_proc, addr as LPVOID, ptr::addr_of_mut!(img_export) as LPVOID, mem::size_of::<IMAGE_EXPORT_DIRECTORY>(), &mut 0) == 0 {
-            return Err(anyhow!("failed to read export directory : {}", io::Error::last_os_error()))
+    // the i-th name goes with AddressOfFunctions[AddressOfNameOrdinals[i]], not with AddressOfFunctions[i]
+    let mut names = vec![Vec::new(); func_count];
+    for (&name_rva, &index) in name_rvas.iter().zip(&name_ordinals) {
+        let name = read_cstr(target, base + name_rva as u64).map_err(|e| anyhow!("failed to read an export name : {e}"))?;
+        match names.get_mut(index as usize) {
+            Some(names) => names.push(name),
+            None => return Err(anyhow!("the export '{name}' has the index {index} but there are only {func_count} functions")),
         }
-        let mut addr_func = vec![0u32;img_export.NumberOfFunctions as usize];
-        let mut addr_name = vec![0u32;img_export.NumberOfFunctions as usize];
-        
-        addr = img_export.AddressOfFunctions as u64 + base;
-        if ReadProcessMemory(h_proc, addr as LPVOID, addr_func.as_mut_ptr() as LPVOID, addr_func.len() * 4, &mut 0) == 0 {
-            return Err(anyhow!("failed to read address function : {}", io::Error::last_os_error()))
+    }
+
+    let dir_range = export_dir.VirtualAddress as u64..export_dir.VirtualAddress as u64 + export_dir.Size as u64;
+    let mut exports = Vec::new();
+    for (index, (&rva, names)) in functions.iter().zip(names).enumerate() {
+        if rva == 0 {
+            continue;
         }
-        
-        addr = img_export.AddressOfNames as u64 + base;
-        if ReadProcessMemory(h_proc, addr as LPVOID, addr_name.as_mut_ptr() as LPVOID, addr_name.len() * 4, &mut 0) == 0 {
-            return Err(anyhow!("failed to read address name : {}", io::Error::last_os_error()))
+        let ordinal = img_export.Base.wrapping_add(index as u32);
+        let forwarder = if dir_range.contains(&(rva as u64)) {
+            Some(read_cstr(target, base + rva as u64).map_err(|e| anyhow!("failed to read the forwarder of ordinal {ordinal} : {e}"))?)
+        } else {
+            None
+        };
+        if names.is_empty() {
+            exports.push(Export { name: None, ordinal, rva, forwarder });
+        } else {
+            exports.extend(names.into_iter().map(|name| Export { name: Some(name), ordinal, rva, forwarder: forwarder.clone() }));
         }
-        
-        for i in 0..img_export.NumberOfNames as usize {
-            addr = addr_name[i] as u64 + base;
-            let mut name_buf = [0u8;260];
-            if ReadProcessMemory(h_proc, addr as LPVOID, name_buf.as_mut_ptr() as LPVOID, name_buf.len(), &mut 0) == 0 {
-                return Err(anyhow!("failed to read name : {}", io::Error::last_os_error()))
+    }
+    Ok(ExportTable { dll, exports })
+}
+
+
+pub fn parse_export_table(sections: &[Section], export_dir: IMAGE_DATA_DIRECTORY) -> Result<ExportTable, anyhow::Error> {
+    read_export_table(&ImageTarget(sections), 0, export_dir)
+}
+
+
+pub fn parse_exports(sections: &[Section], export_dir: IMAGE_DATA_DIRECTORY) -> Result<Vec<SymbolFile>, anyhow::Error> {
+    Ok(parse_export_table(sections, export_dir)?.symbols(0))
+}
+
+
+pub fn read_module_exports(target: &dyn DebugTarget, base: u64) -> Result<ExportTable, anyhow::Error> {
+    let mut dword = [0u8; 4];
+    read_exact(target, base + 0x3c, &mut dword).map_err(|e| anyhow!("failed to read the dos header : {e}"))?;
+    let nt = base + u32::from_le_bytes(dword) as u64;
+    read_exact(target, nt, &mut dword).map_err(|e| anyhow!("failed to read the nt headers : {e}"))?;
+    if dword != *b"PE\0\0" {
+        return Err(anyhow!("invalid nt signature at {:#x}", nt));
+    }
+    let optional = nt + 24;
+    let mut magic = [0u8; 2];
+    read_exact(target, optional, &mut magic)?;
+    let (count_offset, dir_offset) = match u16::from_le_bytes(magic) {
+        0x10b => (92, 96),
+        0x20b => (108, 112),
+        magic => return Err(anyhow!("invalid optional header magic {:#x}", magic)),
+    };
+    read_exact(target, optional + count_offset, &mut dword)?;
+    if u32::from_le_bytes(dword) == 0 {
+        return Ok(ExportTable::default());
+    }
+    let dir = read_u32s(target, optional + dir_offset, 2)?;
+    read_export_table(target, base, IMAGE_DATA_DIRECTORY { VirtualAddress: dir[0], Size: dir[1] })
+}
+
+
+#[derive(Debug, Clone)]
+pub struct ResolvedExport {
+    pub dll: String,
+    pub base: u64,
+    pub export: Export,
+    pub chain: Vec<String>,
+}
+
+
+// `open` gives the base and the export table of a module, forwarders are followed until the code
+pub fn resolve_export<F>(mut open: F, dll: &str, func: &str) -> Result<ResolvedExport, anyhow::Error>
+where
+    F: FnMut(&str) -> Result<(u64, Rc<ExportTable>), anyhow::Error>,
+{
+    let (mut dll, mut func) = (dll.to_string(), func.to_string());
+    let mut chain = Vec::new();
+    while chain.len() <= MAX_FORWARD_DEPTH {
+        let (base, table) = open(&dll)?;
+        let export = table.find(&func).ok_or_else(|| anyhow!("{} does not export {func}", dll_stem(&dll)))?;
+        let Some(forwarder) = export.forwarder.clone() else {
+            return Ok(ResolvedExport { dll, base, export: export.clone(), chain });
+        };
+        let (next_dll, next_func) = split_forwarder(&forwarder).ok_or_else(|| anyhow!("invalid forwarder '{forwarder}'"))?;
+        (dll, func) = (next_dll.to_string(), next_func.to_string());
+        chain.push(forwarder);
+    }
+    Err(anyhow!("more than {MAX_FORWARD_DEPTH} forwarders from {}", chain[0]))
+}
+
+
+struct Pending {
+    from: u64,
+    dll: String,
+    export: Export,
+}
+
+#[derive(Default)]
+pub struct ModuleExports {
+    modules: Vec<(u64, Rc<ExportTable>)>,
+    pending: Vec<Pending>,
+}
+
+impl ModuleExports {
+    // symbols of the new module and of the forwarders of the other modules that now point to code
+    pub fn add(&mut self, base: u64, table: ExportTable) -> Vec<SymbolFile> {
+        self.remove(base);
+        let mut result = table.symbols(base);
+        self.pending.extend(table.exports.iter()
+            .filter(|e| e.forwarder.is_some())
+            .map(|e| Pending { from: base, dll: table.dll.clone(), export: e.clone() }));
+        self.modules.push((base, Rc::new(table)));
+
+        let mut still_pending = Vec::new();
+        for pending in std::mem::take(&mut self.pending) {
+            match self.resolve(pending.export.forwarder.as_deref().unwrap_or_default()) {
+                Some(found) => result.push(pending.export.symbol(&pending.dll, found.base, found.export.rva)),
+                None => still_pending.push(pending),
             }
-            let mut sym = SymbolFile::default();
-            sym.name = CStr::from_ptr(name_buf.as_ptr() as *const i8).to_string_lossy().to_string();
-            sym.offset = addr_func[i] as i64;
-            sym.src_file = SrcFile::Dll(base);
-            result.push(sym);
         }
+        self.pending = still_pending;
+        result
+    }
+
+    pub fn remove(&mut self, base: u64) {
+        self.modules.retain(|(b, _)| *b != base);
+        self.pending.retain(|p| p.from != base);
+    }
+
+    fn resolve(&self, forwarder: &str) -> Option<ResolvedExport> {
+        let (dll, func) = split_forwarder(forwarder)?;
+        let open = |dll: &str| self.modules.iter()
+            .find(|(_, t)| dll_stem(&t.dll) == dll_stem(dll))
+            .map(|(base, table)| (*base, Rc::clone(table)))
+            .ok_or_else(|| anyhow!("{dll} is not loaded"));
+        resolve_export(open, dll, func).ok()
+    }
+}
+
+
+fn system_dir(bitness: usize) -> PathBuf {
+    let root = PathBuf::from(std::env::var_os("SystemRoot").unwrap_or_else(|| "C:\\Windows".into()));
+    let wow64 = root.join("SysWOW64");
+    if bitness == 32 && wow64.is_dir() {
+        wow64
+    } else {
+        root.join("System32")
+    }
+}
+
+
+// same order as the loader without the PATH: application directory, system directory, windows directory, current directory
+pub fn find_dll(dll: &str, app_dir: Option<&Path>, bitness: usize) -> Option<PathBuf> {
+    let file = if Path::new(dll).extension().is_some() { dll.to_string() } else { format!("{dll}.dll") };
+    let path = Path::new(&file);
+    if path.components().count() > 1 {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+    let system = system_dir(bitness);
+    let windows = system.parent().map(Path::to_path_buf);
+    app_dir.map(Path::to_path_buf).into_iter()
+        .chain([Some(system), windows, std::env::current_dir().ok()].into_iter().flatten())
+        .map(|dir| dir.join(path))
+        .find(|p| p.is_file())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbg::target::{MemTarget, PROT_READONLY};
+
+    const BASE: u64 = 0x180000000;
+
+    fn put(image: &mut [u8], off: usize, bytes: &[u8]) {
+        image[off..off + bytes.len()].copy_from_slice(bytes);
+    }
+
+    fn dwords(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    // names are sorted, so the i-th name does not go with the i-th function
+    fn module() -> MemTarget {
+        let mut image = vec![0u8; 0x1100];
+        put(&mut image, 0x3c, &0x40u32.to_le_bytes());
+        put(&mut image, 0x40, b"PE\0\0");
+        put(&mut image, 0x58, &0x20bu16.to_le_bytes());
+        put(&mut image, 0x58 + 108, &16u32.to_le_bytes());
+        put(&mut image, 0x58 + 112, &dwords(&[0x1000, 0x100]));
+        // Name, Base, NumberOfFunctions, NumberOfNames, AddressOfFunctions, AddressOfNames, AddressOfNameOrdinals
+        put(&mut image, 0x100c, &dwords(&[0x1080, 10, 4, 2, 0x1028, 0x1038, 0x1040]));
+        put(&mut image, 0x1028, &dwords(&[0x2000, 0x1090, 0x2100, 0]));
+        put(&mut image, 0x1038, &dwords(&[0x10b8, 0x10b0]));
+        put(&mut image, 0x1040, &[2, 0, 0, 0]);
+        put(&mut image, 0x1080, b"lisa.dll\0");
+        put(&mut image, 0x1090, b"NTDLL.#12\0");
+        put(&mut image, 0x10b0, b"Zeta\0");
+        put(&mut image, 0x10b8, b"Alpha\0");
+        let target = MemTarget::new();
+        target.map(BASE, image, PROT_READONLY);
+        target
+    }
+
+    fn export(name: Option<&str>, ordinal: u32, rva: u32, forwarder: Option<&str>) -> Export {
+        Export { name: name.map(str::to_string), ordinal, rva, forwarder: forwarder.map(str::to_string) }
+    }
+
+    fn table(dll: &str, exports: Vec<Export>) -> ExportTable {
+        ExportTable { dll: dll.to_string(), exports }
+    }
+
+    fn opener(tables: Vec<(u64, ExportTable)>) -> impl FnMut(&str) -> Result<(u64, Rc<ExportTable>), anyhow::Error> {
+        let tables: Vec<(u64, Rc<ExportTable>)> = tables.into_iter().map(|(b, t)| (b, Rc::new(t))).collect();
+        move |dll: &str| tables.iter()
+            .find(|(_, t)| dll_stem(&t.dll) == dll_stem(dll))
+            .map(|(b, t)| (*b, t.clone()))
+            .ok_or_else(|| anyhow!("{dll} is not loaded"))
+    }
+
+    #[test]
+    fn names_follow_their_ordinals() {
+        let table = read_module_exports(&module(), BASE).unwrap();
+        assert_eq!(table.dll, "lisa.dll");
+        let exports: Vec<(String, u32, u32)> = table.exports.iter().map(|e| (e.label(&table.dll), e.ordinal, e.rva)).collect();
+        assert_eq!(exports, [("Zeta".to_string(), 10, 0x2000), ("lisa!#11".to_string(), 11, 0x1090), ("Alpha".to_string(), 12, 0x2100)]);
+        assert_eq!(table.exports[1].forwarder.as_deref(), Some("NTDLL.#12"));
+        assert_eq!(table.find("Alpha").unwrap().rva, 0x2100);
+        assert_eq!(table.find("#10").unwrap().name.as_deref(), Some("Zeta"));
+        assert!(table.find("#13").is_none());
+        let symbols: Vec<(String, i64)> = table.symbols(BASE).into_iter().map(|s| (s.name, s.offset)).collect();
+        assert_eq!(symbols, [("Zeta".to_string(), 0x2000), ("Alpha".to_string(), 0x2100)]);
+    }
+
+    #[test]
+    fn forwarder_chains() {
+        let kernel32 = table("KERNEL32.dll", vec![
+            export(Some("HeapAlloc"), 1, 0x90, Some("KERNELBASE.HeapAlloc")),
+            export(Some("ByOrdinal"), 2, 0x98, Some("NTDLL.#12")),
+            export(Some("Loop"), 3, 0xa0, Some("KERNEL32.Loop")),
+            export(Some("Missing"), 4, 0xa8, Some("NTDLL.Missing")),
+            export(Some("Broken"), 5, 0xb0, Some("NTDLL")),
+        ]);
+        let kernelbase = table("KERNELBASE.dll", vec![export(Some("HeapAlloc"), 7, 0x40, Some("NTDLL.RtlAllocateHeap"))]);
+        let ntdll = table("ntdll.dll", vec![export(Some("RtlAllocateHeap"), 12, 0x500, None)]);
+        let mut open = opener(vec![(0x1000, kernel32), (0x5000, kernelbase), (0x9000, ntdll)]);
+
+        let found = resolve_export(&mut open, "kernel32.dll", "HeapAlloc").unwrap();
+        assert_eq!((found.base, found.export.rva, found.dll.as_str()), (0x9000, 0x500, "NTDLL"));
+        assert_eq!(found.chain, ["KERNELBASE.HeapAlloc", "NTDLL.RtlAllocateHeap"]);
+        let found = resolve_export(&mut open, "KERNEL32", "ByOrdinal").unwrap();
+        assert_eq!((found.base, found.export.ordinal), (0x9000, 12));
+        assert!(resolve_export(&mut open, "KERNEL32", "#5").is_err());
+        assert!(resolve_export(&mut open, "KERNEL32", "Missing").is_err());
+        assert!(resolve_export(&mut open, "user32", "MessageBoxA").is_err());
+        let err = resolve_export(&mut open, "KERNEL32", "Loop").unwrap_err().to_string();
+        assert!(err.contains(&format!("more than {MAX_FORWARD_DEPTH} forwarders")), "{err}");
+    }
+
+    #[test]
+    fn pending_forwarders_resolve_when_their_module_loads() {
+        let mut modules = ModuleExports::default();
+        let kernel32 = table("KERNEL32.dll", vec![
+            export(Some("CloseHandle"), 1, 0x80, None),
+            export(Some("HeapAlloc"), 2, 0x90, Some("NTDLL.RtlAllocateHeap")),
+            export(None, 3, 0x98, Some("NTDLL.#12")),
+        ]);
+        let names: Vec<String> = modules.add(0x1000, kernel32.clone()).into_iter().map(|s| s.name).collect();
+        assert_eq!(names, ["CloseHandle"]);
+
+        let ntdll = table("ntdll.dll", vec![export(Some("RtlAllocateHeap"), 12, 0x500, None)]);
+        let symbols: Vec<(String, i64, SrcFile, String)> = modules.add(0x9000, ntdll.clone()).into_iter().map(|s| (s.name, s.offset, s.src_file, s.types_e)).collect();
+        assert_eq!(symbols, [
+            ("RtlAllocateHeap".to_string(), 0x500, SrcFile::Dll(0x9000), "export".to_string()),
+            ("HeapAlloc".to_string(), 0x500, SrcFile::Dll(0x9000), "forwarder".to_string()),
+            ("kernel32!#3".to_string(), 0x500, SrcFile::Dll(0x9000), "forwarder".to_string()),
+        ]);
+
+        modules.remove(0x1000);
+        assert_eq!(modules.add(0x9000, ntdll.clone()).len(), 1);
+        assert_eq!(modules.add(0x1000, kernel32).len(), 3);
+        assert_eq!(modules.add(0x9000, ntdll).len(), 1);
     }
-    Ok(result)
 }
@@ -1,31 +1,184 @@
-use crate::pefile;
-use std::{ptr, slice};
-use winapi::um::winnt::{IMAGE_DATA_DIRECTORY, RUNTIME_FUNCTION};
+use anyhow::anyhow;
+use crate::pefile::{read_rva, Section};
+use crate::pefile::winnt::{IMAGE_DATA_DIRECTORY, RUNTIME_FUNCTION};
 use crate::ut::fmt::{print_lg, LevelPrint};
 
-pub static mut FUNC_INFO: Vec<RUNTIME_FUNCTION> = Vec::new();
 
 
+pub fn parse_pdata(sections: &[Section], pdata_dir: IMAGE_DATA_DIRECTORY) -> Vec<RUNTIME_FUNCTION> {
+    match read_pdata(sections, pdata_dir) {
+        Some(runt_func) => runt_func,
+        None => {
+            print_lg(LevelPrint::WarningO, "no section is IMAGE_DIRECTORY_ENTRY_EXCEPTION");
+            Vec::new()
+        }
+    }
+}
 
-pub fn parse_pdata(pdata_dir: IMAGE_DATA_DIRECTORY) {
+
+pub fn read_pdata(sections: &[Section], pdata_dir: IMAGE_DATA_DIRECTORY) -> Option<Vec<RUNTIME_FUNCTION>> {
     if pdata_dir.VirtualAddress == 0 || pdata_dir.Size == 0 {
-        print_lg(LevelPrint::WarningO, "no section is IMAGE_DIRECTORY_ENTRY_EXCEPTION");
-        return;
+        return None;
     }
-    let rva_pdata = pdata_dir.VirtualAddress;
-    for section in unsafe { &*pefile::section::SECTION_VS } {
-        if section.addr <= rva_pdata && section.addr + section.content.len() as u32 >= rva_pdata + pdata_dir.Size {
-            let runt_size = section.content.len() / size_of::<RUNTIME_FUNCTION>();
-            let base_pdata = section.content.as_ptr() as *const RUNTIME_FUNCTION;
-            let mut runt_func = unsafe { slice::from_raw_parts(base_pdata, runt_size) }.to_vec();
-            runt_func.retain(|f| f.BeginAddress != 0);
-            unsafe {
-                let pfunc_info = ptr::addr_of_mut!(FUNC_INFO);
-                (*pfunc_info).clear();
-                (*pfunc_info).extend_from_slice(&runt_func);
-            }
-            return;
+    let size = pdata_dir.Size as usize - pdata_dir.Size as usize % RUNTIME_FUNCTION_SIZE;
+    let pdata = read_rva(sections, pdata_dir.VirtualAddress as u64, size)?;
+    let mut runt_func = pdata.chunks_exact(RUNTIME_FUNCTION_SIZE).map(runtime_function).collect::<Vec<_>>();
+    runt_func.retain(|f| f.BeginAddress != 0);
+    runt_func.sort_by_key(|f| f.BeginAddress);
+    Some(runt_func)
+}
+
+
+pub fn runtime_function(bytes: &[u8]) -> RUNTIME_FUNCTION {
+    let dword = |i: usize| u32::from_le_bytes([bytes[i * 4], bytes[i * 4 + 1], bytes[i * 4 + 2], bytes[i * 4 + 3]]);
+    RUNTIME_FUNCTION { BeginAddress: dword(0), EndAddress: dword(1), UnwindData: dword(2) }
+}
+
+
+pub const RUNTIME_FUNCTION_SIZE: usize = 12;
+pub const UNW_FLAG_EHANDLER: u8 = 0x1;
+pub const UNW_FLAG_UHANDLER: u8 = 0x2;
+pub const UNW_FLAG_CHAININFO: u8 = 0x4;
+
+const REG_NAMES: [&str; 16] = ["rax", "rcx", "rdx", "rbx", "rsp", "rbp", "rsi", "rdi", "r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15"];
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnwindOp {
+    PushNonVol(u8),
+    AllocLarge(u32),
+    AllocSmall(u32),
+    SetFpReg,
+    SaveNonVol(u8, u32),
+    SaveXmm128(u8, u32),
+    PushMachFrame(bool),
+    Epilog,
+    Unknown(u8),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct UnwindCode {
+    pub prolog_offset: u8,
+    pub op: UnwindOp,
+}
+
+impl UnwindCode {
+    pub fn describe(&self, frame_register: u8, frame_offset: u8) -> String {
+        match self.op {
+            UnwindOp::PushNonVol(reg) => format!("push {}", reg_name(reg)),
+            UnwindOp::AllocLarge(size) | UnwindOp::AllocSmall(size) => format!("sub rsp, {:#x}", size),
+            UnwindOp::SetFpReg => format!("lea {}, [rsp+{:#x}]", reg_name(frame_register), frame_offset as u32 * 16),
+            UnwindOp::SaveNonVol(reg, off) => format!("mov [frame+{:#x}], {}", off, reg_name(reg)),
+            UnwindOp::SaveXmm128(reg, off) => format!("movaps [frame+{:#x}], xmm{}", off, reg),
+            UnwindOp::PushMachFrame(code) => format!("push machine frame{}", if code { " with error code" } else { "" }),
+            UnwindOp::Epilog => "epilog".to_string(),
+            UnwindOp::Unknown(op) => format!("unknown op {op}"),
         }
     }
-    print_lg(LevelPrint::WarningO, "no section is IMAGE_DIRECTORY_ENTRY_EXCEPTION");
+}
+
+
+#[derive(Clone)]
+pub struct UnwindInfo {
+    pub version: u8,
+    pub flags: u8,
+    pub prolog_size: u8,
+    pub frame_register: u8,
+    pub frame_offset: u8,
+    pub codes: Vec<UnwindCode>,
+    pub handler: Option<u32>,
+    pub chained: Option<RUNTIME_FUNCTION>,
+}
+
+impl UnwindInfo {
+    pub fn size(header: &[u8; 4]) -> usize {
+        let count = header[2] as usize;
+        4 + count.div_ceil(2) * 4 + if header[0] >> 3 & UNW_FLAG_CHAININFO != 0 { RUNTIME_FUNCTION_SIZE } else { 4 }
+    }
+
+    pub fn parse(data: &[u8]) -> Result<UnwindInfo, anyhow::Error> {
+        if data.len() < 4 {
+            return Err(anyhow!("truncated unwind info"));
+        }
+        let version = data[0] & 0x7;
+        if version != 1 && version != 2 {
+            return Err(anyhow!("unsupported unwind info version {version}"));
+        }
+        let flags = data[0] >> 3;
+        let count = data[2] as usize;
+        let slots = data.get(4..4 + count * 2).ok_or_else(|| anyhow!("truncated unwind codes"))?
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect::<Vec<_>>();
+        let slot = |i: usize| slots.get(i).copied().ok_or_else(|| anyhow!("truncated unwind code at slot {i}"));
+
+        let mut codes = Vec::new();
+        let mut i = 0;
+        while i < count {
+            let (prolog_offset, op, info) = (data[4 + i * 2], data[5 + i * 2] & 0xf, data[5 + i * 2] >> 4);
+            let (op, used) = match op {
+                0 => (UnwindOp::PushNonVol(info), 1),
+                1 if info == 0 => (UnwindOp::AllocLarge(slot(i + 1)? as u32 * 8), 2),
+                1 => (UnwindOp::AllocLarge(slot(i + 1)? as u32 | (slot(i + 2)? as u32) << 16), 3),
+                2 => (UnwindOp::AllocSmall(info as u32 * 8 + 8), 1),
+                3 => (UnwindOp::SetFpReg, 1),
+                4 => (UnwindOp::SaveNonVol(info, slot(i + 1)? as u32 * 8), 2),
+                5 => (UnwindOp::SaveNonVol(info, slot(i + 1)? as u32 | (slot(i + 2)? as u32) << 16), 3),
+                6 if version == 2 => (UnwindOp::Epilog, 1),
+                6 => (UnwindOp::Unknown(op), 2),
+                7 => (UnwindOp::Unknown(op), 3),
+                8 => (UnwindOp::SaveXmm128(info, slot(i + 1)? as u32 * 16), 2),
+                9 => (UnwindOp::SaveXmm128(info, slot(i + 1)? as u32 | (slot(i + 2)? as u32) << 16), 3),
+                10 => (UnwindOp::PushMachFrame(info != 0), 1),
+                _ => return Err(anyhow!("invalid unwind op {op} at slot {i}")),
+            };
+            codes.push(UnwindCode { prolog_offset, op });
+            i += used;
+        }
+
+        let trailer = 4 + count.div_ceil(2) * 4;
+        let mut unwind = UnwindInfo {
+            version,
+            flags,
+            prolog_size: data[1],
+            frame_register: data[3] & 0xf,
+            frame_offset: data[3] >> 4,
+            codes,
+            handler: None,
+            chained: None,
+        };
+        if flags & UNW_FLAG_CHAININFO != 0 {
+            let chain = data.get(trailer..trailer + RUNTIME_FUNCTION_SIZE).ok_or_else(|| anyhow!("truncated chained function entry"))?;
+            unwind.chained = Some(runtime_function(chain));
+        } else if flags & (UNW_FLAG_EHANDLER | UNW_FLAG_UHANDLER) != 0 {
+            let handler = data.get(trailer..trailer + 4).ok_or_else(|| anyhow!("truncated exception handler"))?;
+            unwind.handler = Some(u32::from_le_bytes(handler.try_into()?));
+        }
+        Ok(unwind)
+    }
+}
+
+
+pub fn reg_name(reg: u8) -> &'static str {
+    REG_NAMES.get(reg as usize).copied().unwrap_or("?")
+}
+
+
+pub fn unwind_data(func: &RUNTIME_FUNCTION) -> u32 {
+    func.UnwindData
+}
+
+
+pub fn find_function(functions: &[RUNTIME_FUNCTION], rva: u64) -> Option<&RUNTIME_FUNCTION> {
+    let i = functions.partition_point(|f| (f.BeginAddress as u64) <= rva).checked_sub(1)?;
+    let func = &functions[i];
+    (rva < func.EndAddress as u64).then_some(func)
+}
+
+
+pub fn read_unwind_info(sections: &[Section], func: &RUNTIME_FUNCTION) -> Result<UnwindInfo, anyhow::Error> {
+    let rva = unwind_data(func) as u64;
+    let header: [u8; 4] = read_rva(sections, rva, 4).ok_or_else(|| anyhow!("unwind info {:#x} is outside of the sections", rva))?.try_into()?;
+    let data = read_rva(sections, rva, UnwindInfo::size(&header)).ok_or_else(|| anyhow!("unwind info {:#x} is truncated", rva))?;
+    UnwindInfo::parse(data)
 }
@@ -0,0 +1,62 @@
+use std::rc::Rc;
+use crate::pefile::{self, debug, export, function, import, loadconfig, reloc, resource, rich, section, tls};
+use crate::symbol::{coff, dwarf};
+
+
+// every parser reachable from an untrusted file, the errors are ignored, only panics and hangs matter
+pub fn parse_all(data: &[u8]) {
+    let _ = rich::parse_rich(data);
+    let Ok((nt_header, sections)) = pefile::parse_pe(data) else {
+        return;
+    };
+    let bitness = nt_header.get_bitness();
+    let image_base = nt_header.image_base();
+    let _ = section::check_sections(&sections, nt_header.entry_point());
+    if let Ok(table) = export::parse_export_table(&sections, nt_header.data_directory(0)) {
+        // every module name resolves to this image, so forwarders can loop on themselves
+        let table = Rc::new(table);
+        for exp in table.exports.iter().filter(|e| e.forwarder.is_some()) {
+            let func = exp.name.clone().unwrap_or_else(|| format!("#{}", exp.ordinal));
+            let _ = export::resolve_export(|_| Ok((image_base, Rc::clone(&table))), &table.dll, &func);
+        }
+    }
+    let _ = import::parse_imports(&sections, nt_header.data_directory(1), bitness);
+    let _ = import::parse_delay_imports(&sections, nt_header.data_directory(13), bitness, image_base);
+    let _ = reloc::parse_relocs(&sections, nt_header.data_directory(5));
+    let _ = tls::parse_tls(&sections, nt_header.data_directory(9), bitness, image_base);
+    let _ = loadconfig::parse_load_config(&sections, nt_header.data_directory(10), bitness, image_base);
+    let _ = debug::parse_debug_directory(&sections, nt_header.data_directory(6));
+    let _ = dwarf::parse_dwarf(&sections, image_base);
+    let _ = coff::parse_coff(data);
+    if let Ok(resources) = resource::parse_resources(&sections, nt_header.data_directory(2)) {
+        for res in resources.iter().filter(|r| r.is(resource::RT_VERSION)) {
+            if let Some(data) = res.data(&sections) {
+                let _ = resource::decode_version_info(data);
+            }
+        }
+    }
+    for func in function::read_pdata(&sections, nt_header.data_directory(3)).unwrap_or_default() {
+        let _ = function::read_unwind_info(&sections, &func);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn corpus() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fuzz/corpus/pe_parse");
+        let mut count = 0;
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let data = fs::read(&path).unwrap();
+            let res = std::panic::catch_unwind(|| parse_all(&data));
+            assert!(res.is_ok(), "{} panicked", path.display());
+            count += 1;
+        }
+        assert!(count > 0);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::pefile::{get_section_of_rva, NtHeaders, Section};
+
+pub const DIRECTORY_NAMES: [&str; 16] = [
+    "export", "import", "resource", "exception", "security", "base relocation", "debug", "architecture",
+    "global ptr", "tls", "load config", "bound import", "iat", "delay import", "clr runtime", "reserved",
+];
+
+const FILE_CHARACTERISTICS: [(u32, &str); 15] = [
+    (0x1, "RELOCS_STRIPPED"),
+    (0x2, "EXECUTABLE_IMAGE"),
+    (0x4, "LINE_NUMS_STRIPPED"),
+    (0x8, "LOCAL_SYMS_STRIPPED"),
+    (0x10, "AGGRESSIVE_WS_TRIM"),
+    (0x20, "LARGE_ADDRESS_AWARE"),
+    (0x80, "BYTES_REVERSED_LO"),
+    (0x100, "32BIT_MACHINE"),
+    (0x200, "DEBUG_STRIPPED"),
+    (0x400, "REMOVABLE_RUN_FROM_SWAP"),
+    (0x800, "NET_RUN_FROM_SWAP"),
+    (0x1000, "SYSTEM"),
+    (0x2000, "DLL"),
+    (0x4000, "UP_SYSTEM_ONLY"),
+    (0x8000, "BYTES_REVERSED_HI"),
+];
+
+const DLL_CHARACTERISTICS: [(u32, &str); 11] = [
+    (0x20, "HIGH_ENTROPY_VA"),
+    (0x40, "DYNAMIC_BASE (ASLR)"),
+    (0x80, "FORCE_INTEGRITY"),
+    (0x100, "NX_COMPAT (DEP)"),
+    (0x200, "NO_ISOLATION"),
+    (0x400, "NO_SEH"),
+    (0x800, "NO_BIND"),
+    (0x1000, "APPCONTAINER"),
+    (0x2000, "WDM_DRIVER"),
+    (0x4000, "GUARD_CF (CFG)"),
+    (0x8000, "TERMINAL_SERVER_AWARE"),
+];
+
+// the alignment bits and the memory permissions are shown on their own
+const SECTION_CHARACTERISTICS: [(u32, &str); 12] = [
+    (0x20, "CNT_CODE"),
+    (0x40, "CNT_INITIALIZED_DATA"),
+    (0x80, "CNT_UNINITIALIZED_DATA"),
+    (0x200, "LNK_INFO"),
+    (0x800, "LNK_REMOVE"),
+    (0x1000, "LNK_COMDAT"),
+    (0x8000, "GPREL"),
+    (0x1000000, "LNK_NRELOC_OVFL"),
+    (0x2000000, "MEM_DISCARDABLE"),
+    (0x4000000, "MEM_NOT_CACHED"),
+    (0x8000000, "MEM_NOT_PAGED"),
+    (0x10000000, "MEM_SHARED"),
+];
+const SECTION_ALIGN_AND_PERMISSIONS: u32 = 0xe0f00000;
+
+
+fn flags_str(flags: u32, table: &[(u32, &str)]) -> String {
+    let mut names = table.iter().filter(|(f, _)| flags & f != 0).map(|(_, n)| n.to_string()).collect::<Vec<_>>();
+    let unknown = table.iter().fold(flags, |rest, (f, _)| rest & !f);
+    if unknown != 0 {
+        names.push(format!("{:#x}", unknown));
+    }
+    names.join(" ")
+}
+
+
+pub fn characteristics_str(characteristics: u16) -> String {
+    flags_str(characteristics as u32, &FILE_CHARACTERISTICS)
+}
+
+
+pub fn dll_characteristics_str(characteristics: u16) -> String {
+    flags_str(characteristics as u32, &DLL_CHARACTERISTICS)
+}
+
+
+pub fn section_characteristics_str(characteristics: u32) -> String {
+    flags_str(characteristics & !SECTION_ALIGN_AND_PERMISSIONS, &SECTION_CHARACTERISTICS)
+}
+
+
+pub fn machine_str(machine: u16) -> &'static str {
+    match machine {
+        0x14c => "i386",
+        0x8664 => "amd64",
+        0x1c0 => "arm",
+        0x1c4 => "armnt",
+        0xaa64 => "arm64",
+        0xa641 => "arm64ec",
+        0x200 => "ia64",
+        _ => "unknown",
+    }
+}
+
+
+pub fn subsystem_str(subsystem: u16) -> &'static str {
+    match subsystem {
+        1 => "native",
+        2 => "windows gui",
+        3 => "windows console",
+        5 => "os/2 console",
+        7 => "posix console",
+        9 => "windows ce gui",
+        10 => "efi application",
+        11 => "efi boot service driver",
+        12 => "efi runtime driver",
+        13 => "efi rom",
+        14 => "xbox",
+        16 => "windows boot application",
+        _ => "unknown",
+    }
+}
+
+
+// the security directory is the only one that holds a file offset instead of an rva
+pub fn directory_section(sections: &[Section], nt_header: NtHeaders, index: usize) -> Option<&str> {
+    let dir = nt_header.data_directory(index);
+    if index == 4 || dir.VirtualAddress == 0 {
+        return None;
+    }
+    get_section_of_rva(sections, dir.VirtualAddress as u64).map(|s| s.name.as_str())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_str_names_known_bits() {
+        assert_eq!(characteristics_str(0x2022), "EXECUTABLE_IMAGE LARGE_ADDRESS_AWARE DLL");
+        assert_eq!(dll_characteristics_str(0x8160), "HIGH_ENTROPY_VA DYNAMIC_BASE (ASLR) NX_COMPAT (DEP) TERMINAL_SERVER_AWARE");
+        assert_eq!(characteristics_str(0), "");
+    }
+
+    #[test]
+    fn flags_str_keeps_unknown_bits() {
+        assert_eq!(characteristics_str(0x42), "EXECUTABLE_IMAGE 0x40");
+        assert_eq!(dll_characteristics_str(0x5), "0x5");
+        // alignment and permissions are not flags here
+        assert_eq!(section_characteristics_str(0xe0500020), "CNT_CODE");
+        assert_eq!(section_characteristics_str(0x42000040), "CNT_INITIALIZED_DATA MEM_DISCARDABLE");
+    }
+}
@@ -0,0 +1,270 @@
+use anyhow::anyhow;
+use iced_x86::{Decoder, DecoderOptions, FlowControl, Instruction, OpKind, Register};
+use crate::pefile::winnt::IMAGE_DATA_DIRECTORY;
+use crate::pefile::{read_rva, read_rva_str, Section, MAX_ENTRIES};
+
+const IMAGE_SCN_MEM_EXECUTE: u32 = 0x20000000;
+const DLATTR_RVA: u32 = 1;
+
+
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub dll: String,
+    pub name: Option<String>,
+    pub ordinal: Option<u16>,
+    pub hint: u16,
+    pub iat_rva: u32,
+    pub delay_thunk: Option<u32>,
+}
+
+
+impl Import {
+    pub fn func_name(&self) -> String {
+        match (&self.name, self.ordinal) {
+            (Some(name), _) => name.clone(),
+            (None, Some(ordinal)) => format!("#{ordinal}"),
+            (None, None) => "?".to_string(),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        format!("{}!{}", self.dll.to_lowercase().trim_end_matches(".dll"), self.func_name())
+    }
+
+    pub fn thunk_name(&self) -> String {
+        format!("__imp_load_{}", self.func_name())
+    }
+
+    pub fn is_delay(&self) -> bool {
+        self.delay_thunk.is_some()
+    }
+
+    pub fn is_from(&self, dll: &str) -> bool {
+        self.dll.to_lowercase().trim_end_matches(".dll") == dll.to_lowercase().trim_end_matches(".dll")
+    }
+
+    pub fn matches(&self, target: &str) -> bool {
+        match target.split_once('!') {
+            Some((dll, func)) => self.is_from(dll) && self.func_matches(func),
+            None => self.func_matches(target),
+        }
+    }
+
+    fn func_matches(&self, func: &str) -> bool {
+        match func.strip_prefix('#') {
+            Some(ordinal) => ordinal.parse::<u16>().ok() == self.ordinal && self.ordinal.is_some(),
+            None => self.name.as_deref() == Some(func),
+        }
+    }
+}
+
+
+fn read_u32(sections: &[Section], rva: u64) -> Result<u32, anyhow::Error> {
+    read_rva(sections, rva, 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).ok_or_else(|| anyhow!("rva {:#x} is outside of the sections", rva))
+}
+
+
+fn read_thunk(sections: &[Section], rva: u64, bitness: usize) -> Result<u64, anyhow::Error> {
+    if bitness == 64 {
+        read_rva(sections, rva, 8).map(|b| u64::from_le_bytes(b.try_into().unwrap())).ok_or_else(|| anyhow!("thunk {:#x} is outside of the sections", rva))
+    } else {
+        read_u32(sections, rva).map(|v| v as u64)
+    }
+}
+
+
+fn read_thunks(sections: &[Section], dll: &str, lookup: u64, iat: u64, bitness: usize, va_base: u64) -> Result<Vec<Import>, anyhow::Error> {
+    let thunk_size = bitness as u64 / 8;
+    let ordinal_flag = 1u64 << (bitness - 1);
+
+    let mut result = Vec::new();
+    let mut i = 0u64;
+    loop {
+        let thunk = read_thunk(sections, lookup + i * thunk_size, bitness)?;
+        if thunk == 0 {
+            break;
+        }
+        if i as usize >= MAX_ENTRIES {
+            return Err(anyhow!("the thunk table of '{dll}' has no end"));
+        }
+        let mut import = Import { dll: dll.to_string(), name: None, ordinal: None, hint: 0, iat_rva: (iat + i * thunk_size) as u32, delay_thunk: None };
+        if thunk & ordinal_flag != 0 {
+            import.ordinal = Some(thunk as u16);
+        } else {
+            let by_name = thunk.wrapping_sub(va_base) as u32 as u64;
+            import.hint = read_rva(sections, by_name, 2).map(|b| u16::from_le_bytes([b[0], b[1]])).ok_or_else(|| anyhow!("hint {:#x} is outside of the sections", by_name))?;
+            import.name = Some(read_rva_str(sections, by_name + 2).ok_or_else(|| anyhow!("import name {:#x} is outside of the sections", by_name + 2))?);
+        }
+        result.push(import);
+        i += 1;
+    }
+    Ok(result)
+}
+
+
+pub fn parse_imports(sections: &[Section], import_dir: IMAGE_DATA_DIRECTORY, bitness: usize) -> Result<Vec<Import>, anyhow::Error> {
+    if import_dir.VirtualAddress == 0 || import_dir.Size == 0 {
+        return Ok(Vec::new());
+    }
+    let mut result = Vec::new();
+    let mut desc_rva = import_dir.VirtualAddress as u64;
+    loop {
+        let original_first_thunk = read_u32(sections, desc_rva)?;
+        let name_rva = read_u32(sections, desc_rva + 12)?;
+        let first_thunk = read_u32(sections, desc_rva + 16)?;
+        if original_first_thunk == 0 && name_rva == 0 && first_thunk == 0 {
+            break;
+        }
+        let dll = read_rva_str(sections, name_rva as u64).ok_or_else(|| anyhow!("import name {:#x} is outside of the sections", name_rva))?;
+        let lookup = if original_first_thunk != 0 { original_first_thunk } else { first_thunk };
+        result.extend(read_thunks(sections, &dll, lookup as u64, first_thunk as u64, bitness, 0)?);
+        if result.len() > MAX_ENTRIES {
+            return Err(anyhow!("more than {MAX_ENTRIES} imports"));
+        }
+        desc_rva += 20;
+    }
+    Ok(result)
+}
+
+
+// the descriptors written by old linkers (Attributes without the rva bit) hold VAs instead of RVAs
+pub fn parse_delay_imports(sections: &[Section], delay_dir: IMAGE_DATA_DIRECTORY, bitness: usize, image_base: u64) -> Result<Vec<Import>, anyhow::Error> {
+    if delay_dir.VirtualAddress == 0 || delay_dir.Size == 0 {
+        return Ok(Vec::new());
+    }
+    let mut result = Vec::new();
+    let mut desc_rva = delay_dir.VirtualAddress as u64;
+    loop {
+        let attributes = read_u32(sections, desc_rva)?;
+        let name = read_u32(sections, desc_rva + 4)?;
+        let iat = read_u32(sections, desc_rva + 12)?;
+        let int = read_u32(sections, desc_rva + 16)?;
+        if name == 0 && iat == 0 && int == 0 {
+            break;
+        }
+        let va_base = if attributes & DLATTR_RVA != 0 { 0 } else { image_base };
+        let to_rva = |v: u32| (v as u64).wrapping_sub(va_base) as u32 as u64;
+        let dll = read_rva_str(sections, to_rva(name)).ok_or_else(|| anyhow!("delay import name {:#x} is outside of the sections", name))?;
+        let mut imports = read_thunks(sections, &dll, to_rva(int), to_rva(iat), bitness, va_base)?;
+        for import in imports.iter_mut() {
+            let thunk = read_thunk(sections, import.iat_rva as u64, bitness)?;
+            import.delay_thunk = Some(thunk.wrapping_sub(image_base) as u32);
+        }
+        result.extend(imports);
+        if result.len() > MAX_ENTRIES {
+            return Err(anyhow!("more than {MAX_ENTRIES} delay imports"));
+        }
+        desc_rva += 32;
+    }
+    Ok(result)
+}
+
+
+pub fn slot_of_operand(insn: &Instruction) -> Option<u64> {
+    if insn.op_count() == 0 || insn.op0_kind() != OpKind::Memory {
+        return None;
+    }
+    if insn.is_ip_rel_memory_operand() {
+        Some(insn.ip_rel_memory_address())
+    } else if insn.memory_base() == Register::None && insn.memory_index() == Register::None {
+        Some(insn.memory_displacement64())
+    } else {
+        None
+    }
+}
+
+
+pub fn find_iat_calls(sections: &[Section], bitness: usize, image_base: u64, iat_rva: u32) -> Vec<u64> {
+    let slot = image_base + iat_rva as u64;
+    let mut result = Vec::new();
+    let mut insn = Instruction::default();
+    for section in sections.iter().filter(|s| s.characteristics & IMAGE_SCN_MEM_EXECUTE != 0) {
+        let mut decoder = Decoder::with_ip(bitness as u32, &section.content, image_base + section.addr as u64, DecoderOptions::NONE);
+        while decoder.can_decode() {
+            decoder.decode_out(&mut insn);
+            if matches!(insn.flow_control(), FlowControl::IndirectCall | FlowControl::IndirectBranch) && slot_of_operand(&insn) == Some(slot) {
+                result.push(insn.ip() - image_base);
+            }
+        }
+    }
+    result
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pefile::load_pe;
+
+    fn imports(name: &str) -> (Vec<Section>, usize, u64, Vec<Import>) {
+        let (nt, sections) = load_pe(&format!("{}/fuzz/corpus/pe_parse/{name}", env!("CARGO_MANIFEST_DIR"))).unwrap();
+        let imports = parse_imports(&sections, nt.data_directory(1), nt.get_bitness()).unwrap();
+        (sections, nt.get_bitness(), nt.image_base(), imports)
+    }
+
+    fn find<'a>(imports: &'a [Import], target: &str) -> &'a Import {
+        imports.iter().find(|i| i.matches(target)).unwrap()
+    }
+
+    #[test]
+    fn by_name_and_ordinal() {
+        for (name, thunk) in [("imports_pe64", 8), ("imports_pe32", 4)] {
+            let (_, _, _, imports) = imports(name);
+            let got: Vec<_> = imports.iter().map(|i| (i.dll.as_str(), i.name.as_deref(), i.ordinal, i.hint, i.iat_rva)).collect();
+            assert_eq!(got, [
+                ("KERNEL32.dll", Some("ExitProcess"), None, 0x167, 0x2100),
+                ("KERNEL32.dll", Some("Sleep"), None, 0x5b2, 0x2100 + thunk),
+                ("USER32.dll", None, Some(17), 0, 0x2120),
+                ("USER32.dll", Some("MessageBoxA"), None, 0x285, 0x2120 + thunk),
+            ], "{name}");
+            assert!(imports.iter().all(|i| !i.is_delay()));
+            assert_eq!(imports[2].label(), "user32!#17");
+        }
+    }
+
+    #[test]
+    fn iat_calls() {
+        for name in ["imports_pe64", "imports_pe32"] {
+            let (sections, bitness, image_base, imports) = imports(name);
+            let calls = |target: &str| find_iat_calls(&sections, bitness, image_base, find(&imports, target).iat_rva);
+            assert_eq!(calls("Sleep"), [0x1004, 0x1010], "{name}");
+            assert_eq!(calls("MessageBoxA"), [0x100a], "{name}");
+            assert_eq!(calls("ExitProcess"), [0x101a], "{name}");
+            assert!(calls("user32!#17").is_empty(), "{name}");
+        }
+    }
+
+    #[test]
+    fn matches() {
+        let (_, _, _, imports) = imports("imports_pe64");
+        let (sleep, ordinal) = (&imports[1], &imports[2]);
+        for target in ["Sleep", "kernel32!Sleep", "KERNEL32.dll!Sleep", "Kernel32.DLL!Sleep"] {
+            assert!(sleep.matches(target), "{target}");
+        }
+        for target in ["sleep", "user32!Sleep", "kernel32!Sleep2", "kernel32!#0", "#0"] {
+            assert!(!sleep.matches(target), "{target}");
+        }
+        for target in ["#17", "user32!#17", "USER32.dll!#17"] {
+            assert!(ordinal.matches(target), "{target}");
+        }
+        for target in ["#18", "kernel32!#17", "user32!17", "user32!#x", "user32!#"] {
+            assert!(!ordinal.matches(target), "{target}");
+        }
+    }
+
+    #[test]
+    fn delay_imports() {
+        for (name, bitness, image_base) in [("delay_imports_rva", 64, 0x140000000), ("delay_imports_va", 32, 0x400000)] {
+            let (nt, sections) = load_pe(&format!("{}/fuzz/corpus/pe_parse/{name}", env!("CARGO_MANIFEST_DIR"))).unwrap();
+            assert_eq!((nt.get_bitness(), nt.image_base()), (bitness, image_base));
+            let imports = parse_delay_imports(&sections, nt.data_directory(13), bitness, image_base).unwrap();
+            let got: Vec<_> = imports.iter().map(|i| (i.dll.as_str(), i.name.as_deref(), i.ordinal, i.hint, i.iat_rva, i.delay_thunk)).collect();
+            assert_eq!(got, [
+                ("DELAY.dll", Some("DelayedFunc"), None, 0x10, 0x22a0, Some(0x1080)),
+                ("DELAY.dll", None, Some(5), 0, 0x22a0 + bitness as u32 / 8, Some(0x1090)),
+            ], "{name}");
+            assert_eq!(imports[0].thunk_name(), "__imp_load_DelayedFunc");
+            assert_eq!(imports[1].label(), "delay!#5");
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use anyhow::anyhow;
+use crate::pefile::winnt::IMAGE_DATA_DIRECTORY;
+use crate::pefile::{read_rva, Section, MAX_ENTRIES};
+
+const MAX_SIZE_64: usize = 320;
+const MAX_SIZE_32: usize = 192;
+
+const GUARD_CF_FUNCTION_TABLE_SIZE_MASK: u32 = 0xf0000000;
+const GUARD_CF_FUNCTION_TABLE_SIZE_SHIFT: u32 = 28;
+
+const GUARD_FLAGS: [(u32, &str); 18] = [
+    (0x100, "CF_INSTRUMENTED"),
+    (0x200, "CFW_INSTRUMENTED"),
+    (0x400, "CF_FUNCTION_TABLE_PRESENT"),
+    (0x800, "SECURITY_COOKIE_UNUSED"),
+    (0x1000, "PROTECT_DELAYLOAD_IAT"),
+    (0x2000, "DELAYLOAD_IAT_IN_ITS_OWN_SECTION"),
+    (0x4000, "CF_EXPORT_SUPPRESSION_INFO_PRESENT"),
+    (0x8000, "CF_ENABLE_EXPORT_SUPPRESSION"),
+    (0x10000, "CF_LONGJUMP_TABLE_PRESENT"),
+    (0x20000, "RF_INSTRUMENTED"),
+    (0x40000, "RF_ENABLE"),
+    (0x80000, "RF_STRICT"),
+    (0x100000, "RETPOLINE_PRESENT"),
+    (0x400000, "EH_CONTINUATION_TABLE_PRESENT"),
+    (0x800000, "XFG_ENABLED"),
+    (0x1000000, "CASTGUARD_PRESENT"),
+    (0x2000000, "MEMCPY_PRESENT"),
+    (0x4000000, "CF_ENABLE_WRITE_ONLY"),
+];
+
+const GUARD_ENTRY_FLAGS: [(u8, &str); 4] = [
+    (0x1, "FID_SUPPRESSED"),
+    (0x2, "EXPORT_SUPPRESSED"),
+    (0x4, "FID_LANGEXCPTHANDLER"),
+    (0x8, "FID_XFG"),
+];
+
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GuardEntry {
+    pub rva: u32,
+    pub flags: u8,
+}
+
+impl GuardEntry {
+    pub fn flags_str(&self) -> String {
+        GUARD_ENTRY_FLAGS.iter().filter(|(f, _)| self.flags & f != 0).map(|(_, n)| *n).collect::<Vec<_>>().join(" ")
+    }
+}
+
+
+// the addresses are VAs at the preferred image base, like in the file
+#[derive(Debug, Clone, Default)]
+pub struct LoadConfig {
+    pub size: u32,
+    pub time_date_stamp: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub global_flags_clear: u32,
+    pub global_flags_set: u32,
+    pub dependent_load_flags: u16,
+    pub security_cookie: u64,
+    pub se_handler_table: u64,
+    pub se_handler_count: u64,
+    pub guard_cf_check_function_pointer: u64,
+    pub guard_cf_dispatch_function_pointer: u64,
+    pub guard_cf_function_table: u64,
+    pub guard_cf_function_count: u64,
+    pub guard_flags: u32,
+    pub guard_address_taken_iat_table: u64,
+    pub guard_address_taken_iat_count: u64,
+    pub guard_long_jump_table: u64,
+    pub guard_long_jump_count: u64,
+    pub dynamic_value_reloc_table: u64,
+    pub chpe_metadata_pointer: u64,
+    pub dynamic_value_reloc_table_offset: u32,
+    pub dynamic_value_reloc_table_section: u16,
+    pub guard_eh_continuation_table: u64,
+    pub guard_eh_continuation_count: u64,
+    pub guard_xfg_check_function_pointer: u64,
+    pub guard_xfg_dispatch_function_pointer: u64,
+    pub guard_xfg_table_dispatch_function_pointer: u64,
+    pub guard_memcpy_function_pointer: u64,
+    pub se_handlers: Vec<u32>,
+    pub guard_cf_functions: Vec<GuardEntry>,
+    pub guard_address_taken_iat: Vec<GuardEntry>,
+    pub guard_long_jumps: Vec<GuardEntry>,
+    pub guard_eh_continuations: Vec<GuardEntry>,
+}
+
+impl LoadConfig {
+    pub fn guard_flags_str(&self) -> String {
+        GUARD_FLAGS.iter().filter(|(f, _)| self.guard_flags & f != 0).map(|(_, n)| *n).collect::<Vec<_>>().join(" ")
+    }
+
+    // an entry of the guard tables is an rva followed by the extra bytes given by the guard flags
+    pub fn guard_entry_size(&self) -> u32 {
+        4 + ((self.guard_flags & GUARD_CF_FUNCTION_TABLE_SIZE_MASK) >> GUARD_CF_FUNCTION_TABLE_SIZE_SHIFT)
+    }
+
+    // the pointers that the compiler calls through, e.g. "call [__guard_check_icall_fptr]", as RVAs
+    pub fn guard_pointers(&self, image_base: u64) -> Vec<(u64, &'static str)> {
+        [
+            (self.guard_cf_check_function_pointer, "__guard_check_icall_fptr"),
+            (self.guard_cf_dispatch_function_pointer, "__guard_dispatch_icall_fptr"),
+            (self.guard_xfg_check_function_pointer, "__guard_xfg_check_icall_fptr"),
+            (self.guard_xfg_dispatch_function_pointer, "__guard_xfg_dispatch_icall_fptr"),
+            (self.guard_xfg_table_dispatch_function_pointer, "__guard_xfg_table_dispatch_icall_fptr"),
+            (self.guard_memcpy_function_pointer, "__guard_memcpy_fptr"),
+        ].into_iter()
+            .filter(|(va, _)| *va != 0)
+            .map(|(va, name)| (va.wrapping_sub(image_base), name))
+            .collect()
+    }
+}
+
+
+struct Fields<'a> {
+    data: &'a [u8],
+    off: usize,
+    ptr_size: usize,
+}
+
+impl Fields<'_> {
+    fn bytes<const N: usize>(&mut self) -> [u8; N] {
+        let mut result = [0u8; N];
+        result.copy_from_slice(&self.data[self.off..self.off + N]);
+        self.off += N;
+        result
+    }
+
+    fn u16(&mut self) -> u16 {
+        u16::from_le_bytes(self.bytes())
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.bytes())
+    }
+
+    fn ptr(&mut self) -> u64 {
+        match self.ptr_size {
+            8 => u64::from_le_bytes(self.bytes()),
+            _ => self.u32() as u64,
+        }
+    }
+}
+
+
+fn read_guard_table(sections: &[Section], va: u64, count: u64, entry_size: u32, image_base: u64, what: &str) -> Result<Vec<GuardEntry>, anyhow::Error> {
+    if va == 0 || count == 0 {
+        return Ok(Vec::new());
+    }
+    if count > MAX_ENTRIES as u64 {
+        return Err(anyhow!("the {what} table has {count} entries"));
+    }
+    let rva = va.wrapping_sub(image_base);
+    let table = read_rva(sections, rva, (count * entry_size as u64) as usize)
+        .ok_or_else(|| anyhow!("the {what} table at {:#x} is outside of the sections", va))?;
+    Ok(table.chunks_exact(entry_size as usize)
+        .map(|e| GuardEntry { rva: u32::from_le_bytes([e[0], e[1], e[2], e[3]]), flags: e.get(4).copied().unwrap_or_default() })
+        .collect())
+}
+
+
+pub fn parse_load_config(sections: &[Section], load_config_dir: IMAGE_DATA_DIRECTORY, bitness: usize, image_base: u64) -> Result<Option<LoadConfig>, anyhow::Error> {
+    if load_config_dir.VirtualAddress == 0 || load_config_dir.Size == 0 {
+        return Ok(None);
+    }
+    let rva = load_config_dir.VirtualAddress as u64;
+    let size = read_rva(sections, rva, 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| anyhow!("load config directory {:#x} is outside of the sections", rva))?;
+    // the structure grows with each version of the sdk, its own Size field says which fields are there
+    let max_size = if bitness == 64 { MAX_SIZE_64 } else { MAX_SIZE_32 };
+    let present = (size as usize).clamp(4, max_size);
+    let bytes = read_rva(sections, rva, present).ok_or_else(|| anyhow!("load config directory of {:#x} bytes at {:#x} is outside of the sections", size, rva))?;
+    let mut data = vec![0u8; max_size];
+    data[..present].copy_from_slice(bytes);
+
+    let mut f = Fields { data: &data, off: 0, ptr_size: bitness / 8 };
+    let mut lc = LoadConfig {
+        size: f.u32(),
+        time_date_stamp: f.u32(),
+        major_version: f.u16(),
+        minor_version: f.u16(),
+        global_flags_clear: f.u32(),
+        global_flags_set: f.u32(),
+        ..Default::default()
+    };
+    // CriticalSectionDefaultTimeout, DeCommitFreeBlockThreshold, DeCommitTotalFreeThreshold, LockPrefixTable,
+    // MaximumAllocationSize, VirtualMemoryThreshold, ProcessAffinityMask, ProcessHeapFlags and CSDVersion
+    f.off += 4 + f.ptr_size * 6 + 4 + 2;
+    lc.dependent_load_flags = f.u16();
+    f.ptr();
+    lc.security_cookie = f.ptr();
+    lc.se_handler_table = f.ptr();
+    lc.se_handler_count = f.ptr();
+    lc.guard_cf_check_function_pointer = f.ptr();
+    lc.guard_cf_dispatch_function_pointer = f.ptr();
+    lc.guard_cf_function_table = f.ptr();
+    lc.guard_cf_function_count = f.ptr();
+    lc.guard_flags = f.u32();
+    f.off += 12;
+    lc.guard_address_taken_iat_table = f.ptr();
+    lc.guard_address_taken_iat_count = f.ptr();
+    lc.guard_long_jump_table = f.ptr();
+    lc.guard_long_jump_count = f.ptr();
+    lc.dynamic_value_reloc_table = f.ptr();
+    lc.chpe_metadata_pointer = f.ptr();
+    f.ptr();
+    f.ptr();
+    lc.dynamic_value_reloc_table_offset = f.u32();
+    lc.dynamic_value_reloc_table_section = f.u16();
+    f.off += 2;
+    f.ptr();
+    f.off += 8;
+    f.ptr();
+    f.ptr();
+    lc.guard_eh_continuation_table = f.ptr();
+    lc.guard_eh_continuation_count = f.ptr();
+    lc.guard_xfg_check_function_pointer = f.ptr();
+    lc.guard_xfg_dispatch_function_pointer = f.ptr();
+    lc.guard_xfg_table_dispatch_function_pointer = f.ptr();
+    f.ptr();
+    lc.guard_memcpy_function_pointer = f.ptr();
+
+    // safe seh only exists for x86, the table is a sorted array of handler rvas
+    if bitness == 32 && lc.se_handler_table != 0 && lc.se_handler_count != 0 {
+        lc.se_handlers = read_guard_table(sections, lc.se_handler_table, lc.se_handler_count, 4, image_base, "safe seh")?
+            .into_iter().map(|e| e.rva).collect();
+    }
+    let entry_size = lc.guard_entry_size();
+    lc.guard_cf_functions = read_guard_table(sections, lc.guard_cf_function_table, lc.guard_cf_function_count, entry_size, image_base, "guard cf function")?;
+    lc.guard_address_taken_iat = read_guard_table(sections, lc.guard_address_taken_iat_table, lc.guard_address_taken_iat_count, entry_size, image_base, "address taken iat")?;
+    lc.guard_long_jumps = read_guard_table(sections, lc.guard_long_jump_table, lc.guard_long_jump_count, entry_size, image_base, "long jump target")?;
+    lc.guard_eh_continuations = read_guard_table(sections, lc.guard_eh_continuation_table, lc.guard_eh_continuation_count, entry_size, image_base, "eh continuation")?;
+    Ok(Some(lc))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pefile::load_pe;
+
+    fn fixture() -> (IMAGE_DATA_DIRECTORY, Vec<Section>) {
+        let (nt, sections) = load_pe(&format!("{}/fuzz/corpus/pe_parse/loadconfig_guard_table", env!("CARGO_MANIFEST_DIR"))).unwrap();
+        (nt.data_directory(10), sections)
+    }
+
+    fn entries(table: &[GuardEntry]) -> Vec<(u32, u8)> {
+        table.iter().map(|e| (e.rva, e.flags)).collect()
+    }
+
+    #[test]
+    fn guard_table_64() {
+        let (dir, sections) = fixture();
+        let lc = parse_load_config(&sections, dir, 64, 0x140000000).unwrap().unwrap();
+        assert_eq!(lc.size, 0x94);
+        assert_eq!(lc.security_cookie, 0x140002000);
+        assert_eq!(lc.guard_cf_check_function_pointer, 0x140002080);
+        assert_eq!(lc.guard_cf_dispatch_function_pointer, 0x140002088);
+        assert_eq!(lc.guard_flags, 0x10000500);
+        assert_eq!(lc.guard_flags_str(), "CF_INSTRUMENTED CF_FUNCTION_TABLE_PRESENT");
+        assert_eq!(lc.guard_entry_size(), 5);
+        assert_eq!(entries(&lc.guard_cf_functions), [(0x1000, 0), (0x1004, 1), (0x1008, 2)]);
+        assert_eq!(lc.guard_cf_functions[2].flags_str(), "EXPORT_SUPPRESSED");
+        assert_eq!(lc.guard_pointers(0x140000000), [(0x2080, "__guard_check_icall_fptr"), (0x2088, "__guard_dispatch_icall_fptr")]);
+    }
+
+    #[test]
+    fn guard_table_32() {
+        let mut content = vec![0u8; 0x300];
+        let mut put = |off: usize, value: u32| content[off..off + 4].copy_from_slice(&value.to_le_bytes());
+        put(0x10, 92);
+        put(0x10 + 60, 0x403000);
+        put(0x10 + 64, 0x401200);
+        put(0x10 + 68, 2);
+        put(0x10 + 72, 0x403010);
+        put(0x10 + 80, 0x401100);
+        put(0x10 + 84, 2);
+        put(0x10 + 88, 0x20000500);
+        put(0x100, 0x1010);
+        put(0x106, 0x1020);
+        put(0x200, 0x1030);
+        put(0x204, 0x1040);
+        content[0x10a] = 8;
+        let sections = vec![Section { name: ".text".to_string(), content, addr: 0x1000, characteristics: 0x60000020, virtual_size: 0x300, raw_size: 0x300, raw_ptr: 0x400 }];
+        let dir = IMAGE_DATA_DIRECTORY { VirtualAddress: 0x1010, Size: 92 };
+        let lc = parse_load_config(&sections, dir, 32, 0x400000).unwrap().unwrap();
+        assert_eq!(lc.security_cookie, 0x403000);
+        assert_eq!(lc.guard_cf_check_function_pointer, 0x403010);
+        assert_eq!(lc.guard_flags, 0x20000500);
+        assert_eq!(lc.guard_entry_size(), 6);
+        assert_eq!(entries(&lc.guard_cf_functions), [(0x1010, 0), (0x1020, 8)]);
+        assert_eq!(lc.guard_cf_functions[1].flags_str(), "FID_XFG");
+        assert_eq!(lc.se_handlers, [0x1030, 0x1040]);
+    }
+
+    #[test]
+    fn old_sdk_layout() {
+        let (dir, mut sections) = fixture();
+        let text = sections.iter_mut().find(|s| s.name == ".text").unwrap();
+        text.content[0x10..0x14].copy_from_slice(&0x70u32.to_le_bytes());
+        let lc = parse_load_config(&sections, dir, 64, 0x140000000).unwrap().unwrap();
+        assert_eq!(lc.size, 0x70);
+        assert_eq!(lc.security_cookie, 0x140002000);
+        assert_eq!((lc.guard_cf_check_function_pointer, lc.guard_cf_function_table, lc.guard_flags), (0, 0, 0));
+        assert!(lc.guard_cf_functions.is_empty());
+        assert!(lc.guard_pointers(0x140000000).is_empty());
+    }
+}
@@ -1,20 +1,41 @@
 pub mod function;
 pub mod section;
+pub mod debug;
+pub mod entry;
+pub mod error;
 pub mod export;
+#[doc(hidden)]
+pub mod fuzz;
+pub mod header;
+pub mod import;
+pub mod loadconfig;
+pub mod reloc;
+pub mod resource;
+pub mod rich;
+pub mod tls;
+pub mod winnt;
 
-use crate::ALL_ELM;
-use std::fs::File;
-use std::io::{Read, Seek, SeekFrom};
-use std::{io, ptr, slice};
-use winapi::um::winnt::{
-    IMAGE_DOS_HEADER, IMAGE_FILE_HEADER, IMAGE_NT_HEADERS32, IMAGE_NT_HEADERS64,
+use crate::session::Session;
+use crate::ut::fmt::{print_lg, LevelPrint};
+use std::{fs, io, ptr};
+use winnt::{
+    IMAGE_DATA_DIRECTORY, IMAGE_DOS_HEADER, IMAGE_FILE_HEADER, IMAGE_NT_HEADERS32, IMAGE_NT_HEADERS64,
 };
-use crate::pefile::section::SECTION_VS;
+
+pub use error::PeError;
+
+const MAX_IMAGE_SIZE: u64 = 0x40000000;
+// the tables of a hostile file can point to each other so that a walk never ends
+pub const MAX_ENTRIES: usize = 0x100000;
 
 pub struct Section {
     pub name: String,
     pub content: Vec<u8>,
     pub addr: u32,
+    pub characteristics: u32,
+    pub virtual_size: u32,
+    pub raw_size: u32,
+    pub raw_ptr: u32,
 }
 
 
@@ -38,61 +59,201 @@ impl NtHeaders {
             NtHeaders::Headers64(_) => 8,
         }
     }
+
+    pub fn file_header(self) -> IMAGE_FILE_HEADER {
+        match self {
+            NtHeaders::Headers32(nt) => nt.FileHeader,
+            NtHeaders::Headers64(nt) => nt.FileHeader,
+        }
+    }
+
+    pub fn image_base(self) -> u64 {
+        match self {
+            NtHeaders::Headers32(nt) => nt.OptionalHeader.ImageBase as u64,
+            NtHeaders::Headers64(nt) => nt.OptionalHeader.ImageBase,
+        }
+    }
+
+    pub fn entry_point(self) -> u32 {
+        match self {
+            NtHeaders::Headers32(nt) => nt.OptionalHeader.AddressOfEntryPoint,
+            NtHeaders::Headers64(nt) => nt.OptionalHeader.AddressOfEntryPoint,
+        }
+    }
+
+    pub fn size_of_image(self) -> u32 {
+        match self {
+            NtHeaders::Headers32(nt) => nt.OptionalHeader.SizeOfImage,
+            NtHeaders::Headers64(nt) => nt.OptionalHeader.SizeOfImage,
+        }
+    }
+
+    pub fn alignments(self) -> (u32, u32) {
+        match self {
+            NtHeaders::Headers32(nt) => (nt.OptionalHeader.FileAlignment, nt.OptionalHeader.SectionAlignment),
+            NtHeaders::Headers64(nt) => (nt.OptionalHeader.FileAlignment, nt.OptionalHeader.SectionAlignment),
+        }
+    }
+
+    fn clear_unused_directories(&mut self) {
+        let (count, directories) = match self {
+            NtHeaders::Headers32(nt) => (nt.OptionalHeader.NumberOfRvaAndSizes, &mut nt.OptionalHeader.DataDirectory),
+            NtHeaders::Headers64(nt) => (nt.OptionalHeader.NumberOfRvaAndSizes, &mut nt.OptionalHeader.DataDirectory),
+        };
+        for dir in directories.iter_mut().skip(count as usize) {
+            dir.VirtualAddress = 0;
+            dir.Size = 0;
+        }
+    }
+
+    pub fn data_directory(self, index: usize) -> IMAGE_DATA_DIRECTORY {
+        match self {
+            NtHeaders::Headers32(nt) => nt.OptionalHeader.DataDirectory[index],
+            NtHeaders::Headers64(nt) => nt.OptionalHeader.DataDirectory[index],
+        }
+    }
 }
 
-pub static mut NT_HEADER: Option<NtHeaders> = None;
-
-pub fn get_name(smptroffs: u64, file: &mut File, name_bytes: Vec<u8>) -> Vec<u8> {
-    if let Some(index) = name_bytes.iter().position(|&b| b == b'/') {
-        let offset_str = std::str::from_utf8(&name_bytes[index + 1..]).unwrap();
-        let cleaned_insn: String = offset_str.chars().filter(|&c| c.is_digit(10) || c == '-').collect();
-        let offset: u64 = cleaned_insn.parse().unwrap();
-        let mut table_string = vec![0u8; 256];
-        file.seek(SeekFrom::Start(offset + smptroffs)).unwrap();
-        file.read_exact(&mut table_string).unwrap();
-        let name_length = table_string.iter().position(|&b| b == 0).unwrap_or(256);
-        [&name_bytes[..index], &table_string[..name_length]].concat()
-    } else {
-        name_bytes
+pub(crate) unsafe fn parse_header(session: &mut Session) -> Result<(), io::Error> {
+    let (nt_header, sections) = load_pe(&session.all.file.clone().unwrap())?;
+    session.nt_header = Some(nt_header);
+    session.image_base = nt_header.image_base();
+    session.sections = sections;
+    session.func_info = function::parse_pdata(&session.sections, nt_header.data_directory(3));
+    session.imports = import::parse_imports(&session.sections, nt_header.data_directory(1), nt_header.get_bitness()).unwrap_or_else(|e| {
+        print_lg(LevelPrint::WarningO, format!("failed to parse the import directory : {e}"));
+        Vec::new()
+    });
+    session.relocs = reloc::parse_relocs(&session.sections, nt_header.data_directory(5)).unwrap_or_else(|e| {
+        print_lg(LevelPrint::WarningO, format!("failed to parse the relocation directory : {e}"));
+        Vec::new()
+    });
+    session.tls = tls::parse_tls(&session.sections, nt_header.data_directory(9), nt_header.get_bitness(), nt_header.image_base()).unwrap_or_else(|e| {
+        print_lg(LevelPrint::WarningO, format!("failed to parse the tls directory : {e}"));
+        None
+    });
+    session.load_config = loadconfig::parse_load_config(&session.sections, nt_header.data_directory(10), nt_header.get_bitness(), nt_header.image_base()).unwrap_or_else(|e| {
+        print_lg(LevelPrint::WarningO, format!("failed to parse the load config directory : {e}"));
+        None
+    });
+    session.resources = resource::parse_resources(&session.sections, nt_header.data_directory(2)).unwrap_or_else(|e| {
+        print_lg(LevelPrint::WarningO, format!("failed to parse the resource directory : {e}"));
+        Vec::new()
+    });
+    match import::parse_delay_imports(&session.sections, nt_header.data_directory(13), nt_header.get_bitness(), nt_header.image_base()) {
+        Ok(delay_imports) => session.imports.extend(delay_imports),
+        Err(e) => print_lg(LevelPrint::WarningO, format!("failed to parse the delay import directory : {e}")),
     }
+    Ok(())
 }
 
-pub unsafe fn parse_header() -> Result<(), io::Error> {
-    let mut file = File::open((*ptr::addr_of!(ALL_ELM)).file.clone().unwrap())?;
-    let mut dos_header: IMAGE_DOS_HEADER = std::mem::zeroed();
-    file.read_exact(slice::from_raw_parts_mut(ptr::addr_of_mut!(dos_header) as *mut u8, size_of::<IMAGE_DOS_HEADER>()))?;
+
+pub fn load_pe(path: &str) -> Result<(NtHeaders, Vec<Section>), io::Error> {
+    let data = fs::read(path)?;
+    Ok(parse_pe(&data)?)
+}
+
+
+pub(crate) fn read_struct<T: Copy>(data: &[u8], offset: u64, what: &'static str) -> Result<T, PeError> {
+    let size = size_of::<T>();
+    let bytes = usize::try_from(offset).ok()
+        .and_then(|off| data.get(off..off.checked_add(size)?))
+        .ok_or(PeError::Truncated { what, offset, size: size as u64 })?;
+    Ok(unsafe { ptr::read_unaligned(bytes.as_ptr() as *const T) })
+}
+
+
+// the optional header may be shorter than the struct, the missing data directories stay zeroed
+fn read_nt<T: Copy>(data: &[u8], offset: u64, opt_size: u16, fixed: usize) -> Result<T, PeError> {
+    if (opt_size as usize) < fixed {
+        return Err(PeError::OptionalHeaderSize(opt_size));
+    }
+    let size = size_of::<T>().min(24 + opt_size as usize);
+    let bytes = usize::try_from(offset).ok()
+        .and_then(|off| data.get(off..off.checked_add(size)?))
+        .ok_or(PeError::Truncated { what: "optional header", offset, size: size as u64 })?;
+    let mut nt: T = unsafe { std::mem::zeroed() };
+    unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), ptr::addr_of_mut!(nt) as *mut u8, size) };
+    Ok(nt)
+}
+
+
+pub fn parse_pe(data: &[u8]) -> Result<(NtHeaders, Vec<Section>), PeError> {
+    let dos_header: IMAGE_DOS_HEADER = read_struct(data, 0, "dos header")?;
     if dos_header.e_magic != 0x5a4d {
-        return Err(io::Error::new(io::ErrorKind::Other, "Invalid DOS signature"));
-    }
-    file.seek(SeekFrom::Start((dos_header.e_lfanew + 4) as u64))?;
-    let mut file_header: IMAGE_FILE_HEADER = std::mem::zeroed();
-    file.read_exact(slice::from_raw_parts_mut(ptr::addr_of_mut!(file_header) as *mut u8, size_of::<IMAGE_FILE_HEADER>()))?;
-    if file_header.Machine == 0x8664 {
-        let mut nt_header_64: IMAGE_NT_HEADERS64 = std::mem::zeroed();
-        file.seek(SeekFrom::Start(dos_header.e_lfanew as u64))?;
-        file.read_exact(slice::from_raw_parts_mut(ptr::addr_of_mut!(nt_header_64) as *mut u8, size_of::<IMAGE_NT_HEADERS64>()))?;
-        NT_HEADER = Some(NtHeaders::Headers64(nt_header_64));
-        crate::symbol::IMAGE_BASE = nt_header_64.OptionalHeader.ImageBase;
-        section::parse_section(NtHeaders::Headers64(nt_header_64), &mut file)?;
-        function::parse_pdata(nt_header_64.OptionalHeader.DataDirectory[3]);
-    }
-    else if file_header.Machine == 0x14c {
-        let mut nt_header_32: IMAGE_NT_HEADERS32 = std::mem::zeroed();
-        file.seek(SeekFrom::Start(dos_header.e_lfanew as u64))?;
-        file.read_exact(slice::from_raw_parts_mut(ptr::addr_of_mut!(nt_header_32) as *mut u8, size_of::<IMAGE_NT_HEADERS64>()))?;
-        NT_HEADER = Some(NtHeaders::Headers32(nt_header_32));
-        crate::symbol::IMAGE_BASE = nt_header_32.OptionalHeader.ImageBase as u64;
-        section::parse_section(NtHeaders::Headers32(nt_header_32), &mut file)?;
-        function::parse_pdata(nt_header_32.OptionalHeader.DataDirectory[3]);
-    } else {
-        return Err(io::Error::new(io::ErrorKind::Other, "only x64 - x32 file is supported", ));
+        return Err(PeError::DosSignature(dos_header.e_magic));
     }
-    Ok(())
+    let nt_offset = dos_header.e_lfanew as u32 as u64;
+    let signature: u32 = read_struct(data, nt_offset, "nt signature")?;
+    if signature != 0x4550 {
+        return Err(PeError::NtSignature(signature));
+    }
+    let file_header: IMAGE_FILE_HEADER = read_struct(data, nt_offset + 4, "file header")?;
+    let magic: u16 = read_struct(data, nt_offset + 24, "optional header")?;
+    let opt_size = file_header.SizeOfOptionalHeader;
+    let mut nt_header = match (file_header.Machine, magic) {
+        (0x8664, 0x20b) => NtHeaders::Headers64(read_nt(data, nt_offset, opt_size, 112)?),
+        (0x14c, 0x10b) => NtHeaders::Headers32(read_nt(data, nt_offset, opt_size, 96)?),
+        (machine @ (0x8664 | 0x14c), magic) => return Err(PeError::Magic { machine, magic }),
+        (machine, _) => return Err(PeError::Machine(machine)),
+    };
+    nt_header.clear_unused_directories();
+
+    let (file_align, section_align) = nt_header.alignments();
+    if !file_align.is_power_of_two() || !section_align.is_power_of_two() || file_align > section_align {
+        return Err(PeError::Alignment { file: file_align, section: section_align });
+    }
+    if nt_header.size_of_image() as u64 > MAX_IMAGE_SIZE {
+        return Err(PeError::TooLarge { what: "image", size: nt_header.size_of_image() as u64 });
+    }
+    let sections = section::parse_sections(data, nt_header, &file_header, nt_offset + 24 + opt_size as u64)?;
+    Ok((nt_header, sections))
 }
 
 
-pub fn get_section_of_rva(rva: u64) -> Option<&'static Section> {
-    unsafe {
-        (*ptr::addr_of!(SECTION_VS)).iter().find(|s| s.addr as u64 <= rva && s.addr as u64 + s.content.len() as u64 >= rva)
+pub fn get_section_of_rva(sections: &[Section], rva: u64) -> Option<&Section> {
+    sections.iter().find(|s| s.addr as u64 <= rva && rva < s.addr as u64 + s.content.len() as u64)
+}
+
+pub fn read_rva(sections: &[Section], rva: u64, len: usize) -> Option<&[u8]> {
+    let section = get_section_of_rva(sections, rva)?;
+    let start = (rva - section.addr as u64) as usize;
+    section.content.get(start..start + len)
+}
+
+
+pub fn read_rva_str(sections: &[Section], rva: u64) -> Option<String> {
+    let section = get_section_of_rva(sections, rva)?;
+    let start = (rva - section.addr as u64) as usize;
+    let bytes = section.content.get(start..)?;
+    let end = bytes.iter().position(|&b| b == 0)?;
+    Some(String::from_utf8_lossy(&bytes[..end]).to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(name: &str) -> Result<(NtHeaders, Vec<Section>), PeError> {
+        parse_pe(&std::fs::read(format!("{}/fuzz/corpus/pe_parse/{name}", env!("CARGO_MANIFEST_DIR"))).unwrap())
+    }
+
+    #[test]
+    fn corpus_errors() {
+        assert_eq!(parse("file_align_gt_section").err(), Some(PeError::Alignment { file: 0x2000, section: 0x1000 }));
+        assert_eq!(parse("section_count_huge").err(), Some(PeError::SectionCount(0xffff)));
+        assert_eq!(parse("overlapping_sections").err(), Some(PeError::OverlappingSections(".text".to_string(), ".rdata".to_string())));
+        assert_eq!(parse("section_raw_overlaps_headers").err(), Some(PeError::HeaderOverlap(".text".to_string())));
+        // out of the image before it can overlap the next section
+        assert_eq!(parse("section_vsize_huge").err(), Some(PeError::SectionOutOfImage(".text".to_string())));
+        assert_eq!(parse("short_optional_header").err(), Some(PeError::OptionalHeaderSize(0x10)));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn valid_files() {
+        for name in ["valid_pe64", "imports_pe64", "imports_pe32"] {
+            assert!(parse(name).is_ok(), "{name}");
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use anyhow::anyhow;
+use crate::pefile::winnt::IMAGE_DATA_DIRECTORY;
+use crate::pefile::{get_section_of_rva, read_rva, Section};
+use crate::session::Session;
+use crate::symbol::{SrcFile, SymbolFile};
+use crate::ut::fmt::*;
+
+const IMAGE_REL_BASED_ABSOLUTE: u8 = 0;
+const IMAGE_REL_BASED_HIGH: u8 = 1;
+const IMAGE_REL_BASED_LOW: u8 = 2;
+const IMAGE_REL_BASED_HIGHLOW: u8 = 3;
+const IMAGE_REL_BASED_HIGHADJ: u8 = 4;
+const IMAGE_REL_BASED_DIR64: u8 = 10;
+const IMAGE_SCN_MEM_EXECUTE: u32 = 0x20000000;
+
+
+#[derive(Debug, Clone, Copy)]
+pub struct Reloc {
+    pub rva: u32,
+    pub kind: u8,
+}
+
+
+impl Reloc {
+    pub fn size(&self) -> usize {
+        match self.kind {
+            IMAGE_REL_BASED_DIR64 => 8,
+            IMAGE_REL_BASED_HIGHLOW => 4,
+            _ => 2,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self.kind {
+            IMAGE_REL_BASED_HIGH => "HIGH",
+            IMAGE_REL_BASED_LOW => "LOW",
+            IMAGE_REL_BASED_HIGHLOW => "HIGHLOW",
+            IMAGE_REL_BASED_HIGHADJ => "HIGHADJ",
+            IMAGE_REL_BASED_DIR64 => "DIR64",
+            _ => "UNKNOWN",
+        }
+    }
+
+    pub fn is_pointer(&self) -> bool {
+        matches!(self.kind, IMAGE_REL_BASED_HIGHLOW | IMAGE_REL_BASED_DIR64)
+    }
+
+    pub fn target(&self, sections: &[Section]) -> Option<u64> {
+        let bytes = read_rva(sections, self.rva as u64, self.size())?;
+        Some(match self.size() {
+            8 => u64::from_le_bytes(bytes.try_into().ok()?),
+            4 => u32::from_le_bytes(bytes.try_into().ok()?) as u64,
+            _ => u16::from_le_bytes(bytes.try_into().ok()?) as u64,
+        })
+    }
+}
+
+
+pub fn parse_relocs(sections: &[Section], reloc_dir: IMAGE_DATA_DIRECTORY) -> Result<Vec<Reloc>, anyhow::Error> {
+    if reloc_dir.VirtualAddress == 0 || reloc_dir.Size == 0 {
+        return Ok(Vec::new());
+    }
+    let data = read_rva(sections, reloc_dir.VirtualAddress as u64, reloc_dir.Size as usize)
+        .ok_or_else(|| anyhow!("relocation directory {:#x} is outside of the sections", reloc_dir.VirtualAddress))?;
+    let mut result = Vec::new();
+    let mut off = 0;
+    while off + 8 <= data.len() {
+        let page = u32::from_le_bytes(data[off..off + 4].try_into()?);
+        let block_size = u32::from_le_bytes(data[off + 4..off + 8].try_into()?) as usize;
+        if block_size < 8 || off + block_size > data.len() {
+            return Err(anyhow!("invalid relocation block size {:#x} at offset {:#x}", block_size, off));
+        }
+        let mut entries = data[off + 8..off + block_size].chunks_exact(2).map(|e| u16::from_le_bytes([e[0], e[1]]));
+        while let Some(entry) = entries.next() {
+            let kind = (entry >> 12) as u8;
+            if kind != IMAGE_REL_BASED_ABSOLUTE {
+                result.push(Reloc { rva: page.wrapping_add((entry & 0xfff) as u32), kind });
+            }
+            // the slot after a HIGHADJ is the low half of the value, not an entry
+            if kind == IMAGE_REL_BASED_HIGHADJ {
+                entries.next();
+            }
+        }
+        off += block_size;
+    }
+    result.sort_by_key(|r| r.rva);
+    Ok(result)
+}
+
+
+pub fn find_reloc(relocs: &[Reloc], rva: u64) -> Option<&Reloc> {
+    relocs.binary_search_by_key(&rva, |r| r.rva as u64).ok().map(|i| &relocs[i])
+}
+
+
+pub fn relocs_in(relocs: &[Reloc], start: u64, end: u64) -> &[Reloc] {
+    let first = relocs.partition_point(|r| (r.rva as u64) < start);
+    let last = relocs.partition_point(|r| (r.rva as u64) < end);
+    &relocs[first..last.max(first)]
+}
+
+
+pub fn reloc_note(session: &Session, addr: u64) -> String {
+    let Some(reloc) = addr.checked_sub(session.base_addr).and_then(|rva| find_reloc(&session.relocs, rva)) else {
+        return String::new();
+    };
+    if session.base_addr != 0 && session.base_addr != session.image_base {
+        format!(" {WAR_COLOR}[{} reloc, rebased by {:+#x}]{RESET_COLOR}", reloc.type_name(), session.base_addr.wrapping_sub(session.image_base) as i64)
+    } else {
+        format!(" {WAR_COLOR}[{} reloc]{RESET_COLOR}", reloc.type_name())
+    }
+}
+
+
+// a slot of a data symbol holding an address inside the image is only rebased by a HIGHLOW/DIR64 relocation
+fn unrelocated_pointers<'a>(symbols: &'a [SymbolFile], sections: &[Section], relocs: &[Reloc], image_base: u64, image_end: u64, ptr_size: usize) -> Vec<&'a str> {
+    symbols.iter()
+        .filter(|s| s.src_file == SrcFile::Ex && s.offset > 0)
+        .filter(|s| get_section_of_rva(sections, s.offset as u64).is_some_and(|sec| sec.characteristics & IMAGE_SCN_MEM_EXECUTE == 0))
+        .filter(|s| {
+            let start = (s.offset as u64).next_multiple_of(ptr_size as u64);
+            let end = s.offset as u64 + s.size.max(ptr_size) as u64;
+            (start..end).step_by(ptr_size)
+                .map_while(|rva| read_rva(sections, rva, ptr_size).map(|b| (rva, b)))
+                .any(|(rva, bytes)| {
+                    let mut buf = [0u8; 8];
+                    buf[..ptr_size].copy_from_slice(bytes);
+                    let value = u64::from_le_bytes(buf);
+                    value.wrapping_sub(image_base) < image_end && !find_reloc(relocs, rva).is_some_and(|r| r.is_pointer())
+                })
+        })
+        .map(|s| s.name.as_str())
+        .collect()
+}
+
+
+pub fn check_rebase(session: &Session) {
+    let Some(nt_header) = session.nt_header else {
+        return;
+    };
+    if session.base_addr == 0 || session.base_addr == session.image_base {
+        return;
+    }
+    if session.relocs.is_empty() {
+        print_lg(LevelPrint::Error, format!("the image is loaded at {:#x} instead of {:#x} but has no relocation, its absolute pointers are not rebased", session.base_addr, session.image_base));
+    }
+    let image_end = nt_header.size_of_image() as u64;
+    let invalid = session.symbols.symbol_file.iter()
+        .filter(|s| s.src_file == SrcFile::Ex && s.offset > 0 && s.offset as u64 >= image_end)
+        .map(|s| s.name.as_str())
+        .collect::<Vec<_>>();
+    if !invalid.is_empty() {
+        print_lg(LevelPrint::Error, format!("{} symbols have an offset outside of the image and will not follow the rebase ({})", invalid.len(), invalid.iter().take(5).copied().collect::<Vec<_>>().join(", ")));
+    }
+    let dangling = session.relocs.iter()
+        .filter(|r| r.is_pointer())
+        .filter_map(|r| r.target(&session.sections))
+        .filter(|t| t.wrapping_sub(session.image_base) >= image_end)
+        .count();
+    if dangling != 0 {
+        print_lg(LevelPrint::Error, format!("{dangling} relocated pointers do not point into the image, the symbols computed against the image base {:#x} may be wrong", session.image_base));
+    }
+    if session.relocs.is_empty() {
+        return;
+    }
+    let unrelocated = unrelocated_pointers(&session.symbols.symbol_file, &session.sections, &session.relocs, session.image_base, image_end, nt_header.get_size_of_arch());
+    if !unrelocated.is_empty() {
+        print_lg(LevelPrint::Error, format!("{} data symbols hold pointers into the image without a HIGHLOW/DIR64 relocation and will not follow the rebase ({})", unrelocated.len(), unrelocated.iter().take(5).copied().collect::<Vec<_>>().join(", ")));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pefile::load_pe;
+
+    const IMAGE_BASE: u64 = 0x140000000;
+
+    fn reloc_section(blocks: &[(u32, &[u16])]) -> (Vec<Section>, IMAGE_DATA_DIRECTORY) {
+        let mut content = Vec::new();
+        for (page, entries) in blocks {
+            content.extend(page.to_le_bytes());
+            content.extend((8 + entries.len() as u32 * 2).to_le_bytes());
+            content.extend(entries.iter().flat_map(|e| e.to_le_bytes()));
+        }
+        let size = content.len() as u32;
+        (vec![Section { name: ".reloc".to_string(), content, addr: 0x5000, characteristics: 0x42000040, virtual_size: size, raw_size: size, raw_ptr: 0x800 }], IMAGE_DATA_DIRECTORY { VirtualAddress: 0x5000, Size: size })
+    }
+
+    fn rvas(relocs: &[Reloc]) -> Vec<(u32, &'static str)> {
+        relocs.iter().map(|r| (r.rva, r.type_name())).collect()
+    }
+
+    #[test]
+    fn blocks_and_padding() {
+        let (sections, dir) = reloc_section(&[(0x2000, &[0xa000, 0x0000]), (0x1000, &[0xa008, 0x3010, 0x0000, 0x4020, 0xa123, 0x2030])]);
+        let relocs = parse_relocs(&sections, dir).unwrap();
+        assert_eq!(rvas(&relocs), [(0x1008, "DIR64"), (0x1010, "HIGHLOW"), (0x1020, "HIGHADJ"), (0x1030, "LOW"), (0x2000, "DIR64")]);
+        assert_eq!(find_reloc(&relocs, 0x1010).map(|r| r.size()), Some(4));
+        assert!(find_reloc(&relocs, 0x1123).is_none());
+        assert!(find_reloc(&relocs, 0x1011).is_none());
+        assert!(parse_relocs(&sections, IMAGE_DATA_DIRECTORY { VirtualAddress: 0, Size: 0 }).unwrap().is_empty());
+    }
+
+    #[test]
+    fn bad_block_sizes() {
+        let (nt, sections) = load_pe(&format!("{}/fuzz/corpus/pe_parse/reloc_bad_block", env!("CARGO_MANIFEST_DIR"))).unwrap();
+        assert!(parse_relocs(&sections, nt.data_directory(5)).is_err());
+        let (mut sections, dir) = reloc_section(&[(0x1000, &[0xa008, 0xa010])]);
+        sections[0].content[4..8].copy_from_slice(&4u32.to_le_bytes());
+        assert!(parse_relocs(&sections, dir).is_err());
+        sections[0].content[4..8].copy_from_slice(&0x100u32.to_le_bytes());
+        assert!(parse_relocs(&sections, dir).is_err());
+    }
+
+    #[test]
+    fn ranges() {
+        let (sections, dir) = reloc_section(&[(0x1000, &[0xa008, 0xa010, 0xa018, 0xa020])]);
+        let relocs = parse_relocs(&sections, dir).unwrap();
+        let range = |start, end| rvas(relocs_in(&relocs, start, end)).into_iter().map(|(rva, _)| rva).collect::<Vec<_>>();
+        assert_eq!(range(0x1009, 0x1020), [0x1010, 0x1018]);
+        assert_eq!(range(0x1008, 0x1021), [0x1008, 0x1010, 0x1018, 0x1020]);
+        assert_eq!(range(0, u64::MAX).len(), 4);
+        assert!(range(0x1011, 0x1012).is_empty());
+        assert!(range(0x1020, 0x1008).is_empty());
+        assert!(range(0x2000, 0x3000).is_empty());
+    }
+
+    fn data(name: &str, offset: i64, size: usize) -> SymbolFile {
+        SymbolFile { name: name.to_string(), offset, size, ..Default::default() }
+    }
+
+    #[test]
+    fn pointers_without_relocs() {
+        let mut content = vec![0u8; 0x40];
+        for (off, value) in [(0x00, IMAGE_BASE + 0x1000), (0x08, IMAGE_BASE + 0x1010), (0x10, 42), (0x18, IMAGE_BASE + 0x2000), (0x20, IMAGE_BASE + 0x9000)] {
+            content[off..off + 8].copy_from_slice(&value.to_le_bytes());
+        }
+        let sections = [
+            Section { name: ".text".to_string(), content: vec![0; 0x10], addr: 0x1000, characteristics: IMAGE_SCN_MEM_EXECUTE, virtual_size: 0x10, raw_size: 0x10, raw_ptr: 0x400 },
+            Section { name: ".data".to_string(), content, addr: 0x2000, characteristics: 0, virtual_size: 0x40, raw_size: 0x40, raw_ptr: 0x600 },
+        ];
+        let relocs = [Reloc { rva: 0x2000, kind: IMAGE_REL_BASED_DIR64 }, Reloc { rva: 0x2018, kind: IMAGE_REL_BASED_HIGHLOW }];
+        let symbols = [
+            data("relocated", 0x2000, 8),
+            data("table", 0x2000, 0x10),
+            data("integer", 0x2010, 8),
+            data("highlow", 0x2018, 8),
+            data("outside", 0x2020, 8),
+            data("function", 0x1000, 0x10),
+            SymbolFile { src_file: SrcFile::Dll(0x7ff800000000), ..data("dll", 0x2008, 8) },
+        ];
+        assert_eq!(unrelocated_pointers(&symbols, &sections, &relocs, IMAGE_BASE, 0x3000, 8), ["table"]);
+    }
+}
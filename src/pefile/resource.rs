@@ -0,0 +1,337 @@
+use std::cell::Cell;
+use std::fmt;
+use anyhow::anyhow;
+use crate::pefile::winnt::IMAGE_DATA_DIRECTORY;
+use crate::pefile::{read_rva, Section, MAX_ENTRIES};
+
+pub const RT_STRING: u16 = 6;
+pub const RT_VERSION: u16 = 16;
+pub const RT_MANIFEST: u16 = 24;
+const VS_FFI_SIGNATURE: u32 = 0xfeef04bd;
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResId {
+    Id(u16),
+    Name(String),
+}
+
+impl fmt::Display for ResId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResId::Id(id) => write!(f, "{id}"),
+            ResId::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl ResId {
+    pub fn matches(&self, s: &str) -> bool {
+        match self {
+            ResId::Id(id) => s.parse::<u16>().ok() == Some(*id) || type_name(*id).is_some_and(|t| t.eq_ignore_ascii_case(s)),
+            ResId::Name(name) => name.eq_ignore_ascii_case(s),
+        }
+    }
+}
+
+
+pub fn type_name(id: u16) -> Option<&'static str> {
+    Some(match id {
+        1 => "CURSOR",
+        2 => "BITMAP",
+        3 => "ICON",
+        4 => "MENU",
+        5 => "DIALOG",
+        6 => "STRING",
+        7 => "FONTDIR",
+        8 => "FONT",
+        9 => "ACCELERATOR",
+        10 => "RCDATA",
+        11 => "MESSAGETABLE",
+        12 => "GROUP_CURSOR",
+        14 => "GROUP_ICON",
+        16 => "VERSION",
+        17 => "DLGINCLUDE",
+        19 => "PLUGPLAY",
+        20 => "VXD",
+        21 => "ANICURSOR",
+        22 => "ANIICON",
+        23 => "HTML",
+        24 => "MANIFEST",
+        _ => return None,
+    })
+}
+
+
+#[derive(Debug, Clone)]
+pub struct Resource {
+    pub kind: ResId,
+    pub name: ResId,
+    pub lang: u16,
+    pub rva: u32,
+    pub size: u32,
+    pub code_page: u32,
+}
+
+impl Resource {
+    pub fn kind_str(&self) -> String {
+        match &self.kind {
+            ResId::Id(id) => type_name(*id).map(str::to_string).unwrap_or_else(|| id.to_string()),
+            ResId::Name(name) => name.clone(),
+        }
+    }
+
+    pub fn is(&self, kind: u16) -> bool {
+        self.kind == ResId::Id(kind)
+    }
+
+    pub fn data<'a>(&self, sections: &'a [Section]) -> Option<&'a [u8]> {
+        read_rva(sections, self.rva as u64, self.size as usize)
+    }
+}
+
+
+struct ResTree<'a> {
+    sections: &'a [Section],
+    root: u64,
+    visited: Cell<usize>,
+}
+
+impl ResTree<'_> {
+    fn bytes(&self, off: u64, len: usize) -> Result<&[u8], anyhow::Error> {
+        read_rva(self.sections, self.root + off, len).ok_or_else(|| anyhow!("resource offset {:#x} is outside of the sections", off))
+    }
+
+    fn u16(&self, off: u64) -> Result<u16, anyhow::Error> {
+        Ok(u16::from_le_bytes(self.bytes(off, 2)?.try_into()?))
+    }
+
+    fn u32(&self, off: u64) -> Result<u32, anyhow::Error> {
+        Ok(u32::from_le_bytes(self.bytes(off, 4)?.try_into()?))
+    }
+
+    fn id(&self, name: u32) -> Result<ResId, anyhow::Error> {
+        if name & 0x80000000 == 0 {
+            return Ok(ResId::Id(name as u16));
+        }
+        let off = (name & 0x7fffffff) as u64;
+        let len = self.u16(off)? as usize;
+        Ok(ResId::Name(utf16(self.bytes(off + 2, len * 2)?)))
+    }
+
+    // the tree is always type -> name -> language, a subdirectory deeper than that is invalid
+    fn walk(&self, off: u64, path: &mut Vec<ResId>, result: &mut Vec<Resource>) -> Result<(), anyhow::Error> {
+        let count = self.u16(off + 12)? as u64 + self.u16(off + 14)? as u64;
+        for i in 0..count {
+            self.visited.set(self.visited.get() + 1);
+            if self.visited.get() > MAX_ENTRIES {
+                return Err(anyhow!("more than {MAX_ENTRIES} resource directory entries"));
+            }
+            let entry = off + 16 + i * 8;
+            let id = self.id(self.u32(entry)?)?;
+            let data = self.u32(entry + 4)?;
+            if data & 0x80000000 != 0 {
+                if path.len() >= 2 {
+                    return Err(anyhow!("resource directory deeper than 3 levels at offset {:#x}", off));
+                }
+                path.push(id);
+                self.walk((data & 0x7fffffff) as u64, path, result)?;
+                path.pop();
+            } else {
+                let (kind, name) = match path.as_slice() {
+                    [kind, name] => (kind.clone(), name.clone()),
+                    [kind] => (kind.clone(), id.clone()),
+                    _ => return Err(anyhow!("resource data at the root of the directory")),
+                };
+                let lang = match (path.len(), &id) {
+                    (2, ResId::Id(lang)) => *lang,
+                    _ => 0,
+                };
+                result.push(Resource {
+                    kind,
+                    name,
+                    lang,
+                    rva: self.u32(data as u64)?,
+                    size: self.u32(data as u64 + 4)?,
+                    code_page: self.u32(data as u64 + 8)?,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+
+fn utf16(bytes: &[u8]) -> String {
+    let units = bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect::<Vec<_>>();
+    String::from_utf16_lossy(&units)
+}
+
+
+pub fn parse_resources(sections: &[Section], res_dir: IMAGE_DATA_DIRECTORY) -> Result<Vec<Resource>, anyhow::Error> {
+    if res_dir.VirtualAddress == 0 || res_dir.Size == 0 {
+        return Ok(Vec::new());
+    }
+    let tree = ResTree { sections, root: res_dir.VirtualAddress as u64, visited: Cell::new(0) };
+    let mut result = Vec::new();
+    tree.walk(0, &mut Vec::new(), &mut result)?;
+    Ok(result)
+}
+
+
+pub fn decode_string_table(block_id: u16, data: &[u8]) -> Vec<(u32, String)> {
+    let mut result = Vec::new();
+    let mut off = 0;
+    for i in 0..16u32 {
+        let Some(len) = data.get(off..off + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize) else {
+            break;
+        };
+        off += 2;
+        if len != 0 {
+            let Some(s) = data.get(off..off + len * 2) else {
+                break;
+            };
+            result.push(((block_id as u32).saturating_sub(1) * 16 + i, utf16(s)));
+            off += len * 2;
+        }
+    }
+    result
+}
+
+
+struct VerBlock<'a> {
+    key: String,
+    value: &'a [u8],
+    text: bool,
+    children: &'a [u8],
+}
+
+fn align4(off: usize) -> usize {
+    (off + 3) & !3
+}
+
+fn ver_block(data: &[u8]) -> Option<VerBlock<'_>> {
+    let length = u16::from_le_bytes(data.get(0..2)?.try_into().ok()?) as usize;
+    let value_length = u16::from_le_bytes(data.get(2..4)?.try_into().ok()?) as usize;
+    let text = u16::from_le_bytes(data.get(4..6)?.try_into().ok()?) == 1;
+    let block = data.get(..length.max(6))?;
+    let key_len = block[6..].chunks_exact(2).position(|c| c == [0, 0])?;
+    let key = utf16(&block[6..6 + key_len * 2]);
+    let value_start = align4(6 + key_len * 2 + 2);
+    let value_size = if text { value_length * 2 } else { value_length };
+    let value = block.get(value_start..(value_start + value_size).min(block.len()))?;
+    let children = block.get(align4(value_start + value_size).min(block.len())..)?;
+    Some(VerBlock { key, value, text, children })
+}
+
+fn ver_children(mut data: &[u8]) -> Vec<VerBlock<'_>> {
+    let mut result = Vec::new();
+    while let Some(block) = ver_block(data) {
+        let length = u16::from_le_bytes([data[0], data[1]]) as usize;
+        result.push(block);
+        if length == 0 || align4(length) >= data.len() {
+            break;
+        }
+        data = &data[align4(length)..];
+    }
+    result
+}
+
+
+pub fn decode_version_info(data: &[u8]) -> Result<Vec<(String, String)>, anyhow::Error> {
+    let root = ver_block(data).ok_or_else(|| anyhow!("truncated VS_VERSIONINFO"))?;
+    if root.key != "VS_VERSION_INFO" {
+        return Err(anyhow!("invalid VS_VERSIONINFO key '{}'", root.key));
+    }
+    let mut result = Vec::new();
+    if root.value.len() >= 52 {
+        let dword = |i: usize| u32::from_le_bytes(root.value[i * 4..i * 4 + 4].try_into().unwrap());
+        if dword(0) != VS_FFI_SIGNATURE {
+            return Err(anyhow!("invalid VS_FIXEDFILEINFO signature {:#x}", dword(0)));
+        }
+        let version = |ms: u32, ls: u32| format!("{}.{}.{}.{}", ms >> 16, ms & 0xffff, ls >> 16, ls & 0xffff);
+        result.push(("FileVersion (fixed)".to_string(), version(dword(2), dword(3))));
+        result.push(("ProductVersion (fixed)".to_string(), version(dword(4), dword(5))));
+        result.push(("FileFlags".to_string(), format!("{:#x}", dword(7) & dword(6))));
+        result.push(("FileOS".to_string(), format!("{:#x}", dword(8))));
+        result.push(("FileType".to_string(), format!("{:#x}", dword(9))));
+    }
+    for info in ver_children(root.children) {
+        match info.key.as_str() {
+            "StringFileInfo" => for table in ver_children(info.children) {
+                for string in ver_children(table.children) {
+                    let value = if string.text { utf16(string.value) } else { String::from_utf8_lossy(string.value).to_string() };
+                    result.push((format!("{} [{}]", string.key, table.key), value.trim_end_matches('\0').to_string()));
+                }
+            },
+            "VarFileInfo" => for var in ver_children(info.children) {
+                let value = var.value.chunks_exact(4)
+                    .map(|c| format!("{:04x}{:04x}", u16::from_le_bytes([c[0], c[1]]), u16::from_le_bytes([c[2], c[3]])))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                result.push((var.key, value));
+            },
+            _ => {}
+        }
+    }
+    Ok(result)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pefile::load_pe;
+
+    fn resources() -> (Vec<Resource>, Vec<Section>) {
+        let (nt, sections) = load_pe(&format!("{}/fuzz/corpus/pe_parse/resources", env!("CARGO_MANIFEST_DIR"))).unwrap();
+        (parse_resources(&sections, nt.data_directory(2)).unwrap(), sections)
+    }
+
+    #[test]
+    fn type_name_lang_triples() {
+        let (res, sections) = resources();
+        let triples: Vec<(String, String, u16)> = res.iter().map(|r| (r.kind_str(), r.name.to_string(), r.lang)).collect();
+        assert_eq!(triples, [
+            ("MYDATA".to_string(), "1".to_string(), 0x409),
+            ("MYDATA".to_string(), "2".to_string(), 0),
+            ("STRING".to_string(), "2".to_string(), 0x409),
+            ("STRING".to_string(), "2".to_string(), 0x40c),
+            ("VERSION".to_string(), "1".to_string(), 0x409),
+        ]);
+        assert_eq!(res[0].kind, ResId::Name("MYDATA".to_string()));
+        assert_eq!(res[0].code_page, 1252);
+        assert_eq!(res[0].data(&sections), Some(&b"hello"[..]));
+        assert_eq!(res[1].data(&sections), Some(&b"abc"[..]));
+        assert!(res[2].is(RT_STRING) && res[4].is(RT_VERSION));
+        assert!(res[0].kind.matches("mydata") && res[4].kind.matches("version") && res[4].kind.matches("16"));
+    }
+
+    #[test]
+    fn version_info() {
+        let (res, sections) = resources();
+        let info = decode_version_info(res[4].data(&sections).unwrap()).unwrap();
+        let pairs: Vec<(&str, &str)> = info.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        assert_eq!(pairs, [
+            ("FileVersion (fixed)", "1.2.3.4"),
+            ("ProductVersion (fixed)", "5.6.7.8"),
+            ("FileFlags", "0x2"),
+            ("FileOS", "0x40004"),
+            ("FileType", "0x1"),
+            ("CompanyName [040904b0]", "Lisa"),
+            ("FileVersion [040904b0]", "1.2.3.4"),
+            ("Translation", "040904b0"),
+        ]);
+        assert!(decode_version_info(&res[4].data(&sections).unwrap()[..4]).is_err());
+        assert!(decode_version_info(res[2].data(&sections).unwrap()).is_err());
+    }
+
+    #[test]
+    fn string_table_ids() {
+        let (res, sections) = resources();
+        let data = res[2].data(&sections).unwrap();
+        assert_eq!(decode_string_table(2, data), [(16, "first".to_string()), (19, "fourth".to_string())]);
+        assert_eq!(decode_string_table(1, data)[1].0, 3);
+        assert_eq!(decode_string_table(2, &data[..20]), [(16, "first".to_string())]);
+    }
+}
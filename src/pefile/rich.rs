@@ -0,0 +1,162 @@
+const RICH_SIGNATURE: u32 = 0x68636952;
+const DANS_SIGNATURE: u32 = 0x536e6144;
+const DOS_HEADER_SIZE: usize = 0x40;
+const E_LFANEW: usize = 0x3c;
+
+// since VS2012 each toolset has the same 18 tools in the same order
+const TOOLS: [&str; 18] = [
+    "AliasObj", "Cvtpgd", "Cvtres", "Export", "Implib", "Linker", "Masm", "Utc C", "Utc C++",
+    "Utc C (CIL)", "Utc C++ (CIL)", "Utc C (LTCG)", "Utc C++ (LTCG)", "Utc MSIL (LTCG)",
+    "Utc C (POGO instrumented)", "Utc C++ (POGO instrumented)", "Utc C (POGO optimized)", "Utc C++ (POGO optimized)",
+];
+const TOOLSETS: [u16; 3] = [0x00b5, 0x00c7, 0x00fd];
+const VS2010_TOOLS: [(u16, &str); 9] = [
+    (0x0098, "AliasObj"), (0x0099, "Cvtpgd"), (0x009a, "Cvtres"), (0x009b, "Export"), (0x009c, "Implib"),
+    (0x009d, "Linker"), (0x009e, "Masm"), (0x00aa, "Utc C"), (0x00ab, "Utc C++"),
+];
+
+
+#[derive(Debug, Clone, Copy)]
+pub struct RichEntry {
+    pub product_id: u16,
+    pub build: u16,
+    pub count: u32,
+}
+
+impl RichEntry {
+    pub fn comp_id(&self) -> u32 {
+        (self.product_id as u32) << 16 | self.build as u32
+    }
+
+    pub fn product_str(&self) -> String {
+        match self.product_id {
+            0 => return "unmarked objects".to_string(),
+            1 => return "imports".to_string(),
+            _ => {}
+        }
+        TOOLSETS.iter()
+            .filter_map(|&start| TOOLS.get(self.product_id.checked_sub(start)? as usize))
+            .next()
+            .or_else(|| VS2010_TOOLS.iter().find(|(id, _)| *id == self.product_id).map(|(_, tool)| tool))
+            .map(|tool| tool.to_string())
+            .unwrap_or_else(|| format!("product {:#x}", self.product_id))
+    }
+
+    // the 14.x toolsets share their product ids, only the build number tells them apart
+    pub fn vs_version(&self) -> &'static str {
+        match self.product_id {
+            0x005a..=0x006c => "VS2003 (7.1)",
+            0x006d..=0x0082 => "VS2005 (8.0)",
+            0x0083..=0x0097 => "VS2008 (9.0)",
+            0x0098..=0x00b4 => "VS2010 (10.0)",
+            0x00b5..=0x00c6 => "VS2012 (11.0)",
+            0x00c7..=0x00dc => "VS2013 (12.0)",
+            0x00dd..=0x010e => match self.build {
+                0..=24999 => "VS2015 (14.0)",
+                25000..=26999 => "VS2017 (14.1x)",
+                27000..=29999 => "VS2019 (14.2x)",
+                _ => "VS2022 (14.3x)",
+            },
+            _ => "",
+        }
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct RichHeader {
+    pub offset: usize,
+    pub key: u32,
+    pub checksum: u32,
+    pub entries: Vec<RichEntry>,
+}
+
+impl RichHeader {
+    // the key is a checksum of the dos header, the dos stub and the entries, a mismatch means one of them was edited
+    pub fn is_valid(&self) -> bool {
+        self.key == self.checksum
+    }
+}
+
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+
+pub fn parse_rich(data: &[u8]) -> Option<RichHeader> {
+    let end = (read_u32(data, E_LFANEW)? as usize).min(data.len());
+    let rich = (DOS_HEADER_SIZE..end.saturating_sub(7)).step_by(4).find(|&i| read_u32(data, i) == Some(RICH_SIGNATURE))?;
+    let key = read_u32(data, rich + 4)?;
+    let offset = (DOS_HEADER_SIZE..rich).step_by(4).rev().find(|&i| read_u32(data, i).map(|v| v ^ key) == Some(DANS_SIGNATURE))?;
+
+    // DanS is followed by three zeroed dwords, then by (comp id, count) pairs
+    let entries = (offset + 16..rich).step_by(8)
+        .filter_map(|i| {
+            let comp_id = read_u32(data, i)? ^ key;
+            let count = read_u32(data, i + 4)? ^ key;
+            Some(RichEntry { product_id: (comp_id >> 16) as u16, build: comp_id as u16, count })
+        })
+        .collect::<Vec<_>>();
+
+    let mut checksum = offset as u32;
+    for (i, &b) in data[..offset].iter().enumerate() {
+        if !(E_LFANEW..E_LFANEW + 4).contains(&i) {
+            checksum = checksum.wrapping_add((b as u32).rotate_left(i as u32));
+        }
+    }
+    for entry in &entries {
+        checksum = checksum.wrapping_add(entry.comp_id().rotate_left(entry.count));
+    }
+    Some(RichHeader { offset, key, checksum, entries })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rich_header() -> Vec<u8> {
+        std::fs::read(format!("{}/fuzz/corpus/pe_parse/rich_header", env!("CARGO_MANIFEST_DIR"))).unwrap()
+    }
+
+    #[test]
+    fn entries() {
+        let rich = parse_rich(&rich_header()).unwrap();
+        assert_eq!((rich.offset, rich.key), (0x40, 0x12345678));
+        let entries: Vec<(u16, u16, u32)> = rich.entries.iter().map(|e| (e.product_id, e.build, e.count)).collect();
+        assert_eq!(entries, [(0x102, 30159, 3), (0x105, 30159, 12)]);
+        assert_eq!(rich.entries[0].comp_id(), 0x010275cf);
+        assert_eq!(rich.entries[0].product_str(), "Linker");
+        assert_eq!(rich.entries[1].product_str(), "Utc C++");
+    }
+
+    #[test]
+    fn key_is_the_checksum() {
+        let mut data = rich_header();
+        let rich = parse_rich(&data).unwrap();
+        assert!(!rich.is_valid());
+        // the checksum is over the decoded entries, so encoding them and the key again with it makes the header valid
+        for off in (0x40..0x60).step_by(4).chain([0x64]) {
+            let value = u32::from_le_bytes(data[off..off + 4].try_into().unwrap()) ^ rich.key ^ rich.checksum;
+            data[off..off + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        let resigned = parse_rich(&data).unwrap();
+        assert_eq!(resigned.key, rich.checksum);
+        assert!(resigned.is_valid());
+        data[0x20] ^= 1;
+        assert!(!parse_rich(&data).unwrap().is_valid());
+    }
+
+    #[test]
+    fn vs_version() {
+        let entry = |product_id, build| RichEntry { product_id, build, count: 1 };
+        assert_eq!(entry(0x5d, 6030).vs_version(), "VS2003 (7.1)");
+        assert_eq!(entry(0xaa, 40219).vs_version(), "VS2010 (10.0)");
+        assert_eq!(entry(0xff, 24215).vs_version(), "VS2015 (14.0)");
+        assert_eq!(entry(0x104, 26715).vs_version(), "VS2017 (14.1x)");
+        assert_eq!(entry(0x105, 29913).vs_version(), "VS2019 (14.2x)");
+        assert_eq!(entry(0x102, 30159).vs_version(), "VS2022 (14.3x)");
+        assert_eq!(entry(1, 0).vs_version(), "");
+    }
+}
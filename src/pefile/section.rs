@@ -1,48 +1,298 @@
-use crate::pefile::{get_name, NtHeaders, Section};
-use std::fs::File;
-use std::io::{Read, Seek, SeekFrom};
-use std::{io, ptr, slice};
-use winapi::um::winnt::IMAGE_SECTION_HEADER;
-
-pub static mut SECTION_VS: Vec<Section> = Vec::new();
-
-pub unsafe fn read_section_headers(file: &mut File, num_sec: usize) -> Result<Vec<IMAGE_SECTION_HEADER>, io::Error> {
-    let mut section_h = Vec::with_capacity(num_sec);
-    for _ in 0..num_sec {
-        let mut section_header: IMAGE_SECTION_HEADER = std::mem::zeroed();
-        file.read_exact(slice::from_raw_parts_mut(ptr::addr_of_mut!(section_header) as *mut u8, size_of::<IMAGE_SECTION_HEADER>()))?;
-        section_h.push(section_header);
-    }
-    Ok(section_h)
-}
-
-unsafe fn process_section(file: &mut File, section: IMAGE_SECTION_HEADER, smptroffs: u64) -> Result<(), io::Error> {
-    let name_bytes = get_name(smptroffs, file, section.Name.to_vec());
-    let name = String::from_utf8_lossy(&name_bytes).trim_end_matches(char::from(0)).to_string();
-    let mut content = vec![0u8; section.SizeOfRawData as usize];
-    file.seek(SeekFrom::Start(section.PointerToRawData as u64))?;
-    file.read_exact(&mut content)?;
-    (*ptr::addr_of_mut!(SECTION_VS)).push(Section { name, content, addr: section.VirtualAddress});
-    Ok(())
-}
-
-pub unsafe fn parse_section(nt_headers: NtHeaders, file: &mut File) -> Result<(), io::Error> {
-    match nt_headers {
-        NtHeaders::Headers32(nt_headers_32) => {
-            let num_sec = nt_headers_32.FileHeader.NumberOfSections as usize;
-            let smptroffs = nt_headers_32.FileHeader.PointerToSymbolTable as u64 + (nt_headers_32.FileHeader.NumberOfSymbols * 18) as u64;
-            for section in read_section_headers(file, num_sec)? {
-                process_section(file, section, smptroffs)?;
-            }
-            Ok(())
-        }
-        NtHeaders::Headers64(nt_headers_64) => {
-            let num_sec = nt_headers_64.FileHeader.NumberOfSections as usize;
-            let smptroffs = nt_headers_64.FileHeader.PointerToSymbolTable as u64 + (nt_headers_64.FileHeader.NumberOfSymbols * 18) as u64;
-            for section in read_section_headers(file, num_sec)? {
-                process_section(file, section, smptroffs)?;
-            }
-            Ok(())
+use std::fmt;
+use crate::pefile::{get_section_of_rva, read_struct, NtHeaders, PeError, Section};
+use crate::pefile::winnt::{IMAGE_FILE_HEADER, IMAGE_SECTION_HEADER};
+
+const SECTION_HEADER_SIZE: u64 = 40;
+const COFF_SYMBOL_SIZE: u64 = 18;
+
+const IMAGE_SCN_CNT_CODE: u32 = 0x20;
+const IMAGE_SCN_MEM_EXECUTE: u32 = 0x20000000;
+const IMAGE_SCN_MEM_READ: u32 = 0x40000000;
+const IMAGE_SCN_MEM_WRITE: u32 = 0x80000000;
+// compressed or encrypted data is close to 8 bits per byte, code and tables rarely go above 7
+const HIGH_ENTROPY: f64 = 7.2;
+
+const PACKER_SECTIONS: [(&str, &str); 22] = [
+    ("UPX0", "UPX"),
+    ("UPX1", "UPX"),
+    ("UPX2", "UPX"),
+    (".aspack", "ASPack"),
+    (".adata", "ASPack"),
+    (".themida", "Themida"),
+    (".winlice", "WinLicense"),
+    (".vmp0", "VMProtect"),
+    (".vmp1", "VMProtect"),
+    (".vmp2", "VMProtect"),
+    (".MPRESS1", "MPRESS"),
+    (".MPRESS2", "MPRESS"),
+    (".petite", "Petite"),
+    ("pebundle", "PEBundle"),
+    (".nsp0", "NsPack"),
+    (".nsp1", "NsPack"),
+    (".enigma1", "Enigma Protector"),
+    (".enigma2", "Enigma Protector"),
+    ("PEC2", "PECompact"),
+    ("pec1", "PECompact"),
+    (".packed", "RLPack"),
+    (".y0da", "Yoda's Crypter"),
+];
+
+
+impl Section {
+    pub fn is_executable(&self) -> bool {
+        self.characteristics & (IMAGE_SCN_MEM_EXECUTE | IMAGE_SCN_CNT_CODE) != 0
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.characteristics & IMAGE_SCN_MEM_WRITE != 0
+    }
+
+    pub fn permissions(&self) -> String {
+        [(IMAGE_SCN_MEM_READ, 'r'), (IMAGE_SCN_MEM_WRITE, 'w'), (IMAGE_SCN_MEM_EXECUTE, 'x')].iter()
+            .map(|&(flag, c)| if self.characteristics & flag != 0 { c } else { '-' })
+            .collect()
+    }
+
+    // the part of the content that comes from the file, the rest is zero-filled by the loader
+    pub fn raw_data(&self) -> &[u8] {
+        &self.content[..(self.raw_size as usize).min(self.content.len())]
+    }
+
+    pub fn entropy(&self) -> f64 {
+        entropy(self.raw_data())
+    }
+}
+
+
+pub fn entropy(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0usize; 256];
+    for &b in data {
+        counts[b as usize] += 1;
+    }
+    counts.iter()
+        .filter(|&&c| c != 0)
+        .map(|&c| {
+            let p = c as f64 / data.len() as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SectionWarning {
+    WritableExecutable(String),
+    EntryOutsideCode { entry: u32, section: Option<String> },
+    EmptyExecutable(String),
+    Packer { section: String, packer: &'static str },
+    HighEntropy { section: String, entropy: f64 },
+}
+
+impl fmt::Display for SectionWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SectionWarning::WritableExecutable(name) => write!(f, "{name} is writable and executable, the code can modify itself"),
+            SectionWarning::EntryOutsideCode { entry, section: Some(name) } => write!(f, "the entry point {entry:#x} is in {name}, which is not a code section"),
+            SectionWarning::EntryOutsideCode { entry, section: None } => write!(f, "the entry point {entry:#x} is outside of the sections"),
+            SectionWarning::EmptyExecutable(name) => write!(f, "{name} is executable but has no raw data, its code is written at run time"),
+            SectionWarning::Packer { section, packer } => write!(f, "{section} is a section name of {packer}"),
+            SectionWarning::HighEntropy { section, entropy } => write!(f, "{section} has an entropy of {entropy:.2}, it is likely compressed or encrypted"),
+        }
+    }
+}
+
+
+// hints that the code on disk is not the code that runs, software breakpoints set before it is unpacked are overwritten
+pub fn check_sections(sections: &[Section], entry_point: u32) -> Vec<SectionWarning> {
+    let mut result = Vec::new();
+    for section in sections {
+        if section.is_writable() && section.characteristics & IMAGE_SCN_MEM_EXECUTE != 0 {
+            result.push(SectionWarning::WritableExecutable(section.name.clone()));
+        }
+        if section.is_executable() && section.raw_size == 0 && section.virtual_size != 0 {
+            result.push(SectionWarning::EmptyExecutable(section.name.clone()));
+        }
+        if let Some((_, packer)) = PACKER_SECTIONS.iter().find(|(name, _)| section.name.eq_ignore_ascii_case(name)) {
+            result.push(SectionWarning::Packer { section: section.name.clone(), packer });
+        }
+        let entropy = section.entropy();
+        if entropy >= HIGH_ENTROPY && section.name != ".rsrc" {
+            result.push(SectionWarning::HighEntropy { section: section.name.clone(), entropy });
+        }
+    }
+    // a dll may have no entry point at all
+    if entry_point != 0 {
+        match get_section_of_rva(sections, entry_point as u64) {
+            Some(section) if section.is_executable() => {}
+            section => result.push(SectionWarning::EntryOutsideCode { entry: entry_point, section: section.map(|s| s.name.clone()) }),
+        }
+    }
+    result
+}
+
+
+fn align_up(value: u64, align: u32) -> u64 {
+    let align = align.max(1) as u64;
+    value.div_ceil(align) * align
+}
+
+
+// a long name is stored as "/<offset>" into the coff string table
+pub fn get_name(data: &[u8], str_table: u64, name_bytes: &[u8]) -> Vec<u8> {
+    let name = &name_bytes[..name_bytes.iter().position(|&b| b == 0).unwrap_or(name_bytes.len())];
+    let long_name = name.strip_prefix(b"/")
+        .and_then(|digits| std::str::from_utf8(digits).ok())
+        .and_then(|digits| digits.parse::<u64>().ok())
+        .and_then(|offset| usize::try_from(str_table.checked_add(offset)?).ok())
+        .and_then(|start| data.get(start..));
+    match long_name {
+        Some(table) if str_table != 0 => {
+            let table = &table[..table.len().min(256)];
+            table[..table.iter().position(|&b| b == 0).unwrap_or(table.len())].to_vec()
         }
+        _ => name.to_vec(),
+    }
+}
+
+
+pub fn read_section_headers(data: &[u8], offset: u64, count: u16) -> Result<Vec<IMAGE_SECTION_HEADER>, PeError> {
+    if offset + count as u64 * SECTION_HEADER_SIZE > data.len() as u64 {
+        return Err(PeError::SectionCount(count));
+    }
+    (0..count as u64).map(|i| read_struct(data, offset + i * SECTION_HEADER_SIZE, "section header")).collect()
+}
+
+
+fn mem_size(header: &IMAGE_SECTION_HEADER) -> u64 {
+    match header.VirtualSize {
+        0 => header.SizeOfRawData as u64,
+        size => size as u64,
+    }
+}
+
+
+fn process_section(data: &[u8], nt_header: NtHeaders, header: &IMAGE_SECTION_HEADER, name: String, headers_end: u64) -> Result<Section, PeError> {
+    let (file_align, _) = nt_header.alignments();
+    let mem_size = mem_size(header);
+
+    // the loader rounds the raw pointer down to 0x200 and maps no more than the virtual size
+    let raw_ptr = if file_align >= 0x200 { header.PointerToRawData as u64 & !0x1ff } else { header.PointerToRawData as u64 };
+    let mut content = vec![0u8; mem_size as usize];
+    if header.SizeOfRawData != 0 {
+        if raw_ptr + header.SizeOfRawData as u64 > data.len() as u64 {
+            return Err(PeError::Truncated { what: "raw data of a section", offset: raw_ptr, size: header.SizeOfRawData as u64 });
+        }
+        if raw_ptr < headers_end {
+            return Err(PeError::HeaderOverlap(name));
+        }
+        let raw_size = align_up(header.SizeOfRawData as u64, file_align).min(mem_size).min(data.len() as u64 - raw_ptr) as usize;
+        content[..raw_size].copy_from_slice(&data[raw_ptr as usize..raw_ptr as usize + raw_size]);
+    }
+    Ok(Section {
+        name,
+        content,
+        addr: header.VirtualAddress,
+        characteristics: header.Characteristics,
+        virtual_size: header.VirtualSize,
+        raw_size: header.SizeOfRawData,
+        raw_ptr: header.PointerToRawData,
+    })
+}
+
+
+pub fn parse_sections(data: &[u8], nt_header: NtHeaders, file_header: &IMAGE_FILE_HEADER, offset: u64) -> Result<Vec<Section>, PeError> {
+    let headers = read_section_headers(data, offset, file_header.NumberOfSections)?;
+    let headers_end = offset + headers.len() as u64 * SECTION_HEADER_SIZE;
+    let str_table = match file_header.PointerToSymbolTable {
+        0 => 0,
+        ptr => ptr as u64 + file_header.NumberOfSymbols as u64 * COFF_SYMBOL_SIZE,
+    };
+    let names = headers.iter().map(|h| String::from_utf8_lossy(&get_name(data, str_table, &h.Name)).to_string()).collect::<Vec<_>>();
+
+    // checked before anything is allocated, a hostile table could map the same range many times
+    if let Some(i) = (0..headers.len()).find(|&i| headers[i].VirtualAddress as u64 + mem_size(&headers[i]) > nt_header.size_of_image() as u64) {
+        return Err(PeError::SectionOutOfImage(names[i].clone()));
+    }
+    let mut order = (0..headers.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| headers[i].VirtualAddress);
+    for pair in order.windows(2) {
+        let (a, b) = (&headers[pair[0]], &headers[pair[1]]);
+        if a.VirtualAddress as u64 + mem_size(a) > b.VirtualAddress as u64 {
+            return Err(PeError::OverlappingSections(names[pair[0]].clone(), names[pair[1]].clone()));
+        }
+    }
+    order.into_iter()
+        .map(|i| process_section(data, nt_header, &headers[i], names[i].clone(), headers_end))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: u32 = IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE | IMAGE_SCN_MEM_READ;
+    const DATA: u32 = IMAGE_SCN_MEM_READ | IMAGE_SCN_MEM_WRITE;
+
+    fn section(name: &str, addr: u32, characteristics: u32, content: Vec<u8>) -> Section {
+        let size = content.len() as u32;
+        Section { name: name.to_string(), content, addr, characteristics, virtual_size: size, raw_size: size, raw_ptr: 0x400 }
+    }
+
+    fn random(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i as u32).wrapping_mul(2654435761).rotate_right(13) as u8).collect()
+    }
+
+    #[test]
+    fn entropy_bounds() {
+        assert_eq!(entropy(&[]), 0.0);
+        assert_eq!(entropy(&[0x90; 0x100]), 0.0);
+        assert_eq!(entropy(&(0..=255).collect::<Vec<u8>>()), 8.0);
+        assert_eq!(entropy(&[0, 1, 0, 1]), 1.0);
+    }
+
+    #[test]
+    fn clean_sections() {
+        let sections = [section(".text", 0x1000, CODE, vec![0xc3; 0x200]), section(".data", 0x2000, DATA, vec![0; 0x200])];
+        assert_eq!(check_sections(&sections, 0x1000), []);
+    }
+
+    #[test]
+    fn writable_executable_and_empty_executable() {
+        let mut bss = section(".textbss", 0x1000, CODE | IMAGE_SCN_MEM_WRITE, Vec::new());
+        bss.virtual_size = 0x1000;
+        let sections = [bss, section(".text", 0x2000, CODE, vec![0xc3; 0x200])];
+        assert_eq!(check_sections(&sections, 0x2000), [
+            SectionWarning::WritableExecutable(".textbss".to_string()),
+            SectionWarning::EmptyExecutable(".textbss".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn packer_names_ignore_case() {
+        let sections = [section("upx0", 0x1000, CODE, vec![0xc3; 0x10]), section(".MPRESS1", 0x2000, CODE, vec![0xc3; 0x10]), section(".upx", 0x3000, CODE, vec![0xc3; 0x10])];
+        assert_eq!(check_sections(&sections, 0x1000), [
+            SectionWarning::Packer { section: "upx0".to_string(), packer: "UPX" },
+            SectionWarning::Packer { section: ".MPRESS1".to_string(), packer: "MPRESS" },
+        ]);
+    }
+
+    #[test]
+    fn high_entropy_except_resources() {
+        let sections = [section(".text", 0x1000, CODE, vec![0xc3; 0x10]), section(".data", 0x2000, DATA, random(0x1000)), section(".rsrc", 0x3000, IMAGE_SCN_MEM_READ, random(0x1000))];
+        let warnings = check_sections(&sections, 0x1000);
+        assert!(matches!(&warnings[..], [SectionWarning::HighEntropy { section, entropy }] if section == ".data" && *entropy >= HIGH_ENTROPY));
+    }
+
+    #[test]
+    fn entry_outside_code() {
+        let sections = [section(".text", 0x1000, CODE, vec![0xc3; 0x100]), section(".data", 0x2000, DATA, vec![0; 0x100])];
+        assert_eq!(check_sections(&sections, 0x2010), [SectionWarning::EntryOutsideCode { entry: 0x2010, section: Some(".data".to_string()) }]);
+        assert_eq!(check_sections(&sections, 0x5000), [SectionWarning::EntryOutsideCode { entry: 0x5000, section: None }]);
+        // a dll without DllMain
+        assert_eq!(check_sections(&sections, 0), []);
     }
 }
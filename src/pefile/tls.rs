@@ -0,0 +1,112 @@
+use anyhow::anyhow;
+use crate::pefile::winnt::IMAGE_DATA_DIRECTORY;
+use crate::pefile::{read_rva, Section, MAX_ENTRIES};
+
+
+#[derive(Debug, Clone, Default)]
+pub struct Tls {
+    pub start_raw_data: u64,
+    pub end_raw_data: u64,
+    pub index_addr: u64,
+    pub callbacks_addr: u64,
+    pub zero_fill: u32,
+    pub characteristics: u32,
+    pub callbacks: Vec<u32>,
+}
+
+
+fn read_ptr(sections: &[Section], rva: u64, bitness: usize) -> Result<u64, anyhow::Error> {
+    let bytes = read_rva(sections, rva, bitness / 8).ok_or_else(|| anyhow!("rva {:#x} is outside of the sections", rva))?;
+    Ok(match bytes.len() {
+        8 => u64::from_le_bytes(bytes.try_into()?),
+        _ => u32::from_le_bytes(bytes.try_into()?) as u64,
+    })
+}
+
+
+pub fn parse_tls(sections: &[Section], tls_dir: IMAGE_DATA_DIRECTORY, bitness: usize, image_base: u64) -> Result<Option<Tls>, anyhow::Error> {
+    if tls_dir.VirtualAddress == 0 || tls_dir.Size == 0 {
+        return Ok(None);
+    }
+    let ptr_size = bitness as u64 / 8;
+    let dir = tls_dir.VirtualAddress as u64;
+    let mut tls = Tls {
+        start_raw_data: read_ptr(sections, dir, bitness)?,
+        end_raw_data: read_ptr(sections, dir + ptr_size, bitness)?,
+        index_addr: read_ptr(sections, dir + ptr_size * 2, bitness)?,
+        callbacks_addr: read_ptr(sections, dir + ptr_size * 3, bitness)?,
+        ..Default::default()
+    };
+    let fields = read_rva(sections, dir + ptr_size * 4, 8).ok_or_else(|| anyhow!("tls directory {:#x} is outside of the sections", dir))?;
+    tls.zero_fill = u32::from_le_bytes(fields[..4].try_into()?);
+    tls.characteristics = u32::from_le_bytes(fields[4..].try_into()?);
+
+    if tls.callbacks_addr != 0 {
+        let mut addr = tls.callbacks_addr.wrapping_sub(image_base);
+        loop {
+            let callback = read_ptr(sections, addr, bitness)?;
+            if callback == 0 {
+                break;
+            }
+            if tls.callbacks.len() >= MAX_ENTRIES {
+                return Err(anyhow!("the tls callback array has no end"));
+            }
+            tls.callbacks.push(callback.wrapping_sub(image_base) as u32);
+            addr += ptr_size;
+        }
+    }
+    Ok(Some(tls))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pefile::load_pe;
+
+    // a directory at 0x2000 whose callback array at 0x2040 is followed by a stray pointer after its terminator
+    fn rdata(bitness: usize, image_base: u64) -> Vec<Section> {
+        let ptr_size = bitness / 8;
+        let mut content = vec![0u8; 0x100];
+        let ptrs = [(0, 0x2080), (1, 0x2088), (2, 0x2090), (3, 0x2040), (8, 0x1000), (9, 0x1020), (11, 0x1040)];
+        for (i, rva) in ptrs {
+            let value = (image_base + rva).to_le_bytes();
+            let off = if i < 4 { i * ptr_size } else { 0x40 + (i - 8) * ptr_size };
+            content[off..off + ptr_size].copy_from_slice(&value[..ptr_size]);
+        }
+        content[ptr_size * 4 + 4..ptr_size * 4 + 8].copy_from_slice(&0x300000u32.to_le_bytes());
+        vec![Section { name: ".rdata".to_string(), content, addr: 0x2000, characteristics: 0x40000040, virtual_size: 0x100, raw_size: 0x100, raw_ptr: 0x400 }]
+    }
+
+    #[test]
+    fn tls_64() {
+        let dir = IMAGE_DATA_DIRECTORY { VirtualAddress: 0x2000, Size: 0x28 };
+        let tls = parse_tls(&rdata(64, 0x140000000), dir, 64, 0x140000000).unwrap().unwrap();
+        assert_eq!((tls.start_raw_data, tls.end_raw_data, tls.index_addr, tls.callbacks_addr), (0x140002080, 0x140002088, 0x140002090, 0x140002040));
+        assert_eq!((tls.zero_fill, tls.characteristics), (0, 0x300000));
+        assert_eq!(tls.callbacks, [0x1000, 0x1020]);
+    }
+
+    #[test]
+    fn tls_32() {
+        let dir = IMAGE_DATA_DIRECTORY { VirtualAddress: 0x2000, Size: 0x18 };
+        let tls = parse_tls(&rdata(32, 0x400000), dir, 32, 0x400000).unwrap().unwrap();
+        assert_eq!((tls.start_raw_data, tls.callbacks_addr, tls.characteristics), (0x402080, 0x402040, 0x300000));
+        assert_eq!(tls.callbacks, [0x1000, 0x1020]);
+    }
+
+    #[test]
+    fn no_directory_or_no_callbacks() {
+        let sections = rdata(64, 0x140000000);
+        assert!(parse_tls(&sections, IMAGE_DATA_DIRECTORY { VirtualAddress: 0, Size: 0 }, 64, 0x140000000).unwrap().is_none());
+        let mut sections = sections;
+        sections[0].content[0x18..0x20].fill(0);
+        assert!(parse_tls(&sections, IMAGE_DATA_DIRECTORY { VirtualAddress: 0x2000, Size: 0x28 }, 64, 0x140000000).unwrap().unwrap().callbacks.is_empty());
+    }
+
+    #[test]
+    fn callbacks_outside_of_the_sections() {
+        let (nt, sections) = load_pe(&format!("{}/fuzz/corpus/pe_parse/tls_callbacks_outside", env!("CARGO_MANIFEST_DIR"))).unwrap();
+        assert!(parse_tls(&sections, nt.data_directory(9), 64, nt.image_base()).is_err());
+    }
+}
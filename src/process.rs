@@ -59,7 +59,7 @@ pub fn get_module(h_proc: HANDLE) -> Result<Vec<MODULEENTRY32>, anyhow::Error> {
         }
         CloseHandle(mod_snap);
     }
-    if result.len() == 0 {
+    if result.is_empty() {
         return Err(anyhow!("Module not found for this process"));
     }
     Ok(result)
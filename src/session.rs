@@ -0,0 +1,104 @@
+use crate::cli::All;
+use crate::elffile::{ElfHeader, ProgramHeader};
+use crate::pefile::{NtHeaders, Section};
+use crate::pefile::import::Import;
+use crate::pefile::loadconfig::LoadConfig;
+use crate::pefile::reloc::Reloc;
+use crate::pefile::resource::Resource;
+use crate::pefile::tls::Tls;
+use crate::symbol::Symbols;
+use crate::dbg::memory::unwind::Frame;
+use crate::pefile::winnt::RUNTIME_FUNCTION;
+
+
+#[derive(Default)]
+pub struct Session {
+    pub all: All,
+    pub symbols: Symbols,
+    pub image_base: u64,
+    pub nt_header: Option<NtHeaders>,
+    pub sections: Vec<Section>,
+    pub imports: Vec<Import>,
+    pub relocs: Vec<Reloc>,
+    pub tls: Option<Tls>,
+    pub load_config: Option<LoadConfig>,
+    pub resources: Vec<Resource>,
+    pub func_info: Vec<RUNTIME_FUNCTION>,
+    pub base_addr: u64,
+    pub st_frame: Vec<Frame>,
+    pub st_len: usize,
+    pub elf_header: Option<ElfHeader>,
+    pub program_headers: Vec<ProgramHeader>,
+    pub dump_bitness: Option<usize>,
+}
+
+
+impl Session {
+    pub fn new(all: All) -> Self {
+        Session { all, ..Default::default() }
+    }
+
+    pub fn get_bitness(&self) -> usize {
+        self.dump_bitness
+            .or_else(|| self.nt_header.map(|h| h.get_bitness()))
+            .or_else(|| self.elf_header.map(|h| h.bitness))
+            .unwrap_or(64)
+    }
+
+    pub fn get_size_of_arch(&self) -> usize {
+        self.get_bitness() / 8
+    }
+
+    pub fn end_process(&mut self) {
+        for crt_func in self.all.crt_func.iter_mut() {
+            crt_func.addr = 0;
+        }
+        for iat_b in self.all.break_iat.iter_mut() {
+            let addr = iat_b.addr;
+            self.all.break_va.retain(|b| b.addr != addr);
+            iat_b.addr = 0;
+        }
+        self.base_addr = 0;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn load(name: &str, breakpoints: &[&str]) -> Session {
+        let mut session = Session::default();
+        let file = format!("file {}/fuzz/corpus/pe_parse/{name}", env!("CARGO_MANIFEST_DIR"));
+        for line in [file.as_str()].iter().chain(breakpoints) {
+            crate::handle_cmd(&mut session, &line.split_whitespace().collect::<Vec<_>>(), line);
+        }
+        session
+    }
+
+    fn breakpoints(session: &Session) -> Vec<u64> {
+        session.all.break_rva.iter().chain(&session.all.break_once).map(|b| b.addr).collect()
+    }
+
+    #[test]
+    fn sessions_are_independent() {
+        let (first, second) = thread::scope(|s| {
+            let first = s.spawn(|| {
+                let session = load("imports_pe64", &["b 0x1004", "b-import kernel32!Sleep"]);
+                (session.all.file.clone(), session.get_bitness(), session.image_base, breakpoints(&session))
+            });
+            let second = s.spawn(|| {
+                let session = load("imports_pe32", &["b 0x100a", "b 0x1000"]);
+                (session.all.file.clone(), session.get_bitness(), session.image_base, breakpoints(&session))
+            });
+            (first.join().unwrap(), second.join().unwrap())
+        });
+        assert!(first.0.unwrap().ends_with("imports_pe64"));
+        assert!(second.0.unwrap().ends_with("imports_pe32"));
+        assert_eq!((first.1, first.2), (64, 0x140000000));
+        assert_eq!((second.1, second.2), (32, 0x400000));
+        assert_eq!(first.3, [0x1004, 0x1010]);
+        assert_eq!(second.3, [0x100a, 0x1000]);
+    }
+}
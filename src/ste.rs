@@ -1,7 +1,5 @@
-use std::ptr;
-use crate::pefile::function::FUNC_INFO;
-use crate::symbol::SYMBOLS_V;
-use winapi::um::winnt::RUNTIME_FUNCTION;
+use crate::session::Session;
+use crate::pefile::winnt::RUNTIME_FUNCTION;
 use crate::ut::cast::str_to;
 
 fn get_(linev: &[&str]) -> usize {
@@ -12,28 +10,24 @@ fn get_(linev: &[&str]) -> usize {
     }
 }
 
-pub fn get_address(linev: &[&str]) -> Result<u64, String> {
+pub fn get_address(session: &Session, linev: &[&str]) -> Result<u64, String> {
     let flag = linev[get_(linev)];
     let target = *linev.last().unwrap();
     if flag == "-a" || flag == "--address" {
         str_to::<u64>(target).map_err(|e| e.to_string())
     } else {
-        unsafe {
-            if let Some(sym) = (*ptr::addr_of!(SYMBOLS_V)).symbol_file.iter().find(|s| s.name == target) {
-                if sym.offset > 0 {
-                    Ok(sym.offset as u64)
-                } else {
-                    Err("the specified symbol cannot have a negative offset".to_string())
-                }
+        if let Some(sym) = session.symbols.symbol_file.iter().find(|s| s.name == target) {
+            if sym.offset > 0 {
+                Ok(sym.offset as u64)
             } else {
-                Err(format!("unknown symbol: '{}'", target))
+                Err("the specified symbol cannot have a negative offset".to_string())
             }
+        } else {
+            Err(format!("unknown symbol: '{}'", target))
         }
     }
 }
 
-pub fn find_func_by_addr(addr: u64) -> Option<&'static RUNTIME_FUNCTION> {
-    unsafe {
-        (*ptr::addr_of_mut!(FUNC_INFO)).iter().find(|func| func.BeginAddress == addr as u32)
-    }
+pub fn find_func_by_addr(session: &Session, addr: u64) -> Option<&RUNTIME_FUNCTION> {
+    session.func_info.iter().find(|func| func.BeginAddress == addr as u32)
 }
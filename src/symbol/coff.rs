@@ -0,0 +1,233 @@
+use std::collections::HashSet;
+use std::fs;
+use anyhow::anyhow;
+use crate::pefile::winnt::{IMAGE_FILE_HEADER, IMAGE_SECTION_HEADER};
+use crate::pefile::{read_struct, section, MAX_ENTRIES};
+use crate::session::Session;
+use crate::symbol::{SymbolFile, SymbolType};
+
+const SYMBOL_SIZE: usize = 18;
+const MACHINE_I386: u16 = 0x14c;
+
+pub const SYM_CLASS_EXTERNAL: u8 = 2;
+pub const SYM_CLASS_STATIC: u8 = 3;
+pub const SYM_CLASS_LABEL: u8 = 6;
+pub const SYM_CLASS_FUNCTION: u8 = 101;
+pub const SYM_CLASS_FILE: u8 = 103;
+pub const SYM_CLASS_SECTION: u8 = 104;
+pub const SYM_CLASS_WEAK_EXTERNAL: u8 = 105;
+const SYM_DTYPE_FUNCTION: u16 = 2;
+
+
+#[derive(Debug, Clone)]
+pub struct CoffSymbol {
+    pub name: String,
+    pub value: u32,
+    pub section: i16,
+    pub sym_type: u16,
+    pub storage_class: u8,
+    pub aux: Vec<[u8; SYMBOL_SIZE]>,
+}
+
+impl CoffSymbol {
+    pub fn is_function(&self) -> bool {
+        (self.sym_type >> 4) & 3 == SYM_DTYPE_FUNCTION
+    }
+
+    // TotalSize of the function definition record that follows a function
+    pub fn function_size(&self) -> Option<u32> {
+        if !self.is_function() {
+            return None;
+        }
+        self.aux.first().map(|aux| u32::from_le_bytes([aux[4], aux[5], aux[6], aux[7]])).filter(|&size| size != 0)
+    }
+
+    // the name of a .file record is spread over its aux records
+    pub fn file_name(&self) -> String {
+        let bytes = self.aux.concat();
+        String::from_utf8_lossy(&bytes[..bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len())]).to_string()
+    }
+
+    pub fn class_str(&self) -> &'static str {
+        match self.storage_class {
+            SYM_CLASS_EXTERNAL => "external",
+            SYM_CLASS_STATIC => "static",
+            SYM_CLASS_LABEL => "label",
+            SYM_CLASS_FUNCTION => "function",
+            SYM_CLASS_FILE => "file",
+            SYM_CLASS_SECTION => "section",
+            SYM_CLASS_WEAK_EXTERNAL => "weak external",
+            _ => "other",
+        }
+    }
+}
+
+
+fn headers(data: &[u8]) -> Result<(IMAGE_FILE_HEADER, Vec<IMAGE_SECTION_HEADER>), anyhow::Error> {
+    let lfanew = read_struct::<u32>(data, 0x3c, "dos header")? as u64;
+    let file_header: IMAGE_FILE_HEADER = read_struct(data, lfanew + 4, "file header")?;
+    let section_table = lfanew + 24 + file_header.SizeOfOptionalHeader as u64;
+    Ok((file_header, section::read_section_headers(data, section_table, file_header.NumberOfSections)?))
+}
+
+
+pub fn read_coff_symbols(data: &[u8], file_header: &IMAGE_FILE_HEADER) -> Result<Vec<CoffSymbol>, anyhow::Error> {
+    let (table, count) = (file_header.PointerToSymbolTable as usize, file_header.NumberOfSymbols as usize);
+    if table == 0 || count == 0 {
+        return Ok(Vec::new());
+    }
+    if count > MAX_ENTRIES {
+        return Err(anyhow!("the symbol table has {count} entries"));
+    }
+    let records = data.get(table..table + count * SYMBOL_SIZE)
+        .ok_or_else(|| anyhow!("the symbol table of {count} entries at {:#x} is past the end of the file", table))?;
+
+    // the size at the start of the string table counts itself, so name offsets are relative to it
+    let str_start = table + count * SYMBOL_SIZE;
+    let str_table = match data.get(str_start..str_start + 4) {
+        Some(size) => &data[str_start..(str_start + u32::from_le_bytes(size.try_into()?) as usize).min(data.len())],
+        None => &[],
+    };
+    let long_name = |offset: usize| -> String {
+        let name = str_table.get(offset..).unwrap_or_default();
+        String::from_utf8_lossy(&name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())]).to_string()
+    };
+
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < count {
+        let record = &records[i * SYMBOL_SIZE..(i + 1) * SYMBOL_SIZE];
+        let name = match u32::from_le_bytes(record[0..4].try_into()?) {
+            0 => long_name(u32::from_le_bytes(record[4..8].try_into()?) as usize),
+            _ => String::from_utf8_lossy(&record[..record[..8].iter().position(|&b| b == 0).unwrap_or(8)]).to_string(),
+        };
+        let aux_count = (record[17] as usize).min(count - i - 1);
+        let aux = (1..=aux_count)
+            .map(|j| records[(i + j) * SYMBOL_SIZE..(i + j + 1) * SYMBOL_SIZE].try_into())
+            .collect::<Result<Vec<_>, _>>()?;
+        result.push(CoffSymbol {
+            name,
+            value: u32::from_le_bytes(record[8..12].try_into()?),
+            section: i16::from_le_bytes(record[12..14].try_into()?),
+            sym_type: u16::from_le_bytes(record[14..16].try_into()?),
+            storage_class: record[16],
+            aux,
+        });
+        i += 1 + aux_count;
+    }
+    Ok(result)
+}
+
+
+pub fn parse_coff(data: &[u8]) -> Result<Vec<SymbolFile>, anyhow::Error> {
+    let (file_header, section_headers) = headers(data)?;
+    let mut result = Vec::new();
+    let mut seen = HashSet::new();
+    let mut file = String::new();
+    for sym in read_coff_symbols(data, &file_header)? {
+        match sym.storage_class {
+            SYM_CLASS_FILE => {
+                file = sym.file_name();
+                continue;
+            }
+            SYM_CLASS_EXTERNAL | SYM_CLASS_STATIC | SYM_CLASS_WEAK_EXTERNAL => {}
+            _ => continue,
+        }
+        // a static with a section definition record is the section itself
+        if sym.storage_class == SYM_CLASS_STATIC && !sym.aux.is_empty() && !sym.is_function() {
+            continue;
+        }
+        // the section number is 1-based, 0 is undefined and the negative ones are absolute or debug values
+        let Some(header) = usize::try_from(sym.section).ok().filter(|&n| n > 0).and_then(|n| section_headers.get(n - 1)) else {
+            continue;
+        };
+        let name = match sym.name.strip_prefix('_') {
+            Some(name) if file_header.Machine == MACHINE_I386 => name.to_string(),
+            _ => sym.name.clone(),
+        };
+        let offset = header.VirtualAddress as i64 + sym.value as i64;
+        if name.is_empty() || !seen.insert((name.clone(), offset)) {
+            continue;
+        }
+        result.push(SymbolFile {
+            name,
+            offset,
+            size: sym.function_size().unwrap_or_default() as usize,
+            types_e: format!("{} {}", sym.class_str(), if sym.is_function() { "function" } else { "data" }),
+            filename: if sym.storage_class == SYM_CLASS_STATIC { file.clone() } else { String::new() },
+            ..Default::default()
+        });
+    }
+    Ok(result)
+}
+
+
+pub fn target_coff_symbols(session: &mut Session) -> Result<usize, anyhow::Error> {
+    let data = fs::read(session.all.file.clone().unwrap())?;
+    let symbol_file = parse_coff(&data)?;
+    let count = symbol_file.len();
+    let symbols = &mut session.symbols;
+    symbols.symbol_file.extend(symbol_file);
+    if count != 0 {
+        symbols.symbol_type = SymbolType::COFF;
+    }
+    Ok(count)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> Vec<u8> {
+        fs::read(format!("{}/fuzz/corpus/pe_parse/coff_symbols", env!("CARGO_MANIFEST_DIR"))).unwrap()
+    }
+
+    fn symbols(data: &[u8]) -> Vec<(String, i64, usize, String, String)> {
+        parse_coff(data).unwrap().into_iter().map(|s| (s.name, s.offset, s.size, s.types_e, s.filename)).collect()
+    }
+
+    fn sym(name: &str, offset: i64, size: usize, types_e: &str, filename: &str) -> (String, i64, usize, String, String) {
+        (name.to_string(), offset, size, types_e.to_string(), filename.to_string())
+    }
+
+    #[test]
+    fn amd64_symbols() {
+        assert_eq!(symbols(&fixture()), [
+            sym("helper", 0x1000, 0xc, "static function", "main.c"),
+            sym("a_very_long_function_name", 0x1004, 0, "external function", ""),
+            sym("gvar", 0x2010, 0, "external data", ""),
+        ]);
+    }
+
+    #[test]
+    fn i386_symbols_lose_their_underscore() {
+        let mut data = fixture();
+        let lfanew = u32::from_le_bytes(data[0x3c..0x40].try_into().unwrap()) as usize;
+        data[lfanew + 4..lfanew + 6].copy_from_slice(&MACHINE_I386.to_le_bytes());
+        let table = headers(&data).unwrap().0.PointerToSymbolTable as usize;
+        let record = |i: usize| table + i * SYMBOL_SIZE;
+        data[record(4)..record(4) + 8].copy_from_slice(b"_helper\0");
+        data[record(7)..record(7) + 8].copy_from_slice(b"_gvar\0\0\0");
+        data[record(9) + 12..record(9) + 14].copy_from_slice(&(-1i16).to_le_bytes());
+        assert_eq!(symbols(&data), [
+            sym("helper", 0x1000, 0xc, "static function", "main.c"),
+            sym("a_very_long_function_name", 0x1004, 0, "external function", ""),
+            sym("gvar", 0x2010, 0, "external data", ""),
+        ]);
+    }
+
+    #[test]
+    fn raw_records() {
+        let data = fixture();
+        let (file_header, _) = headers(&data).unwrap();
+        let records = read_coff_symbols(&data, &file_header).unwrap();
+        let names: Vec<&str> = records.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, [".file", ".text", "helper", "a_very_long_function_name", "gvar", "undef", "badsect"]);
+        assert_eq!(records[0].file_name(), "main.c");
+        assert_eq!((records[1].storage_class, records[1].aux.len()), (SYM_CLASS_STATIC, 1));
+        assert_eq!(records[2].function_size(), Some(0xc));
+        assert_eq!(records[4].function_size(), None);
+        assert_eq!(records[5].section, 0);
+    }
+}
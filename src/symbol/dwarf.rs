@@ -1,11 +1,23 @@
 use crate::pefile::Section;
-use crate::symbol::{SymbolFile, SymbolType, IMAGE_BASE, SYMBOLS_V};
+use crate::session::Session;
+use crate::symbol::{SymbolFile, SymbolType};
 use anyhow::Error;
 use gimli::{AttributeValue, Dwarf, DwarfSections, EndianSlice, Reader, RunTimeEndian, SectionId};
 use std::borrow::Cow;
-use std::{io, ptr};
+use std::io;
 
-pub fn target_dwarf_info(sections: &[Section]) -> Result<(), Error> {
+pub fn target_dwarf_info(session: &mut Session) -> Result<(), Error> {
+    let symbol_file = parse_dwarf(&session.sections, session.image_base)?;
+    let symbols = &mut session.symbols;
+    symbols.symbol_file.extend(symbol_file);
+    if !symbols.symbol_file.is_empty() {
+        symbols.symbol_type = SymbolType::DWARF
+    }
+    Ok(())
+}
+
+pub fn parse_dwarf(sections: &[Section], image_base: u64) -> Result<Vec<SymbolFile>, Error> {
+    let mut symbol_file = Vec::new();
     let load_section = |id: SectionId| -> Result<Cow<[u8]>, io::Error> {
         sections.iter().find(|section| section.name == id.name()).map_or(Ok(Cow::Borrowed(&[])), |section| {
                 Ok(Cow::Borrowed(&section.content))
@@ -24,34 +36,29 @@ pub fn target_dwarf_info(sections: &[Section]) -> Result<(), Error> {
             if entry.tag() == gimli::DW_TAG_subprogram || entry.tag() == gimli::DW_TAG_variable{
                 symbol_info.types_e = entry.tag().to_string();
                 while let Some(attr) = attrs.next()? {
-                    process_attribute(&attr, &dwarf, &unit, &mut symbol_info)?;
+                    process_attribute(&attr, &dwarf, &unit, &mut symbol_info, image_base)?;
                 }
-                if symbol_info.offset != 0 && symbol_info.name != "" {
-                    unsafe { (*ptr::addr_of_mut!(SYMBOLS_V)).symbol_file.push(symbol_info) }
+                if symbol_info.offset != 0 && !symbol_info.name.is_empty() {
+                    symbol_file.push(symbol_info)
                 }
             }
         }
     }
-    unsafe {
-        if !(*ptr::addr_of!(SYMBOLS_V)).symbol_file.is_empty() {
-            (*ptr::addr_of_mut!(SYMBOLS_V)).symbol_type = SymbolType::DWARF
-        }
-    }
-    Ok(())
+    Ok(symbol_file)
 }
 
-fn process_attribute<'a>(attr: &gimli::Attribute<EndianSlice<'a, RunTimeEndian>>, dwarf: &Dwarf<EndianSlice<RunTimeEndian>>, unit: &gimli::Unit<EndianSlice<'a, RunTimeEndian>>, symbol_info: &mut SymbolFile) -> Result<(), Error> {
+fn process_attribute<'a>(attr: &gimli::Attribute<EndianSlice<'a, RunTimeEndian>>, dwarf: &Dwarf<EndianSlice<RunTimeEndian>>, unit: &gimli::Unit<EndianSlice<'a, RunTimeEndian>>, symbol_info: &mut SymbolFile, image_base: u64) -> Result<(), Error> {
     match attr.value() {
         AttributeValue::Exprloc(ref data) => {
             if let AttributeValue::Exprloc(_) = attr.raw_value() {
                 symbol_info.size = data.0.len();
                 symbol_info.value_str = format!("{:x?}", data.0.to_vec());
             }
-            dump_exprloc(unit.encoding(), data, symbol_info)?;
+            dump_exprloc(unit.encoding(), data, symbol_info, image_base)?;
         }
         AttributeValue::Addr(addr) => {
-            symbol_info.offset = if addr > unsafe { IMAGE_BASE } {
-                (addr - unsafe { IMAGE_BASE }) as i64
+            symbol_info.offset = if addr > image_base {
+                (addr - image_base) as i64
             } else {
                 addr as i64
             };
@@ -96,12 +103,12 @@ fn dump_file_index<R: Reader>(file_index: u64, unit: &gimli::Unit<R>, dwarf: &Dw
     Ok(())
 }
 
-fn dump_exprloc<'a>(encoding: gimli::Encoding, data: &gimli::Expression<EndianSlice<'a, RunTimeEndian>>, symbol: &mut SymbolFile) -> Result<(), Error> {
-    let mut pc = data.0.clone();
+fn dump_exprloc<'a>(encoding: gimli::Encoding, data: &gimli::Expression<EndianSlice<'a, RunTimeEndian>>, symbol: &mut SymbolFile, image_base: u64) -> Result<(), Error> {
+    let mut pc = data.0;
     while !pc.is_empty() {
-        let pc_clone = pc.clone();
+        let pc_clone = pc;
         if let Ok(op) = gimli::Operation::parse(&mut pc, encoding) {
-            dump_op(encoding, pc_clone, op, symbol)?;
+            dump_op(encoding, pc_clone, op, symbol, image_base)?;
         } else {
             return Ok(());
         }
@@ -109,14 +116,13 @@ fn dump_exprloc<'a>(encoding: gimli::Encoding, data: &gimli::Expression<EndianSl
     Ok(())
 }
 
-fn dump_op<'a>(encoding: gimli::Encoding, mut pc: EndianSlice<'a, RunTimeEndian>, op: gimli::Operation<EndianSlice<'a, RunTimeEndian>>, symbol: &mut SymbolFile) -> Result<(), Error> {
+fn dump_op<'a>(encoding: gimli::Encoding, mut pc: EndianSlice<'a, RunTimeEndian>, op: gimli::Operation<EndianSlice<'a, RunTimeEndian>>, symbol: &mut SymbolFile, image_base: u64) -> Result<(), Error> {
     let wop = gimli::DwOp(pc.read_u8()?);
     match op {
-        gimli::Operation::Deref { size, .. } => {
-            if wop == gimli::DW_OP_deref_size || wop == gimli::DW_OP_xderef_size {
+        gimli::Operation::Deref { size, .. }
+            if (wop == gimli::DW_OP_deref_size || wop == gimli::DW_OP_xderef_size) => {
                 symbol.size = size as usize;
             }
-        }
         gimli::Operation::ImplicitValue { data } => {
             let data = data.to_slice()?;
             symbol.value_str = format!("{:x?}", data.to_vec());
@@ -125,11 +131,11 @@ fn dump_op<'a>(encoding: gimli::Encoding, mut pc: EndianSlice<'a, RunTimeEndian>
             symbol.value_str = format!("{:#x}", value.0)
         }
         gimli::Operation::EntryValue { expression } => {
-            dump_exprloc(encoding, &gimli::Expression(expression), symbol)?
+            dump_exprloc(encoding, &gimli::Expression(expression), symbol, image_base)?
         }
-        gimli::Operation::Address { address } => unsafe {
-            symbol.offset = if address >= IMAGE_BASE {
-                (address - IMAGE_BASE) as i64
+        gimli::Operation::Address { address } => {
+            symbol.offset = if address >= image_base {
+                (address - image_base) as i64
             } else {
                 address as i64
             }
@@ -1,23 +1,32 @@
-mod dwarf;
+pub mod coff;
+pub mod dwarf;
 pub mod pdb;
-use crate::dbg::{memory, RealAddr, BASE_ADDR};
-use crate::{pefile, ALL_ELM};
+use crate::dbg::{RealAddr, CONTEXT};
+use crate::session::Session;
 use std::cmp::PartialEq;
-use std::{fmt, io, mem, ptr};
+use std::fmt;
 use std::fmt::Formatter;
+#[cfg(windows)]
+use std::{io, mem, ptr};
+#[cfg(windows)]
 use anyhow::anyhow;
-use once_cell::sync::Lazy;
+#[cfg(windows)]
 use winapi::shared::minwindef::BOOL;
-use winapi::shared::ntdef::HANDLE;
-use winapi::um::winnt::{CONTEXT, WOW64_CONTEXT};
+#[cfg(windows)]
+use crate::dbg::{memory, HANDLE, WOW64_CONTEXT};
+#[cfg(windows)]
 use crate::dllib::Dll;
-use crate::pefile::{NtHeaders, NT_HEADER};
+#[cfg(windows)]
+use crate::pefile::NtHeaders;
 use crate::ut::fmt::*;
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Default)]
 pub enum SymbolType {
     DWARF,
     PDB,
+    COFF,
+    #[default]
     Un,
 }
 
@@ -27,30 +36,23 @@ impl fmt::Display for SymbolType {
             SymbolType::Un => write!(f, "UNKNOW"),
             SymbolType::DWARF => write!(f, "DWARF"),
             SymbolType::PDB => write!(f, "PDB"),
+            SymbolType::COFF => write!(f, "COFF"),
         }
     }
 }
 
-impl Default for SymbolType {
-    fn default() -> Self {
-        SymbolType::Un
-    }
-}
 
 
 
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Default)]
 pub enum SymType {
+    #[default]
     Global,
     Local,
     // flemme pr les autres en v2v
 }
 
-impl Default for SymType {
-    fn default() -> Self {
-        SymType::Global
-    }
-}
 
 
 #[derive(Debug, Default, Clone, Eq, PartialEq)]
@@ -68,18 +70,17 @@ pub struct SymbolFile {
 
 
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Default)]
 pub enum SrcFile {
     Dll(u64),
+    #[default]
     Ex,
 }
 
 
 impl SrcFile {
     pub fn is_dll(&self) -> bool {
-        match self {
-            SrcFile::Dll(_) => true,
-            _ => false
-        }
+        matches!(self, SrcFile::Dll(_))
     }
     
     pub fn dll_base(&self) -> u64 {
@@ -90,16 +91,11 @@ impl SrcFile {
     }
 }
 
-impl Default for SrcFile {
-    fn default() -> Self {
-        SrcFile::Ex
-    }
-}
 
 
 impl SymbolFile {
-    pub fn is_in_sym(&self, addr: u64, ctx: *const CONTEXT) -> bool {
-        let start_addr = self.real_addr(ctx);
+    pub fn is_in_sym(&self, session: &Session, addr: u64, ctx: *const CONTEXT) -> bool {
+        let start_addr = self.real_addr(session, ctx);
         let end_addr = start_addr + self.size as u64;
         addr >= start_addr && addr <= end_addr
     }
@@ -112,7 +108,7 @@ impl SymbolFile {
         }
     }
     
-     pub fn addr_ot(&self, ctx: *const CONTEXT) -> u64 {
+     pub fn addr_ot(&self, session: &Session, ctx: *const CONTEXT) -> u64 {
          if ctx.is_null() {
              if self.offset > 0 {
                  self.offset as u64
@@ -120,46 +116,43 @@ impl SymbolFile {
                  0
              }
          }else {
-             self.real_addr(ctx)
+             self.real_addr(session, ctx)
          }
      }
+
+    fn image_addr(&self, session: &Session) -> u64 {
+        match self.symbol_type {
+            SymType::Global if self.src_file.is_dll() => self.src_file.dll_base() + self.offset as u64,
+            SymType::Global => session.base_addr + self.offset as u64,
+            SymType::Local => self.offset as u64,
+        }
+    }
 }
 
 impl RealAddr for SymbolFile {
-    fn real_addr64(&self, ctx: CONTEXT) -> u64 {
-        if self.offset < 0 && unsafe { (*&raw const SYMBOLS_V).symbol_type == SymbolType::DWARF }{
-            unsafe {
-                if let Some(b_frame) = memory::stack::get_frame_before_func(ctx.Rip) {
-                    (b_frame.AddrStack.Offset as i64 + self.offset) as u64
-                } else {
-                    print_lg(LevelPrint::Error, "failed to get last frame before current frame".to_string());
-                    0
-                }
+    #[cfg(windows)]
+    fn real_addr64(&self, session: &Session, ctx: CONTEXT) -> u64 {
+        if self.offset < 0 && session.symbols.symbol_type == SymbolType::DWARF {
+            if let Some(b_frame) = memory::stack::get_frame_before_func(session, ctx.Rip) {
+                (b_frame.stack as i64 + self.offset) as u64
+            } else {
+                print_lg(LevelPrint::Error, "failed to get last frame before current frame".to_string());
+                0
             }
         }else {
-            match self.symbol_type {
-                SymType::Global => {
-                    if self.src_file.is_dll(){
-                        self.src_file.dll_base() + self.offset as u64
-                    } else {
-                        unsafe { BASE_ADDR + self.offset as u64 }
-                    }
-                }
-                SymType::Local => self.offset as u64
-            }
+            self.image_addr(session)
         }
     }
 
 
-    fn real_addr32(&self, ctx: WOW64_CONTEXT) -> u32 {
-        if self.offset < 0 && unsafe { (*&raw const SYMBOLS_V).symbol_type == SymbolType::DWARF }{
-            unsafe {
-                if let Some(b_frame) = memory::stack::get_frame_before_func(ctx.Eip as u64) {
-                    (b_frame.AddrStack.Offset as i64 + self.offset) as u32
-                } else {
-                    print_lg(LevelPrint::Error, "failed to get last frame before current frame".to_string());
-                    0
-                }
+    #[cfg(windows)]
+    fn real_addr32(&self, session: &Session, ctx: WOW64_CONTEXT) -> u32 {
+        if self.offset < 0 && session.symbols.symbol_type == SymbolType::DWARF {
+            if let Some(b_frame) = memory::stack::get_frame_before_func(session, ctx.Eip as u64) {
+                (b_frame.stack as i64 + self.offset) as u32
+            } else {
+                print_lg(LevelPrint::Error, "failed to get last frame before current frame".to_string());
+                0
             }
         }else {
             match self.symbol_type {
@@ -167,7 +160,7 @@ impl RealAddr for SymbolFile {
                     if self.src_file.is_dll(){
                         (self.src_file.dll_base() + self.offset as u64) as u32
                     } else {
-                        unsafe { BASE_ADDR as u32 + self.offset as u32 }
+                        session.base_addr as u32 + self.offset as u32
                     }
                 }
                 SymType::Local => self.offset as u32
@@ -176,11 +169,12 @@ impl RealAddr for SymbolFile {
     }
 
 
-    fn real_addr(&self, ctx: *const CONTEXT) -> u64 {
+    #[cfg(windows)]
+    fn real_addr(&self, session: &Session, ctx: *const CONTEXT) -> u64 {
         unsafe {
-            match NT_HEADER {
-                Some(NtHeaders::Headers32(_)) => self.real_addr32(*(ctx as *const WOW64_CONTEXT)) as u64,
-                Some(NtHeaders::Headers64(_)) => self.real_addr64(*ctx),
+            match session.nt_header {
+                Some(NtHeaders::Headers32(_)) => self.real_addr32(session, *(ctx as *const WOW64_CONTEXT)) as u64,
+                Some(NtHeaders::Headers64(_)) => self.real_addr64(session, *ctx),
                 None => {
                     print_lg(LevelPrint::Error, "file is not loaded");
                     0
@@ -188,6 +182,16 @@ impl RealAddr for SymbolFile {
             }
         }
     }
+
+    // the locals of a dwarf frame need the stack of a windows thread
+    #[cfg(not(windows))]
+    fn real_addr(&self, session: &Session, _ctx: *const CONTEXT) -> u64 {
+        if self.offset < 0 && session.symbols.symbol_type == SymbolType::DWARF {
+            print_lg(LevelPrint::Error, "failed to get last frame before current frame".to_string());
+            return 0;
+        }
+        self.image_addr(session)
+    }
 }
 
 #[derive(Debug, Default, Eq, PartialEq)]
@@ -198,48 +202,48 @@ pub struct Symbols {
 
 
 
-pub static mut SYMBOLS_V: Lazy<Symbols> = Lazy::new(|| Symbols::default());
-pub static mut IMAGE_BASE: u64 = 0;
-
-
-
-pub fn load_symbol(linev: &[&str], line: &str) {
-    unsafe {
-        let psym = ptr::addr_of!(SYMBOLS_V);
-        if (*ptr::addr_of!(ALL_ELM)).file.is_none() {
-            print_lg(LevelPrint::ErrorO, "you must first specify a file");
+pub fn load_symbol(session: &mut Session, linev: &[&str], line: &str) {
+    if session.all.file.is_none() {
+        print_lg(LevelPrint::ErrorO, "you must first specify a file");
+        return;
+    }
+    if linev.len() > 1 && linev[0] == "s" || linev[0] == "symbol" {
+        let path1 = line[linev[0].len()+1..].trim();
+        let path = path1.replace("\"", "");
+        if let Err(e) = pdb::from_pdb_file(session, &path) {
+            print_lg(LevelPrint::ErrorO, format!("error for loading symbols from pdb file : {e}"));
+        }else if session.symbols.symbol_type == SymbolType::PDB{
+            session.all.pdb_path = Some(path);
+        }
+    }else {
+        if let Err(e) = dwarf::target_dwarf_info(session) {
+            print_lg(LevelPrint::ErrorO, format!("Error target symbol dwarf: {e}"));
             return;
         }
-        if linev.len() > 1 && linev[0] == "s" || linev[0] == "symbol" {
-            let path1 = line[linev[0].len()+1..].trim();
-            let path = path1.replace("\"", "");
-            if let Err(e) = pdb::from_pdb_file(&path) {
-                print_lg(LevelPrint::ErrorO, format!("error for loading symbols from pdb file : {e}"));
-            }else if (*psym).symbol_type == SymbolType::PDB{
-                ALL_ELM.pdb_path = Some(path);
-            }
-        }else {
-            if let Err(e) = dwarf::target_dwarf_info(&*pefile::section::SECTION_VS) {
-                print_lg(LevelPrint::ErrorO, format!("Error target symbol dwarf: {e}"));
-                return;
+        if session.elf_header.is_none() {
+            unsafe { pdb::target_symbol(session) };
+            if session.symbols.symbol_type == SymbolType::Un {
+                if let Err(e) = coff::target_coff_symbols(session) {
+                    print_lg(LevelPrint::ErrorO, format!("Error target coff symbol table: {e}"));
+                }
             }
-            pdb::target_symbol();
-        }
-        
-        if (*psym).symbol_type != SymbolType::Un {
-            print_lg(LevelPrint::DebugO, format!("the symbol file was loaded with success\nsymbol type: {}", (*psym).symbol_type));
-        } else {
-            print_lg(LevelPrint::ErrorO, "the file does not contain a supported symbol format");
         }
     }
+
+    if session.symbols.symbol_type != SymbolType::Un {
+        print_lg(LevelPrint::DebugO, format!("the symbol file was loaded with success\nsymbol type: {}", session.symbols.symbol_type));
+    } else {
+        print_lg(LevelPrint::ErrorO, "the file does not contain a supported symbol format");
+    }
 }
 
 
-pub unsafe fn sym_init(h_proc: HANDLE) -> Result<(), anyhow::Error>{
+#[cfg(windows)]
+pub(crate) unsafe fn sym_init(session: &Session, h_proc: HANDLE) -> Result<(), anyhow::Error>{
     let symbol_pe = Dll::new("symbol_pe.dll")?;
     let sym_init: unsafe extern "C" fn(HANDLE, *const u8, u64) -> BOOL = mem::transmute(symbol_pe.get_func("sym_init")?);
-    let pdb_path = (*&raw const ALL_ELM).pdb_path.clone().map(|f|f.as_ptr()).unwrap_or(ptr::null());
-    let base_addr = if BASE_ADDR != 0 {BASE_ADDR} else {IMAGE_BASE};
+    let pdb_path = session.all.pdb_path.clone().map(|f|f.as_ptr()).unwrap_or(ptr::null());
+    let base_addr = if session.base_addr != 0 {session.base_addr} else {session.image_base};
     if sym_init(h_proc, pdb_path, base_addr) == 0 {
         return Err(anyhow!("failed to init symbol : {}", io::Error::last_os_error()));
     }
@@ -1,8 +1,8 @@
-use crate::ALL_ELM;
 use std::ffi::{c_char, CStr};
-use std::{mem, ptr};
-use crate::dllib::Dll;
-use crate::symbol::{SymbolFile, SymbolType, IMAGE_BASE, SYMBOLS_V};
+use std::mem;
+use crate::dllib::{Dll, FARPROC};
+use crate::session::Session;
+use crate::symbol::{SymbolFile, SymbolType, Symbols};
 use crate::ut::fmt::{print_lg, LevelPrint};
 
 
@@ -20,12 +20,12 @@ pub struct SymbolsPdb {
     pub line: u32,
 }
 
-pub unsafe fn target_symbol() {
+pub(crate) unsafe fn target_symbol(session: &mut Session) {
     match Dll::new("symbol_pe.dll") {
         Ok(dll) => {
-            let get_symbol: unsafe extern "C" fn (&mut usize, *const u8) -> *mut SymbolsPdb = {
+            let get_symbol = {
                 match dll.get_func("getSymbols") {
-                    Ok(func) => mem::transmute(func),
+                    Ok(func) => mem::transmute::<FARPROC, unsafe extern "C" fn(&mut usize, *const u8) -> *mut SymbolsPdb>(func),
                     Err(e) => {
                         print_lg(LevelPrint::ErrorO, e);
                         return;
@@ -34,27 +34,27 @@ pub unsafe fn target_symbol() {
             };
 
             let mut len = 0;
-            let file = (*ptr::addr_of_mut!(ALL_ELM)).file.clone().unwrap();
+            let file = session.all.file.clone().unwrap();
             let res = get_symbol(&mut len, format!("{file}\0").as_ptr());
             if len != 0 {
-                SYMBOLS_V.symbol_type = SymbolType::PDB;
+                session.symbols.symbol_type = SymbolType::PDB;
             } else {
                 return;
             }
             let symv = std::slice::from_raw_parts(res, len);
-            let get_tag_str: unsafe extern "C" fn(u32) -> *const u8 = {
+            let get_tag_str = {
                 match dll.get_func("GetTagString") {
-                    Ok(func) => mem::transmute(func),
+                    Ok(func) => mem::transmute::<FARPROC, unsafe extern "C" fn(u32) -> *const u8>(func),
                     Err(e) => {
                         print_lg(LevelPrint::ErrorO, e);
                         return
                     },
                 }
             };
-            push_pdb(symv, get_tag_str);
-            let free_symbols: unsafe extern "C" fn(*mut SymbolsPdb, usize) = {
+            push_pdb(&mut session.symbols, session.image_base, symv, get_tag_str);
+            let free_symbols = {
                 match dll.get_func("freeSymbols") {
-                    Ok(func) => mem::transmute(func),
+                    Ok(func) => mem::transmute::<FARPROC, unsafe extern "C" fn(*mut SymbolsPdb, usize)>(func),
                     Err(e) => {
                         print_lg(LevelPrint::ErrorO, e);
                         return
@@ -69,12 +69,12 @@ pub unsafe fn target_symbol() {
 
 
 
-fn push_pdb(sym: &[SymbolsPdb], get_tag_str: unsafe extern "C" fn(u32) -> *const u8) {
+fn push_pdb(symbols: &mut Symbols, image_base: u64, sym: &[SymbolsPdb], get_tag_str: unsafe extern "C" fn(u32) -> *const u8) {
     unsafe {
         for sym in sym {
             let mut sym_e = SymbolFile::default();
-            if IMAGE_BASE < sym.address {
-                sym_e.offset = (sym.address - IMAGE_BASE) as i64;
+            if image_base < sym.address {
+                sym_e.offset = (sym.address - image_base) as i64;
             } else {
                 sym_e.offset = sym.address as i64;
             }
@@ -83,27 +83,27 @@ fn push_pdb(sym: &[SymbolsPdb], get_tag_str: unsafe extern "C" fn(u32) -> *const
             sym_e.name = CStr::from_ptr(sym.name).to_string_lossy().to_string();
             sym_e.filename = CStr::from_ptr(sym.filename).to_string_lossy().to_string();
             sym_e.line = sym.line as usize;
-            (*ptr::addr_of_mut!(SYMBOLS_V.symbol_file)).push(sym_e);
+            symbols.symbol_file.push(sym_e);
         }
     }
 }
 
 
-pub fn from_pdb_file(pdb_file: &str) -> Result<(), anyhow::Error>{
+pub fn from_pdb_file(session: &mut Session, pdb_file: &str) -> Result<(), anyhow::Error>{
     unsafe {
         let symbol_pe = Dll::new("symbol_pe.dll")?;
         let get_pdb_sym: unsafe extern "C" fn(*const u8, &mut usize, u64) -> *mut SymbolsPdb = mem::transmute(symbol_pe.get_func("GetPdbSym")?);
 
         let mut len = 0;
-        let res = get_pdb_sym(pdb_file.as_ptr(), &mut len, IMAGE_BASE);
+        let res = get_pdb_sym(pdb_file.as_ptr(), &mut len, session.image_base);
         if len != 0 {
-            SYMBOLS_V.symbol_type = SymbolType::PDB;
+            session.symbols.symbol_type = SymbolType::PDB;
         } else {
             return Ok(());
         }
         let symv = std::slice::from_raw_parts(res, len);
         let get_tag_str: unsafe extern "C" fn(u32) -> *const u8 = mem::transmute(symbol_pe.get_func("GetTagString")?);
-        push_pdb(symv, get_tag_str);
+        push_pdb(&mut session.symbols, session.image_base, symv, get_tag_str);
         let free_symbols: unsafe extern "C" fn(*mut SymbolsPdb, usize) = mem::transmute(symbol_pe.get_func("freeSymbols")?);
         free_symbols(res, len);
     }
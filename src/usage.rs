@@ -34,21 +34,19 @@ Options:
     all-register, all-reg      - Display all general-purpose registers (rax, rbx, rcx, etc..)
     all-segment, all-seg       - Display all segment registers (cs, ds, es, fs, gs, ss)
     all-vector, all-vec        - Display all vector registers (xmm0, xmm1, xmm2, etc..)
+    all-debug, all-dbg         - Display the debug registers (dr0-dr3, dr6, dr7)
     all                        - Display all elements
     <element>                  - Display the specified element
 
-on x64, you can display individual element by specifying their names:
-    rax, rbx, rcx, rdx, rsi, rdi, rbp, rsp, rip, r8, r9, r10, r11, r12, r13, r14, r15
-    cs, ds, es, fs, gs, ss
-    lbfrip, lbtrip, flag
-    xmm0, xmm1, xmm2, xmm3, xmm4, xmm5, xmm6, xmm7, xmm8, xmm9, xmm10, xmm11, xmm12, xmm13, xmm14, xmm15
+you can display individual element by specifying their names, sub-registers included:
+    x64 : rax, rbx, rcx, rdx, rsi, rdi, rbp, rsp, rip, r8, r9, r10, r11, r12, r13, r14, r15
+    x32 : eax, ebx, ecx, edx, esi, edi, ebp, esp, eip
+    eax, ax, al, ah, r8d, r8w, r8b, etc..
+    cs, ds, es, fs, gs, ss (or segcs, segds, ...)
+    flag, eflags
+    xmm0 to xmm15 (xmm0 to xmm7 on x32)
     mxcsr
-
-for x32, it is :
-    eax, ebx, ecx, edx, esi, edi, ebp, esp, eip
-    cs, segcs, ds, segds, es, seges, fs, segfs, gs, seggs, ss, segss
-    flag, eflag
-    ctrl-word, control-word, status-word, tag-word, err-offset, error-offset, err-select, error-selector, data-offset, data-selector, data-select
+    dr0, dr1, dr2, dr3, dr6, dr7
 
 Examples:
     value all-reg        # Display all general-purpose registers
@@ -92,7 +90,10 @@ Description:
 Arguments:
     <register>      The name of the register to modify. Supported registers include:
                     - General Purpose Registers: rax, rbx, rcx, rdx, rsi, rdi, rsp, rbp, rip, r8, r9, r10, r11, r12, r13, r14, r15
-                    - SIMD Registers: xmm0, xmm1, xmm2, xmm3, xmm4, xmm5, xmm6, xmm7, xmm8, xmm9, xmm10, xmm11, xmm12, xmm13, xmm14, xmm15
+                      on x32: eax, ebx, ecx, edx, esi, edi, esp, ebp, eip
+                      and their sub-registers: eax, ax, al, ah, r8d, r8w, r8b, etc..
+                    - Segment Registers: cs, ds, es, fs, gs, ss
+                    - SIMD Registers: xmm0 to xmm15 (xmm0 to xmm7 on x32), mxcsr
                     - Flags Register: flag (Rflags)
 
     <value>         The new value to set for the specified register. Must be a valid numeric value
@@ -145,7 +146,15 @@ Options:
     hook-func, hook, h          Display all hooks that have been defined
     def <elm>                   Show a defined element, specify the element type with \"<elm>\" (if it is a function, a var, etc.) and its name
     function, func, f           Display all function entry
-    section, sec                Displays sections informations
+    section, sec                Displays the sections (sizes, file offset, characteristics, entropy) and warns about packed or self-modifying code
+    headers, hdr [part]         Displays the dos, file and optional headers with their decoded flags, the data directories, the rich header and the debug directory (only one with dos, file, optional, dirs, rich or debug)
+    loadconfig, lc [table]      Displays the load config directory (security cookie, safe seh, control flow guard), with seh, cf, iat, longjmp or ehcont the entries of that table
+    unwind <rva>                Decodes the x64 unwind info (and its chained entries) of the function containing <rva>
+    tls                         Displays the tls directory and its callbacks
+    resources, res [sel]        Displays the resource tree, with <type>[/<name>[/<lang>]] the version info, manifests and string tables are decoded
+    relocs [start[-end]]        Displays the base relocations (of the page of <start>, or between <start> and <end> rva)
+    imports, imp [--delay] [dll] Displays the imported functions with their IAT slot (only those of <dll> if specified, the delay-loaded ones with --delay)
+    segment, seg                Displays the program headers of an elf file
     hmodule, module, m, proc    displays all modules loaded by the process (for info on a target module type \"info module <name>\" and to see the module functions type:\"info module <name> function\")
     thread, th                  Displays thread information for the process being debugged
     \x1b[0m";
@@ -417,6 +426,7 @@ Examples:
 Notes:
    - Ensure the specified PDB file exists and is accessible.
    - If no file path is provided, the command will attempt to load the default symbol configuration
+   - Without a path the DWARF info is tried first, then the pdb of the file, then the COFF symbol table (MinGW/GCC builds)
 \x1b[0m";
 
 
@@ -435,6 +445,126 @@ Note:
   If you specify the command with the process name, be sure to put quotes '\"'
 \x1b[0m";
 
+pub const USAGE_TARGET: &str = "\x1b[32mUSAGE: target remote <host:port>
+
+Description:
+   Connects to a gdb remote stub (gdbserver, qemu -s, an emulator...) and stops on the current thread.
+   The breakpoints (b, b-va) and watchpoints (w) defined before are sent to the stub, the breakpoints rva are relative to the text offset reported by the stub.
+   At the stop prompt you can use reg, deref, set, disasm, backtrace, find, b, b-va, si (single step), dbg-thread, info thread and c.
+   \"q\" detaches from the stub.
+
+Examples:
+   target remote localhost:1234
+   target remote 192.168.1.20:2345
+\x1b[0m";
+
+pub const USAGE_GDBSERVER: &str = "\x1b[32mUSAGE: gdbserver <[host]:port>
+
+Description:
+   Starts the file with its arguments and waits for a gdb remote client (gdb, IDA, Ghidra, radare2...) on the address.
+   The client drives the process with the breakpoints, watchpoints and memory access of LisaDbg, the breakpoints (b, b-va) and watchpoints (w) defined before are already set.
+   Only one client is served, the session ends when it detaches, kills the process or when the process exits.
+   The interruption of a running process from the client (Ctrl-C) is not supported.
+   Without a host the server only listens on 127.0.0.1, give the address of an interface (or 0.0.0.0) to accept remote clients.
+
+Examples:
+   gdbserver :1234
+   gdbserver 0.0.0.0:2345
+   then from gdb : target remote localhost:1234
+\x1b[0m";
+
+pub const USAGE_LOAD_DUMP: &str = "\x1b[32mUSAGE: load-dump <path>
+
+Description:
+   Opens a Windows minidump (.dmp) and drops into a read-only session on the thread that raised the exception.
+   The registers, memory, modules and threads recorded in the dump can be inspected with reg, deref, disasm, backtrace, mem-info, find, info module, info thread and info exception.
+   Use \"dbg-thread <tid>\" to switch to another thread and \"q\" to leave the dump.
+
+Examples:
+   load-dump crash.dmp
+   load-dump \"C:\\dumps\\app crash.dmp\"
+\x1b[0m";
+
+pub const USAGE_LOAD_CORE: &str = "\x1b[32mUSAGE: load-core <path>
+
+Description:
+   Opens a Linux elf core file and drops into a read-only session on the thread that received the signal.
+   Load the crashed program first with \"file <path>\" (or --file) to get its symbols and the code that the core does not contain.
+   The registers, memory and threads recorded in the core can be inspected with reg, deref, disasm, backtrace, mem-info, find, info module, info thread and info signal.
+   Use \"dbg-thread <tid>\" to switch to another thread and \"q\" to leave the core.
+
+Examples:
+   load-core core.1234
+   LisaDbg --core core.1234 --file ./prog
+\x1b[0m";
+
+pub const USAGE_RECORD: &str = "\x1b[32mUSAGE: record <path|off>
+
+Description:
+   Records the next run in a trace file : every debug event, register context and memory read served during the session.
+   While recording, the stop prompt is the one of the replay : it offers reg, deref, set, disasm, backtrace, mem-info and find so that everything they read is in the trace.
+   The other commands of the stop prompt (b, remove, ret, skip, hook, w, sym-local, dbg-thread...) are not available during a recorded run.
+   The trace can be replayed on any machine with \"replay <path>\" (or --replay), no process is started.
+   \"record off\" disables the recording.
+
+Examples:
+   record crash.trace
+   LisaDbg app.exe --exec \"record crash.trace\" --exec run
+\x1b[0m";
+
+pub const USAGE_REPLAY: &str = "\x1b[32mUSAGE: replay <path>
+
+Description:
+   Replays a trace written by \"record\" : the events are dispatched again with the breakpoints and hooks of the recorded session,
+   and the stop prompt serves the recorded registers and memory, so the same commands give the same output.
+   A command that reads something that was not read during the recording fails with an error.
+   If no file is loaded, the file of the recorded session is loaded when it exists.
+
+Examples:
+   replay crash.trace
+   LisaDbg --replay crash.trace --file app.exe
+\x1b[0m";
+
+pub const USAGE_START: &str = "\x1b[32mUSAGE: start [entry|main]
+
+Description:
+  places a one-shot breakpoint and starts the program, the breakpoint is removed once it is hit.
+  entry (default) : the entry point of the optional header (AddressOfEntryPoint)
+  main            : main, wmain or WinMain, taken from the symbols (dwarf/pdb) or, for a stripped file,
+                    found by following the msvc/mingw crt startup code up to the call whose result is given to exit
+  the options --break-entry and --break-main place the same breakpoints without starting the program
+
+Example:
+  start
+  start main
+\x1b[0m";
+
+pub const USAGE_DUMP_RESOURCE: &str = "\x1b[32mUSAGE: dump-resource <type>[/<name>[/<lang>]] <path>
+       dump-resource #<index> <path>
+
+Description:
+  writes the raw data of a resource of the file context to <path>, the selector must match exactly one resource.
+  the type is a name (ICON, VERSION, MANIFEST, RCDATA...) or a number, the index is the one shown by \"info resources\"
+
+Example:
+  dump-resource manifest/1 app.manifest
+  dump-resource RCDATA/CONFIG/0x409 config.bin
+  dump-resource #3 icon.ico
+\x1b[0m";
+
+pub const USAGE_B_IMPORT: &str = "\x1b[32mUSAGE: b-import <dll!function>
+
+Description:
+  places a breakpoint (rva) on every call and jmp that goes through the IAT slot of the imported function.
+  the function can be given by name or by ordinal with '#', the extension of the dll is optional.
+  for a delay-loaded function, a breakpoint (va) is also set on the function itself once the delay-load helper has patched the IAT.
+  \"info imports\" lists the imported functions, \"info imports --delay\" the delay-loaded ones
+
+Example:
+  b-import kernel32!CreateFileW     # breaks on every call qword ptr [CreateFileW]
+  b-import ws2_32.dll!#23           # breaks on the calls to the ordinal 23 of ws2_32.dll
+\x1b[0m";
+
 pub const USAGE_B_RET_VA: &str = "\x1b[32mUSAGE: b-ret-va <Va>
 
 Description:
@@ -444,13 +574,18 @@ Example:
   b-ret 0xdeadbeef     # places a breakpoint at the return address of the function that contains the instruction at address 0xdeadbeef (va)
 \x1b[0m";
 
-pub const USAGE_PROC_ADDR: &str = "\x1b[32mUSAGE: proc-addr <dllname> <function-name>
+pub const USAGE_PROC_ADDR: &str = "\x1b[32mUSAGE: proc-addr <dllname> <function-name/#ordinal>
 
 Description:
- allows you to know the address of a function in a dll by specifying the dll and the name of the function
+ allows you to know the address of a function in a dll by specifying the dll and the name or the ordinal of the function
+ forwarded exports are followed to the dll that contains the code
+ before the process is started the dll is read from disk (directory of the file, system directory, windows directory, current directory)
+ and the rva is given with the address at the preferred base
 
 Example:
   proc-addr test1.dll test        # get the test address in the dll
+  proc-addr kernel32 HeapAlloc    # forwarded to NTDLL.RtlAllocateHeap
+  proc-addr ws2_32.dll #115       # function exported by ordinal
 \x1b[0m";
 
 pub const USAGE_MEM_INFO: &str = "\x1b[32mUSAGE: mem-info <address/register>
@@ -466,28 +601,38 @@ Example:
 fn print_help() {
     println!("{VALID_COLOR}LisaDbg Help:");
     println!("Available commands:");
-    println!("    {:<38}{}", "breakpoint, b", "Sets a breakpoint at the specified address (rva) or symbol");
-    println!("    {:<38}{}", "file", "Change the current file context");
-    println!("    {:<38}{}", "run", "Start or resume execution of the debugged program");
-    println!("    {:<38}{}", "reset", "Reset the debugger settings or context");
-    println!("    {:<38}{}", "remove", "removes a specified element, for more information type \"help remove\"");
-    println!("    {:<38}{}", "quit, q, exit", "Exit the debugger");
-    println!("    {:<38}{}", "s, sym, symbol", "Load symbols, this will allow commands like \"b-ret\" to be used with the function name directly");
-    println!("    {:<38}{}", "b-ret", "places a breakpoint at the return address of the function or to the function which contains the instruction at the address");
-    println!("    {:<38}{}", "skip", "skip calls to the specified function");
-    println!("    {:<38}{}", "proc-addr", "get the address of a function in a dll");
-    println!("    {:<38}{}", "hook, ho", "Setup a function hook to redirect execution flow");
-    println!("    {:<38}{}", "info", "see certain information like the symbol that have been placed etc");
-    println!("    {:<38}{}", "watchpoint, watch, w", "Set an observation point to a memory location, if the memory location is on the stack, this must be specified");
-    println!("    {:<38}{}", "sym-info", "displays all information of the specified symbol");
-    println!("    {:<38}{}", "arg, args, argv", "defined the arguments with which the debugger will launch the target program");
-    println!("    {:<38}{}", "attach", "to attach the debugger to a running process");
-    println!("    {:<38}{}", "break-va, b-va", "Sets a breakpoint at the specified address (va)");
-    println!("    {:<38}{}", "break-ret-va, b-ret-va", "Sets a breakpoint at the ret address of function of addr specified (va)");
-    println!("    {:<38}{}", "def", "to declare a function or a type or a structure");
-    println!("    {:<38}{}", "printf", "printf displays a formatted string by replacing specifiers (%d, %s, etc) with the values of the provided variables or arguments");
-    println!("    {:<38}{}", "help-c", "to display the commands available when the program reaches a breakpoint");
-    println!("    {:<38}{}", "help, h", "Display this help message");
+    println!("    {:<38}Sets a breakpoint at the specified address (rva) or symbol", "breakpoint, b");
+    println!("    {:<38}Change the current file context", "file");
+    println!("    {:<38}Start or resume execution of the debugged program", "run");
+    println!("    {:<38}Start the program and stop once at its entry point or at main", "start [entry|main]");
+    println!("    {:<38}Reset the debugger settings or context", "reset");
+    println!("    {:<38}removes a specified element, for more information type \"help remove\"", "remove");
+    println!("    {:<38}Exit the debugger", "quit, q, exit");
+    println!("    {:<38}Load symbols, this will allow commands like \"b-ret\" to be used with the function name directly", "s, sym, symbol");
+    println!("    {:<38}places a breakpoint at the return address of the function or to the function which contains the instruction at the address", "b-ret");
+    println!("    {:<38}skip calls to the specified function", "skip");
+    println!("    {:<38}get the address of a function in a dll", "proc-addr");
+    println!("    {:<38}Setup a function hook to redirect execution flow", "hook, ho");
+    println!("    {:<38}see certain information like the symbol that have been placed etc", "info");
+    println!("    {:<38}Set an observation point to a memory location, if the memory location is on the stack, this must be specified", "watchpoint, watch, w");
+    println!("    {:<38}displays all information of the specified symbol", "sym-info");
+    println!("    {:<38}defined the arguments with which the debugger will launch the target program", "arg, args, argv");
+    println!("    {:<38}to attach the debugger to a running process", "attach");
+    println!("    {:<38}connect to a gdb remote stub (gdbserver, qemu...)", "target remote");
+    println!("    {:<38}serve the file to a gdb remote client", "gdbserver");
+    println!("    {:<38}open a minidump file (.dmp) and inspect it in a read-only session", "load-dump");
+    println!("    {:<38}open an elf core file and inspect it in a read-only session", "load-core");
+    println!("    {:<38}record the next run in a trace file", "record");
+    println!("    {:<38}replay a trace file without starting the process", "replay");
+    println!("    {:<38}Sets a breakpoint at the specified address (va)", "break-va, b-va");
+    println!("    {:<38}Write the raw data of a resource of the file to disk", "dump-resource");
+    println!("    {:<38}Sets a breakpoint on every tls callback, they run before the entry point", "b-tls");
+    println!("    {:<38}Sets a breakpoint on every call through the IAT slot of an imported function", "b-import");
+    println!("    {:<38}Sets a breakpoint at the ret address of function of addr specified (va)", "break-ret-va, b-ret-va");
+    println!("    {:<38}to declare a function or a type or a structure", "def");
+    println!("    {:<38}printf displays a formatted string by replacing specifiers (%d, %s, etc) with the values of the provided variables or arguments", "printf");
+    println!("    {:<38}to display the commands available when the program reaches a breakpoint", "help-c");
+    println!("    {:<38}Display this help message", "help, h");
     println!("\n\nyou can type \"help all <element-name>\" to know all the commands associated with the element");
     println!(" <element>:");
     println!("    b, break, breakpoint");
@@ -504,6 +649,7 @@ fn print_choice(arg: &[&str]) {
         "breakpoint" | "b" => println!("{}", USAGE_BRPT),
         "file" => println!("{VALID_COLOR}for select a file to debug{RESET_COLOR}"),
         "run" => println!("{VALID_COLOR}Start or resume execution of the debugged program{RESET_COLOR}"),
+        "start" => println!("{}", USAGE_START),
         "reset" => println!("{}", USAGE_RESET),
         "remove" => println!("{}", USAGE_REMOVE),
         "quit" | "q" | "exit" => println!("{VALID_COLOR}Exit the debugger{RESET_COLOR}"),
@@ -517,10 +663,19 @@ fn print_choice(arg: &[&str]) {
         "sym-info" => println!("{}", USAGE_SYM_INFO),
         "args" | "argc" | "argv" | "arg" => println!("{}", USAGE_SET_ARG),
         "attach" => println!("{}", USAGE_ATTACH),
+        "target" => println!("{}", USAGE_TARGET),
+        "gdbserver" => println!("{}", USAGE_GDBSERVER),
+        "load-dump" => println!("{}", USAGE_LOAD_DUMP),
+        "load-core" => println!("{}", USAGE_LOAD_CORE),
+        "record" => println!("{}", USAGE_RECORD),
+        "replay" => println!("{}", USAGE_REPLAY),
         "printf" => println!("{}", USAGE_PRINTF_VAR),
-        "def" => help_def(&arg),
+        "def" => help_def(arg),
         "break-va" | "b-va" => println!("b-va <va>"),
         "break-ret-va" | "b-ret-va" => println!("{USAGE_B_RET_VA}"),
+        "b-import" => println!("{USAGE_B_IMPORT}"),
+        "dump-resource" => println!("{USAGE_DUMP_RESOURCE}"),
+        "b-tls" | "break-tls" => println!("{VALID_COLOR}b-tls : places a breakpoint (rva) on every tls callback of the file, same as the --break-tls option{RESET_COLOR}"),
         "help-c" => println!("{VALID_COLOR}to display the commands available when the program reaches a breakpoint{RESET_COLOR}"),
         "help" => println!("{VALID_COLOR}to display the commands to do before starting debugging{RESET_COLOR}"),
         _ => print_lg(LevelPrint::ErrorO, format!("undefined command : {}", arg[0])),
@@ -532,23 +687,23 @@ pub fn ca_help(help_c: &str) {
     print!("\x1b[32m");
     match help_c {
         "b" | "break" | "breakpoint" => {
-            println!("    {:<43}{}", "breakpoint, b", "Sets a breakpoint at the specified address (rva) or symbol");
-            println!("    {:<38}{}", "b-ret", "places a breakpoint at the return address of the function or to the function which contains the instruction at the address");
-            println!("    {:<38}{}", "break-va, b-va", "Sets a breakpoint at the specified address (va)");
-            println!("    {:<38}{}", "break-ret-va, b-ret-va", "Sets a breakpoint at the ret address of function of addr specified (va)");
+            println!("    {:<43}Sets a breakpoint at the specified address (rva) or symbol", "breakpoint, b");
+            println!("    {:<38}places a breakpoint at the return address of the function or to the function which contains the instruction at the address", "b-ret");
+            println!("    {:<38}Sets a breakpoint at the specified address (va)", "break-va, b-va");
+            println!("    {:<38}Sets a breakpoint at the ret address of function of addr specified (va)", "break-ret-va, b-ret-va");
             println!("    {:<38}", "to remove an element that has been placed, this is done with the \"remove\" command\x1b[0m");
         }
         "run" => {
-            println!("    {:<38}{}", "run", "\x1b[32mStart or resume execution of the debugged program");
-            println!("    {:<38}{}", "quit, q, break", "Terminate the debugging session. Confirmation required\x1b[0m");
+            println!("    {:<38}\x1b[32mStart or resume execution of the debugged program", "run");
+            println!("    {:<38}Terminate the debugging session. Confirmation required\x1b[0m", "quit, q, break");
         }
         "reset" | "remove" => {
-            println!("    {:<38}{}", "remove", "removes a specified element, for more information type \"help remove\"");
-            println!("    {:<38}{}", "reset", "Reset the debugged program");
+            println!("    {:<38}removes a specified element, for more information type \"help remove\"", "remove");
+            println!("    {:<38}Reset the debugged program", "reset");
         },
         "ret" => {
-            println!("    {:<38}{}", "b-ret", "places a breakpoint at the return address of the function or to the function which contains the instruction at the address");
-            println!("    {:<38}{}", "break-ret-va, b-ret-va", "Sets a breakpoint at the ret address of function of addr specified (va)");
+            println!("    {:<38}places a breakpoint at the return address of the function or to the function which contains the instruction at the address", "b-ret");
+            println!("    {:<38}Sets a breakpoint at the ret address of function of addr specified (va)", "break-ret-va, b-ret-va");
         }
         "thread" | "th" => {
             println!("dbg-thread, dbg-th          : to debug a thread specified with its id");
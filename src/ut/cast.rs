@@ -117,7 +117,7 @@ impl ToType for f64 {
 
 impl ToType for char {
     fn from_str_value(value: &str) -> Result<Self, Error> {
-        let c = if value != "" {
+        let c = if !value.is_empty() {
             value.as_bytes()[0] as char
         } else {
             '\0'
@@ -145,25 +145,16 @@ impl ToType for bool {
 
 
 pub trait NumConvert {
-    fn to_u64(&self) -> u64;
     fn from_u64(value: u64) -> Self;
 }
 
 impl NumConvert for u32 {
-    fn to_u64(&self) -> u64 {
-        *self as u64
-    }
-
     fn from_u64(value: u64) -> Self {
         value as u32
     }
 }
 
 impl NumConvert for u64 {
-    fn to_u64(&self) -> u64 {
-        *self
-    }
-
     fn from_u64(value: u64) -> Self {
         value
     }
@@ -1,6 +1,8 @@
 use std::fmt;
-use crate::cli::ALL_ELM;
-use crate::dbg::{stop_dbg, DbgState};
+use std::sync::atomic::{AtomicU8, Ordering};
+#[cfg(windows)]
+use crate::dbg::stop_dbg;
+use crate::dbg::DbgState;
 use crate::ut::cast::Char;
 
 const ERR_COLOR: &str = "\x1b[31m";
@@ -19,6 +21,8 @@ pub const CYAN_COLOR: &str = "\x1b[36m";
 pub const MAGENTA: &str = "\x1b[35m";
 pub const BLUE_COLOR: &str = "\x1b[34m";
 
+pub static PRINT_OT: AtomicU8 = AtomicU8::new(0);
+
 
 
 
@@ -143,11 +147,14 @@ pub fn print_lg<F: fmt::Display>(lvl: LevelPrint, fmt: F) {
         LevelPrint::Critical1(proc, dbg_state) => unsafe {
             eprintln!("[{ERR_COLOR}Critical{RESET_COLOR}] -> {fmt}");
             *dbg_state = DbgState::Stopped;
-            stop_dbg(proc)
+            #[cfg(windows)]
+            stop_dbg(proc);
+            #[cfg(not(windows))]
+            let _ = proc;
         }
         LevelPrint::Error => eprintln!("[{ERR_COLOR}Error{RESET_COLOR}] -> {fmt}"),
-        LevelPrint::Debug => unsafe {
-            if ALL_ELM.print_ot < 1 {
+        LevelPrint::Debug => {
+            if PRINT_OT.load(Ordering::Relaxed) < 1 {
                 println!("[{DBG_COLOR}Debug{RESET_COLOR}] -> {fmt}");
             }
         }